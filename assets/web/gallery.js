@@ -0,0 +1,151 @@
+// A minimal, read-only browser for an Artchiver archive, served by the built-in HTTP API.
+"use strict";
+
+const PAGE_SIZE = 100;
+const MAX_TAGS_SHOWN = 500;
+
+// Carry the API token from the page link, if we were given one, on every request.
+const params = new URLSearchParams(window.location.search);
+if (params.has("token")) {
+    localStorage.setItem("artchiver-token", params.get("token"));
+}
+const token = localStorage.getItem("artchiver-token");
+
+function apiUrl(path, query = {}) {
+    const url = new URL(path, window.location.origin);
+    for (const [key, value] of Object.entries(query)) {
+        url.searchParams.set(key, value);
+    }
+    if (token) {
+        url.searchParams.set("token", token);
+    }
+    return url.toString();
+}
+
+async function fetchJson(path, query) {
+    const response = await fetch(apiUrl(path, query));
+    if (!response.ok) {
+        throw new Error(`${response.status}: ${await response.text()}`);
+    }
+    return response.json();
+}
+
+const tagList = document.getElementById("tags");
+const tagFilter = document.getElementById("tag-filter");
+const gallery = document.getElementById("gallery");
+const viewer = document.getElementById("viewer");
+const viewerImage = document.getElementById("viewer-image");
+const viewerCaption = document.getElementById("viewer-caption");
+
+let allTags = [];
+
+function renderTags() {
+    const filter = tagFilter.value.trim().toLowerCase();
+    tagList.replaceChildren();
+    let shown = 0;
+    for (const tag of allTags) {
+        if (tag.hidden || (filter && !tag.name.toLowerCase().includes(filter))) {
+            continue;
+        }
+        // Big archives have far more tags than a phone wants to lay out.
+        if (++shown > MAX_TAGS_SHOWN) {
+            const more = document.createElement("p");
+            more.className = "hint";
+            more.textContent = "Filter to see more tags...";
+            tagList.append(more);
+            break;
+        }
+        const link = document.createElement("a");
+        link.href = `#tag-${tag.id}`;
+        link.dataset.id = tag.id;
+        const name = document.createElement("span");
+        name.textContent = (tag.favorite ? "✨ " : "") + tag.name;
+        const count = document.createElement("span");
+        count.textContent = tag.network_count;
+        link.append(name, count);
+        link.addEventListener("click", () => showTag(tag));
+        tagList.append(link);
+    }
+}
+
+async function loadTags() {
+    try {
+        allTags = await fetchJson("/api/tags");
+        allTags.sort((a, b) => a.name.localeCompare(b.name));
+        renderTags();
+    } catch (err) {
+        gallery.replaceChildren(errorMessage(err));
+    }
+}
+
+function errorMessage(err) {
+    const p = document.createElement("p");
+    p.className = "hint";
+    p.textContent = `Could not reach Artchiver: ${err.message}`;
+    return p;
+}
+
+async function showTag(tag) {
+    for (const link of tagList.querySelectorAll("a")) {
+        link.classList.toggle("selected", link.dataset.id === String(tag.id));
+    }
+    const heading = document.createElement("h2");
+    heading.textContent = tag.name;
+    const grid = document.createElement("div");
+    grid.className = "grid";
+    gallery.replaceChildren(heading, grid);
+    await loadPage(tag, grid, 0);
+}
+
+async function loadPage(tag, grid, after) {
+    let works;
+    try {
+        works = await fetchJson(`/api/tags/${tag.id}/works`, { after, limit: PAGE_SIZE });
+    } catch (err) {
+        gallery.append(errorMessage(err));
+        return;
+    }
+    for (const work of works) {
+        // Only show what we have actually archived.
+        if (work.hidden || !work.preview_path) {
+            continue;
+        }
+        const img = document.createElement("img");
+        img.loading = "lazy";
+        img.alt = work.name;
+        img.title = work.name;
+        img.src = apiUrl(`/api/works/${work.id}/preview`);
+        img.addEventListener("click", () => showWork(work));
+        grid.append(img);
+    }
+    if (works.length === PAGE_SIZE) {
+        const more = document.createElement("button");
+        more.className = "more";
+        more.textContent = "Load more";
+        more.addEventListener("click", () => {
+            more.remove();
+            loadPage(tag, grid, works[works.length - 1].id);
+        });
+        gallery.append(more);
+    }
+}
+
+function showWork(work) {
+    viewerImage.src = apiUrl(`/api/works/${work.id}/${work.screen_path ? "screen" : "preview"}`);
+    viewerImage.alt = work.name;
+    viewerCaption.textContent = work.date ? `${work.name} (${work.date})` : work.name;
+    viewer.hidden = false;
+}
+
+viewer.addEventListener("click", () => {
+    viewer.hidden = true;
+    viewerImage.removeAttribute("src");
+});
+document.addEventListener("keydown", (event) => {
+    if (event.key === "Escape") {
+        viewer.hidden = true;
+    }
+});
+tagFilter.addEventListener("input", renderTags);
+
+loadTags();
@@ -1,6 +1,12 @@
+#[cfg(feature = "scrape")]
+pub mod scrape;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 mod work;
 
-pub use crate::work::{History, Location, Measurement, PhysicalData, SiUnit, Work};
+pub use crate::work::{
+    Authority, History, Location, Measurement, MediaRole, PhysicalData, SiUnit, Work, WorkRange,
+};
 
 use anyhow::{Result, bail};
 // use jiff::civil::Date;
@@ -18,6 +24,7 @@ use thiserror::Error;
 #[macro_export]
 macro_rules! import_section {
     () => {
+        #[cfg(target_arch = "wasm32")]
         #[extism_pdk::host_fn]
         extern "ExtismHost" {
             fn progress_spinner();
@@ -25,6 +32,58 @@ macro_rules! import_section {
             fn progress_clear();
             fn log_message(level: u32, message: &str);
             fn fetch_text(req: Json<Request>) -> Json<TextResponse>;
+            fn rate_limit_status() -> Json<RateLimitStatus>;
+            fn resolve_media(url: &str) -> Json<MediaResponse>;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        fn config_get(name: impl AsRef<str>) -> Result<Option<String>, extism_pdk::Error> {
+            config::get(name)
+        }
+
+        // Outside of wasm there is no host, so we stand in the mock from artchiver_sdk::testing.
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn progress_spinner() -> Result<(), extism_pdk::Error> {
+            $crate::testing::progress_event($crate::testing::ProgressEvent::Spinner);
+            Ok(())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn progress_percent(current: i32, total: i32) -> Result<(), extism_pdk::Error> {
+            $crate::testing::progress_event($crate::testing::ProgressEvent::Percent(
+                current, total,
+            ));
+            Ok(())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn progress_clear() -> Result<(), extism_pdk::Error> {
+            $crate::testing::progress_event($crate::testing::ProgressEvent::Clear);
+            Ok(())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn log_message(level: u32, message: &str) -> Result<(), extism_pdk::Error> {
+            $crate::testing::log_message(level, message);
+            Ok(())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn fetch_text(
+            req: extism_pdk::Json<Request>,
+        ) -> Result<extism_pdk::Json<TextResponse>, extism_pdk::Error> {
+            Ok(extism_pdk::Json($crate::testing::fetch_text(&req.0)))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn rate_limit_status()
+        -> Result<extism_pdk::Json<RateLimitStatus>, extism_pdk::Error> {
+            Ok(extism_pdk::Json($crate::testing::rate_limit_status()))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        unsafe fn resolve_media(
+            url: &str,
+        ) -> Result<extism_pdk::Json<MediaResponse>, extism_pdk::Error> {
+            Ok(extism_pdk::Json($crate::testing::resolve_media(url)))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        fn config_get(name: impl AsRef<str>) -> Result<Option<String>, extism_pdk::Error> {
+            Ok($crate::testing::config_get(name.as_ref()))
         }
 
         pub struct Progress;
@@ -76,18 +135,38 @@ macro_rules! import_section {
             }
         }
 
+        pub struct Media;
+        impl Media {
+            // The videos at a page, channel, or playlist url, as found by the host's yt-dlp.
+            pub fn resolve(url: impl AsRef<str>) -> MediaResponse {
+                match unsafe { resolve_media(url.as_ref()) } {
+                    Ok(Json(response)) => response,
+                    Err(e) => Err(TextFetchError::HostError(e.to_string())),
+                }
+            }
+        }
+
+        pub struct RateLimit;
+        impl RateLimit {
+            // How close we are to the rate limit in the plugin metadata, and whether the
+            // remote has asked us to back off. The host waits out any pause by itself.
+            pub fn status() -> extism_pdk::FnResult<RateLimitStatus> {
+                Ok(unsafe { rate_limit_status() }?.0)
+            }
+        }
+
         pub struct Config;
         impl Config {
             pub fn get_string(name: impl AsRef<str>) -> FnResult<String> {
                 let raw =
-                    config::get(name)?.ok_or_else(|| extism_pdk::Error::msg("no such config"))?;
+                    config_get(name)?.ok_or_else(|| extism_pdk::Error::msg("no such config"))?;
                 let val = $crate::serde_json::from_str::<ConfigValue>(&raw)?;
                 Ok(val.as_string()?.to_owned())
             }
 
             pub fn get_string_list(name: impl AsRef<str>) -> FnResult<Vec<String>> {
                 let raw =
-                    config::get(name)?.ok_or_else(|| extism_pdk::Error::msg("no such config"))?;
+                    config_get(name)?.ok_or_else(|| extism_pdk::Error::msg("no such config"))?;
                 let val = $crate::serde_json::from_str::<ConfigValue>(&raw)?;
                 Ok(val.as_string_list()?.to_vec())
             }
@@ -132,6 +211,16 @@ pub struct PluginMetadata {
     rate_window_ms: u32, // window time in milliseconds
     cache_timeout: Duration,
     configurations: Vec<(String, ConfigValue)>,
+    // The configurations that the plugin can't work without, like an API key; the host asks
+    // for these when the plugin is first set up.
+    #[serde(default)]
+    required: Vec<String>,
+    #[serde(default)]
+    actions: Vec<PluginAction>,
+    // Set by plugins that read web pages made for people, rather than an API made for programs;
+    // the host checks their fetches against each site's robots.txt.
+    #[serde(default)]
+    scraper: bool,
 }
 
 impl PluginMetadata {
@@ -144,6 +233,9 @@ impl PluginMetadata {
             rate_window_ms: 1,
             cache_timeout: Duration::from_secs(7 * 24 * 60 * 60),
             configurations: Vec::new(),
+            required: Vec::new(),
+            actions: Vec::new(),
+            scraper: false,
         }
     }
 
@@ -167,6 +259,21 @@ impl PluginMetadata {
         self
     }
 
+    pub fn with_required_configuration(mut self, name: &str, kind: ConfigKind) -> Self {
+        self.required.push(name.to_string());
+        self.with_configuration(name, kind)
+    }
+
+    pub fn with_action(mut self, action: PluginAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn with_scraping(mut self) -> Self {
+        self.scraper = true;
+        self
+    }
+
     pub fn set_config_value(&mut self, key: &str, value: ConfigValue) {
         for (k, v) in self.configurations_mut() {
             if key == k {
@@ -207,6 +314,125 @@ impl PluginMetadata {
     pub fn configurations_mut(&mut self) -> impl Iterator<Item = (&str, &mut ConfigValue)> {
         self.configurations.iter_mut().map(|(k, v)| (k.as_str(), v))
     }
+
+    pub fn is_required(&self, key: &str) -> bool {
+        self.required.iter().any(|k| k == key)
+    }
+
+    // Whether any required configuration is still empty.
+    pub fn is_missing_configuration(&self) -> bool {
+        self.configurations
+            .iter()
+            .filter(|(k, _)| self.is_required(k))
+            .any(|(_, v)| match v {
+                ConfigValue::String(s) => s.trim().is_empty(),
+                ConfigValue::StringList(v) => v.iter().all(|s| s.trim().is_empty()),
+            })
+    }
+
+    pub fn actions(&self) -> &[PluginAction] {
+        &self.actions
+    }
+
+    pub fn is_scraper(&self) -> bool {
+        self.scraper
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ActionScope {
+    // Shown in Work Info, for the plugin's works that have a remote id.
+    #[default]
+    Work,
+    // Shown with the plugin, in the plugins panel.
+    Plugin,
+}
+
+// A button that the host shows for the plugin, which calls the plugin's `run_action` when clicked.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PluginAction {
+    id: String,
+    label: String,
+    description: String,
+    scope: ActionScope,
+}
+
+impl PluginAction {
+    pub fn for_work(id: impl ToString, label: impl ToString) -> Self {
+        Self::new(id, label, ActionScope::Work)
+    }
+
+    pub fn for_plugin(id: impl ToString, label: impl ToString) -> Self {
+        Self::new(id, label, ActionScope::Plugin)
+    }
+
+    fn new(id: impl ToString, label: impl ToString, scope: ActionScope) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            description: String::new(),
+            scope,
+        }
+    }
+
+    // Shown when the user hovers over the button.
+    pub fn with_description(mut self, description: impl ToString) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn scope(&self) -> ActionScope {
+        self.scope
+    }
+}
+
+// The action to run, and for work actions, the remote id of the work it was run on, as given
+// to Work::with_remote_id.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ActionRequest {
+    pub action_id: String,
+    pub work_remote_id: Option<String>,
+}
+
+// What came of running an action, for the host to pass on to the user.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ActionOutcome {
+    message: Option<String>,
+    open_url: Option<String>,
+}
+
+impl ActionOutcome {
+    // Written to the plugin's log.
+    pub fn with_message(mut self, message: impl ToString) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    // An http or https page for the host to open in the user's browser.
+    pub fn with_open_url(mut self, url: impl ToString) -> Self {
+        self.open_url = Some(url.to_string());
+        self
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn open_url(&self) -> Option<&str> {
+        self.open_url.as_deref()
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -224,6 +450,21 @@ pub enum TagKind {
     Theme,
 }
 
+impl TagKind {
+    pub const ALL: [TagKind; 10] = [
+        TagKind::Default,
+        TagKind::Character,
+        TagKind::Copyright,
+        TagKind::Location,
+        TagKind::Meta,
+        TagKind::School,
+        TagKind::Series,
+        TagKind::Style,
+        TagKind::Technique,
+        TagKind::Theme,
+    ];
+}
+
 impl FromStr for TagKind {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -267,6 +508,14 @@ pub struct Tag {
     remote_work_count: u64,
     wiki_url: Option<String>,
     remote_id: Option<String>,
+    // The source's own name for the kind of tag, e.g. a term type. The host maps these to a
+    // TagKind, which the user can override per plugin.
+    #[serde(default)]
+    source_type: Option<String>,
+    // Vocabulary terms that the tag stands for, e.g. a Getty AAT concept, so that the host can
+    // look up the term's other names and broader terms.
+    #[serde(default)]
+    external_ids: Vec<(Authority, String)>,
 }
 
 impl PartialEq for Tag {
@@ -303,6 +552,8 @@ impl Tag {
             remote_work_count: 0,
             wiki_url: None,
             remote_id: None,
+            source_type: None,
+            external_ids: Vec::new(),
         }
     }
 
@@ -311,6 +562,11 @@ impl Tag {
         self
     }
 
+    pub fn with_source_type(mut self, source_type: impl ToString) -> Self {
+        self.source_type = Some(source_type.to_string());
+        self
+    }
+
     pub fn with_remote_id(mut self, id: impl ToString) -> Self {
         self.remote_id = Some(id.to_string());
         self
@@ -326,6 +582,21 @@ impl Tag {
         self
     }
 
+    /// Record a vocabulary term that the tag stands for. `Authority::from_uri` splits the Getty
+    /// and Wikidata urls that most open access dumps carry. Ids that don't look like the
+    /// authority's are dropped.
+    pub fn with_external_id(mut self, authority: Authority, id: impl ToString) -> Self {
+        self.add_external_id(authority, id);
+        self
+    }
+
+    pub fn add_external_id(&mut self, authority: Authority, id: impl ToString) {
+        let id = id.to_string();
+        if authority.is_valid_id(id.trim()) {
+            self.external_ids.push((authority, id.trim().to_owned()));
+        }
+    }
+
     pub fn work_count(&self) -> u64 {
         self.remote_work_count
     }
@@ -346,6 +617,14 @@ impl Tag {
         self.kind
     }
 
+    pub fn set_kind(&mut self, kind: TagKind) {
+        self.kind = kind;
+    }
+
+    pub fn source_type(&self) -> Option<&str> {
+        self.source_type.as_deref()
+    }
+
     pub fn presumed_work_count(&self) -> u64 {
         self.remote_work_count
     }
@@ -353,6 +632,129 @@ impl Tag {
     pub fn wiki_url(&self) -> Option<&str> {
         self.wiki_url.as_deref()
     }
+
+    pub fn external_ids(&self) -> &[(Authority, String)] {
+        &self.external_ids
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GroupKind {
+    // A record in the source that only exists to hold other works, e.g. NGA's virtual objects.
+    #[default]
+    Virtual,
+    Portfolio,
+    Series,
+    Volume,
+}
+
+impl fmt::Display for GroupKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupKind::Virtual => write!(f, "virtual"),
+            GroupKind::Portfolio => write!(f, "portfolio"),
+            GroupKind::Series => write!(f, "series"),
+            GroupKind::Volume => write!(f, "volume"),
+        }
+    }
+}
+
+impl FromStr for GroupKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "portfolio" => GroupKind::Portfolio,
+            "series" => GroupKind::Series,
+            "volume" => GroupKind::Volume,
+            _ => GroupKind::Virtual,
+        })
+    }
+}
+
+// A grouping of works that the source records, e.g. the prints in a portfolio. Members are the
+// remote ids of works, as given to Work::with_remote_id; the host shows each group as a
+// collection, and keeps it as the plugin says it is.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    name: String,
+    kind: GroupKind,
+    members: Vec<String>,
+}
+
+impl Group {
+    pub fn new(name: impl ToString, kind: GroupKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            members: Vec::new(),
+        }
+    }
+
+    pub fn with_member(mut self, remote_id: impl ToString) -> Self {
+        self.add_member(remote_id);
+        self
+    }
+
+    pub fn add_member(&mut self, remote_id: impl ToString) {
+        self.members.push(remote_id.to_string());
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> GroupKind {
+        self.kind
+    }
+
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+}
+
+// Tag edits that the user made to one of the plugin's works in Artchiver, for the plugin to send
+// back to its source, e.g. with Request::put. `remote_id` is as given to Work::with_remote_id.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct WorkTagEdit {
+    remote_id: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl WorkTagEdit {
+    pub fn new(remote_id: impl ToString) -> Self {
+        Self {
+            remote_id: remote_id.to_string(),
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    pub fn with_added(mut self, tag: impl ToString) -> Self {
+        self.added.push(tag.to_string());
+        self
+    }
+
+    pub fn with_removed(mut self, tag: impl ToString) -> Self {
+        self.removed.push(tag.to_string());
+        self
+    }
+
+    pub fn remote_id(&self) -> &str {
+        &self.remote_id
+    }
+
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -362,16 +764,33 @@ pub struct Request {
     path: String,
     query: Vec<(String, String)>,
     headers: Vec<(String, String)>,
+    // Sent form encoded, for POST and PUT.
+    #[serde(default)]
+    form: Vec<(String, String)>,
 }
 
 impl Request {
     pub fn get<S: ToString>(url: S) -> Self {
+        Self::with_method("GET", url)
+    }
+
+    // Requests that change things on the remote are never answered from the host's cache.
+    pub fn post<S: ToString>(url: S) -> Self {
+        Self::with_method("POST", url)
+    }
+
+    pub fn put<S: ToString>(url: S) -> Self {
+        Self::with_method("PUT", url)
+    }
+
+    fn with_method<S: ToString>(method: &str, url: S) -> Self {
         Self {
-            method: "GET".to_string(),
+            method: method.to_string(),
             url: url.to_string(),
             path: "".to_string(),
             query: Vec::new(),
             headers: Vec::new(),
+            form: Vec::new(),
         }
     }
 
@@ -398,10 +817,23 @@ impl Request {
         self
     }
 
+    pub fn add_form<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+        self.form.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
     pub fn headers(&self) -> &[(String, String)] {
         &self.headers
     }
 
+    pub fn form(&self) -> &[(String, String)] {
+        &self.form
+    }
+
     pub fn to_url(&self) -> String {
         let query = self
             .query
@@ -416,8 +848,145 @@ impl Request {
     }
 }
 
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitStatus {
+    limit: usize,
+    window_ms: u64,
+    recent_requests: usize,
+    paused_for_ms: u64,
+}
+
+impl RateLimitStatus {
+    pub fn new(
+        limit: usize,
+        window: Duration,
+        recent_requests: usize,
+        paused_for: Duration,
+    ) -> Self {
+        Self {
+            limit,
+            window_ms: window.as_millis().try_into().unwrap_or(u64::MAX),
+            recent_requests,
+            paused_for_ms: paused_for.as_millis().try_into().unwrap_or(u64::MAX),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms)
+    }
+
+    // Requests made within the last window.
+    pub fn recent_requests(&self) -> usize {
+        self.recent_requests
+    }
+
+    // How much longer the remote has asked us to wait, if it has.
+    pub fn paused_for(&self) -> Duration {
+        Duration::from_millis(self.paused_for_ms)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_for_ms > 0
+    }
+}
+
+impl fmt::Display for RateLimitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} requests in the last {:.1}s",
+            self.recent_requests,
+            self.limit,
+            self.window().as_secs_f32()
+        )?;
+        if self.is_paused() {
+            write!(f, ", throttled for {}s", self.paused_for().as_secs().max(1))?;
+        }
+        Ok(())
+    }
+}
+
 pub type TextResponse = Result<String, TextFetchError>;
 
+// Stream urls from video and music sites expire within hours, so works keep the page url behind
+// one of these prefixes instead and the host resolves it again when it downloads. The video
+// prefix is the one mpv uses.
+pub const MEDIA_URL_PREFIX: &str = "ytdl://";
+pub const AUDIO_URL_PREFIX: &str = "ytdl-audio://";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+impl MediaKind {
+    // What the host stores the download as.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Video => "mp4",
+            Self::Audio => "mp3",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Video => MEDIA_URL_PREFIX,
+            Self::Audio => AUDIO_URL_PREFIX,
+        }
+    }
+}
+
+// The url for a work whose media is on the page at `page_url`.
+pub fn media_url(page_url: &str, kind: MediaKind) -> String {
+    format!("{}{page_url}", kind.prefix())
+}
+
+// The page url to resolve, if this is a url that the host has to resolve before downloading.
+pub fn media_page_url(url: &str) -> Option<(&str, MediaKind)> {
+    [MediaKind::Video, MediaKind::Audio]
+        .into_iter()
+        .find_map(|kind| Some((url.strip_prefix(kind.prefix())?, kind)))
+}
+
+/// A video found by the host on a page, channel, or playlist.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MediaEntry {
+    pub id: String,
+    pub title: String,
+    pub page_url: String,
+    pub thumbnail_url: Option<String>,
+    pub uploader: Option<String>,
+    // As yt-dlp gives it: YYYYMMDD.
+    pub upload_date: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub categories: Vec<String>,
+    // The title of the playlist or channel that listed the video, if any.
+    pub playlist: Option<String>,
+}
+
+impl MediaEntry {
+    pub fn download_url(&self) -> String {
+        media_url(&self.page_url, MediaKind::Video)
+    }
+}
+
+pub type MediaResponse = Result<Vec<MediaEntry>, TextFetchError>;
+
+// An entry in the index.json of a directory of fetches recorded by the host: the file holding the
+// body, next to the index, or the error the plugin was given instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFetch {
+    pub url: String,
+    pub result: Result<String, TextFetchError>,
+}
+
 #[derive(Error, Clone, Debug, Serialize, Deserialize)]
 pub enum TextFetchError {
     #[error("timeout")]
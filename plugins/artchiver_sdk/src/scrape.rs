@@ -0,0 +1,296 @@
+// Helpers for plugins whose source has no API, only web pages: pick things out of a page with
+// CSS selectors, make the links in it absolute, and follow its pagination. This module is behind
+// the `scrape` feature, so that plugins with an API don't carry an HTML parser.
+//
+// Fetching stays with the plugin, through `Web::fetch_text`, so that scraping gets the host's
+// rate limiting, caching, and recording like any other request; see `paginate`.
+use anyhow::{Result, anyhow};
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+
+fn selector(css: &str) -> Result<Selector> {
+    Selector::parse(css).map_err(|e| anyhow!("invalid selector `{css}`: {e}"))
+}
+
+// Text as a reader sees it: with the runs of whitespace from the markup collapsed.
+fn clean_text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The largest candidate in a `srcset`, e.g. `a.jpg 400w, b.jpg 1200w` gives `b.jpg`.
+fn largest_in_srcset(srcset: &str) -> Option<&str> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_whitespace();
+            let url = parts.next()?;
+            let size = parts
+                .next()
+                .and_then(|size| size.trim_end_matches(['w', 'x']).parse::<f64>().ok())
+                .unwrap_or(1.0);
+            Some((url, size))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(url, _)| url)
+}
+
+/// A fetched web page, parsed, and the url it came from, for resolving the links in it.
+pub struct Page {
+    url: String,
+    html: Html,
+}
+
+impl Page {
+    pub fn parse(url: impl ToString, html: &str) -> Self {
+        let url = url.to_string();
+        let html = Html::parse_document(html);
+        // Note: a <base> tag changes what relative links are relative to.
+        let base = selector("base[href]")
+            .ok()
+            .and_then(|base| {
+                html.select(&base)
+                    .next()
+                    .and_then(|el| el.value().attr("href"))
+                    .map(|href| resolve_url(&url, href))
+            })
+            .unwrap_or(url);
+        Self { url: base, html }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn root(&self) -> Item<'_> {
+        Item {
+            base: &self.url,
+            element: self.html.root_element(),
+        }
+    }
+
+    /// Each element matching `css`, e.g. each card in a grid of search results, to pick the
+    /// parts out of in turn.
+    pub fn items(&self, css: &str) -> Result<Vec<Item<'_>>> {
+        self.root().items(css)
+    }
+
+    pub fn text(&self, css: &str) -> Result<Option<String>> {
+        self.root().text(css)
+    }
+
+    pub fn texts(&self, css: &str) -> Result<Vec<String>> {
+        self.root().texts(css)
+    }
+
+    pub fn attr(&self, css: &str, attr: &str) -> Result<Option<String>> {
+        self.root().attr(css, attr)
+    }
+
+    pub fn link(&self, css: &str) -> Result<Option<String>> {
+        self.root().link(css)
+    }
+
+    pub fn links(&self, css: &str) -> Result<Vec<String>> {
+        self.root().links(css)
+    }
+
+    pub fn image(&self, css: &str) -> Result<Option<String>> {
+        self.root().image(css)
+    }
+
+    /// The content of a `<meta>` tag by its name or property, e.g. `og:image`. Museum sites
+    /// often fill these in for sharing even when the page itself is hard to pick apart.
+    pub fn meta(&self, name: &str) -> Option<String> {
+        let css = format!("meta[property=\"{name}\"], meta[name=\"{name}\"]");
+        self.attr(&css, "content").ok().flatten()
+    }
+
+    /// Where the next page of results is: the link matching `css` if given, else the page's
+    /// `rel="next"` link, if either.
+    pub fn next_page(&self, css: Option<&str>) -> Result<Option<String>> {
+        match css {
+            Some(css) => self.link(css),
+            None => self.link("link[rel=next], a[rel=next]"),
+        }
+    }
+}
+
+/// An element in a page, and the url to resolve its links against.
+#[derive(Clone, Copy)]
+pub struct Item<'a> {
+    base: &'a str,
+    element: ElementRef<'a>,
+}
+
+impl<'a> Item<'a> {
+    fn select(&self, css: &str) -> Result<Vec<ElementRef<'a>>> {
+        let selector = selector(css)?;
+        Ok(self.element.select(&selector).collect())
+    }
+
+    pub fn items(&self, css: &str) -> Result<Vec<Item<'a>>> {
+        Ok(self
+            .select(css)?
+            .into_iter()
+            .map(|element| Item {
+                base: self.base,
+                element,
+            })
+            .collect())
+    }
+
+    /// The text of this item, e.g. a caption or title found with `items`.
+    pub fn own_text(&self) -> String {
+        clean_text(self.element)
+    }
+
+    pub fn own_attr(&self, attr: &str) -> Option<&'a str> {
+        self.element.value().attr(attr)
+    }
+
+    /// The text of the first element matching `css`, if it has any.
+    pub fn text(&self, css: &str) -> Result<Option<String>> {
+        Ok(self.texts(css)?.into_iter().next())
+    }
+
+    /// The text of every element matching `css` that has any, e.g. a list of subjects.
+    pub fn texts(&self, css: &str) -> Result<Vec<String>> {
+        Ok(self
+            .select(css)?
+            .into_iter()
+            .map(clean_text)
+            .filter(|text| !text.is_empty())
+            .collect())
+    }
+
+    pub fn attr(&self, css: &str, attr: &str) -> Result<Option<String>> {
+        Ok(self
+            .select(css)?
+            .into_iter()
+            .find_map(|el| el.value().attr(attr))
+            .map(|value| value.trim().to_owned()))
+    }
+
+    /// Where the first element matching `css` links to, as an absolute url.
+    pub fn link(&self, css: &str) -> Result<Option<String>> {
+        Ok(self.links(css)?.into_iter().next())
+    }
+
+    pub fn links(&self, css: &str) -> Result<Vec<String>> {
+        Ok(self
+            .select(css)?
+            .into_iter()
+            .filter_map(|el| el.value().attr("href"))
+            .map(|href| resolve_url(self.base, href))
+            .collect())
+    }
+
+    /// The url of the first image matching `css`, as large as the page offers it: the biggest
+    /// in its `srcset`, or the `data-src` that lazy loading swaps in, before its `src`.
+    pub fn image(&self, css: &str) -> Result<Option<String>> {
+        Ok(self.select(css)?.into_iter().find_map(|el| {
+            let el = el.value();
+            let url = el
+                .attr("srcset")
+                .or_else(|| el.attr("data-srcset"))
+                .and_then(largest_in_srcset)
+                .or_else(|| el.attr("data-src"))
+                .or_else(|| el.attr("src"))?;
+            Some(resolve_url(self.base, url))
+        }))
+    }
+}
+
+/// Make `href` absolute, as a browser would on the page at `base`.
+pub fn resolve_url(base: &str, href: &str) -> String {
+    let href = href.trim();
+    if href.contains("://") || href.starts_with("data:") || href.starts_with("mailto:") {
+        return href.to_owned();
+    }
+    let (scheme, rest) = base.split_once("://").unwrap_or(("https", base));
+    if let Some(authority_path) = href.strip_prefix("//") {
+        return format!("{scheme}://{authority_path}");
+    }
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let origin = format!("{scheme}://{}", &rest[..authority_end]);
+    let base_path_and_query = &rest[authority_end..];
+    let base_path_and_query = base_path_and_query.split('#').next().unwrap_or_default();
+    let base_path = base_path_and_query.split('?').next().unwrap_or_default();
+
+    if href.is_empty() {
+        return format!("{origin}{base_path_and_query}");
+    }
+    if href.starts_with('#') {
+        return format!("{origin}{base_path_and_query}{href}");
+    }
+    if href.starts_with('?') {
+        return format!("{origin}{base_path}{href}");
+    }
+    let path = if href.starts_with('/') {
+        href.to_owned()
+    } else {
+        let dir = &base_path[..base_path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        let dir = if dir.is_empty() { "/" } else { dir };
+        format!("{dir}{href}")
+    };
+
+    // Resolve the dot segments, keeping the query and fragment as they are.
+    let suffix_start = path.find(['?', '#']).unwrap_or(path.len());
+    let (path, suffix) = path.split_at(suffix_start);
+    let mut segments = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    // Note: a path ending in a dot segment names a directory.
+    if path.ends_with("/.") || path.ends_with("/..") {
+        segments.push("");
+    }
+    format!("{origin}/{}{suffix}", segments.join("/"))
+}
+
+/// Fetch and parse the page at `start` and the pages after it, as found by `next_page` with
+/// `next_css`, until there are no more, or `max_pages` of them. Pass `Web::fetch_text` as
+/// `fetch`, or a closure around it that logs or reports progress.
+pub fn paginate<E>(
+    start: &str,
+    next_css: Option<&str>,
+    max_pages: usize,
+    mut fetch: impl FnMut(&str) -> Result<String, E>,
+) -> Result<Vec<Page>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut pages = Vec::new();
+    let mut seen = HashSet::new();
+    let mut next = Some(start.to_owned());
+    while let Some(url) = next.take() {
+        // Note: some sites link the last page back to the first, or to itself.
+        if pages.len() >= max_pages || !seen.insert(url.clone()) {
+            break;
+        }
+        let page = Page::parse(&url, &fetch(&url)?);
+        next = page.next_page(next_css)?;
+        pages.push(page);
+    }
+    Ok(pages)
+}
+
+/// The urls of numbered pages, for sites that page with a number in the url rather than links,
+/// e.g. `https://example.org/search?page={page}` for pages 1 to 3.
+pub fn numbered_pages(template: &str, pages: std::ops::RangeInclusive<usize>) -> Vec<String> {
+    pages
+        .map(|page| template.replace("{page}", &page.to_string()))
+        .collect()
+}
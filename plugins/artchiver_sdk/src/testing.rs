@@ -0,0 +1,299 @@
+// A stand-in for the Artchiver host, so that plugins can be tested natively with `cargo test`.
+//
+// When a plugin is built for anything other than wasm, `import_section!` routes Progress, Log,
+// Web, RateLimit, and Config through here instead of the host. Set up a MockHost with canned
+// responses, install it, then call the plugin's exports through `list_tags` and friends:
+//
+//     #[cfg_attr(target_arch = "wasm32", plugin_fn)]
+//     pub fn list_tags() -> FnResult<Json<Vec<Tag>>> { ... }
+//
+//     #[test]
+//     fn test_list_tags() -> anyhow::Result<()> {
+//         MockHost::new()
+//             .with_fixture(TAGS_URL, concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/tags.csv"))
+//             .install();
+//         let tags = testing::list_tags(list_tags)?;
+//         assert!(testing::logs().iter().all(|line| line.level < LogLevel::Warn));
+//         Ok(())
+//     }
+//
+// Note: `#[plugin_fn]` wraps each export in a function that talks to the wasm host, so exports
+//       have to leave it off outside of wasm for the tests to link and call them.
+//
+// The mock is per-thread, as `cargo test` runs each test on its own thread.
+use crate::{
+    ActionOutcome, ActionRequest, ConfigValue, Group, MediaResponse, RateLimitStatus,
+    RecordedFetch, Request, Tag, TextFetchError, TextResponse, Work, WorkRange, WorkTagEdit,
+};
+use anyhow::{Context as _, Result};
+use extism_pdk::{FnResult, Json};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn from_wire(level: u32) -> Self {
+        match level {
+            0 => Self::Trace,
+            1 => Self::Debug,
+            2 => Self::Info,
+            3 => Self::Warn,
+            _ => Self::Error,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogLine {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProgressEvent {
+    Spinner,
+    Percent(i32, i32),
+    Clear,
+}
+
+#[derive(Clone, Debug)]
+enum Fixture {
+    File(PathBuf),
+    Text(String),
+    Error(TextFetchError),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MockHost {
+    fixtures: HashMap<String, Fixture>,
+    // JSON lists of MediaEntry, by the url to resolve.
+    media: HashMap<String, PathBuf>,
+    config: HashMap<String, ConfigValue>,
+    rate_limit: RateLimitStatus,
+
+    // What the plugin did, for the test to check.
+    logs: Vec<LogLine>,
+    progress: Vec<ProgressEvent>,
+    requests: Vec<String>,
+}
+
+thread_local! {
+    static HOST: RefCell<MockHost> = RefCell::new(MockHost::default());
+}
+
+impl MockHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Answer requests for `url` with the contents of the file at `path`, read when requested.
+    pub fn with_fixture(mut self, url: impl ToString, path: impl Into<PathBuf>) -> Self {
+        self.fixtures
+            .insert(url.to_string(), Fixture::File(path.into()));
+        self
+    }
+
+    pub fn with_text(mut self, url: impl ToString, text: impl ToString) -> Self {
+        self.fixtures
+            .insert(url.to_string(), Fixture::Text(text.to_string()));
+        self
+    }
+
+    pub fn with_error(mut self, url: impl ToString, error: TextFetchError) -> Self {
+        self.fixtures.insert(url.to_string(), Fixture::Error(error));
+        self
+    }
+
+    // Resolve `url` to the media entries in the JSON file at `path`.
+    pub fn with_media(mut self, url: impl ToString, path: impl Into<PathBuf>) -> Self {
+        self.media.insert(url.to_string(), path.into());
+        self
+    }
+
+    // Answer requests from a job that Artchiver recorded with its HTTP fixtures set to Record.
+    pub fn with_recording(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let index = dir.join("index.json");
+        let raw =
+            fs::read_to_string(&index).with_context(|| format!("reading {}", index.display()))?;
+        for fetch in serde_json::from_str::<Vec<RecordedFetch>>(&raw)? {
+            let fixture = match fetch.result {
+                Ok(file) => Fixture::File(dir.join(file)),
+                Err(error) => Fixture::Error(error),
+            };
+            self.fixtures.insert(fetch.url, fixture);
+        }
+        Ok(self)
+    }
+
+    pub fn with_config(mut self, name: impl ToString, value: ConfigValue) -> Self {
+        self.config.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, status: RateLimitStatus) -> Self {
+        self.rate_limit = status;
+        self
+    }
+
+    // Use this host for everything the plugin does on this thread, from here on.
+    pub fn install(self) {
+        HOST.with(|host| *host.borrow_mut() = self);
+    }
+}
+
+// Everything the plugin logged, oldest first.
+pub fn logs() -> Vec<LogLine> {
+    HOST.with(|host| host.borrow().logs.clone())
+}
+
+pub fn progress() -> Vec<ProgressEvent> {
+    HOST.with(|host| host.borrow().progress.clone())
+}
+
+// The urls the plugin fetched, in order, including any that had no fixture.
+pub fn requests() -> Vec<String> {
+    HOST.with(|host| host.borrow().requests.clone())
+}
+
+pub fn list_tags(export: impl FnOnce() -> FnResult<Json<Vec<Tag>>>) -> Result<Vec<Tag>> {
+    call(export)
+}
+
+pub fn list_works_for_tag(
+    export: impl FnOnce(String) -> FnResult<Json<Vec<Work>>>,
+    tag: &str,
+) -> Result<Vec<Work>> {
+    call(|| export(tag.to_owned()))
+}
+
+pub fn list_works_for_tag_range(
+    export: impl FnOnce(Json<WorkRange>) -> FnResult<Json<Vec<Work>>>,
+    tag: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<Work>> {
+    call(|| {
+        export(Json(WorkRange {
+            tag: tag.to_owned(),
+            offset,
+            limit,
+        }))
+    })
+}
+
+pub fn list_groups_for_tag(
+    export: impl FnOnce(String) -> FnResult<Json<Vec<Group>>>,
+    tag: &str,
+) -> Result<Vec<Group>> {
+    call(|| export(tag.to_owned()))
+}
+
+pub fn push_work_tags(
+    export: impl FnOnce(Json<WorkTagEdit>) -> FnResult<()>,
+    edit: WorkTagEdit,
+) -> Result<()> {
+    export(Json(edit)).map_err(|e| e.0)
+}
+
+pub fn run_action(
+    export: impl FnOnce(Json<ActionRequest>) -> FnResult<Json<ActionOutcome>>,
+    action_id: &str,
+    work_remote_id: Option<&str>,
+) -> Result<ActionOutcome> {
+    call(|| {
+        export(Json(ActionRequest {
+            action_id: action_id.to_owned(),
+            work_remote_id: work_remote_id.map(str::to_owned),
+        }))
+    })
+}
+
+pub fn refresh_work(
+    export: impl FnOnce(String) -> FnResult<Json<Option<Work>>>,
+    remote_id: &str,
+) -> Result<Option<Work>> {
+    call(|| export(remote_id.to_owned()))
+}
+
+// Run any export and unwrap its result.
+pub fn call<T>(export: impl FnOnce() -> FnResult<Json<T>>) -> Result<T> {
+    export().map(|Json(out)| out).map_err(|e| e.0)
+}
+
+// The host functions, as called by the code that `import_section!` generates.
+#[doc(hidden)]
+pub fn progress_event(event: ProgressEvent) {
+    HOST.with(|host| host.borrow_mut().progress.push(event));
+}
+
+#[doc(hidden)]
+pub fn log_message(level: u32, message: &str) {
+    HOST.with(|host| {
+        host.borrow_mut().logs.push(LogLine {
+            level: LogLevel::from_wire(level),
+            message: message.to_owned(),
+        });
+    });
+}
+
+#[doc(hidden)]
+pub fn fetch_text(req: &Request) -> TextResponse {
+    let url = req.to_url();
+    let fixture = HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        host.requests.push(url.clone());
+        host.fixtures.get(&url).cloned()
+    });
+    match fixture {
+        Some(Fixture::File(path)) => fs::read_to_string(&path).map_err(|e| {
+            TextFetchError::IoError(format!("reading fixture {}: {e}", path.display()))
+        }),
+        Some(Fixture::Text(text)) => Ok(text),
+        Some(Fixture::Error(error)) => Err(error),
+        None => Err(TextFetchError::HttpError(404)),
+    }
+}
+
+#[doc(hidden)]
+pub fn resolve_media(url: &str) -> MediaResponse {
+    let fixture = HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        host.requests.push(url.to_owned());
+        host.media.get(url).cloned()
+    });
+    let Some(path) = fixture else {
+        return Err(TextFetchError::HttpError(404));
+    };
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| TextFetchError::IoError(format!("reading fixture {}: {e}", path.display())))?;
+    serde_json::from_str(&raw).map_err(|e| TextFetchError::HostError(e.to_string()))
+}
+
+#[doc(hidden)]
+pub fn rate_limit_status() -> RateLimitStatus {
+    HOST.with(|host| host.borrow().rate_limit.clone())
+}
+
+// Serialized like the host does, for Config to parse.
+#[doc(hidden)]
+pub fn config_get(name: &str) -> Option<String> {
+    HOST.with(|host| {
+        host.borrow()
+            .config
+            .get(name)
+            .and_then(|value| serde_json::to_string(value).ok())
+    })
+}
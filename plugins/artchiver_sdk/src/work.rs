@@ -35,6 +35,129 @@ impl TryFrom<&str> for SiUnit {
     }
 }
 
+/// Which of a work's files a url is for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MediaRole {
+    Preview,
+    Screen,
+    Archive,
+    /// A 3D model of the work, as glTF, e.g. from a sculpture scan.
+    Model3D,
+}
+
+impl fmt::Display for MediaRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txt = match self {
+            Self::Preview => "preview",
+            Self::Screen => "screen",
+            Self::Archive => "archive",
+            Self::Model3D => "model3d",
+        };
+        write!(f, "{txt}")
+    }
+}
+
+impl TryFrom<&str> for MediaRole {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "preview" => Self::Preview,
+            "screen" => Self::Screen,
+            "archive" => Self::Archive,
+            "model3d" => Self::Model3D,
+            _ => bail!("not a known MediaRole name: {value}"),
+        })
+    }
+}
+
+/// Who issued an identifier for a work, for `Work::with_external_id`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Authority {
+    /// The Wikidata item for the object itself, e.g. "Q12418". Two plugins that report the same
+    /// item are showing the same physical object.
+    Wikidata,
+    /// The Getty Union List of Artist Names id of the work's artist, e.g. "500010879".
+    Ulan,
+    /// A Getty Art & Architecture Thesaurus concept that the work is an instance of.
+    Aat,
+    /// The accession number the holding institution gave the object. Only unique within the one
+    /// institution, so never used to match works across plugins.
+    Accession,
+}
+
+impl Authority {
+    /// Split a Wikidata or Getty url, as found in most open access dumps, into its authority and
+    /// bare id; e.g. "https://www.wikidata.org/wiki/Q12418" or "http://vocab.getty.edu/page/ulan/500010879".
+    pub fn from_uri(uri: &str) -> Option<(Self, String)> {
+        let uri = uri.trim();
+        let uri = uri
+            .split(['?', '#'])
+            .next()
+            .unwrap_or(uri)
+            .trim_end_matches('/');
+        let (prefix, id) = uri.rsplit_once('/')?;
+        let authority = if prefix.contains("wikidata.org") {
+            Self::Wikidata
+        } else if prefix.contains("getty.edu") && prefix.ends_with("/ulan") {
+            Self::Ulan
+        } else if prefix.contains("getty.edu") && prefix.ends_with("/aat") {
+            Self::Aat
+        } else {
+            return None;
+        };
+        authority
+            .is_valid_id(id)
+            .then(|| (authority, id.to_owned()))
+    }
+
+    /// Whether the id has the shape that the authority gives its ids: "Q" and a number for
+    /// Wikidata, and a number for the Getty vocabularies. Anything else would break the queries
+    /// we make of them.
+    pub fn is_valid_id(&self, id: &str) -> bool {
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            Self::Wikidata => id.strip_prefix('Q').is_some_and(is_number),
+            Self::Ulan | Self::Aat => is_number(id),
+            Self::Accession => !id.is_empty(),
+        }
+    }
+
+    /// Where to read about the id on the web, if the authority has a page for it.
+    pub fn url_for(&self, id: &str) -> Option<String> {
+        match self {
+            Self::Wikidata => Some(format!("https://www.wikidata.org/wiki/{id}")),
+            Self::Ulan => Some(format!("http://vocab.getty.edu/page/ulan/{id}")),
+            Self::Aat => Some(format!("http://vocab.getty.edu/page/aat/{id}")),
+            Self::Accession => None,
+        }
+    }
+}
+
+impl fmt::Display for Authority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txt = match self {
+            Self::Wikidata => "wikidata",
+            Self::Ulan => "ulan",
+            Self::Aat => "aat",
+            Self::Accession => "accession",
+        };
+        write!(f, "{txt}")
+    }
+}
+
+impl TryFrom<&str> for Authority {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "wikidata" => Self::Wikidata,
+            "ulan" => Self::Ulan,
+            "aat" => Self::Aat,
+            "accession" => Self::Accession,
+            _ => bail!("not a known Authority name: {value}"),
+        })
+    }
+}
+
 /// An arbitrary physical characteristic.
 ///
 /// Note: neither `name` nor `description` may contain any ',' or '|', for dumb technical reasons.
@@ -537,10 +660,29 @@ pub struct Work {
 
     remote_id: Option<String>,
     archive_url: Option<String>,
+    // A glTF (.gltf or .glb) model of the work; the preview and screen urls stay images.
+    model_url: Option<String>,
+    // A transcript or subtitles for an audio or video work, as WebVTT, SRT, the podcast
+    // namespace's JSON, HTML, or plain text.
+    transcript_url: Option<String>,
 
     physical_data: Option<PhysicalData>,
     history: Option<History>,
     location: Option<Location>,
+
+    // The raw record the work was mapped from, e.g. a JSON object or CSV row, so that the host
+    // can keep it and re-map it later.
+    source: Option<String>,
+
+    // Other places to get the work's files from, in the order to try them, if the url for the
+    // role is gone.
+    #[serde(default)]
+    mirrors: Vec<(MediaRole, String)>,
+
+    // Ids that other catalogs know the work by, so that we can tell when two plugins are showing
+    // us the same object.
+    #[serde(default)]
+    external_ids: Vec<(Authority, String)>,
 }
 
 impl Work {
@@ -560,10 +702,15 @@ impl Work {
 
             remote_id: None,
             archive_url: None,
+            model_url: None,
+            transcript_url: None,
             // artist_name: None,
             physical_data: None,
             history: None,
             location: None,
+            source: None,
+            mirrors: Vec::new(),
+            external_ids: Vec::new(),
         }
     }
 
@@ -577,6 +724,21 @@ impl Work {
         self
     }
 
+    /// A glTF model of the work, for sculpture and other objects that were scanned in 3D. Binary
+    /// `.glb` files, or `.gltf` files with their buffers and textures embedded, work best, as we
+    /// only download the one file.
+    pub fn with_model_url(mut self, url: impl ToString) -> Self {
+        self.model_url = Some(url.to_string());
+        self
+    }
+
+    /// A transcript of an audio or video work, e.g. from a feed's `<podcast:transcript>`. It is
+    /// fetched along with the screen file and shown under the player, and its text is searchable.
+    pub fn with_transcript_url(mut self, url: impl ToString) -> Self {
+        self.transcript_url = Some(url.to_string());
+        self
+    }
+
     pub fn with_physical_data(mut self, physical_data: PhysicalData) -> Self {
         self.physical_data = Some(physical_data);
         self
@@ -592,6 +754,29 @@ impl Work {
         self
     }
 
+    pub fn with_source(mut self, source: impl ToString) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Add another url to get the file for `role` from, should its usual url stop working; e.g. a
+    /// copy of the image at a different size, on another server, or in an aggregator.
+    pub fn with_mirror(mut self, role: MediaRole, url: impl ToString) -> Self {
+        self.mirrors.push((role, url.to_string()));
+        self
+    }
+
+    /// Record an id that another catalog knows the work by. Works from different plugins with the
+    /// same Wikidata id are linked together, as the same physical object.
+    /// Ids that don't look like the authority's, e.g. a url rather than "Q12418", are dropped.
+    pub fn with_external_id(mut self, authority: Authority, id: impl ToString) -> Self {
+        let id = id.to_string();
+        if authority.is_valid_id(id.trim()) {
+            self.external_ids.push((authority, id.trim().to_owned()));
+        }
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -616,6 +801,14 @@ impl Work {
         self.archive_url.as_deref()
     }
 
+    pub fn model_url(&self) -> Option<&str> {
+        self.model_url.as_deref()
+    }
+
+    pub fn transcript_url(&self) -> Option<&str> {
+        self.transcript_url.as_deref()
+    }
+
     pub fn physical_data(&self) -> Option<&PhysicalData> {
         self.physical_data.as_ref()
     }
@@ -631,4 +824,47 @@ impl Work {
     pub fn tags(&self) -> &[String] {
         &self.tags
     }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn mirrors(&self) -> &[(MediaRole, String)] {
+        &self.mirrors
+    }
+
+    pub fn external_ids(&self) -> &[(Authority, String)] {
+        &self.external_ids
+    }
+
+    /// The mirrors for one of the work's files, in the order to try them.
+    pub fn mirrors_for(&self, role: MediaRole) -> impl Iterator<Item = &str> {
+        self.mirrors
+            .iter()
+            .filter(move |(r, _)| *r == role)
+            .map(|(_, url)| url.as_str())
+    }
+}
+
+/// One page of the works for a tag, for `list_works_for_tag_range`.
+///
+/// Plugins that export `list_works_for_tag_range` let the user look at a huge tag a page at a
+/// time, rather than committing to all of it. The pages are in the same order as
+/// `list_works_for_tag` would return the works.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WorkRange {
+    pub tag: String,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl WorkRange {
+    /// Take this page out of the full list, for plugins whose API can't page for itself.
+    pub fn select<T>(&self, items: impl IntoIterator<Item = T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect()
+    }
 }
@@ -7,11 +7,14 @@ use std::time::Duration;
 // Extism does require a macro around our imports, so this macro saves us copying that boilerplate.
 import_section!();
 
+// Exports are marked `plugin_fn` only when building for wasm, so that `cargo test` can call them
+// directly against the mock host in `artchiver_sdk::testing`. See the tests at the bottom.
+
 /// The startup method gets called by the Artchiver when it detects this wasm file.
 /// Since configuration via config:: must be supplied at build time, if the user changes
 /// the configuration values, the plugin may get restarted between calls to startup and
 /// other APIs. For this reason, don't store pointers between startup and other calls.
-#[plugin_fn]
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
 pub fn startup() -> FnResult<Json<PluginMetadata>> {
     Ok(Json(
         // The plugin metadata block tells Artchiver how to show the plugin in the UX.
@@ -32,18 +35,28 @@ pub fn startup() -> FnResult<Json<PluginMetadata>> {
         // Plugins can accept configuration parameters from the UX, such as login and password.
         // All parameters are provided to the plugin via `config::get`. See the Extism docs for
         // more information, or consult the example usage below.
-        .with_configuration("Debug", ConfigKind::String),
+        .with_configuration("Debug", ConfigKind::String)
+        // Actions show up as buttons: work actions in Work Info, for works with a remote id,
+        // and plugin actions next to the plugin's name. Clicking one calls `run_action`.
+        .with_action(
+            PluginAction::for_work(DESCRIBE_ACTION, "Describe")
+                .with_description("Log what the demo knows about this work"),
+        )
+        .with_action(PluginAction::for_plugin(
+            WORD_LIST_ACTION,
+            "Open Word List",
+        )),
     ))
 }
 
+// For this demo, we're going to use the words in the English dictionary.
+const URL: &str = "https://raw.githubusercontent.com/karthikramx/snippable-dictionary/refs/heads/main/english_dictionary.csv";
+
 // Tags are how we browse and find artwork in Artchiver. The `list_tags` method will be called
 // when the user clicks on the Refresh Tags button next to the plugin's name in the UX.
 // Plugins should find and return all tags that could be applied to works from our provider.
-#[plugin_fn]
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
 pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
-    // For this demo, we're going to use the words in the English dictionary.
-    const URL: &str = "https://raw.githubusercontent.com/karthikramx/snippable-dictionary/refs/heads/main/english_dictionary.csv";
-
     // The Progress API lets us display our current state in the UX. We should make use of
     // it to provide feedback to the user during long-running operations.
     Progress::spinner()?;
@@ -92,10 +105,10 @@ pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
 // the data. Artchiver takes care of safely and quickly fetching all the data URLs, storing
 // the data into the data folder, and doing that in the background while allowing the user
 // to continue browsing in the meantime.
-#[plugin_fn]
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
 pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
-    // When a configuration is set in the UX, it will be available to the plugin via `config::get`.
-    if config::get("Debug")?.as_deref() == Some("panic") {
+    // When a configuration is set in the UX, it will be available to the plugin via `Config`.
+    if Config::get_string("Debug").ok().as_deref() == Some("panic") {
         // This message will show up prominently at the top of the UX.
         panic!("Here is where you can find the message when a plugin panics.")
     }
@@ -109,20 +122,182 @@ pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
             "https://static.wikia.nocookie.net/nyancat/images/a/a1/Nyan_Cat_Power.png/revision/latest/scale-to-width-down/220",
             "https://static.wikia.nocookie.net/nyancat/images/a/a1/Nyan_Cat_Power.png/revision/latest/scale-to-width-down/1024",
             vec![tag.to_owned(), "Nyan_Cat".into()],
-        ),
+        )
+        .with_remote_id("demo-01"),
         Work::new(
             "Demo Work 02",
             Date::new(2022, 1, 2)?,
             "https://static.wikia.nocookie.net/nyancat/images/3/36/Nyan_Cat_Unlock_Power.gif/revision/latest/scale-to-width-down/220",
             "https://static.wikia.nocookie.net/nyancat/images/3/36/Nyan_Cat_Unlock_Power.gif/revision/latest/scale-to-width-down/1024",
             vec![tag.to_owned(), "Nyan_Cat".into()],
-        ),
+        )
+        .with_remote_id("demo-02"),
         Work::new(
             "Demo Work 03",
             Date::new(2022, 1, 3)?,
             "https://static.wikia.nocookie.net/nyancat/images/c/cd/Nyan_Cat_Ability.gif/revision/latest/scale-to-width-down/220",
             "https://static.wikia.nocookie.net/nyancat/images/c/cd/Nyan_Cat_Ability.gif/revision/latest/scale-to-width-down/1024",
             vec![tag.to_owned(), "Nyan_Cat".into()],
-        ),
+        )
+        .with_remote_id("demo-03"),
     ].into())
 }
+
+// Optional: lets the user fetch a big tag a page at a time. Our API can't page, so we list
+// everything and pick out the page that was asked for.
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag_range(range: Json<WorkRange>) -> FnResult<Json<Vec<Work>>> {
+    let Json(range) = range;
+    let Json(works) = list_works_for_tag(range.tag.clone())?;
+    Ok(Json(range.select(works)))
+}
+
+// Optional: sources often record which works belong together, like the prints in a portfolio.
+// Artchiver shows each group as a collection of the works whose remote ids are listed.
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_groups_for_tag(_tag: String) -> FnResult<Json<Vec<Group>>> {
+    Ok(vec![
+        Group::new("Nyan Cat Powers", GroupKind::Series)
+            .with_member("demo-02")
+            .with_member("demo-03"),
+    ]
+    .into())
+}
+
+// Optional: sources that take edits, like Danbooru-compatible boorus, can accept the tag edits
+// that the user makes in Artchiver. The user reviews the edits before Artchiver sends any, and
+// an edit that returns an error is kept to try again on the next push. A dictionary can't be
+// edited, so the demo only logs what a booru would be sent, with something like:
+//
+//     Web::fetch_text(
+//         Request::put(BOORU_URL)
+//             .in_path(format!("/posts/{}.json", edit.remote_id()))
+//             .add_query("login", Config::get_string("Login")?)
+//             .add_query("api_key", Config::get_string("API Key")?)
+//             .add_form("post[tag_string]", edit_string(&edit)),
+//     )?;
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn push_work_tags(edit: Json<WorkTagEdit>) -> FnResult<()> {
+    let Json(edit) = edit;
+    Log::info(format!(
+        "Would edit {}: {}",
+        edit.remote_id(),
+        edit_string(&edit)
+    ))?;
+    Ok(())
+}
+
+// Boorus take removals as the tag with a leading `-`.
+fn edit_string(edit: &WorkTagEdit) -> String {
+    edit.added()
+        .iter()
+        .cloned()
+        .chain(edit.removed().iter().map(|tag| format!("-{tag}")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const DESCRIBE_ACTION: &str = "describe";
+const WORD_LIST_ACTION: &str = "open-word-list";
+
+// Optional: runs the actions listed in the metadata. The outcome can carry a message for the
+// plugin's log and a web page for Artchiver to open in the user's browser, like a work's page
+// at the source, or a page to buy a print of it.
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn run_action(request: Json<ActionRequest>) -> FnResult<Json<ActionOutcome>> {
+    let Json(request) = request;
+    let outcome = match (request.action_id.as_str(), request.work_remote_id) {
+        (DESCRIBE_ACTION, Some(remote_id)) => {
+            ActionOutcome::default().with_message(format!("{remote_id} is a demo work"))
+        }
+        (WORD_LIST_ACTION, _) => ActionOutcome::default().with_open_url(URL),
+        (other, _) => ActionOutcome::default().with_message(format!("Unknown action {other}")),
+    };
+    Ok(Json(outcome))
+}
+
+// Plugins can be tested natively with `cargo test`: the testing module stands in for Artchiver,
+// answering fetches from canned responses and recording what the plugin logs.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, LogLevel, MockHost};
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        MockHost::new()
+            .with_text(URL, "id,word\n1,apple\n2,banana\n")
+            .install();
+        let tags = testing::list_tags(list_tags)?;
+        let names = tags.iter().map(|tag| tag.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["apple", "banana"]);
+        assert_eq!(testing::requests(), [URL]);
+        assert!(
+            testing::logs()
+                .iter()
+                .all(|line| line.level < LogLevel::Warn)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_tags_fetch_failure() {
+        MockHost::new().install();
+        assert!(testing::list_tags(list_tags).is_err());
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        MockHost::new()
+            .with_config("Debug", ConfigValue::String("off".into()))
+            .install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "apple")?;
+        assert_eq!(works.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag_range() -> Result<(), Error> {
+        MockHost::new()
+            .with_config("Debug", ConfigValue::String("off".into()))
+            .install();
+        let works = testing::list_works_for_tag_range(list_works_for_tag_range, "apple", 1, 5)?;
+        assert_eq!(works.len(), 2);
+        assert_eq!(works[0].name(), "Demo Work 02");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_groups_for_tag() -> Result<(), Error> {
+        MockHost::new().install();
+        let groups = testing::list_groups_for_tag(list_groups_for_tag, "apple")?;
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].members(), ["demo-02", "demo-03"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_work_tags() -> Result<(), Error> {
+        MockHost::new().install();
+        let edit = WorkTagEdit::new("demo-01")
+            .with_added("apple")
+            .with_removed("banana");
+        testing::push_work_tags(push_work_tags, edit)?;
+        assert_eq!(
+            testing::logs()[0].message,
+            "Would edit demo-01: apple -banana"
+        );
+        assert!(testing::requests().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_action() -> Result<(), Error> {
+        MockHost::new().install();
+        let outcome = testing::run_action(run_action, DESCRIBE_ACTION, Some("demo-01"))?;
+        assert_eq!(outcome.message(), Some("demo-01 is a demo work"));
+        let outcome = testing::run_action(run_action, WORD_LIST_ACTION, None)?;
+        assert_eq!(outcome.open_url(), Some(URL));
+        Ok(())
+    }
+}
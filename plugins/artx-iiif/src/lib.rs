@@ -0,0 +1,492 @@
+use artchiver_sdk::*;
+use extism_pdk::*;
+use jiff::civil::Date;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+import_section!();
+
+// IIIF is how libraries and museums publish their images for the web: a manifest describes one
+// object, with a canvas for each page or view of it, and collections list manifests. This plugin
+// walks the collections and manifests that the user lists, in either version 2 or 3 of the
+// Presentation API, and makes a work of each canvas.
+//
+// Tags come from the labels of the collections and manifests that a canvas is in, the ranges of
+// the manifest's table of contents, and the short values in the manifest's metadata.
+
+const MANIFESTS: &str = "Manifests";
+
+// Collections can nest, and some list themselves; don't follow them forever.
+const MAX_DEPTH: usize = 8;
+
+// Metadata values longer than this are descriptions, not tags.
+const MAX_TAG_LEN: usize = 64;
+
+// Metadata labels that name who made the work, rather than what it is.
+const CREATOR_LABELS: [&str; 5] = ["creator", "artist", "author", "maker", "photographer"];
+const DATE_LABELS: [&str; 3] = ["date", "created", "date created"];
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn startup() -> FnResult<Json<PluginMetadata>> {
+    Ok(Json(
+        PluginMetadata::new(
+            "IIIF",
+            "0.0.1",
+            "Archives the images in IIIF collections and manifests, from any library or museum that publishes them.",
+        )
+        .with_rate_limit(2, 1.0)
+        .with_cache_timeout(Duration::from_secs(7 * 24 * 60 * 60))
+        .with_required_configuration(MANIFESTS, ConfigKind::StringList),
+    ))
+}
+
+// The text of a label or value: a plain string in v2, a language map in v3, or either as a list
+// or a `@value` object. We take the English, or else whatever comes first.
+fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Array(items) => items.iter().find_map(text),
+        Value::Object(map) => {
+            if let Some(value) = map.get("@value") {
+                text(value)
+            } else {
+                map.get("en")
+                    .or_else(|| map.get("none"))
+                    .or_else(|| map.values().next())
+                    .and_then(text)
+            }
+        }
+        _ => None,
+    }?;
+    let text = strip_html(&text);
+    (!text.is_empty()).then_some(text)
+}
+
+// Values may carry a little HTML, like links and emphasis.
+fn strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_owned()
+}
+
+fn id(value: &Value) -> Option<&str> {
+    value
+        .get("id")
+        .or_else(|| value.get("@id"))
+        .and_then(Value::as_str)
+}
+
+fn kind(value: &Value) -> &str {
+    let kind = value
+        .get("type")
+        .or_else(|| value.get("@type"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    kind.strip_prefix("sc:").unwrap_or(kind)
+}
+
+fn list<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn parse_year(date: &str) -> Option<Date> {
+    let start = date.find(|c: char| c.is_ascii_digit())?;
+    let year = date.get(start..start + 4)?;
+    Date::new(year.parse().ok()?, 1, 1).ok()
+}
+
+// Where to get an image from its Image API service, in both sizes.
+fn image_urls(body: &Value) -> Option<(String, String)> {
+    let service = match body.get("service") {
+        Some(Value::Array(services)) => services.first(),
+        other => other,
+    };
+    if let Some(service) = service
+        && let Some(base) = id(service)
+    {
+        let base = base.trim_end_matches('/');
+        // Note: v2 image servers may not know `max`; v3 servers need not know `full`.
+        let is_v2 = kind(service) == "ImageService2"
+            || service
+                .get("@context")
+                .and_then(Value::as_str)
+                .is_some_and(|context| context.contains("/image/2"))
+            || service
+                .get("profile")
+                .and_then(Value::as_str)
+                .is_some_and(|profile| profile.contains("/image/2"));
+        let full = if is_v2 { "full" } else { "max" };
+        return Some((
+            format!("{base}/full/!400,400/0/default.jpg"),
+            format!("{base}/full/{full}/0/default.jpg"),
+        ));
+    }
+    let url = id(body)?;
+    Some((url.to_owned(), url.to_owned()))
+}
+
+// The image painted on a canvas: in v3 the body of the first annotation on the first page, and
+// in v2 the resource of the first image.
+fn canvas_image(canvas: &Value) -> Option<&Value> {
+    if let Some(image) = list(canvas, "images").first() {
+        return image.get("resource");
+    }
+    let annotation = list(canvas, "items")
+        .first()
+        .and_then(|page| list(page, "items").first())?;
+    match annotation.get("body")? {
+        Value::Array(bodies) => bodies.first(),
+        body => Some(body),
+    }
+}
+
+fn canvases(manifest: &Value) -> Vec<&Value> {
+    if let Some(sequence) = list(manifest, "sequences").first() {
+        return list(sequence, "canvases").iter().collect();
+    }
+    list(manifest, "items")
+        .iter()
+        .filter(|item| kind(item) == "Canvas")
+        .collect()
+}
+
+// The labels of the ranges that each canvas is in, and the ranges around those, by canvas id.
+fn range_labels(manifest: &Value) -> HashMap<String, Vec<String>> {
+    fn walk(range: &Value, parents: &[String], out: &mut HashMap<String, Vec<String>>) {
+        let mut labels = parents.to_vec();
+        labels.extend(range.get("label").and_then(text));
+        // v2 lists canvases by id; v3 lists canvases and sub-ranges as items.
+        let canvases = list(range, "canvases")
+            .iter()
+            .filter_map(Value::as_str)
+            .chain(
+                list(range, "items")
+                    .iter()
+                    .filter(|item| kind(item) == "Canvas")
+                    .filter_map(id),
+            );
+        for canvas in canvases {
+            out.entry(canvas_key(canvas).to_owned())
+                .or_default()
+                .extend(labels.iter().cloned());
+        }
+        for sub in list(range, "items")
+            .iter()
+            .filter(|item| kind(item) == "Range")
+        {
+            walk(sub, &labels, out);
+        }
+    }
+    let mut out = HashMap::new();
+    for range in list(manifest, "structures") {
+        walk(range, &[], &mut out);
+    }
+    out
+}
+
+// Canvas ids carry the fragment of the region that they show, e.g. `#xywh=...`, in some
+// manifests; the range lists them without.
+fn canvas_key(canvas_id: &str) -> &str {
+    canvas_id.split('#').next().unwrap_or(canvas_id)
+}
+
+#[derive(Default)]
+struct Walker {
+    visited: HashSet<String>,
+    works: Vec<Work>,
+    // What we know about each tag, other than its name, by name.
+    tags: HashMap<String, Tag>,
+}
+
+impl Walker {
+    fn walk_all() -> FnResult<Self> {
+        let mut walker = Self::default();
+        let urls = Config::get_string_list(MANIFESTS)?;
+        for (i, url) in urls.iter().enumerate() {
+            Progress::percent(i as i32, urls.len() as i32)?;
+            let url = url.trim();
+            if !url.is_empty() {
+                walker.walk(url, &[], 0)?;
+            }
+        }
+        Progress::clear()?;
+        Ok(walker)
+    }
+
+    fn add_tag(&mut self, name: &str, kind: TagKind, source_type: &str) {
+        self.tags
+            .entry(name.to_owned())
+            .or_insert_with(|| Tag::new(name).with_kind(kind).with_source_type(source_type));
+    }
+
+    fn walk(&mut self, url: &str, parents: &[String], depth: usize) -> FnResult<()> {
+        if depth > MAX_DEPTH || !self.visited.insert(url.to_owned()) {
+            return Ok(());
+        }
+        let raw = match Web::fetch_text(Request::get(url)) {
+            Ok(raw) => raw,
+            // Note: one missing manifest should not stop the rest of the collection.
+            Err(TextFetchError::HttpError(code)) if depth > 0 => {
+                Log::warn(format!("Skipping {url}: HTTP {code}"))?;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let doc = serde_json::from_str::<Value>(&raw)?;
+        let label = doc.get("label").and_then(text);
+        match kind(&doc) {
+            "Collection" => {
+                let mut parents = parents.to_vec();
+                if let Some(label) = label {
+                    self.add_tag(&label, TagKind::Series, "collection");
+                    parents.push(label);
+                }
+                let members = list(&doc, "items")
+                    .iter()
+                    .chain(list(&doc, "manifests"))
+                    .chain(list(&doc, "collections"))
+                    .chain(list(&doc, "members"))
+                    .filter_map(id)
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>();
+                Log::trace(format!("{url} lists {} members", members.len()))?;
+                for member in members {
+                    self.walk(&member, &parents, depth + 1)?;
+                }
+            }
+            "Manifest" => self.add_manifest(&doc, label, parents)?,
+            other => Log::warn(format!(
+                "Skipping {url}: not a collection or manifest: {other}"
+            ))?,
+        }
+        Ok(())
+    }
+
+    fn add_manifest(
+        &mut self,
+        manifest: &Value,
+        label: Option<String>,
+        parents: &[String],
+    ) -> FnResult<()> {
+        let manifest_id = id(manifest).unwrap_or_default();
+        let label = label.unwrap_or_else(|| manifest_id.to_owned());
+
+        self.add_tag(&label, TagKind::Series, "manifest");
+        let mut tags = parents.to_vec();
+        tags.push(label.clone());
+        let mut history = History::default();
+        let mut date = None;
+        for entry in list(manifest, "metadata") {
+            let (Some(name), Some(value)) = (
+                entry.get("label").and_then(text),
+                entry.get("value").and_then(text),
+            ) else {
+                continue;
+            };
+            let key = name.to_lowercase();
+            let is_creator = CREATOR_LABELS.contains(&key.as_str());
+            if is_creator {
+                history.set_attribution(&value);
+            }
+            if DATE_LABELS.contains(&key.as_str()) {
+                history.set_display_date(&value);
+                date = parse_year(&value);
+                continue;
+            }
+            if value.chars().count() <= MAX_TAG_LEN {
+                let source_type = if is_creator { "artist" } else { name.as_str() };
+                self.add_tag(&value, TagKind::Default, source_type);
+                tags.push(value);
+            }
+        }
+        if let Some(nav_date) = manifest.get("navDate").and_then(Value::as_str) {
+            date = date.or_else(|| parse_year(nav_date));
+        }
+        if let Some(date) = date {
+            history.set_begin_year(date.year().into());
+        }
+        if let Some(credit) = manifest
+            .get("requiredStatement")
+            .and_then(|statement| statement.get("value"))
+            .or_else(|| manifest.get("attribution"))
+            .and_then(text)
+        {
+            history.set_credit_line(credit);
+        }
+        if let Some(rights) = manifest
+            .get("rights")
+            .or_else(|| manifest.get("license"))
+            .and_then(text)
+        {
+            history.set_provenance(rights);
+        }
+
+        let ranges = range_labels(manifest);
+        let canvases = canvases(manifest);
+        for (i, canvas) in canvases.iter().enumerate() {
+            let Some((preview, screen)) = canvas_image(canvas).and_then(image_urls) else {
+                continue;
+            };
+            let canvas_id = id(canvas).unwrap_or_default();
+            // Note: single canvas manifests name the canvas after the object.
+            let name = match canvas.get("label").and_then(text) {
+                Some(canvas_label) if canvases.len() > 1 => format!("{label}: {canvas_label}"),
+                _ if canvases.len() > 1 => format!("{label}: {}", i + 1),
+                _ => label.clone(),
+            };
+            let mut work_tags = tags.clone();
+            if let Some(range_tags) = ranges.get(canvas_key(canvas_id)) {
+                for range in range_tags {
+                    self.add_tag(range, TagKind::Default, "range");
+                    work_tags.push(range.to_owned());
+                }
+            }
+            work_tags.sort();
+            work_tags.dedup();
+            self.works.push(
+                Work::new(name, date.unwrap_or_default(), preview, screen, work_tags)
+                    .with_remote_id(canvas_id)
+                    .with_history(history.clone()),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
+    let Walker {
+        works, mut tags, ..
+    } = Walker::walk_all()?;
+    for work in &works {
+        for name in work.tags() {
+            if let Some(tag) = tags.get_mut(name) {
+                tag.increment_work_count();
+            }
+        }
+    }
+    Log::info(format!(
+        "Found {} canvases with {} tags",
+        works.len(),
+        tags.len()
+    ))?;
+    let mut tags = tags
+        .into_values()
+        .map(|tag| {
+            let count = tag.work_count();
+            tag.with_remote_work_count(count)
+        })
+        .collect::<Vec<_>>();
+    tags.sort();
+    Ok(tags.into())
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
+    Ok(Walker::walk_all()?
+        .works
+        .into_iter()
+        .filter(|work| work.tags().contains(&tag))
+        .collect::<Vec<_>>()
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, LogLevel, MockHost};
+
+    const COLLECTION_URL: &str = "https://iiif.example.org/collection.json";
+    const MANIFEST_V3_URL: &str = "https://iiif.example.org/atlas/manifest.json";
+    const MANIFEST_V2_URL: &str = "https://iiif.example.org/letter/manifest.json";
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn host() -> MockHost {
+        MockHost::new()
+            .with_config(
+                MANIFESTS,
+                ConfigValue::StringList(vec![COLLECTION_URL.to_owned()]),
+            )
+            .with_fixture(COLLECTION_URL, fixture("collection.json"))
+            .with_fixture(MANIFEST_V3_URL, fixture("manifest-v3.json"))
+            .with_fixture(MANIFEST_V2_URL, fixture("manifest-v2.json"))
+    }
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        host().install();
+        let tags = testing::list_tags(list_tags)?;
+        let count = |name: &str| {
+            tags.iter()
+                .find(|tag| tag.name() == name)
+                .map(Tag::work_count)
+        };
+        assert_eq!(count("Maps and Letters"), Some(3));
+        assert_eq!(count("Atlas of the North"), Some(2));
+        assert_eq!(count("Front Matter"), Some(1));
+        assert_eq!(count("Frontispiece"), Some(1));
+        assert_eq!(count("Maps"), Some(2));
+        assert_eq!(count("Letter to a Friend"), Some(1));
+        // Long metadata values are descriptions, not tags.
+        assert!(tags.iter().all(|tag| tag.name().len() <= MAX_TAG_LEN));
+        assert!(
+            testing::logs()
+                .iter()
+                .all(|line| line.level < LogLevel::Warn)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        host().install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "Maps and Letters")?;
+        assert_eq!(works.len(), 3);
+
+        let cover = &works[0];
+        assert_eq!(cover.name(), "Atlas of the North: Cover");
+        assert_eq!(
+            cover.screen_url(),
+            "https://images.example.org/iiif/3/atlas-1/full/max/0/default.jpg"
+        );
+        assert_eq!(
+            cover.preview_url(),
+            "https://images.example.org/iiif/3/atlas-1/full/!400,400/0/default.jpg"
+        );
+        assert_eq!(cover.date(), &Date::new(1595, 1, 1)?);
+        assert_eq!(
+            cover.history().and_then(History::attribution),
+            Some("Mercator, Gerardus")
+        );
+
+        let letter = &works[2];
+        assert_eq!(letter.name(), "Letter to a Friend");
+        assert_eq!(
+            letter.screen_url(),
+            "https://images.example.org/iiif/2/letter-1/full/full/0/default.jpg"
+        );
+        assert_eq!(
+            letter.history().and_then(History::credit_line),
+            Some("Example Library")
+        );
+        Ok(())
+    }
+}
@@ -13,10 +13,37 @@ pub fn startup() -> FnResult<Json<PluginMetadata>> {
             "0.0.1",
             "A plugin for Artchiver to provide The Metropolitan Gallery of the Arts open data.",
         )
-        .with_rate_limit(1, 2.0),
+        .with_rate_limit(1, 2.0)
+        .with_action(
+            PluginAction::for_work(OPEN_OBJECT_ACTION, "Open objectURL")
+                .with_description("Open the work's page on metmuseum.org"),
+        ),
     ))
 }
 
+const OPEN_OBJECT_ACTION: &str = "open-object-url";
+
+#[plugin_fn]
+pub fn run_action(Json(request): Json<ActionRequest>) -> FnResult<Json<ActionOutcome>> {
+    let (OPEN_OBJECT_ACTION, Some(obj_id)) = (request.action_id.as_str(), request.work_remote_id)
+    else {
+        return Ok(Json(
+            ActionOutcome::default().with_message(format!("Unknown action {}", request.action_id)),
+        ));
+    };
+    // Note: the object page is not in the CSV, so ask the Object API for it.
+    let req = Request::get(URL)
+        .in_path(OBJECTS_PATH)
+        .append_path_segment(&obj_id);
+    let api_object = serde_json::from_str::<ObjectInfo>(&Web::fetch_text(req)?)?;
+    let outcome = if api_object.objectURL.is_empty() {
+        ActionOutcome::default().with_message(format!("The Met has no page for object {obj_id}"))
+    } else {
+        ActionOutcome::default().with_open_url(api_object.objectURL)
+    };
+    Ok(Json(outcome))
+}
+
 const DISPLAY_TAG: &str = "On Display";
 const HIGHLIGHT_TAG: &str = "Met Highlight";
 const TIMELINE_TAG: &str = "Met Timeline";
@@ -145,6 +172,13 @@ fn get_record_tags(obj: &CsvMetObject) -> impl Iterator<Item = (&str, &str)> {
     tags.zip(wiki_urls)
 }
 
+// The AAT concept for each of the record's tags, where the Met gives one.
+fn get_record_tag_terms(obj: &CsvMetObject) -> impl Iterator<Item = (&str, &str)> {
+    let aat_urls = obj.tags_aat_url.split('|').filter(|s| !s.is_empty());
+    let tags = obj.tags.split('|').filter(|s| !s.is_empty());
+    tags.zip(aat_urls)
+}
+
 const URL: &str = "https://collectionapi.metmuseum.org";
 const OBJECTS_PATH: &str = "/public/collection/v1/objects";
 
@@ -205,6 +239,14 @@ pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
                 .and_modify(|t| t.increment_work_count())
                 .or_insert_with(|| Tag::new(tag).with_remote_work_count(1).with_wiki_url(wiki));
         }
+        for (tag, aat) in get_record_tag_terms(obj) {
+            if let Some(t) = term_tags.get_mut(tag)
+                && t.external_ids().is_empty()
+                && let Some((authority, id)) = Authority::from_uri(aat)
+            {
+                t.add_external_id(authority, id);
+            }
+        }
     }
     Log::info(format!("found {} tag terms", term_tags.len()))?;
     Log::info(format!("found {} room terms", room_tags.len()))?;
@@ -384,128 +426,161 @@ pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
             }
         };
 
-        // Parse JSON into an ObjectInfo.
-        let api_object = match serde_json::from_str::<ObjectInfo>(&object_info) {
-            Ok(obj) => obj,
-            Err(err) => {
-                Log::error(format!("Failed to parse JSON object, {err}"))?;
-                Log::error(format!("Document is: {object_info}"))?;
-                return Err(err.into());
-            }
-        };
-        if api_object.primaryImage.is_empty() {
-            Log::warn(format!(
-                "Missing image for object {}: {}",
-                api_object.objectID, api_object.title
-            ))?;
-            continue;
+        if let Some(work) = map_object_info(&object_info)? {
+            all_works.push(work);
         }
+    }
+    Progress::clear()?;
+    Ok(all_works.into())
+}
 
-        // Collect tags
-        let mut tags: Vec<String> = api_object
-            .tags
-            .unwrap_or_default()
-            .iter()
-            .map(|t| &t.term)
-            .cloned()
-            .collect();
-        if api_object.isHighlight {
-            tags.push(HIGHLIGHT_TAG.to_owned());
-        }
-        if api_object.isTimelineWork {
-            tags.push(TIMELINE_TAG.to_owned());
-        }
+// Re-build the work from the Object API JSON that we handed over as its source.
+#[plugin_fn]
+pub fn map_source(source: String) -> FnResult<Json<Vec<Work>>> {
+    Ok(map_object_info(&source)?
+        .into_iter()
+        .collect::<Vec<_>>()
+        .into())
+}
 
-        // We don't have much information for location.
-        let mut loc = Location::default().with_custody("The Metropolitan Gallery of Art");
-        if let Some(room_tag) = room_tag_for_gallery_number(&api_object.GalleryNumber) {
-            loc.set_room(api_object.GalleryNumber);
-            tags.push(DISPLAY_TAG.to_owned());
-            tags.push(room_tag);
-        }
+// Look the object up again, for when the image urls we recorded have stopped working.
+#[plugin_fn]
+pub fn refresh_work(obj_id: String) -> FnResult<Json<Option<Work>>> {
+    let req = Request::get(URL)
+        .in_path(OBJECTS_PATH)
+        .append_path_segment(&obj_id);
+    let object_info = match Web::fetch_text(req) {
+        Ok(s) => s,
+        // Note: the Met has taken the object down.
+        Err(TextFetchError::HttpError(404)) => return Ok(Json(None)),
+        Err(other) => return Err(other.into()),
+    };
+    Ok(Json(map_object_info(&object_info)?))
+}
 
-        // We have more information about the work history.
-        let mut history = History::default()
-            .with_begin_year(api_object.objectBeginDate.into())
-            .with_end_year(api_object.objectEndDate.into());
-        if !api_object.artistDisplayName.is_empty() {
-            history.set_attribution(api_object.artistDisplayName);
-        }
-        if !api_object.artistAlphaSort.is_empty() {
-            history.set_attribution_sort_key(api_object.artistAlphaSort);
-        }
-        if !api_object.objectDate.is_empty() {
-            history.set_display_date(api_object.objectDate);
-        }
-        if !api_object.rightsAndReproduction.is_empty() {
-            history.set_provenance(api_object.rightsAndReproduction);
-        }
-        if !api_object.creditLine.is_empty() {
-            history.set_credit_line(api_object.creditLine);
+// Build a Work from the Object API's JSON, or None if the object has no image to show.
+fn map_object_info(object_info: &str) -> FnResult<Option<Work>> {
+    // Parse JSON into an ObjectInfo.
+    let api_object = match serde_json::from_str::<ObjectInfo>(object_info) {
+        Ok(obj) => obj,
+        Err(err) => {
+            Log::error(format!("Failed to parse JSON object, {err}"))?;
+            Log::error(format!("Document is: {object_info}"))?;
+            return Err(err.into());
         }
+    };
+    if api_object.primaryImage.is_empty() {
+        Log::warn(format!(
+            "Missing image for object {}: {}",
+            api_object.objectID, api_object.title
+        ))?;
+        return Ok(None);
+    }
 
-        let mut physical = PhysicalData::default();
-        if !api_object.medium.is_empty() {
-            physical.set_medium(&api_object.medium);
-        }
-        if !api_object.dimensions.is_empty() {
-            physical.set_dimensions_display(&api_object.dimensions);
-        }
-        if let Some(measurements) = api_object.measurements.as_deref() {
-            for measure in measurements {
-                if let Some(width) = measure.elementMeasurements.Width {
-                    physical.add_measurement(
-                        Measurement::new(
-                            width / 100., // documented as centimeters
-                            SiUnit::Meter,
-                        )?
-                        .with_name(format!("{}-width", measure.elementName))
-                        .with_description(
-                            measure.elementDescription.as_deref().unwrap_or_default(),
-                        ),
-                    );
-                }
-                if let Some(height) = measure.elementMeasurements.Height {
-                    physical.add_measurement(
-                        Measurement::new(
-                            height / 100., // documented as centimeters
-                            SiUnit::Meter,
-                        )?
-                        .with_name(format!("{}-height", measure.elementName))
-                        .with_description(
-                            measure.elementDescription.as_deref().unwrap_or_default(),
-                        ),
-                    );
-                }
-                if let Some(depth) = measure.elementMeasurements.Depth {
-                    physical.add_measurement(
-                        Measurement::new(
-                            depth / 100., // documented as centimeters
-                            SiUnit::Meter,
-                        )?
-                        .with_name(format!("{}-depth", measure.elementName))
-                        .with_description(
-                            measure.elementDescription.as_deref().unwrap_or_default(),
-                        ),
-                    );
-                }
+    // Collect tags
+    let mut tags: Vec<String> = api_object
+        .tags
+        .unwrap_or_default()
+        .iter()
+        .map(|t| &t.term)
+        .cloned()
+        .collect();
+    if api_object.isHighlight {
+        tags.push(HIGHLIGHT_TAG.to_owned());
+    }
+    if api_object.isTimelineWork {
+        tags.push(TIMELINE_TAG.to_owned());
+    }
+
+    // We don't have much information for location.
+    let mut loc = Location::default().with_custody("The Metropolitan Gallery of Art");
+    if let Some(room_tag) = room_tag_for_gallery_number(&api_object.GalleryNumber) {
+        loc.set_room(api_object.GalleryNumber);
+        tags.push(DISPLAY_TAG.to_owned());
+        tags.push(room_tag);
+    }
+
+    // We have more information about the work history.
+    let mut history = History::default()
+        .with_begin_year(api_object.objectBeginDate.into())
+        .with_end_year(api_object.objectEndDate.into());
+    if !api_object.artistDisplayName.is_empty() {
+        history.set_attribution(api_object.artistDisplayName);
+    }
+    if !api_object.artistAlphaSort.is_empty() {
+        history.set_attribution_sort_key(api_object.artistAlphaSort);
+    }
+    if !api_object.objectDate.is_empty() {
+        history.set_display_date(api_object.objectDate);
+    }
+    if !api_object.rightsAndReproduction.is_empty() {
+        history.set_provenance(api_object.rightsAndReproduction);
+    }
+    if !api_object.creditLine.is_empty() {
+        history.set_credit_line(api_object.creditLine);
+    }
+
+    let mut physical = PhysicalData::default();
+    if !api_object.medium.is_empty() {
+        physical.set_medium(&api_object.medium);
+    }
+    if !api_object.dimensions.is_empty() {
+        physical.set_dimensions_display(&api_object.dimensions);
+    }
+    if let Some(measurements) = api_object.measurements.as_deref() {
+        for measure in measurements {
+            if let Some(width) = measure.elementMeasurements.Width {
+                physical.add_measurement(
+                    Measurement::new(
+                        width / 100., // documented as centimeters
+                        SiUnit::Meter,
+                    )?
+                    .with_name(format!("{}-width", measure.elementName))
+                    .with_description(measure.elementDescription.as_deref().unwrap_or_default()),
+                );
+            }
+            if let Some(height) = measure.elementMeasurements.Height {
+                physical.add_measurement(
+                    Measurement::new(
+                        height / 100., // documented as centimeters
+                        SiUnit::Meter,
+                    )?
+                    .with_name(format!("{}-height", measure.elementName))
+                    .with_description(measure.elementDescription.as_deref().unwrap_or_default()),
+                );
+            }
+            if let Some(depth) = measure.elementMeasurements.Depth {
+                physical.add_measurement(
+                    Measurement::new(
+                        depth / 100., // documented as centimeters
+                        SiUnit::Meter,
+                    )?
+                    .with_name(format!("{}-depth", measure.elementName))
+                    .with_description(measure.elementDescription.as_deref().unwrap_or_default()),
+                );
             }
         }
+    }
 
-        let work = Work::new(
-            api_object.title,
-            Date::strptime("%Y-%m-%d", format!("{}-01-01", api_object.objectBeginDate))
-                .unwrap_or_default(),
-            api_object.primaryImageSmall.replace(' ', "%20").to_owned(),
-            api_object.primaryImage.replace(' ', "%20").to_owned(),
-            tags,
-        )
-        .with_remote_id(obj_id)
-        .with_location(loc)
-        .with_history(history)
-        .with_physical_data(physical);
-        all_works.push(work);
+    let mut work = Work::new(
+        api_object.title,
+        Date::strptime("%Y-%m-%d", format!("{}-01-01", api_object.objectBeginDate))
+            .unwrap_or_default(),
+        api_object.primaryImageSmall.replace(' ', "%20").to_owned(),
+        api_object.primaryImage.replace(' ', "%20").to_owned(),
+        tags,
+    )
+    .with_remote_id(api_object.objectID)
+    .with_location(loc)
+    .with_history(history)
+    .with_physical_data(physical)
+    .with_source(object_info)
+    .with_external_id(Authority::Accession, &api_object.accessionNumber);
+    // Note: the Met gives these as urls, and leaves them blank when it doesn't know.
+    for uri in [&api_object.objectWikidata_URL, &api_object.artistULAN_URL] {
+        if let Some((authority, id)) = Authority::from_uri(uri) {
+            work = work.with_external_id(authority, id);
+        }
     }
-    Progress::clear()?;
-    Ok(all_works.into())
+    Ok(Some(work))
 }
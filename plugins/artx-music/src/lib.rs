@@ -0,0 +1,456 @@
+use artchiver_sdk::*;
+use extism_pdk::*;
+use jiff::civil::Date;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+
+import_section!();
+
+// Archives freely licensed music from Bandcamp and the Free Music Archive: a work per track, with
+// the cover art as its image and the audio as its media, tagged by artist, album, and genre.
+//
+// Neither site has a public API any more, so we read the data they embed in their pages for
+// their own players. Bandcamp's stream urls expire, so the host resolves its track pages when it
+// downloads them; the Free Music Archive's files stay put, so we link those directly.
+
+const BANDCAMP_ARTISTS: &str = "Bandcamp Artists";
+const FMA_ARTISTS: &str = "Free Music Archive Artists";
+
+const BANDCAMP_TAG: &str = "Bandcamp";
+const FMA_TAG: &str = "Free Music Archive";
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn startup() -> FnResult<Json<PluginMetadata>> {
+    Ok(Json(
+        PluginMetadata::new(
+            "Music",
+            "0.0.1",
+            "Archives tracks and their cover art from artists on Bandcamp and the Free Music Archive.",
+        )
+        .with_rate_limit(1, 1.0)
+        .with_scraping()
+        .with_cache_timeout(Duration::from_secs(7 * 24 * 60 * 60))
+        .with_configuration(BANDCAMP_ARTISTS, ConfigKind::StringList)
+        .with_configuration(FMA_ARTISTS, ConfigKind::StringList),
+    ))
+}
+
+// The values that pages give to an attribute, e.g. the JSON in `data-tralbum="{&quot;..."`.
+fn attr_values(html: &str, attr: &str) -> Vec<String> {
+    let needle = format!("{attr}=\"");
+    html.match_indices(&needle)
+        .filter_map(|(start, _)| {
+            let rest = &html[start + needle.len()..];
+            rest.find('"').map(|end| unescape(&rest[..end]))
+        })
+        .collect()
+}
+
+fn unescape(html: &str) -> String {
+    html.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// The text of each `<a class="{class}" ...>text</a>`.
+fn link_texts(html: &str, class: &str) -> Vec<String> {
+    let needle = format!("class=\"{class}\"");
+    html.match_indices(&needle)
+        .filter_map(|(start, _)| {
+            let rest = &html[start..];
+            let open = rest.find('>')? + 1;
+            let close = rest[open..].find('<')?;
+            let text = unescape(rest[open..open + close].trim());
+            (!text.is_empty()).then_some(text)
+        })
+        .collect()
+}
+
+// e.g. `https://creativecommons.org/licenses/by-nc-sa/3.0/` to `CC BY-NC-SA 3.0`.
+fn license_name(html: &str) -> Option<String> {
+    const LICENSES: &str = "creativecommons.org/licenses/";
+    let start = html.find(LICENSES)? + LICENSES.len();
+    let rest = &html[start..];
+    let end = rest.find(['"', '\'', ' ', '>'])?;
+    let mut parts = rest[..end].trim_matches('/').split('/');
+    let kind = parts.next().filter(|kind| !kind.is_empty())?;
+    let version = parts.next().unwrap_or_default();
+    Some(
+        format!("CC {} {version}", kind.to_uppercase())
+            .trim()
+            .to_owned(),
+    )
+}
+
+fn str_of<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+}
+
+// Bandcamp gives dates like `07 Mar 2019 00:00:00 GMT`.
+fn parse_bandcamp_date(date: &str) -> Option<Date> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = date.split_whitespace();
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month)? + 1;
+    let year = parts.next()?.parse().ok()?;
+    Date::new(year, month as i8, day).ok()
+}
+
+struct Track {
+    title: String,
+    artist: String,
+    album: Option<String>,
+    date: Option<Date>,
+    cover_url: String,
+    media_url: String,
+    page_url: String,
+    genres: Vec<String>,
+    license: Option<String>,
+    site: &'static str,
+    source: String,
+}
+
+impl Track {
+    fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::new(self.site)
+                .with_kind(TagKind::Meta)
+                .with_source_type("site"),
+            Tag::new(&self.artist).with_source_type("artist"),
+        ];
+        if let Some(album) = &self.album {
+            tags.push(
+                Tag::new(album)
+                    .with_kind(TagKind::Series)
+                    .with_source_type("album"),
+            );
+        }
+        tags.extend(
+            self.genres
+                .iter()
+                .map(|genre| Tag::new(genre).with_kind(TagKind::Style)),
+        );
+        if let Some(license) = &self.license {
+            tags.push(
+                Tag::new(license)
+                    .with_kind(TagKind::Copyright)
+                    .with_source_type("license"),
+            );
+        }
+        tags
+    }
+
+    fn to_work(&self) -> Work {
+        let mut history = History::default().with_attribution(&self.artist);
+        if let Some(date) = self.date {
+            history.set_display_date(date);
+            history.set_begin_year(date.year().into());
+        }
+        Work::new(
+            &self.title,
+            self.date.unwrap_or_default(),
+            &self.cover_url,
+            &self.media_url,
+            self.tags()
+                .iter()
+                .map(|tag| tag.name().to_owned())
+                .collect(),
+        )
+        .with_remote_id(&self.page_url)
+        .with_history(history)
+        .with_source(&self.source)
+    }
+}
+
+// -- Bandcamp --
+
+fn bandcamp_origin(url: &str) -> &str {
+    let after_scheme = url.find("://").map(|i| i + 3).unwrap_or_default();
+    match url[after_scheme..].find('/') {
+        Some(slash) => &url[..after_scheme + slash],
+        None => url,
+    }
+}
+
+// The album and track pages that an artist's music page links to, both as links and in the
+// JSON that it loads the rest of a long discography from.
+fn bandcamp_releases(html: &str, origin: &str) -> Vec<String> {
+    let html = unescape(html);
+    let mut releases = BTreeSet::new();
+    for prefix in ["\"/album/", "\"/track/"] {
+        for (start, _) in html.match_indices(prefix) {
+            let rest = &html[start + 1..];
+            if let Some(end) = rest.find(['"', '?']) {
+                releases.insert(format!("{origin}{}", &rest[..end]));
+            }
+        }
+    }
+    releases.into_iter().collect()
+}
+
+// The cover art at `size`: 16 is 700px square, 10 is as uploaded.
+fn bandcamp_art_url(art_id: u64, size: u32) -> String {
+    format!("https://f4.bcbits.com/img/a{art_id:010}_{size}.jpg")
+}
+
+fn bandcamp_tracks(html: &str, release_url: &str) -> FnResult<Vec<Track>> {
+    let Some(raw) = attr_values(html, "data-tralbum").into_iter().next() else {
+        Log::warn(format!("No release data at {release_url}"))?;
+        return Ok(Vec::new());
+    };
+    let tralbum = serde_json::from_str::<Value>(&raw)?;
+    let origin = bandcamp_origin(release_url);
+    let current = tralbum.get("current").unwrap_or(&Value::Null);
+    let artist = str_of(&tralbum, "artist").unwrap_or("Unknown Artist");
+    let is_album = str_of(&tralbum, "item_type") == Some("album");
+    let album = is_album
+        .then(|| str_of(current, "title"))
+        .flatten()
+        .map(str::to_owned);
+    let date = str_of(current, "release_date")
+        .or_else(|| str_of(&tralbum, "album_release_date"))
+        .and_then(parse_bandcamp_date);
+    let Some(art_id) = tralbum.get("art_id").and_then(Value::as_u64) else {
+        Log::warn(format!("No cover art at {release_url}"))?;
+        return Ok(Vec::new());
+    };
+    let genres = link_texts(html, "tag");
+    let license = license_name(html);
+
+    let mut tracks = Vec::new();
+    for info in tralbum
+        .get("trackinfo")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        // Note: tracks that are not streamable have no file; there is nothing to archive.
+        if info.get("file").is_none_or(Value::is_null) {
+            continue;
+        }
+        let page_url = match str_of(info, "title_link") {
+            Some(link) => format!("{origin}{link}"),
+            None => release_url.to_owned(),
+        };
+        let title = str_of(info, "title").unwrap_or("Untitled");
+        tracks.push(Track {
+            title: title.to_owned(),
+            artist: str_of(info, "artist").unwrap_or(artist).to_owned(),
+            album: album.clone(),
+            date,
+            cover_url: bandcamp_art_url(art_id, 16),
+            media_url: media_url(&page_url, MediaKind::Audio),
+            page_url,
+            genres: genres.clone(),
+            license: license.clone(),
+            site: BANDCAMP_TAG,
+            source: info.to_string(),
+        });
+    }
+    Ok(tracks)
+}
+
+fn bandcamp_artist(url: &str) -> FnResult<Vec<Track>> {
+    let origin = bandcamp_origin(url);
+    let music = Web::fetch_text(Request::get(format!("{origin}/music")))?;
+    let mut releases = bandcamp_releases(&music, origin);
+    // Note: artists with only one release get sent straight to it.
+    if releases.is_empty() {
+        return bandcamp_tracks(&music, &format!("{origin}/music"));
+    }
+    let mut tracks = Vec::new();
+    for release in releases.drain(..) {
+        let html = Web::fetch_text(Request::get(&release))?;
+        tracks.extend(bandcamp_tracks(&html, &release)?);
+    }
+    Ok(tracks)
+}
+
+// -- Free Music Archive --
+
+fn fma_tracks(html: &str, artist_url: &str) -> FnResult<Vec<Track>> {
+    let genres = link_texts(html, "genre");
+    let license = license_name(html);
+    let mut tracks = Vec::new();
+    for raw in attr_values(html, "data-track-info") {
+        let info = serde_json::from_str::<Value>(&raw)?;
+        let (Some(file_url), Some(image)) = (str_of(&info, "fileUrl"), str_of(&info, "image"))
+        else {
+            continue;
+        };
+        let page_url = str_of(&info, "url").unwrap_or(artist_url);
+        tracks.push(Track {
+            title: str_of(&info, "title").unwrap_or("Untitled").to_owned(),
+            artist: str_of(&info, "artistName")
+                .unwrap_or("Unknown Artist")
+                .to_owned(),
+            album: str_of(&info, "albumTitle").map(str::to_owned),
+            date: None,
+            cover_url: image.to_owned(),
+            media_url: file_url.to_owned(),
+            page_url: page_url.to_owned(),
+            genres: genres.clone(),
+            license: license.clone(),
+            site: FMA_TAG,
+            source: raw,
+        });
+    }
+    Ok(tracks)
+}
+
+fn fma_artist(url: &str) -> FnResult<Vec<Track>> {
+    let html = Web::fetch_text(Request::get(url))?;
+    fma_tracks(&html, url)
+}
+
+fn all_tracks() -> FnResult<Vec<Track>> {
+    // Note: both lists are optional; a missing one is just empty.
+    let bandcamp = Config::get_string_list(BANDCAMP_ARTISTS).unwrap_or_default();
+    let fma = Config::get_string_list(FMA_ARTISTS).unwrap_or_default();
+    let total = (bandcamp.len() + fma.len()) as i32;
+    let mut tracks = Vec::new();
+    for (i, url) in bandcamp.iter().chain(&fma).enumerate() {
+        Progress::percent(i as i32, total)?;
+        let url = url.trim();
+        if url.is_empty() {
+            continue;
+        }
+        let found = if i < bandcamp.len() {
+            bandcamp_artist(url)?
+        } else {
+            fma_artist(url)?
+        };
+        Log::trace(format!("Found {} tracks at {url}", found.len()))?;
+        tracks.extend(found);
+    }
+    Progress::clear()?;
+    Ok(tracks)
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
+    let mut acc = HashMap::<Tag, u64>::new();
+    for track in all_tracks()? {
+        for tag in track.tags() {
+            *acc.entry(tag).or_default() += 1;
+        }
+    }
+    let mut tags = acc
+        .into_iter()
+        .map(|(tag, count)| tag.with_remote_work_count(count))
+        .collect::<Vec<_>>();
+    tags.sort();
+    Ok(tags.into())
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
+    Ok(all_tracks()?
+        .iter()
+        .filter(|track| track.tags().iter().any(|t| t.name() == tag))
+        .map(Track::to_work)
+        .collect::<Vec<_>>()
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, MockHost};
+
+    const BANDCAMP_URL: &str = "https://quietrooms.bandcamp.com";
+    const FMA_URL: &str = "https://freemusicarchive.org/music/Field_Notes/";
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn host() -> MockHost {
+        MockHost::new()
+            .with_config(
+                BANDCAMP_ARTISTS,
+                ConfigValue::StringList(vec![BANDCAMP_URL.to_owned()]),
+            )
+            .with_config(
+                FMA_ARTISTS,
+                ConfigValue::StringList(vec![FMA_URL.to_owned()]),
+            )
+            .with_fixture(
+                format!("{BANDCAMP_URL}/music"),
+                fixture("bandcamp-music.html"),
+            )
+            .with_fixture(
+                format!("{BANDCAMP_URL}/album/low-light"),
+                fixture("bandcamp-album.html"),
+            )
+            .with_fixture(FMA_URL, fixture("fma-artist.html"))
+    }
+
+    #[test]
+    fn test_license_name() {
+        assert_eq!(
+            license_name(r#"<a href="https://creativecommons.org/licenses/by-nc-sa/3.0/">"#),
+            Some("CC BY-NC-SA 3.0".to_owned())
+        );
+        assert_eq!(license_name("all rights reserved"), None);
+    }
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        host().install();
+        let tags = testing::list_tags(list_tags)?;
+        let count = |name: &str| {
+            tags.iter()
+                .find(|tag| tag.name() == name)
+                .map(Tag::work_count)
+        };
+        assert_eq!(count(BANDCAMP_TAG), Some(2));
+        assert_eq!(count(FMA_TAG), Some(1));
+        assert_eq!(count("Low Light"), Some(2));
+        assert_eq!(count("ambient"), Some(2));
+        assert_eq!(count("CC BY 4.0"), Some(2));
+        assert_eq!(count("Field Notes"), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        host().install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "Quiet Rooms")?;
+        // The third track on the album is not streamable.
+        assert_eq!(works.len(), 2);
+        let first = &works[0];
+        assert_eq!(first.name(), "Dusk");
+        assert_eq!(first.date(), &Date::new(2019, 3, 7)?);
+        assert_eq!(
+            first.preview_url(),
+            "https://f4.bcbits.com/img/a0123456789_16.jpg"
+        );
+        assert_eq!(
+            media_page_url(first.screen_url()),
+            Some((
+                "https://quietrooms.bandcamp.com/track/dusk",
+                MediaKind::Audio
+            ))
+        );
+
+        let works = testing::list_works_for_tag(list_works_for_tag, FMA_TAG)?;
+        assert_eq!(works.len(), 1);
+        assert_eq!(
+            works[0].screen_url(),
+            "https://files.freemusicarchive.org/storage-freemusicarchive-org/tracks/walk.mp3"
+        );
+        Ok(())
+    }
+}
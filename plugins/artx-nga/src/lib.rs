@@ -15,10 +15,36 @@ pub fn startup() -> FnResult<Json<PluginMetadata>> {
             "0.0.1",
             "A plugin for Artchiver to provide The National Gallery of the Art (artx-nga.gov) open data.",
         )
-            .with_rate_limit(1, 2.0),
+            .with_rate_limit(1, 2.0)
+            .with_action(
+                PluginAction::for_work(PRINT_ACTION, "Open print purchase page")
+                    .with_description("Order a print of the work from the NGA shop"),
+            ),
     ))
 }
 
+const PRINT_ACTION: &str = "open-print-page";
+
+#[plugin_fn]
+pub fn run_action(Json(request): Json<ActionRequest>) -> FnResult<Json<ActionOutcome>> {
+    let (PRINT_ACTION, Some(remote_id)) = (request.action_id.as_str(), request.work_remote_id)
+    else {
+        return Ok(Json(
+            ActionOutcome::default().with_message(format!("Unknown action {}", request.action_id)),
+        ));
+    };
+    let obj_id = remote_id.parse::<i64>()?;
+    let objects = objects(&Web::fetch_text(Request::get(OBJECTS_URL))?)?;
+    let outcome = match objects.get(&obj_id) {
+        Some(obj) if !obj.customprinturl.is_empty() => {
+            ActionOutcome::default().with_open_url(&obj.customprinturl)
+        }
+        _ => ActionOutcome::default()
+            .with_message(format!("The NGA does not sell prints of object {obj_id}")),
+    };
+    Ok(Json(outcome))
+}
+
 fn csv_reader(raw: &str) -> FnResult<csv::Reader<&[u8]>> {
     let rdr = csv::ReaderBuilder::new()
         .has_headers(true)
@@ -60,8 +86,9 @@ pub struct NgaObject {
     // Hierarchy
     // ---------
     parentid: Option<i64>,
-    // TODO: some "works" appear to be recursive folder nodes that collect works; I think isvirtual identifies these nodes;
-    //       see if we can use these virtual nodes to add additional tags to "real" works; at the very least, filter them out.
+    // Some "works" are folder nodes that collect works, marked by isvirtual; we report these as
+    // groups of their children from list_groups_for_tag.
+    // TODO: filter the virtual nodes themselves out of the works.
     isvirtual: i64,
 
     // History / Creation Info
@@ -212,16 +239,22 @@ pub struct NgaObject {
     lastdetectedmodification: Timestamp,
     wikidataid: String,
     customprinturl: String,
+
+    // The row as we read it, as a JSON object from column name to value.
+    #[serde(skip)]
+    source: String,
 }
 const OBJECTS_URL: &str =
     "https://github.com/NationalGalleryOfArt/opendata/raw/refs/heads/main/data/objects.csv";
 
 fn objects(csv: &str) -> FnResult<HashMap<i64, NgaObject>> {
-    Ok(csv_reader(csv)?
+    let mut rdr = csv_reader(csv)?;
+    let headers = rdr.headers()?.clone();
+    Ok(rdr
         .records()
         .flatten()
         .map(|row| {
-            let r = match row.deserialize::<NgaObject>(None) {
+            let mut r = match row.deserialize::<NgaObject>(None) {
                 Ok(r) => r,
                 Err(e) => {
                     Log::error(format!("Failed to deserialize object: {e}")).ok();
@@ -229,6 +262,14 @@ fn objects(csv: &str) -> FnResult<HashMap<i64, NgaObject>> {
                     panic!("Failed to deserialize object: {e}")
                 }
             };
+            r.source = serde_json::Value::Object(
+                headers
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(k, v)| (k.to_owned(), v.into()))
+                    .collect(),
+            )
+            .to_string();
             (r.objectid, r)
         })
         .collect())
@@ -348,8 +389,9 @@ fn objects_dimensions(csv: &str) -> FnResult<Vec<NgaObjectDimensions>> {
 
 const DISPLAY_TAG: &str = "On Display";
 
-fn type_to_kind(termtype: &str) -> TagKind {
-    match termtype {
+// Note: the host lets the user re-map these, and any that we don't know, by term type.
+fn type_to_kind(termtype: &str) -> Option<TagKind> {
+    Some(match termtype {
         "Keyword" => TagKind::Default,
         "School" => TagKind::School,
         "Place Executed" => TagKind::Location,
@@ -357,8 +399,8 @@ fn type_to_kind(termtype: &str) -> TagKind {
         "Systematic Catalogue Volume" => TagKind::Series,
         "Theme" => TagKind::Theme,
         "Style" => TagKind::Style,
-        v => panic!("Unknown term type: {v}"),
-    }
+        _ => return None,
+    })
 }
 
 fn location_tag_names(loc: &NgaLocation) -> [String; 2] {
@@ -393,18 +435,27 @@ pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
     let delta = 90. / terms.len() as f64;
     let mut position = 10.;
     let mut term_tags = HashMap::<&str, Tag>::new();
+    let mut unknown_types = HashSet::<&str>::new();
     for term in &terms {
         Progress::percent(position as i32, 100)?;
         position += delta;
         term_tags
             .entry(&term.term)
             .or_insert_with(|| {
+                let kind = type_to_kind(&term.termtype).unwrap_or_else(|| {
+                    unknown_types.insert(&term.termtype);
+                    TagKind::Default
+                });
                 Tag::new(term.term.to_owned())
                     .with_remote_id(term.termid)
-                    .with_kind(type_to_kind(&term.termtype))
+                    .with_source_type(&term.termtype)
+                    .with_kind(kind)
             })
             .increment_work_count();
     }
+    for termtype in unknown_types {
+        Log::warn(format!("Unknown term type {termtype}; tagging as default"))?;
+    }
 
     // We also want to tag works that are in the same room or building.
     let mut loc_tags = HashMap::<String, Tag>::new();
@@ -430,35 +481,12 @@ pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
     Ok(all.into())
 }
 
-#[plugin_fn]
-pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
-    Progress::percent(0, 100)?;
-
-    Log::info("Downloading locations list...")?;
-    let locations_csv = Web::fetch_text(Request::get(LOCATIONS_URL))?;
-    let locations = locations(&locations_csv)?;
-    Progress::percent(1, 100)?;
-
-    Log::info("Downloading terms list...")?;
-    let terms_csv = Web::fetch_text(Request::get(TERMS_URL))?;
-    let terms = terms(&terms_csv)?;
-    Progress::percent(2, 100)?;
-
-    Log::info("Downloading published images list...")?;
-    let published_images_csv = Web::fetch_text(Request::get(PUBLISHED_IMAGES_URL))?;
-    let published_images = published_images(&published_images_csv)?;
-    Progress::percent(5, 100)?;
-
-    Log::info("Downloading objects dimensions list...")?;
-    let objects_dimensions_csv = Web::fetch_text(Request::get(OBJECTS_DIMENSIONS_URL))?;
-    let objects_dimensions = objects_dimensions(&objects_dimensions_csv)?;
-    Progress::percent(7, 100)?;
-
-    Log::info("Downloading objects list (this may take awhile)...")?;
-    let objects_csv = Web::fetch_text(Request::get(OBJECTS_URL))?;
-    let objects = objects(&objects_csv)?;
-    Progress::percent(10, 100)?;
-
+fn object_ids_for_tag(
+    tag_name: &str,
+    terms: &[NgaTerm],
+    locations: &[NgaLocation],
+    objects: &HashMap<i64, NgaObject>,
+) -> Vec<i64> {
     // Map from the tag name to all the tags matching that name. We need to check if the tag
     // is a term, or a location, or "On Display". Each of these checks requires a different
     // strategy for performance.
@@ -476,7 +504,7 @@ pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
         // the requested tag, so visit the locations first, since that list is much smaller.
         let loc_ids = locations
             .iter()
-            .filter(|l| location_tag_names(l).contains(&tag_name))
+            .filter(|l| location_tag_names(l).iter().any(|name| name == tag_name))
             .map(|l| l.locationid)
             .collect::<HashSet<i64>>();
         obj_ids = objects
@@ -503,6 +531,39 @@ pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
             .map(|o| o.objectid)
             .collect();
     }
+    obj_ids
+}
+
+#[plugin_fn]
+pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
+    Progress::percent(0, 100)?;
+
+    Log::info("Downloading locations list...")?;
+    let locations_csv = Web::fetch_text(Request::get(LOCATIONS_URL))?;
+    let locations = locations(&locations_csv)?;
+    Progress::percent(1, 100)?;
+
+    Log::info("Downloading terms list...")?;
+    let terms_csv = Web::fetch_text(Request::get(TERMS_URL))?;
+    let terms = terms(&terms_csv)?;
+    Progress::percent(2, 100)?;
+
+    Log::info("Downloading published images list...")?;
+    let published_images_csv = Web::fetch_text(Request::get(PUBLISHED_IMAGES_URL))?;
+    let published_images = published_images(&published_images_csv)?;
+    Progress::percent(5, 100)?;
+
+    Log::info("Downloading objects dimensions list...")?;
+    let objects_dimensions_csv = Web::fetch_text(Request::get(OBJECTS_DIMENSIONS_URL))?;
+    let objects_dimensions = objects_dimensions(&objects_dimensions_csv)?;
+    Progress::percent(7, 100)?;
+
+    Log::info("Downloading objects list (this may take awhile)...")?;
+    let objects_csv = Web::fetch_text(Request::get(OBJECTS_URL))?;
+    let objects = objects(&objects_csv)?;
+    Progress::percent(10, 100)?;
+
+    let obj_ids = object_ids_for_tag(&tag_name, &terms, &locations, &objects);
     Log::info(format!(
         "Found {} objects with tag '{tag_name}'",
         obj_ids.len()
@@ -643,10 +704,51 @@ pub fn list_works_for_tag(tag_name: String) -> FnResult<Json<Vec<Work>>> {
         .with_archive_url(img.iiifurl.to_owned())
         .with_location(loc)
         .with_history(history)
-        .with_physical_data(physical);
+        .with_physical_data(physical)
+        .with_source(&obj.source)
+        .with_external_id(Authority::Wikidata, &obj.wikidataid)
+        .with_external_id(Authority::Accession, &obj.accessionnum);
         works.push(work);
     }
 
     Progress::clear()?;
     Ok(works.into())
 }
+
+// The virtual parent objects, portfolios, series, and volumes that the tag's objects belong to.
+#[plugin_fn]
+pub fn list_groups_for_tag(tag_name: String) -> FnResult<Json<Vec<Group>>> {
+    Progress::spinner()?;
+    let locations = locations(&Web::fetch_text(Request::get(LOCATIONS_URL))?)?;
+    let terms = terms(&Web::fetch_text(Request::get(TERMS_URL))?)?;
+    Log::info("Downloading objects list (this may take awhile)...")?;
+    let objects = objects(&Web::fetch_text(Request::get(OBJECTS_URL))?)?;
+
+    let mut groups = HashMap::<(String, GroupKind), Group>::new();
+    let mut add = |name: &str, kind: GroupKind, obj_id: i64| {
+        if !name.is_empty() {
+            groups
+                .entry((name.to_owned(), kind))
+                .or_insert_with(|| Group::new(name, kind))
+                .add_member(obj_id);
+        }
+    };
+    for obj_id in object_ids_for_tag(&tag_name, &terms, &locations, &objects) {
+        let obj = objects.get(&obj_id).expect("linked object");
+        if let Some(parent) = obj.parentid.and_then(|id| objects.get(&id))
+            && parent.isvirtual == 1
+        {
+            add(&parent.title, GroupKind::Virtual, obj_id);
+        }
+        add(&obj.portfolio, GroupKind::Portfolio, obj_id);
+        add(&obj.series, GroupKind::Series, obj_id);
+        add(&obj.volume, GroupKind::Volume, obj_id);
+    }
+    Log::info(format!(
+        "Found {} groups for tag '{tag_name}'",
+        groups.len()
+    ))?;
+
+    Progress::clear()?;
+    Ok(groups.into_values().collect::<Vec<_>>().into())
+}
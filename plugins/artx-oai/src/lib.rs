@@ -0,0 +1,527 @@
+use artchiver_sdk::*;
+use extism_pdk::*;
+use jiff::civil::Date;
+use quick_xml::{Reader, events::Event};
+use std::{collections::HashMap, time::Duration};
+
+import_section!();
+
+// OAI-PMH is how libraries, museums, and archives share their catalogs with each other: every
+// repository answers the same few verbs, with records in Dublin Core at the least. This plugin
+// harvests one repository, or one set in it, as configured. Tags come from the records' subjects,
+// types, and creators, plus one for the whole repository.
+//
+// Note: the protocol only promises metadata, not images. Records without a link to an image
+//       that we can show are left out.
+
+const ENDPOINT: &str = "Endpoint";
+const METADATA_PREFIX: &str = "Metadata Prefix";
+const SET: &str = "Set";
+const DEFAULT_METADATA_PREFIX: &str = "oai_dc";
+
+// A runaway resumption token should not harvest forever.
+const MAX_PAGES: usize = 1000;
+
+const IMAGE_EXTENSIONS: [&str; 8] = [
+    ".jpg", ".jpeg", ".png", ".gif", ".webp", ".tif", ".tiff", ".jp2",
+];
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn startup() -> FnResult<Json<PluginMetadata>> {
+    Ok(Json(
+        PluginMetadata::new(
+            "OAI-PMH",
+            "0.0.1",
+            "Harvests works from any library, museum, or archive with an OAI-PMH endpoint.",
+        )
+        // Note: repositories are often small institutions' servers; harvest gently.
+        .with_rate_limit(1, 1.0)
+        .with_cache_timeout(Duration::from_secs(24 * 60 * 60))
+        .with_required_configuration(ENDPOINT, ConfigKind::String)
+        .with_configuration(METADATA_PREFIX, ConfigKind::String)
+        .with_configuration(SET, ConfigKind::String),
+    ))
+}
+
+// Unset configuration is an empty string, or missing entirely.
+fn config_or_default(name: &str) -> String {
+    Config::get_string(name)
+        .map(|value| value.trim().to_owned())
+        .unwrap_or_default()
+}
+
+struct Harvest {
+    endpoint: String,
+    metadata_prefix: String,
+    set: String,
+}
+
+impl Harvest {
+    fn from_config() -> FnResult<Self> {
+        let endpoint = config_or_default(ENDPOINT);
+        if endpoint.is_empty() {
+            return Err(Error::msg("set the endpoint of the repository to harvest").into());
+        }
+        let mut metadata_prefix = config_or_default(METADATA_PREFIX);
+        if metadata_prefix.is_empty() {
+            metadata_prefix = DEFAULT_METADATA_PREFIX.to_owned();
+        }
+        Ok(Self {
+            endpoint,
+            metadata_prefix,
+            set: config_or_default(SET),
+        })
+    }
+
+    fn identify_request(&self) -> Request {
+        Request::get(&self.endpoint).add_query("verb", "Identify")
+    }
+
+    // The first page names what we want; the rest only pass the token, as the protocol requires.
+    fn records_request(&self, token: Option<&str>) -> Request {
+        let request = Request::get(&self.endpoint).add_query("verb", "ListRecords");
+        if let Some(token) = token {
+            return request.add_query("resumptionToken", token);
+        }
+        let request = request.add_query("metadataPrefix", &self.metadata_prefix);
+        if self.set.is_empty() {
+            request
+        } else {
+            request.add_query("set", &self.set)
+        }
+    }
+
+    // The tag that every work from the repository is under.
+    fn repository_tag(&self) -> FnResult<String> {
+        let raw = Web::fetch_text(self.identify_request())?;
+        let name = first_text(&raw, "repositoryName")?.unwrap_or_else(|| self.endpoint.clone());
+        Ok(if self.set.is_empty() {
+            name
+        } else {
+            format!("{name}: {}", self.set)
+        })
+    }
+
+    fn records(&self) -> FnResult<Vec<Record>> {
+        let mut records = Vec::new();
+        let mut token = None;
+        for page in 0..MAX_PAGES {
+            Progress::spinner()?;
+            let raw = Web::fetch_text(self.records_request(token.as_deref()))?;
+            let (mut found, next) = parse_records(&raw)?;
+            Log::trace(format!("Page {page}: {} records", found.len()))?;
+            records.append(&mut found);
+            match next {
+                Some(next) => token = Some(next),
+                None => return Ok(records),
+            }
+        }
+        Log::warn(format!(
+            "Stopped harvesting after {MAX_PAGES} pages; narrow it down with a set"
+        ))?;
+        Ok(records)
+    }
+}
+
+// The Dublin Core of one record, by element name, plus where it came from.
+#[derive(Clone, Debug, Default)]
+struct Record {
+    identifier: String,
+    deleted: bool,
+    fields: HashMap<String, Vec<String>>,
+    // The record's XML, kept as the work's source so that it can be re-mapped later.
+    xml: String,
+}
+
+impl Record {
+    fn all(&self, name: &str) -> &[String] {
+        self.fields.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn first(&self, name: &str) -> Option<&str> {
+        self.all(name).first().map(String::as_str)
+    }
+
+    fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        for (element, kind) in [
+            ("subject", TagKind::Default),
+            ("type", TagKind::Meta),
+            ("creator", TagKind::Default),
+            ("coverage", TagKind::Location),
+        ] {
+            for value in self.all(element) {
+                tags.push(Tag::new(value).with_kind(kind).with_source_type(element));
+            }
+        }
+        tags
+    }
+
+    // The image to show: an identifier or relation that points straight at an image file.
+    fn image_url(&self) -> Option<&str> {
+        self.all("identifier")
+            .iter()
+            .chain(self.all("relation"))
+            .map(String::as_str)
+            .find(|value| {
+                let lower = value.to_lowercase();
+                let path = lower.split(['?', '#']).next().unwrap_or_default();
+                lower.starts_with("http") && IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+            })
+    }
+
+    fn to_work(&self, repository_tag: &str) -> Option<Work> {
+        if self.deleted {
+            return None;
+        }
+        let image = self.image_url()?;
+        let title = self
+            .first("title")
+            .map(str::to_owned)
+            .unwrap_or_else(|| self.identifier.clone());
+
+        let mut history = History::default();
+        let creators = self.all("creator");
+        if !creators.is_empty() {
+            history.set_attribution(creators.join("; "));
+        }
+        let date = self.first("date");
+        if let Some(date) = date {
+            history.set_display_date(date);
+            if let Some(year) = leading_year(date) {
+                history.set_begin_year(year);
+            }
+        }
+        if let Some(rights) = self.first("rights") {
+            history.set_provenance(rights);
+        }
+        if let Some(publisher) = self.first("publisher").or(self.first("source")) {
+            history.set_credit_line(publisher);
+        }
+
+        let mut physical = PhysicalData::default();
+        // Note: format is usually a mime type, which says nothing about the work itself.
+        if let Some(format) = self.all("format").iter().find(|f| !f.contains('/')) {
+            physical.set_medium(format);
+        }
+
+        let mut tags = self
+            .tags()
+            .iter()
+            .map(|tag| tag.name().to_owned())
+            .collect::<Vec<_>>();
+        tags.push(repository_tag.to_owned());
+        Some(
+            Work::new(
+                title,
+                date.and_then(parse_date).unwrap_or_default(),
+                image,
+                image,
+                tags,
+            )
+            .with_remote_id(&self.identifier)
+            .with_history(history)
+            .with_physical_data(physical)
+            .with_source(&self.xml),
+        )
+    }
+}
+
+fn leading_year(date: &str) -> Option<i64> {
+    let digits = date
+        .trim_start_matches(|c: char| !c.is_ascii_digit() && c != '-')
+        .get(..4)?;
+    digits.parse().ok()
+}
+
+// Dublin Core dates are W3CDTF when the repository follows the guidelines, and free text when
+// it doesn't; take whatever year we can find.
+fn parse_date(date: &str) -> Option<Date> {
+    if let Some(day) = date.get(..10)
+        && let Ok(date) = day.parse::<Date>()
+    {
+        return Some(date);
+    }
+    Date::new(leading_year(date)?.try_into().ok()?, 1, 1).ok()
+}
+
+fn oai_error(code: &str, message: &str) -> Error {
+    Error::msg(format!("the repository said {code}: {message}"))
+}
+
+// The text of the first element with this local name.
+fn first_text(xml: &str, name: &str) -> FnResult<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut inside = false;
+    loop {
+        match reader.read_event().map_err(Error::msg)? {
+            Event::Start(e) if e.local_name().as_ref() == name.as_bytes() => inside = true,
+            Event::Text(t) if inside => {
+                return Ok(Some(t.unescape().map_err(Error::msg)?.into_owned()));
+            }
+            Event::End(_) if inside => return Ok(None),
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// The records on one page of ListRecords, and the token for the next page, if there is one.
+// Also reads a lone `<record>`, as kept in a work's source.
+fn parse_records(xml: &str) -> FnResult<(Vec<Record>, Option<String>)> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut records = Vec::new();
+    let mut token = None;
+    let mut record: Option<(usize, Record)> = None;
+    let mut in_header = false;
+    let mut in_metadata = false;
+    let mut error = None;
+    // The element whose text we are reading.
+    let mut element = None::<String>;
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(Error::msg)?;
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "record" => record = Some((start, Record::default())),
+                    "header" => {
+                        in_header = true;
+                        if let Some((_, record)) = &mut record
+                            && let Some(status) =
+                                e.try_get_attribute("status").map_err(Error::msg)?
+                        {
+                            record.deleted =
+                                status.unescape_value().map_err(Error::msg)? == "deleted";
+                        }
+                    }
+                    "metadata" => in_metadata = true,
+                    "error" => {
+                        let code = match e.try_get_attribute("code").map_err(Error::msg)? {
+                            Some(code) => code.unescape_value().map_err(Error::msg)?.into_owned(),
+                            None => "an error".to_owned(),
+                        };
+                        error = Some((code, String::new()));
+                    }
+                    _ => {}
+                }
+                element = Some(name);
+            }
+            Event::Text(t) => {
+                let text = t.unescape().map_err(Error::msg)?.into_owned();
+                match element.as_deref() {
+                    Some("error") => {
+                        if let Some((_, message)) = &mut error {
+                            *message = text;
+                        }
+                    }
+                    Some("resumptionToken") => token = Some(text),
+                    Some("identifier") if in_header => {
+                        if let Some((_, record)) = &mut record {
+                            record.identifier = text;
+                        }
+                    }
+                    Some(name) if in_metadata => {
+                        if let Some((_, record)) = &mut record
+                            && !text.is_empty()
+                        {
+                            record.fields.entry(name.to_owned()).or_default().push(text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"error" => {
+                let code = match e.try_get_attribute("code").map_err(Error::msg)? {
+                    Some(code) => code.unescape_value().map_err(Error::msg)?.into_owned(),
+                    None => "an error".to_owned(),
+                };
+                error = Some((code, String::new()));
+            }
+            Event::End(e) => {
+                element = None;
+                match e.local_name().as_ref() {
+                    b"header" => in_header = false,
+                    b"metadata" => in_metadata = false,
+                    b"record" => {
+                        if let Some((start, mut done)) = record.take() {
+                            let end = reader.buffer_position() as usize;
+                            done.xml = xml.get(start..end).unwrap_or_default().trim().to_owned();
+                            records.push(done);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    match error {
+        // Note: an empty set or a harvest with nothing new is an error in OAI-PMH, not to us.
+        Some((code, _)) if code == "noRecordsMatch" => Ok((Vec::new(), None)),
+        Some((code, message)) => Err(oai_error(&code, &message).into()),
+        None => Ok((records, token.filter(|token| !token.is_empty()))),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
+    let harvest = Harvest::from_config()?;
+    let repository_tag = harvest.repository_tag()?;
+    Log::info(format!(
+        "Harvesting {repository_tag} from {}",
+        harvest.endpoint
+    ))?;
+    let records = harvest.records()?;
+
+    let mut counts = HashMap::<Tag, u64>::new();
+    let mut shown = 0;
+    for record in &records {
+        if record.deleted || record.image_url().is_none() {
+            continue;
+        }
+        shown += 1;
+        for tag in record.tags() {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    Log::info(format!(
+        "Found {shown} works with images in {} records",
+        records.len()
+    ))?;
+    let mut tags = counts
+        .into_iter()
+        .map(|(tag, count)| tag.with_remote_work_count(count))
+        .collect::<Vec<_>>();
+    tags.push(
+        Tag::new(repository_tag)
+            .with_kind(TagKind::Series)
+            .with_source_type("repository")
+            .with_remote_work_count(shown),
+    );
+    tags.sort();
+    Progress::clear()?;
+    Ok(tags.into())
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
+    let harvest = Harvest::from_config()?;
+    let repository_tag = harvest.repository_tag()?;
+    let works = harvest
+        .records()?
+        .iter()
+        .filter_map(|record| record.to_work(&repository_tag))
+        .filter(|work| work.tags().contains(&tag))
+        .collect::<Vec<_>>();
+    Progress::clear()?;
+    Ok(works.into())
+}
+
+// Re-build the work from the record XML that we handed over as its source.
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn map_source(source: String) -> FnResult<Json<Vec<Work>>> {
+    let harvest = Harvest::from_config()?;
+    let repository_tag = harvest.repository_tag()?;
+    let (records, _) = parse_records(&source)?;
+    Ok(records
+        .iter()
+        .filter_map(|record| record.to_work(&repository_tag))
+        .collect::<Vec<_>>()
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, LogLevel, MockHost};
+
+    const TEST_ENDPOINT: &str = "https://archive.example.org/oai";
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn host() -> MockHost {
+        let harvest = Harvest {
+            endpoint: TEST_ENDPOINT.to_owned(),
+            metadata_prefix: DEFAULT_METADATA_PREFIX.to_owned(),
+            set: "maps".to_owned(),
+        };
+        MockHost::new()
+            .with_config(ENDPOINT, ConfigValue::String(TEST_ENDPOINT.into()))
+            .with_config(SET, ConfigValue::String("maps".into()))
+            .with_fixture(harvest.identify_request().to_url(), fixture("identify.xml"))
+            .with_fixture(
+                harvest.records_request(None).to_url(),
+                fixture("records-1.xml"),
+            )
+            .with_fixture(
+                harvest.records_request(Some("maps/2")).to_url(),
+                fixture("records-2.xml"),
+            )
+    }
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        host().install();
+        let tags = testing::list_tags(list_tags)?;
+        let count = |name: &str| {
+            tags.iter()
+                .find(|tag| tag.name() == name)
+                .map(Tag::work_count)
+        };
+        // The deleted record and the one without an image are not counted.
+        assert_eq!(count("Example Archive: maps"), Some(2));
+        assert_eq!(count("Cartography"), Some(2));
+        assert_eq!(count("Mercator, Gerardus"), Some(1));
+        assert_eq!(testing::requests().len(), 3);
+        assert!(
+            testing::logs()
+                .iter()
+                .all(|line| line.level < LogLevel::Warn)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        host().install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "Mercator, Gerardus")?;
+        assert_eq!(works.len(), 1);
+        let work = &works[0];
+        assert_eq!(work.name(), "Map of the World");
+        assert_eq!(
+            work.screen_url(),
+            "https://archive.example.org/images/world.jpg"
+        );
+        assert_eq!(work.remote_id(), Some("oai:archive.example.org:1"));
+        assert_eq!(work.date(), &Date::new(1569, 1, 1)?);
+        let history = work.history().expect("history");
+        assert_eq!(history.attribution(), Some("Mercator, Gerardus"));
+        assert_eq!(history.display_date(), Some("1569"));
+
+        // The source maps back to the same work.
+        let remapped = testing::call(|| map_source(work.source().unwrap_or_default().to_owned()))?;
+        assert_eq!(remapped.len(), 1);
+        assert_eq!(remapped[0].name(), work.name());
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_records_match() -> Result<(), Error> {
+        let (records, token) = parse_records(
+            r#"<OAI-PMH><error code="noRecordsMatch">Nothing here</error></OAI-PMH>"#,
+        )?;
+        assert!(records.is_empty());
+        assert!(token.is_none());
+        assert!(
+            parse_records(r#"<OAI-PMH><error code="badArgument">No</error></OAI-PMH>"#).is_err()
+        );
+        Ok(())
+    }
+}
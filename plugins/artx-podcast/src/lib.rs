@@ -42,6 +42,32 @@ pub fn tags_for_item(channel: &Channel, item: &rss::Item) -> Vec<Tag> {
     tags
 }
 
+// The item's `<podcast:transcript>` in the format that shows best, if the feed has any.
+pub fn transcript_for_item(item: &rss::Item) -> Option<String> {
+    let rank = |mime: &str| match mime {
+        "text/vtt" => 4,
+        "application/x-subrip" | "application/srt" | "text/srt" => 3,
+        "application/json" => 2,
+        "text/html" => 1,
+        _ => 0,
+    };
+    item.extensions
+        .get("podcast")?
+        .get("transcript")?
+        .iter()
+        .filter_map(|ext| {
+            let url = ext.attrs.get("url")?;
+            let mime = ext
+                .attrs
+                .get("type")
+                .map(String::as_str)
+                .unwrap_or_default();
+            Some((rank(mime), url))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, url)| url.to_owned())
+}
+
 #[plugin_fn]
 pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
     let config = Config::get_string_list("Podcasts")?;
@@ -107,7 +133,7 @@ pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
                     panic!("Missing image");
                 };
 
-                works.push(Work::new(
+                let mut work = Work::new(
                     item.title
                         .clone()
                         .unwrap_or_else(|| format!("{} - {i}", channel.title)),
@@ -115,7 +141,11 @@ pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
                     image,
                     enclosure.url.clone(),
                     item_tags.iter().map(|t| t.name().to_owned()).collect(),
-                ));
+                );
+                if let Some(url) = transcript_for_item(item) {
+                    work = work.with_transcript_url(url);
+                }
+                works.push(work);
             }
         }
     }
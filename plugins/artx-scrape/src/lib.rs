@@ -0,0 +1,308 @@
+use artchiver_sdk::{scrape::Page, *};
+use extism_pdk::*;
+use std::{collections::HashMap, time::Duration};
+
+import_section!();
+
+// An example of a plugin for a source that has no API, only web pages, like the collection pages
+// of many smaller museums. It is also useful as it is: point it at a gallery page and tell it,
+// with CSS selectors, where the works are on the page, and it will archive them.
+//
+// The scraping helpers come from `artchiver_sdk::scrape`, which plugins get by turning on the
+// SDK's `scrape` feature in their Cargo.toml. See the tests at the bottom for a sample site.
+
+// The page listing the works, e.g. a search for a collection, sorted by date.
+const GALLERY_PAGE: &str = "Gallery Page";
+// Every selector is optional; the defaults match a typical grid of cards.
+const ITEM_SELECTOR: &str = "Item Selector";
+const TITLE_SELECTOR: &str = "Title Selector";
+const IMAGE_SELECTOR: &str = "Image Selector";
+const LINK_SELECTOR: &str = "Link Selector";
+const NEXT_SELECTOR: &str = "Next Page Selector";
+const TAG_SELECTOR: &str = "Tag Selector";
+const MAX_PAGES: &str = "Max Pages";
+
+const DEFAULT_ITEM: &str = "article, li.object, .card";
+const DEFAULT_TITLE: &str = "h2, h3, .title, figcaption";
+const DEFAULT_IMAGE: &str = "img";
+const DEFAULT_LINK: &str = "a[href]";
+const DEFAULT_MAX_PAGES: usize = 20;
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn startup() -> FnResult<Json<PluginMetadata>> {
+    Ok(Json(
+        PluginMetadata::new(
+            "Website Scraper",
+            "0.0.1",
+            "Archives the works listed on a gallery page of any website, found with CSS selectors.",
+        )
+        // Websites are not APIs and are often run on a shoestring; be gentle.
+        .with_rate_limit(1, 2.0)
+        .with_scraping()
+        .with_cache_timeout(Duration::from_secs(7 * 24 * 60 * 60))
+        .with_required_configuration(GALLERY_PAGE, ConfigKind::String)
+        .with_configuration(ITEM_SELECTOR, ConfigKind::String)
+        .with_configuration(TITLE_SELECTOR, ConfigKind::String)
+        .with_configuration(IMAGE_SELECTOR, ConfigKind::String)
+        .with_configuration(LINK_SELECTOR, ConfigKind::String)
+        .with_configuration(NEXT_SELECTOR, ConfigKind::String)
+        .with_configuration(TAG_SELECTOR, ConfigKind::String)
+        .with_configuration(MAX_PAGES, ConfigKind::String),
+    ))
+}
+
+// Unset and empty configurations both mean the default.
+fn config_or(name: &str, default: &str) -> String {
+    Config::get_string(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| default.to_owned())
+}
+
+fn config_opt(name: &str) -> Option<String> {
+    Config::get_string(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+// What we found for one work, before we know every tag it has.
+struct Found {
+    title: String,
+    preview_url: String,
+    screen_url: String,
+    page_url: Option<String>,
+    tags: Vec<String>,
+}
+
+// Read every gallery page, then the page for each work on them.
+fn scrape_site() -> FnResult<(String, Vec<Found>)> {
+    let start = Config::get_string(GALLERY_PAGE)?;
+    let item_css = config_or(ITEM_SELECTOR, DEFAULT_ITEM);
+    let title_css = config_or(TITLE_SELECTOR, DEFAULT_TITLE);
+    let image_css = config_or(IMAGE_SELECTOR, DEFAULT_IMAGE);
+    let link_css = config_or(LINK_SELECTOR, DEFAULT_LINK);
+    let next_css = config_opt(NEXT_SELECTOR);
+    let tag_css = config_opt(TAG_SELECTOR);
+    let max_pages = config_opt(MAX_PAGES)
+        .and_then(|max| max.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_PAGES);
+
+    // `paginate` follows the next page links for us, fetching through the host like any other
+    // request. Without a selector for the link, it looks for the page's `rel="next"` link.
+    Progress::spinner()?;
+    let pages = scrape::paginate(&start, next_css.as_deref(), max_pages, |url| {
+        Log::trace(format!("Reading gallery page {url}")).ok();
+        Web::fetch_text(Request::get(url))
+    })?;
+
+    // Every work gets tagged with the name of the site, so that the user has somewhere to start.
+    let site = pages
+        .first()
+        .and_then(|page| {
+            page.meta("og:site_name")
+                .or_else(|| page.text("title").ok().flatten())
+        })
+        .unwrap_or_else(|| start.clone());
+
+    let mut found = Vec::new();
+    for page in &pages {
+        for item in page.items(&item_css)? {
+            // Skip anything in the grid that is not a work, like an advertisement or a banner.
+            let Some(preview_url) = item.image(&image_css)? else {
+                continue;
+            };
+            let title = item.text(&title_css)?.unwrap_or_else(|| item.own_text());
+            let page_url = item.link(&link_css)?.or_else(|| {
+                item.own_attr("href")
+                    .map(|href| scrape::resolve_url(page.url(), href))
+            });
+            found.push(Found {
+                title,
+                screen_url: preview_url.clone(),
+                preview_url,
+                page_url,
+                tags: vec![site.clone()],
+            });
+        }
+    }
+
+    // The gallery usually shows a thumbnail; the page for each work has the full image, in the
+    // tag that the site fills in for sharing, and often the subjects or keywords for it.
+    let total = found.len() as i32;
+    for (i, work) in found.iter_mut().enumerate() {
+        Progress::percent(i as i32, total)?;
+        let Some(page_url) = &work.page_url else {
+            continue;
+        };
+        let html = match Web::fetch_text(Request::get(page_url)) {
+            Ok(html) => html,
+            Err(e) => {
+                Log::warn(format!("Failed to read {page_url}: {e}"))?;
+                continue;
+            }
+        };
+        let page = Page::parse(page_url, &html);
+        if let Some(image) = page.meta("og:image") {
+            work.screen_url = scrape::resolve_url(page.url(), &image);
+        }
+        if let Some(tag_css) = &tag_css {
+            work.tags.extend(page.texts(tag_css)?);
+        }
+    }
+    Progress::clear()?;
+    Ok((site, found))
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
+    let (site, found) = scrape_site()?;
+    let mut counts = HashMap::<&str, u64>::new();
+    for work in &found {
+        for tag in &work.tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut tags = counts
+        .into_iter()
+        .map(|(name, count)| {
+            let tag = Tag::new(name).with_remote_work_count(count);
+            if name == site {
+                tag.with_kind(TagKind::Series).with_source_type("site")
+            } else {
+                tag
+            }
+        })
+        .collect::<Vec<_>>();
+    tags.sort();
+    Ok(tags.into())
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
+    let (_site, found) = scrape_site()?;
+    Ok(found
+        .into_iter()
+        .filter(|work| work.tags.contains(&tag))
+        .map(|work| {
+            // Web pages rarely give a date we can trust, so we leave it unset.
+            let out = Work::new(
+                work.title,
+                Default::default(),
+                work.preview_url,
+                work.screen_url,
+                work.tags,
+            );
+            match work.page_url {
+                Some(page_url) => out.with_remote_id(page_url),
+                None => out,
+            }
+        })
+        .collect::<Vec<_>>()
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, MockHost};
+
+    const SITE: &str = "https://museum.example.org";
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn host() -> MockHost {
+        MockHost::new()
+            .with_config(
+                GALLERY_PAGE,
+                ConfigValue::String(format!("{SITE}/collection/prints/")),
+            )
+            .with_config(TAG_SELECTOR, ConfigValue::String(".subjects li".to_owned()))
+            .with_fixture(
+                format!("{SITE}/collection/prints/"),
+                fixture("gallery-1.html"),
+            )
+            .with_fixture(
+                format!("{SITE}/collection/prints/?page=2"),
+                fixture("gallery-2.html"),
+            )
+            .with_fixture(
+                format!("{SITE}/collection/objects/101"),
+                fixture("object-101.html"),
+            )
+            .with_fixture(
+                format!("{SITE}/collection/objects/102"),
+                fixture("object-102.html"),
+            )
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://museum.example.org/collection/prints/index.html?page=2#top";
+        assert_eq!(
+            scrape::resolve_url(base, "../objects/101"),
+            "https://museum.example.org/collection/objects/101"
+        );
+        assert_eq!(
+            scrape::resolve_url(base, "/img/a.jpg"),
+            "https://museum.example.org/img/a.jpg"
+        );
+        assert_eq!(
+            scrape::resolve_url(base, "//cdn.example.org/a.jpg"),
+            "https://cdn.example.org/a.jpg"
+        );
+        assert_eq!(
+            scrape::resolve_url(base, "?page=3"),
+            "https://museum.example.org/collection/prints/index.html?page=3"
+        );
+        assert_eq!(
+            scrape::resolve_url(base, "./"),
+            "https://museum.example.org/collection/prints/"
+        );
+    }
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        host().install();
+        let tags = testing::list_tags(list_tags)?;
+        let count = |name: &str| {
+            tags.iter()
+                .find(|tag| tag.name() == name)
+                .map(Tag::work_count)
+        };
+        // Three works over two pages; the banner in the grid is not one.
+        assert_eq!(count("Example Museum"), Some(3));
+        assert_eq!(count("Landscapes"), Some(2));
+        assert_eq!(count("Harbours"), Some(1));
+        // A missing page for a work is not fatal.
+        assert!(testing::requests().contains(&format!("{SITE}/collection/objects/103")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        host().install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "Example Museum")?;
+        assert_eq!(works.len(), 3);
+        let first = &works[0];
+        assert_eq!(first.name(), "View of the Harbour");
+        // The biggest image in the thumbnail's srcset, and the full image from the work's page.
+        assert_eq!(
+            first.preview_url(),
+            "https://museum.example.org/media/101-600.jpg"
+        );
+        assert_eq!(
+            first.screen_url(),
+            "https://museum.example.org/media/101-full.jpg"
+        );
+        assert_eq!(
+            first.remote_id(),
+            Some("https://museum.example.org/collection/objects/101")
+        );
+        // The third work's page is missing, so the thumbnail is all we have.
+        let third = &works[2];
+        assert_eq!(third.preview_url(), third.screen_url());
+        Ok(())
+    }
+}
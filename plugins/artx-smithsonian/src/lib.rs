@@ -12,7 +12,7 @@ pub fn startup() -> FnResult<Json<PluginMetadata>> {
             "A plugin for Artchiver to provide The Smithsonian's open data.",
         )
         .with_rate_limit(10, 1.0)
-        .with_configuration("API Key", ConfigKind::String),
+        .with_required_configuration("API Key", ConfigKind::String),
     ))
 }
 
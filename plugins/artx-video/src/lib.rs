@@ -0,0 +1,195 @@
+use artchiver_sdk::*;
+use extism_pdk::*;
+use jiff::civil::Date;
+use std::{collections::HashMap, time::Duration};
+
+import_section!();
+
+// Archives recorded artist talks, lectures, and video art from YouTube, PeerTube, and the other
+// sites that yt-dlp knows, by channel or playlist. The host does the resolving: it lists the
+// videos at each url, and downloads each video when asked, as the stream urls expire.
+
+const CHANNELS: &str = "Channels";
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn startup() -> FnResult<Json<PluginMetadata>> {
+    Ok(Json(
+        PluginMetadata::new(
+            "Video",
+            "0.0.1",
+            "Archives the videos in YouTube or PeerTube channels and playlists, via yt-dlp.",
+        )
+        .with_rate_limit(1, 2.0)
+        .with_cache_timeout(Duration::from_secs(24 * 60 * 60))
+        .with_required_configuration(CHANNELS, ConfigKind::StringList),
+    ))
+}
+
+fn parse_upload_date(date: &str) -> Option<Date> {
+    let year = date.get(0..4)?.parse().ok()?;
+    let month = date.get(4..6)?.parse().ok()?;
+    let day = date.get(6..8)?.parse().ok()?;
+    Date::new(year, month, day).ok()
+}
+
+// Every video is tagged with the channel or playlist it came from and who uploaded it, then with
+// whatever keywords the site has for it.
+fn tags_for_entry(entry: &MediaEntry) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    if let Some(playlist) = &entry.playlist {
+        tags.push(
+            Tag::new(playlist)
+                .with_kind(TagKind::Series)
+                .with_source_type("playlist"),
+        );
+    }
+    if let Some(uploader) = &entry.uploader {
+        tags.push(Tag::new(uploader).with_source_type("artist"));
+    }
+    tags.extend(
+        entry
+            .categories
+            .iter()
+            .map(|category| Tag::new(category).with_kind(TagKind::Meta)),
+    );
+    tags.extend(entry.tags.iter().map(Tag::new));
+    tags
+}
+
+fn all_entries() -> FnResult<Vec<MediaEntry>> {
+    let urls = Config::get_string_list(CHANNELS)?;
+    let mut entries = Vec::new();
+    for (i, url) in urls.iter().enumerate() {
+        Progress::percent(i as i32, urls.len() as i32)?;
+        let url = url.trim();
+        if url.is_empty() {
+            continue;
+        }
+        let found = Media::resolve(url)?;
+        Log::trace(format!("Found {} videos at {url}", found.len()))?;
+        entries.extend(found);
+    }
+    Progress::clear()?;
+    Ok(entries)
+}
+
+fn to_work(entry: &MediaEntry, tags: &[Tag]) -> FnResult<Option<Work>> {
+    // Note: without a thumbnail there is nothing to show in the gallery.
+    let Some(thumbnail_url) = &entry.thumbnail_url else {
+        Log::trace(format!("Skipping {}: no thumbnail", entry.page_url))?;
+        return Ok(None);
+    };
+    let date = entry.upload_date.as_deref().and_then(parse_upload_date);
+    let mut history = History::default();
+    if let Some(uploader) = &entry.uploader {
+        history.set_attribution(uploader);
+    }
+    if let Some(date) = date {
+        history.set_display_date(date);
+        history.set_begin_year(date.year().into());
+    }
+    Ok(Some(
+        Work::new(
+            &entry.title,
+            date.unwrap_or_default(),
+            thumbnail_url,
+            entry.download_url(),
+            tags.iter().map(|tag| tag.name().to_owned()).collect(),
+        )
+        .with_remote_id(&entry.id)
+        .with_history(history)
+        .with_source(serde_json::to_string(entry)?),
+    ))
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_tags() -> FnResult<Json<Vec<Tag>>> {
+    let mut acc = HashMap::<Tag, u64>::new();
+    for entry in all_entries()? {
+        for tag in tags_for_entry(&entry) {
+            *acc.entry(tag).or_default() += 1;
+        }
+    }
+    let mut tags = acc
+        .into_iter()
+        .map(|(tag, count)| tag.with_remote_work_count(count))
+        .collect::<Vec<_>>();
+    tags.sort();
+    Ok(tags.into())
+}
+
+#[cfg_attr(target_arch = "wasm32", plugin_fn)]
+pub fn list_works_for_tag(tag: String) -> FnResult<Json<Vec<Work>>> {
+    let mut works = Vec::new();
+    for entry in all_entries()? {
+        let tags = tags_for_entry(&entry);
+        if tags.iter().any(|t| t.name() == tag)
+            && let Some(work) = to_work(&entry, &tags)?
+        {
+            works.push(work);
+        }
+    }
+    Ok(works.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use artchiver_sdk::testing::{self, MockHost};
+
+    const CHANNEL_URL: &str = "https://www.youtube.com/@studiotalks/videos";
+
+    fn host() -> MockHost {
+        MockHost::new()
+            .with_config(
+                CHANNELS,
+                ConfigValue::StringList(vec![CHANNEL_URL.to_owned()]),
+            )
+            .with_media(
+                CHANNEL_URL,
+                format!("{}/fixtures/channel.json", env!("CARGO_MANIFEST_DIR")),
+            )
+    }
+
+    #[test]
+    fn test_list_tags() -> Result<(), Error> {
+        host().install();
+        let tags = testing::list_tags(list_tags)?;
+        let find = |name: &str| tags.iter().find(|tag| tag.name() == name);
+        let channel = find("Studio Talks - Videos").expect("channel tag");
+        assert_eq!(channel.kind(), TagKind::Series);
+        assert_eq!(channel.work_count(), 3);
+        assert_eq!(find("painting").map(Tag::work_count), Some(2));
+        assert_eq!(find("Education").map(Tag::kind), Some(TagKind::Meta));
+        assert_eq!(testing::requests(), vec![CHANNEL_URL]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_works_for_tag() -> Result<(), Error> {
+        host().install();
+        let works = testing::list_works_for_tag(list_works_for_tag, "Studio Talks - Videos")?;
+        // The third video has no thumbnail.
+        assert_eq!(works.len(), 2);
+        let work = &works[0];
+        assert_eq!(work.name(), "On Colour");
+        assert_eq!(work.date(), &Date::new(2024, 1, 2)?);
+        assert_eq!(
+            work.preview_url(),
+            "https://i.ytimg.com/vi/abc/maxresdefault.jpg"
+        );
+        assert_eq!(
+            work.screen_url(),
+            "ytdl://https://www.youtube.com/watch?v=abc"
+        );
+        assert_eq!(
+            media_page_url(work.screen_url()),
+            Some(("https://www.youtube.com/watch?v=abc", MediaKind::Video))
+        );
+        assert_eq!(
+            work.history().and_then(History::attribution),
+            Some("Studio Talks")
+        );
+        Ok(())
+    }
+}
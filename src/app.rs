@@ -5,8 +5,9 @@ use crate::{
         sync::{DbSyncHandle, connect_or_create},
         writer::DbWriteHandle,
     },
+    http::server::HttpServer,
     plugin::host::PluginHost,
-    shared::{environment::Environment, progress::ProgressMonitor},
+    shared::{environment::Environment, instance_lock::is_read_only, progress::ProgressMonitor},
     ux::dock::UxToplevel,
 };
 use eframe::glow;
@@ -35,6 +36,9 @@ pub struct ArtchiverApp {
     // Rebuild plugins on each run as we don't know where we'll be running from.
     host: PluginHost,
 
+    // The optional HTTP API; settings persist, but the listener is restarted each run.
+    http: HttpServer,
+
     // The main ux container.
     toplevel: UxToplevel,
 }
@@ -47,6 +51,7 @@ impl Default for ArtchiverApp {
         let (db_sync, db_write, db_read, db_cancel) =
             connect_or_create(&env, &progress_mon).expect("failed to connect to database");
         let host = PluginHost::default();
+        let http = HttpServer::default();
         let toplevel = UxToplevel::default();
 
         Self {
@@ -57,6 +62,7 @@ impl Default for ArtchiverApp {
             db_read,
             db_cancel,
             host,
+            http,
             toplevel,
         }
     }
@@ -79,11 +85,14 @@ impl ArtchiverApp {
         } else {
             Default::default()
         };
+        app.http
+            .initialize(&app.env, &app.progress_mon, &app.db_sync, &app.db_write);
 
         app.toplevel.startup(
             &cc.egui_ctx,
-            &app.environment().data_dir(),
+            app.environment().storage(),
             &app.db_read,
+            &app.db_write,
             cc,
         );
         app
@@ -102,16 +111,28 @@ impl eframe::App for ArtchiverApp {
         self.toplevel.handle_updates(&updates, &self.db_read);
 
         self.toplevel
-            .draw(&self.db_read, &self.db_write, &mut self.host, ctx, frame)
+            .draw(
+                &self.db_read,
+                &self.db_write,
+                &mut self.host,
+                &mut self.http,
+                ctx,
+                frame,
+            )
             .expect("ux update error");
     }
 
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        // Note: the instance that holds the lock owns the app state; we didn't load any plugins,
+        //       so would throw out all of their settings.
+        if !is_read_only() {
+            eframe::set_value(storage, eframe::APP_KEY, self);
+        }
     }
 
     fn on_exit(&mut self, _gl: Option<&glow::Context>) {
+        self.http.cleanup_for_exit();
         self.host
             .cleanup_for_exit()
             .expect("failed to cleanup plugins on exit");
@@ -120,5 +141,10 @@ impl eframe::App for ArtchiverApp {
         self.db_cancel.cancel();
         self.db_write.send_exit_request();
         self.db_read.wait_for_exit();
+
+        // Note: a read-only instance shares the cache with the one that holds the lock.
+        if !is_read_only() {
+            self.env.storage().clear_decrypted();
+        }
     }
 }
@@ -0,0 +1,127 @@
+// Storing what Wikidata told us about works; see shared::enrichment for the asking.
+//
+// Enrichments are keyed by screen_url, as work ids change when a plugin re-sends a work, and
+// the tags are applied to work_tags again after every upsert, as for collections.
+use crate::shared::enrichment::{EnrichedWork, Statement};
+use anyhow::Result;
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, params};
+
+fn tagged_statements() -> String {
+    Statement::TAGGED
+        .map(|statement| format!("'{}'", statement.name()))
+        .join(", ")
+}
+
+// Record what we found, and mark the works as looked up, even if we found nothing. Returns
+// whether any works got tags.
+pub fn save_enrichments(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    works: &[EnrichedWork],
+) -> Result<bool> {
+    let now = Timestamp::now().as_millisecond();
+    let mut tagged = false;
+    let xaction = conn.transaction()?;
+    {
+        let mut insert_scan = xaction.prepare(
+            "INSERT OR REPLACE INTO enrichment_scans (screen_url, scanned_at) VALUES (?, ?)",
+        )?;
+        let mut delete_enrichments =
+            xaction.prepare("DELETE FROM enrichments WHERE screen_url = ?")?;
+        let mut insert_enrichment = xaction.prepare(
+            r#"INSERT OR IGNORE INTO enrichments (screen_url, statement, value, label)
+            VALUES (?, ?, ?, ?)"#,
+        )?;
+        // Note: the tag may already exist, from a plugin; leave its kind alone.
+        let mut insert_tag = xaction
+            .prepare("INSERT INTO tags (name, kind) VALUES (?, ?) ON CONFLICT DO NOTHING")?;
+        let mut upsert_artist = xaction.prepare(
+            r#"INSERT INTO artists
+                (name, birthday, deathday, nationality, bio, wikidata_id, enriched)
+            VALUES (?, ?, ?, ?, ?, ?, true)
+            ON CONFLICT (wikidata_id) DO UPDATE SET
+                name = excluded.name, birthday = excluded.birthday,
+                deathday = excluded.deathday, nationality = excluded.nationality,
+                bio = excluded.bio"#,
+        )?;
+        for work in works {
+            insert_scan.execute(params![work.screen_url, now])?;
+            delete_enrichments.execute(params![work.screen_url])?;
+            for enrichment in &work.enrichments {
+                insert_enrichment.execute(params![
+                    work.screen_url,
+                    enrichment.statement.name(),
+                    enrichment.value,
+                    enrichment.label
+                ])?;
+                if let Some(kind) = enrichment.statement.tag_kind() {
+                    insert_tag.execute(params![enrichment.label, kind.to_string()])?;
+                    tagged = true;
+                }
+            }
+            for artist in &work.artists {
+                upsert_artist.execute(params![
+                    artist.name,
+                    artist.birthday.map(|date| date.to_string()),
+                    artist.deathday.map(|date| date.to_string()),
+                    artist.nationality,
+                    artist.bio,
+                    artist.wikidata_id
+                ])?;
+            }
+        }
+        apply_enriched_tags(&xaction)?;
+    }
+    xaction.commit()?;
+    Ok(tagged)
+}
+
+// Tag the works with what they were enriched with. Tags the user took off of a work stay off.
+pub fn apply_enriched_tags(conn: &Connection) -> Result<()> {
+    conn.execute(
+        &format!(
+            r#"INSERT OR IGNORE INTO work_tags (tag_id, work_id)
+            SELECT tags.id, works.id FROM enrichments AS e
+                JOIN works ON works.screen_url = e.screen_url
+                JOIN tags ON tags.name = e.label
+            WHERE e.statement IN ({})
+                AND NOT EXISTS (
+                    SELECT 1 FROM tag_edits
+                    WHERE tag_edits.screen_url = e.screen_url AND tag_edits.tag = e.label
+                        AND tag_edits.added = 0
+                )"#,
+            tagged_statements()
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+// Take everything that enrichment added back off again, and forget which works we looked up,
+// so that turning enrichment back on starts over. Returns how many tags came off of works.
+//
+// Note: a tag that a plugin also has is the plugin's to give, so we leave it on the works.
+pub fn remove_enrichments(conn: &mut PooledConnection<SqliteConnectionManager>) -> Result<usize> {
+    let xaction = conn.transaction()?;
+    let removed = xaction.execute(
+        &format!(
+            r#"DELETE FROM work_tags WHERE id IN (
+                SELECT work_tags.id FROM enrichments AS e
+                    JOIN works ON works.screen_url = e.screen_url
+                    JOIN tags ON tags.name = e.label
+                    JOIN work_tags ON work_tags.tag_id = tags.id AND work_tags.work_id = works.id
+                WHERE e.statement IN ({})
+                    AND NOT EXISTS (SELECT 1 FROM plugin_tags WHERE plugin_tags.tag_id = tags.id)
+            )"#,
+            tagged_statements()
+        ),
+        [],
+    )?;
+    xaction.execute("DELETE FROM enrichments", [])?;
+    xaction.execute("DELETE FROM enrichment_scans", [])?;
+    xaction.execute("DELETE FROM artists WHERE enriched", [])?;
+    xaction.commit()?;
+    Ok(removed)
+}
@@ -0,0 +1,476 @@
+// Imports from Hydrus Network, for users moving an existing archive over. Either point us at the
+// Hydrus db folder, the one with client.db in it, or at a folder that Hydrus exported files to
+// with .txt sidecars of their tags and urls.
+//
+// Hydrus names each file by its sha256, so we know what a file is without reading it, and
+// record it in file_hashes like a scrub would. A file that we already have, by hash or by one of
+// its urls, gets the tags and rating added to the work that we have, rather than a second work.
+//
+// Note: the client DB layout is Hydrus' own and changes between releases; this reads the tables
+//       that have been stable for a long while (hashes, tags, urls, and local ratings) and fails
+//       with the missing table rather than guessing, should that change. Hydrus should be closed
+//       while we read it.
+use crate::{
+    db::{models::work::WorkId, relocate::list_files, scrub::hash_file},
+    plugin::thumbnail::media_type_of,
+    shared::progress::{HostUpdateSender, LogSender, ProgressSender},
+};
+use anyhow::{Context as _, Result, bail};
+use artchiver_sdk::{Tag, TagKind};
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OpenFlags, OptionalExtension as _, params};
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+pub const HYDRUS_IMPORT_TAG: &str = "Hydrus";
+
+// Ratings at or above this, out of 1, make the work a favorite; a "like" is a 1.
+const FAVORITE_RATING: f64 = 0.8;
+
+// Namespaces that describe the file rather than what is in it. The title becomes the work's
+// name instead.
+const SKIPPED_NAMESPACES: [&str; 5] = ["title", "page", "filename", "md5", "sha256"];
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct HydrusTag {
+    pub namespace: String,
+    pub subtag: String,
+}
+
+impl HydrusTag {
+    // As Hydrus writes them: `namespace:subtag`, or just `subtag`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return None;
+        }
+        let (namespace, subtag) = match tag.split_once(':') {
+            // Note: Hydrus escapes a leading colon in a plain tag, e.g. `::)`, with another one.
+            Some(("", subtag)) => ("", subtag),
+            Some((namespace, subtag)) if !namespace.contains(' ') => (namespace, subtag),
+            _ => ("", tag),
+        };
+        let subtag = subtag.trim();
+        (!subtag.is_empty()).then(|| Self {
+            namespace: namespace.to_lowercase(),
+            subtag: subtag.to_owned(),
+        })
+    }
+
+    pub fn to_tag(&self) -> Tag {
+        let kind = match self.namespace.as_str() {
+            "character" => TagKind::Character,
+            "series" => TagKind::Series,
+            "copyright" => TagKind::Copyright,
+            "meta" | "system" => TagKind::Meta,
+            "location" | "place" => TagKind::Location,
+            "style" => TagKind::Style,
+            "medium" | "technique" => TagKind::Technique,
+            _ => TagKind::Default,
+        };
+        let source_type = if self.namespace.is_empty() {
+            "unnamespaced"
+        } else {
+            &self.namespace
+        };
+        Tag::new(&self.subtag)
+            .with_kind(kind)
+            .with_source_type(source_type)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HydrusFile {
+    pub path: PathBuf,
+    // Hex, as in file_hashes.
+    pub sha256: String,
+    pub tags: Vec<HydrusTag>,
+    pub urls: Vec<String>,
+    // Out of 1, the best of the file's ratings.
+    pub rating: Option<f64>,
+}
+
+impl HydrusFile {
+    pub fn title(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.namespace == "title")
+            .map(|tag| tag.subtag.as_str())
+    }
+
+    pub fn is_favorite(&self) -> bool {
+        self.rating.is_some_and(|rating| rating >= FAVORITE_RATING)
+    }
+
+    pub fn imported_tags(&self) -> impl Iterator<Item = &HydrusTag> {
+        self.tags
+            .iter()
+            .filter(|tag| !SKIPPED_NAMESPACES.contains(&tag.namespace.as_str()))
+    }
+
+    // The names of the tags to give the work, including the one for everything from Hydrus.
+    pub fn tag_names(&self) -> Vec<String> {
+        let mut names = self
+            .imported_tags()
+            .map(|tag| tag.subtag.clone())
+            .collect::<BTreeSet<_>>();
+        names.insert(HYDRUS_IMPORT_TAG.to_owned());
+        names.into_iter().collect()
+    }
+}
+
+pub fn read_hydrus(
+    path: &Path,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<Vec<HydrusFile>> {
+    let files = if let Some(db_dir) = hydrus_db_dir(path) {
+        log.info(format!(
+            "Reading the Hydrus database in {}",
+            db_dir.display()
+        ));
+        read_client_db(&db_dir, log, progress)?
+    } else if path.is_dir() {
+        log.info(format!("Reading Hydrus exports in {}", path.display()));
+        read_sidecars(path, log, progress)?
+    } else {
+        bail!(
+            "{} is neither a Hydrus db folder nor a folder of exported files",
+            path.display()
+        );
+    };
+    progress.clear();
+    Ok(files)
+}
+
+fn hydrus_db_dir(path: &Path) -> Option<PathBuf> {
+    if path.is_file() && path.file_name().is_some_and(|name| name == "client.db") {
+        path.parent().map(Path::to_owned)
+    } else if path.join("client.db").is_file() {
+        Some(path.to_owned())
+    } else {
+        None
+    }
+}
+
+fn read_client_db(
+    db_dir: &Path,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<Vec<HydrusFile>> {
+    // Note: databases attached to a read-only connection are opened read-only too.
+    let conn = Connection::open_with_flags(
+        db_dir.join("client.db"),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    for (schema, file) in [
+        ("master", "client.master.db"),
+        ("mappings", "client.mappings.db"),
+    ] {
+        let path = db_dir.join(file);
+        conn.execute(
+            &format!("ATTACH DATABASE ? AS {schema}"),
+            [path.to_string_lossy()],
+        )
+        .with_context(|| format!("opening {}", path.display()))?;
+    }
+    let mapping_tables = conn
+        .prepare(
+            r#"SELECT name FROM mappings.sqlite_master
+            WHERE type = 'table' AND name LIKE 'current_mappings_%'"#,
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let has_ratings = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'local_ratings'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+
+    let on_disk = client_file_paths(&conn, db_dir)?;
+    log.info(format!(
+        "Found {} files in the Hydrus file store",
+        on_disk.len()
+    ));
+    let total = on_disk.len();
+    let mut files = Vec::with_capacity(total);
+    for (i, (sha256, path)) in on_disk.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        let Some(hash) = decode_hex(&sha256) else {
+            continue;
+        };
+        let hash_id = conn
+            .prepare_cached("SELECT hash_id FROM master.hashes WHERE hash = ?")?
+            .query_row([hash], |row| row.get::<_, i64>(0))
+            .optional()?;
+        let Some(hash_id) = hash_id else {
+            log.warn(format!("Skipping {}: not in the database", path.display()));
+            continue;
+        };
+        let mut file = HydrusFile {
+            path,
+            sha256,
+            ..HydrusFile::default()
+        };
+        for table in &mapping_tables {
+            let mut stmt = conn.prepare_cached(&format!(
+                r#"SELECT n.namespace, s.subtag FROM mappings.{table} AS m
+                JOIN master.tags AS t ON t.tag_id = m.tag_id
+                JOIN master.namespaces AS n ON n.namespace_id = t.namespace_id
+                JOIN master.subtags AS s ON s.subtag_id = t.subtag_id
+                WHERE m.hash_id = ?"#
+            ))?;
+            for row in stmt.query_map([hash_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })? {
+                let (namespace, subtag) = row?;
+                file.tags.push(HydrusTag { namespace, subtag });
+            }
+        }
+        file.tags.sort();
+        file.tags.dedup();
+        file.urls = conn
+            .prepare_cached(
+                r#"SELECT u.url FROM url_map AS m JOIN master.urls AS u ON u.url_id = m.url_id
+                WHERE m.hash_id = ?"#,
+            )?
+            .query_map([hash_id], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if has_ratings {
+            file.rating = conn
+                .prepare_cached("SELECT MAX(rating) FROM local_ratings WHERE hash_id = ?")?
+                .query_row([hash_id], |row| row.get::<_, Option<f64>>(0))?;
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+// The files in the Hydrus file store, by their hash. The store may have been split up over
+// several drives; otherwise it is client_files, next to the db.
+fn client_file_paths(conn: &Connection, db_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut locations = BTreeSet::new();
+    for table in ["client_files_subfolders", "client_files_locations"] {
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [table],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if exists {
+            let mut stmt = conn.prepare(&format!("SELECT DISTINCT location FROM {table}"))?;
+            for location in stmt.query_map([], |row| row.get::<_, String>(0))? {
+                // Note: relative locations are relative to the db dir.
+                locations.insert(db_dir.join(location?));
+            }
+        }
+    }
+    if locations.is_empty() {
+        locations.insert(db_dir.join("client_files"));
+    }
+
+    let mut paths = Vec::new();
+    for location in &locations {
+        if let Err(e) = list_files(location, &mut paths) {
+            bail!("listing {}: {e}", location.display());
+        }
+    }
+    let mut files = paths
+        .into_iter()
+        .filter(|path| media_type_of(path).is_some())
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_ascii_lowercase();
+            (stem.len() == 64 && decode_hex(&stem).is_some()).then_some((stem, path))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup_by(|a, b| a.0 == b.0);
+    Ok(files)
+}
+
+// Hydrus writes `<file>.txt` next to each exported file, with a tag or url per line.
+fn read_sidecars(
+    dir: &Path,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<Vec<HydrusFile>> {
+    let mut paths = Vec::new();
+    list_files(dir, &mut paths)?;
+    paths.retain(|path| media_type_of(path).is_some());
+    paths.sort();
+    let mut files = Vec::with_capacity(paths.len());
+    for (i, path) in paths.iter().enumerate() {
+        progress.set_percent(i, paths.len());
+        let mut sidecar = OsString::from(path.as_os_str());
+        sidecar.push(".txt");
+        let Ok(lines) = std::fs::read_to_string(PathBuf::from(sidecar)) else {
+            log.trace(format!("No sidecar for {}", path.display()));
+            continue;
+        };
+        let sha256 = match hash_file(path) {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                log.warn(format!("Skipping {}: {e}", path.display()));
+                continue;
+            }
+        };
+        let mut file = HydrusFile {
+            path: path.to_owned(),
+            sha256,
+            ..HydrusFile::default()
+        };
+        for line in lines.lines().map(str::trim) {
+            if line.starts_with("http://") || line.starts_with("https://") {
+                file.urls.push(line.to_owned());
+            } else if let Some(tag) = HydrusTag::parse(line) {
+                file.tags.push(tag);
+            }
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// The work that we already have for the file, going by the hash of its screen file, or by one
+// of its urls.
+pub fn find_existing(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    file: &HydrusFile,
+) -> Result<Option<i64>> {
+    let key = conn
+        .prepare_cached("SELECT path FROM file_hashes WHERE sha256 = ? AND NOT corrupt LIMIT 1")?
+        .query_row([&file.sha256], |row| row.get::<_, String>(0))
+        .optional()?;
+    if let Some(key) = key {
+        // Note: paths on a named root are stored as `<root>:<key>`.
+        let found = conn
+            .prepare_cached(
+                r#"SELECT id FROM works
+                WHERE screen_path = ?1 OR screen_path LIKE ('%:' || ?1) LIMIT 1"#,
+            )?
+            .query_row([&key], |row| row.get(0))
+            .optional()?;
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    let mut stmt = conn
+        .prepare_cached("SELECT id FROM works WHERE screen_url = ?1 OR archive_url = ?1 LIMIT 1")?;
+    for url in &file.urls {
+        if let Some(found) = stmt.query_row([url], |row| row.get(0)).optional()? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+// Add what Hydrus knows to a work that we already have. The tags must already exist.
+pub fn merge_into_existing(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: i64,
+    file: &HydrusFile,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO work_tags (tag_id, work_id) SELECT id, ? FROM tags WHERE name = ?",
+    )?;
+    for name in file.tag_names() {
+        stmt.execute(params![work_id, name])?;
+    }
+    if file.is_favorite() {
+        mark_favorite(conn, work_id, host)?;
+    }
+    Ok(())
+}
+
+pub fn mark_favorite(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: i64,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE works SET favorite = true, favorite_mtime = ? WHERE id = ? AND NOT favorite",
+        params![Timestamp::now().as_millisecond(), work_id],
+    )?;
+    if changed > 0 {
+        host.note_work_favorite_status_changed(WorkId::wrap(work_id), true)?;
+    }
+    Ok(())
+}
+
+// Where the file was found, for the work's notes, so that it can be traced back.
+pub fn source_note(file: &HydrusFile) -> Option<String> {
+    if file.urls.is_empty() {
+        return None;
+    }
+    let mut note = "Imported from Hydrus. Known urls:\n".to_owned();
+    for url in &file.urls {
+        note.push_str(&format!("\n- <{url}>"));
+    }
+    Some(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        let parse = |tag: &str| HydrusTag::parse(tag).map(|tag| (tag.namespace, tag.subtag));
+        assert_eq!(
+            parse("creator:Claude Monet"),
+            Some(("creator".to_owned(), "Claude Monet".to_owned()))
+        );
+        assert_eq!(
+            parse("water lilies"),
+            Some((String::new(), "water lilies".to_owned()))
+        );
+        assert_eq!(parse("::)"), Some((String::new(), ":)".to_owned())));
+        assert_eq!(
+            parse("note: see back"),
+            Some((String::new(), "note: see back".to_owned()))
+        );
+        assert_eq!(parse("  "), None);
+        assert_eq!(parse("series:"), None);
+    }
+
+    #[test]
+    fn test_tag_names() {
+        let file = HydrusFile {
+            tags: [
+                "title:Impression, Sunrise",
+                "creator:monet",
+                "monet",
+                "page:2",
+            ]
+            .into_iter()
+            .filter_map(HydrusTag::parse)
+            .collect(),
+            rating: Some(1.0),
+            ..HydrusFile::default()
+        };
+        assert_eq!(file.title(), Some("Impression, Sunrise"));
+        assert_eq!(file.tag_names(), [HYDRUS_IMPORT_TAG, "monet"]);
+        assert!(file.is_favorite());
+    }
+}
@@ -0,0 +1,168 @@
+// Files that the user drops on the window become works of their own, under a tag of their own.
+//
+// We copy the files in, rather than pointing at them where they are, so that the archive does not
+// break when the originals are moved or cleaned up. The work's url is the file:// url of where the
+// file came from, which keeps dropping the same file twice from making a second work.
+use crate::{
+    db::models::plugin::PluginId,
+    plugin::thumbnail::{GalleryThumb, is_image, make_gallery_thumbnail, media_type_of},
+    shared::{
+        progress::{LogSender, ProgressSender},
+        storage::{DataKind, Storage},
+    },
+};
+use anyhow::{Context as _, Result, ensure};
+use artchiver_sdk::Work;
+use jiff::{Timestamp, tz::TimeZone};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::{fs, io, path::Path};
+
+// Local imports are owned by a plugin that isn't, so that they have somewhere to hang their tag.
+pub const LOCAL_PLUGIN_NAME: &str = "Local Files";
+pub const LOCAL_IMPORT_TAG: &str = "Imported";
+
+#[derive(Clone, Debug)]
+pub struct LocalFile {
+    pub work: Work,
+    pub screen_path: String,
+    pub thumb: Option<GalleryThumb>,
+    pub file_size: Option<i64>,
+}
+
+pub fn local_plugin_id(conn: &PooledConnection<SqliteConnectionManager>) -> Result<PluginId> {
+    conn.execute(
+        "INSERT OR IGNORE INTO plugins (name) VALUES (?)",
+        params![LOCAL_PLUGIN_NAME],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM plugins WHERE name = ?",
+        params![LOCAL_PLUGIN_NAME],
+        PluginId::from_row,
+    )?)
+}
+
+// Copy each file into storage and describe it as a work. Files that we can't show, or can't
+// copy, are skipped with a warning rather than failing the whole drop.
+pub fn copy_local_files(
+    paths: &[impl AsRef<Path>],
+    storage: &Storage,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Vec<LocalFile> {
+    log.info(format!("Importing {} local files...", paths.len()));
+    let mut files = Vec::with_capacity(paths.len());
+    for (i, path) in paths.iter().enumerate() {
+        progress.set_percent(i, paths.len());
+        let path = path.as_ref();
+        match copy_local_file(path, storage, None, vec![LOCAL_IMPORT_TAG.to_owned()]) {
+            Ok(file) => files.push(file),
+            Err(e) => log.warn(format!("Skipping {}: {e}", path.display())),
+        }
+    }
+    progress.clear();
+    files
+}
+
+// The name defaults to the file's name.
+pub fn copy_local_file(
+    path: &Path,
+    storage: &Storage,
+    name: Option<&str>,
+    tags: Vec<String>,
+) -> Result<LocalFile> {
+    ensure!(
+        media_type_of(path).is_some(),
+        "not an image, video, or song that we know how to show"
+    );
+    let path = fs::canonicalize(path)?;
+    let meta = fs::metadata(&path)?;
+    ensure!(meta.is_file(), "not a file");
+    let url = format!("file://{}", path.display());
+
+    let (abs_path, screen_path) = storage.place_for_url(DataKind::Screen, &url)?;
+    if !abs_path.exists() {
+        fs::copy(&path, &abs_path)?;
+        storage.commit(&screen_path)?;
+    }
+    let thumb = if is_image(&path) {
+        Some(make_gallery_thumbnail(&url, &screen_path, storage)?)
+    } else {
+        None
+    };
+
+    // Note: the file's own date is the best guess we have for when the work was made.
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|time| Timestamp::try_from(time).ok())
+        .unwrap_or_else(Timestamp::now);
+    let name = name.map(str::to_owned).unwrap_or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| url.clone())
+    });
+    let work = Work::new(
+        name,
+        modified.to_zoned(TimeZone::system()).date(),
+        &url,
+        &url,
+        tags,
+    );
+    Ok(LocalFile {
+        work,
+        screen_path,
+        thumb,
+        file_size: i64::try_from(meta.len()).ok(),
+    })
+}
+
+// Download a file that the user pointed us at, e.g. from a manifest, into storage as a work of
+// its own, where a plugin would otherwise have fetched it.
+pub fn download_url_file(
+    url: &str,
+    storage: &Storage,
+    name: &str,
+    tags: Vec<String>,
+) -> Result<LocalFile> {
+    let (abs_path, screen_path) = storage.place_for_url(DataKind::Screen, url)?;
+    ensure!(
+        media_type_of(&abs_path).is_some(),
+        "not an image, video, or song that we know how to show"
+    );
+    if !abs_path.exists() {
+        let partial = abs_path.with_extension("partial");
+        {
+            let mut resp = ureq::get(url).call()?;
+            let mut fp = io::BufWriter::new(
+                fs::File::create(&partial)
+                    .with_context(|| format!("creating {}", partial.display()))?,
+            );
+            io::copy(&mut resp.body_mut().as_reader(), &mut fp)?;
+        }
+        fs::rename(&partial, &abs_path)?;
+        storage.commit(&screen_path)?;
+    }
+    let thumb = if is_image(&abs_path) {
+        Some(make_gallery_thumbnail(url, &screen_path, storage)?)
+    } else {
+        None
+    };
+    let file_size = fs::metadata(&abs_path)
+        .ok()
+        .and_then(|meta| i64::try_from(meta.len()).ok());
+    let work = Work::new(
+        name,
+        Timestamp::now().to_zoned(TimeZone::system()).date(),
+        url,
+        url,
+        tags,
+    );
+    Ok(LocalFile {
+        work,
+        screen_path,
+        thumb,
+        file_size,
+    })
+}
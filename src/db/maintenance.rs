@@ -0,0 +1,93 @@
+// Routine upkeep for the metadata database.
+//
+// Years of imports and re-fetches leave SQLite with free pages it never gives back and query
+// statistics that describe a much smaller archive. Scheduled runs are cheap: refresh the
+// statistics and hand back a bounded number of free pages. Running by hand also does the
+// one-time full VACUUM that switches an older database over to incremental vacuuming.
+use crate::shared::progress::{LogSender, ProgressSender};
+use anyhow::Result;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// How many free pages a scheduled run will release; about 64MiB with the default page size.
+const SCHEDULED_VACUUM_PAGES: u64 = 16 * 1024;
+
+// The value of PRAGMA auto_vacuum that allows incremental_vacuum.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OptimizeReport {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    // Whether this run rebuilt the whole file.
+    pub vacuumed: bool,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+fn pragma_u64(conn: &PooledConnection<SqliteConnectionManager>, name: &str) -> Result<u64> {
+    Ok(conn.query_one(&format!("PRAGMA {name}"), [], |row| row.get(0))?)
+}
+
+fn database_bytes(conn: &PooledConnection<SqliteConnectionManager>) -> Result<u64> {
+    Ok(pragma_u64(conn, "page_count")? * pragma_u64(conn, "page_size")?)
+}
+
+pub fn optimize_database(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    manual: bool,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<OptimizeReport> {
+    let start = Instant::now();
+    let before_bytes = database_bytes(conn)?;
+    log.info(format!(
+        "Optimizing the database ({:.1} MiB)",
+        before_bytes as f64 / (1024. * 1024.)
+    ));
+
+    progress.set_percent(0, 3);
+    conn.execute_batch("ANALYZE; PRAGMA optimize;")?;
+
+    progress.set_percent(1, 3);
+    let auto_vacuum: i64 = conn.query_one("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+    let mut vacuumed = false;
+    if auto_vacuum == AUTO_VACUUM_INCREMENTAL {
+        let free_pages = pragma_u64(conn, "freelist_count")?;
+        let pages = if manual {
+            free_pages
+        } else {
+            free_pages.min(SCHEDULED_VACUUM_PAGES)
+        };
+        if pages > 0 {
+            log.info(format!("Releasing {pages} of {free_pages} free pages"));
+            conn.execute_batch(&format!("PRAGMA incremental_vacuum({pages});"))?;
+        }
+    } else if manual {
+        // Note: the auto_vacuum mode of an existing database only changes on a full VACUUM,
+        //       which rewrites the whole file and needs as much free disk again.
+        log.info("Rebuilding the database to enable incremental vacuuming");
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+        vacuumed = true;
+    }
+
+    progress.set_percent(2, 3);
+    // Note: otherwise the freed space stays tied up in the write-ahead log.
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+    progress.clear();
+
+    let after_bytes = database_bytes(conn)?;
+    log.info(format!(
+        "Optimized the database to {:.1} MiB",
+        after_bytes as f64 / (1024. * 1024.)
+    ));
+    Ok(OptimizeReport {
+        before_bytes,
+        after_bytes,
+        vacuumed,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        error: None,
+    })
+}
@@ -0,0 +1,557 @@
+// Folder based sync of user metadata between devices.
+//
+// Each device writes its own `<device>.json` into a shared folder (Dropbox, Syncthing, a network
+// share, etc) and reads everyone else's. Because no two devices ever write the same file, the
+// sync tool never has to merge anything itself. We merge each field last-writer-wins using the
+// `*_mtime` stamps that the writer records when the user touches a field.
+//
+// Works are keyed by screen_url and tags by name, as those are stable across databases where
+// the row ids are not. Synced fields are favorite and hidden on works and tags, and the rating
+// on works; anything new that the user sets by hand should be added to SyncField. Exhibitions
+// are edited as a whole, so they sync as a whole, by name, with the newest copy winning.
+//
+// Note: collections are not synced, as they are not the user's: plugins report them, and every
+//       device rebuilds its own from its plugins.
+use crate::{
+    db::{
+        models::{exhibition::ExhibitionItem, tag::TagId, work::WorkId},
+        reader::list_exhibitions,
+        writer::{remove_exhibition, write_exhibition},
+    },
+    shared::progress::{HostUpdateSender, LogSender},
+};
+use anyhow::{Context as _, Result};
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OptionalExtension as _, params};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SyncTable {
+    Works,
+    Tags,
+}
+
+impl SyncTable {
+    fn table(&self) -> &'static str {
+        match self {
+            Self::Works => "works",
+            Self::Tags => "tags",
+        }
+    }
+
+    fn key_column(&self) -> &'static str {
+        match self {
+            Self::Works => "screen_url",
+            Self::Tags => "name",
+        }
+    }
+
+    fn fields(&self) -> &'static [SyncField] {
+        match self {
+            Self::Works => &[SyncField::Favorite, SyncField::Hidden, SyncField::Rating],
+            Self::Tags => &[SyncField::Favorite, SyncField::Hidden],
+        }
+    }
+}
+
+impl fmt::Display for SyncTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Works => write!(f, "Work"),
+            Self::Tags => write!(f, "Tag"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SyncField {
+    Favorite,
+    Hidden,
+    Rating,
+}
+
+impl SyncField {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Favorite => "favorite",
+            Self::Hidden => "hidden",
+            Self::Rating => "rating",
+        }
+    }
+
+    pub fn format_value(&self, value: i64) -> String {
+        match self {
+            Self::Favorite | Self::Hidden => (value != 0).to_string(),
+            Self::Rating => format!("{value}★"),
+        }
+    }
+}
+
+impl fmt::Display for SyncField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.column())
+    }
+}
+
+// One user-set field, as recorded in a device's sync file. Flags are stored as 0 or 1.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SyncEntry {
+    table: SyncTable,
+    key: String,
+    field: SyncField,
+    value: i64,
+    modified: i64,
+}
+
+impl SyncEntry {
+    // Sync files come from other machines, so don't trust them to hold sane values.
+    fn is_valid(&self) -> bool {
+        self.table.fields().contains(&self.field)
+            && match self.field {
+                SyncField::Favorite | SyncField::Hidden => self.value == 0 || self.value == 1,
+                SyncField::Rating => (0..=5).contains(&self.value),
+            }
+    }
+}
+
+// A whole exhibition, as recorded in a device's sync file. Deleted exhibitions are recorded,
+// with no items, so that the delete reaches the other devices.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SyncExhibition {
+    name: String,
+    deleted: bool,
+    items: Vec<ExhibitionItem>,
+    modified: i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SyncFile {
+    device: String,
+    written: i64,
+    entries: Vec<SyncEntry>,
+    #[serde(default)]
+    exhibitions: Vec<SyncExhibition>,
+}
+
+// A field that the user set on this device, but which was overridden by a newer edit made on
+// another device. We resolve these automatically, but show them so that surprises can be undone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub table: SyncTable,
+    pub key: String,
+    pub field: SyncField,
+    pub local_value: i64,
+    pub remote_value: i64,
+    pub remote_device: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub finished_at: Option<Timestamp>,
+    pub devices: Vec<String>,
+    pub applied: usize,
+    pub unmatched: usize,
+    pub conflicts: Vec<SyncConflict>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeOutcome {
+    // Nothing to do: the values agree or our edit is newer.
+    Keep,
+    // Adopt the remote value and stamp; the local value was never set by the user.
+    Adopt,
+    // Adopt the remote value and stamp, replacing an older edit made on this device.
+    Overwrite,
+}
+
+// Last-writer-wins for a single field. An un-stamped local value has never been touched by the
+// user on this device, so any remote edit beats it and it is not a conflict.
+//
+// Note: exact ties are broken by device name so that every device settles on the same answer.
+pub fn merge_field(
+    local_value: i64,
+    local_mtime: Option<i64>,
+    remote: &SyncEntry,
+    remote_wins_ties: bool,
+) -> MergeOutcome {
+    if local_value == remote.value {
+        return MergeOutcome::Keep;
+    }
+    match local_mtime {
+        None => MergeOutcome::Adopt,
+        Some(local_mtime) if remote.modified > local_mtime => MergeOutcome::Overwrite,
+        Some(local_mtime) if remote.modified == local_mtime && remote_wins_ties => {
+            MergeOutcome::Overwrite
+        }
+        Some(_) => MergeOutcome::Keep,
+    }
+}
+
+pub fn sync_file_path(folder: &Path, device: &str) -> PathBuf {
+    let safe = device
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    folder.join(format!("{safe}.json"))
+}
+
+pub fn sync_user_metadata(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    folder: &Path,
+    device: &str,
+    log: &mut LogSender,
+    host: &mut HostUpdateSender,
+) -> Result<SyncReport> {
+    fs::create_dir_all(folder)
+        .with_context(|| format!("creating sync folder {}", folder.display()))?;
+    let own_path = sync_file_path(folder, device);
+    let mut report = SyncReport::default();
+
+    // Merge everyone else's edits into our DB first, so that our export reflects the result.
+    // The merge is all or nothing, so the UI is only told about changes once they are committed.
+    let mut changes = Vec::new();
+    let mut exhibitions_changed = false;
+    let tx = conn.transaction()?;
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path == own_path || path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let remote: SyncFile = match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(serde_json::from_str(&s)?))
+        {
+            Ok(remote) => remote,
+            Err(e) => {
+                log.warn(format!(
+                    "Skipping unreadable sync file {}: {e}",
+                    path.display()
+                ));
+                continue;
+            }
+        };
+        log.info(format!(
+            "Merging {} entries from device {}",
+            remote.entries.len(),
+            remote.device
+        ));
+        for item in &remote.entries {
+            if !item.is_valid() {
+                log.warn(format!(
+                    "Skipping invalid {} {} for {} from device {}",
+                    item.field, item.value, item.key, remote.device
+                ));
+                continue;
+            }
+            if let Some(id) = merge_entry(&tx, item, &remote.device, device, &mut report)? {
+                changes.push((id, item.clone()));
+            }
+        }
+        for exhibition in &remote.exhibitions {
+            if merge_exhibition(&tx, exhibition)? {
+                report.applied += 1;
+                exhibitions_changed = true;
+            }
+        }
+        report.devices.push(remote.device);
+    }
+    tx.commit()?;
+    for (id, item) in changes {
+        note_change(host, id, &item)?;
+    }
+    if exhibitions_changed {
+        host.return_exhibitions(list_exhibitions(conn)?)?;
+    }
+
+    // Export our own view, including anything we just adopted.
+    let local = SyncFile {
+        device: device.to_owned(),
+        written: Timestamp::now().as_millisecond(),
+        entries: list_stamped_entries(conn)?,
+        exhibitions: list_sync_exhibitions(conn)?,
+    };
+    let tmp_path = own_path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(&local)?)?;
+    fs::rename(&tmp_path, &own_path)?;
+
+    log.info(format!(
+        "Metadata sync finished: applied {} changes from {} devices with {} conflicts",
+        report.applied,
+        report.devices.len(),
+        report.conflicts.len()
+    ));
+    report.finished_at = Some(Timestamp::now());
+    Ok(report)
+}
+
+// Returns the local row id if the remote value was adopted.
+fn merge_entry(
+    conn: &Connection,
+    item: &SyncEntry,
+    remote_device: &str,
+    local_device: &str,
+    report: &mut SyncReport,
+) -> Result<Option<i64>> {
+    let (table, key_col, col) = (
+        item.table.table(),
+        item.table.key_column(),
+        item.field.column(),
+    );
+    let local: Option<(i64, i64, Option<i64>)> = conn
+        .query_row(
+            &format!("SELECT id, {col}, {col}_mtime FROM {table} WHERE {key_col} = ?"),
+            params![item.key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((id, local_value, local_mtime)) = local else {
+        // We don't have this work or tag (yet); it will merge on a later sync once we do.
+        report.unmatched += 1;
+        return Ok(None);
+    };
+
+    let outcome = merge_field(local_value, local_mtime, item, remote_device > local_device);
+    if outcome == MergeOutcome::Overwrite {
+        report.conflicts.push(SyncConflict {
+            table: item.table,
+            key: item.key.clone(),
+            field: item.field,
+            local_value,
+            remote_value: item.value,
+            remote_device: remote_device.to_owned(),
+        });
+    }
+    if outcome == MergeOutcome::Keep {
+        return Ok(None);
+    }
+    conn.execute(
+        &format!("UPDATE {table} SET {col} = ?, {col}_mtime = ? WHERE id = ?"),
+        params![item.value, item.modified, id],
+    )?;
+    report.applied += 1;
+    Ok(Some(id))
+}
+
+// Returns whether the remote copy replaced ours.
+//
+// Note: a copy adopted from another device keeps its stamp, so equal stamps are the same edit.
+fn merge_exhibition(conn: &Connection, remote: &SyncExhibition) -> Result<bool> {
+    let local: Option<i64> = conn
+        .query_row(
+            "SELECT updated_at FROM exhibitions WHERE name = ?",
+            [&remote.name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if local.is_some_and(|modified| modified >= remote.modified) {
+        return Ok(false);
+    }
+    if remote.deleted {
+        remove_exhibition(conn, &remote.name, remote.modified)?;
+    } else {
+        write_exhibition(conn, &remote.name, &remote.items, remote.modified)?;
+    }
+    Ok(true)
+}
+
+fn note_change(host: &mut HostUpdateSender, id: i64, item: &SyncEntry) -> Result<()> {
+    let flag = item.value != 0;
+    match (item.table, item.field) {
+        (SyncTable::Works, SyncField::Favorite) => {
+            host.note_work_favorite_status_changed(WorkId::wrap(id), flag)?;
+        }
+        (SyncTable::Works, SyncField::Hidden) => {
+            host.note_work_hidden_status_changed(WorkId::wrap(id), flag)?;
+        }
+        (SyncTable::Works, SyncField::Rating) => {
+            host.note_work_rating_changed(WorkId::wrap(id), u8::try_from(item.value)?)?;
+        }
+        (SyncTable::Tags, SyncField::Favorite) => {
+            host.note_tag_favorite_status_changed(TagId::wrap(id), flag)?;
+        }
+        (SyncTable::Tags, SyncField::Hidden) => {
+            host.note_tag_hidden_status_changed(TagId::wrap(id), flag)?;
+        }
+        (SyncTable::Tags, SyncField::Rating) => unreachable!("tags are not rated"),
+    }
+    Ok(())
+}
+
+fn list_stamped_entries(conn: &Connection) -> Result<Vec<SyncEntry>> {
+    let mut out = Vec::new();
+    for table in [SyncTable::Works, SyncTable::Tags] {
+        for &field in table.fields() {
+            let (name, key_col, col) = (table.table(), table.key_column(), field.column());
+            let query = format!(
+                "SELECT {key_col}, {col}, {col}_mtime FROM {name} WHERE {col}_mtime IS NOT NULL"
+            );
+            let mut stmt = conn.prepare(&query)?;
+            let rows = stmt.query_map([], |row| {
+                Ok(SyncEntry {
+                    table,
+                    key: row.get(0)?,
+                    field,
+                    value: row.get(1)?,
+                    modified: row.get(2)?,
+                })
+            })?;
+            for row in rows {
+                out.push(row?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn list_sync_exhibitions(conn: &Connection) -> Result<Vec<SyncExhibition>> {
+    let mut exhibitions = conn
+        .prepare("SELECT id, name, deleted, updated_at FROM exhibitions")?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>("id")?,
+                SyncExhibition {
+                    name: row.get("name")?,
+                    deleted: row.get("deleted")?,
+                    items: Vec::new(),
+                    modified: row.get("updated_at")?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut items_stmt = conn.prepare(
+        r#"SELECT screen_url, title, caption, notes FROM exhibition_items
+            WHERE exhibition_id = ? ORDER BY position"#,
+    )?;
+    for (id, exhibition) in &mut exhibitions {
+        exhibition.items = items_stmt
+            .query_map([*id], ExhibitionItem::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+    }
+    Ok(exhibitions
+        .into_iter()
+        .map(|(_, exhibition)| exhibition)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::model::MIGRATIONS;
+
+    fn remote(value: i64, modified: i64) -> SyncEntry {
+        SyncEntry {
+            table: SyncTable::Works,
+            key: "https://example.com/a.jpg".to_owned(),
+            field: SyncField::Favorite,
+            value,
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_merge_field_same_value_is_kept() {
+        assert_eq!(
+            merge_field(1, Some(5), &remote(1, 10), false),
+            MergeOutcome::Keep
+        );
+        assert_eq!(
+            merge_field(0, None, &remote(0, 10), false),
+            MergeOutcome::Keep
+        );
+    }
+
+    #[test]
+    fn test_merge_field_untouched_local_adopts() {
+        assert_eq!(
+            merge_field(0, None, &remote(1, 0), false),
+            MergeOutcome::Adopt
+        );
+    }
+
+    #[test]
+    fn test_merge_field_newest_wins() {
+        assert_eq!(
+            merge_field(0, Some(5), &remote(1, 10), false),
+            MergeOutcome::Overwrite
+        );
+        assert_eq!(
+            merge_field(0, Some(50), &remote(1, 10), true),
+            MergeOutcome::Keep
+        );
+    }
+
+    #[test]
+    fn test_merge_field_ties_are_stable() {
+        assert_eq!(
+            merge_field(0, Some(10), &remote(1, 10), true),
+            MergeOutcome::Overwrite
+        );
+        assert_eq!(
+            merge_field(0, Some(10), &remote(1, 10), false),
+            MergeOutcome::Keep
+        );
+    }
+
+    #[test]
+    fn test_entry_validation() {
+        let rating = |table, value| SyncEntry {
+            table,
+            field: SyncField::Rating,
+            ..remote(value, 0)
+        };
+        assert!(remote(1, 0).is_valid());
+        assert!(!remote(2, 0).is_valid());
+        assert!(rating(SyncTable::Works, 5).is_valid());
+        assert!(!rating(SyncTable::Works, 6).is_valid());
+        assert!(!rating(SyncTable::Tags, 3).is_valid());
+    }
+
+    #[test]
+    fn test_merge_exhibition_newest_wins() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        for migration in MIGRATIONS {
+            conn.execute(migration, ())?;
+        }
+        let items = vec![ExhibitionItem::section("Nighthawks")];
+        write_exhibition(&conn, "Hopper", &items, 10)?;
+        let copy = |deleted, items: &[ExhibitionItem], modified| SyncExhibition {
+            name: "Hopper".to_owned(),
+            deleted,
+            items: items.to_vec(),
+            modified,
+        };
+
+        assert!(!merge_exhibition(&conn, &copy(false, &[], 5))?);
+        assert!(!merge_exhibition(&conn, &copy(false, &[], 10))?);
+        assert_eq!(list_sync_exhibitions(&conn)?, vec![copy(false, &items, 10)]);
+
+        let newer = [ExhibitionItem::section("Automat"), items[0].clone()];
+        assert!(merge_exhibition(&conn, &copy(false, &newer, 20))?);
+        assert_eq!(list_sync_exhibitions(&conn)?, vec![copy(false, &newer, 20)]);
+
+        assert!(merge_exhibition(&conn, &copy(true, &[], 30))?);
+        assert_eq!(list_sync_exhibitions(&conn)?, vec![copy(true, &[], 30)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_file_path_sanitizes_device() {
+        assert_eq!(
+            sync_file_path(Path::new("/sync"), "my laptop/1"),
+            PathBuf::from("/sync/my_laptop_1.json")
+        );
+    }
+}
@@ -0,0 +1,199 @@
+// Bring the database up to date with MIGRATIONS, after checking that it is one we understand.
+//
+// Migrations are applied by ordinal, so an entry that gets edited or moved after it has shipped
+// would quietly leave databases in different shapes. We record the sha256 of each migration as it
+// runs and refuse to open a database whose history does not match ours. Likewise, a database that
+// has run migrations we have never heard of was upgraded by a newer Artchiver, and we could easily
+// break it by writing to it the old way.
+use crate::{db::model::MIGRATIONS, shared::encryption::unlocked_keys};
+use anyhow::Result;
+use log::info;
+use rusqlite::{Connection, OpenFlags, params};
+use sha2::{Digest as _, Sha256};
+use std::{collections::HashSet, path::Path};
+use thiserror::Error;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error(
+        "This database was last opened by Artchiver {last_opened}, which is newer than this \
+         version ({VERSION}): it has {found} migrations, but we only know about {known}. Please \
+         update Artchiver to open it."
+    )]
+    TooNew {
+        last_opened: String,
+        found: usize,
+        known: usize,
+    },
+    #[error(
+        "Migration {ordinal} in this database does not match the one in this version of \
+         Artchiver ({VERSION}), so the tables may not be in the shape that we expect. This can \
+         happen with development builds; please open it with the version that made it, or \
+         restore a backup."
+    )]
+    ChecksumMismatch { ordinal: usize },
+}
+
+pub fn checksum(migration: &str) -> String {
+    format!("{:x}", Sha256::digest(migration))
+}
+
+struct Applied {
+    ordinal: usize,
+    // None for migrations run before we started recording checksums.
+    checksum: Option<String>,
+}
+
+fn list_applied(conn: &Connection) -> Vec<Applied> {
+    // Note: the checksum column is itself added by a migration, and there is no migrations
+    //       table at all in a new database.
+    let query = |sql: &str| -> rusqlite::Result<Vec<Applied>> {
+        conn.prepare(sql)?
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .map(|row| {
+                let (ordinal, checksum) = row?;
+                Ok(usize::try_from(ordinal)
+                    .ok()
+                    .map(|ordinal| Applied { ordinal, checksum }))
+            })
+            .filter_map(Result::transpose)
+            .collect()
+    };
+    query("SELECT ordinal, checksum FROM migrations")
+        .or_else(|_| query("SELECT ordinal, NULL FROM migrations"))
+        .unwrap_or_default()
+}
+
+fn database_info(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM database_info WHERE key = ?",
+        [key],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+// Make sure that we can safely run our migrations over the database.
+pub fn check_migrations(conn: &Connection) -> Result<(), MigrationError> {
+    let applied = list_applied(conn);
+    let found = applied.iter().map(|m| m.ordinal + 1).max().unwrap_or(0);
+    if found > MIGRATIONS.len() {
+        return Err(MigrationError::TooNew {
+            last_opened: database_info(conn, "last_opened_by")
+                .unwrap_or_else(|| "(unknown)".to_owned()),
+            found,
+            known: MIGRATIONS.len(),
+        });
+    }
+    for m in &applied {
+        if m.checksum
+            .as_ref()
+            .is_some_and(|sum| *sum != checksum(MIGRATIONS[m.ordinal]))
+        {
+            return Err(MigrationError::ChecksumMismatch { ordinal: m.ordinal });
+        }
+    }
+    Ok(())
+}
+
+// The same checks, for before we start the app proper, so that we can explain what is wrong
+// rather than fail somewhere in the middle of startup. A database that doesn't exist yet, or that
+// we can't read here, is left for the usual startup to create or report.
+pub fn check_database_file(path: &Path) -> Result<(), MigrationError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let Ok(conn) = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
+        return Ok(());
+    };
+    if let Some(keys) = unlocked_keys()
+        && keys.key_connection(&conn).is_err()
+    {
+        return Ok(());
+    }
+    check_migrations(&conn)
+}
+
+// Migrations that have yet to run; a read-only instance can't run them, so can't use the DB.
+pub fn count_pending_migrations(conn: &Connection) -> usize {
+    MIGRATIONS.len().saturating_sub(list_applied(conn).len())
+}
+
+pub fn run_migrations(conn: &Connection) -> Result<()> {
+    check_migrations(conn)?;
+    let applied = list_applied(conn);
+    let fresh = applied.is_empty();
+    let mut missing_checksums = applied.iter().any(|m| m.checksum.is_none());
+    let done = applied.iter().map(|m| m.ordinal).collect::<HashSet<_>>();
+
+    for (ordinal, migration) in MIGRATIONS.iter().enumerate() {
+        if !done.contains(&ordinal) {
+            info!("Running migration {ordinal}");
+            conn.execute(migration, ())?;
+            conn.execute("INSERT INTO migrations (ordinal) VALUES (?)", [ordinal])?;
+            missing_checksums = true;
+        }
+    }
+
+    // Note: the migrations that just ran, and any from before we kept checksums, are taken to
+    //       be the ones we have now.
+    if missing_checksums {
+        for (ordinal, migration) in MIGRATIONS.iter().enumerate() {
+            conn.execute(
+                "UPDATE migrations SET checksum = ? WHERE ordinal = ? AND checksum IS NULL",
+                params![checksum(migration), ordinal],
+            )?;
+        }
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO database_info (key, value) VALUES ('created_by', ?)",
+        [if fresh { VERSION } else { "(unknown)" }],
+    )?;
+    conn.execute(
+        "INSERT OR REPLACE INTO database_info (key, value) VALUES ('last_opened_by', ?)",
+        [VERSION],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_verify() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        run_migrations(&conn)?;
+        assert_eq!(database_info(&conn, "created_by").as_deref(), Some(VERSION));
+        // Opening again is a no-op.
+        run_migrations(&conn)?;
+
+        conn.execute(
+            "UPDATE migrations SET checksum = 'edited' WHERE ordinal = 3",
+            [],
+        )?;
+        assert!(matches!(
+            check_migrations(&conn),
+            Err(MigrationError::ChecksumMismatch { ordinal: 3 })
+        ));
+        conn.execute(
+            "UPDATE migrations SET checksum = ? WHERE ordinal = 3",
+            [checksum(MIGRATIONS[3])],
+        )?;
+
+        conn.execute(
+            "INSERT INTO migrations (ordinal) VALUES (?)",
+            [MIGRATIONS.len()],
+        )?;
+        assert!(matches!(
+            check_migrations(&conn),
+            Err(MigrationError::TooNew { found, .. }) if found == MIGRATIONS.len() + 1
+        ));
+        Ok(())
+    }
+}
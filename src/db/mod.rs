@@ -1,5 +1,17 @@
+pub mod enrichment;
+pub mod hydrus_import;
+pub mod local_import;
+pub mod maintenance;
+pub mod metadata_sync;
+pub mod migrate;
 pub mod model;
 pub mod models;
+pub mod purge;
 pub mod reader;
+pub mod relocate;
+pub mod scrub;
 pub mod sync;
+pub mod tiering;
+pub mod trash;
+pub mod vocabulary;
 pub mod writer;
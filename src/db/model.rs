@@ -1,3 +1,5 @@
+use crate::shared::metrics;
+use artchiver_sdk::TagKind;
 use log::{debug, warn};
 use parking_lot::Mutex;
 use rusqlite::types::Value;
@@ -9,7 +11,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-pub const MIGRATIONS: [&str; 41] = [
+pub const MIGRATIONS: [&str; 128] = [
     // Migrations
     r#"CREATE TABLE migrations (
         id INTEGER PRIMARY KEY,
@@ -128,6 +130,382 @@ pub const MIGRATIONS: [&str; 41] = [
         FOREIGN KEY(work_id) REFERENCES works(id),
         UNIQUE (work_id, name)
     );"#,
+    // Sync: track when the user last touched each syncable flag (unix ms), so that we can merge
+    //       with other devices last-writer-wins. Existing user flags get a zero stamp so that they
+    //       are exported, but lose to any real edit made elsewhere.
+    r#"ALTER TABLE works ADD COLUMN favorite_mtime INTEGER;"#,
+    r#"ALTER TABLE works ADD COLUMN hidden_mtime INTEGER;"#,
+    r#"ALTER TABLE tags ADD COLUMN favorite_mtime INTEGER;"#,
+    r#"ALTER TABLE tags ADD COLUMN hidden_mtime INTEGER;"#,
+    r#"UPDATE works SET
+        favorite_mtime = CASE WHEN favorite THEN 0 END,
+        hidden_mtime = CASE WHEN hidden THEN 0 END;"#,
+    r#"UPDATE tags SET
+        favorite_mtime = CASE WHEN favorite THEN 0 END,
+        hidden_mtime = CASE WHEN hidden THEN 0 END;"#,
+    // Sync: a 0-5 star rating on works; 0 is unrated.
+    r#"ALTER TABLE works ADD COLUMN rating INTEGER NOT NULL DEFAULT 0;"#,
+    r#"ALTER TABLE works ADD COLUMN rating_mtime INTEGER;"#,
+    // Plugin Logs: kept across runs so that failures can be diagnosed after the fact.
+    r#"CREATE TABLE plugin_logs (
+        id INTEGER PRIMARY KEY,
+        plugin_id INTEGER NOT NULL,
+        level TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        job TEXT,
+        message TEXT NOT NULL,
+        FOREIGN KEY(plugin_id) REFERENCES plugins(id)
+    );"#,
+    r#"CREATE INDEX plugin_logs_plugin_idx ON plugin_logs(plugin_id, id);"#,
+    // Cold Storage: when each work was last downloaded or viewed (unix ms), and which of its
+    //               files have been off-loaded. Existing works count as accessed now, so that
+    //               nothing is off-loaded before we have seen how the library is used. Files
+    //               are keyed by screen_url, since work ids change on refresh.
+    r#"ALTER TABLE works ADD COLUMN last_accessed INTEGER;"#,
+    r#"UPDATE works SET last_accessed = strftime('%s', 'now') * 1000;"#,
+    r#"CREATE TABLE cold_files (
+        screen_url TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        cold_path TEXT NOT NULL,
+        offloaded_at INTEGER NOT NULL,
+        PRIMARY KEY (screen_url, kind)
+    );"#,
+    r#"CREATE INDEX cold_files_cold_path_idx ON cold_files(cold_path);"#,
+    // Integrity: the hash of each downloaded file, keyed by its root-relative path.
+    r#"CREATE TABLE file_hashes (
+        path TEXT PRIMARY KEY NOT NULL,
+        sha256 TEXT NOT NULL,
+        checked_at INTEGER NOT NULL,
+        corrupt BOOLEAN NOT NULL DEFAULT false
+    );"#,
+    // Sources: the raw record that each work was mapped from, zstd-compressed, if the user has
+    //          asked us to keep them. Keyed by screen_url, since work ids change on refresh.
+    r#"CREATE TABLE work_sources (
+        screen_url TEXT PRIMARY KEY NOT NULL,
+        plugin_id INTEGER NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        source BLOB NOT NULL,
+        FOREIGN KEY(plugin_id) REFERENCES plugins(id)
+    );"#,
+    r#"CREATE INDEX work_sources_plugin_idx ON work_sources(plugin_id);"#,
+    // Revisions: the fields a plugin changed each time it updated a work, as a JSON list of
+    //            WorkChange. Keyed by screen_url for the same reason as work_sources.
+    r#"CREATE TABLE work_revisions (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        changed_at INTEGER NOT NULL,
+        changes TEXT NOT NULL
+    );"#,
+    r#"CREATE INDEX work_revisions_screen_url_idx ON work_revisions(screen_url, changed_at);"#,
+    // Inbox: works that arrived since the user last reviewed them, with the tag they came in
+    //        under. Rows are deleted as the user reviews them.
+    r#"CREATE TABLE work_inbox (
+        screen_url TEXT PRIMARY KEY NOT NULL,
+        plugin_id INTEGER NOT NULL,
+        for_tag TEXT NOT NULL,
+        added_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE INDEX work_inbox_added_at_idx ON work_inbox(added_at);"#,
+    // Display: the user's rotation, crop, etc. for a work, as a JSON DisplayTransform.
+    r#"CREATE TABLE work_display (
+        screen_url TEXT PRIMARY KEY NOT NULL,
+        transform TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );"#,
+    // Thumbnails: a small copy of each screen image, made on download, for the gallery to load
+    //             in place of the full size file. Stored relative to the data dir.
+    r#"ALTER TABLE works ADD COLUMN thumb_path TEXT;"#,
+    r#"ALTER TABLE works ADD COLUMN aspect_ratio REAL;"#,
+    r#"ALTER TABLE works ADD COLUMN media_type TEXT;"#,
+    // Tag Kinds: the TagKind for each type of tag a plugin reports, e.g. NGA term types. We
+    //            record the plugin's own guess until the user edits it.
+    r#"CREATE TABLE tag_kind_mappings (
+        id INTEGER PRIMARY KEY,
+        plugin_id INTEGER NOT NULL,
+        source_type TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        edited BOOLEAN NOT NULL DEFAULT false,
+        FOREIGN KEY(plugin_id) REFERENCES plugins(id),
+        UNIQUE (plugin_id, source_type)
+    );"#,
+    // Migrations: the sha256 of each migration as it ran, to catch edits to past entries. Rows
+    //             from before this are filled in with the current text on the next open.
+    r#"ALTER TABLE migrations ADD COLUMN checksum TEXT;"#,
+    // Database Info: which versions of Artchiver created and last opened the database.
+    r#"CREATE TABLE database_info (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );"#,
+    // Work Sorting: when we first saw each work, and how big its screen file is. Works from
+    //               before this count as added now, and get a size as they are re-downloaded.
+    r#"ALTER TABLE works ADD COLUMN created_at INTEGER;"#,
+    r#"UPDATE works SET created_at = strftime('%s', 'now') * 1000;"#,
+    r#"ALTER TABLE works ADD COLUMN file_size INTEGER;"#,
+    r#"CREATE INDEX work_created_at_idx ON works(created_at);"#,
+    r#"CREATE INDEX work_file_size_idx ON works(file_size);"#,
+    r#"CREATE INDEX work_attribution_sort_idx ON works(history_attribution_sort_key, history_attribution);"#,
+    // Collections: groupings of works that a plugin reports, like portfolios and series. Each
+    //              is shown through a tag of its own, which we attach to the works whose
+    //              remote ids are members, as those works show up.
+    r#"ALTER TABLE works ADD COLUMN remote_id TEXT;"#,
+    r#"CREATE INDEX work_remote_id_idx ON works(remote_id);"#,
+    r#"CREATE TABLE collections (
+        id INTEGER PRIMARY KEY,
+        tag_id INTEGER NOT NULL UNIQUE,
+        plugin_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        FOREIGN KEY(tag_id) REFERENCES tags(id),
+        FOREIGN KEY(plugin_id) REFERENCES plugins(id)
+    );"#,
+    r#"CREATE TABLE collection_members (
+        id INTEGER PRIMARY KEY,
+        collection_id INTEGER NOT NULL,
+        remote_id TEXT NOT NULL,
+        FOREIGN KEY(collection_id) REFERENCES collections(id),
+        UNIQUE (collection_id, remote_id)
+    );"#,
+    // Exhibitions: the user's ordered sequences of works, with captions, presenter notes, and
+    //              section headings between them. Works are by screen url, like the other
+    //              user data, so that they survive a re-import. Deleted exhibitions stay, empty,
+    //              so that metadata sync can pass the delete on.
+    r#"CREATE TABLE exhibitions (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        deleted BOOLEAN NOT NULL DEFAULT false,
+        updated_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE TABLE exhibition_items (
+        id INTEGER PRIMARY KEY,
+        exhibition_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        screen_url TEXT,
+        title TEXT,
+        caption TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        FOREIGN KEY(exhibition_id) REFERENCES exhibitions(id),
+        UNIQUE (exhibition_id, position)
+    );"#,
+    // Variants: edits the user made to a work in another program and saved next to its file.
+    //           These are the user's own, so the scrubber leaves them alone.
+    r#"CREATE TABLE work_variants (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        path TEXT NOT NULL UNIQUE,
+        added_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE INDEX work_variants_screen_url_idx ON work_variants(screen_url);"#,
+    // Notes: the user's own research notes on a work, as markdown, with a full-text index that
+    //        the triggers keep in step with the table.
+    r#"CREATE TABLE work_notes (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL UNIQUE,
+        body TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE VIRTUAL TABLE work_notes_fts USING fts5(
+        body, content='work_notes', content_rowid='id'
+    );"#,
+    r#"CREATE TRIGGER work_notes_ai AFTER INSERT ON work_notes BEGIN
+        INSERT INTO work_notes_fts (rowid, body) VALUES (new.id, new.body);
+    END;"#,
+    r#"CREATE TRIGGER work_notes_ad AFTER DELETE ON work_notes BEGIN
+        INSERT INTO work_notes_fts (work_notes_fts, rowid, body) VALUES ('delete', old.id, old.body);
+    END;"#,
+    r#"CREATE TRIGGER work_notes_au AFTER UPDATE ON work_notes BEGIN
+        INSERT INTO work_notes_fts (work_notes_fts, rowid, body) VALUES ('delete', old.id, old.body);
+        INSERT INTO work_notes_fts (rowid, body) VALUES (new.id, new.body);
+    END;"#,
+    // Annotations: labeled points and rectangles that the user draws on a work, in fractions of
+    //              the untransformed image; a point has no width or height.
+    r#"CREATE TABLE annotations (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        label TEXT NOT NULL,
+        x REAL NOT NULL,
+        y REAL NOT NULL,
+        width REAL NOT NULL DEFAULT 0,
+        height REAL NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE INDEX annotations_screen_url_idx ON annotations(screen_url);"#,
+    // Detection: the faces and figures that the user's models found in each work, in fractions
+    //            of the image, and the tags we suggest from them. A scan row means the work has
+    //            been looked at, even if nothing was found. Suggestions are pending until the
+    //            user accepts or rejects them; rejected ones stay, so we don't suggest them again.
+    r#"CREATE TABLE detection_scans (
+        screen_url TEXT PRIMARY KEY NOT NULL,
+        scanned_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE TABLE detections (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        x REAL NOT NULL,
+        y REAL NOT NULL,
+        width REAL NOT NULL,
+        height REAL NOT NULL,
+        confidence REAL NOT NULL
+    );"#,
+    r#"CREATE INDEX detections_screen_url_idx ON detections(screen_url);"#,
+    r#"CREATE TABLE tag_suggestions (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        UNIQUE (screen_url, tag)
+    );"#,
+    r#"CREATE INDEX tag_suggestions_status_idx ON tag_suggestions(status);"#,
+    // NSFW: the user's classifier's confidence that the work is NSFW, from 0 to 1; null until
+    //       the classifier has looked at its thumbnail.
+    r#"ALTER TABLE works ADD COLUMN nsfw_score REAL;"#,
+    // Tag Edits: the tags that the user has added to or removed from works, newest edit per tag,
+    //            for pushing back to the works' sources; pushed_at is null until then.
+    r#"CREATE TABLE tag_edits (
+        screen_url TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        added INTEGER NOT NULL,
+        edited_at INTEGER NOT NULL,
+        pushed_at INTEGER,
+        UNIQUE (screen_url, tag)
+    );"#,
+    r#"CREATE INDEX tag_edits_pushed_at_idx ON tag_edits(pushed_at);"#,
+    // Mirrors: the other urls that a plugin gave for a work's files, by role, in the order to
+    //          try them if the work's own url for the role is gone.
+    r#"CREATE TABLE work_mirrors (
+        screen_url TEXT NOT NULL,
+        role TEXT NOT NULL,
+        url TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        UNIQUE (screen_url, role, url)
+    );"#,
+    // Fetched From: where we got a work's file from, by role, when it was not the work's own url;
+    //               e.g. a mirror, or the Wayback Machine.
+    r#"CREATE TABLE work_fetched_from (
+        screen_url TEXT NOT NULL,
+        role TEXT NOT NULL,
+        url TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        PRIMARY KEY (screen_url, role)
+    );"#,
+    // Link Checks: when we last checked the screen url of a work that we have not downloaded, and
+    //              what we found; see LinkStatus. http_status is null if the server didn't answer.
+    r#"CREATE TABLE link_checks (
+        screen_url TEXT PRIMARY KEY NOT NULL,
+        checked_at INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        http_status INTEGER
+    );"#,
+    // Models: a glTF model of the work, e.g. a sculpture scan, where the plugin has one; the
+    //         preview and screen files stay images.
+    r#"ALTER TABLE works ADD COLUMN model_url TEXT;"#,
+    r#"ALTER TABLE works ADD COLUMN model_path TEXT;"#,
+    // Transcripts: the cues of a work's transcript or subtitles, and where they came from, with a
+    //              full-text index of the cues, so that a search lands on the moment it matches.
+    r#"ALTER TABLE works ADD COLUMN transcript_url TEXT;"#,
+    r#"CREATE TABLE work_transcripts (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL UNIQUE,
+        source TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE TABLE transcript_cues (
+        id INTEGER PRIMARY KEY,
+        transcript_id INTEGER NOT NULL REFERENCES work_transcripts(id) ON DELETE CASCADE,
+        start_secs REAL NOT NULL,
+        end_secs REAL NOT NULL,
+        body TEXT NOT NULL
+    );"#,
+    r#"CREATE INDEX transcript_cues_transcript_id_idx ON transcript_cues(transcript_id);"#,
+    r#"CREATE VIRTUAL TABLE transcript_cues_fts USING fts5(
+        body, content='transcript_cues', content_rowid='id'
+    );"#,
+    r#"CREATE TRIGGER transcript_cues_ai AFTER INSERT ON transcript_cues BEGIN
+        INSERT INTO transcript_cues_fts (rowid, body) VALUES (new.id, new.body);
+    END;"#,
+    r#"CREATE TRIGGER transcript_cues_ad AFTER DELETE ON transcript_cues BEGIN
+        INSERT INTO transcript_cues_fts (transcript_cues_fts, rowid, body) VALUES ('delete', old.id, old.body);
+    END;"#,
+    // Trash: files that were removed from works, held for a while in case the user wants them
+    //        back. One row per path column of each work, so works that shared a file each get
+    //        theirs back. Keyed by screen_url, since work ids change on refresh.
+    r#"CREATE TABLE trashed_files (
+        id INTEGER PRIMARY KEY,
+        screen_url TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        original_path TEXT NOT NULL,
+        trash_path TEXT NOT NULL,
+        file_size INTEGER,
+        reason TEXT NOT NULL,
+        trashed_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE INDEX trashed_files_screen_url_idx ON trashed_files(screen_url);"#,
+    // External ids: what other catalogs, e.g. Wikidata or the Getty vocabularies, call a work.
+    //               Keyed by screen_url, like mirrors, as the plugin replaces them wholesale.
+    r#"CREATE TABLE work_external_ids (
+        screen_url TEXT NOT NULL,
+        plugin_id INTEGER NOT NULL REFERENCES plugins(id),
+        authority TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (screen_url, authority, value)
+    );"#,
+    r#"CREATE INDEX work_external_ids_value_idx ON work_external_ids(authority, value);"#,
+    // Enrichment: what Wikidata says about works with a Wikidata id. Scans record which works we
+    //             have asked about, so that we only ask once; tag statements are applied to
+    //             work_tags by label, and can be taken off again all at once.
+    r#"CREATE TABLE enrichment_scans (
+        screen_url TEXT PRIMARY KEY,
+        scanned_at INTEGER NOT NULL
+    );"#,
+    r#"CREATE TABLE enrichments (
+        screen_url TEXT NOT NULL,
+        statement TEXT NOT NULL,
+        value TEXT NOT NULL,
+        label TEXT NOT NULL,
+        PRIMARY KEY (screen_url, statement, value)
+    );"#,
+    r#"CREATE INDEX enrichments_value_idx ON enrichments(statement, value);"#,
+    r#"ALTER TABLE artists ADD COLUMN wikidata_id TEXT;"#,
+    r#"ALTER TABLE artists ADD COLUMN enriched BOOLEAN NOT NULL DEFAULT false;"#,
+    r#"CREATE UNIQUE INDEX artists_wikidata_id_idx ON artists(wikidata_id);"#,
+    // Vocabularies: the Getty AAT and ULAN terms that plugins say their tags stand for, with the
+    //               names and broader terms that Getty gives them. Tag aliases and implications
+    //               are derived from these, marked with where they came from.
+    r#"CREATE TABLE tag_external_ids (
+        tag_id INTEGER NOT NULL REFERENCES tags(id),
+        authority TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (tag_id, authority, value)
+    );"#,
+    r#"CREATE INDEX tag_external_ids_value_idx ON tag_external_ids(authority, value);"#,
+    r#"CREATE TABLE vocabulary_terms (
+        authority TEXT NOT NULL,
+        value TEXT NOT NULL,
+        label TEXT NOT NULL,
+        alt_labels TEXT NOT NULL,
+        broader TEXT,
+        fetched_at INTEGER NOT NULL,
+        PRIMARY KEY (authority, value)
+    );"#,
+    r#"CREATE TABLE tag_aliases (
+        alias TEXT NOT NULL,
+        tag_id INTEGER NOT NULL REFERENCES tags(id),
+        source TEXT NOT NULL,
+        PRIMARY KEY (alias, tag_id)
+    );"#,
+    r#"CREATE TABLE tag_implications (
+        tag_id INTEGER NOT NULL REFERENCES tags(id),
+        implied_tag_id INTEGER NOT NULL REFERENCES tags(id),
+        source TEXT NOT NULL,
+        PRIMARY KEY (tag_id, implied_tag_id)
+    );"#,
+    r#"CREATE INDEX tag_aliases_tag_idx ON tag_aliases(tag_id);"#,
+    // Watched Tags: tags the user wants to hear about new works for, and when they last looked at
+    //               the tag's works, to count the works that are new since.
+    r#"ALTER TABLE tags ADD COLUMN watched BOOLEAN NOT NULL DEFAULT false;"#,
+    r#"ALTER TABLE tags ADD COLUMN last_seen INTEGER;"#,
 ];
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -165,6 +543,78 @@ impl OrderDir {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TagSortCol {
+    #[default]
+    Name,
+    LocalCount,
+    NetworkCount,
+}
+
+impl TagSortCol {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut selected = match self {
+            Self::Name => 0,
+            Self::LocalCount => 1,
+            Self::NetworkCount => 2,
+        };
+        let labels = ["Name", "Works Downloaded", "Total Works"];
+        egui::ComboBox::new("tag_order_column", "Column")
+            .wrap_mode(egui::TextWrapMode::Truncate)
+            .show_index(ui, &mut selected, labels.len(), |i| labels[i]);
+        *self = match selected {
+            0 => Self::Name,
+            1 => Self::LocalCount,
+            2 => Self::NetworkCount,
+            _ => panic!("invalid column selected"),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TagOrder {
+    column: TagSortCol,
+    order: OrderDir,
+}
+
+impl TagOrder {
+    pub fn new(column: TagSortCol, order: OrderDir) -> Self {
+        Self { column, order }
+    }
+
+    pub fn column(&self) -> TagSortCol {
+        self.column
+    }
+
+    pub fn order(&self) -> OrderDir {
+        self.order
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        self.column.ui(ui);
+        self.order.ui("tags", ui);
+    }
+}
+
+// Which plugin's tags to list, if any.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum TagSource {
+    #[default]
+    All,
+    Hidden,
+    Plugin(String),
+}
+
+// A filtered and sorted view of the tags, for listing a window of at a time.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagListQuery {
+    // A case-insensitive substring of the tag name.
+    pub name: String,
+    pub source: TagSource,
+    pub kind: Option<TagKind>,
+    pub order: TagOrder,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DbCancellation {
     cancelled: Arc<Mutex<bool>>,
@@ -190,6 +640,7 @@ pub fn string_to_rarray(v: &[String]) -> Rc<Vec<Value>> {
 
 pub fn report_slow_query(start: Instant, name: &str, query: &str) {
     let elapsed = start.elapsed();
+    metrics::time(metrics::DB_QUERY_SECONDS, name, elapsed);
     if elapsed > Duration::from_millis(30) {
         warn!("Slow query {name} took {elapsed:?}");
         debug!(
@@ -0,0 +1,43 @@
+use rusqlite::{
+    Row, ToSql,
+    types::{ToSqlOutput, Value},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct AnnotationId(i64);
+impl ToSql for AnnotationId {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Integer(self.0)))
+    }
+}
+
+// A labeled spot on a work, e.g. "signature here". The region is in fractions of the whole
+// image, before any display transform, with 0,0 at the top left, so that it stays on the same
+// spot when the user crops or turns the work; a point has no width or height.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DbAnnotation {
+    pub id: AnnotationId,
+    pub label: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DbAnnotation {
+    pub fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: AnnotationId(row.get("id")?),
+            label: row.get("label")?,
+            x: row.get("x")?,
+            y: row.get("y")?,
+            width: row.get("width")?,
+            height: row.get("height")?,
+        })
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.width <= 0. && self.height <= 0.
+    }
+}
@@ -0,0 +1,55 @@
+use crate::db::models::tag::TagId;
+use artchiver_sdk::{Group, GroupKind};
+use rusqlite::Row;
+
+// A plugin reported grouping of works, e.g. a portfolio, as shown in the Collections panel.
+#[derive(Clone, Debug)]
+pub struct DbCollection {
+    tag_id: TagId,
+    name: String,
+    kind: GroupKind,
+    plugin: String,
+    work_count: u64,
+}
+
+impl DbCollection {
+    pub fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            tag_id: TagId::wrap(row.get("tag_id")?),
+            name: row.get("name")?,
+            kind: row
+                .get::<&str, String>("kind")?
+                .parse()
+                .ok()
+                .unwrap_or_default(),
+            plugin: row.get("plugin")?,
+            work_count: row.get("work_count")?,
+        })
+    }
+
+    // The tag that stands in for the group. Named for its kind as well, so that e.g. a series
+    // called "Venice" doesn't end up merged with the location tag of the same name.
+    pub fn tag_name_for(group: &Group) -> String {
+        format!("{} ({})", group.name(), group.kind())
+    }
+
+    pub fn tag_id(&self) -> TagId {
+        self.tag_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> GroupKind {
+        self.kind
+    }
+
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    pub fn work_count(&self) -> u64 {
+        self.work_count
+    }
+}
@@ -0,0 +1,34 @@
+use crate::db::models::work::DbWork;
+use rusqlite::{
+    ToSql,
+    types::{ToSqlOutput, Value},
+};
+
+// A downloaded work that the detector has not looked at yet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingScan {
+    pub screen_url: String,
+    pub screen_path: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct SuggestionId(i64);
+impl SuggestionId {
+    pub fn wrap(id: i64) -> Self {
+        Self(id)
+    }
+}
+impl ToSql for SuggestionId {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Integer(self.0)))
+    }
+}
+
+// A tag that detection suggests for a work, waiting for the user to accept or reject it.
+#[derive(Clone, Debug)]
+pub struct DbTagSuggestion {
+    pub id: SuggestionId,
+    pub tag: String,
+    pub reason: String,
+    pub work: DbWork,
+}
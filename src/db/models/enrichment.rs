@@ -0,0 +1,6 @@
+// A work with a Wikidata id that we have not asked Wikidata about yet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingEnrichment {
+    pub screen_url: String,
+    pub wikidata_id: String,
+}
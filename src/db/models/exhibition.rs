@@ -0,0 +1,107 @@
+use crate::db::models::work::DbWork;
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// One step through an exhibition: a work with what to say about it, or a heading that starts a
+// new section.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExhibitionItem {
+    Section {
+        title: String,
+        caption: String,
+        notes: String,
+    },
+    Work {
+        screen_url: String,
+        caption: String,
+        notes: String,
+    },
+}
+
+impl ExhibitionItem {
+    pub fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let caption = row.get("caption")?;
+        let notes = row.get("notes")?;
+        Ok(match row.get::<_, Option<String>>("screen_url")? {
+            Some(screen_url) => Self::Work {
+                screen_url,
+                caption,
+                notes,
+            },
+            None => Self::Section {
+                title: row.get::<_, Option<String>>("title")?.unwrap_or_default(),
+                caption,
+                notes,
+            },
+        })
+    }
+
+    pub fn section(title: &str) -> Self {
+        Self::Section {
+            title: title.to_owned(),
+            caption: String::new(),
+            notes: String::new(),
+        }
+    }
+
+    pub fn work(work: &DbWork) -> Self {
+        Self::Work {
+            screen_url: work.screen_url().to_owned(),
+            caption: String::new(),
+            notes: String::new(),
+        }
+    }
+
+    pub fn screen_url(&self) -> Option<&str> {
+        match self {
+            Self::Section { .. } => None,
+            Self::Work { screen_url, .. } => Some(screen_url),
+        }
+    }
+
+    pub fn caption(&self) -> &str {
+        match self {
+            Self::Section { caption, .. } | Self::Work { caption, .. } => caption,
+        }
+    }
+
+    pub fn caption_mut(&mut self) -> &mut String {
+        match self {
+            Self::Section { caption, .. } | Self::Work { caption, .. } => caption,
+        }
+    }
+
+    pub fn notes(&self) -> &str {
+        match self {
+            Self::Section { notes, .. } | Self::Work { notes, .. } => notes,
+        }
+    }
+
+    pub fn notes_mut(&mut self) -> &mut String {
+        match self {
+            Self::Section { notes, .. } | Self::Work { notes, .. } => notes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DbExhibition {
+    pub name: String,
+    pub items: Vec<ExhibitionItem>,
+    // The works that the items show, by screen url, as of when the exhibition was loaded.
+    pub works: HashMap<String, DbWork>,
+}
+
+impl DbExhibition {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn work_for(&self, item: &ExhibitionItem) -> Option<&DbWork> {
+        item.screen_url().and_then(|url| self.works.get(url))
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::{Result, bail};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// What we found when we last checked the url for a work that we have not downloaded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LinkStatus {
+    Ok,
+    // The url was dead, but the plugin found the work again; the new urls are mirrors of it.
+    Refreshed,
+    // The url was dead and the plugin could not find the work again.
+    Lost,
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txt = match self {
+            Self::Ok => "ok",
+            Self::Refreshed => "refreshed",
+            Self::Lost => "lost",
+        };
+        write!(f, "{txt}")
+    }
+}
+
+impl TryFrom<&str> for LinkStatus {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "ok" => Self::Ok,
+            "refreshed" => Self::Refreshed,
+            "lost" => Self::Lost,
+            _ => bail!("not a known LinkStatus name: {value}"),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkCheck {
+    pub screen_url: String,
+    pub status: LinkStatus,
+    // The status the server answered the url with, if it answered.
+    pub http_status: Option<u16>,
+}
+
+// A work whose files are gone from its source, for the Health panel.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LostLink {
+    pub screen_url: String,
+    pub work_name: String,
+    pub plugin: Option<String>,
+    pub http_status: Option<u16>,
+    pub checked_at: Timestamp,
+}
@@ -0,0 +1,61 @@
+use crate::db::models::plugin::PluginId;
+use jiff::Timestamp;
+use log::Level;
+use rusqlite::Row;
+
+// A single persisted plugin log line.
+#[derive(Clone, Debug)]
+pub struct DbLogLine {
+    plugin_id: PluginId,
+    level: Level,
+    timestamp: Timestamp,
+    job: Option<String>,
+    message: String,
+}
+
+impl DbLogLine {
+    pub fn new(plugin_id: PluginId, level: Level, job: Option<String>, message: String) -> Self {
+        Self {
+            plugin_id,
+            level,
+            timestamp: Timestamp::now(),
+            job,
+            message,
+        }
+    }
+
+    pub fn from_row(plugin_id: PluginId, row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            plugin_id,
+            // Note: an unknown level means the row was written by something newer than us;
+            //       show it rather than dropping it.
+            level: row
+                .get::<&str, String>("level")?
+                .parse()
+                .unwrap_or(Level::Info),
+            timestamp: Timestamp::from_millisecond(row.get("timestamp")?).unwrap_or_default(),
+            job: row.get("job")?,
+            message: row.get("message")?,
+        })
+    }
+
+    pub fn plugin_id(&self) -> PluginId {
+        self.plugin_id
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    pub fn job(&self) -> Option<&str> {
+        self.job.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
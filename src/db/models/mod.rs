@@ -1,3 +1,14 @@
+pub mod annotation;
+pub mod collection;
+pub mod detection;
+pub mod enrichment;
+pub mod exhibition;
+pub mod link_check;
+pub mod log;
+pub mod note;
 pub mod plugin;
 pub mod tag;
+pub mod tag_edit;
+pub mod transcript;
+pub mod trash;
 pub mod work;
@@ -0,0 +1,41 @@
+use jiff::Timestamp;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DbWorkNote {
+    pub body: String,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+// A note that matched a search, with the matching words marked in the snippet.
+#[derive(Clone, Debug)]
+pub struct NoteHit {
+    pub screen_url: String,
+    // None if the work has gone away since the note was written.
+    pub work_name: Option<String>,
+    pub snippet: String,
+    pub updated_at: Timestamp,
+}
+
+// Turn what the user typed into an FTS5 query: every word must appear, as a prefix, and nothing
+// they type is taken as query syntax.
+pub fn fts_query(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fts_query;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("plate 4"), r#""plate"* "4"*"#);
+        assert_eq!(fts_query("  raisonné  "), r#""raisonné"*"#);
+        assert_eq!(fts_query(r#"a"b OR c"#), r#""a""b"* "OR"* "c"*"#);
+        assert_eq!(fts_query(""), "");
+    }
+}
@@ -4,7 +4,7 @@ use rusqlite::{
     types::{ToSqlOutput, Value},
 };
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{collections::HashMap, fmt};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct TagId(i64);
@@ -32,8 +32,13 @@ pub struct DbTag {
     kind: TagKind,
     network_count: u64,
     local_count: Option<u64>,
+    // Of the local works, those with their screen file on disk.
+    downloaded_count: Option<u64>,
     hidden: bool,
     favorite: bool,
+    watched: bool,
+    // Of the local works, those that arrived since the user last looked at a watched tag.
+    new_count: u64,
     wiki_url: Option<String>,
     remote_id: Option<String>,
     sources: Vec<String>,
@@ -51,8 +56,11 @@ impl DbTag {
                 .unwrap_or_default(),
             network_count: row.get("network_count")?,
             local_count: None,
+            downloaded_count: None,
             hidden: row.get("hidden")?,
             favorite: row.get("favorite")?,
+            watched: row.get("watched")?,
+            new_count: 0,
             wiki_url: row.get("wiki_url")?,
             remote_id: row.get("remote_id")?,
             sources: row
@@ -63,8 +71,9 @@ impl DbTag {
         })
     }
 
-    pub fn set_local_count(&mut self, actual_work_count: u64) {
+    pub fn set_local_count(&mut self, actual_work_count: u64, downloaded_count: u64) {
         self.local_count = Some(actual_work_count);
+        self.downloaded_count = Some(downloaded_count);
     }
 
     pub fn id(&self) -> TagId {
@@ -87,6 +96,17 @@ impl DbTag {
         self.local_count
     }
 
+    pub fn downloaded_count(&self) -> Option<u64> {
+        self.downloaded_count
+    }
+
+    // The works we know about, but haven't fetched the files for.
+    pub fn missing_count(&self) -> u64 {
+        self.local_count
+            .unwrap_or(0)
+            .saturating_sub(self.downloaded_count.unwrap_or(0))
+    }
+
     pub fn hidden(&self) -> bool {
         self.hidden
     }
@@ -103,6 +123,22 @@ impl DbTag {
         self.favorite = favorite;
     }
 
+    pub fn watched(&self) -> bool {
+        self.watched
+    }
+
+    pub fn set_watched(&mut self, watched: bool) {
+        self.watched = watched;
+    }
+
+    pub fn new_count(&self) -> u64 {
+        self.new_count
+    }
+
+    pub fn set_new_count(&mut self, new_count: u64) {
+        self.new_count = new_count;
+    }
+
     pub fn wiki_url(&self) -> Option<&str> {
         self.wiki_url.as_deref()
     }
@@ -115,3 +151,23 @@ impl DbTag {
         self.sources.iter().map(|s| s.as_str())
     }
 }
+
+// The kind to give tags of one type from a plugin.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TagKindMapping {
+    pub source_type: String,
+    pub kind: TagKind,
+    // Whether the user picked the kind, rather than the plugin.
+    pub edited: bool,
+}
+
+// How often other tags show up on the works of one tag, for exploring related tags.
+#[derive(Clone, Debug)]
+pub struct CoTags {
+    pub tag_id: TagId,
+    pub work_count: usize,
+    // The most common co-occurring tags, most common first, with the number of shared works.
+    pub tags: Vec<(TagId, usize)>,
+    // How often each pair of the above show up together on the tag's works; the lower id first.
+    pub pairs: HashMap<(TagId, TagId), usize>,
+}
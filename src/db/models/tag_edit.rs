@@ -0,0 +1,11 @@
+// A tag that the user added to or removed from a work, waiting to be pushed to the work's source.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DbTagEdit {
+    pub screen_url: String,
+    pub work_name: String,
+    pub remote_id: String,
+    // The plugin that the work came from.
+    pub plugin: String,
+    pub tag: String,
+    pub added: bool,
+}
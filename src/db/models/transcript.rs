@@ -0,0 +1,18 @@
+use crate::shared::transcript::{Cue, TranscriptSource};
+
+// A work's transcript, a cue at a time, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DbTranscript {
+    pub source: TranscriptSource,
+    pub cues: Vec<Cue>,
+}
+
+// A cue that matched a search, with the matching words marked in the snippet.
+#[derive(Clone, Debug)]
+pub struct TranscriptHit {
+    pub screen_url: String,
+    // None if the work has gone away since we stored the transcript.
+    pub work_name: Option<String>,
+    pub start: f64,
+    pub snippet: String,
+}
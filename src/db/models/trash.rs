@@ -0,0 +1,13 @@
+use jiff::Timestamp;
+
+// A work with files in the trash, for the Trash view.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrashedWork {
+    pub screen_url: String,
+    pub work_name: String,
+    // Why the files were trashed, e.g. the quota purge that took them.
+    pub reason: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub trashed_at: Timestamp,
+}
@@ -1,11 +1,18 @@
-use crate::db::models::tag::TagId;
+use crate::{
+    db::models::tag::TagId,
+    plugin::thumbnail::media_type_of,
+    shared::enrichment::{EnrichedArtist, Enrichment},
+};
 use anyhow::anyhow;
 use artchiver_sdk::{History, Location, Measurement, PhysicalData, SiUnit};
-use jiff::civil::Date;
+use jiff::{Timestamp, civil::Date};
 use rusqlite::types::{ToSqlOutput, Value};
 use rusqlite::{Row, ToSql};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct WorkId(i64);
@@ -20,6 +27,43 @@ impl WorkId {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MediaType {
+    Image,
+    Video,
+    Audio,
+    Document,
+}
+
+impl MediaType {
+    pub const ALL: [Self; 4] = [Self::Image, Self::Video, Self::Audio, Self::Document];
+
+    // How this is stored in the media_type column.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Document => "document",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image => write!(f, "Images"),
+            Self::Video => write!(f, "Videos"),
+            Self::Audio => write!(f, "Audio"),
+            Self::Document => write!(f, "Documents"),
+        }
+    }
+}
+
 pub fn location_from_row(row: &Row<'_>) -> rusqlite::Result<Option<Location>> {
     let mut loc = Location::default();
     if let Some(custody) = row.get::<&str, Option<String>>("location_custody")? {
@@ -149,6 +193,7 @@ pub struct DbWork {
 
     favorite: bool,
     hidden: bool,
+    rating: u8,
 
     location: Option<Location>,
     history: Option<History>,
@@ -157,10 +202,24 @@ pub struct DbWork {
     preview_url: String,
     screen_url: String,
     archive_url: Option<String>,
+    model_url: Option<String>,
+    transcript_url: Option<String>,
+    // The work's id at its source, for plugins that act on it.
+    remote_id: Option<String>,
 
     preview_path: Option<PathBuf>,
     screen_path: Option<PathBuf>,
     archive_path: Option<PathBuf>,
+    model_path: Option<PathBuf>,
+    // A small copy of the screen image for the gallery, under the data dir.
+    thumb_path: Option<PathBuf>,
+    // Width over height of the screen image, once we have decoded it.
+    aspect_ratio: Option<f32>,
+    nsfw_score: Option<f32>,
+    media_type: Option<MediaType>,
+    // When we first saw the work, in milliseconds, and the size of its screen file.
+    created_at: i64,
+    file_size: Option<u64>,
 
     tags: Vec<TagId>,
 }
@@ -175,13 +234,14 @@ impl DbWork {
 
         let measurements = measurements_from_row(row)?;
 
-        Ok(Self {
+        let mut work = Self {
             id: WorkId(row.get("id")?),
             name: row.get("name")?,
             artist_id: row.get("artist_id")?,
             date: row.get("date")?,
             favorite: row.get("favorite")?,
             hidden: row.get("hidden")?,
+            rating: row.get("rating")?,
             location: location_from_row(row)?,
             history: history_from_row(row)?,
             physical_data: physical_from_row(row)?
@@ -189,6 +249,9 @@ impl DbWork {
             preview_url: row.get("preview_url")?,
             screen_url: row.get("screen_url")?,
             archive_url: row.get("archive_url")?,
+            model_url: row.get("model_url")?,
+            transcript_url: row.get("transcript_url")?,
+            remote_id: row.get("remote_id")?,
             preview_path: row
                 .get::<&str, Option<String>>("preview_path")?
                 .map(|s| s.into()),
@@ -198,8 +261,40 @@ impl DbWork {
             archive_path: row
                 .get::<&str, Option<String>>("archive_path")?
                 .map(|s| s.into()),
+            model_path: row
+                .get::<&str, Option<String>>("model_path")?
+                .map(|s| s.into()),
+            thumb_path: row
+                .get::<&str, Option<String>>("thumb_path")?
+                .map(|s| s.into()),
+            aspect_ratio: row.get("aspect_ratio")?,
+            nsfw_score: row.get("nsfw_score")?,
+            media_type: row
+                .get::<&str, Option<String>>("media_type")?
+                .and_then(|name| MediaType::from_name(&name)),
+            created_at: row
+                .get::<&str, Option<i64>>("created_at")?
+                .unwrap_or_default(),
+            file_size: row
+                .get::<&str, Option<i64>>("file_size")?
+                .and_then(|size| u64::try_from(size).ok()),
             tags,
-        })
+        };
+        // Note: works downloaded before we recorded the media type can still be sorted out by
+        //       looking at their files.
+        if work.media_type.is_none() {
+            work.media_type = work.derived_media_type();
+        }
+        Ok(work)
+    }
+
+    // The screen file is the work itself; the preview is only worth going by when that is all
+    // we have, since videos and songs usually come with an image for a preview.
+    fn derived_media_type(&self) -> Option<MediaType> {
+        match self.screen_path() {
+            Some(screen_path) => media_type_of(screen_path),
+            None => self.preview_path().and_then(media_type_of),
+        }
     }
 
     pub fn id(&self) -> WorkId {
@@ -214,6 +309,18 @@ impl DbWork {
         &self.date
     }
 
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    pub fn file_size(&self) -> Option<u64> {
+        self.file_size
+    }
+
+    pub fn remote_id(&self) -> Option<&str> {
+        self.remote_id.as_deref()
+    }
+
     pub fn preview_url(&self) -> &str {
         self.preview_url.as_str()
     }
@@ -226,6 +333,14 @@ impl DbWork {
         self.archive_url.as_deref()
     }
 
+    pub fn model_url(&self) -> Option<&str> {
+        self.model_url.as_deref()
+    }
+
+    pub fn transcript_url(&self) -> Option<&str> {
+        self.transcript_url.as_deref()
+    }
+
     pub fn preview_path(&self) -> Option<&Path> {
         self.preview_path.as_deref()
     }
@@ -250,6 +365,14 @@ impl DbWork {
         self.hidden = hidden;
     }
 
+    pub fn rating(&self) -> u8 {
+        self.rating
+    }
+
+    pub fn set_rating(&mut self, rating: u8) {
+        self.rating = rating;
+    }
+
     pub fn location(&self) -> Option<&Location> {
         self.location.as_ref()
     }
@@ -258,6 +381,15 @@ impl DbWork {
         self.history.as_ref()
     }
 
+    // e.g. "Gogh, Vincent van" where the plugin gives us one, or the attribution as displayed.
+    pub fn artist_sort_key(&self) -> Option<&str> {
+        self.history().and_then(|history| {
+            history
+                .attribution_sort_key()
+                .or_else(|| history.attribution())
+        })
+    }
+
     pub fn physical_data(&self) -> Option<&PhysicalData> {
         self.physical_data.as_ref()
     }
@@ -270,6 +402,34 @@ impl DbWork {
         self.archive_path.as_deref()
     }
 
+    pub fn model_path(&self) -> Option<&Path> {
+        self.model_path.as_deref()
+    }
+
+    pub fn set_model_path(&mut self, model_path: PathBuf) {
+        self.model_path = Some(model_path);
+    }
+
+    pub fn thumb_path(&self) -> Option<&Path> {
+        self.thumb_path.as_deref()
+    }
+
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        self.aspect_ratio
+    }
+
+    pub fn media_type(&self) -> Option<MediaType> {
+        self.media_type
+    }
+
+    pub fn nsfw_score(&self) -> Option<f32> {
+        self.nsfw_score
+    }
+
+    pub fn set_nsfw_score(&mut self, score: f32) {
+        self.nsfw_score = Some(score);
+    }
+
     pub fn tags(&self) -> impl Iterator<Item = TagId> {
         self.tags.iter().copied()
     }
@@ -278,11 +438,95 @@ impl DbWork {
     pub fn set_paths(
         &mut self,
         preview_path: PathBuf,
-        screen_path: PathBuf,
-        archive_path: Option<PathBuf>,
+        (screen_path, archive_path, thumb_path): (
+            Option<PathBuf>,
+            Option<PathBuf>,
+            Option<PathBuf>,
+        ),
+        aspect_ratio: Option<f32>,
     ) {
         self.preview_path = Some(preview_path);
-        self.screen_path = Some(screen_path);
+        self.screen_path = screen_path;
         self.archive_path = archive_path;
+        self.thumb_path = thumb_path;
+        self.aspect_ratio = aspect_ratio;
+        self.media_type = self.derived_media_type().or(self.media_type);
+    }
+}
+
+// A downloaded work that doesn't have a gallery thumbnail yet, e.g. from before we made them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MissingThumb {
+    pub work_id: WorkId,
+    pub screen_url: String,
+    pub screen_path: String,
+}
+
+// One field that a plugin changed when it updated a work.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WorkChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+// Everything that changed in one update of a work, as stored in work_revisions.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DbWorkRevision {
+    pub changed_at: Timestamp,
+    pub changes: Vec<WorkChange>,
+}
+
+// What other catalogs call a work, and the works from other plugins that are the same object.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DbWorkIdentity {
+    // (authority, id), as the plugin reported them.
+    pub external_ids: Vec<(String, String)>,
+    pub same_object: Vec<DbLinkedWork>,
+    // What Wikidata says about the work, if enrichment has looked it up.
+    pub enrichments: Vec<Enrichment>,
+    pub artists: Vec<EnrichedArtist>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DbLinkedWork {
+    pub work_id: WorkId,
+    pub name: String,
+    pub screen_url: String,
+    pub plugin: String,
+}
+
+// How the user wants a work shown, e.g. to fix a sideways scan or trim a huge border. Applied
+// when drawing; the archived files are never touched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayTransform {
+    // Clockwise quarter turns, 0 to 3.
+    pub quarter_turns: u8,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    // The fraction of the image to trim off of the left, top, right, and bottom edges.
+    pub crop: [f32; 4],
+    // -100 to 100; 0 leaves the image as it is.
+    pub brightness: i32,
+    pub contrast: i32,
+}
+
+impl DisplayTransform {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Brightness and contrast need the pixels re-processed, unlike the rest.
+    pub fn has_adjustments(&self) -> bool {
+        self.brightness != 0 || self.contrast != 0
+    }
+
+    pub fn rotate_clockwise(&mut self) {
+        self.quarter_turns = (self.quarter_turns + 1) % 4;
+    }
+
+    pub fn rotate_counter_clockwise(&mut self) {
+        self.quarter_turns = (self.quarter_turns + 3) % 4;
     }
 }
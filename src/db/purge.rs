@@ -0,0 +1,76 @@
+// Purging a plugin's works: the files of every work the plugin brought in that the user has not
+// marked as a favorite go to the trash, and the works go back to being recorded but not
+// downloaded, so that anything the user misses can be fetched again from the gallery, or
+// restored from the trash for a while.
+use crate::{
+    db::{
+        models::plugin::PluginId,
+        trash::{PATH_COLUMNS, move_to_trash, trash_work_files},
+    },
+    shared::{
+        progress::{LogSender, ProgressSender},
+        storage::Storage,
+    },
+};
+use anyhow::Result;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::BTreeSet;
+
+#[derive(Clone, Debug, Default)]
+pub struct PurgeReport {
+    pub works: usize,
+    pub files: usize,
+    pub failed: usize,
+}
+
+pub fn purge_plugin_works(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    plugin_id: PluginId,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<PurgeReport> {
+    let mut report = PurgeReport::default();
+    let mut stmt = conn.prepare(&format!(
+        r#"SELECT screen_url, file_size, {} FROM works
+        WHERE NOT favorite AND preview_path IS NOT NULL AND id IN (
+            SELECT work_tags.work_id FROM work_tags
+            INNER JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+            WHERE plugin_tags.plugin_id = ?
+        )"#,
+        PATH_COLUMNS.join(", ")
+    ))?;
+    let rows = stmt
+        .query_map([plugin_id], |row| {
+            let mut paths = Vec::new();
+            for column in PATH_COLUMNS {
+                if let Some(path) = row.get::<&str, Option<String>>(column)? {
+                    paths.push((column, path));
+                }
+            }
+            Ok((
+                row.get::<&str, String>("screen_url")?,
+                row.get::<&str, Option<i64>>("file_size")?,
+                paths,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if rows.is_empty() {
+        return Ok(report);
+    }
+    log.info(format!("Purging the files of {} works", rows.len()));
+
+    let mut files = BTreeSet::new();
+    for (screen_url, file_size, paths) in rows {
+        trash_work_files(conn, storage, &screen_url, &paths, file_size, "quota purge")?;
+        files.extend(paths.into_iter().map(|(_, path)| path));
+        report.works += 1;
+    }
+    (report.files, report.failed) = move_to_trash(conn, storage, files, log, progress)?;
+    log.info(format!(
+        "Purged {} works, moving {} files to the trash; {} could not be moved",
+        report.works, report.files, report.failed
+    ));
+    Ok(report)
+}
@@ -1,24 +1,46 @@
 use crate::{
     db::{
-        model::{DbCancellation, report_slow_query},
+        local_import::{LOCAL_IMPORT_TAG, LOCAL_PLUGIN_NAME},
+        model::{
+            DbCancellation, TagListQuery, TagSortCol, TagSource, report_slow_query,
+            string_to_rarray,
+        },
         models::{
-            tag::{DbTag, TagId},
-            work::{DbWork, WorkId},
+            annotation::DbAnnotation,
+            collection::DbCollection,
+            detection::{DbTagSuggestion, PendingScan, SuggestionId},
+            enrichment::PendingEnrichment,
+            exhibition::{DbExhibition, ExhibitionItem},
+            link_check::LostLink,
+            log::DbLogLine,
+            note::{DbWorkNote, NoteHit, fts_query},
+            plugin::PluginId,
+            tag::{CoTags, DbTag, TagId, TagKindMapping},
+            tag_edit::DbTagEdit,
+            transcript::{DbTranscript, TranscriptHit},
+            work::{
+                DbLinkedWork, DbWork, DbWorkIdentity, DbWorkRevision, DisplayTransform,
+                MissingThumb, WorkId,
+            },
         },
     },
     shared::{
+        enrichment::{EnrichedArtist, Enrichment, Statement},
         progress::{HostUpdateSender, LogSender, UpdateSource},
+        transcript::{Cue, TranscriptSource},
         update::DataUpdate,
     },
 };
 use anyhow::Result;
+use artchiver_sdk::{Authority, MediaRole};
 use crossbeam::channel::Sender;
+use jiff::Timestamp;
 use log::trace;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use rayon::ThreadPool;
-use rusqlite::params;
-use std::{collections::HashMap, mem, thread, thread::JoinHandle, time::Instant};
+use rusqlite::{OptionalExtension as _, ToSql, params, params_from_iter};
+use std::{collections::HashMap, mem, ops::Range, thread, thread::JoinHandle, time::Instant};
 
 #[derive(Debug)]
 pub struct DbReadHandle {
@@ -97,6 +119,34 @@ impl DbReadHandle {
         });
     }
 
+    pub fn get_watched_tag_counts(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let counts = count_new_works_per_watched_tag(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to count new works on watched tags: {e}"));
+                Vec::new()
+            });
+            host.return_watched_tag_counts(counts)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_tags_window(&self, generation: u64, query: TagListQuery, range: Range<usize>) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(
+            move || match list_tags_window(&conn, &query, range.clone()) {
+                Ok((total, tag_ids)) => host
+                    .return_tags_window(generation, total, range.start, tag_ids)
+                    .expect("connection closed"),
+                Err(e) => log.warn(format!("Failed to list tags: {e}")),
+            },
+        );
+    }
+
     pub fn get_works_for_tag(&self, tag_id: TagId) {
         let mut log = self.log.clone();
         let mut host = self.host.clone();
@@ -124,6 +174,309 @@ impl DbReadHandle {
             trace!("Dispatching fetched works to UX");
         });
     }
+
+    pub fn get_work_source(&self, work_id: WorkId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let source = get_work_source(&conn, work_id).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the source for {work_id:?}: {e}"));
+                None
+            });
+            host.return_work_source(work_id, source)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_work_history(&self, work_id: WorkId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let revisions = list_work_revisions(&conn, work_id).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the history for {work_id:?}: {e}"));
+                Vec::new()
+            });
+            host.return_work_history(work_id, revisions)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_work_identity(&self, work_id: WorkId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let identity = get_work_identity(&conn, work_id).unwrap_or_else(|e| {
+                log.warn(format!(
+                    "Failed to read the external ids of {work_id:?}: {e}"
+                ));
+                DbWorkIdentity::default()
+            });
+            host.return_work_identity(work_id, identity)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_work_variants(&self, work_id: WorkId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let variants = list_work_variants(&conn, work_id).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the variants of {work_id:?}: {e}"));
+                Vec::new()
+            });
+            host.return_work_variants(work_id, variants)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_work_note(&self, work_id: WorkId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let note = lookup_work_note(&conn, work_id).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the note on {work_id:?}: {e}"));
+                None
+            });
+            host.return_work_note(work_id, note)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn search_work_notes(&self, query: &str) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        let query = query.to_owned();
+        self.reader_threads.spawn(move || {
+            let hits = search_work_notes(&conn, &query).unwrap_or_else(|e| {
+                log.warn(format!("Failed to search notes for {query}: {e}"));
+                Vec::new()
+            });
+            host.return_note_search(query, hits)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_transcript(&self, screen_url: &str) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        let screen_url = screen_url.to_owned();
+        self.reader_threads.spawn(move || {
+            let transcript = lookup_transcript(&conn, &screen_url).unwrap_or_else(|e| {
+                log.warn(format!(
+                    "Failed to read the transcript of {screen_url}: {e}"
+                ));
+                None
+            });
+            host.return_transcript(screen_url, transcript)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn search_transcripts(&self, query: &str) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        let query = query.to_owned();
+        self.reader_threads.spawn(move || {
+            let hits = search_transcripts(&conn, &query).unwrap_or_else(|e| {
+                log.warn(format!("Failed to search transcripts for {query}: {e}"));
+                Vec::new()
+            });
+            host.return_transcript_search(query, hits)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_annotations(&self, screen_url: &str) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        let screen_url = screen_url.to_owned();
+        self.reader_threads.spawn(move || {
+            let annotations = list_annotations(&conn, &screen_url).unwrap_or_else(|e| {
+                log.warn(format!(
+                    "Failed to read the annotations on {screen_url}: {e}"
+                ));
+                Vec::new()
+            });
+            host.return_annotations(screen_url, annotations)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_display_transforms(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let transforms = list_display_transforms(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read display transforms: {e}"));
+                HashMap::new()
+            });
+            host.return_display_transforms(transforms)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_co_tags(&self, tag_id: TagId) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads
+            .spawn(move || match list_co_tags(&conn, tag_id) {
+                Ok(co_tags) => host.return_co_tags(co_tags).expect("connection closed"),
+                Err(e) => log.warn(format!("Failed to find related tags for {tag_id:?}: {e}")),
+            });
+    }
+
+    pub fn get_collections(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let collections = list_collections(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to list collections: {e}"));
+                Vec::new()
+            });
+            host.return_collections(collections)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_exhibitions(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let exhibitions = list_exhibitions(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to list exhibitions: {e}"));
+                Vec::new()
+            });
+            host.return_exhibitions(exhibitions)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_works_missing_thumbs(&self, unscored: bool) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let works = list_works_missing_thumbs(&conn, unscored).unwrap_or_else(|e| {
+                log.warn(format!("Failed to find works without thumbnails: {e}"));
+                Vec::new()
+            });
+            host.return_works_missing_thumbs(works)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_inbox_works(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let works = list_inbox_works(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the inbox: {e}"));
+                Vec::new()
+            });
+            host.return_inbox_works(works).expect("connection closed");
+        });
+    }
+
+    pub fn get_works_to_detect(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let works = list_works_to_detect(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to find works to look for faces in: {e}"));
+                Vec::new()
+            });
+            host.return_works_to_detect(works)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_works_to_enrich(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let works = list_works_to_enrich(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to find works to look up in Wikidata: {e}"));
+                Vec::new()
+            });
+            host.return_works_to_enrich(works)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_vocabulary_to_fetch(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let terms = list_vocabulary_to_fetch(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to find terms to fetch from Getty: {e}"));
+                Vec::new()
+            });
+            host.return_vocabulary_to_fetch(terms)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_tag_suggestions(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let suggestions = list_tag_suggestions(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the tag suggestions: {e}"));
+                Vec::new()
+            });
+            host.return_tag_suggestions(suggestions)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_pending_tag_edits(&self) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        self.reader_threads.spawn(move || {
+            let edits = list_pending_tag_edits(&conn).unwrap_or_else(|e| {
+                log.warn(format!("Failed to read the tag edits: {e}"));
+                Vec::new()
+            });
+            host.return_pending_tag_edits(edits)
+                .expect("connection closed");
+        });
+    }
+
+    pub fn get_linked_work(&self, screen_url: &str) {
+        let mut log = self.log.clone();
+        let mut host = self.host.clone();
+        let conn = self.pool.get().expect("failed to get connection");
+        let screen_url = screen_url.to_owned();
+        self.reader_threads.spawn(move || {
+            let work = list_works_by_screen_url(&conn, std::slice::from_ref(&screen_url))
+                .unwrap_or_else(|e| {
+                    log.warn(format!("Failed to look up the work at {screen_url}: {e}"));
+                    Vec::new()
+                })
+                .pop();
+            host.return_linked_work(screen_url, work)
+                .expect("connection closed");
+        });
+    }
 }
 
 pub fn list_works_with_tag(
@@ -132,47 +485,997 @@ pub fn list_works_with_tag(
     log: &mut LogSender,
     host: &mut HostUpdateSender,
 ) -> Result<()> {
-    const LIMIT: i64 = 1_000;
+    const LIMIT: usize = 1_000;
     let mut total_count = 0;
     let mut last_id = Some(WorkId::wrap(0));
     while let Some(last_work_id) = last_id {
-        let start = Instant::now();
-        // If we decide we *have* to apply AND up front, it looks like this.
-        // GROUP BY works.id HAVING COUNT(DISTINCT tags.name) = {enabled_size}
-        let query = format!(
-            r#"
-            SELECT works.*,
-                GROUP_CONCAT(DISTINCT tags.id) as tags,
-                GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names
-            FROM works
-                LEFT JOIN work_tags ON work_tags.work_id = works.id
-                LEFT JOIN tags ON work_tags.tag_id = tags.id
-                LEFT JOIN work_measurements AS m ON m.work_id = works.id
-            WHERE works.id IN (
-                SELECT work_tags.work_id FROM work_tags WHERE work_tags.tag_id = ?
-            ) AND works.id > ?
-            GROUP BY works.id
-            ORDER BY works.id
-            LIMIT {LIMIT}
-            "#
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let page = stmt
-            .query_map(params![tag_id, last_work_id], DbWork::from_row)?
-            .try_fold(Vec::new(), |mut expand, item| -> Result<Vec<DbWork>> {
-                expand.push(item?);
-                Ok(expand)
-            })?;
+        let page = list_works_with_tag_page(conn, tag_id, last_work_id, LIMIT, None)?;
         last_id = page.last().map(|w| w.id());
         total_count += page.len();
         let chunk = page.into_iter().map(|w| (w.id(), w)).collect();
         host.return_list_works_chunk(Some(tag_id), chunk, last_id.is_none())?;
-        report_slow_query(start, "list_works_with_any_tags", &query);
     }
     log.trace(format!("Finished collecting {total_count} works"));
     Ok(())
 }
 
+// What visitors to the HTTP API without the token may see: the works the gallery would show,
+// under the user's content filters. They never see hidden works, or works with a hidden tag.
+#[derive(Clone, Debug, Default)]
+pub struct PublicView {
+    // Works with any of these tags are withheld, unless the flag is set and they are favorites.
+    withheld_tags: Vec<(TagId, bool)>,
+    // Works that aren't favorites and that the NSFW classifier scored at least this high.
+    nsfw_threshold: Option<f32>,
+}
+
+impl PublicView {
+    pub fn new(withheld_tags: Vec<(TagId, bool)>, nsfw_threshold: Option<f32>) -> Self {
+        Self {
+            withheld_tags,
+            nsfw_threshold,
+        }
+    }
+
+    // The terms of a WHERE clause on works that leave only what visitors may see.
+    fn sql(&self) -> String {
+        let mut sql = r#"NOT works.hidden AND NOT EXISTS (
+            SELECT 1 FROM work_tags AS wt INNER JOIN tags AS t ON t.id = wt.tag_id
+            WHERE wt.work_id = works.id AND t.hidden
+        )"#
+        .to_owned();
+        for except_favorites in [false, true] {
+            let tags = self
+                .withheld_tags
+                .iter()
+                .filter(|(_, except)| *except == except_favorites)
+                .map(|(tag_id, _)| tag_id.to_string())
+                .collect::<Vec<_>>();
+            if tags.is_empty() {
+                continue;
+            }
+            let favorite = if except_favorites {
+                "works.favorite OR"
+            } else {
+                ""
+            };
+            sql += &format!(
+                r#" AND ({favorite} NOT EXISTS (
+                    SELECT 1 FROM work_tags AS wt
+                    WHERE wt.work_id = works.id AND wt.tag_id IN ({})
+                ))"#,
+                tags.join(", ")
+            );
+        }
+        if let Some(threshold) = self.nsfw_threshold {
+            sql += &format!(
+                " AND (works.favorite OR works.nsfw_score IS NULL OR works.nsfw_score < {threshold})"
+            );
+        }
+        sql
+    }
+}
+
+// Keyset paged query of the works with a tag, in id order, starting after `after`. Public pages
+// only have the works that visitors without the token may see.
+pub fn list_works_with_tag_page(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    tag_id: TagId,
+    after: WorkId,
+    limit: usize,
+    public: Option<&PublicView>,
+) -> Result<Vec<DbWork>> {
+    let start = Instant::now();
+    let public = public
+        .map(|view| format!("AND {}", view.sql()))
+        .unwrap_or_default();
+    // If we decide we *have* to apply AND up front, it looks like this.
+    // GROUP BY works.id HAVING COUNT(DISTINCT tags.name) = {enabled_size}
+    let query = format!(
+        r#"
+        SELECT works.*,
+            GROUP_CONCAT(DISTINCT tags.id) as tags,
+            GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names
+        FROM works
+            LEFT JOIN work_tags ON work_tags.work_id = works.id
+            LEFT JOIN tags ON work_tags.tag_id = tags.id
+            LEFT JOIN work_measurements AS m ON m.work_id = works.id
+        WHERE works.id IN (
+            SELECT work_tags.work_id FROM work_tags WHERE work_tags.tag_id = ?
+        ) AND works.id > ? {public}
+        GROUP BY works.id
+        ORDER BY works.id
+        LIMIT {limit}
+        "#
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let page = stmt
+        .query_map(params![tag_id, after], DbWork::from_row)?
+        .try_fold(Vec::new(), |mut expand, item| -> Result<Vec<DbWork>> {
+            expand.push(item?);
+            Ok(expand)
+        })?;
+    report_slow_query(start, "list_works_with_tag_page", &query);
+    Ok(page)
+}
+
+// The works with the given screen urls, e.g. to fetch the files for works that were only
+// recorded.
+// The works on a tag that we have recorded, but not fetched the screen files for.
+pub fn list_undownloaded_screen_urls(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    tag_id: TagId,
+) -> Result<Vec<String>> {
+    let query = r#"SELECT works.screen_url
+    FROM work_tags
+    INNER JOIN works ON works.id = work_tags.work_id
+    WHERE work_tags.tag_id = ? AND works.screen_path IS NULL"#;
+    Ok(conn
+        .prepare(query)?
+        .query_map([tag_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// A sample of the plugin's works that we have not downloaded, as (screen_url, remote_id), those
+// checked longest ago first. Works that we already know are lost are left alone.
+pub fn list_link_check_sample(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    limit: usize,
+) -> Result<Vec<(String, Option<String>)>> {
+    let query = r#"SELECT works.screen_url, works.remote_id
+    FROM works
+        LEFT JOIN link_checks AS c ON c.screen_url = works.screen_url
+    WHERE works.screen_path IS NULL AND (c.status IS NULL OR c.status != 'lost') AND works.id IN (
+        SELECT work_tags.work_id FROM work_tags
+        INNER JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+        WHERE plugin_tags.plugin_id = ?
+    )
+    ORDER BY COALESCE(c.checked_at, 0), RANDOM()
+    LIMIT ?"#;
+    Ok(conn
+        .prepare(query)?
+        .query_map(params![plugin_id, limit], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+pub fn list_lost_links(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<LostLink>> {
+    let query = r#"
+    SELECT
+        c.screen_url, works.name,
+        (SELECT plugins.name FROM work_tags
+            JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+            JOIN plugins ON plugins.id = plugin_tags.plugin_id
+         WHERE work_tags.work_id = works.id
+         LIMIT 1) AS plugin,
+        c.http_status, c.checked_at
+    FROM link_checks AS c
+        JOIN works ON works.screen_url = c.screen_url
+    WHERE c.status = 'lost'
+    ORDER BY plugin, works.name, c.screen_url
+"#;
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        out.push(LostLink {
+            screen_url: row.get(0)?,
+            work_name: row.get(1)?,
+            plugin: row.get(2)?,
+            http_status: row.get(3)?,
+            checked_at: Timestamp::from_millisecond(row.get(4)?)?,
+        });
+    }
+    Ok(out)
+}
+
+// The paths of the most recently downloaded works from a plugin, for estimating file sizes.
+pub fn list_downloaded_paths_sample(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    limit: usize,
+) -> Result<Vec<(Option<String>, String)>> {
+    let query = r#"SELECT works.preview_path, works.screen_path
+    FROM works
+    WHERE works.screen_path IS NOT NULL AND works.id IN (
+        SELECT work_tags.work_id FROM work_tags
+        INNER JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+        WHERE plugin_tags.plugin_id = ?
+    )
+    ORDER BY works.id DESC
+    LIMIT ?"#;
+    Ok(conn
+        .prepare(query)?
+        .query_map(params![plugin_id, limit], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// What the plugin's works take up on disk, going by the size of their full size files.
+pub fn plugin_storage_usage(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+) -> Result<u64> {
+    let query = r#"SELECT COALESCE(SUM(works.file_size), 0)
+    FROM works
+    WHERE works.screen_path IS NOT NULL AND works.id IN (
+        SELECT work_tags.work_id FROM work_tags
+        INNER JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+        WHERE plugin_tags.plugin_id = ?
+    )"#;
+    let bytes = conn.query_one(query, [plugin_id], |row| row.get::<usize, i64>(0))?;
+    Ok(u64::try_from(bytes).unwrap_or_default())
+}
+
+pub fn list_works_by_screen_url(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_urls: &[String],
+) -> Result<Vec<DbWork>> {
+    let query = r#"
+    SELECT
+        works.*,
+        GROUP_CONCAT(DISTINCT tags.id) as tags,
+        GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names
+    FROM works
+        LEFT JOIN work_tags ON work_tags.work_id = works.id
+        LEFT JOIN tags ON work_tags.tag_id = tags.id
+        LEFT JOIN work_measurements AS m ON m.work_id = works.id
+    WHERE works.screen_url IN rarray(?)
+    GROUP BY works.id
+"#;
+    let mut stmt = conn.prepare(query)?;
+    let works = stmt
+        .query_map([string_to_rarray(screen_urls)], DbWork::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(works)
+}
+
+pub fn get_work(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<Option<DbWork>> {
+    let query = r#"
+    SELECT
+        works.*,
+        GROUP_CONCAT(DISTINCT tags.id) as tags,
+        GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names
+    FROM works
+        LEFT JOIN work_tags ON work_tags.work_id = works.id
+        LEFT JOIN tags ON work_tags.tag_id = tags.id
+        LEFT JOIN work_measurements AS m ON m.work_id = works.id
+    WHERE works.id = ?
+    GROUP BY works.id
+"#;
+    Ok(conn
+        .query_row(query, params![work_id], DbWork::from_row)
+        .optional()?)
+}
+
+pub fn is_public_work(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+    view: &PublicView,
+) -> Result<bool> {
+    Ok(conn.query_one(
+        &format!(
+            "SELECT EXISTS(SELECT 1 FROM works WHERE works.id = ? AND {})",
+            view.sql()
+        ),
+        params![work_id],
+        |row| row.get(0),
+    )?)
+}
+
+// The raw record the work was mapped from, if we kept it.
+pub fn get_work_source(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<Option<String>> {
+    let source: Option<Vec<u8>> = conn
+        .query_row(
+            r#"SELECT work_sources.source FROM work_sources
+                JOIN works ON works.screen_url = work_sources.screen_url
+                WHERE works.id = ?"#,
+            params![work_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    source.as_deref().map(decompress_source).transpose()
+}
+
+// Newest first.
+pub fn list_work_revisions(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<Vec<DbWorkRevision>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT work_revisions.changed_at, work_revisions.changes FROM work_revisions
+            JOIN works ON works.screen_url = work_revisions.screen_url
+            WHERE works.id = ?
+            ORDER BY work_revisions.changed_at DESC"#,
+    )?;
+    let mut rows = stmt.query(params![work_id])?;
+    let mut revisions = Vec::new();
+    while let Some(row) = rows.next()? {
+        let changes: String = row.get(1)?;
+        revisions.push(DbWorkRevision {
+            changed_at: Timestamp::from_millisecond(row.get(0)?)?,
+            changes: serde_json::from_str(&changes)?,
+        });
+    }
+    Ok(revisions)
+}
+
+// The work's external ids, the other works that share its Wikidata id, and what Wikidata said
+// about it.
+pub fn get_work_identity(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<DbWorkIdentity> {
+    let external_ids = conn
+        .prepare(
+            r#"SELECT DISTINCT e.authority, e.value FROM work_external_ids AS e
+            JOIN works ON works.screen_url = e.screen_url
+            WHERE works.id = ?
+            ORDER BY e.authority, e.value"#,
+        )?
+        .query_map(params![work_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let same_object = conn
+        .prepare(
+            r#"SELECT DISTINCT other.id, other.name, other.screen_url, plugins.name
+            FROM works AS mine
+                JOIN work_external_ids AS a ON a.screen_url = mine.screen_url
+                JOIN work_external_ids AS b
+                    ON b.authority = a.authority AND b.value = a.value
+                    AND b.screen_url != a.screen_url
+                JOIN works AS other ON other.screen_url = b.screen_url
+                JOIN plugins ON plugins.id = b.plugin_id
+            WHERE mine.id = ? AND a.authority = ?
+            ORDER BY plugins.name, other.name"#,
+        )?
+        .query_map(params![work_id, Authority::Wikidata.to_string()], |row| {
+            Ok(DbLinkedWork {
+                work_id: WorkId::wrap(row.get(0)?),
+                name: row.get(1)?,
+                screen_url: row.get(2)?,
+                plugin: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let enrichments = conn
+        .prepare(
+            r#"SELECT statement, value, label FROM enrichments
+            JOIN works ON works.screen_url = enrichments.screen_url
+            WHERE works.id = ?
+            ORDER BY statement, label"#,
+        )?
+        .query_map(params![work_id], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(statement, value, label)| {
+            Some(Enrichment {
+                statement: Statement::from_name(&statement)?,
+                value,
+                label,
+            })
+        })
+        .collect();
+    let artists = conn
+        .prepare(
+            r#"SELECT artists.wikidata_id, artists.name, artists.birthday, artists.deathday,
+                artists.nationality, artists.bio
+            FROM artists
+                JOIN enrichments ON enrichments.value = artists.wikidata_id
+                JOIN works ON works.screen_url = enrichments.screen_url
+            WHERE works.id = ? AND enrichments.statement = ?
+            ORDER BY artists.name"#,
+        )?
+        .query_map(params![work_id, Statement::Creator.name()], |row| {
+            Ok(EnrichedArtist {
+                wikidata_id: row.get(0)?,
+                name: row.get(1)?,
+                birthday: row
+                    .get::<usize, Option<String>>(2)?
+                    .and_then(|date| date.parse().ok()),
+                deathday: row
+                    .get::<usize, Option<String>>(3)?
+                    .and_then(|date| date.parse().ok()),
+                nationality: row.get(4)?,
+                bio: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(DbWorkIdentity {
+        external_ids,
+        same_object,
+        enrichments,
+        artists,
+    })
+}
+
+// The stored paths of the user's edits of the work, oldest first.
+pub fn list_work_variants(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<Vec<String>> {
+    let variants = conn
+        .prepare(
+            r#"SELECT work_variants.path FROM work_variants
+            JOIN works ON works.screen_url = work_variants.screen_url
+            WHERE works.id = ?
+            ORDER BY work_variants.added_at"#,
+        )?
+        .query_map(params![work_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(variants)
+}
+
+pub fn list_annotations(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+) -> Result<Vec<DbAnnotation>> {
+    let annotations = conn
+        .prepare(
+            r#"SELECT id, label, x, y, width, height FROM annotations
+            WHERE screen_url = ?
+            ORDER BY created_at"#,
+        )?
+        .query_map(params![screen_url], DbAnnotation::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(annotations)
+}
+
+pub fn lookup_work_note(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+) -> Result<Option<DbWorkNote>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT work_notes.body, work_notes.created_at, work_notes.updated_at
+            FROM work_notes
+            JOIN works ON works.screen_url = work_notes.screen_url
+            WHERE works.id = ?"#,
+    )?;
+    let mut rows = stmt.query(params![work_id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(DbWorkNote {
+        body: row.get(0)?,
+        created_at: Timestamp::from_millisecond(row.get(1)?)?,
+        updated_at: Timestamp::from_millisecond(row.get(2)?)?,
+    }))
+}
+
+// Notes with every word of the query, best match first.
+pub fn search_work_notes(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    query: &str,
+) -> Result<Vec<NoteHit>> {
+    let query = fts_query(query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        r#"SELECT work_notes.screen_url, works.name,
+                snippet(work_notes_fts, 0, '**', '**', '…', 16), work_notes.updated_at
+            FROM work_notes_fts
+            JOIN work_notes ON work_notes.id = work_notes_fts.rowid
+            LEFT JOIN works ON works.screen_url = work_notes.screen_url
+            WHERE work_notes_fts MATCH ?
+            ORDER BY rank
+            LIMIT 200"#,
+    )?;
+    let mut rows = stmt.query(params![query])?;
+    let mut hits = Vec::new();
+    while let Some(row) = rows.next()? {
+        hits.push(NoteHit {
+            screen_url: row.get(0)?,
+            work_name: row.get(1)?,
+            snippet: row.get(2)?,
+            updated_at: Timestamp::from_millisecond(row.get(3)?)?,
+        });
+    }
+    Ok(hits)
+}
+
+pub fn lookup_transcript(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+) -> Result<Option<DbTranscript>> {
+    let Some((id, source)) = conn
+        .query_row(
+            "SELECT id, source FROM work_transcripts WHERE screen_url = ?",
+            params![screen_url],
+            |row| Ok((row.get::<usize, i64>(0)?, row.get::<usize, String>(1)?)),
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let cues = conn
+        .prepare(
+            r#"SELECT start_secs, end_secs, body FROM transcript_cues
+            WHERE transcript_id = ?
+            ORDER BY start_secs, id"#,
+        )?
+        .query_map(params![id], |row| {
+            Ok(Cue {
+                start: row.get(0)?,
+                end: row.get(1)?,
+                text: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Some(DbTranscript {
+        source: TranscriptSource::try_from(source.as_str())?,
+        cues,
+    }))
+}
+
+// Cues with every word of the query, best match first.
+pub fn search_transcripts(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    query: &str,
+) -> Result<Vec<TranscriptHit>> {
+    let query = fts_query(query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        r#"SELECT work_transcripts.screen_url, works.name, transcript_cues.start_secs,
+                snippet(transcript_cues_fts, 0, '**', '**', '…', 16)
+            FROM transcript_cues_fts
+            JOIN transcript_cues ON transcript_cues.id = transcript_cues_fts.rowid
+            JOIN work_transcripts ON work_transcripts.id = transcript_cues.transcript_id
+            LEFT JOIN works ON works.screen_url = work_transcripts.screen_url
+            WHERE transcript_cues_fts MATCH ?
+            ORDER BY rank
+            LIMIT 200"#,
+    )?;
+    let mut rows = stmt.query(params![query])?;
+    let mut hits = Vec::new();
+    while let Some(row) = rows.next()? {
+        hits.push(TranscriptHit {
+            screen_url: row.get(0)?,
+            work_name: row.get(1)?,
+            start: row.get(2)?,
+            snippet: row.get(3)?,
+        });
+    }
+    Ok(hits)
+}
+
+fn decompress_source(compressed: &[u8]) -> Result<String> {
+    Ok(String::from_utf8(zstd::decode_all(compressed)?)?)
+}
+
+pub fn count_work_sources(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+) -> Result<usize> {
+    Ok(conn.query_one(
+        "SELECT COUNT(*) FROM work_sources WHERE plugin_id = ?",
+        params![plugin_id],
+        |row| row.get(0),
+    )?)
+}
+
+// The kept sources for the plugin as (screen_url, source), in screen_url order, starting after
+// the given screen_url.
+pub fn list_work_sources_page(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    after: Option<&str>,
+    limit: usize,
+) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT screen_url, source FROM work_sources
+            WHERE plugin_id = ? AND screen_url > ?
+            ORDER BY screen_url LIMIT ?"#,
+    )?;
+    let mut rows = stmt.query(params![plugin_id, after.unwrap_or_default(), limit])?;
+    let mut page = Vec::new();
+    while let Some(row) = rows.next()? {
+        let compressed: Vec<u8> = row.get(1)?;
+        page.push((row.get(0)?, decompress_source(&compressed)?));
+    }
+    Ok(page)
+}
+
+// The mirrors for each of the works, as (role, url), in the order to try them.
+pub fn list_work_mirrors(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_urls: &[String],
+) -> Result<HashMap<String, Vec<(MediaRole, String)>>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT screen_url, role, url FROM work_mirrors
+            WHERE screen_url IN rarray(?)
+            ORDER BY screen_url, position"#,
+    )?;
+    let mut rows = stmt.query([string_to_rarray(screen_urls)])?;
+    let mut mirrors = HashMap::<String, Vec<_>>::new();
+    while let Some(row) = rows.next()? {
+        let role = MediaRole::try_from(row.get_ref(1)?.as_str()?)?;
+        mirrors
+            .entry(row.get(0)?)
+            .or_default()
+            .push((role, row.get(2)?));
+    }
+    Ok(mirrors)
+}
+
+pub fn list_display_transforms(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<HashMap<String, DisplayTransform>> {
+    let mut stmt = conn.prepare("SELECT screen_url, transform FROM work_display")?;
+    let mut rows = stmt.query([])?;
+    let mut out = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let transform: String = row.get(1)?;
+        out.insert(row.get(0)?, serde_json::from_str(&transform)?);
+    }
+    Ok(out)
+}
+
+// Note: any more than this is an unreadable hairball when drawn as a graph.
+const MAX_CO_TAGS: usize = 24;
+
+pub fn list_co_tags(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    tag_id: TagId,
+) -> Result<CoTags> {
+    // The works with the tag, and the tags most often found on them; ?1 is the tag.
+    const WITH_TOP: &str = r#"
+    WITH focus AS (SELECT work_id FROM work_tags WHERE tag_id = ?1),
+        top AS (
+            SELECT wt.tag_id, COUNT(*) AS shared
+            FROM work_tags AS wt
+                JOIN focus ON focus.work_id = wt.work_id
+            WHERE wt.tag_id != ?1
+            GROUP BY wt.tag_id
+            ORDER BY shared DESC
+            LIMIT ?2
+        )"#;
+    let start = Instant::now();
+    let work_count = conn.query_row(
+        "SELECT COUNT(*) FROM work_tags WHERE tag_id = ?",
+        params![tag_id],
+        |row| row.get(0),
+    )?;
+
+    let query = format!("{WITH_TOP} SELECT tag_id, shared FROM top ORDER BY shared DESC");
+    let tags = conn
+        .prepare(&query)?
+        .query_map(params![tag_id, MAX_CO_TAGS], |row| {
+            Ok((TagId::wrap(row.get(0)?), row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(TagId, usize)>>>()?;
+
+    let query = format!(
+        r#"{WITH_TOP}
+    SELECT a.tag_id, b.tag_id, COUNT(*)
+    FROM work_tags AS a
+        JOIN focus ON focus.work_id = a.work_id
+        JOIN work_tags AS b ON b.work_id = a.work_id
+    WHERE a.tag_id IN (SELECT tag_id FROM top)
+        AND b.tag_id IN (SELECT tag_id FROM top)
+        AND a.tag_id < b.tag_id
+    GROUP BY a.tag_id, b.tag_id"#
+    );
+    let pairs = conn
+        .prepare(&query)?
+        .query_map(params![tag_id, MAX_CO_TAGS], |row| {
+            Ok((
+                (TagId::wrap(row.get(0)?), TagId::wrap(row.get(1)?)),
+                row.get(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, usize>>>()?;
+    report_slow_query(start, "list_co_tags", &query);
+    Ok(CoTags {
+        tag_id,
+        work_count,
+        tags,
+        pairs,
+    })
+}
+
+pub fn list_collections(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<DbCollection>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT collections.tag_id, collections.name, collections.kind, plugins.name AS plugin,
+        (SELECT COUNT(*) FROM work_tags WHERE work_tags.tag_id = collections.tag_id) AS work_count
+    FROM collections
+        JOIN plugins ON plugins.id = collections.plugin_id
+    ORDER BY plugins.name, collections.kind, collections.name"#;
+    let collections = conn
+        .prepare(query)?
+        .query_map((), DbCollection::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_collections", query);
+    Ok(collections)
+}
+
+pub fn list_exhibitions(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<DbExhibition>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT exhibitions.name, exhibition_items.screen_url, exhibition_items.title,
+        exhibition_items.caption, exhibition_items.notes
+    FROM exhibitions
+        LEFT JOIN exhibition_items ON exhibition_items.exhibition_id = exhibitions.id
+    WHERE NOT exhibitions.deleted
+    ORDER BY exhibitions.name, exhibition_items.position"#;
+    let mut exhibitions: Vec<DbExhibition> = Vec::new();
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query(())?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get("name")?;
+        if exhibitions.last().is_none_or(|e| e.name != name) {
+            exhibitions.push(DbExhibition::new(&name));
+        }
+        let exhibition = exhibitions.last_mut().expect("just pushed");
+        // Note: an exhibition with no items yet still comes back once from the left join.
+        if row.get::<_, Option<String>>("caption")?.is_none() {
+            continue;
+        }
+        exhibition.items.push(ExhibitionItem::from_row(row)?);
+    }
+    report_slow_query(start, "list_exhibitions", query);
+
+    let screen_urls = exhibitions
+        .iter()
+        .flat_map(|e| e.items.iter().filter_map(|item| item.screen_url()))
+        .map(|url| url.to_owned())
+        .collect::<Vec<_>>();
+    let works = list_works_by_screen_url(conn, &screen_urls)?
+        .into_iter()
+        .map(|work| (work.screen_url().to_owned(), work))
+        .collect::<HashMap<_, _>>();
+    for exhibition in &mut exhibitions {
+        exhibition.works = exhibition
+            .items
+            .iter()
+            .filter_map(|item| item.screen_url())
+            .filter_map(|url| works.get(url).map(|work| (url.to_owned(), work.clone())))
+            .collect();
+    }
+    Ok(exhibitions)
+}
+
+// Note: the inbox is for reviewing by hand, so there is no point in paging through more than
+//       the most recent few thousand.
+const MAX_INBOX_WORKS: usize = 2_000;
+
+pub fn list_inbox_works(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<(String, DbWork)>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT
+        works.*,
+        GROUP_CONCAT(DISTINCT tags.id) as tags,
+        GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names,
+        CASE WHEN inbox.for_tag = '' THEN plugins.name ELSE inbox.for_tag END as inbox_group
+    FROM work_inbox AS inbox
+        JOIN works ON works.screen_url = inbox.screen_url
+        LEFT JOIN plugins ON plugins.id = inbox.plugin_id
+        LEFT JOIN work_tags ON work_tags.work_id = works.id
+        LEFT JOIN tags ON work_tags.tag_id = tags.id
+        LEFT JOIN work_measurements AS m ON m.work_id = works.id
+    GROUP BY works.id
+    ORDER BY inbox.added_at DESC
+    LIMIT ?
+"#;
+    let mut stmt = conn.prepare(query)?;
+    let out = stmt
+        .query_map([MAX_INBOX_WORKS], |row| {
+            Ok((
+                row.get::<&str, Option<String>>("inbox_group")?
+                    .unwrap_or_default(),
+                DbWork::from_row(row)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_inbox_works", query);
+    Ok(out)
+}
+
+// Downloaded works that detection hasn't looked at, local imports first, as those are the ones
+// that arrive without tags.
+pub fn list_works_to_detect(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<PendingScan>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT works.screen_url, works.screen_path FROM works
+        LEFT JOIN detection_scans ON detection_scans.screen_url = works.screen_url
+    WHERE works.screen_path IS NOT NULL AND detection_scans.screen_url IS NULL
+    ORDER BY works.id IN (
+        SELECT work_tags.work_id FROM work_tags
+        JOIN tags ON tags.id = work_tags.tag_id
+        WHERE tags.name = ?
+    ) DESC, works.id DESC
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map(params![LOCAL_IMPORT_TAG], |row| {
+            Ok(PendingScan {
+                screen_url: row.get(0)?,
+                screen_path: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_works_to_detect", query);
+    Ok(out)
+}
+
+// Works with a Wikidata id that we haven't looked up yet, newest first.
+pub fn list_works_to_enrich(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<PendingEnrichment>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT e.screen_url, MIN(e.value) FROM work_external_ids AS e
+        LEFT JOIN enrichment_scans ON enrichment_scans.screen_url = e.screen_url
+    WHERE e.authority = ? AND enrichment_scans.screen_url IS NULL
+    GROUP BY e.screen_url
+    ORDER BY MAX(e.rowid) DESC
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map(params![Authority::Wikidata.to_string()], |row| {
+            Ok(PendingEnrichment {
+                screen_url: row.get(0)?,
+                wikidata_id: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_works_to_enrich", query);
+    Ok(out)
+}
+
+// The Getty terms that tags stand for, then the broader AAT terms above those, that we haven't
+// fetched yet. Each fetch finds the next level up, until the top of the hierarchy.
+pub fn list_vocabulary_to_fetch(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<(Authority, String)>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT x.authority, x.value FROM tag_external_ids AS x
+        LEFT JOIN vocabulary_terms AS v ON v.authority = x.authority AND v.value = x.value
+    WHERE x.authority IN (?1, ?2) AND v.value IS NULL
+    UNION
+    SELECT v.authority, v.broader FROM vocabulary_terms AS v
+        LEFT JOIN vocabulary_terms AS b ON b.authority = v.authority AND b.value = v.broader
+    WHERE v.authority = ?1 AND v.broader IS NOT NULL AND b.value IS NULL
+"#;
+    let rows = conn
+        .prepare(query)?
+        .query_map(
+            params![Authority::Aat.to_string(), Authority::Ulan.to_string()],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_vocabulary_to_fetch", query);
+    let mut out = Vec::with_capacity(rows.len());
+    for (authority, value) in rows {
+        out.push((Authority::try_from(authority.as_str())?, value));
+    }
+    Ok(out)
+}
+
+const MAX_TAG_SUGGESTIONS: usize = 2_000;
+
+// Pending suggestions, by tag, newest works first.
+pub fn list_tag_suggestions(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<DbTagSuggestion>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT
+        works.*,
+        GROUP_CONCAT(DISTINCT tags.id) as tags,
+        GROUP_CONCAT(DISTINCT m.name || '|' || m.description || '|' || m.value || '|' || m.si_unit) as measure_names,
+        s.id AS suggestion_id, s.tag AS suggestion_tag, s.reason AS suggestion_reason
+    FROM tag_suggestions AS s
+        JOIN works ON works.screen_url = s.screen_url
+        LEFT JOIN work_tags ON work_tags.work_id = works.id
+        LEFT JOIN tags ON work_tags.tag_id = tags.id
+        LEFT JOIN work_measurements AS m ON m.work_id = works.id
+    WHERE s.status = 'pending'
+    GROUP BY s.id
+    ORDER BY s.tag, works.id DESC
+    LIMIT ?
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map([MAX_TAG_SUGGESTIONS], |row| {
+            Ok(DbTagSuggestion {
+                id: SuggestionId::wrap(row.get("suggestion_id")?),
+                tag: row.get("suggestion_tag")?,
+                reason: row.get("suggestion_reason")?,
+                work: DbWork::from_row(row)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_tag_suggestions", query);
+    Ok(out)
+}
+
+// Edits can only be pushed for works that a plugin gave us, with the id that its source knows
+// them by; the plugin is whichever gave the work the rest of its tags.
+pub fn list_pending_tag_edits(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<DbTagEdit>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT
+        works.screen_url, works.name, works.remote_id, e.tag, e.added,
+        (SELECT plugins.name FROM work_tags
+            JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+            JOIN plugins ON plugins.id = plugin_tags.plugin_id
+         WHERE work_tags.work_id = works.id AND plugins.name != ?
+         LIMIT 1) AS plugin
+    FROM tag_edits AS e
+        JOIN works ON works.screen_url = e.screen_url
+    WHERE e.pushed_at IS NULL AND works.remote_id IS NOT NULL AND plugin IS NOT NULL
+    ORDER BY plugin, works.name, works.screen_url, e.tag
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map([LOCAL_PLUGIN_NAME], |row| {
+            Ok(DbTagEdit {
+                screen_url: row.get("screen_url")?,
+                work_name: row.get("name")?,
+                remote_id: row.get("remote_id")?,
+                plugin: row.get("plugin")?,
+                tag: row.get("tag")?,
+                added: row.get("added")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_pending_tag_edits", query);
+    Ok(out)
+}
+
+// With `unscored`, also the works with a thumbnail that the NSFW classifier hasn't scored.
+pub fn list_works_missing_thumbs(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    unscored: bool,
+) -> Result<Vec<MissingThumb>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT id, screen_url, screen_path FROM works
+    WHERE screen_path IS NOT NULL AND (
+        thumb_path IS NULL OR aspect_ratio IS NULL
+        OR (? AND thumb_path IS NOT NULL AND nsfw_score IS NULL)
+    )
+    ORDER BY id DESC
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map([unscored], |row| {
+            Ok(MissingThumb {
+                work_id: WorkId::wrap(row.get(0)?),
+                screen_url: row.get(1)?,
+                screen_path: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_works_missing_thumbs", query);
+    Ok(out)
+}
+
 pub fn list_favorite_works(
     conn: &PooledConnection<SqliteConnectionManager>,
 ) -> Result<Vec<DbWork>> {
@@ -206,6 +1509,7 @@ pub fn list_favorite_works(
 pub fn list_all_tags(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<DbTag>> {
     let query = r#"
     SELECT tags.id, tags.name, tags.kind, tags.wiki_url, tags.remote_id, tags.favorite, tags.hidden,
+        tags.watched,
         SUM(plugin_tags.presumed_work_count) AS network_count,
         GROUP_CONCAT(plugins.name) AS plugin_names
     FROM tags
@@ -217,21 +1521,154 @@ pub fn list_all_tags(conn: &PooledConnection<SqliteConnectionManager>) -> Result
     Ok(tags)
 }
 
+pub fn get_tag(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    tag_id: TagId,
+) -> Result<Option<DbTag>> {
+    let query = r#"
+    SELECT tags.id, tags.name, tags.kind, tags.wiki_url, tags.remote_id, tags.favorite, tags.hidden,
+        tags.watched,
+        SUM(plugin_tags.presumed_work_count) AS network_count,
+        GROUP_CONCAT(plugins.name) AS plugin_names
+    FROM tags
+    LEFT JOIN plugin_tags ON tags.id == plugin_tags.tag_id
+    LEFT JOIN plugins ON plugin_tags.plugin_id == plugins.id
+    WHERE tags.id = ?
+    GROUP BY tags.id;"#;
+    Ok(conn
+        .query_row(query, params![tag_id], DbTag::from_row)
+        .optional()?)
+}
+
+// Newest first.
+pub fn list_plugin_logs(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    limit: usize,
+) -> Result<Vec<DbLogLine>> {
+    let query = r#"
+    SELECT level, timestamp, job, message
+    FROM plugin_logs
+    WHERE plugin_id = ?
+    ORDER BY id DESC
+    LIMIT ?;"#;
+    Ok(conn
+        .prepare(query)?
+        .query_map(params![plugin_id, limit], |row| {
+            DbLogLine::from_row(plugin_id, row)
+        })?
+        .flatten()
+        .collect())
+}
+
+pub fn list_tag_kind_mappings(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+) -> Result<Vec<TagKindMapping>> {
+    let query = r#"
+    SELECT source_type, kind, edited
+    FROM tag_kind_mappings
+    WHERE plugin_id = ?
+    ORDER BY source_type;"#;
+    Ok(conn
+        .prepare(query)?
+        .query_map(params![plugin_id], |row| {
+            Ok(TagKindMapping {
+                source_type: row.get(0)?,
+                kind: row.get::<usize, String>(1)?.parse().unwrap_or_default(),
+                edited: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// The total number of tags that match the query, and the ids of those in the range, in order.
+pub fn list_tags_window(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    query: &TagListQuery,
+    range: Range<usize>,
+) -> Result<(usize, Vec<TagId>)> {
+    let start = Instant::now();
+    let escaped = query
+        .name
+        .replace('\\', r"\\")
+        .replace('%', r"\%")
+        .replace('_', r"\_");
+    let pattern = format!("%{escaped}%");
+    // Note: a tag is also found by its aliases, e.g. the other names Getty knows it by.
+    let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(pattern.clone()), Box::new(pattern)];
+    let mut filter = r#"(tags.name LIKE ? ESCAPE '\' OR EXISTS (SELECT 1 FROM tag_aliases
+        WHERE tag_aliases.tag_id = tags.id AND tag_aliases.alias LIKE ? ESCAPE '\'))"#
+        .to_owned();
+    if let Some(kind) = query.kind {
+        filter.push_str(" AND tags.kind = ?");
+        params.push(Box::new(kind.to_string()));
+    }
+    match &query.source {
+        TagSource::All => {}
+        TagSource::Hidden => filter.push_str(" AND tags.hidden"),
+        TagSource::Plugin(name) => {
+            filter.push_str(
+                r#" AND EXISTS (SELECT 1 FROM plugin_tags
+                    JOIN plugins ON plugins.id = plugin_tags.plugin_id
+                    WHERE plugin_tags.tag_id = tags.id AND plugins.name = ?)"#,
+            );
+            params.push(Box::new(name.clone()));
+        }
+    }
+
+    let count_query = format!("SELECT COUNT(*) FROM tags WHERE {filter}");
+    let total: usize = conn.query_row(
+        &count_query,
+        params_from_iter(params.iter().map(|p| p.as_ref())),
+        |row| row.get(0),
+    )?;
+
+    // Note: favorites always sort first, and ties sort by name in the same direction.
+    let dir = query.order.order();
+    let column = match query.order.column() {
+        TagSortCol::Name => "tags.name",
+        TagSortCol::LocalCount => {
+            "(SELECT COUNT(*) FROM work_tags WHERE work_tags.tag_id = tags.id)"
+        }
+        TagSortCol::NetworkCount => {
+            "(SELECT SUM(presumed_work_count) FROM plugin_tags WHERE plugin_tags.tag_id = tags.id)"
+        }
+    };
+    let window_query = format!(
+        r#"SELECT tags.id FROM tags WHERE {filter}
+        ORDER BY tags.favorite DESC, {column} {dir}, tags.name {dir}
+        LIMIT ? OFFSET ?"#
+    );
+    params.push(Box::new(range.len()));
+    params.push(Box::new(range.start));
+    let tag_ids = conn
+        .prepare(&window_query)?
+        .query_map(params_from_iter(params.iter().map(|p| p.as_ref())), |row| {
+            Ok(TagId::wrap(row.get(0)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "list_tags_window", &window_query);
+    Ok((total, tag_ids))
+}
+
 pub fn count_works_per_tag(
     conn: &PooledConnection<SqliteConnectionManager>,
     log: &mut LogSender,
     host: &mut HostUpdateSender,
 ) -> Result<()> {
-    let query = r#"SELECT tags.id, COUNT(work_tags.id)
+    let query = r#"SELECT tags.id, COUNT(work_tags.id), COUNT(works.screen_path)
     FROM tags
     LEFT JOIN work_tags ON tags.id == work_tags.tag_id
+    LEFT JOIN works ON works.id == work_tags.work_id
     GROUP BY tags.id;"#;
     let work_counts = conn
         .prepare(query)?
         .query_map((), |row| {
             let tag_id = TagId::wrap(row.get(0)?);
             let count = row.get(1)?;
-            Ok((tag_id, count))
+            let downloaded = row.get(2)?;
+            Ok((tag_id, count, downloaded))
         })?
         .flatten()
         .collect();
@@ -242,3 +1679,24 @@ pub fn count_works_per_tag(
 
     Ok(())
 }
+
+// For each watched tag, the works that arrived since the user last looked at it.
+pub fn count_new_works_per_watched_tag(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<(TagId, u64)>> {
+    let start = Instant::now();
+    let query = r#"
+    SELECT tags.id, COUNT(works.id) FROM tags
+        LEFT JOIN work_tags ON work_tags.tag_id = tags.id
+        LEFT JOIN works ON works.id = work_tags.work_id
+            AND works.created_at > COALESCE(tags.last_seen, 0)
+    WHERE tags.watched
+    GROUP BY tags.id
+"#;
+    let out = conn
+        .prepare(query)?
+        .query_map((), |row| Ok((TagId::wrap(row.get(0)?), row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    report_slow_query(start, "count_new_works_per_watched_tag", query);
+    Ok(out)
+}
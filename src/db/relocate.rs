@@ -0,0 +1,244 @@
+// Moving downloaded files between storage roots, and moving the whole data dir.
+//
+// Both run on the writer thread, so no download can record a path while we are rewriting them.
+// Downloads that are already in flight may still land in the old location; they will be picked
+// up the next time the rules are applied.
+use crate::shared::{
+    blob::move_file,
+    progress::{LogSender, ProgressSender},
+    storage::{DataKind, Storage, join_stored_path, split_stored_path},
+};
+use anyhow::{Context as _, Result, ensure};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RelocateReport {
+    pub moved: usize,
+    pub failed: usize,
+    // Set when the data dir was copied; takes effect on the next start.
+    pub new_data_dir: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+// Move every downloaded file that is not on the root the current rules want it on.
+pub fn apply_storage_rules(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<RelocateReport> {
+    let mut stmt =
+        conn.prepare("SELECT id, preview_path, screen_path, archive_path, model_path FROM works")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<usize, i64>(0)?,
+                [
+                    row.get::<usize, Option<String>>(1)?,
+                    row.get::<usize, Option<String>>(2)?,
+                    row.get::<usize, Option<String>>(3)?,
+                    row.get::<usize, Option<String>>(4)?,
+                ],
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Note: works can share files, e.g. when the preview and screen urls are the same, so copy
+    //       rather than move until the last reference to each file has been rewritten.
+    let mut references = HashMap::<String, usize>::new();
+    let mut moves = Vec::new();
+    let config = storage.config();
+    let cold_root = config
+        .cold_policy()
+        .is_active()
+        .then(|| config.cold_policy().root());
+    for (id, paths) in rows {
+        for (kind, stored) in DataKind::ALL.into_iter().zip(paths) {
+            let Some(stored) = stored else {
+                continue;
+            };
+            *references.entry(stored.clone()).or_default() += 1;
+            // Note: cold files come back when viewed, not when the rules change.
+            if cold_root.is_some() && split_stored_path(&stored).0 == cold_root {
+                continue;
+            }
+            if let Some(target) = storage.placement_for(kind, &stored) {
+                moves.push((id, kind, stored, target));
+            }
+        }
+    }
+    log.info(format!("Moving {} files to new storage roots", moves.len()));
+
+    let mut report = RelocateReport::default();
+    let total = moves.len();
+    for (i, (id, kind, stored, target)) in moves.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        let remaining = references.get_mut(&stored).map_or(0, |count| {
+            *count -= 1;
+            *count
+        });
+        if storage.exists(&stored).unwrap_or(false) {
+            if let Err(e) = storage.transfer(&stored, &target, remaining > 0) {
+                log.warn(format!("Failed to move {stored} to {target}: {e}"));
+                report.failed += 1;
+                continue;
+            }
+            report.moved += 1;
+        } else if !storage.exists(&target).unwrap_or(false) {
+            log.warn(format!("Missing file for work {id}: {stored}"));
+            report.failed += 1;
+            continue;
+        }
+        conn.execute(
+            &format!("UPDATE works SET {} = ? WHERE id = ?", kind.column()),
+            params![target, id],
+        )?;
+    }
+    progress.clear();
+    log.info(format!(
+        "Moved {} files; {} could not be moved",
+        report.moved, report.failed
+    ));
+    Ok(report)
+}
+
+// Thumbnails of works that were downloaded before their root was encrypted are still in the data
+// dir in the clear; seal them on their work's root.
+pub fn seal_clear_thumbnails(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    log: &mut LogSender,
+) -> Result<RelocateReport> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT thumb_path, screen_path FROM works
+            WHERE thumb_path IS NOT NULL AND screen_path IS NOT NULL",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut report = RelocateReport::default();
+    let mut seen = HashSet::new();
+    for (thumb, screen) in rows {
+        if split_stored_path(&thumb).0.is_some()
+            || !storage.is_encrypted(&screen)
+            || !seen.insert(thumb.clone())
+        {
+            continue;
+        }
+        let target = join_stored_path(split_stored_path(&screen).0, &thumb);
+        if storage.exists(&thumb)?
+            && let Err(e) = storage.transfer(&thumb, &target, false)
+        {
+            log.warn(format!("Failed to seal the thumbnail {thumb}: {e}"));
+            report.failed += 1;
+            continue;
+        }
+        // Note: works that share a screen url share the thumbnail.
+        conn.execute(
+            "UPDATE works SET thumb_path = ? WHERE thumb_path = ?",
+            params![target, thumb],
+        )?;
+        report.moved += 1;
+    }
+    if report.moved + report.failed > 0 {
+        log.info(format!(
+            "Sealed {} thumbnails of encrypted works; {} could not be sealed",
+            report.moved, report.failed
+        ));
+    }
+    Ok(report)
+}
+
+pub(crate) fn list_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(&entry.path(), out)?;
+        } else if file_type.is_file() {
+            out.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+// Copy the data dir, including the metadata DB, to a new location and point the storage config
+// at it. We copy rather than move so that nothing is lost if the copy is interrupted; the user
+// can delete the old data dir once they have restarted and checked that everything is there.
+pub fn move_data_dir(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    to: &Path,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<RelocateReport> {
+    let from = storage.data_dir();
+    ensure!(
+        to.is_absolute(),
+        "the new data directory must be an absolute path"
+    );
+    ensure!(
+        !to.starts_with(&from) && !from.starts_with(to),
+        "the new data directory cannot be inside the old one, or the other way around"
+    );
+    if to.exists() {
+        ensure!(
+            fs::read_dir(to)?.next().is_none(),
+            "{} is not empty",
+            to.display()
+        );
+    }
+    fs::create_dir_all(to).with_context(|| format!("creating {}", to.display()))?;
+
+    let mut files = Vec::new();
+    list_files(&from, &mut files)?;
+    // Note: the live DB can't be copied file-by-file; we snapshot it below instead.
+    files.retain(|path| {
+        path.parent() != Some(from.as_path())
+            || !path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("metadata.db"))
+    });
+    log.info(format!(
+        "Copying {} files from {} to {}",
+        files.len(),
+        from.display(),
+        to.display()
+    ));
+
+    let mut report = RelocateReport::default();
+    for (i, path) in files.iter().enumerate() {
+        progress.set_percent(i, files.len());
+        let rel = path.strip_prefix(&from)?;
+        move_file(path, &to.join(rel), true)
+            .with_context(|| format!("copying {}", path.display()))?;
+        report.moved += 1;
+    }
+
+    log.info("Copying the metadata database");
+    progress.set_spinner();
+    conn.execute("VACUUM INTO ?", [to.join("metadata.db").to_string_lossy()])?;
+
+    let mut config = storage.config();
+    config.move_data_dir(&from, to);
+    storage.set_config(config)?;
+    progress.clear();
+    log.info(format!(
+        "Data directory copied to {}; restart Artchiver to use it",
+        to.display()
+    ));
+    report.new_data_dir = Some(to.to_owned());
+    Ok(report)
+}
@@ -0,0 +1,236 @@
+// Integrity checking for downloaded files.
+//
+// Every file gets a SHA-256 when it is downloaded, or the first time the scrubber sees it for
+// files that predate hashing. Each scrub re-hashes the slice of files that were checked longest
+// ago, so that the whole collection is covered every SCRUB_CYCLE_WEEKS weekly runs without
+// ever having to read the entire archive in one go.
+//
+// Hashes are keyed by the root-relative path, so they follow files between storage roots.
+use crate::{
+    db::models::work::WorkId,
+    shared::{
+        progress::{LogSender, ProgressSender},
+        storage::{DataKind, Storage, relative_path_for_url, split_stored_path},
+    },
+};
+use anyhow::{Context as _, Result, bail};
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension as _, params};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::Path,
+};
+
+pub const SCRUB_CYCLE_WEEKS: usize = 13;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CorruptFile {
+    pub work_id: WorkId,
+    pub work_name: String,
+    pub kind: DataKind,
+    pub stored_path: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScrubReport {
+    pub checked: usize,
+    // Files we could not read this time, e.g. on a drive that is not plugged in.
+    pub skipped: usize,
+    // How many files have a known good hash, across all scrubs.
+    pub hashed: usize,
+    pub corrupt: Vec<CorruptFile>,
+    pub error: Option<String>,
+}
+
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut io::BufReader::new(fs::File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn record_file_hash(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    stored_path: &str,
+    sha256: &str,
+) -> Result<()> {
+    let (_, key) = split_stored_path(stored_path);
+    conn.execute(
+        r#"INSERT INTO file_hashes (path, sha256, checked_at, corrupt) VALUES (?, ?, ?, false)
+            ON CONFLICT(path) DO UPDATE SET
+                sha256 = excluded.sha256, checked_at = excluded.checked_at, corrupt = false"#,
+        params![key, sha256, Timestamp::now().as_millisecond()],
+    )?;
+    Ok(())
+}
+
+// Every downloaded file, by hash key; works that share a file share a key.
+fn downloaded_files(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<BTreeMap<String, String>> {
+    let mut stmt =
+        conn.prepare("SELECT preview_path, screen_path, archive_path, model_path FROM works")?;
+    let mut files = BTreeMap::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for i in 0..DataKind::ALL.len() {
+            if let Some(stored) = row.get::<usize, Option<String>>(i)? {
+                let (_, key) = split_stored_path(&stored);
+                files.insert(key.to_owned(), stored);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn corrupt_files(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<CorruptFile>> {
+    let corrupt = conn
+        .prepare("SELECT path FROM file_hashes WHERE corrupt")?
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    if corrupt.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, name, preview_path, screen_path, archive_path, model_path FROM works",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        for (i, kind) in DataKind::ALL.into_iter().enumerate() {
+            let Some(stored) = row.get::<usize, Option<String>>(i + 2)? else {
+                continue;
+            };
+            if corrupt.contains(split_stored_path(&stored).1) {
+                out.push(CorruptFile {
+                    work_id: WorkId::wrap(row.get(0)?),
+                    work_name: row.get(1)?,
+                    kind,
+                    stored_path: stored,
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+// Re-hash the files that were checked longest ago and flag any that have changed.
+pub fn scrub_files(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<ScrubReport> {
+    let checked_at = conn
+        .prepare("SELECT path, checked_at FROM file_hashes")?
+        .query_map([], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    let mut queue = downloaded_files(conn)?
+        .into_iter()
+        .map(|(key, stored)| (checked_at.get(&key).copied().unwrap_or(0), key, stored))
+        .collect::<Vec<_>>();
+    queue.sort();
+    queue.truncate(queue.len().div_ceil(SCRUB_CYCLE_WEEKS));
+    log.info(format!("Checking {} files for corruption", queue.len()));
+
+    let mut report = ScrubReport::default();
+    let total = queue.len();
+    for (i, (_, key, stored)) in queue.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        // Note: don't pull remote files down just to check them; the remote has its own
+        //       integrity checks, and we check the cached copy when it is next used.
+        let path = storage.resolve(Path::new(&stored));
+        if !storage.is_available(Path::new(&stored)) || !path.exists() {
+            report.skipped += 1;
+            continue;
+        }
+        let actual = match hash_file(&path) {
+            Ok(actual) => actual,
+            Err(e) => {
+                log.warn(format!("Failed to read {}: {e}", path.display()));
+                report.skipped += 1;
+                continue;
+            }
+        };
+        let expected: Option<String> = conn
+            .query_row(
+                "SELECT sha256 FROM file_hashes WHERE path = ?",
+                [&key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match expected {
+            Some(expected) if expected != actual => {
+                log.warn(format!("{} has changed on disk", path.display()));
+                conn.execute(
+                    "UPDATE file_hashes SET checked_at = ?, corrupt = true WHERE path = ?",
+                    params![Timestamp::now().as_millisecond(), key],
+                )?;
+            }
+            _ => record_file_hash(conn, &stored, &actual)?,
+        }
+        report.checked += 1;
+    }
+    progress.clear();
+
+    report.hashed = conn.query_one(
+        "SELECT COUNT(*) FROM file_hashes WHERE NOT corrupt",
+        [],
+        |row| row.get(0),
+    )?;
+    report.corrupt = corrupt_files(conn)?;
+    log.info(format!(
+        "Checked {} files; {} corrupt, {} skipped",
+        report.checked,
+        report.corrupt.len(),
+        report.skipped
+    ));
+    Ok(report)
+}
+
+// Replace a corrupt file with a fresh copy from the url it was originally downloaded from.
+pub fn repair_file(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    file: &CorruptFile,
+    log: &mut LogSender,
+) -> Result<()> {
+    let url: Option<String> = conn.query_one(
+        &format!("SELECT {} FROM works WHERE id = ?", file.kind.url_column()),
+        [file.work_id],
+        |row| row.get(0),
+    )?;
+    let Some(url) = url else {
+        bail!("{} has no url to download from", file.work_name);
+    };
+    let (_, key) = split_stored_path(&file.stored_path);
+    if relative_path_for_url(&url) != key {
+        bail!(
+            "{} was generated locally rather than downloaded; refresh the work's tag to rebuild it",
+            file.stored_path
+        );
+    }
+
+    log.info(format!("Re-downloading {url}"));
+    let path = storage.resolve(Path::new(&file.stored_path));
+    let partial = path.with_extension("partial");
+    {
+        let mut resp = ureq::get(&url).call()?;
+        let mut fp = io::BufWriter::new(
+            fs::File::create(&partial)
+                .with_context(|| format!("creating {}", partial.display()))?,
+        );
+        io::copy(&mut resp.body_mut().as_reader(), &mut fp)?;
+    }
+    let sha256 = hash_file(&partial)?;
+    fs::rename(&partial, &path)?;
+    storage.commit(&file.stored_path)?;
+    record_file_hash(conn, &file.stored_path, &sha256)?;
+    Ok(())
+}
@@ -1,22 +1,38 @@
 use crate::{
     db::{
+        migrate::{count_pending_migrations, run_migrations},
         model::DbCancellation,
         models::{
+            annotation::DbAnnotation,
+            log::DbLogLine,
             plugin::{DbPlugin, PluginId},
-            tag::TagId,
+            tag::{DbTag, TagId, TagKindMapping},
+            work::{DbWork, WorkId},
+        },
+        reader::{
+            DbReadHandle, PublicView, count_work_sources, get_tag, get_work, is_public_work,
+            list_all_tags, list_annotations, list_downloaded_paths_sample, list_link_check_sample,
+            list_plugin_logs, list_tag_kind_mappings, list_undownloaded_screen_urls,
+            list_work_mirrors, list_work_sources_page, list_works_by_screen_url,
+            list_works_with_tag_page, plugin_storage_usage,
         },
-        reader::DbReadHandle,
         writer::{DbBgWriter, DbWriteHandle},
     },
-    shared::{environment::Environment, progress::ProgressMonitor},
+    shared::{
+        encryption::unlocked_keys, environment::Environment, instance_lock::is_read_only,
+        progress::ProgressMonitor,
+    },
 };
-use anyhow::Result;
-use artchiver_sdk::ConfigValue;
+use anyhow::{Result, ensure};
+use artchiver_sdk::{ConfigValue, MediaRole};
 use crossbeam::channel;
 use log::{error, info};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
-use std::{collections::HashSet, thread};
+use rusqlite::{OpenFlags, params};
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+};
 
 pub fn connect_or_create(
     env: &Environment,
@@ -26,18 +42,30 @@ pub fn connect_or_create(
         "Opening Metadata DB at {}",
         env.metadata_file_path().display()
     );
-    let manager = SqliteConnectionManager::file(env.metadata_file_path())
-        .with_init(|conn| rusqlite::vtab::array::load_module(conn));
+    let read_only = is_read_only();
+    let mut manager = SqliteConnectionManager::file(env.metadata_file_path()).with_init(|conn| {
+        if let Some(keys) = unlocked_keys() {
+            keys.key_connection(conn)?;
+        }
+        rusqlite::vtab::array::load_module(conn)
+    });
+    if read_only {
+        manager =
+            manager.with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX);
+    }
     let pool = r2d2::Pool::builder().max_size(32).build(manager)?;
     let conn = pool.get()?;
     let cancel = DbCancellation::default();
     // FIXME: use library intrinsics to set these rather than `execute`
-    let params = [("journal_mode", "WAL", "wal")];
-    for (name, value, expect) in params {
-        info!("Configuring DB: {name} = {value}");
-        let result: String =
-            conn.query_one(&format!("PRAGMA {name} = {value};"), [], |row| row.get(0))?;
-        assert_eq!(result, expect, "failed to configure database");
+    // Note: the instance that holds the lock has already put the DB in WAL mode.
+    if !read_only {
+        let params = [("journal_mode", "WAL", "wal")];
+        for (name, value, expect) in params {
+            info!("Configuring DB: {name} = {value}");
+            let result: String =
+                conn.query_one(&format!("PRAGMA {name} = {value};"), [], |row| row.get(0))?;
+            assert_eq!(result, expect, "failed to configure database");
+        }
     }
     let params = [
         ("journal_size_limit", (64 * 1024 * 1024).to_string()),
@@ -54,38 +82,29 @@ pub fn connect_or_create(
         conn.execute(&format!("PRAGMA {name} = {value};"), [])?;
     }
 
-    // List all migrations that we've already run.
-    let finished_migrations = {
-        match conn.prepare("SELECT ordinal FROM migrations") {
-            Ok(mut stmt) => match stmt.query_map([], |row| row.get(0)) {
-                Ok(q) => q.flatten().collect::<Vec<i64>>(),
-                Err(_) => vec![],
-            },
-            Err(_) => vec![],
-        }
-    };
-
-    // Execute and record all migration statements
-    for (ordinal, migration) in crate::db::model::MIGRATIONS.iter().enumerate() {
-        if !finished_migrations.contains(&(ordinal as i64)) {
-            conn.execute(migration, ())?;
-            conn.execute("INSERT INTO migrations (ordinal) VALUES (?)", [ordinal])?;
-        }
-    }
-
     // Send writes to a background thread.
     let (tx_to_writer, rx_writer_from_app) = channel::unbounded();
-    let mut writer = DbBgWriter::new(
-        pool.clone(),
-        cancel.clone(),
-        rx_writer_from_app,
-        progress_mon.monitor_channel(),
-    );
-    let writer_handle = thread::spawn(move || {
-        while let Err(e) = writer.main() {
-            error!("Error in DB writer thread: {e}\n{}", e.backtrace());
-        }
-    });
+    let writer_handle = if read_only {
+        ensure!(
+            count_pending_migrations(&conn) == 0,
+            "the database needs upgrading, which can't be done read-only"
+        );
+        thread::spawn(move || DbBgWriter::discard(&rx_writer_from_app))
+    } else {
+        run_migrations(&conn)?;
+        let mut writer = DbBgWriter::new(
+            pool.clone(),
+            env.storage().clone(),
+            cancel.clone(),
+            rx_writer_from_app,
+            progress_mon.monitor_channel(),
+        );
+        thread::spawn(move || {
+            while let Err(e) = writer.main() {
+                error!("Error in DB writer thread: {e}\n{}", e.backtrace());
+            }
+        })
+    };
     let db_writer = DbWriteHandle::new(tx_to_writer);
 
     let reader_pool = rayon::ThreadPoolBuilder::new()
@@ -157,6 +176,110 @@ impl DbSyncHandle {
             .collect())
     }
 
+    // How many works each plugin that has this tag says it has for it.
+    pub fn sync_list_presumed_work_counts(&self, tag_id: TagId) -> Result<Vec<(PluginId, u64)>> {
+        let conn = self.pool.get()?;
+        let query = r#"SELECT plugin_id AS id, COALESCE(presumed_work_count, 0)
+            FROM plugin_tags
+            WHERE tag_id = ?"#;
+        Ok(conn
+            .prepare(query)?
+            .query_map([tag_id], |row| Ok((PluginId::from_row(row)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    pub fn sync_list_plugin_logs(
+        &self,
+        plugin_id: PluginId,
+        limit: usize,
+    ) -> Result<Vec<DbLogLine>> {
+        list_plugin_logs(&self.pool.get()?, plugin_id, limit)
+    }
+
+    pub fn sync_list_tag_kind_mappings(&self, plugin_id: PluginId) -> Result<Vec<TagKindMapping>> {
+        list_tag_kind_mappings(&self.pool.get()?, plugin_id)
+    }
+
+    // TAGS ///////////////////////////////////////
+    pub fn sync_list_tags(&self) -> Result<Vec<DbTag>> {
+        list_all_tags(&self.pool.get()?)
+    }
+
+    pub fn sync_get_tag(&self, tag_id: TagId) -> Result<Option<DbTag>> {
+        get_tag(&self.pool.get()?, tag_id)
+    }
+
+    // WORKS ///////////////////////////////////////
+    pub fn sync_list_works_for_tag(
+        &self,
+        tag_id: TagId,
+        after: WorkId,
+        limit: usize,
+        public: Option<&PublicView>,
+    ) -> Result<Vec<DbWork>> {
+        list_works_with_tag_page(&self.pool.get()?, tag_id, after, limit, public)
+    }
+
+    pub fn sync_get_work(&self, work_id: WorkId) -> Result<Option<DbWork>> {
+        get_work(&self.pool.get()?, work_id)
+    }
+
+    pub fn sync_is_public_work(&self, work_id: WorkId, view: &PublicView) -> Result<bool> {
+        is_public_work(&self.pool.get()?, work_id, view)
+    }
+
+    pub fn sync_list_annotations(&self, screen_url: &str) -> Result<Vec<DbAnnotation>> {
+        list_annotations(&self.pool.get()?, screen_url)
+    }
+
+    pub fn sync_list_works_by_screen_url(&self, screen_urls: &[String]) -> Result<Vec<DbWork>> {
+        list_works_by_screen_url(&self.pool.get()?, screen_urls)
+    }
+
+    pub fn sync_list_work_mirrors(
+        &self,
+        screen_urls: &[String],
+    ) -> Result<HashMap<String, Vec<(MediaRole, String)>>> {
+        list_work_mirrors(&self.pool.get()?, screen_urls)
+    }
+
+    pub fn sync_list_undownloaded_screen_urls(&self, tag_id: TagId) -> Result<Vec<String>> {
+        list_undownloaded_screen_urls(&self.pool.get()?, tag_id)
+    }
+
+    pub fn sync_list_downloaded_paths_sample(
+        &self,
+        plugin_id: PluginId,
+        limit: usize,
+    ) -> Result<Vec<(Option<String>, String)>> {
+        list_downloaded_paths_sample(&self.pool.get()?, plugin_id, limit)
+    }
+
+    pub fn sync_plugin_storage_usage(&self, plugin_id: PluginId) -> Result<u64> {
+        plugin_storage_usage(&self.pool.get()?, plugin_id)
+    }
+
+    pub fn sync_list_link_check_sample(
+        &self,
+        plugin_id: PluginId,
+        limit: usize,
+    ) -> Result<Vec<(String, Option<String>)>> {
+        list_link_check_sample(&self.pool.get()?, plugin_id, limit)
+    }
+
+    pub fn sync_count_work_sources(&self, plugin_id: PluginId) -> Result<usize> {
+        count_work_sources(&self.pool.get()?, plugin_id)
+    }
+
+    pub fn sync_list_work_sources(
+        &self,
+        plugin_id: PluginId,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(String, String)>> {
+        list_work_sources_page(&self.pool.get()?, plugin_id, after, limit)
+    }
+
     // CONFIGURATION ///////////////////////////////////////
     pub fn sync_save_configurations(
         &self,
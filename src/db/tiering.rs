@@ -0,0 +1,312 @@
+// Cold storage tiering: full size files for works that have not been viewed in a while are
+// moved to the cold root named by the ColdPolicy, and brought back when next viewed.
+//
+// A file's tier is recorded in cold_files; the works path column is rewritten to point at the
+// cold copy, so that anything that can reach the cold root (e.g. the drive is plugged in) can
+// still open it without waiting for it to come back.
+use crate::{
+    db::{models::work::WorkId, relocate::RelocateReport},
+    shared::{
+        progress::{HostUpdateSender, LogSender, ProgressSender},
+        storage::{DataKind, Storage, join_stored_path, split_stored_path},
+    },
+};
+use anyhow::Result;
+use itertools::Itertools as _;
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, params};
+use std::collections::BTreeMap;
+
+// A work's stored files, and whether the policy says that it has gone cold.
+struct WorkFiles {
+    id: WorkId,
+    screen_url: String,
+    cold: bool,
+    paths: Vec<(DataKind, Option<String>)>,
+}
+
+// Note: a work that has never been downloaded or viewed has no last_accessed, and so counts as
+//       cold; it rarely has any files to move anyway.
+fn list_work_files(
+    conn: &Connection,
+    cutoff: Timestamp,
+    keep_favorites: bool,
+) -> Result<Vec<WorkFiles>> {
+    let columns = DataKind::ALL.iter().map(|kind| kind.column()).join(", ");
+    let mut stmt = conn.prepare(&format!(
+        r#"SELECT id, screen_url,
+                COALESCE(last_accessed, 0) < ? AND (NOT favorite OR NOT ?) AS cold, {columns}
+            FROM works"#
+    ))?;
+    let rows = stmt
+        .query_map(params![cutoff.as_millisecond(), keep_favorites], |row| {
+            Ok(WorkFiles {
+                id: WorkId::wrap(row.get("id")?),
+                screen_url: row.get("screen_url")?,
+                cold: row.get("cold")?,
+                paths: DataKind::ALL
+                    .into_iter()
+                    .map(|kind| Ok((kind, row.get(kind.column())?)))
+                    .collect::<rusqlite::Result<_>>()?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+// Move the files of any works that the policy says are cold to the cold root.
+pub fn offload_cold_files(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<RelocateReport> {
+    let config = storage.config();
+    let policy = config.cold_policy();
+    let mut report = RelocateReport::default();
+    if !policy.is_active() {
+        return Ok(report);
+    }
+
+    let now = Timestamp::now();
+    let works = list_work_files(conn, policy.cutoff(now), policy.keep_favorites())?;
+
+    // Note: works can share files, e.g. when the screen and archive urls are the same, so we
+    //       move each file once and rewrite every reference to it. A file can only go if
+    //       all of those references are cold, and none of them is a preview, which has to stay
+    //       where the gallery can reach it.
+    let mut references = BTreeMap::<String, (bool, Vec<(WorkId, String, DataKind)>)>::new();
+    for work in works {
+        for (kind, stored) in work.paths {
+            let Some(stored) = stored else {
+                continue;
+            };
+            let (movable, refs) = references.entry(stored).or_insert((true, Vec::new()));
+            *movable &= work.cold && kind != DataKind::Preview;
+            refs.push((work.id, work.screen_url.clone(), kind));
+        }
+    }
+    let moves = references
+        .into_iter()
+        .filter(|(stored, (movable, _))| {
+            *movable && split_stored_path(stored).0 != Some(policy.root())
+        })
+        .map(|(stored, (_, refs))| (stored, refs))
+        .collect::<Vec<_>>();
+    if moves.is_empty() {
+        return Ok(report);
+    }
+    log.info(format!("Moving {} files to cold storage", moves.len()));
+
+    let total = moves.len();
+    for (i, (stored, refs)) in moves.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        let (_, rel) = split_stored_path(&stored);
+        let target = join_stored_path(Some(policy.root()), rel);
+        if let Err(e) = storage.transfer(&stored, &target, false) {
+            log.warn(format!("Failed to move {stored} to cold storage: {e}"));
+            report.failed += 1;
+            continue;
+        }
+        for (id, screen_url, kind) in refs {
+            conn.execute(
+                &format!("UPDATE works SET {} = ? WHERE id = ?", kind.column()),
+                params![target, id],
+            )?;
+            conn.execute(
+                "INSERT OR REPLACE INTO cold_files (screen_url, kind, cold_path, offloaded_at) VALUES (?, ?, ?, ?)",
+                params![screen_url, kind.column(), target, now.as_millisecond()],
+            )?;
+        }
+        report.moved += 1;
+    }
+    progress.clear();
+    log.info(format!(
+        "Moved {} files to cold storage; {} could not be moved",
+        report.moved, report.failed
+    ));
+    Ok(report)
+}
+
+// Record that the user looked at the work, and bring back anything that was in cold storage.
+pub fn note_work_viewed(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    work_id: WorkId,
+    log: &mut LogSender,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE works SET last_accessed = ? WHERE id = ?",
+        params![Timestamp::now().as_millisecond(), work_id],
+    )?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT DISTINCT cold_files.cold_path FROM cold_files
+            JOIN works ON works.screen_url = cold_files.screen_url
+            WHERE works.id = ?"#,
+    )?;
+    // Note: the work's own columns can share a file, too; it only has to come back once.
+    let cold = stmt
+        .query_map([work_id], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if cold.is_empty() {
+        return Ok(());
+    }
+
+    // Note: other works may share the file; they all point at the one copy, so follow it back.
+    let mut viewed = vec![work_id];
+    for cold_path in cold {
+        let refs = conn
+            .prepare(
+                r#"SELECT works.id, cold_files.kind FROM cold_files
+                    JOIN works ON works.screen_url = cold_files.screen_url
+                    WHERE cold_files.cold_path = ?"#,
+            )?
+            .query_map([&cold_path], |row| {
+                Ok((
+                    WorkId::wrap(row.get::<usize, i64>(0)?),
+                    row.get::<usize, String>(1)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let refs = refs
+            .into_iter()
+            .filter_map(|(id, column)| DataKind::from_column(&column).map(|kind| (id, kind)))
+            .collect::<Vec<_>>();
+        let Some((_, kind)) = refs.iter().find(|(id, _)| *id == work_id) else {
+            continue;
+        };
+        let (_, rel) = split_stored_path(&cold_path);
+        let hot_path = join_stored_path(storage.config().rule(*kind), rel);
+        // Note: the cold root is frequently a drive that isn't plugged in; leave the file where
+        //       it is and try again on the next view.
+        if let Err(e) = storage.transfer(&cold_path, &hot_path, false) {
+            log.warn(format!(
+                "Failed to bring {cold_path} back from cold storage: {e}"
+            ));
+            continue;
+        }
+        for (id, kind) in refs {
+            conn.execute(
+                &format!("UPDATE works SET {} = ? WHERE id = ?", kind.column()),
+                params![hot_path, id],
+            )?;
+            if !viewed.contains(&id) {
+                viewed.push(id);
+            }
+        }
+        conn.execute(
+            "DELETE FROM cold_files WHERE cold_path = ?",
+            params![cold_path],
+        )?;
+    }
+
+    for work_id in viewed {
+        note_work_paths(conn, work_id, host)?;
+    }
+    Ok(())
+}
+
+// A refreshed work gets a new row with no paths, and is downloaded again. Once it has fresh
+// files, the cold copies are stale, so drop them.
+pub fn forget_cold_files(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    screen_url: &str,
+    log: &mut LogSender,
+) -> Result<()> {
+    let cold = conn
+        .prepare("SELECT cold_path FROM cold_files WHERE screen_url = ?")?
+        .query_map([screen_url], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for cold_path in cold {
+        // Note: the file may be shared with another work that is still cold.
+        let in_use: bool = conn.query_one(
+            "SELECT EXISTS(SELECT 1 FROM cold_files WHERE cold_path = ? AND screen_url != ?)",
+            params![cold_path, screen_url],
+            |row| row.get(0),
+        )?;
+        if !in_use && let Err(e) = storage.remove(&cold_path) {
+            log.warn(format!("Failed to remove stale cold copy {cold_path}: {e}"));
+        }
+    }
+    conn.execute(
+        "DELETE FROM cold_files WHERE screen_url = ?",
+        params![screen_url],
+    )?;
+    Ok(())
+}
+
+// Tell the UX where the work's files are now.
+fn note_work_paths(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    work_id: WorkId,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let (preview_path, screen_path, archive_path, thumb_path, aspect_ratio) = conn.query_one(
+        "SELECT preview_path, screen_path, archive_path, thumb_path, aspect_ratio
+        FROM works WHERE id = ?",
+        [work_id],
+        |row| {
+            Ok((
+                row.get::<usize, Option<String>>(0)?,
+                row.get::<usize, Option<String>>(1)?,
+                row.get::<usize, Option<String>>(2)?,
+                row.get::<usize, Option<String>>(3)?,
+                row.get::<usize, Option<f32>>(4)?,
+            ))
+        },
+    )?;
+    if let Some(preview_path) = preview_path {
+        host.note_completed_download(
+            work_id,
+            &preview_path,
+            (
+                screen_path.as_deref(),
+                archive_path.as_deref(),
+                thumb_path.as_deref(),
+            ),
+            aspect_ratio,
+        )?;
+    }
+    let model_path = conn.query_one(
+        "SELECT model_path FROM works WHERE id = ?",
+        [work_id],
+        |row| row.get::<usize, Option<String>>(0),
+    )?;
+    if let Some(model_path) = model_path {
+        host.note_work_model_downloaded(work_id, &model_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::model::MIGRATIONS;
+
+    #[test]
+    fn test_never_accessed_work_is_cold() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        for migration in MIGRATIONS {
+            conn.execute(migration, ())?;
+        }
+        conn.execute(
+            r#"INSERT INTO works (name, artist_id, preview_url, screen_url, screen_path, last_accessed)
+                VALUES ('fresh', 0, 'p1', 's1', 'a:s1', NULL), ('viewed', 0, 'p2', 's2', 'a:s2', ?)"#,
+            [Timestamp::now().as_millisecond()],
+        )?;
+
+        let cutoff = Timestamp::now() - jiff::SignedDuration::from_hours(24);
+        let works = list_work_files(&conn, cutoff, true)?;
+        let cold = works
+            .iter()
+            .map(|work| (work.screen_url.as_str(), work.cold))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(cold, BTreeMap::from([("s1", true), ("s2", false)]));
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+// The trash: files taken off of works, e.g. by a quota purge, are moved into the data dir's
+// trash (or, for encrypted roots, a trash on the root, so that they stay sealed) rather than
+// deleted, and only deleted for good once they have been there for longer than the retention
+// period set in the Trash view. Until then, restoring a work puts its files back where they were.
+//
+// Each path column of each work gets a row in trashed_files; works that shared a file share the
+// one copy in the trash, and the first of them to be restored takes it back.
+use crate::{
+    db::models::trash::TrashedWork,
+    shared::{
+        progress::{LogSender, ProgressSender},
+        storage::{Storage, join_stored_path, relative_path_for_url, split_stored_path},
+    },
+};
+use anyhow::Result;
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::collections::BTreeSet;
+
+pub const TRASH_DIR: &str = "trash";
+
+// The path columns of works that hold files we downloaded, and so may be trashed.
+pub const PATH_COLUMNS: [&str; 5] = [
+    "preview_path",
+    "screen_path",
+    "archive_path",
+    "thumb_path",
+    "model_path",
+];
+
+// Where a stored file goes in the trash: in the data dir, unless it is on an encrypted root.
+pub fn trash_path_for(storage: &Storage, stored: &str) -> String {
+    let trash = format!("{TRASH_DIR}/{}", relative_path_for_url(stored));
+    if storage.is_encrypted(stored) {
+        join_stored_path(split_stored_path(stored).0, &trash)
+    } else {
+        trash
+    }
+}
+
+fn is_work_file(conn: &PooledConnection<SqliteConnectionManager>, stored: &str) -> Result<bool> {
+    Ok(conn.query_one(
+        r#"SELECT EXISTS(SELECT 1 FROM works WHERE preview_path = ?1 OR screen_path = ?1
+            OR archive_path = ?1 OR thumb_path = ?1 OR model_path = ?1)"#,
+        [stored],
+        |row| row.get::<usize, bool>(0),
+    )?)
+}
+
+// Take the files off of the work, recording where each of them was. The files themselves stay
+// where they are until `move_to_trash`, as other works may still be using them.
+pub fn trash_work_files(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    screen_url: &str,
+    files: &[(&str, String)],
+    file_size: Option<i64>,
+    reason: &str,
+) -> Result<()> {
+    let now = Timestamp::now().as_millisecond();
+    for (column, stored) in files {
+        conn.execute(
+            r#"INSERT INTO trashed_files
+                (screen_url, kind, original_path, trash_path, file_size, reason, trashed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+            params![
+                screen_url,
+                column,
+                stored,
+                trash_path_for(storage, stored),
+                (*column == "screen_path").then_some(file_size).flatten(),
+                reason,
+                now
+            ],
+        )?;
+    }
+    conn.execute(
+        &format!(
+            "UPDATE works SET {}, file_size = NULL WHERE screen_url = ?",
+            PATH_COLUMNS
+                .map(|column| format!("{column} = NULL"))
+                .join(", ")
+        ),
+        [screen_url],
+    )?;
+    conn.execute("DELETE FROM cold_files WHERE screen_url = ?", [screen_url])?;
+    Ok(())
+}
+
+// Move the files that no work uses any more into the trash. Returns how many were moved and how
+// many could not be.
+pub fn move_to_trash(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    files: BTreeSet<String>,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<(usize, usize)> {
+    let (mut moved, mut failed) = (0, 0);
+    let total = files.len();
+    for (i, stored) in files.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        if is_work_file(conn, &stored)? {
+            continue;
+        }
+        if let Err(e) = storage.transfer(&stored, &trash_path_for(storage, &stored), false) {
+            log.warn(format!("Failed to move {stored} to the trash: {e}"));
+            failed += 1;
+            continue;
+        }
+        moved += 1;
+    }
+    progress.clear();
+    Ok((moved, failed))
+}
+
+// Put the works' files back where they were. Returns how many works got all of their files back;
+// any that didn't keep the rest in the trash, to try again.
+pub fn restore_trashed_works(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    screen_urls: &[String],
+    log: &mut LogSender,
+) -> Result<usize> {
+    let mut restored = 0;
+    for screen_url in screen_urls {
+        let mut stmt = conn.prepare(
+            r#"SELECT id, kind, original_path, trash_path, file_size FROM trashed_files
+            WHERE screen_url = ?"#,
+        )?;
+        let rows = stmt
+            .query_map([screen_url], |row| {
+                Ok((
+                    row.get::<usize, i64>(0)?,
+                    row.get::<usize, String>(1)?,
+                    row.get::<usize, String>(2)?,
+                    row.get::<usize, String>(3)?,
+                    row.get::<usize, Option<i64>>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut complete = true;
+        for (id, column, original, trash, file_size) in rows {
+            let Some(column) = PATH_COLUMNS.iter().find(|c| **c == column) else {
+                continue;
+            };
+            // Note: a work that shared the file may have taken it back already.
+            if !storage.exists(&original).unwrap_or(false)
+                && let Err(e) = storage.transfer(&trash, &original, false)
+            {
+                log.warn(format!("Failed to restore {original} from the trash: {e}"));
+                complete = false;
+                continue;
+            }
+            conn.execute(
+                &format!(
+                    "UPDATE works SET {column} = ?, file_size = COALESCE(?, file_size)
+                    WHERE screen_url = ?"
+                ),
+                params![original, file_size, screen_url],
+            )?;
+            conn.execute("DELETE FROM trashed_files WHERE id = ?", [id])?;
+        }
+        if complete {
+            restored += 1;
+        }
+    }
+    log.info(format!("Restored {restored} works from the trash"));
+    Ok(restored)
+}
+
+// Delete the files that went into the trash before `cutoff`, for good. Returns how many files
+// were deleted.
+pub fn empty_trash(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    storage: &Storage,
+    cutoff: Timestamp,
+    log: &mut LogSender,
+    progress: &mut ProgressSender,
+) -> Result<usize> {
+    let mut stmt =
+        conn.prepare("SELECT original_path, trash_path FROM trashed_files WHERE trashed_at < ?")?;
+    let expired = stmt
+        .query_map([cutoff.as_millisecond()], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<BTreeSet<_>>>()?;
+    if expired.is_empty() {
+        return Ok(0);
+    }
+    conn.execute(
+        "DELETE FROM trashed_files WHERE trashed_at < ?",
+        [cutoff.as_millisecond()],
+    )?;
+
+    let mut deleted = 0;
+    let total = expired.len();
+    for (i, (original, trash)) in expired.into_iter().enumerate() {
+        progress.set_percent(i, total);
+        // Note: a work trashed later may still want the same file back.
+        let wanted = conn.query_one(
+            "SELECT EXISTS(SELECT 1 FROM trashed_files WHERE trash_path = ?)",
+            [&trash],
+            |row| row.get::<usize, bool>(0),
+        )?;
+        if wanted {
+            continue;
+        }
+        match storage.remove(&trash) {
+            Ok(()) => deleted += 1,
+            Err(e) => log.warn(format!("Failed to delete {trash} from the trash: {e}")),
+        }
+        if !is_work_file(conn, &original)? {
+            let (_, rel) = split_stored_path(&original);
+            conn.execute("DELETE FROM file_hashes WHERE path = ?", [rel])?;
+        }
+    }
+    progress.clear();
+    log.info(format!("Deleted {deleted} files from the trash"));
+    Ok(deleted)
+}
+
+pub fn list_trash(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<TrashedWork>> {
+    let query = r#"
+    SELECT
+        t.screen_url, works.name, MAX(t.reason), COUNT(*), COALESCE(SUM(t.file_size), 0),
+        MAX(t.trashed_at) AS trashed_at
+    FROM trashed_files AS t
+        JOIN works ON works.screen_url = t.screen_url
+    GROUP BY t.screen_url
+    ORDER BY trashed_at DESC, works.name
+"#;
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        out.push(TrashedWork {
+            screen_url: row.get(0)?,
+            work_name: row.get(1)?,
+            reason: row.get(2)?,
+            files: row.get(3)?,
+            bytes: u64::try_from(row.get::<usize, i64>(4)?).unwrap_or_default(),
+            trashed_at: Timestamp::from_millisecond(row.get(5)?)?,
+        });
+    }
+    Ok(out)
+}
@@ -0,0 +1,132 @@
+// Storing what Getty told us about the terms that tags stand for; see shared::vocabulary for the
+// asking.
+//
+// Aliases and implications are derived from the terms afresh after every fetch, and implied tags
+// are applied to work_tags again after every upsert, as for enrichments.
+use crate::shared::vocabulary::{SOURCE, VocabularyTerm};
+use anyhow::Result;
+use artchiver_sdk::Authority;
+use jiff::Timestamp;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, params};
+
+// How far up the AAT hierarchy to follow broader terms; it is about a dozen deep at its deepest.
+const MAX_DEPTH: usize = 32;
+
+pub fn save_vocabulary_terms(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    terms: &[VocabularyTerm],
+) -> Result<(usize, usize)> {
+    let now = Timestamp::now().as_millisecond();
+    let derived;
+    let xaction = conn.transaction()?;
+    {
+        let mut insert_term = xaction.prepare(
+            r#"INSERT OR REPLACE INTO vocabulary_terms
+                (authority, value, label, alt_labels, broader, fetched_at)
+            VALUES (?, ?, ?, ?, ?, ?)"#,
+        )?;
+        for term in terms {
+            insert_term.execute(params![
+                term.authority.to_string(),
+                term.value,
+                term.label,
+                serde_json::to_string(&term.alt_labels)?,
+                term.broader,
+                now
+            ])?;
+        }
+        derived = derive_tag_vocabulary(&xaction)?;
+        apply_tag_implications(&xaction)?;
+    }
+    xaction.commit()?;
+    Ok(derived)
+}
+
+// Rebuild the aliases and implications that came from Getty. Returns how many of each there are.
+pub fn derive_tag_vocabulary(conn: &Connection) -> Result<(usize, usize)> {
+    conn.execute("DELETE FROM tag_aliases WHERE source = ?", params![SOURCE])?;
+    conn.execute(
+        "DELETE FROM tag_implications WHERE source = ?",
+        params![SOURCE],
+    )?;
+
+    // Aliases: every name the term has that the tag doesn't already go by.
+    let mut aliases = 0;
+    {
+        let mut select_names = conn.prepare(
+            r#"SELECT tags.id, tags.name, v.label, v.alt_labels FROM tag_external_ids AS x
+                JOIN tags ON tags.id = x.tag_id
+                JOIN vocabulary_terms AS v ON v.authority = x.authority AND v.value = x.value"#,
+        )?;
+        let mut insert_alias = conn.prepare(
+            "INSERT OR IGNORE INTO tag_aliases (alias, tag_id, source) VALUES (?, ?, ?)",
+        )?;
+        let rows = select_names
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (tag_id, name, label, alt_labels) in rows {
+            let alt_labels = serde_json::from_str::<Vec<String>>(&alt_labels).unwrap_or_default();
+            for alias in std::iter::once(label).chain(alt_labels) {
+                if !alias.eq_ignore_ascii_case(&name) {
+                    aliases += insert_alias.execute(params![alias, tag_id, SOURCE])?;
+                }
+            }
+        }
+    }
+
+    // Implications: every broader AAT term above the tag's, that is itself a tag, by id or name.
+    let implications = conn.execute(
+        &format!(
+            r#"WITH RECURSIVE ancestors (tag_id, value, depth) AS (
+                SELECT x.tag_id, v.broader, 1 FROM tag_external_ids AS x
+                    JOIN vocabulary_terms AS v ON v.authority = x.authority AND v.value = x.value
+                WHERE x.authority = '{aat}' AND v.broader IS NOT NULL
+                UNION
+                SELECT a.tag_id, v.broader, a.depth + 1 FROM ancestors AS a
+                    JOIN vocabulary_terms AS v ON v.authority = '{aat}' AND v.value = a.value
+                WHERE v.broader IS NOT NULL AND a.depth < {MAX_DEPTH}
+            )
+            INSERT OR IGNORE INTO tag_implications (tag_id, implied_tag_id, source)
+            SELECT DISTINCT a.tag_id, tags.id, ? FROM ancestors AS a
+                LEFT JOIN vocabulary_terms AS v ON v.authority = '{aat}' AND v.value = a.value
+                JOIN tags ON tags.id IN (
+                        SELECT tag_id FROM tag_external_ids
+                        WHERE authority = '{aat}' AND value = a.value
+                    )
+                    OR lower(tags.name) = lower(v.label)
+            WHERE tags.id != a.tag_id"#,
+            aat = Authority::Aat
+        ),
+        params![SOURCE],
+    )?;
+    Ok((aliases, implications))
+}
+
+// Tag works with what their tags imply. Tags the user took off of a work stay off.
+//
+// Note: implications already reach all the way up the hierarchy, so one pass is enough.
+pub fn apply_tag_implications(conn: &Connection) -> Result<()> {
+    conn.execute(
+        r#"INSERT OR IGNORE INTO work_tags (tag_id, work_id)
+        SELECT i.implied_tag_id, work_tags.work_id FROM tag_implications AS i
+            JOIN work_tags ON work_tags.tag_id = i.tag_id
+            JOIN works ON works.id = work_tags.work_id
+            JOIN tags AS implied ON implied.id = i.implied_tag_id
+        WHERE NOT EXISTS (
+            SELECT 1 FROM tag_edits
+            WHERE tag_edits.screen_url = works.screen_url AND tag_edits.tag = implied.name
+                AND tag_edits.added = 0
+        )"#,
+        [],
+    )?;
+    Ok(())
+}
@@ -1,44 +1,181 @@
 use crate::{
     db::{
+        enrichment::{apply_enriched_tags, remove_enrichments, save_enrichments},
+        hydrus_import::{
+            HYDRUS_IMPORT_TAG, find_existing, mark_favorite, merge_into_existing, read_hydrus,
+            source_note,
+        },
+        local_import::{
+            LOCAL_IMPORT_TAG, LocalFile, copy_local_file, copy_local_files, download_url_file,
+            local_plugin_id,
+        },
+        maintenance::{OptimizeReport, optimize_database},
+        metadata_sync::{SyncReport, sync_user_metadata},
         model::{DbCancellation, string_to_rarray},
-        models::{plugin::PluginId, tag::TagId, work::WorkId},
+        models::{
+            annotation::AnnotationId,
+            collection::DbCollection,
+            detection::SuggestionId,
+            exhibition::ExhibitionItem,
+            link_check::LinkCheck,
+            log::DbLogLine,
+            plugin::PluginId,
+            tag::{TagId, TagKindMapping},
+            work::{DisplayTransform, WorkChange, WorkId},
+        },
+        purge::{PurgeReport, purge_plugin_works},
+        reader::{list_annotations, list_lost_links, list_tag_kind_mappings, lookup_transcript},
+        relocate::{RelocateReport, apply_storage_rules, move_data_dir, seal_clear_thumbnails},
+        scrub::{CorruptFile, ScrubReport, record_file_hash, repair_file, scrub_files},
+        tiering::{forget_cold_files, note_work_viewed, offload_cold_files},
+        trash::{empty_trash, list_trash, restore_trashed_works},
+        vocabulary::{apply_tag_implications, derive_tag_vocabulary, save_vocabulary_terms},
     },
+    plugin::thumbnail::media_type_of,
     shared::{
+        detection::Detection,
+        enrichment::EnrichedWork,
+        manifest::{ManifestSource, ManifestWork},
         progress::{HostUpdateSender, LogSender, ProgressSender, UpdateSource},
+        storage::Storage,
+        transcript::{Cue, TranscriptSource},
         update::DataUpdate,
+        vocabulary::VocabularyTerm,
     },
 };
 use anyhow::{Result, ensure};
-use artchiver_sdk::{Tag, Work};
+use artchiver_sdk::{Authority, Group, History, MediaRole, Tag, TagKind, Work, WorkTagEdit};
 use crossbeam::channel::{Receiver, Sender};
-use log::error;
+use jiff::Timestamp;
+use log::{debug, error};
+use parking_lot::Mutex;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::{
+    Connection, OptionalExtension as _, Statement, ToSql, params, params_from_iter,
+    types::{ToSqlOutput, Value},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 pub enum DbWriterRequest {
     UpsertTags {
         plugin_id: PluginId,
         tags: Vec<Tag>,
     },
+    SetTagKindMapping {
+        plugin_id: PluginId,
+        source_type: String,
+        kind: TagKind,
+    },
     UpsertWorks {
         plugin_id: PluginId,
         for_tag: String,
         works: Vec<Work>,
     },
+    UpsertGroups {
+        plugin_id: PluginId,
+        groups: Vec<Group>,
+    },
+    ImportLocalFiles {
+        paths: Vec<PathBuf>,
+    },
+    ImportHydrus {
+        path: PathBuf,
+    },
+    ImportManifest {
+        tag: String,
+        works: Vec<ManifestWork>,
+    },
     SetWorkDownloadPaths {
         screen_url: String,
         preview_path: String,
-        screen_path: String,
+        screen_path: Option<String>,
         archive_path: Option<String>,
+        thumb_path: Option<String>,
+        aspect_ratio: Option<f32>,
     },
-    SetWorkFavorite {
+    SetWorkThumbPath {
         work_id: WorkId,
-        favorite: bool,
+        thumb_path: String,
+        aspect_ratio: f32,
+    },
+    AddWorkVariant {
+        screen_url: String,
+        stored_path: String,
+    },
+    SetWorkNote {
+        screen_url: String,
+        body: String,
+    },
+    AddAnnotation {
+        screen_url: String,
+        label: String,
+        // x, y, width, height
+        region: [f32; 4],
+    },
+    DeleteAnnotation {
+        screen_url: String,
+        id: AnnotationId,
+    },
+    SetNsfwScore {
+        screen_url: String,
+        score: f32,
+    },
+    SetTranscript {
+        screen_url: String,
+        source: TranscriptSource,
+        cues: Vec<Cue>,
+    },
+    SetModelPath {
+        screen_url: String,
+        model_path: String,
+    },
+    SaveDetections {
+        screen_url: String,
+        detections: Vec<Detection>,
+        // The tag, and why we suggest it.
+        suggestions: Vec<(String, String)>,
+    },
+    ReviewTagSuggestions {
+        ids: Vec<SuggestionId>,
+        accept: bool,
+    },
+    SaveEnrichments {
+        works: Vec<EnrichedWork>,
+    },
+    RemoveEnrichments,
+    SaveVocabularyTerms {
+        terms: Vec<VocabularyTerm>,
     },
-    SetWorkHidden {
+    RemoveWorkTag {
+        screen_url: String,
+        tag: String,
+    },
+    MarkTagEditsPushed {
+        screen_url: String,
+        edit: WorkTagEdit,
+    },
+    SetWorkFlags {
+        flags: Vec<PendingFlag>,
+    },
+    ReviewInboxWorks {
+        screen_urls: Vec<String>,
+    },
+    SetDisplayTransform {
+        screen_url: String,
+        transform: DisplayTransform,
+    },
+    ClearInbox,
+    SetWorkRating {
         work_id: WorkId,
-        hidden: bool,
+        rating: u8,
     },
     SetTagFavorite {
         tag_id: TagId,
@@ -48,31 +185,170 @@ pub enum DbWriterRequest {
         tag_id: TagId,
         hidden: bool,
     },
+    SetTagWatched {
+        tag_id: TagId,
+        watched: bool,
+    },
+    MarkTagSeen {
+        tag_id: TagId,
+    },
+    SaveExhibition {
+        name: String,
+        items: Vec<ExhibitionItem>,
+    },
+    DeleteExhibition {
+        name: String,
+    },
+    SyncUserMetadata {
+        folder: PathBuf,
+        device: String,
+    },
+    AppendPluginLogs {
+        lines: Vec<DbLogLine>,
+    },
+    ApplyStorageRules,
+    MoveDataDir {
+        to: PathBuf,
+    },
+    OffloadColdFiles,
+    SealClearThumbnails,
+    PurgePluginWorks {
+        plugin_id: PluginId,
+    },
+    RestoreTrashedWorks {
+        screen_urls: Vec<String>,
+    },
+    EmptyTrash {
+        cutoff: Timestamp,
+    },
+    NoteWorkViewed {
+        work_id: WorkId,
+    },
+    RecordFileHash {
+        stored_path: String,
+        sha256: String,
+    },
+    SetWorkFetchedFrom {
+        screen_url: String,
+        role: MediaRole,
+        url: String,
+    },
+    AddWorkMirrors {
+        screen_url: String,
+        mirrors: Vec<(MediaRole, String)>,
+    },
+    RecordLinkChecks {
+        checks: Vec<LinkCheck>,
+    },
+    OptimizeDatabase {
+        manual: bool,
+    },
+    ScrubFiles,
+    RepairFile {
+        file: CorruptFile,
+    },
+    SetImportSettings(ImportSettings),
     Shutdown,
 }
 
+// How the works that plugins find are saved. The PluginHost owns these, as it drives imports,
+// and pushes them here whenever they change.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    // Keep the raw record that each work was built from, so that it can be re-mapped later.
+    pub keep_sources: bool,
+
+    // How many works to write per transaction when importing; bigger is faster, but holds
+    // the database for longer.
+    pub chunk_size: usize,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            keep_sources: false,
+            chunk_size: 1_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WorkFlag {
+    Favorite,
+    Hidden,
+}
+
+// A favorite or hidden toggle that has not been written yet, and when the user made it.
+#[derive(Clone, Copy, Debug)]
+pub struct PendingFlag {
+    work_id: WorkId,
+    flag: WorkFlag,
+    value: bool,
+    at: i64,
+}
+
+// Triage on the keyboard can toggle hundreds of works in a minute, so we hold those writes
+// here, where the latest toggle of each flag wins, and write them in one transaction.
+#[derive(Debug, Default)]
+struct PendingFlags {
+    since: Option<Instant>,
+    flags: HashMap<(WorkId, WorkFlag), PendingFlag>,
+}
+
 #[derive(Clone, Debug)]
 pub struct DbWriteHandle {
     tx_to_writer: Sender<DbWriterRequest>,
+    pending_flags: Arc<Mutex<PendingFlags>>,
 }
 
 impl DbWriteHandle {
+    // How long toggles may wait before we write them.
+    pub const FLAG_FLUSH_DELAY: Duration = Duration::from_millis(750);
+
     pub fn new(tx_to_writer: Sender<DbWriterRequest>) -> Self {
-        Self { tx_to_writer }
+        Self {
+            tx_to_writer,
+            pending_flags: Arc::new(Mutex::new(PendingFlags::default())),
+        }
     }
 
     pub fn send_exit_request(&self) {
+        if let Err(e) = self.flush_work_flags() {
+            error!("Failed to write favorites and hidden works at exit: {e}");
+        }
         self.tx_to_writer
             .send(DbWriterRequest::Shutdown)
             .expect("writer send died at exit");
     }
 
+    pub fn set_import_settings(&self, settings: ImportSettings) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SetImportSettings(settings))?;
+        Ok(())
+    }
+
     pub fn upsert_tags(&self, plugin_id: PluginId, tags: Vec<Tag>) -> Result<()> {
         self.tx_to_writer
             .send(DbWriterRequest::UpsertTags { plugin_id, tags })?;
         Ok(())
     }
 
+    // Takes effect on the plugin's next tag refresh.
+    pub fn set_tag_kind_mapping(
+        &self,
+        plugin_id: PluginId,
+        source_type: &str,
+        kind: TagKind,
+    ) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetTagKindMapping {
+            plugin_id,
+            source_type: source_type.to_owned(),
+            kind,
+        })?;
+        Ok(())
+    }
+
     pub fn upsert_works(&self, plugin_id: PluginId, tag: &str, works: Vec<Work>) -> Result<()> {
         self.tx_to_writer.send(DbWriterRequest::UpsertWorks {
             plugin_id,
@@ -82,12 +358,37 @@ impl DbWriteHandle {
         Ok(())
     }
 
+    pub fn upsert_groups(&self, plugin_id: PluginId, groups: Vec<Group>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::UpsertGroups { plugin_id, groups })?;
+        Ok(())
+    }
+
+    pub fn import_local_files(&self, paths: Vec<PathBuf>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::ImportLocalFiles { paths })?;
+        Ok(())
+    }
+
+    // A Hydrus db folder, or a folder of files exported from Hydrus with their sidecars.
+    pub fn import_hydrus(&self, path: PathBuf) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::ImportHydrus { path })?;
+        Ok(())
+    }
+
+    pub fn import_manifest(&self, tag: String, works: Vec<ManifestWork>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::ImportManifest { tag, works })?;
+        Ok(())
+    }
+
     pub fn set_work_download_paths(
         &self,
         screen_url: &str,
         preview_path: String,
-        screen_path: String,
-        archive_path: Option<String>,
+        (screen_path, archive_path, thumb_path): (Option<String>, Option<String>, Option<String>),
+        aspect_ratio: Option<f32>,
     ) -> Result<()> {
         self.tx_to_writer
             .send(DbWriterRequest::SetWorkDownloadPaths {
@@ -95,19 +396,232 @@ impl DbWriteHandle {
                 preview_path,
                 screen_path,
                 archive_path,
+                thumb_path,
+                aspect_ratio,
             })?;
         Ok(())
     }
 
-    pub fn set_work_favorite(&self, work_id: WorkId, favorite: bool) -> Result<()> {
+    pub fn set_work_thumb_path(
+        &self,
+        work_id: WorkId,
+        (thumb_path, aspect_ratio): (String, f32),
+    ) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetWorkThumbPath {
+            work_id,
+            thumb_path,
+            aspect_ratio,
+        })?;
+        Ok(())
+    }
+
+    pub fn add_work_variant(&self, screen_url: &str, stored_path: &str) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::AddWorkVariant {
+            screen_url: screen_url.to_owned(),
+            stored_path: stored_path.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    // Note: an empty note deletes the note.
+    pub fn set_work_note(&self, screen_url: &str, body: &str) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetWorkNote {
+            screen_url: screen_url.to_owned(),
+            body: body.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    pub fn add_annotation(&self, screen_url: &str, label: &str, region: [f32; 4]) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::AddAnnotation {
+            screen_url: screen_url.to_owned(),
+            label: label.to_owned(),
+            region,
+        })?;
+        Ok(())
+    }
+
+    pub fn delete_annotation(&self, screen_url: &str, id: AnnotationId) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::DeleteAnnotation {
+            screen_url: screen_url.to_owned(),
+            id,
+        })?;
+        Ok(())
+    }
+
+    pub fn set_nsfw_score(&self, screen_url: &str, score: f32) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetNsfwScore {
+            screen_url: screen_url.to_owned(),
+            score,
+        })?;
+        Ok(())
+    }
+
+    // Note: this replaces any transcript the work already had.
+    pub fn set_work_transcript(
+        &self,
+        screen_url: &str,
+        source: TranscriptSource,
+        cues: Vec<Cue>,
+    ) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetTranscript {
+            screen_url: screen_url.to_owned(),
+            source,
+            cues,
+        })?;
+        Ok(())
+    }
+
+    pub fn set_work_model_path(&self, screen_url: &str, model_path: String) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SetModelPath {
+            screen_url: screen_url.to_owned(),
+            model_path,
+        })?;
+        Ok(())
+    }
+
+    // Note: this also marks the work as scanned, so we don't look at it again.
+    pub fn save_detections(
+        &self,
+        screen_url: &str,
+        detections: Vec<Detection>,
+        suggestions: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SaveDetections {
+            screen_url: screen_url.to_owned(),
+            detections,
+            suggestions,
+        })?;
+        Ok(())
+    }
+
+    // Note: this also marks the works as looked up, so we don't ask about them again.
+    pub fn save_enrichments(&self, works: Vec<EnrichedWork>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SaveEnrichments { works })?;
+        Ok(())
+    }
+
+    pub fn remove_enrichments(&self) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::RemoveEnrichments)?;
+        Ok(())
+    }
+
+    pub fn save_vocabulary_terms(&self, terms: Vec<VocabularyTerm>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SaveVocabularyTerms { terms })?;
+        Ok(())
+    }
+
+    pub fn review_tag_suggestions(&self, ids: Vec<SuggestionId>, accept: bool) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::ReviewTagSuggestions { ids, accept })?;
+        Ok(())
+    }
+
+    pub fn remove_work_tag(&self, screen_url: &str, tag: &str) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::RemoveWorkTag {
+            screen_url: screen_url.to_owned(),
+            tag: tag.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    pub fn mark_tag_edits_pushed(&self, screen_url: &str, edit: WorkTagEdit) -> Result<()> {
         self.tx_to_writer
-            .send(DbWriterRequest::SetWorkFavorite { work_id, favorite })?;
+            .send(DbWriterRequest::MarkTagEditsPushed {
+                screen_url: screen_url.to_owned(),
+                edit,
+            })?;
+        Ok(())
+    }
+
+    // Note: the UX updates optimistically, so these only need to reach the DB eventually.
+    pub fn set_work_favorite(&self, work_id: WorkId, favorite: bool) -> Result<()> {
+        self.buffer_work_flag(work_id, WorkFlag::Favorite, favorite);
         Ok(())
     }
 
     pub fn set_work_hidden(&self, work_id: WorkId, hidden: bool) -> Result<()> {
+        self.buffer_work_flag(work_id, WorkFlag::Hidden, hidden);
+        Ok(())
+    }
+
+    fn buffer_work_flag(&self, work_id: WorkId, flag: WorkFlag, value: bool) {
+        let mut pending = self.pending_flags.lock();
+        pending.since.get_or_insert_with(Instant::now);
+        pending.flags.insert(
+            (work_id, flag),
+            PendingFlag {
+                work_id,
+                flag,
+                value,
+                at: Timestamp::now().as_millisecond(),
+            },
+        );
+    }
+
+    pub fn flush_work_flags(&self) -> Result<()> {
+        let flags = {
+            let mut pending = self.pending_flags.lock();
+            pending.since = None;
+            pending
+                .flags
+                .drain()
+                .map(|(_, flag)| flag)
+                .collect::<Vec<_>>()
+        };
+        if !flags.is_empty() {
+            self.tx_to_writer
+                .send(DbWriterRequest::SetWorkFlags { flags })?;
+        }
+        Ok(())
+    }
+
+    pub fn has_pending_work_flags(&self) -> bool {
+        self.pending_flags.lock().since.is_some()
+    }
+
+    // Write any toggles that have waited long enough.
+    pub fn tick(&self) -> Result<()> {
+        let due = self
+            .pending_flags
+            .lock()
+            .since
+            .is_some_and(|since| since.elapsed() >= Self::FLAG_FLUSH_DELAY);
+        if due {
+            self.flush_work_flags()?;
+        }
+        Ok(())
+    }
+
+    pub fn review_inbox_works(&self, screen_urls: Vec<String>) -> Result<()> {
         self.tx_to_writer
-            .send(DbWriterRequest::SetWorkHidden { work_id, hidden })?;
+            .send(DbWriterRequest::ReviewInboxWorks { screen_urls })?;
+        Ok(())
+    }
+
+    pub fn set_display_transform(
+        &self,
+        screen_url: &str,
+        transform: DisplayTransform,
+    ) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SetDisplayTransform {
+                screen_url: screen_url.to_owned(),
+                transform,
+            })?;
+        Ok(())
+    }
+
+    pub fn clear_inbox(&self) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::ClearInbox)?;
+        Ok(())
+    }
+
+    pub fn set_work_rating(&self, work_id: WorkId, rating: u8) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SetWorkRating { work_id, rating })?;
         Ok(())
     }
 
@@ -122,27 +636,197 @@ impl DbWriteHandle {
             .send(DbWriterRequest::SetTagHidden { tag_id, hidden })?;
         Ok(())
     }
+
+    pub fn set_tag_watched(&self, tag_id: TagId, watched: bool) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SetTagWatched { tag_id, watched })?;
+        Ok(())
+    }
+
+    pub fn mark_tag_seen(&self, tag_id: TagId) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::MarkTagSeen { tag_id })?;
+        Ok(())
+    }
+
+    pub fn save_exhibition(&self, name: &str, items: Vec<ExhibitionItem>) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SaveExhibition {
+            name: name.to_owned(),
+            items,
+        })?;
+        Ok(())
+    }
+
+    pub fn delete_exhibition(&self, name: &str) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::DeleteExhibition {
+            name: name.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    pub fn sync_user_metadata(&self, folder: &Path, device: &str) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::SyncUserMetadata {
+            folder: folder.to_owned(),
+            device: device.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    pub fn append_plugin_logs(&self, lines: Vec<DbLogLine>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::AppendPluginLogs { lines })?;
+        Ok(())
+    }
+
+    pub fn apply_storage_rules(&self) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::ApplyStorageRules)?;
+        Ok(())
+    }
+
+    pub fn move_data_dir(&self, to: &Path) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::MoveDataDir { to: to.to_owned() })?;
+        Ok(())
+    }
+
+    pub fn offload_cold_files(&self) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::OffloadColdFiles)?;
+        Ok(())
+    }
+
+    pub fn seal_clear_thumbnails(&self) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SealClearThumbnails)?;
+        Ok(())
+    }
+
+    // Trash the files of the plugin's works that aren't favorites, to make room under its quota.
+    pub fn purge_plugin_works(&self, plugin_id: PluginId) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::PurgePluginWorks { plugin_id })?;
+        Ok(())
+    }
+
+    pub fn restore_trashed_works(&self, screen_urls: Vec<String>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::RestoreTrashedWorks { screen_urls })?;
+        Ok(())
+    }
+
+    // Delete what went into the trash before the cutoff for good; the writer answers with what
+    // is left.
+    pub fn empty_trash(&self, cutoff: Timestamp) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::EmptyTrash { cutoff })?;
+        Ok(())
+    }
+
+    pub fn note_work_viewed(&self, work_id: WorkId) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::NoteWorkViewed { work_id })?;
+        Ok(())
+    }
+
+    pub fn record_file_hash(&self, stored_path: &str, sha256: String) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::RecordFileHash {
+            stored_path: stored_path.to_owned(),
+            sha256,
+        })?;
+        Ok(())
+    }
+
+    // Note: only for files that came from somewhere other than the work's own url.
+    pub fn set_work_fetched_from(
+        &self,
+        screen_url: &str,
+        role: MediaRole,
+        url: &str,
+    ) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::SetWorkFetchedFrom {
+                screen_url: screen_url.to_owned(),
+                role,
+                url: url.to_owned(),
+            })?;
+        Ok(())
+    }
+
+    // Note: these go ahead of the work's other mirrors.
+    pub fn add_work_mirrors(
+        &self,
+        screen_url: &str,
+        mirrors: Vec<(MediaRole, String)>,
+    ) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::AddWorkMirrors {
+            screen_url: screen_url.to_owned(),
+            mirrors,
+        })?;
+        Ok(())
+    }
+
+    pub fn record_link_checks(&self, checks: Vec<LinkCheck>) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::RecordLinkChecks { checks })?;
+        Ok(())
+    }
+
+    pub fn optimize_database(&self, manual: bool) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::OptimizeDatabase { manual })?;
+        Ok(())
+    }
+
+    pub fn scrub_files(&self) -> Result<()> {
+        self.tx_to_writer.send(DbWriterRequest::ScrubFiles)?;
+        Ok(())
+    }
+
+    pub fn repair_file(&self, file: CorruptFile) -> Result<()> {
+        self.tx_to_writer
+            .send(DbWriterRequest::RepairFile { file })?;
+        Ok(())
+    }
 }
 
 pub struct DbBgWriter {
     pool: r2d2::Pool<SqliteConnectionManager>,
+    storage: Storage,
     db_cancellation: DbCancellation,
     rx_from_app: Receiver<DbWriterRequest>,
     tx_to_app: Sender<DataUpdate>,
+    import: ImportSettings,
+
+    // Tag ids by name, so that imports don't have to look up the same tags for every work.
+    tag_ids: HashMap<String, i64>,
 }
 
 impl DbBgWriter {
     pub fn new(
         pool: r2d2::Pool<SqliteConnectionManager>,
+        storage: Storage,
         db_cancellation: DbCancellation,
         rx_from_app: Receiver<DbWriterRequest>,
         tx_to_app: Sender<DataUpdate>,
     ) -> Self {
         Self {
             pool,
+            storage,
             db_cancellation,
             rx_from_app,
             tx_to_app,
+            import: ImportSettings::default(),
+            tag_ids: HashMap::new(),
+        }
+    }
+
+    // Stands in for the writer when we are read-only, so that the rest of the app can carry on
+    // sending requests.
+    pub fn discard(rx_from_app: &Receiver<DbWriterRequest>) {
+        while let Ok(msg) = rx_from_app.recv() {
+            if matches!(msg, DbWriterRequest::Shutdown) {
+                break;
+            }
+            debug!("Read-only: dropping a database write");
         }
     }
 
@@ -162,74 +846,716 @@ impl DbBgWriter {
         Ok(())
     }
 
+    // Note: a bad destination is a user problem, not a reason to restart the writer.
+    fn relocate_report(result: Result<RelocateReport>, log: &mut LogSender) -> RelocateReport {
+        result.unwrap_or_else(|e| {
+            log.error(format!("Failed to relocate files: {e}"));
+            RelocateReport {
+                error: Some(e.to_string()),
+                ..Default::default()
+            }
+        })
+    }
+
     pub fn handle_message(&mut self, msg: DbWriterRequest) -> Result<()> {
         let mut log = LogSender::wrap(UpdateSource::DbWriter, self.tx_to_app.clone());
         let mut progress = ProgressSender::wrap(UpdateSource::DbWriter, self.tx_to_app.clone());
         let mut host = HostUpdateSender::wrap(UpdateSource::DbWriter, self.tx_to_app.clone());
         match msg {
+            DbWriterRequest::SetImportSettings(settings) => {
+                self.import = settings;
+            }
             DbWriterRequest::Shutdown => panic!("expected exit to be handled in main"),
-            DbWriterRequest::UpsertTags { plugin_id, tags } => {
-                upsert_tags(
-                    &mut self.pool.get()?,
-                    plugin_id,
-                    &tags,
-                    &mut log,
-                    &mut progress,
-                )?;
+            DbWriterRequest::UpsertTags {
+                plugin_id,
+                mut tags,
+            } => {
+                let mut conn = self.pool.get()?;
+                map_tag_kinds(&conn, plugin_id, &mut tags, &mut log)?;
+                upsert_tags(&mut conn, plugin_id, &tags, &mut log, &mut progress)?;
+                // Note: ids are stable across upserts, but there is no sense risking it.
+                self.tag_ids.clear();
                 host.note_tags_were_refreshed()?;
             }
+            DbWriterRequest::SetTagKindMapping {
+                plugin_id,
+                source_type,
+                kind,
+            } => {
+                set_tag_kind_mapping(&self.pool.get()?, plugin_id, &source_type, kind)?;
+            }
             DbWriterRequest::UpsertWorks {
-                plugin_id: _,
+                plugin_id,
                 for_tag,
                 works,
             } => {
-                upsert_works(
+                let new_works = upsert_works(
                     self.pool.get()?,
                     &self.db_cancellation,
-                    &works,
+                    (plugin_id, &for_tag, &works),
+                    &self.import,
+                    &mut self.tag_ids,
+                    &mut log,
+                    &mut progress,
+                )?;
+                host.note_works_were_refreshed(for_tag, new_works)?;
+            }
+            DbWriterRequest::UpsertGroups { plugin_id, groups } => {
+                upsert_groups(&mut self.pool.get()?, plugin_id, &groups, &mut log)?;
+                self.tag_ids.clear();
+                host.note_tags_were_refreshed()?;
+            }
+            DbWriterRequest::ImportLocalFiles { paths } => {
+                let mut conn = self.pool.get()?;
+                let plugin_id = local_plugin_id(&conn)?;
+                upsert_tags(
+                    &mut conn,
+                    plugin_id,
+                    &[Tag::new(LOCAL_IMPORT_TAG)],
+                    &mut log,
+                    &mut progress,
+                )?;
+                host.note_tags_were_refreshed()?;
+                let files = copy_local_files(&paths, &self.storage, &mut log, &mut progress);
+                let works = files
+                    .iter()
+                    .map(|file| file.work.clone())
+                    .collect::<Vec<_>>();
+                let new_works = upsert_works(
+                    conn,
+                    &self.db_cancellation,
+                    (plugin_id, LOCAL_IMPORT_TAG, &works),
+                    &self.storage.config(),
+                    &mut self.tag_ids,
                     &mut log,
                     &mut progress,
                 )?;
-                host.note_works_were_refreshed(for_tag)?;
+                let conn = self.pool.get()?;
+                for file in &files {
+                    finish_local_file(&conn, file, &mut host)?;
+                }
+                log.info(format!("Imported {} of {} files", files.len(), paths.len()));
+                host.note_works_were_refreshed(LOCAL_IMPORT_TAG.to_owned(), new_works)?;
+            }
+            DbWriterRequest::ImportHydrus { path } => {
+                // Note: a folder that isn't what we expect is the user's to fix, not ours.
+                let sources = match read_hydrus(&path, &mut log, &mut progress) {
+                    Ok(sources) => sources,
+                    Err(e) => {
+                        log.error(format!("Failed to read {}: {e}", path.display()));
+                        return Ok(());
+                    }
+                };
+                let mut conn = self.pool.get()?;
+                let plugin_id = local_plugin_id(&conn)?;
+                let mut tags = sources
+                    .iter()
+                    .flat_map(|file| file.imported_tags())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(|tag| tag.to_tag())
+                    .collect::<Vec<_>>();
+                tags.push(Tag::new(HYDRUS_IMPORT_TAG));
+                map_tag_kinds(&conn, plugin_id, &mut tags, &mut log)?;
+                upsert_tags(&mut conn, plugin_id, &tags, &mut log, &mut progress)?;
+                self.tag_ids.clear();
+                host.note_tags_were_refreshed()?;
+
+                let mut merged = 0;
+                let mut copied = Vec::new();
+                for (i, source) in sources.iter().enumerate() {
+                    progress.set_percent(i, sources.len());
+                    if let Some(work_id) = find_existing(&conn, source)? {
+                        merge_into_existing(&conn, work_id, source, &mut host)?;
+                        merged += 1;
+                        continue;
+                    }
+                    match copy_local_file(
+                        &source.path,
+                        &self.storage,
+                        source.title(),
+                        source.tag_names(),
+                    ) {
+                        Ok(file) => copied.push((source, file)),
+                        Err(e) => log.warn(format!("Skipping {}: {e}", source.path.display())),
+                    }
+                }
+                progress.clear();
+                let works = copied
+                    .iter()
+                    .map(|(_, file)| file.work.clone())
+                    .collect::<Vec<_>>();
+                let new_works = upsert_works(
+                    conn,
+                    &self.db_cancellation,
+                    (plugin_id, HYDRUS_IMPORT_TAG, &works),
+                    &self.storage.config(),
+                    &mut self.tag_ids,
+                    &mut log,
+                    &mut progress,
+                )?;
+                let conn = self.pool.get()?;
+                for (source, file) in &copied {
+                    finish_local_file(&conn, file, &mut host)?;
+                    record_file_hash(&conn, &file.screen_path, &source.sha256)?;
+                    let work_id = conn.query_row(
+                        "SELECT id FROM works WHERE screen_url = ?",
+                        [file.work.screen_url()],
+                        |row| row.get::<_, i64>(0),
+                    )?;
+                    if source.is_favorite() {
+                        mark_favorite(&conn, work_id, &mut host)?;
+                    }
+                    // Note: don't clobber a note that was written on an earlier import.
+                    if let Some(note) = source_note(source) {
+                        conn.execute(
+                            r#"INSERT OR IGNORE INTO work_notes (screen_url, body, created_at, updated_at)
+                            VALUES (?1, ?2, ?3, ?3)"#,
+                            params![file.work.screen_url(), note, Timestamp::now().as_millisecond()],
+                        )?;
+                    }
+                }
+                log.info(format!(
+                    "Imported {} of {} files from Hydrus; added to {merged} works we already had",
+                    copied.len(),
+                    sources.len()
+                ));
+                host.note_works_were_refreshed(HYDRUS_IMPORT_TAG.to_owned(), new_works)?;
+            }
+            DbWriterRequest::ImportManifest { tag, works } => {
+                let mut conn = self.pool.get()?;
+                let plugin_id = local_plugin_id(&conn)?;
+                let mut tags = works
+                    .iter()
+                    .flat_map(|work| work.tags.iter())
+                    .chain([&tag])
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(Tag::new)
+                    .collect::<Vec<_>>();
+                tags.extend(
+                    works
+                        .iter()
+                        .filter_map(|work| work.artist.as_ref())
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .map(|artist| Tag::new(artist).with_source_type("artist")),
+                );
+                map_tag_kinds(&conn, plugin_id, &mut tags, &mut log)?;
+                upsert_tags(&mut conn, plugin_id, &tags, &mut log, &mut progress)?;
+                self.tag_ids.clear();
+                host.note_tags_were_refreshed()?;
+
+                log.info(format!(
+                    "Importing {} works from the manifest...",
+                    works.len()
+                ));
+                let mut files = Vec::with_capacity(works.len());
+                for (i, work) in works.iter().enumerate() {
+                    progress.set_percent(i, works.len());
+                    let mut names = work.tags.clone();
+                    names.push(tag.clone());
+                    names.extend(work.artist.clone());
+                    let file = match &work.source {
+                        ManifestSource::Url(url) => {
+                            download_url_file(url, &self.storage, &work.title, names)
+                        }
+                        ManifestSource::Path(path) => {
+                            copy_local_file(path, &self.storage, Some(&work.title), names)
+                        }
+                    };
+                    let mut file = match file {
+                        Ok(file) => file,
+                        Err(e) => {
+                            log.warn(format!("Skipping row {}: {e}", work.row));
+                            continue;
+                        }
+                    };
+                    // Note: what the manifest says about the work wins over what we could guess.
+                    let mut history = History::default();
+                    if let Some(artist) = &work.artist {
+                        history.set_attribution(artist);
+                    }
+                    file.work = Work::new(
+                        file.work.name(),
+                        work.date.unwrap_or(*file.work.date()),
+                        file.work.preview_url(),
+                        file.work.screen_url(),
+                        file.work.tags().to_vec(),
+                    )
+                    .with_history(history)
+                    .with_source(&work.record);
+                    files.push(file);
+                }
+                progress.clear();
+                let new_works = upsert_works(
+                    conn,
+                    &self.db_cancellation,
+                    (
+                        plugin_id,
+                        &tag,
+                        &files
+                            .iter()
+                            .map(|file| file.work.clone())
+                            .collect::<Vec<_>>(),
+                    ),
+                    &self.storage.config(),
+                    &mut self.tag_ids,
+                    &mut log,
+                    &mut progress,
+                )?;
+                let conn = self.pool.get()?;
+                for file in &files {
+                    finish_local_file(&conn, file, &mut host)?;
+                }
+                log.info(format!(
+                    "Imported {} of {} works from the manifest",
+                    files.len(),
+                    works.len()
+                ));
+                host.note_works_were_refreshed(tag, new_works)?;
             }
             DbWriterRequest::SetWorkDownloadPaths {
                 screen_url,
                 preview_path,
                 screen_path,
                 archive_path,
+                thumb_path,
+                aspect_ratio,
             } => {
+                let file_size = screen_path.as_deref().and_then(|stored| {
+                    fs::metadata(self.storage.resolve(Path::new(stored)))
+                        .ok()
+                        .and_then(|meta| i64::try_from(meta.len()).ok())
+                });
+                let conn = self.pool.get()?;
                 update_work_paths(
-                    &self.pool.get()?,
+                    &conn,
                     &screen_url,
                     &preview_path,
-                    &screen_path,
-                    archive_path.as_deref(),
+                    (
+                        screen_path.as_deref(),
+                        archive_path.as_deref(),
+                        thumb_path.as_deref(),
+                    ),
+                    (aspect_ratio, file_size),
                     &mut host,
                 )?;
+                forget_cold_files(&conn, &self.storage, &screen_url, &mut log)?;
+            }
+            DbWriterRequest::SetWorkThumbPath {
+                work_id,
+                thumb_path,
+                aspect_ratio,
+            } => {
+                set_work_thumb_path(
+                    &self.pool.get()?,
+                    work_id,
+                    (&thumb_path, aspect_ratio),
+                    &mut host,
+                )?;
+            }
+            DbWriterRequest::AddWorkVariant {
+                screen_url,
+                stored_path,
+            } => {
+                log.info(format!("Found an edit of {screen_url}: {stored_path}"));
+                self.pool.get()?.execute(
+                    r#"INSERT OR IGNORE INTO work_variants (screen_url, path, added_at)
+                    VALUES (?, ?, ?)"#,
+                    params![screen_url, stored_path, Timestamp::now().as_millisecond()],
+                )?;
+            }
+            DbWriterRequest::SetWorkNote { screen_url, body } => {
+                set_work_note(&self.pool.get()?, &screen_url, &body)?;
+            }
+            DbWriterRequest::AddAnnotation {
+                screen_url,
+                label,
+                region: [x, y, width, height],
+            } => {
+                let conn = self.pool.get()?;
+                conn.execute(
+                    r#"INSERT INTO annotations (screen_url, label, x, y, width, height, created_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+                    params![
+                        screen_url,
+                        label,
+                        x,
+                        y,
+                        width,
+                        height,
+                        Timestamp::now().as_millisecond()
+                    ],
+                )?;
+                // Note: the UX needs the new id to be able to delete it again.
+                host.return_annotations(screen_url.clone(), list_annotations(&conn, &screen_url)?)?;
+            }
+            DbWriterRequest::DeleteAnnotation { screen_url, id } => {
+                let conn = self.pool.get()?;
+                conn.execute("DELETE FROM annotations WHERE id = ?", params![id])?;
+                host.return_annotations(screen_url.clone(), list_annotations(&conn, &screen_url)?)?;
+            }
+            DbWriterRequest::SetNsfwScore { screen_url, score } => {
+                set_nsfw_score(&self.pool.get()?, &screen_url, score, &mut host)?;
+            }
+            DbWriterRequest::SetTranscript {
+                screen_url,
+                source,
+                cues,
+            } => {
+                let mut conn = self.pool.get()?;
+                set_transcript(&mut conn, &screen_url, source, &cues)?;
+                host.return_transcript(screen_url.clone(), lookup_transcript(&conn, &screen_url)?)?;
+            }
+            DbWriterRequest::SetModelPath {
+                screen_url,
+                model_path,
+            } => {
+                let work_id = self
+                    .pool
+                    .get()?
+                    .query_row(
+                        "UPDATE works SET model_path = ? WHERE screen_url = ? RETURNING id",
+                        params![model_path, screen_url],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                if let Some(work_id) = work_id {
+                    host.note_work_model_downloaded(WorkId::wrap(work_id), &model_path)?;
+                }
+            }
+            DbWriterRequest::SaveDetections {
+                screen_url,
+                detections,
+                suggestions,
+            } => {
+                let suggested = save_detections(
+                    &mut self.pool.get()?,
+                    &screen_url,
+                    &detections,
+                    &suggestions,
+                )?;
+                if suggested {
+                    host.note_tag_suggestions_changed()?;
+                }
+            }
+            DbWriterRequest::SaveEnrichments { works } => {
+                if save_enrichments(&mut self.pool.get()?, &works)? {
+                    self.tag_ids.clear();
+                    host.note_tags_were_refreshed()?;
+                }
+            }
+            DbWriterRequest::RemoveEnrichments => {
+                let removed = remove_enrichments(&mut self.pool.get()?)?;
+                log.info(format!("Took {removed} enriched tags off of works"));
+                self.tag_ids.clear();
+                host.note_tags_were_refreshed()?;
+            }
+            DbWriterRequest::SaveVocabularyTerms { terms } => {
+                let (aliases, implications) = save_vocabulary_terms(&mut self.pool.get()?, &terms)?;
+                debug!("Tags have {aliases} aliases and {implications} implications from Getty");
+                self.tag_ids.clear();
+                host.note_tags_were_refreshed()?;
+            }
+            DbWriterRequest::ReviewTagSuggestions { ids, accept } => {
+                let tags = review_tag_suggestions(&mut self.pool.get()?, &ids, accept)?;
+                if !tags.is_empty() {
+                    log.info(format!(
+                        "Tagged {} works from suggestions: {}",
+                        ids.len(),
+                        tags.join(", ")
+                    ));
+                    self.tag_ids.clear();
+                    host.note_tags_were_refreshed()?;
+                    for tag in tags {
+                        host.note_works_were_refreshed(tag, 0)?;
+                    }
+                    host.note_tag_edits_changed()?;
+                }
+                host.note_tag_suggestions_changed()?;
+            }
+            DbWriterRequest::RemoveWorkTag { screen_url, tag } => {
+                remove_work_tag(&mut self.pool.get()?, &screen_url, &tag)?;
+                host.note_tags_were_refreshed()?;
+                host.note_works_were_refreshed(tag, 0)?;
+                host.note_tag_edits_changed()?;
             }
-            DbWriterRequest::SetWorkFavorite { work_id, favorite } => {
-                set_work_favorite(&self.pool.get()?, work_id, favorite)?;
-                host.note_work_favorite_status_changed(work_id, favorite)?;
+            DbWriterRequest::MarkTagEditsPushed { screen_url, edit } => {
+                mark_tag_edits_pushed(&mut self.pool.get()?, &screen_url, &edit)?;
+                host.note_tag_edits_changed()?;
             }
-            DbWriterRequest::SetWorkHidden { work_id, hidden } => {
-                set_work_hidden(&self.pool.get()?, work_id, hidden)?;
-                host.note_work_hidden_status_changed(work_id, hidden)?;
+            DbWriterRequest::SetWorkFlags { flags } => {
+                set_work_flags(&mut self.pool.get()?, &flags, &mut host)?;
+            }
+            DbWriterRequest::ReviewInboxWorks { screen_urls } => {
+                review_inbox_works(&self.pool.get()?, &screen_urls)?;
+            }
+            DbWriterRequest::SetDisplayTransform {
+                screen_url,
+                transform,
+            } => {
+                set_display_transform(&self.pool.get()?, &screen_url, &transform)?;
+            }
+            DbWriterRequest::ClearInbox => {
+                log.info("Clearing the inbox");
+                self.pool.get()?.execute("DELETE FROM work_inbox", [])?;
+            }
+            DbWriterRequest::SetWorkRating { work_id, rating } => {
+                set_work_rating(&self.pool.get()?, work_id, rating)?;
+                host.note_work_rating_changed(work_id, rating)?;
             }
             DbWriterRequest::SetTagFavorite { tag_id, favorite } => {
                 log.info(format!("Setting tag {tag_id} to favorite: {favorite}"));
                 set_tag_favorite(&self.pool.get()?, tag_id, favorite)?;
                 host.note_tag_favorite_status_changed(tag_id, favorite)?;
             }
-            DbWriterRequest::SetTagHidden { tag_id, hidden } => {
-                log.info(format!("Setting tag {tag_id} to hidden: {hidden}"));
-                set_tag_hidden(&self.pool.get()?, tag_id, hidden)?;
-                host.note_tag_hidden_status_changed(tag_id, hidden)?;
+            DbWriterRequest::SetTagHidden { tag_id, hidden } => {
+                log.info(format!("Setting tag {tag_id} to hidden: {hidden}"));
+                set_tag_hidden(&self.pool.get()?, tag_id, hidden)?;
+                host.note_tag_hidden_status_changed(tag_id, hidden)?;
+            }
+            DbWriterRequest::SetTagWatched { tag_id, watched } => {
+                log.info(format!("Setting tag {tag_id} to watched: {watched}"));
+                set_tag_watched(&self.pool.get()?, tag_id, watched)?;
+                host.note_tag_watched_status_changed(tag_id, watched)?;
+            }
+            DbWriterRequest::MarkTagSeen { tag_id } => {
+                mark_tag_seen(&self.pool.get()?, tag_id)?;
+                host.return_watched_tag_counts(vec![(tag_id, 0)])?;
+            }
+            DbWriterRequest::SaveExhibition { name, items } => {
+                log.info(format!(
+                    "Saving exhibition {name} with {} items",
+                    items.len()
+                ));
+                save_exhibition(&mut self.pool.get()?, &name, &items)?;
+            }
+            DbWriterRequest::DeleteExhibition { name } => {
+                log.info(format!("Deleting exhibition {name}"));
+                delete_exhibition(&mut self.pool.get()?, &name)?;
+            }
+            DbWriterRequest::SyncUserMetadata { folder, device } => {
+                // Note: a bad sync folder is a user problem, not a reason to restart the writer.
+                match sync_user_metadata(
+                    &mut self.pool.get()?,
+                    &folder,
+                    &device,
+                    &mut log,
+                    &mut host,
+                ) {
+                    Ok(report) => host.note_metadata_sync_completed(report)?,
+                    Err(e) => {
+                        log.error(format!("Metadata sync failed: {e}"));
+                        host.note_metadata_sync_completed(SyncReport {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        })?;
+                    }
+                }
+            }
+            DbWriterRequest::AppendPluginLogs { lines } => {
+                append_plugin_logs(&mut self.pool.get()?, &lines)?;
+            }
+            DbWriterRequest::ApplyStorageRules => {
+                let result =
+                    apply_storage_rules(&self.pool.get()?, &self.storage, &mut log, &mut progress);
+                host.note_storage_relocated(Self::relocate_report(result, &mut log))?;
+            }
+            DbWriterRequest::MoveDataDir { to } => {
+                let result = move_data_dir(
+                    &self.pool.get()?,
+                    &self.storage,
+                    &to,
+                    &mut log,
+                    &mut progress,
+                );
+                host.note_storage_relocated(Self::relocate_report(result, &mut log))?;
+            }
+            DbWriterRequest::SealClearThumbnails => {
+                if let Err(e) = seal_clear_thumbnails(&self.pool.get()?, &self.storage, &mut log) {
+                    log.error(format!("Failed to seal thumbnails: {e}"));
+                }
+            }
+            DbWriterRequest::OffloadColdFiles => {
+                let result =
+                    offload_cold_files(&self.pool.get()?, &self.storage, &mut log, &mut progress);
+                host.note_storage_relocated(Self::relocate_report(result, &mut log))?;
+            }
+            DbWriterRequest::PurgePluginWorks { plugin_id } => {
+                let report = purge_plugin_works(
+                    &self.pool.get()?,
+                    &self.storage,
+                    plugin_id,
+                    &mut log,
+                    &mut progress,
+                )
+                .unwrap_or_else(|e| {
+                    log.error(format!("Failed to purge works: {e}"));
+                    PurgeReport::default()
+                });
+                host.note_plugin_works_purged(plugin_id, report)?;
+                host.note_trash(list_trash(&self.pool.get()?)?)?;
+            }
+            DbWriterRequest::RestoreTrashedWorks { screen_urls } => {
+                let conn = self.pool.get()?;
+                if let Err(e) = restore_trashed_works(&conn, &self.storage, &screen_urls, &mut log)
+                {
+                    log.error(format!("Failed to restore works from the trash: {e}"));
+                }
+                host.note_trash(list_trash(&conn)?)?;
+            }
+            DbWriterRequest::EmptyTrash { cutoff } => {
+                let conn = self.pool.get()?;
+                if let Err(e) = empty_trash(&conn, &self.storage, cutoff, &mut log, &mut progress) {
+                    log.error(format!("Failed to empty the trash: {e}"));
+                }
+                host.note_trash(list_trash(&conn)?)?;
+            }
+            DbWriterRequest::NoteWorkViewed { work_id } => {
+                note_work_viewed(
+                    &self.pool.get()?,
+                    &self.storage,
+                    work_id,
+                    &mut log,
+                    &mut host,
+                )?;
+            }
+            DbWriterRequest::RecordFileHash {
+                stored_path,
+                sha256,
+            } => {
+                record_file_hash(&self.pool.get()?, &stored_path, &sha256)?;
+            }
+            DbWriterRequest::SetWorkFetchedFrom {
+                screen_url,
+                role,
+                url,
+            } => {
+                self.pool.get()?.execute(
+                    r#"INSERT OR REPLACE INTO work_fetched_from (screen_url, role, url, fetched_at)
+                    VALUES (?, ?, ?, ?)"#,
+                    params![
+                        screen_url,
+                        role.to_string(),
+                        url,
+                        Timestamp::now().as_millisecond()
+                    ],
+                )?;
+            }
+            DbWriterRequest::AddWorkMirrors {
+                screen_url,
+                mirrors,
+            } => {
+                add_work_mirrors(&mut self.pool.get()?, &screen_url, &mirrors)?;
+            }
+            DbWriterRequest::RecordLinkChecks { checks } => {
+                let conn = self.pool.get()?;
+                record_link_checks(&conn, &checks)?;
+                host.note_lost_links(list_lost_links(&conn)?)?;
+            }
+            DbWriterRequest::OptimizeDatabase { manual } => {
+                // Note: a failed VACUUM (e.g. for lack of disk) leaves the database as it was.
+                let report = optimize_database(&self.pool.get()?, manual, &mut log, &mut progress)
+                    .unwrap_or_else(|e| {
+                        log.error(format!("Database maintenance failed: {e}"));
+                        progress.clear();
+                        OptimizeReport {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        }
+                    });
+                host.note_database_optimized(report)?;
+            }
+            DbWriterRequest::ScrubFiles => {
+                let report = scrub_files(&self.pool.get()?, &self.storage, &mut log, &mut progress)
+                    .unwrap_or_else(|e| {
+                        log.error(format!("Integrity scrub failed: {e}"));
+                        ScrubReport {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        }
+                    });
+                host.note_scrub_completed(report)?;
+            }
+            DbWriterRequest::RepairFile { file } => {
+                // Note: the original url may be gone; that's for the user to sort out.
+                let error = repair_file(&self.pool.get()?, &self.storage, &file, &mut log)
+                    .err()
+                    .map(|e| {
+                        log.warn(format!("Failed to repair {}: {e}", file.stored_path));
+                        e.to_string()
+                    });
+                host.note_file_repaired(file.stored_path, error)?;
             }
         }
         Ok(())
     }
 }
 
+// Give each tag the kind the user picked for its source type, if they picked one. Types that we
+// have not seen before are recorded with the plugin's guess, so that they show up for editing.
+pub fn map_tag_kinds(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    tags: &mut [Tag],
+    log: &mut LogSender,
+) -> Result<()> {
+    let mut known = list_tag_kind_mappings(conn, plugin_id)?
+        .into_iter()
+        .map(|mapping| (mapping.source_type.clone(), mapping))
+        .collect::<HashMap<_, _>>();
+    let mut record_stmt = conn.prepare(
+        r#"INSERT INTO tag_kind_mappings (plugin_id, source_type, kind) VALUES (?, ?, ?)
+        ON CONFLICT DO UPDATE SET kind = excluded.kind WHERE NOT edited"#,
+    )?;
+    for tag in tags {
+        let Some(source_type) = tag.source_type() else {
+            continue;
+        };
+        match known.get(source_type) {
+            Some(mapping) if mapping.edited => {
+                let kind = mapping.kind;
+                tag.set_kind(kind);
+            }
+            Some(mapping) if mapping.kind == tag.kind() => {}
+            prior => {
+                if prior.is_none() {
+                    log.info(format!(
+                        "New tag type \"{source_type}\", tagged as {}",
+                        tag.kind()
+                    ));
+                }
+                record_stmt.execute(params![plugin_id, source_type, tag.kind().to_string()])?;
+                let source_type = source_type.to_owned();
+                known.insert(
+                    source_type.clone(),
+                    TagKindMapping {
+                        source_type,
+                        kind: tag.kind(),
+                        edited: false,
+                    },
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn set_tag_kind_mapping(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    source_type: &str,
+    kind: TagKind,
+) -> Result<()> {
+    conn.execute(
+        r#"INSERT INTO tag_kind_mappings (plugin_id, source_type, kind, edited) VALUES (?, ?, ?, true)
+        ON CONFLICT DO UPDATE SET kind = excluded.kind, edited = true"#,
+        params![plugin_id, source_type, kind.to_string()],
+    )?;
+    Ok(())
+}
+
 pub fn upsert_tags(
     conn: &mut PooledConnection<SqliteConnectionManager>,
     plugin_id: PluginId,
@@ -253,6 +1579,9 @@ pub fn upsert_tags(
             let mut insert_tag_stmt = xaction
                 .prepare("INSERT INTO tags (name, kind, wiki_url) VALUES (?, ?, ?) ON CONFLICT DO UPDATE SET kind = ?, wiki_url = ? WHERE tags.name = ?")?;
             let mut select_tag_id_stmt = xaction.prepare("SELECT id FROM tags WHERE name = ?")?;
+            let mut insert_external_id_stmt = xaction.prepare(
+                "INSERT OR IGNORE INTO tag_external_ids (tag_id, authority, value) VALUES (?, ?, ?)",
+            )?;
 
             for tag in chunk {
                 let row_cnt = insert_tag_stmt.execute(params![
@@ -268,6 +1597,13 @@ pub fn upsert_tags(
                 if tag_id == 0 {
                     tag_id = select_tag_id_stmt.query_row(params![tag.name()], |row| row.get(0))?;
                 }
+                for (authority, value) in tag.external_ids() {
+                    insert_external_id_stmt.execute(params![
+                        tag_id,
+                        authority.to_string(),
+                        value
+                    ])?;
+                }
                 tag_ids.push((tag_id, tag.presumed_work_count()));
             }
         }
@@ -293,23 +1629,106 @@ pub fn upsert_tags(
         current_pos += chunk.len();
         progress.set_percent(current_pos, total_count);
     }
+    // Note: new tags may stand for terms that we already fetched for other tags.
+    derive_tag_vocabulary(conn)?;
 
     progress.clear();
     Ok(())
 }
 
+// The columns that we keep a history of when a plugin changes them, with their offset in the
+// parameters to the works upsert.
+const REVISION_COLUMNS: [(&str, usize); 24] = [
+    ("name", 0),
+    ("date", 2),
+    ("preview_url", 3),
+    ("archive_url", 5),
+    ("location_custody", 6),
+    ("location_site", 7),
+    ("location_room", 8),
+    ("location_position", 9),
+    ("location_description", 10),
+    ("location_on_display", 11),
+    ("history_attribution", 12),
+    ("history_attribution_sort_key", 13),
+    ("history_display_date", 14),
+    ("history_begin_year", 15),
+    ("history_end_year", 16),
+    ("history_provenance", 17),
+    ("history_credit_line", 18),
+    ("physical_medium", 19),
+    ("physical_dimensions_display", 20),
+    ("physical_inscription", 21),
+    ("physical_markings", 22),
+    ("physical_watermarks", 23),
+    ("model_url", 27),
+    ("transcript_url", 28),
+];
+
+fn value_label(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer(v) => Some(v.to_string()),
+        Value::Real(v) => Some(v.to_string()),
+        Value::Text(v) => Some(v.clone()),
+        Value::Blob(v) => Some(format!("<{} bytes>", v.len())),
+    }
+}
+
+// Compare the upsert's parameters against what we have for the work now. Returns None if the
+// work is new.
+fn work_changes(
+    select_prior_stmt: &mut Statement<'_>,
+    screen_url: &str,
+    params: &[&dyn ToSql],
+) -> Result<Option<Vec<WorkChange>>> {
+    let prior = select_prior_stmt
+        .query_row([screen_url], |row| {
+            (0..REVISION_COLUMNS.len())
+                .map(|i| row.get::<usize, Value>(i))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .optional()?;
+    let Some(prior) = prior else {
+        return Ok(None);
+    };
+    let mut changes = Vec::new();
+    for ((field, offset), old) in REVISION_COLUMNS.into_iter().zip(prior) {
+        let new = match params[offset].to_sql()? {
+            ToSqlOutput::Borrowed(value) => Value::from(value),
+            ToSqlOutput::Owned(value) => value,
+            _ => continue,
+        };
+        // Note: compare as text, so that e.g. a year stored as 1850.0 matches 1850.
+        let (old, new) = (value_label(&old), value_label(&new));
+        if old != new {
+            changes.push(WorkChange {
+                field: field.to_owned(),
+                old,
+                new,
+            });
+        }
+    }
+    Ok(Some(changes))
+}
+
+// Returns the number of works that we had not seen before.
 pub fn upsert_works(
     mut conn: PooledConnection<SqliteConnectionManager>,
     db_cancellation: &DbCancellation,
-    works: &[Work],
+    (plugin_id, for_tag, works): (PluginId, &str, &[Work]),
+    import: &ImportSettings,
+    tag_ids: &mut HashMap<String, i64>,
     log: &mut LogSender,
     progress: &mut ProgressSender,
-) -> Result<()> {
+) -> Result<usize> {
     let total_count = works.len();
+    let mut new_works = 0;
     let mut current_pos = 0;
+    let keep_sources = import.keep_sources;
     log.info(format!("Writing {total_count} works to the database..."));
 
-    for chunk in works.chunks(1_000) {
+    for chunk in works.chunks(import.chunk_size.max(1)) {
         if db_cancellation.is_cancelled() {
             log.warn("Exiting upsert_works early due to cancellation");
         }
@@ -324,24 +1743,52 @@ pub fn upsert_works(
                     name, artist_id, date, preview_url, screen_url, archive_url,
                     location_custody, location_site, location_room, location_position, location_description, location_on_display,
                     history_attribution, history_attribution_sort_key, history_display_date, history_begin_year, history_end_year, history_provenance, history_credit_line,
-                    physical_medium, physical_dimensions_display, physical_inscription, physical_markings, physical_watermarks
+                    physical_medium, physical_dimensions_display, physical_inscription, physical_markings, physical_watermarks,
+                    created_at, remote_id, model_url, transcript_url
                 )
                 VALUES
                 (?, ?, ?, ?, ?, ?,
                  ?, ?, ?, ?, ?, ?,
                  ?, ?, ?, ?, ?, ?, ?,
-                 ?, ?, ?, ?, ?)
+                 ?, ?, ?, ?, ?,
+                 COALESCE((SELECT created_at FROM works WHERE screen_url = ?), ?), ?, ?, ?)
                 RETURNING id"#,
             )?;
             let mut insert_measurement_stmt = xaction.prepare(r#"
                 INSERT OR REPLACE INTO work_measurements (work_id, name, description, value, si_unit)
                 VALUES (?, ?, ?, ?, ?)
             "#)?;
-            let mut select_tag_ids_from_names =
-                xaction.prepare("SELECT id FROM tags WHERE name IN rarray(?)")?;
-            let mut insert_work_tag_stmt = xaction
-                .prepare("INSERT OR IGNORE INTO work_tags (tag_id, work_id) VALUES (?, ?)")?;
+            cache_tag_ids(&xaction, chunk, tag_ids)?;
+            let mut work_tags = Vec::new();
             let mut select_work_id_stmt = xaction.prepare("SELECT id FROM works WHERE name = ?")?;
+            let mut insert_source_stmt = xaction.prepare(
+                r#"INSERT OR REPLACE INTO work_sources (screen_url, plugin_id, fetched_at, source)
+                VALUES (?, ?, ?, ?)"#,
+            )?;
+            let mut select_prior_stmt = xaction.prepare(&format!(
+                "SELECT {} FROM works WHERE screen_url = ?",
+                REVISION_COLUMNS.map(|(column, _)| column).join(", ")
+            ))?;
+            let mut insert_revision_stmt = xaction.prepare(
+                "INSERT INTO work_revisions (screen_url, changed_at, changes) VALUES (?, ?, ?)",
+            )?;
+            let mut insert_inbox_stmt = xaction.prepare(
+                r#"INSERT OR IGNORE INTO work_inbox (screen_url, plugin_id, for_tag, added_at)
+                VALUES (?, ?, ?, ?)"#,
+            )?;
+            let mut delete_mirrors_stmt =
+                xaction.prepare("DELETE FROM work_mirrors WHERE screen_url = ?")?;
+            let mut insert_mirror_stmt = xaction.prepare(
+                r#"INSERT OR IGNORE INTO work_mirrors (screen_url, role, url, position)
+                VALUES (?, ?, ?, ?)"#,
+            )?;
+            let mut delete_external_ids_stmt =
+                xaction.prepare("DELETE FROM work_external_ids WHERE screen_url = ?")?;
+            let mut insert_external_id_stmt = xaction.prepare(
+                r#"INSERT OR IGNORE INTO work_external_ids (screen_url, plugin_id, authority, value)
+                VALUES (?, ?, ?, ?)"#,
+            )?;
+            let fetched_at = Timestamp::now().as_millisecond();
 
             for work in chunk {
                 let params_array = params![
@@ -369,7 +1816,32 @@ pub fn upsert_works(
                     work.physical_data().map(|p| p.inscription()),
                     work.physical_data().map(|p| p.markings()),
                     work.physical_data().map(|p| p.watermarks()),
+                    // Note: the replace would otherwise reset when we first saw the work.
+                    work.screen_url(),
+                    fetched_at,
+                    work.remote_id(),
+                    work.model_url(),
+                    work.transcript_url(),
                 ];
+                match work_changes(&mut select_prior_stmt, work.screen_url(), params_array)? {
+                    None => {
+                        new_works += 1;
+                        insert_inbox_stmt.execute(params![
+                            work.screen_url(),
+                            plugin_id,
+                            for_tag,
+                            fetched_at
+                        ])?;
+                    }
+                    Some(changes) if !changes.is_empty() => {
+                        insert_revision_stmt.execute(params![
+                            work.screen_url(),
+                            fetched_at,
+                            serde_json::to_string(&changes)?
+                        ])?;
+                    }
+                    Some(_) => {}
+                }
                 let result =
                     insert_work_stmt.query_one(params_array, |row| row.get::<usize, i64>(0));
                 let work_id = match result {
@@ -395,21 +1867,205 @@ pub fn upsert_works(
                     }
                 }
 
-                let tag_ids: Vec<i64> = select_tag_ids_from_names
-                    .query_map([string_to_rarray(work.tags())], |row| row.get(0))?
-                    .flatten()
-                    .collect();
-                for tag_id in &tag_ids {
-                    insert_work_tag_stmt.execute(params![*tag_id, work_id])?;
+                // Note: tags the plugin hasn't told us about are skipped, as before.
+                work_tags.extend(
+                    work.tags()
+                        .iter()
+                        .filter_map(|name| tag_ids.get(name))
+                        .map(|tag_id| (*tag_id, work_id)),
+                );
+
+                // Note: the plugin knows best, so its mirrors replace any that we had.
+                delete_mirrors_stmt.execute(params![work.screen_url()])?;
+                for (position, (role, url)) in work.mirrors().iter().enumerate() {
+                    insert_mirror_stmt.execute(params![
+                        work.screen_url(),
+                        role.to_string(),
+                        url,
+                        position
+                    ])?;
+                }
+
+                delete_external_ids_stmt.execute(params![work.screen_url()])?;
+                for (authority, value) in work.external_ids() {
+                    insert_external_id_stmt.execute(params![
+                        work.screen_url(),
+                        plugin_id,
+                        authority.to_string(),
+                        value
+                    ])?;
+                }
+
+                if keep_sources && let Some(source) = work.source() {
+                    insert_source_stmt.execute(params![
+                        work.screen_url(),
+                        plugin_id,
+                        fetched_at,
+                        zstd::encode_all(source.as_bytes(), 0)?
+                    ])?;
                 }
             }
+            insert_work_tags(&xaction, &work_tags)?;
+            // Note: the source may still have tags that the user removed here; keep them off.
+            xaction.execute(
+                r#"DELETE FROM work_tags WHERE (tag_id, work_id) IN (
+                    SELECT tags.id, works.id FROM tag_edits AS e
+                    JOIN works ON works.screen_url = e.screen_url
+                    JOIN tags ON tags.name = e.tag
+                    WHERE e.added = 0)"#,
+                [],
+            )?;
         }
         xaction.commit()?;
 
         current_pos += chunk.len();
         progress.set_percent(current_pos, total_count);
     }
+    link_collection_members(&conn, plugin_id)?;
+    merge_same_objects(&conn, plugin_id)?;
+    apply_enriched_tags(&conn)?;
+    apply_tag_implications(&conn)?;
+
+    Ok(new_works)
+}
+
+// Record the groups a plugin reported, each with a tag to browse it by, and attach those tags to
+// the member works that we already have. Members are replaced wholesale, as the plugin knows best.
+pub fn upsert_groups(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    plugin_id: PluginId,
+    groups: &[Group],
+    log: &mut LogSender,
+) -> Result<()> {
+    log.info(format!(
+        "Writing {} collections for plugin {plugin_id} to the database...",
+        groups.len()
+    ));
+    let xaction = conn.transaction()?;
+    {
+        let mut insert_tag_stmt = xaction
+            .prepare("INSERT INTO tags (name, kind) VALUES (?, ?) ON CONFLICT DO NOTHING")?;
+        let mut select_tag_id_stmt = xaction.prepare("SELECT id FROM tags WHERE name = ?")?;
+        let mut insert_collection_stmt = xaction.prepare(
+            r#"INSERT INTO collections (tag_id, plugin_id, name, kind) VALUES (?, ?, ?, ?)
+            ON CONFLICT (tag_id) DO UPDATE SET name = excluded.name, kind = excluded.kind
+            RETURNING id"#,
+        )?;
+        let mut delete_members_stmt =
+            xaction.prepare("DELETE FROM collection_members WHERE collection_id = ?")?;
+        let mut insert_member_stmt = xaction.prepare(
+            "INSERT OR IGNORE INTO collection_members (collection_id, remote_id) VALUES (?, ?)",
+        )?;
+        for group in groups {
+            let tag_name = DbCollection::tag_name_for(group);
+            insert_tag_stmt.execute(params![tag_name, TagKind::Series.to_string()])?;
+            let tag_id: i64 = select_tag_id_stmt.query_row([&tag_name], |row| row.get(0))?;
+            let collection_id: i64 = insert_collection_stmt.query_row(
+                params![tag_id, plugin_id, group.name(), group.kind().to_string()],
+                |row| row.get(0),
+            )?;
+            delete_members_stmt.execute([collection_id])?;
+            for remote_id in group.members() {
+                insert_member_stmt.execute(params![collection_id, remote_id])?;
+            }
+        }
+    }
+    link_collection_members(&xaction, plugin_id)?;
+    xaction.commit()?;
+    Ok(())
+}
+
+// Tag the works we have from the plugin with the collections that list them.
+// Note: remote ids are only unique within a plugin, so works have to have a tag from the plugin
+//       that reported the collection to count.
+fn link_collection_members(conn: &Connection, plugin_id: PluginId) -> Result<()> {
+    conn.execute(
+        r#"INSERT OR IGNORE INTO work_tags (tag_id, work_id)
+        SELECT collections.tag_id, works.id
+        FROM collections
+            JOIN collection_members ON collection_members.collection_id = collections.id
+            JOIN works ON works.remote_id = collection_members.remote_id
+        WHERE collections.plugin_id = ?1
+            AND EXISTS (
+                SELECT 1 FROM work_tags
+                    JOIN plugin_tags ON plugin_tags.tag_id = work_tags.tag_id
+                WHERE work_tags.work_id = works.id AND plugin_tags.plugin_id = ?1
+            )"#,
+        params![plugin_id],
+    )?;
+    Ok(())
+}
+
+// Works from different plugins with the same Wikidata id are the same physical object, so share
+// the tags that each plugin gave it between them, both ways. Tags the user took off of a work stay
+// off of it. Note: the other authorities don't name the object: a ULAN id is the artist, and
+// accession numbers are only unique within one museum.
+fn merge_same_objects(conn: &Connection, plugin_id: PluginId) -> Result<()> {
+    conn.execute(
+        r#"INSERT OR IGNORE INTO work_tags (tag_id, work_id)
+        SELECT other_tags.tag_id, mine.id
+        FROM work_external_ids AS a
+            JOIN work_external_ids AS b
+                ON b.authority = a.authority AND b.value = a.value AND b.screen_url != a.screen_url
+            JOIN works AS mine ON mine.screen_url = a.screen_url
+            JOIN works AS other ON other.screen_url = b.screen_url
+            JOIN work_tags AS other_tags ON other_tags.work_id = other.id
+        WHERE a.authority = ?1 AND (a.plugin_id = ?2 OR b.plugin_id = ?2)
+            AND NOT EXISTS (
+                SELECT 1 FROM tag_edits AS e
+                    JOIN tags ON tags.name = e.tag
+                WHERE e.screen_url = mine.screen_url AND tags.id = other_tags.tag_id
+                    AND e.added = 0
+            )"#,
+        params![Authority::Wikidata.to_string(), plugin_id],
+    )?;
+    Ok(())
+}
+
+// Look up the ids of any tags in the chunk that we haven't seen yet, all at once.
+fn cache_tag_ids(
+    conn: &Connection,
+    works: &[Work],
+    tag_ids: &mut HashMap<String, i64>,
+) -> Result<()> {
+    let unknown = works
+        .iter()
+        .flat_map(|work| work.tags())
+        .filter(|name| !tag_ids.contains_key(name.as_str()))
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare_cached("SELECT name, id FROM tags WHERE name IN rarray(?)")?;
+    for row in stmt.query_map([string_to_rarray(&unknown)], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    })? {
+        let (name, id) = row?;
+        tag_ids.insert(name, id);
+    }
+    Ok(())
+}
 
+// Insert (tag_id, work_id) links many rows per statement, which is much faster than one by one.
+fn insert_work_tags(conn: &Connection, links: &[(i64, i64)]) -> Result<()> {
+    // Note: stay well under SQLite's limit on bound parameters, which is 999 in older builds.
+    const ROWS_PER_INSERT: usize = 400;
+    for batch in links.chunks(ROWS_PER_INSERT) {
+        let query = format!(
+            "INSERT OR IGNORE INTO work_tags (tag_id, work_id) VALUES {}",
+            vec!["(?, ?)"; batch.len()].join(", ")
+        );
+        // Note: batches are full size except the last, so most share one cached statement.
+        let mut stmt = conn.prepare_cached(&query)?;
+        let params = batch
+            .iter()
+            .flat_map(|(tag_id, work_id)| [tag_id, work_id])
+            .collect::<Vec<_>>();
+        stmt.execute(params_from_iter(params))?;
+    }
     Ok(())
 }
 
@@ -417,52 +2073,514 @@ pub fn update_work_paths(
     conn: &PooledConnection<SqliteConnectionManager>,
     screen_url: &str,
     preview_path: &str,
-    screen_path: &str,
-    archive_path: Option<&str>,
+    (screen_path, archive_path, thumb_path): (Option<&str>, Option<&str>, Option<&str>),
+    (aspect_ratio, file_size): (Option<f32>, Option<i64>),
     host: &mut HostUpdateSender,
 ) -> Result<()> {
     assert!(!screen_url.is_empty(), "have a path for empty screen url");
     assert!(!preview_path.is_empty(), "empty preview path");
-    assert!(!screen_path.is_empty(), "empty screen path");
-    let work_id: i64 = conn.query_one(
-        "SELECT id FROM works WHERE screen_url = ?",
-        [screen_url],
-        |row| row.get(0),
+    assert!(screen_path != Some(""), "empty screen path");
+    // Note: a preview only says what the work is when there is no screen file; see DbWork.
+    let media_type = |path: &str| media_type_of(Path::new(path)).map(|kind| kind.name());
+    let screen_type = screen_path.and_then(media_type);
+    let preview_type = media_type(preview_path);
+    // Note: the fetch policy may have left out the screen or archive files, in which case we
+    //       keep whatever we fetched for them before.
+    let (work_id, screen_path, archive_path, thumb_path, aspect_ratio): (
+        i64,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<f32>,
+    ) = conn.query_one(
+        r#"UPDATE works SET
+            preview_path = ?,
+            screen_path = COALESCE(?, screen_path),
+            archive_path = COALESCE(?, archive_path),
+            thumb_path = COALESCE(?, thumb_path),
+            aspect_ratio = COALESCE(?, aspect_ratio),
+            media_type = COALESCE(?, IIF(screen_path IS NULL, ?, media_type)),
+            file_size = COALESCE(?, file_size),
+            last_accessed = ?
+        WHERE screen_url = ?
+        RETURNING id, screen_path, archive_path, thumb_path, aspect_ratio"#,
+        params![
+            preview_path,
+            screen_path,
+            archive_path,
+            thumb_path,
+            aspect_ratio,
+            screen_type,
+            preview_type,
+            file_size,
+            Timestamp::now().as_millisecond(),
+            screen_url
+        ],
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        },
     )?;
-    let row_cnt = conn.execute(
-        "UPDATE works SET preview_path = ?, screen_path = ?, archive_path = ? WHERE id = ?",
-        params![preview_path, screen_path, archive_path, work_id],
-    )?;
-    ensure!(row_cnt == 1);
     host.note_completed_download(
         WorkId::wrap(work_id),
         preview_path,
-        screen_path,
-        archive_path,
+        (
+            screen_path.as_deref(),
+            archive_path.as_deref(),
+            thumb_path.as_deref(),
+        ),
+        aspect_ratio,
     )?;
     Ok(())
 }
 
-fn set_work_favorite(
+pub fn set_work_thumb_path(
     conn: &PooledConnection<SqliteConnectionManager>,
     work_id: WorkId,
-    favorite: bool,
+    (thumb_path, aspect_ratio): (&str, f32),
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let (preview_path, screen_path, archive_path): (
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    ) = conn.query_one(
+        r#"UPDATE works SET thumb_path = ?, aspect_ratio = ? WHERE id = ?
+            RETURNING preview_path, screen_path, archive_path"#,
+        params![thumb_path, aspect_ratio, work_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if let Some(preview_path) = preview_path {
+        host.note_completed_download(
+            work_id,
+            &preview_path,
+            (
+                screen_path.as_deref(),
+                archive_path.as_deref(),
+                Some(thumb_path),
+            ),
+            Some(aspect_ratio),
+        )?;
+    }
+    Ok(())
+}
+
+// Note: logs are only for looking back at recent trouble, so we only keep the newest lines.
+const MAX_PERSISTED_LOGS_PER_PLUGIN: usize = 20_000;
+
+fn append_plugin_logs(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    lines: &[DbLogLine],
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    let mut plugin_ids = HashSet::new();
+    {
+        let mut insert = xaction.prepare(
+            "INSERT INTO plugin_logs (plugin_id, level, timestamp, job, message) VALUES (?, ?, ?, ?, ?)",
+        )?;
+        for line in lines {
+            insert.execute(params![
+                line.plugin_id(),
+                line.level().as_str(),
+                line.timestamp().as_millisecond(),
+                line.job(),
+                line.message()
+            ])?;
+            plugin_ids.insert(line.plugin_id());
+        }
+    }
+    for plugin_id in plugin_ids {
+        xaction.execute(
+            r#"DELETE FROM plugin_logs WHERE plugin_id = ? AND id <= (
+                SELECT id FROM plugin_logs WHERE plugin_id = ? ORDER BY id DESC LIMIT 1 OFFSET ?
+            )"#,
+            params![plugin_id, plugin_id, MAX_PERSISTED_LOGS_PER_PLUGIN],
+        )?;
+    }
+    xaction.commit()?;
+    Ok(())
+}
+
+fn set_work_flags(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    flags: &[PendingFlag],
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    {
+        let mut set_favorite =
+            xaction.prepare("UPDATE works SET favorite = ?, favorite_mtime = ? WHERE id = ?")?;
+        let mut set_hidden =
+            xaction.prepare("UPDATE works SET hidden = ?, hidden_mtime = ? WHERE id = ?")?;
+        for pending in flags {
+            let stmt = match pending.flag {
+                WorkFlag::Favorite => &mut set_favorite,
+                WorkFlag::Hidden => &mut set_hidden,
+            };
+            stmt.execute(params![pending.value, pending.at, pending.work_id])?;
+        }
+    }
+    xaction.commit()?;
+
+    for pending in flags {
+        match pending.flag {
+            WorkFlag::Favorite => {
+                host.note_work_favorite_status_changed(pending.work_id, pending.value)?;
+            }
+            WorkFlag::Hidden => {
+                host.note_work_hidden_status_changed(pending.work_id, pending.value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn set_display_transform(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    transform: &DisplayTransform,
+) -> Result<()> {
+    if transform.is_identity() {
+        conn.execute(
+            "DELETE FROM work_display WHERE screen_url = ?",
+            [screen_url],
+        )?;
+    } else {
+        conn.execute(
+            r#"INSERT OR REPLACE INTO work_display (screen_url, transform, updated_at)
+            VALUES (?, ?, ?)"#,
+            params![
+                screen_url,
+                serde_json::to_string(transform)?,
+                Timestamp::now().as_millisecond()
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// Point the work at the copied file and its thumbnail, once the work exists.
+fn finish_local_file(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    file: &LocalFile,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let thumb_path = file.thumb.as_ref().map(|thumb| thumb.path.as_str());
+    update_work_paths(
+        conn,
+        file.work.screen_url(),
+        thumb_path.unwrap_or(&file.screen_path),
+        (Some(&file.screen_path), None, thumb_path),
+        (
+            file.thumb.as_ref().map(|thumb| thumb.aspect_ratio),
+            file.file_size,
+        ),
+        host,
+    )?;
+    if let Some(score) = file.thumb.as_ref().and_then(|thumb| thumb.nsfw_score) {
+        set_nsfw_score(conn, file.work.screen_url(), score, host)?;
+    }
+    Ok(())
+}
+
+fn set_work_note(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    body: &str,
+) -> Result<()> {
+    if body.trim().is_empty() {
+        conn.execute("DELETE FROM work_notes WHERE screen_url = ?", [screen_url])?;
+    } else {
+        // Note: not INSERT OR REPLACE, which would lose when the note was first written.
+        let now = Timestamp::now().as_millisecond();
+        conn.execute(
+            r#"INSERT INTO work_notes (screen_url, body, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?3)
+            ON CONFLICT (screen_url) DO UPDATE
+                SET body = excluded.body, updated_at = excluded.updated_at"#,
+            params![screen_url, body, now],
+        )?;
+    }
+    Ok(())
+}
+
+fn set_transcript(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    source: TranscriptSource,
+    cues: &[Cue],
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    let transcript_id: i64 = xaction.query_row(
+        r#"INSERT INTO work_transcripts (screen_url, source, created_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT (screen_url) DO UPDATE
+            SET source = excluded.source, created_at = excluded.created_at
+        RETURNING id"#,
+        params![
+            screen_url,
+            source.to_string(),
+            Timestamp::now().as_millisecond()
+        ],
+        |row| row.get(0),
+    )?;
+    // Note: the upsert keeps the transcript's id, so the old cues are still under it.
+    xaction.execute(
+        "DELETE FROM transcript_cues WHERE transcript_id = ?",
+        params![transcript_id],
+    )?;
+    {
+        let mut insert_cue_stmt = xaction.prepare(
+            r#"INSERT INTO transcript_cues (transcript_id, start_secs, end_secs, body)
+            VALUES (?, ?, ?, ?)"#,
+        )?;
+        for cue in cues {
+            insert_cue_stmt.execute(params![transcript_id, cue.start, cue.end, cue.text])?;
+        }
+    }
+    xaction.commit()?;
+    Ok(())
+}
+
+fn add_work_mirrors(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    mirrors: &[(MediaRole, String)],
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    xaction.execute(
+        "UPDATE work_mirrors SET position = position + ? WHERE screen_url = ?",
+        params![mirrors.len(), screen_url],
+    )?;
+    {
+        // Note: a mirror we already had moves to the front.
+        let mut insert_mirror_stmt = xaction.prepare(
+            r#"INSERT OR REPLACE INTO work_mirrors (screen_url, role, url, position)
+            VALUES (?, ?, ?, ?)"#,
+        )?;
+        for (position, (role, url)) in mirrors.iter().enumerate() {
+            insert_mirror_stmt.execute(params![screen_url, role.to_string(), url, position])?;
+        }
+    }
+    xaction.commit()?;
+    Ok(())
+}
+
+fn record_link_checks(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    checks: &[LinkCheck],
+) -> Result<()> {
+    let checked_at = Timestamp::now().as_millisecond();
+    let mut stmt = conn.prepare(
+        r#"INSERT OR REPLACE INTO link_checks (screen_url, checked_at, status, http_status)
+        VALUES (?, ?, ?, ?)"#,
+    )?;
+    for check in checks {
+        stmt.execute(params![
+            check.screen_url,
+            checked_at,
+            check.status.to_string(),
+            check.http_status
+        ])?;
+    }
+    Ok(())
+}
+
+fn set_nsfw_score(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    score: f32,
+    host: &mut HostUpdateSender,
+) -> Result<()> {
+    let work_id = conn
+        .query_row(
+            "UPDATE works SET nsfw_score = ? WHERE screen_url = ? RETURNING id",
+            params![score, screen_url],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(work_id) = work_id {
+        host.note_work_nsfw_scored(WorkId::wrap(work_id), score)?;
+    }
+    Ok(())
+}
+
+// Returns whether there are new suggestions to review.
+fn save_detections(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    detections: &[Detection],
+    suggestions: &[(String, String)],
+) -> Result<bool> {
+    let xaction = conn.transaction()?;
+    xaction.execute(
+        "DELETE FROM detections WHERE screen_url = ?",
+        params![screen_url],
+    )?;
+    xaction.execute(
+        "INSERT OR REPLACE INTO detection_scans (screen_url, scanned_at) VALUES (?, ?)",
+        params![screen_url, Timestamp::now().as_millisecond()],
+    )?;
+    let mut suggested = 0;
+    {
+        let mut insert_detection = xaction.prepare(
+            r#"INSERT INTO detections (screen_url, kind, x, y, width, height, confidence)
+            VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+        )?;
+        for d in detections {
+            insert_detection.execute(params![
+                screen_url,
+                d.kind.name(),
+                d.x,
+                d.y,
+                d.width,
+                d.height,
+                d.confidence
+            ])?;
+        }
+        // Note: there is no point suggesting a tag that the work already has, and the unique
+        //       key keeps us from suggesting one again that the user already rejected.
+        let mut insert_suggestion = xaction.prepare(
+            r#"INSERT OR IGNORE INTO tag_suggestions (screen_url, tag, reason)
+            SELECT ?1, ?2, ?3 WHERE NOT EXISTS (
+                SELECT 1 FROM works
+                JOIN work_tags ON work_tags.work_id = works.id
+                JOIN tags ON tags.id = work_tags.tag_id
+                WHERE works.screen_url = ?1 AND tags.name = ?2
+            )"#,
+        )?;
+        for (tag, reason) in suggestions {
+            suggested += insert_suggestion.execute(params![screen_url, tag, reason])?;
+        }
+    }
+    xaction.commit()?;
+    Ok(suggested > 0)
+}
+
+// Accepted suggestions become tags of the Local Files plugin, like the tag on local imports.
+// Returns the tags that works were added to.
+fn review_tag_suggestions(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    ids: &[SuggestionId],
+    accept: bool,
+) -> Result<Vec<String>> {
+    let plugin_id = local_plugin_id(conn)?;
+    let status = if accept { "accepted" } else { "rejected" };
+    let mut tags = HashSet::new();
+    let xaction = conn.transaction()?;
+    {
+        let mut select_tag = xaction
+            .prepare("SELECT tag FROM tag_suggestions WHERE id = ? AND status = 'pending'")?;
+        let mut set_status =
+            xaction.prepare("UPDATE tag_suggestions SET status = ? WHERE id = ?")?;
+        // Note: the tag may already exist, from a plugin; leave its kind and wiki link alone.
+        let mut insert_tag = xaction.prepare("INSERT OR IGNORE INTO tags (name) VALUES (?)")?;
+        let mut insert_plugin_tag = xaction.prepare(
+            r#"INSERT OR IGNORE INTO plugin_tags (plugin_id, tag_id)
+            SELECT ?, id FROM tags WHERE name = ?"#,
+        )?;
+        let mut insert_work_tag = xaction.prepare(
+            r#"INSERT OR IGNORE INTO work_tags (tag_id, work_id)
+            SELECT tags.id, works.id FROM tag_suggestions AS s
+            JOIN works ON works.screen_url = s.screen_url
+            JOIN tags ON tags.name = s.tag
+            WHERE s.id = ?"#,
+        )?;
+        let mut insert_edit = xaction.prepare(
+            r#"INSERT OR REPLACE INTO tag_edits (screen_url, tag, added, edited_at)
+            SELECT screen_url, tag, 1, ? FROM tag_suggestions WHERE id = ?"#,
+        )?;
+        let edited_at = Timestamp::now().as_millisecond();
+        for id in ids {
+            let Some(tag) = select_tag
+                .query_row(params![id], |row| row.get::<_, String>(0))
+                .optional()?
+            else {
+                continue;
+            };
+            if accept {
+                if tags.insert(tag.clone()) {
+                    insert_tag.execute(params![tag])?;
+                    insert_plugin_tag.execute(params![plugin_id, tag])?;
+                }
+                insert_work_tag.execute(params![id])?;
+                insert_edit.execute(params![edited_at, id])?;
+            }
+            set_status.execute(params![status, id])?;
+        }
+    }
+    xaction.commit()?;
+    Ok(tags.into_iter().collect())
+}
+
+fn remove_work_tag(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    tag: &str,
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    xaction.execute(
+        r#"DELETE FROM work_tags
+        WHERE work_id = (SELECT id FROM works WHERE screen_url = ?)
+            AND tag_id = (SELECT id FROM tags WHERE name = ?)"#,
+        params![screen_url, tag],
+    )?;
+    xaction.execute(
+        r#"INSERT OR REPLACE INTO tag_edits (screen_url, tag, added, edited_at)
+        VALUES (?, ?, 0, ?)"#,
+        params![screen_url, tag, Timestamp::now().as_millisecond()],
+    )?;
+    xaction.commit()?;
+    Ok(())
+}
+
+// Note: only the edits that were sent; the user may have changed their mind about a tag since.
+fn mark_tag_edits_pushed(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    screen_url: &str,
+    edit: &WorkTagEdit,
+) -> Result<()> {
+    let pushed_at = Timestamp::now().as_millisecond();
+    let xaction = conn.transaction()?;
+    {
+        let mut set_pushed = xaction.prepare(
+            r#"UPDATE tag_edits SET pushed_at = ?
+            WHERE screen_url = ? AND tag = ? AND added = ? AND pushed_at IS NULL"#,
+        )?;
+        let added = edit.added().iter().map(|tag| (tag, true));
+        let removed = edit.removed().iter().map(|tag| (tag, false));
+        for (tag, added) in added.chain(removed) {
+            set_pushed.execute(params![pushed_at, screen_url, tag, added])?;
+        }
+    }
+    xaction.commit()?;
+    Ok(())
+}
+
+fn review_inbox_works(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    screen_urls: &[String],
 ) -> Result<()> {
     conn.execute(
-        "UPDATE works SET favorite = ? WHERE id = ?",
-        params![favorite, work_id],
+        "DELETE FROM work_inbox WHERE screen_url IN rarray(?)",
+        [string_to_rarray(screen_urls)],
     )?;
     Ok(())
 }
 
-fn set_work_hidden(
+fn set_work_rating(
     conn: &PooledConnection<SqliteConnectionManager>,
     work_id: WorkId,
-    hidden: bool,
+    rating: u8,
 ) -> Result<()> {
     conn.execute(
-        "UPDATE works SET hidden = ? WHERE id = ?",
-        params![hidden, work_id],
+        "UPDATE works SET rating = ?, rating_mtime = ? WHERE id = ?",
+        params![rating, Timestamp::now().as_millisecond(), work_id],
     )?;
     Ok(())
 }
@@ -473,8 +2591,86 @@ fn set_tag_favorite(
     favorite: bool,
 ) -> Result<()> {
     conn.execute(
-        "UPDATE tags SET favorite = ? WHERE id = ?",
-        params![favorite, tag_id],
+        "UPDATE tags SET favorite = ?, favorite_mtime = ? WHERE id = ?",
+        params![favorite, Timestamp::now().as_millisecond(), tag_id],
+    )?;
+    Ok(())
+}
+
+fn save_exhibition(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    name: &str,
+    items: &[ExhibitionItem],
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    write_exhibition(&xaction, name, items, Timestamp::now().as_millisecond())?;
+    xaction.commit()?;
+    Ok(())
+}
+
+// Note: exhibitions are small and edited as a whole, so we rewrite every item on each save rather
+//       than trying to track moves.
+pub fn write_exhibition(
+    conn: &Connection,
+    name: &str,
+    items: &[ExhibitionItem],
+    updated_at: i64,
+) -> Result<()> {
+    let exhibition_id: i64 = conn.query_row(
+        r#"INSERT INTO exhibitions (name, deleted, updated_at) VALUES (?, false, ?)
+        ON CONFLICT (name) DO UPDATE SET deleted = false, updated_at = excluded.updated_at
+        RETURNING id"#,
+        params![name, updated_at],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "DELETE FROM exhibition_items WHERE exhibition_id = ?",
+        [exhibition_id],
+    )?;
+    let mut insert_stmt = conn.prepare(
+        r#"INSERT INTO exhibition_items
+            (exhibition_id, position, screen_url, title, caption, notes)
+        VALUES (?, ?, ?, ?, ?, ?)"#,
+    )?;
+    for (position, item) in items.iter().enumerate() {
+        let title = match item {
+            ExhibitionItem::Section { title, .. } => Some(title.as_str()),
+            ExhibitionItem::Work { .. } => None,
+        };
+        insert_stmt.execute(params![
+            exhibition_id,
+            i64::try_from(position)?,
+            item.screen_url(),
+            title,
+            item.caption(),
+            item.notes()
+        ])?;
+    }
+    Ok(())
+}
+
+fn delete_exhibition(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    name: &str,
+) -> Result<()> {
+    let xaction = conn.transaction()?;
+    remove_exhibition(&xaction, name, Timestamp::now().as_millisecond())?;
+    xaction.commit()?;
+    Ok(())
+}
+
+// Note: the exhibition is kept, empty, so that metadata sync can pass the delete on to other
+//       devices, rather than bringing the exhibition back from them.
+pub fn remove_exhibition(conn: &Connection, name: &str, updated_at: i64) -> Result<()> {
+    conn.execute(
+        r#"DELETE FROM exhibition_items
+        WHERE exhibition_id IN (SELECT id FROM exhibitions WHERE name = ?)"#,
+        [name],
+    )?;
+    conn.execute(
+        r#"INSERT INTO exhibitions (name, deleted, updated_at) VALUES (?, true, ?)
+        ON CONFLICT (name) DO UPDATE SET deleted = true, updated_at = excluded.updated_at"#,
+        params![name, updated_at],
     )?;
     Ok(())
 }
@@ -485,8 +2681,29 @@ fn set_tag_hidden(
     hidden: bool,
 ) -> Result<()> {
     conn.execute(
-        "UPDATE tags SET hidden = ? WHERE id = ?",
-        params![hidden, tag_id],
+        "UPDATE tags SET hidden = ?, hidden_mtime = ? WHERE id = ?",
+        params![hidden, Timestamp::now().as_millisecond(), tag_id],
+    )?;
+    Ok(())
+}
+
+// Note: a newly watched tag starts counting from now, rather than calling everything it has new.
+fn set_tag_watched(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    tag_id: TagId,
+    watched: bool,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE tags SET watched = ?, last_seen = COALESCE(last_seen, ?) WHERE id = ?",
+        params![watched, Timestamp::now().as_millisecond(), tag_id],
+    )?;
+    Ok(())
+}
+
+fn mark_tag_seen(conn: &PooledConnection<SqliteConnectionManager>, tag_id: TagId) -> Result<()> {
+    conn.execute(
+        "UPDATE tags SET last_seen = ? WHERE id = ?",
+        params![Timestamp::now().as_millisecond(), tag_id],
     )?;
     Ok(())
 }
@@ -0,0 +1,249 @@
+use crate::{
+    db::{
+        models::{
+            annotation::DbAnnotation,
+            tag::{DbTag, TagId},
+            work::{DbWork, WorkId},
+        },
+        reader::PublicView,
+        sync::DbSyncHandle,
+        writer::DbWriteHandle,
+    },
+    http::server::{HttpRequest, HttpResponse},
+    shared::{metrics, storage::Storage, update::DataUpdate},
+};
+use anyhow::Result;
+use crossbeam::channel::Sender;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::json;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+
+// A work's metadata, with what the user has marked on it.
+#[derive(Serialize)]
+struct WorkMetadata {
+    #[serde(flatten)]
+    work: DbWork,
+    annotations: Vec<DbAnnotation>,
+}
+
+// Everything the HTTP worker threads need to answer requests. Reads go straight to the DB
+// through the sync handle; writes go through the writer, same as the UX, so that the UX
+// hears about them. Plugin work gets forwarded to the PluginHost on the UX thread.
+#[derive(Debug)]
+pub struct ApiContext {
+    storage: Storage,
+    db_sync: DbSyncHandle,
+    db_write: DbWriteHandle,
+    tx_to_app: Sender<DataUpdate>,
+    // None until the UX has resolved its content filters against the tags.
+    public_view: RwLock<Option<PublicView>>,
+}
+
+impl ApiContext {
+    pub fn new(
+        storage: Storage,
+        db_sync: DbSyncHandle,
+        db_write: DbWriteHandle,
+        tx_to_app: Sender<DataUpdate>,
+    ) -> Self {
+        Self {
+            storage,
+            db_sync,
+            db_write,
+            tx_to_app,
+            public_view: RwLock::new(None),
+        }
+    }
+
+    pub fn set_public_view(&self, view: PublicView) {
+        *self.public_view.write() = Some(view);
+    }
+
+    // Requests without the token only get here for GETs with public browsing on; they see what
+    // the gallery would show and nothing else.
+    pub fn handle(&self, req: &HttpRequest, authorized: bool) -> HttpResponse {
+        // Note: take a copy, so that the UX isn't kept waiting on a slow request to update it.
+        let view = self.public_view.read().clone();
+        let public = match (authorized, view) {
+            (true, _) => None,
+            (false, Some(view)) => Some(view),
+            (false, None) => return HttpResponse::service_unavailable(),
+        };
+        match self.route(req, public.as_ref()) {
+            Ok(resp) => resp,
+            Err(e) => HttpResponse::text(500, &e.to_string()),
+        }
+    }
+
+    fn get_tag(&self, tag_id: TagId, public: Option<&PublicView>) -> Result<Option<DbTag>> {
+        Ok(self
+            .db_sync
+            .sync_get_tag(tag_id)?
+            .filter(|tag| public.is_none() || !tag.hidden()))
+    }
+
+    fn get_work(&self, work_id: WorkId, public: Option<&PublicView>) -> Result<Option<DbWork>> {
+        if let Some(view) = public
+            && !self.db_sync.sync_is_public_work(work_id, view)?
+        {
+            return Ok(None);
+        }
+        self.db_sync.sync_get_work(work_id)
+    }
+
+    fn route(&self, req: &HttpRequest, public: Option<&PublicView>) -> Result<HttpResponse> {
+        let segments = req.segments();
+        Ok(match (req.method(), segments.as_slice()) {
+            ("GET", ["api", "status"]) => HttpResponse::json(&json!({
+                "name": "artchiver",
+                "version": env!("CARGO_PKG_VERSION"),
+            })),
+
+            // Note: this says which plugins we run and how big the archive is, so it always
+            //       takes the token.
+            ("GET", ["metrics"]) if public.is_some() => HttpResponse::unauthorized(),
+            ("GET", ["metrics"]) => HttpResponse::new(
+                200,
+                "text/plain; version=0.0.4; charset=utf-8",
+                metrics::render_prometheus().into_bytes(),
+            ),
+
+            // Tags
+            ("GET", ["api", "tags"]) => {
+                let mut tags = self.db_sync.sync_list_tags()?;
+                if public.is_some() {
+                    tags.retain(|tag| !tag.hidden());
+                }
+                HttpResponse::json(&tags)
+            }
+            ("GET", ["api", "tags", id]) => match parse_tag_id(id) {
+                Some(tag_id) => match self.get_tag(tag_id, public)? {
+                    Some(tag) => HttpResponse::json(&tag),
+                    None => HttpResponse::not_found(),
+                },
+                None => HttpResponse::bad_request("invalid tag id"),
+            },
+            ("GET", ["api", "tags", id, "works"]) => {
+                let Some(tag_id) = parse_tag_id(id) else {
+                    return Ok(HttpResponse::bad_request("invalid tag id"));
+                };
+                if self.get_tag(tag_id, public)?.is_none() {
+                    return Ok(HttpResponse::not_found());
+                }
+                let after =
+                    WorkId::wrap(req.query("after").and_then(|v| v.parse().ok()).unwrap_or(0));
+                let limit = req
+                    .query("limit")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_PAGE_SIZE)
+                    .min(MAX_PAGE_SIZE);
+                HttpResponse::json(
+                    &self
+                        .db_sync
+                        .sync_list_works_for_tag(tag_id, after, limit, public)?,
+                )
+            }
+            ("POST", ["api", "tags", id, "refresh"]) => {
+                let Some(tag) =
+                    parse_tag_id(id).and_then(|id| self.db_sync.sync_get_tag(id).ok()?)
+                else {
+                    return Ok(HttpResponse::not_found());
+                };
+                self.tx_to_app
+                    .send(DataUpdate::RefreshWorksForTagRequested { tag })?;
+                HttpResponse::ok()
+            }
+            ("POST", ["api", "tags", id, flag @ ("favorite" | "hidden")]) => {
+                let (Some(tag_id), Some(value)) = (parse_tag_id(id), parse_flag(req)) else {
+                    return Ok(HttpResponse::bad_request(
+                        "expected a tag id and ?value=bool",
+                    ));
+                };
+                if *flag == "favorite" {
+                    self.db_write.set_tag_favorite(tag_id, value)?;
+                } else {
+                    self.db_write.set_tag_hidden(tag_id, value)?;
+                }
+                HttpResponse::ok()
+            }
+
+            // Works
+            ("GET", ["api", "works", id]) => match parse_work_id(id) {
+                Some(work_id) => match self.get_work(work_id, public)? {
+                    Some(work) => HttpResponse::json(&WorkMetadata {
+                        annotations: self.db_sync.sync_list_annotations(work.screen_url())?,
+                        work,
+                    }),
+                    None => HttpResponse::not_found(),
+                },
+                None => HttpResponse::bad_request("invalid work id"),
+            },
+            (
+                "GET",
+                [
+                    "api",
+                    "works",
+                    id,
+                    kind @ ("preview" | "screen" | "archive" | "model"),
+                ],
+            ) => {
+                let Some(work) = parse_work_id(id).and_then(|id| self.get_work(id, public).ok()?)
+                else {
+                    return Ok(HttpResponse::not_found());
+                };
+                let path = match *kind {
+                    "preview" => work.preview_path(),
+                    "screen" => work.screen_path(),
+                    "model" => work.model_path(),
+                    _ => work.archive_path(),
+                };
+                match path {
+                    Some(path) => HttpResponse::file(self.storage.ensure_local(path)?)
+                        .with_header("Cache-Control", "private, max-age=86400"),
+                    None => HttpResponse::not_found(),
+                }
+            }
+            ("POST", ["api", "works", id, flag @ ("favorite" | "hidden")]) => {
+                let (Some(work_id), Some(value)) = (parse_work_id(id), parse_flag(req)) else {
+                    return Ok(HttpResponse::bad_request(
+                        "expected a work id and ?value=bool",
+                    ));
+                };
+                if *flag == "favorite" {
+                    self.db_write.set_work_favorite(work_id, value)?;
+                } else {
+                    self.db_write.set_work_hidden(work_id, value)?;
+                }
+                // Note: API callers expect to read back what they wrote.
+                self.db_write.flush_work_flags()?;
+                HttpResponse::ok()
+            }
+
+            // Plugins
+            ("POST", ["api", "plugins", "refresh-tags"]) => {
+                self.tx_to_app.send(DataUpdate::RefreshTagsRequested)?;
+                HttpResponse::ok()
+            }
+
+            (_, ["api", ..]) if req.method() != "GET" && req.method() != "POST" => {
+                HttpResponse::method_not_allowed()
+            }
+            _ => HttpResponse::not_found(),
+        })
+    }
+}
+
+fn parse_tag_id(s: &str) -> Option<TagId> {
+    s.parse().ok().map(TagId::wrap)
+}
+
+fn parse_work_id(s: &str) -> Option<WorkId> {
+    s.parse().ok().map(WorkId::wrap)
+}
+
+fn parse_flag(req: &HttpRequest) -> Option<bool> {
+    req.query("value").and_then(|v| v.parse().ok())
+}
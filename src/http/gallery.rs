@@ -0,0 +1,22 @@
+use crate::http::server::{HttpRequest, HttpResponse};
+
+// The web gallery is a handful of static files compiled into the binary, so that it always
+// matches the API it talks to. It contains no data itself, so it doesn't need a token.
+const INDEX_HTML: &str = include_str!("../../assets/web/index.html");
+const GALLERY_JS: &str = include_str!("../../assets/web/gallery.js");
+const GALLERY_CSS: &str = include_str!("../../assets/web/gallery.css");
+const FAVICON: &[u8] = include_bytes!("../../assets/favicon.ico");
+
+pub fn serve_static(req: &HttpRequest) -> Option<HttpResponse> {
+    if req.method() != "GET" {
+        return None;
+    }
+    let (content_type, body) = match req.path() {
+        "/" | "/index.html" => ("text/html; charset=utf-8", INDEX_HTML.as_bytes()),
+        "/gallery.js" => ("text/javascript; charset=utf-8", GALLERY_JS.as_bytes()),
+        "/gallery.css" => ("text/css; charset=utf-8", GALLERY_CSS.as_bytes()),
+        "/favicon.ico" => ("image/x-icon", FAVICON),
+        _ => return None,
+    };
+    Some(HttpResponse::new(200, content_type, body.to_vec()))
+}
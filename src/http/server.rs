@@ -0,0 +1,614 @@
+use crate::{
+    db::{reader::PublicView, sync::DbSyncHandle, writer::DbWriteHandle},
+    http::{api::ApiContext, gallery::serve_static},
+    shared::{environment::Environment, metrics, progress::ProgressMonitor},
+};
+use anyhow::{Result, bail, ensure};
+use log::{debug, info, warn};
+use rand::{Rng as _, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read as _, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
+const MAX_HEADERS: usize = 100;
+// Connections that we will serve at once; any more are turned away rather than given a thread.
+const MAX_CONNECTIONS: usize = 32;
+
+pub fn make_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    enabled: bool,
+    bind_address: String,
+    token: String,
+    // Let anyone who can reach us use the read-only endpoints (and the web gallery) without
+    // the token. Changes still require the token.
+    public_browsing: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:8421".to_owned(),
+            token: make_token(),
+            public_browsing: false,
+        }
+    }
+}
+
+// Decode %XX escapes, and `+` as space if this came from a query string.
+pub fn percent_decode(s: &str, plus_is_space: bool) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| char::from(b).to_digit(16);
+                if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    out.push((hi * 16 + lo) as u8);
+                    i += 3;
+                    continue;
+                }
+                out.push(b'%');
+            }
+            b'+' if plus_is_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+}
+
+impl HttpRequest {
+    // We only ever need the request line and headers: all of our control endpoints take
+    // their arguments in the query string.
+    pub fn read(stream: &mut impl BufRead) -> Result<Self> {
+        let mut line = String::new();
+        stream.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            bail!("malformed request line");
+        };
+        let method = method.to_ascii_uppercase();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let path = percent_decode(path, false);
+        let query = query
+            .split('&')
+            .filter(|kv| !kv.is_empty())
+            .map(|kv| {
+                let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+                (percent_decode(k, true), percent_decode(v, true))
+            })
+            .collect();
+
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line)? == 0 {
+                break;
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((k, v)) = header.split_once(':') {
+                headers.insert(k.trim().to_ascii_lowercase(), v.trim().to_owned());
+            }
+            ensure!(headers.len() <= MAX_HEADERS, "too many headers");
+        }
+
+        Ok(Self {
+            method,
+            path,
+            query,
+            headers,
+        })
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query.get(key).map(|s| s.as_str())
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers.get(key).map(|s| s.as_str())
+    }
+
+    // Accept the token as a bearer token, or for reads, in the query so that plain links work.
+    // Note: urls end up in logs and browser history, so a token in one can't make changes.
+    fn is_authorized(&self, token: &str) -> bool {
+        let offered = self
+            .header("authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| self.query("token").filter(|_| self.method() == "GET"));
+        offered.is_some_and(|offered| constant_time_eq(offered.as_bytes(), token.as_bytes()))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Debug)]
+enum HttpBody {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    status: u16,
+    content_type: String,
+    headers: Vec<(String, String)>,
+    body: HttpBody,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_owned(),
+            headers: Vec::new(),
+            body: HttpBody::Bytes(body),
+        }
+    }
+
+    pub fn file(path: PathBuf) -> Self {
+        Self {
+            status: 200,
+            content_type: content_type_for(&path.to_string_lossy()).to_owned(),
+            headers: Vec::new(),
+            body: HttpBody::File(path),
+        }
+    }
+
+    pub fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::new(200, "application/json", body),
+            Err(e) => Self::text(500, &format!("failed to encode response: {e}")),
+        }
+    }
+
+    pub fn text(status: u16, message: &str) -> Self {
+        Self::new(
+            status,
+            "text/plain; charset=utf-8",
+            message.as_bytes().to_vec(),
+        )
+    }
+
+    pub fn ok() -> Self {
+        Self::text(200, "ok")
+    }
+
+    pub fn bad_request(message: &str) -> Self {
+        Self::text(400, message)
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::text(401, "missing or invalid token")
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "not found")
+    }
+
+    pub fn method_not_allowed() -> Self {
+        Self::text(405, "method not allowed")
+    }
+
+    pub fn service_unavailable() -> Self {
+        Self::text(503, "unavailable, try again shortly")
+    }
+
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+
+    pub fn write_to(self, stream: &mut impl Write) -> io::Result<()> {
+        let (mut file, len) = match &self.body {
+            HttpBody::Bytes(bytes) => (None, bytes.len() as u64),
+            HttpBody::File(path) => match File::open(path) {
+                Ok(file) => {
+                    let len = file.metadata()?.len();
+                    (Some(file), len)
+                }
+                Err(_) => return Self::not_found().write_to(stream),
+            },
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {len}\r\nConnection: close\r\n",
+            self.status,
+            self.reason(),
+            self.content_type
+        )?;
+        for (k, v) in &self.headers {
+            write!(stream, "{k}: {v}\r\n")?;
+        }
+        write!(stream, "\r\n")?;
+        match (&self.body, file.as_mut()) {
+            (HttpBody::Bytes(bytes), _) => stream.write_all(bytes)?,
+            (HttpBody::File(_), Some(file)) => {
+                io::copy(file, stream)?;
+            }
+            (HttpBody::File(_), None) => {}
+        }
+        stream.flush()
+    }
+}
+
+pub fn content_type_for(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or_default();
+    match ext.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, ctx: &ApiContext, settings: &HttpSettings) {
+    stream.set_read_timeout(Some(Duration::from_secs(10))).ok();
+    let request = HttpRequest::read(&mut BufReader::new((&stream).take(MAX_HEADER_BYTES)));
+    let response = match request {
+        Ok(req) => {
+            if let Some(resp) = serve_static(&req) {
+                resp
+            } else if req.is_authorized(&settings.token) {
+                ctx.handle(&req, true)
+            } else if settings.public_browsing && req.method() == "GET" {
+                ctx.handle(&req, false)
+            } else {
+                HttpResponse::unauthorized()
+            }
+        }
+        Err(e) => HttpResponse::bad_request(&e.to_string()),
+    };
+    metrics::count(
+        metrics::HTTP_REQUESTS_TOTAL,
+        &response.status.to_string(),
+        1,
+    );
+    if let Err(e) = response.write_to(&mut stream) {
+        debug!("HTTP client went away: {e}");
+    }
+}
+
+// One of the MAX_CONNECTIONS connections we serve at once, given back when dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn take(in_flight: &Arc<AtomicUsize>) -> Option<Self> {
+        in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(in_flight.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Debug)]
+struct RunningServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl RunningServer {
+    fn start(settings: &HttpSettings, ctx: Arc<ApiContext>) -> Result<Self> {
+        ensure!(
+            settings.token.len() >= 16,
+            "refusing to serve with a token shorter than 16 characters"
+        );
+        let listener = TcpListener::bind(&settings.bind_address)?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let settings = Arc::new(settings.clone());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let thread = {
+            let shutdown = shutdown.clone();
+            thread::Builder::new()
+                .name("HTTP Server".to_owned())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if shutdown.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        match stream {
+                            Ok(mut stream) => {
+                                let Some(slot) = ConnectionSlot::take(&in_flight) else {
+                                    stream.set_write_timeout(Some(Duration::from_secs(1))).ok();
+                                    HttpResponse::service_unavailable()
+                                        .write_to(&mut stream)
+                                        .ok();
+                                    continue;
+                                };
+                                let ctx = ctx.clone();
+                                let settings = settings.clone();
+                                thread::spawn(move || {
+                                    handle_connection(stream, &ctx, &settings);
+                                    drop(slot);
+                                });
+                            }
+                            Err(e) => warn!("HTTP accept failed: {e}"),
+                        }
+                    }
+                })?
+        };
+        info!("HTTP API listening on http://{addr}");
+        Ok(Self {
+            addr,
+            shutdown,
+            thread,
+        })
+    }
+
+    fn stop(self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // Wake the accept loop so that it notices the shutdown flag.
+        let mut wake = self.addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(Ipv4Addr::LOCALHOST.into());
+        }
+        TcpStream::connect_timeout(&wake, Duration::from_secs(1)).ok();
+        self.thread.join().ok();
+    }
+}
+
+// The optional local HTTP API. Off by default; when on, every request must carry the token.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpServer {
+    settings: HttpSettings,
+
+    #[serde(skip)]
+    applied: Option<HttpSettings>,
+    #[serde(skip)]
+    context: Option<Arc<ApiContext>>,
+    #[serde(skip)]
+    running: Option<RunningServer>,
+    #[serde(skip)]
+    last_error: Option<String>,
+}
+
+impl HttpServer {
+    pub fn initialize(
+        &mut self,
+        env: &Environment,
+        progress_mon: &ProgressMonitor,
+        db_sync: &DbSyncHandle,
+        db_write: &DbWriteHandle,
+    ) {
+        self.context = Some(Arc::new(ApiContext::new(
+            env.storage().clone(),
+            db_sync.clone(),
+            db_write.clone(),
+            progress_mon.monitor_channel(),
+        )));
+        self.apply();
+    }
+
+    fn apply(&mut self) {
+        if let Some(running) = self.running.take() {
+            running.stop();
+        }
+        self.applied = Some(self.settings.clone());
+        self.last_error = None;
+        if !self.settings.enabled {
+            return;
+        }
+        let Some(ctx) = self.context.clone() else {
+            return;
+        };
+        match RunningServer::start(&self.settings, ctx) {
+            Ok(running) => self.running = Some(running),
+            Err(e) => {
+                warn!("Failed to start HTTP API: {e}");
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    // Note: until this is called, visitors without the token are turned away.
+    pub fn set_public_view(&self, view: PublicView) {
+        if let Some(ctx) = &self.context {
+            ctx.set_public_view(view);
+        }
+    }
+
+    // Settings and state for a diagnostics bundle; never includes the token.
+    pub fn diagnostics(&self) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": self.settings.enabled,
+            "bind_address": self.settings.bind_address,
+            "public_browsing": self.settings.public_browsing,
+            "listening": self.running.as_ref().map(|r| r.addr.to_string()),
+            "last_error": self.last_error,
+        })
+    }
+
+    pub fn cleanup_for_exit(&mut self) {
+        if let Some(running) = self.running.take() {
+            running.stop();
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("HTTP API");
+        ui.label(
+            "Lets other tools on this machine (or your network) browse and control Artchiver.",
+        );
+        ui.checkbox(&mut self.settings.enabled, "Enable HTTP API");
+        ui.checkbox(
+            &mut self.settings.public_browsing,
+            "Allow browsing without the token",
+        )
+        .on_hover_text(
+            "Anyone who can reach the listen address can view your archive in a web browser. \
+             Use 0.0.0.0 as the listen address to share it with your local network.",
+        );
+        egui::Grid::new("http_preferences_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Listen Address");
+                ui.text_edit_singleline(&mut self.settings.bind_address);
+                ui.end_row();
+                ui.label("Token");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.token).password(true));
+                    if ui.button("📋").on_hover_text("Copy token").clicked() {
+                        ui.ctx().copy_text(self.settings.token.clone());
+                    }
+                    if ui
+                        .button("New")
+                        .on_hover_text("Generate a new token")
+                        .clicked()
+                    {
+                        self.settings.token = make_token();
+                    }
+                });
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            let dirty = self.applied.as_ref() != Some(&self.settings);
+            if ui.add_enabled(dirty, egui::Button::new("Apply")).clicked() {
+                self.apply();
+            }
+            if let Some(err) = &self.last_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            } else if let Some(running) = &self.running {
+                let url = format!("http://{}/?token={}", running.addr, self.settings.token);
+                ui.hyperlink_to(format!("Listening on http://{}", running.addr), url);
+            } else {
+                ui.label("Not running");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b", false), "a b");
+        assert_eq!(percent_decode("a+b", true), "a b");
+        assert_eq!(percent_decode("a+b", false), "a+b");
+        assert_eq!(percent_decode("100%", false), "100%");
+        assert_eq!(percent_decode("%zz", false), "%zz");
+        assert_eq!(percent_decode("caf%C3%A9", false), "café");
+    }
+
+    #[test]
+    fn test_read_request() -> Result<()> {
+        let raw = b"GET /api/tags/12/works?after=5&name=a+b HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer abc\r\n\r\n";
+        let req = HttpRequest::read(&mut &raw[..])?;
+        assert_eq!(req.method(), "GET");
+        assert_eq!(req.segments(), vec!["api", "tags", "12", "works"]);
+        assert_eq!(req.query("after"), Some("5"));
+        assert_eq!(req.query("name"), Some("a b"));
+        assert!(req.is_authorized("abc"));
+        assert!(!req.is_authorized("abd"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_token_only_reads() -> Result<()> {
+        let raw = b"GET /api/tags?token=abc HTTP/1.1\r\nHost: x\r\n\r\n";
+        assert!(HttpRequest::read(&mut &raw[..])?.is_authorized("abc"));
+        let raw = b"POST /api/tags/12/refresh?token=abc HTTP/1.1\r\nHost: x\r\n\r\n";
+        assert!(!HttpRequest::read(&mut &raw[..])?.is_authorized("abc"));
+        let raw =
+            b"POST /api/tags/12/refresh HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer abc\r\n\r\n";
+        assert!(HttpRequest::read(&mut &raw[..])?.is_authorized("abc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_connection_slots() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let slots = (0..MAX_CONNECTIONS)
+            .map(|_| ConnectionSlot::take(&in_flight))
+            .collect::<Option<Vec<_>>>()
+            .expect("slots");
+        assert!(ConnectionSlot::take(&in_flight).is_none());
+        drop(slots);
+        assert_eq!(in_flight.load(Ordering::Acquire), 0);
+        assert!(ConnectionSlot::take(&in_flight).is_some());
+    }
+}
@@ -3,25 +3,143 @@
 
 mod app;
 pub mod db;
+pub mod http;
 pub mod plugin;
 pub mod shared;
 pub mod ux;
 
-use crate::{app::ArtchiverApp, shared::environment::Environment};
+use crate::{
+    app::ArtchiverApp,
+    db::migrate::check_database_file,
+    shared::{
+        deep_link::{self, DeepLink},
+        encryption::EncryptionConfig,
+        environment::Environment,
+        instance_lock::{InstanceLock, LockOutcome, is_read_only, set_read_only},
+        profile::{profile, set_portable, set_profile},
+    },
+    ux::{
+        already_running::run_already_running_prompt, startup_error::StartupError,
+        unlock::run_unlock_prompt,
+    },
+};
 use clap::Parser;
 use eframe::HardwareAcceleration;
+use std::path::PathBuf;
 
 #[derive(Clone, Debug, Parser)]
-pub struct ArtchiverArgs {}
+pub struct ArtchiverArgs {
+    /// Encrypt the database, and any encrypted storage roots, with a passphrase. Downloads are
+    /// only encrypted if the storage rules put them on an encrypted root.
+    #[arg(long)]
+    encrypt: bool,
+
+    /// Open the archive without changing anything, e.g. alongside another running instance.
+    #[arg(long)]
+    read_only: bool,
+
+    /// Run from DIR rather than the current directory, as link handlers are started elsewhere.
+    #[arg(long, value_name = "DIR")]
+    dir: Option<PathBuf>,
+
+    /// Keep everything in the executable's folder, wherever Artchiver is started from.
+    #[arg(long, conflicts_with = "dir")]
+    portable: bool,
+
+    /// Open the named profile, a separate archive with its own database, data, and settings.
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// An artchiver:// link to a work, tag, or collection to open.
+    link: Option<String>,
+}
 
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
-    let _args = ArtchiverArgs::parse();
+    let args = ArtchiverArgs::parse();
+    if let Some(dir) = &args.dir {
+        std::env::set_current_dir(dir).expect("failed to change to --dir");
+    }
+    if args.portable {
+        let dir = set_portable().expect("failed to find the executable's folder");
+        std::env::set_current_dir(dir).expect("failed to change to the executable's folder");
+    }
+    if let Some(profile) = &args.profile
+        && let Err(e) = set_profile(profile)
+    {
+        log::error!("Not opening profile {profile}: {e}");
+        return Ok(());
+    }
+    let link = args
+        .link
+        .as_deref()
+        .and_then(|link| match DeepLink::parse(link) {
+            Ok(link) => Some(link),
+            Err(e) => {
+                log::error!("Not opening the link: {e:#}");
+                None
+            }
+        });
 
     let pwd = std::env::current_dir().expect("failed to get working directory");
     let env = Environment::new(&pwd).expect("failed to create environment");
+    // Note: held until we exit.
+    let _lock = if args.read_only {
+        set_read_only();
+        None
+    } else {
+        match InstanceLock::acquire(&env.data_dir()) {
+            Ok(LockOutcome::Acquired(lock)) => Some(lock),
+            Ok(LockOutcome::Held { path, owner }) => {
+                // Note: the instance with the lock opens the link; we have nothing else to do.
+                if let Some(link) = &link {
+                    match deep_link::deliver(&env.data_dir(), link) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => log::error!("Failed to pass {link} on: {e:#}"),
+                    }
+                }
+                if !run_already_running_prompt(path, owner)? {
+                    return Ok(());
+                }
+                set_read_only();
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to take the instance lock; carrying on without it: {e:#}");
+                None
+            }
+        }
+    };
+    if !is_read_only() {
+        env.clean_up_after_last_run()
+            .expect("failed to clean up after the last run");
+        if let Some(link) = &link
+            && let Err(e) = deep_link::deliver(&env.data_dir(), link)
+        {
+            log::error!("Failed to queue {link}: {e:#}");
+        }
+    }
+    let encrypted = EncryptionConfig::is_enabled(&env.data_dir());
+    if (encrypted || args.encrypt) && !run_unlock_prompt(&env, !encrypted)? {
+        return Ok(());
+    }
+    if let Err(e) = check_database_file(&env.metadata_file_path()) {
+        log::error!("{e}");
+        let heading = format!(
+            "Artchiver can't open the database at {}",
+            env.metadata_file_path().display()
+        );
+        return eframe::run_native(
+            "Artchiver",
+            eframe::NativeOptions {
+                viewport: egui::ViewportBuilder::default().with_inner_size([560.0, 220.0]),
+                ..Default::default()
+            },
+            Box::new(move |_cc| Ok(Box::new(StartupError::new(heading, e)))),
+        );
+    }
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_min_inner_size([300.0, 220.0])
@@ -35,8 +153,12 @@ fn main() -> eframe::Result {
         persistence_path: Some(env.data_dir().join("artchiver.ron")),
         ..Default::default()
     };
+    let title = match profile() {
+        Some(name) => format!("Artchiver ({name})"),
+        None => "Artchiver".to_owned(),
+    };
     eframe::run_native(
-        "Artchiver",
+        &title,
         native_options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
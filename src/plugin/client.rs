@@ -1,20 +1,33 @@
 use crate::{
     db::{
-        models::plugin::PluginId,
+        models::{
+            link_check::{LinkCheck, LinkStatus},
+            plugin::PluginId,
+        },
         {sync::DbSyncHandle, writer::DbWriteHandle},
     },
-    plugin::download::download_works,
+    plugin::{download::download_works, media},
     shared::{
+        bandwidth::{DownloadGovernor, PluginBandwidth},
+        compliance::Compliance,
         environment::Environment,
+        fetch_policy::{FetchPolicies, PluginFetchPolicy},
+        http_fixtures::{FixtureSession, HttpFixtures},
+        metrics,
         plugin::{PluginCancellation, PluginRequest},
         progress::{HostUpdateSender, LogSender, ProgressSender, UpdateSource},
-        throttle::CallingThrottle,
+        storage::Storage,
+        tag_exclusion::TagExclusionFilter,
+        throttle::{CallingThrottle, is_rate_limit_status},
         update::DataUpdate,
+        warc::WarcRecorder,
     },
 };
-use anyhow::Result;
+use anyhow::{Result, ensure};
 use artchiver_sdk::{
-    ConfigValue, PluginMetadata, Request, Tag, TextFetchError, TextResponse, Work,
+    ActionOutcome, ActionRequest, ConfigValue, Group, MediaResponse, MediaRole, PluginMetadata,
+    RateLimitStatus, Request, Tag, TextFetchError, TextResponse, Work, WorkRange, WorkTagEdit,
+    media_page_url,
 };
 use crossbeam::channel::{Receiver, Sender};
 use extism::{
@@ -29,9 +42,13 @@ use std::{
     fs, io,
     path::{Path, PathBuf},
     thread::{JoinHandle, spawn},
-    time::Duration,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use ureq::{
+    Agent, Body, RequestBuilder, config::RedirectAuthHeaders, http::Response,
+    typestate::WithoutBody,
 };
-use ureq::{Agent, config::RedirectAuthHeaders};
 
 fn make_plugin(
     source: &Path,
@@ -56,6 +73,14 @@ fn make_plugin(
         .with_function("progress_clear", [], [], state.clone(), progress_clear)
         .with_function("log_message", [PTR, PTR], [], state.clone(), log_message)
         .with_function("fetch_text", [PTR], [PTR], state.clone(), fetch_text)
+        .with_function(
+            "rate_limit_status",
+            [],
+            [PTR],
+            state.clone(),
+            rate_limit_status,
+        )
+        .with_function("resolve_media", [PTR], [PTR], state.clone(), resolve_media)
         .build()?;
     Ok(plugin)
 }
@@ -67,10 +92,26 @@ pub(crate) fn create_plugin_task(
     db_write: DbWriteHandle,
     rx_from_runner: Receiver<PluginRequest>,
     tx_to_runner: Sender<DataUpdate>,
-) -> Result<(JoinHandle<()>, PluginCancellation)> {
+    shared: (
+        (DownloadGovernor, Compliance),
+        (WarcRecorder, HttpFixtures),
+        TagExclusionFilter,
+        FetchPolicies,
+    ),
+) -> Result<(JoinHandle<()>, PluginCancellation, CallingThrottle)> {
     info!("Loading plugin: {}", source.display());
-    let state = UserData::new(PluginState::new(env, db_sync, db_write, tx_to_runner));
-    let cancellation = state.get()?.lock().expect("poison").cancellation.clone();
+    let state = UserData::new(PluginState::new(
+        env,
+        db_sync,
+        db_write,
+        tx_to_runner,
+        shared,
+    ));
+    let (cancellation, throttle) = {
+        let state_ref = state.get()?;
+        let state = state_ref.lock().expect("poison");
+        (state.cancellation.clone(), state.throttle.clone())
+    };
     // Note: on configuration; we support moving the plugin file around, so we need to key on the
     //       name rather than the source path. As such, we have to wait until the plugin returns
     //       its metadata to us. At which point we look up the config and rebuild the plugin.
@@ -86,16 +127,18 @@ pub(crate) fn create_plugin_task(
             state.log.error(format!("Error: {e}"));
         }
     });
-    Ok((plugin_task, cancellation))
+    Ok((plugin_task, cancellation, throttle))
 }
 
 #[derive(Debug)]
 pub struct PluginState {
     // Environment
     cache_dir: PathBuf,
-    data_dir: PathBuf,
+    storage: Storage,
     tmp_dir: PathBuf,
     cache_timeout: Duration,
+    // Set while the plugin re-resolves a work, so that it sees the urls the source gives out now.
+    skip_cache: bool,
     progress: ProgressSender,
     log: LogSender,
     host: HostUpdateSender,
@@ -108,6 +151,22 @@ pub struct PluginState {
     // Web
     agent: Agent,
     throttle: CallingThrottle,
+    governor: DownloadGovernor,
+    compliance: Compliance,
+    // Whether the plugin says it scrapes web pages, so is held to robots.txt.
+    scraper: bool,
+    bandwidth: PluginBandwidth,
+    warc: WarcRecorder,
+    fixtures: HttpFixtures,
+    // Recording or replaying the fetches of the current job, if asked.
+    fixture_session: Option<FixtureSession>,
+
+    // Tags
+    tag_filter: TagExclusionFilter,
+
+    // Downloads
+    policies: FetchPolicies,
+    fetch_policy: PluginFetchPolicy,
 }
 
 fn make_agent() -> Agent {
@@ -130,12 +189,19 @@ impl PluginState {
         db_sync: DbSyncHandle,
         db_write: DbWriteHandle,
         tx_to_runner: Sender<DataUpdate>,
+        ((governor, compliance), (warc, fixtures), tag_filter, policies): (
+            (DownloadGovernor, Compliance),
+            (WarcRecorder, HttpFixtures),
+            TagExclusionFilter,
+            FetchPolicies,
+        ),
     ) -> Self {
         Self {
             cache_dir: env.cache_dir().clone(),
-            data_dir: env.data_dir().clone(),
+            storage: env.storage().clone(),
             tmp_dir: env.tmp_dir().clone(),
             cache_timeout: Duration::from_secs(60 * 60 * 24 * 7), // one week
+            skip_cache: false,
             progress: ProgressSender::wrap(UpdateSource::Unknown, tx_to_runner.clone()),
             log: LogSender::wrap(UpdateSource::Unknown, tx_to_runner.clone()),
             host: HostUpdateSender::wrap(UpdateSource::Unknown, tx_to_runner),
@@ -144,6 +210,16 @@ impl PluginState {
             db_write,
             agent: make_agent(),
             throttle: CallingThrottle::default(),
+            bandwidth: PluginBandwidth::default(),
+            governor,
+            compliance,
+            scraper: false,
+            warc,
+            fixtures,
+            fixture_session: None,
+            tag_filter,
+            policies,
+            fetch_policy: PluginFetchPolicy::default(),
         }
     }
 }
@@ -165,14 +241,21 @@ fn plugin_main(
     state: &UserData<PluginState>,
     rx_from_runner: &Receiver<PluginRequest>,
 ) -> Result<()> {
+    let start = Instant::now();
     let mut metadata = plugin.call::<(), Json<PluginMetadata>>("startup", ())?.0;
+    metrics::time(metrics::PLUGIN_CALL_SECONDS, "startup", start.elapsed());
     let name = metadata.name().to_owned();
     let db_plugin = {
         let state_ref = state.get()?;
         let mut state = state_ref.lock().expect("poison");
         state.cache_timeout = metadata.cache_timeout();
         let db_plugin = state.db_sync.sync_upsert_plugin(metadata.name())?;
-        state.throttle = CallingThrottle::new(metadata.rate_limit(), metadata.rate_window());
+        state
+            .throttle
+            .set_limits(metadata.rate_limit(), metadata.rate_window());
+        state.bandwidth = state.governor.for_plugin(metadata.name());
+        state.scraper = metadata.is_scraper();
+        state.fetch_policy = state.policies.for_plugin(metadata.name());
         state.progress = ProgressSender::wrap(
             UpdateSource::Plugin(db_plugin.id()),
             state.progress.channel(),
@@ -202,6 +285,12 @@ fn plugin_main(
     host.plugin_loaded(plugin_source, &db_plugin, &metadata)?;
 
     'outer: while let Ok(msg) = rx_from_runner.recv() {
+        let task = msg.to_string();
+        {
+            let state_ref = state.get()?;
+            let mut state = state_ref.lock().expect("poison");
+            state.fixture_session = state.fixtures.begin(metadata.name(), &task);
+        }
         let rv = match msg {
             PluginRequest::Shutdown => {
                 log.info(format!("Shutting down plugin: {}", db_plugin.id()));
@@ -218,20 +307,79 @@ fn plugin_main(
                 plugin = make_plugin(plugin_source, config, state)?;
                 Ok(())
             }
-            PluginRequest::RefreshTags => {
-                refresh_tags(db_plugin.id(), &mut plugin, state, &mut log)
-            }
-            PluginRequest::RefreshWorksForTag { tag } => refresh_works_for_tag(
+            PluginRequest::RefreshTags => refresh_tags(
+                (db_plugin.id(), metadata.name()),
+                &mut plugin,
+                state,
+                &mut log,
+            ),
+            PluginRequest::RefreshWorksForTag {
+                tag,
+                metadata_only,
+                max_works,
+            } => refresh_works_for_tag(
+                (db_plugin.id(), &tag, None),
+                (metadata_only, max_works),
+                &mut plugin,
+                state,
+                &pool,
+                (&mut progress, &mut log),
+            ),
+            PluginRequest::FetchWorksForTagRange {
+                tag,
+                offset,
+                limit,
+                metadata_only,
+            } => refresh_works_for_tag(
+                (
+                    db_plugin.id(),
+                    &tag,
+                    Some(WorkRange {
+                        tag: tag.clone(),
+                        offset,
+                        limit,
+                    }),
+                ),
+                (metadata_only, None),
+                &mut plugin,
+                state,
+                &pool,
+                (&mut progress, &mut log),
+            ),
+            PluginRequest::DownloadWorks {
+                screen_urls,
+                archives,
+            } => download_recorded_works(
+                (&screen_urls, archives),
+                state,
+                &pool,
+                (&mut progress, &mut log),
+            ),
+            PluginRequest::ReprocessSources => reprocess_sources(
                 db_plugin.id(),
-                &tag,
                 &mut plugin,
                 state,
                 &pool,
                 (&mut progress, &mut log),
             ),
+            PluginRequest::PushTagEdits { edits } => {
+                push_tag_edits(&edits, &mut plugin, state, (&mut progress, &mut log))
+            }
+            PluginRequest::RunAction {
+                action_id,
+                work_remote_id,
+                ..
+            } => run_action(action_id, work_remote_id, &mut plugin, &mut log),
+            PluginRequest::CheckLinks { sample } => check_links(
+                (db_plugin.id(), sample),
+                &mut plugin,
+                state,
+                (&mut progress, &mut log),
+            ),
         };
         if let Err(e) = rv {
             log.error(format!("Error handling plugin message: {e}"));
+            host.note_task_failed(metadata.name(), task, e.to_string())?;
             // Note: reset the agent if we fail, to hopefully break any bad connections.
             state.get()?.lock().expect("poison").agent = make_agent();
         }
@@ -239,7 +387,12 @@ fn plugin_main(
         host.note_completed_task()?;
         // Note: always reset the cancellation on task complete. If we missed hitting
         //       a trigger, it no longer matters once we get to this point.
-        state.get()?.lock().expect("poison").cancellation.reset();
+        {
+            let state_ref = state.get()?;
+            let mut state = state_ref.lock().expect("poison");
+            state.cancellation.reset();
+            state.fixture_session = None;
+        }
         // And ditto for our progress situation, particularly if we were canceled.
         progress.clear();
     }
@@ -247,65 +400,139 @@ fn plugin_main(
 }
 
 fn refresh_tags(
-    plugin_id: PluginId,
+    (plugin_id, plugin_name): (PluginId, &str),
     plugin: &mut ExtPlugin,
     state: &UserData<PluginState>,
     log: &mut LogSender,
 ) -> Result<()> {
     // Progress will get sent for the download or file read.
     log.trace(format!("Calling plugin ({plugin_id}) -> list_tags"));
-    let tags = plugin.call::<(), Json<Vec<Tag>>>("list_tags", ())?.0;
+    let start = Instant::now();
+    let mut tags = plugin.call::<(), Json<Vec<Tag>>>("list_tags", ())?.0;
+    metrics::time(metrics::PLUGIN_CALL_SECONDS, "list_tags", start.elapsed());
 
     // Progress will get sent a second time for writing to the DB.
     let state_ref = state.get()?;
     let state = state_ref.lock().expect("poison");
+    let listed = tags.len();
+    tags.retain(|tag| !state.tag_filter.is_excluded(plugin_name, tag.name()));
+    if tags.len() < listed {
+        log.info(format!(
+            "Skipped {} of {listed} tags matching the exclusion patterns",
+            listed - tags.len()
+        ));
+    }
     state.db_write.upsert_tags(plugin_id, tags)?;
 
     Ok(())
 }
 
 fn refresh_works_for_tag(
-    plugin_id: PluginId,
-    tag: &str,
+    (plugin_id, tag, range): (PluginId, &str, Option<WorkRange>),
+    (metadata_only, max_works): (bool, Option<usize>),
     plugin: &mut ExtPlugin,
     state: &UserData<PluginState>,
     pool: &ThreadPool,
     (progress, log): (&mut ProgressSender, &mut LogSender),
 ) -> Result<()> {
-    let (data_dir, tmp_dir, db, agent, throttle, cancellation) = {
+    let (storage, tmp_dir, db, (agent, throttle, bandwidth, warc), cancellation, policy) = {
         let state_ref = state.get()?;
         let state = state_ref.lock().expect("poison");
         (
-            state.data_dir.clone(),
+            state.storage.clone(),
             state.tmp_dir.clone(),
             state.db_write.clone(),
-            state.agent.clone(),
-            state.throttle.clone(),
+            (
+                state.agent.clone(),
+                state.throttle.clone(),
+                state.bandwidth.clone(),
+                state.warc.clone(),
+            ),
             state.cancellation.clone(),
+            state.fetch_policy.current(),
         )
     };
 
     // Ask the plugin to figure out what works we have for this tag.
     progress.set_spinner();
-    log.trace(format!("Calling plugin->list_works_for_tag(\"{tag}\")"));
-    let works = plugin
-        .call::<String, Json<Vec<Work>>>("list_works_for_tag", tag.to_owned())?
-        .0;
+    let start = Instant::now();
+    let (works, call) = match range {
+        Some(range) if plugin.function_exists("list_works_for_tag_range") => {
+            log.trace(format!(
+                "Calling plugin->list_works_for_tag_range(\"{tag}\", {}, {})",
+                range.offset, range.limit
+            ));
+            let works = plugin
+                .call::<Json<WorkRange>, Json<Vec<Work>>>("list_works_for_tag_range", Json(range))?
+                .0;
+            (works, "list_works_for_tag_range")
+        }
+        range => {
+            log.trace(format!("Calling plugin->list_works_for_tag(\"{tag}\")"));
+            let works = plugin
+                .call::<String, Json<Vec<Work>>>("list_works_for_tag", tag.to_owned())?
+                .0;
+            // Note: plugins that can't page still only get the page we asked for saved.
+            let works = match range {
+                Some(range) => range.select(works),
+                None => works,
+            };
+            (works, "list_works_for_tag")
+        }
+    };
+    metrics::time(metrics::PLUGIN_CALL_SECONDS, call, start.elapsed());
 
     // Save the works we found.
     log.trace(format!("Saving {} works to Database async", works.len()));
     db.upsert_works(plugin_id, tag, works.clone())?;
 
+    // Optional: the portfolios, series, and such that the plugin knows these works belong to.
+    if plugin.function_exists("list_groups_for_tag") {
+        log.trace(format!("Calling plugin->list_groups_for_tag(\"{tag}\")"));
+        let start = Instant::now();
+        let groups = plugin
+            .call::<String, Json<Vec<Group>>>("list_groups_for_tag", tag.to_owned())?
+            .0;
+        metrics::time(
+            metrics::PLUGIN_CALL_SECONDS,
+            "list_groups_for_tag",
+            start.elapsed(),
+        );
+        if !groups.is_empty() {
+            db.upsert_groups(plugin_id, groups)?;
+        }
+    }
+    if metadata_only {
+        log.info(format!(
+            "Recorded {} works for tag {tag}; not downloading, as asked",
+            works.len()
+        ));
+        progress.clear();
+        return Ok(());
+    }
+
+    let works = match max_works {
+        Some(max_works) if max_works < works.len() => {
+            log.info(format!(
+                "Downloading the first {max_works} of {} works for tag {tag}, as asked",
+                works.len()
+            ));
+            works.into_iter().take(max_works).collect()
+        }
+        _ => works,
+    };
+
     // Fetch all images
     // Note: we don't need to wait for the upsert to happen before we start downloading, since
     //       all we need in the urls and those are in the Work. As we download files, the messages
     //       to update the local paths will just queue up behind the upsert.
     download_works(
         works,
+        policy.on_refresh(),
         &db,
         pool,
-        (&agent, &throttle),
-        (&data_dir, &tmp_dir),
+        (&agent, &throttle, &bandwidth, &warc),
+        (&storage, &tmp_dir),
         (progress, log, &cancellation),
     )?;
     log.info(format!("Finished download tag {tag}..."));
@@ -314,6 +541,76 @@ fn refresh_works_for_tag(
     Ok(())
 }
 
+// Fetch the files for works that we have already recorded, e.g. from a metadata-only refresh.
+fn download_recorded_works(
+    (screen_urls, archives): (&[String], bool),
+    state: &UserData<PluginState>,
+    pool: &ThreadPool,
+    (progress, log): (&mut ProgressSender, &mut LogSender),
+) -> Result<()> {
+    let (storage, tmp_dir, (db_sync, db), (agent, throttle, bandwidth, warc), cancellation, policy) = {
+        let state_ref = state.get()?;
+        let state = state_ref.lock().expect("poison");
+        (
+            state.storage.clone(),
+            state.tmp_dir.clone(),
+            (state.db_sync.clone(), state.db_write.clone()),
+            (
+                state.agent.clone(),
+                state.throttle.clone(),
+                state.bandwidth.clone(),
+                state.warc.clone(),
+            ),
+            state.cancellation.clone(),
+            state.fetch_policy.current(),
+        )
+    };
+
+    // Note: the download only needs the urls, so the rest of the work can stay in the database.
+    let mut mirrors = db_sync.sync_list_work_mirrors(screen_urls)?;
+    let works = db_sync
+        .sync_list_works_by_screen_url(screen_urls)?
+        .into_iter()
+        .map(|work| {
+            let mut recorded = Work::new(
+                work.name(),
+                *work.date(),
+                work.preview_url(),
+                work.screen_url(),
+                vec![],
+            );
+            if let Some(url) = work.archive_url() {
+                recorded = recorded.with_archive_url(url);
+            }
+            if let Some(url) = work.model_url() {
+                recorded = recorded.with_model_url(url);
+            }
+            if let Some(url) = work.transcript_url() {
+                recorded = recorded.with_transcript_url(url);
+            }
+            mirrors
+                .remove(work.screen_url())
+                .into_iter()
+                .flatten()
+                .fold(recorded, |recorded, (role, url)| {
+                    recorded.with_mirror(role, url)
+                })
+        })
+        .collect::<Vec<_>>();
+    download_works(
+        works,
+        policy.on_request(archives),
+        &db,
+        pool,
+        (&agent, &throttle, &bandwidth, &warc),
+        (&storage, &tmp_dir),
+        (progress, log, &cancellation),
+    )?;
+
+    progress.clear();
+    Ok(())
+}
+
 host_fn!(progress_spinner(state: PluginState;) {
     state.get()?.lock().expect("poison").progress.set_spinner();
     Ok(())
@@ -329,18 +626,296 @@ host_fn!(progress_clear(state: PluginState;) {
     Ok(())
 });
 
+// Re-run the plugin's mapping over the sources we kept for its works, so that improvements to
+// the mapping can be picked up without fetching everything again. Only files whose urls have
+// changed get downloaded.
+fn reprocess_sources(
+    plugin_id: PluginId,
+    plugin: &mut ExtPlugin,
+    state: &UserData<PluginState>,
+    pool: &ThreadPool,
+    (progress, log): (&mut ProgressSender, &mut LogSender),
+) -> Result<()> {
+    const PAGE_SIZE: usize = 1_000;
+
+    if !plugin.function_exists("map_source") {
+        log.warn("This plugin can not re-build works from their sources");
+        return Ok(());
+    }
+    let (storage, tmp_dir, (db_sync, db), (agent, throttle, bandwidth, warc), cancellation, policy) = {
+        let state_ref = state.get()?;
+        let state = state_ref.lock().expect("poison");
+        (
+            state.storage.clone(),
+            state.tmp_dir.clone(),
+            (state.db_sync.clone(), state.db_write.clone()),
+            (
+                state.agent.clone(),
+                state.throttle.clone(),
+                state.bandwidth.clone(),
+                state.warc.clone(),
+            ),
+            state.cancellation.clone(),
+            state.fetch_policy.current(),
+        )
+    };
+
+    let total = db_sync.sync_count_work_sources(plugin_id)?;
+    log.info(format!("Reprocessing {total} kept sources..."));
+    let mut after: Option<String> = None;
+    let mut done = 0;
+    while !cancellation.is_cancelled() {
+        let page = db_sync.sync_list_work_sources(plugin_id, after.as_deref(), PAGE_SIZE)?;
+        let Some((last, _)) = page.last() else {
+            break;
+        };
+        after = Some(last.clone());
+
+        let mut works = Vec::new();
+        for (screen_url, source) in page {
+            progress.set_percent(done, total);
+            done += 1;
+            match plugin.call::<&str, Json<Vec<Work>>>("map_source", &source) {
+                Ok(Json(mapped)) => works.extend(mapped),
+                Err(e) => log.warn(format!("Failed to map the source for {screen_url}: {e}")),
+            }
+        }
+
+        // Note: there is no one tag for these, so an empty tag asks the UX to refresh whatever
+        //       it is showing.
+        db.upsert_works(plugin_id, "", works.clone())?;
+        download_works(
+            works,
+            policy.on_refresh(),
+            &db,
+            pool,
+            (&agent, &throttle, &bandwidth, &warc),
+            (&storage, &tmp_dir),
+            (progress, log, &cancellation),
+        )?;
+    }
+    log.info(format!("Finished reprocessing {done} sources"));
+
+    progress.clear();
+    Ok(())
+}
+
+// Send the user's tag edits back to the source. Edits that the plugin fails on stay pending, to
+// be pushed with the next batch.
+fn push_tag_edits(
+    edits: &[(String, WorkTagEdit)],
+    plugin: &mut ExtPlugin,
+    state: &UserData<PluginState>,
+    (progress, log): (&mut ProgressSender, &mut LogSender),
+) -> Result<()> {
+    if !plugin.function_exists("push_work_tags") {
+        log.warn("This plugin can not push tag edits to its source");
+        return Ok(());
+    }
+    let (db, cancellation) = {
+        let state_ref = state.get()?;
+        let state = state_ref.lock().expect("poison");
+        (state.db_write.clone(), state.cancellation.clone())
+    };
+
+    let mut pushed = 0;
+    for (done, (screen_url, edit)) in edits.iter().enumerate() {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        progress.set_percent(done, edits.len());
+        match plugin.call::<Json<WorkTagEdit>, ()>("push_work_tags", Json(edit.clone())) {
+            Ok(()) => {
+                db.mark_tag_edits_pushed(screen_url, edit.clone())?;
+                pushed += 1;
+            }
+            Err(e) => log.warn(format!(
+                "Failed to push the tag edits for {screen_url}: {e}"
+            )),
+        }
+    }
+    log.info(format!(
+        "Pushed the tag edits for {pushed} of {} works",
+        edits.len()
+    ));
+
+    progress.clear();
+    Ok(())
+}
+
+// Run one of the buttons from the plugin's metadata, passing what it has to say on to the user.
+fn run_action(
+    action_id: String,
+    work_remote_id: Option<String>,
+    plugin: &mut ExtPlugin,
+    log: &mut LogSender,
+) -> Result<()> {
+    if !plugin.function_exists("run_action") {
+        log.warn("This plugin lists actions, but can not run them");
+        return Ok(());
+    }
+    let request = ActionRequest {
+        action_id,
+        work_remote_id,
+    };
+    let Json(outcome) =
+        plugin.call::<Json<ActionRequest>, Json<ActionOutcome>>("run_action", Json(request))?;
+    if let Some(message) = outcome.message() {
+        log.info(message);
+    }
+    if let Some(url) = outcome.open_url() {
+        // Note: the plugin should not be able to start just any program on the user's machine.
+        ensure!(
+            url.starts_with("https://") || url.starts_with("http://"),
+            "not opening {url}; plugins may only open web pages"
+        );
+        log.info(format!("Opening {url}"));
+        open::that_detached(url)?;
+    }
+    Ok(())
+}
+
+// HEAD a sample of the urls for works that we have not downloaded yet. Where the source says a
+// url is gone, ask the plugin to find the work again: many sources hand out CDN links that expire.
+// The fresh urls are kept as mirrors of the work, for the next download to fall back on.
+fn check_links(
+    (plugin_id, sample): (PluginId, usize),
+    plugin: &mut ExtPlugin,
+    state: &UserData<PluginState>,
+    (progress, log): (&mut ProgressSender, &mut LogSender),
+) -> Result<()> {
+    let (db_sync, db, (agent, throttle, compliance, scraper), cancellation) = {
+        let state_ref = state.get()?;
+        let state = state_ref.lock().expect("poison");
+        (
+            state.db_sync.clone(),
+            state.db_write.clone(),
+            (
+                state.agent.clone(),
+                state.throttle.clone(),
+                state.compliance.clone(),
+                state.scraper,
+            ),
+            state.cancellation.clone(),
+        )
+    };
+    let can_refresh = plugin.function_exists("refresh_work");
+    let probe = |url: &str, log: &mut LogSender| -> Result<Option<u16>> {
+        compliance.check(url, scraper, (&agent, &cancellation), log)?;
+        match call_with_backoff(|| agent.head(url), &throttle, &cancellation, log) {
+            Ok(response) => Ok(Some(response.status().as_u16())),
+            Err(RequestError::Http(ureq::Error::StatusCode(status))) => Ok(Some(status)),
+            Err(RequestError::Http(e)) => {
+                log.warn(format!("Could not check {url}: {e}"));
+                Ok(None)
+            }
+            Err(RequestError::Cancelled) => Err(RequestError::Cancelled.into()),
+        }
+    };
+
+    let works = db_sync.sync_list_link_check_sample(plugin_id, sample)?;
+    let mut checks = Vec::new();
+    for (done, (screen_url, remote_id)) in works.iter().enumerate() {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        progress.set_percent(done, works.len());
+        // Note: a media page is not a file; whether yt-dlp can still find it is another question.
+        if media_page_url(screen_url).is_some() {
+            continue;
+        }
+        let http_status = match probe(screen_url, log) {
+            Ok(Some(status)) => status,
+            // Note: leave the work for next time; this says nothing about whether the file is gone.
+            Ok(None) => continue,
+            Err(e) => {
+                log.warn(format!("Skipped checking {screen_url}: {e}"));
+                continue;
+            }
+        };
+        if !matches!(http_status, 404 | 410) {
+            checks.push(LinkCheck {
+                screen_url: screen_url.clone(),
+                status: LinkStatus::Ok,
+                http_status: Some(http_status),
+            });
+            continue;
+        }
+
+        let refreshed = match remote_id {
+            Some(remote_id) if can_refresh => {
+                state.get()?.lock().expect("poison").skip_cache = true;
+                let rv = plugin.call::<&str, Json<Option<Work>>>("refresh_work", remote_id);
+                state.get()?.lock().expect("poison").skip_cache = false;
+                match rv {
+                    Ok(Json(work)) => work,
+                    Err(e) => {
+                        log.warn(format!("Failed to refresh {screen_url}: {e}"));
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let fresh = refreshed.filter(|work| {
+            work.screen_url() != screen_url
+                && matches!(probe(work.screen_url(), log), Ok(Some(status)) if status < 400)
+        });
+        let status = match fresh {
+            Some(work) => {
+                let mut mirrors = vec![
+                    (MediaRole::Screen, work.screen_url().to_owned()),
+                    (MediaRole::Preview, work.preview_url().to_owned()),
+                ];
+                if let Some(url) = work.archive_url() {
+                    mirrors.push((MediaRole::Archive, url.to_owned()));
+                }
+                if let Some(url) = work.model_url() {
+                    mirrors.push((MediaRole::Model3D, url.to_owned()));
+                }
+                mirrors.extend(work.mirrors().iter().cloned());
+                db.add_work_mirrors(screen_url, mirrors)?;
+                LinkStatus::Refreshed
+            }
+            None => LinkStatus::Lost,
+        };
+        checks.push(LinkCheck {
+            screen_url: screen_url.clone(),
+            status,
+            http_status: Some(http_status),
+        });
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    log.info(format!(
+        "Checked {} links: {} found again, {} lost",
+        checks.len(),
+        count(LinkStatus::Refreshed),
+        count(LinkStatus::Lost)
+    ));
+    db.record_link_checks(checks)?;
+
+    progress.clear();
+    Ok(())
+}
+
 host_fn!(log_message(state: PluginState; level: u32, msg: String) {
     state.get()?.lock().expect("poison").log.log_message(level, msg);
     Ok(())
 });
 
 fn fetch_text_inner(state: &mut PluginState, request: &Request) -> TextResponse {
+    if request.method() != "GET" {
+        return send_text(state, request);
+    }
     let url = request.to_url();
 
     // Check our cache first
     let key = Sha256::digest(&url);
     let key_path = state.cache_dir.join(format!("{key:x}"));
-    if let Ok(mut cache_fp) = fs::File::open(&key_path) {
+    if !state.skip_cache
+        && let Ok(mut cache_fp) = fs::File::open(&key_path)
+    {
         let last_write = cache_fp
             .metadata()
             .expect("stat failed")
@@ -351,6 +926,7 @@ fn fetch_text_inner(state: &mut PluginState, request: &Request) -> TextResponse
             && staleness < state.cache_timeout
         {
             // state.log.trace(format!("cached: fetch_text({url})"));
+            metrics::count(metrics::FETCH_TEXT_CACHE_HITS_TOTAL, "", 1);
             let mut buffer = Vec::new();
             io::copy(&mut cache_fp, &mut buffer)?;
             let out = String::from_utf8_lossy(&buffer).to_string();
@@ -359,20 +935,32 @@ fn fetch_text_inner(state: &mut PluginState, request: &Request) -> TextResponse
     }
 
     // Stream the response simultaneously to the cache file and to a string for use by the plugin.
-    state
-        .throttle
-        .throttle(&state.cancellation)
-        .map_err(|_e| TextFetchError::Cancellation)?;
     state.log.trace(format!("fetch_text({url})"));
+    state.compliance.check(
+        &url,
+        state.scraper,
+        (&state.agent, &state.cancellation),
+        &mut state.log,
+    )?;
     let tmp_path = make_temp_path(&state.tmp_dir);
     let buffer = {
-        let mut req = state.agent.get(&url);
-        for (key, value) in request.headers() {
-            req = req.header(key, value);
-        }
-        let mut response = match req.call() {
+        let agent = &state.agent;
+        let make_request = || {
+            let mut req = agent.get(&url);
+            for (key, value) in request.headers() {
+                req = req.header(key, value);
+            }
+            req
+        };
+        let mut response = match call_with_backoff(
+            make_request,
+            &state.throttle,
+            &state.cancellation,
+            &mut state.log,
+        ) {
             Ok(response) => response,
-            Err(e) => {
+            Err(RequestError::Cancelled) => return Err(TextFetchError::Cancellation),
+            Err(RequestError::Http(e)) => {
                 state.log.error(format!("Request failed for {url}: {e}"));
                 return Err(e.into());
             }
@@ -380,19 +968,174 @@ fn fetch_text_inner(state: &mut PluginState, request: &Request) -> TextResponse
         let mut tmp_fp = fs::File::create(&tmp_path)?;
         let mut buffer = Vec::new();
         let mut tee = TeeWriter::new(&mut tmp_fp, &mut buffer);
-        io::copy(&mut response.body_mut().as_reader(), &mut tee)?;
+        let bytes = io::copy(&mut response.body_mut().as_reader(), &mut tee)?;
+        metrics::count(metrics::FETCH_TEXT_TOTAL, "", 1);
+        metrics::count(metrics::DOWNLOAD_BYTES_TOTAL, "text", bytes);
+        if state.warc.is_enabled() {
+            let sha256 = format!("{:x}", Sha256::digest(&buffer));
+            if let Err(e) =
+                state
+                    .warc
+                    .record(&url, &response, (&mut buffer.as_slice(), bytes, &sha256))
+            {
+                state
+                    .log
+                    .warn(format!("Failed to record {url} to WARC: {e}"));
+            }
+        }
         String::from_utf8_lossy(&buffer).to_string()
     };
     fs::rename(&tmp_path, &key_path)?;
     Ok(buffer)
 }
 
+// Send a request that changes something on the remote, e.g. pushing tag edits. These go through
+// the throttle like any fetch, but are neither cached nor recorded to WARC.
+fn send_text(state: &mut PluginState, request: &Request) -> TextResponse {
+    let url = request.to_url();
+    let method = request.method();
+    if method != "POST" && method != "PUT" {
+        return Err(TextFetchError::HostError(format!(
+            "unsupported method {method}"
+        )));
+    }
+    state.log.trace(format!("fetch_text({method} {url})"));
+    state.compliance.check(
+        &url,
+        state.scraper,
+        (&state.agent, &state.cancellation),
+        &mut state.log,
+    )?;
+    let agent = &state.agent;
+    let send = || {
+        let req = if method == "POST" {
+            agent.post(&url)
+        } else {
+            agent.put(&url)
+        };
+        let req = request
+            .headers()
+            .iter()
+            .fold(req, |req, (key, value)| req.header(key, value));
+        req.config()
+            .http_status_as_error(false)
+            .build()
+            .send_form(request.form().iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    };
+    let mut response =
+        match with_backoff(send, &state.throttle, &state.cancellation, &mut state.log) {
+            Ok(response) => response,
+            Err(RequestError::Cancelled) => return Err(TextFetchError::Cancellation),
+            Err(RequestError::Http(e)) => {
+                state.log.error(format!("Request failed for {url}: {e}"));
+                return Err(e.into());
+            }
+        };
+    Ok(response.body_mut().read_to_string()?)
+}
+
+host_fn!(rate_limit_status(state: PluginState;) -> Json<RateLimitStatus> {
+    Ok(Json(state.get()?.lock().expect("poison").throttle.status()))
+});
+
 host_fn!(fetch_text(state: PluginState; req: Json<Request>) -> Json<TextResponse> {
     // Note: it is fine to hold our plugin lock across long-running tasks;
     //       there is no conflict on this lock, by design.
-    Ok(Json(fetch_text_inner(&mut state.get()?.lock().expect("poison"), &req.0)))
+    let state_ref = state.get()?;
+    let mut state = state_ref.lock().expect("poison");
+    let url = req.0.to_url();
+    let replayed = state.fixture_session.as_mut().and_then(|session| session.replay(&url));
+    if let Some(response) = replayed {
+        state.log.trace(format!("replayed: fetch_text({url})"));
+        return Ok(Json(response));
+    }
+    let response = fetch_text_inner(&mut state, &req.0);
+    let recorded = state.fixture_session.as_mut().map(|session| session.record(&url, &response));
+    if let Some(Err(e)) = recorded {
+        state.log.warn(format!("Failed to record {url} as a fixture: {e}"));
+    }
+    Ok(Json(response))
+});
+
+// Note: these are not recorded as fixtures; plugin tests give media with MockHost::with_media.
+host_fn!(resolve_media(state: PluginState; url: String) -> Json<MediaResponse> {
+    let state_ref = state.get()?;
+    let mut state = state_ref.lock().expect("poison");
+    let state = &mut *state;
+    Ok(Json(media::resolve_media(
+        &url,
+        (&state.cache_dir, state.cache_timeout),
+        (&state.throttle, &state.cancellation),
+        &mut state.log,
+    )))
 });
 
+#[derive(Error, Debug)]
+pub enum RequestError {
+    #[error("request was cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    Http(#[from] ureq::Error),
+}
+
+// Give up if the remote keeps refusing us, rather than waiting forever.
+const MAX_RATE_LIMIT_RETRIES: usize = 5;
+
+// Make a request through the throttle. If the remote says we are going too fast, pause the
+// throttle (and thus every other request for this plugin) for as long as it asks, then retry.
+pub fn call_with_backoff(
+    make_request: impl Fn() -> RequestBuilder<WithoutBody>,
+    throttle: &CallingThrottle,
+    cancellation: &PluginCancellation,
+    log: &mut LogSender,
+) -> Result<Response<Body>, RequestError> {
+    let send = || {
+        make_request()
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .call()
+    };
+    with_backoff(send, throttle, cancellation, log)
+}
+
+// Note: `send` must not treat HTTP errors as errors, so that we can see rate limiting.
+fn with_backoff(
+    send: impl Fn() -> Result<Response<Body>, ureq::Error>,
+    throttle: &CallingThrottle,
+    cancellation: &PluginCancellation,
+    log: &mut LogSender,
+) -> Result<Response<Body>, RequestError> {
+    let mut retries = 0;
+    loop {
+        throttle
+            .throttle(cancellation)
+            .map_err(|_e| RequestError::Cancelled)?;
+        let start = Instant::now();
+        let response = send()?;
+        throttle.note_latency(start.elapsed());
+        let status = response.status().as_u16();
+        if is_rate_limit_status(status) && retries < MAX_RATE_LIMIT_RETRIES {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok());
+            let delay = throttle.back_off(retry_after);
+            log.warn(format!(
+                "Rate limited (HTTP {status}); pausing requests for {}s",
+                delay.as_secs()
+            ));
+            retries += 1;
+            continue;
+        }
+        if status >= 400 {
+            return Err(ureq::Error::StatusCode(status).into());
+        }
+        throttle.note_success();
+        return Ok(response);
+    }
+}
+
 pub fn make_temp_path(tmp_dir: &Path) -> PathBuf {
     let tmp_name: String = rand::rng()
         .sample_iter(&Alphanumeric)
@@ -1,20 +1,30 @@
 use crate::{
     db::writer::DbWriteHandle,
     plugin::{
-        client::make_temp_path,
-        thumbnail::{is_image, make_preview_thumbnail},
+        client::{RequestError, call_with_backoff, make_temp_path},
+        media::download_media,
+        thumbnail::{is_image, make_gallery_thumbnail, make_preview_thumbnail},
     },
     shared::{
+        bandwidth::PluginBandwidth,
+        blob::move_file,
+        fetch_policy::{FetchFiles, archive_fetch_url, wayback_url},
+        metrics,
         plugin::PluginCancellation,
         progress::{LogSender, ProgressSender},
-        throttle::{CallingThrottle, ThrottleError},
+        storage::{DataKind, Storage},
+        throttle::CallingThrottle,
+        transcript::{self, Cue, TranscriptSource},
+        warc::WarcRecorder,
     },
 };
-use artchiver_sdk::Work;
+use artchiver_sdk::{MediaKind, MediaRole, TextFetchError, Work, media_page_url};
+use jiff::Timestamp;
 use rayon::ThreadPool;
 use sha2::{Digest as _, Sha256};
 use std::{
-    fs, io,
+    fs,
+    io::{self, Write},
     path::{Path, PathBuf},
 };
 use thiserror::Error;
@@ -34,16 +44,19 @@ pub enum DownloadError {
     DownloadHeaders(#[from] ureq::Error),
     #[error("failed to download work: {0}")]
     DownloadBody(#[from] io::Error),
+    #[error("failed to upload {0} to remote storage: {1}")]
+    Upload(String, String),
     #[error("artchiver is shutting down")]
     Shutdown,
 }
 
 pub fn download_works(
     mut works: Vec<Work>,
+    fetch: FetchFiles,
     db: &DbWriteHandle,
     pool: &ThreadPool,
-    (agent, throttle): (&Agent, &CallingThrottle),
-    (data_dir, tmp_dir): (&Path, &Path),
+    (agent, throttle, bandwidth, warc): (&Agent, &CallingThrottle, &PluginBandwidth, &WarcRecorder),
+    (storage, tmp_dir): (&Storage, &Path),
     (progress, log, cancellation): (&mut ProgressSender, &mut LogSender, &PluginCancellation),
 ) -> anyhow::Result<()> {
     log.info(format!("Downloading {} works to disk...", works.len()));
@@ -63,9 +76,10 @@ pub fn download_works(
                 progress.set_percent(i, works_len);
                 match ensure_work_data_is_cached(
                     &work,
+                    fetch,
                     db,
-                    (agent, throttle),
-                    (data_dir, tmp_dir),
+                    (agent, throttle, bandwidth, warc),
+                    (storage, tmp_dir),
                     (&mut log.clone(), cancellation),
                 ) {
                     Ok(_) => {}
@@ -101,49 +115,26 @@ pub fn download_works(
     Ok(())
 }
 
-// Returns the absolute path for I/O and the relative path in the data directory for metadata.
-pub fn get_data_path_for_url(data_dir: &Path, url: &str) -> Result<(PathBuf, String), io::Error> {
-    let ext = url
-        .rsplit('/')
-        .next()
-        .unwrap_or_default()
-        .rsplit('.')
-        .next()
-        .unwrap_or_default()
-        .split('?')
-        .next()
-        .unwrap_or_default();
-    let key = Sha256::digest(url.as_bytes());
-    let key = format!("{key:x}");
-    let level1 = &key[0..2];
-    let level2 = &key[2..4];
-    let file_base = &key[4..];
-    let relative = format!("{level1}/{level2}/{file_base}.{ext}");
-    let dir_path = data_dir.join(level1).join(level2);
-    fs::create_dir_all(&dir_path)?;
-    Ok((data_dir.join(&relative), relative))
-}
-
 fn ensure_work_data_is_cached(
     work: &Work,
+    fetch: FetchFiles,
     db: &DbWriteHandle,
-    (agent, throttle): (&Agent, &CallingThrottle),
-    (data_dir, tmp_dir): (&Path, &Path),
+    net: (&Agent, &CallingThrottle, &PluginBandwidth, &WarcRecorder),
+    (storage, tmp_dir): (&Storage, &Path),
     (log, cancellation): (&mut LogSender, &PluginCancellation),
 ) -> Result<(), DownloadError> {
-    let mut preview_path = ensure_data_url(
-        work.preview_url(),
-        data_dir,
-        tmp_dir,
-        agent,
-        log,
-        throttle,
-        cancellation,
+    let (mut preview_path, _) = ensure_work_file(
+        (work, MediaRole::Preview, work.preview_url()),
+        fetch,
+        db,
+        net,
+        (storage, tmp_dir),
+        (log, cancellation),
     )?;
 
     // If the preview we downloaded is not an image, try to thumbnail it.
-    if !is_image(&data_dir.join(&preview_path)) {
-        match make_preview_thumbnail(work.preview_url(), &preview_path, data_dir, log) {
+    if !is_image(&storage.resolve(Path::new(&preview_path))) {
+        match make_preview_thumbnail(work.preview_url(), &preview_path, storage, log) {
             Ok(v) => {
                 preview_path = v;
             }
@@ -153,102 +144,345 @@ fn ensure_work_data_is_cached(
         }
     }
 
-    let screen_path = ensure_data_url(
+    let (screen_path, screen_is_new) = if fetch.screen {
+        let (path, is_new) = ensure_work_file(
+            (work, MediaRole::Screen, work.screen_url()),
+            fetch,
+            db,
+            net,
+            (storage, tmp_dir),
+            (log, cancellation),
+        )?;
+        (Some(path), is_new)
+    } else {
+        (None, false)
+    };
+    let thumb = screen_path
+        .as_deref()
+        .filter(|screen_path| is_image(Path::new(screen_path)))
+        .and_then(|screen_path| {
+            match make_gallery_thumbnail(work.screen_url(), screen_path, storage) {
+                Ok(thumb) => Some(thumb),
+                Err(e) => {
+                    log.warn(format!("failed to make gallery thumbnail: {e}"));
+                    None
+                }
+            }
+        });
+
+    // Note: we fetch the full-size rendition from tiled image servers, rather than the tiles.
+    let archive_path = match work.archive_url() {
+        Some(archive_url) if fetch.archive => Some(
+            ensure_work_file(
+                (work, MediaRole::Archive, &archive_fetch_url(archive_url)),
+                fetch,
+                db,
+                net,
+                (storage, tmp_dir),
+                (log, cancellation),
+            )?
+            .0,
+        ),
+        _ => None,
+    };
+
+    // Note: the model goes with the screen file, as it is what you look at in the slideshow.
+    let model_path = match work.model_url() {
+        Some(model_url) if fetch.screen => Some(
+            ensure_work_file(
+                (work, MediaRole::Model3D, model_url),
+                fetch,
+                db,
+                net,
+                (storage, tmp_dir),
+                (log, cancellation),
+            )?
+            .0,
+        ),
+        _ => None,
+    };
+
+    // Note: the transcript comes along the first time we fetch the screen file; after that it is
+    //       in the DB, and refreshing the feed doesn't fetch it again.
+    let transcript = match work.transcript_url() {
+        Some(transcript_url) if screen_is_new => {
+            fetch_transcript(transcript_url, net, log, cancellation)
+        }
+        _ => None,
+    };
+
+    let nsfw_score = thumb.as_ref().and_then(|thumb| thumb.nsfw_score);
+    let (thumb_path, aspect_ratio) = thumb.map(|thumb| (thumb.path, thumb.aspect_ratio)).unzip();
+    db.set_work_download_paths(
         work.screen_url(),
-        data_dir,
-        tmp_dir,
-        agent,
+        preview_path,
+        (screen_path, archive_path, thumb_path),
+        aspect_ratio,
+    )
+    .map_err(|_err| DownloadError::Shutdown)?;
+    if let Some(score) = nsfw_score {
+        db.set_nsfw_score(work.screen_url(), score)
+            .map_err(|_err| DownloadError::Shutdown)?;
+    }
+    if let Some(model_path) = model_path {
+        db.set_work_model_path(work.screen_url(), model_path)
+            .map_err(|_err| DownloadError::Shutdown)?;
+    }
+    if let Some(cues) = transcript {
+        db.set_work_transcript(work.screen_url(), TranscriptSource::Feed, cues)
+            .map_err(|_err| DownloadError::Shutdown)?;
+    }
+    Ok(())
+}
+
+// A transcript is a nice to have, so failing to get one is only worth a warning.
+fn fetch_transcript(
+    url: &str,
+    (agent, throttle, _, _): (&Agent, &CallingThrottle, &PluginBandwidth, &WarcRecorder),
+    log: &mut LogSender,
+    cancellation: &PluginCancellation,
+) -> Option<Vec<Cue>> {
+    log.trace(format!("fetch_transcript({url})"));
+    let text = call_with_backoff(|| agent.get(url), throttle, cancellation, log)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut resp| Ok(resp.body_mut().read_to_string()?));
+    match text.and_then(|text| transcript::parse(&text)) {
+        Ok(cues) => Some(cues),
+        Err(e) => {
+            log.warn(format!("Failed to get the transcript at {url}: {e}"));
+            None
+        }
+    }
+}
+
+// Make sure we have one of the work's files, trying the work's mirrors for it, then the Wayback
+// Machine if asked, should `url` be gone. Returns the stored path for the DB, and whether we
+// fetched the file just now.
+fn ensure_work_file(
+    (work, role, url): (&Work, MediaRole, &str),
+    fetch: FetchFiles,
+    db: &DbWriteHandle,
+    net: (&Agent, &CallingThrottle, &PluginBandwidth, &WarcRecorder),
+    (storage, tmp_dir): (&Storage, &Path),
+    (log, cancellation): (&mut LogSender, &PluginCancellation),
+) -> Result<(String, bool), DownloadError> {
+    let mut fallbacks = work
+        .mirrors_for(role)
+        .map(|mirror| match role {
+            MediaRole::Archive => archive_fetch_url(mirror),
+            _ => mirror.to_owned(),
+        })
+        .collect::<Vec<_>>();
+    if fetch.wayback
+        && let Some(wayback) = wayback_url(url, Timestamp::now())
+    {
+        fallbacks.push(wayback);
+    }
+    let kind = match role {
+        MediaRole::Preview => DataKind::Preview,
+        MediaRole::Screen => DataKind::Screen,
+        MediaRole::Archive => DataKind::Archive,
+        MediaRole::Model3D => DataKind::Model3D,
+    };
+    let (path, sha256, fetched_from) = ensure_data_url(
+        (url, &fallbacks),
+        kind,
+        (storage, tmp_dir),
+        net,
         log,
-        throttle,
         cancellation,
     )?;
+    let is_new = sha256.is_some();
+    if let Some(sha256) = sha256 {
+        db.record_file_hash(&path, sha256)
+            .map_err(|_err| DownloadError::Shutdown)?;
+    }
+    if let Some(fetched_from) = fetched_from {
+        db.set_work_fetched_from(work.screen_url(), role, &fetched_from)
+            .map_err(|_err| DownloadError::Shutdown)?;
+    }
+    Ok((path, is_new))
+}
 
-    // FIXME: figure out how to download an iiif tiled image.
-    let archive_path = None;
-    // let archive_path = if let Some(archive_url) = work.archive_url() {
-    //     Some(ensure_data_url(
-    //         archive_url,
-    //         data_dir,
-    //         tmp_dir,
-    //         agent,
-    //         log,
-    //         throttle,
-    //         cancellation,
-    //     )?)
-    // } else {
-    //     None
-    // };
-
-    db.set_work_download_paths(work.screen_url(), preview_path, screen_path, archive_path)
-        .map_err(|_err| DownloadError::Shutdown)?;
-    Ok(())
+// Passes writes through, hashing them on the way, so that we don't have to read the file back.
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
-// Reads the data to disk and returns the data-dir-relative path for storage.
+// Reads the data to disk and returns the stored path for the DB, the file's hash if we
+// downloaded it just now, and the fallback we got it from, if `url` was gone. The file is stored
+// under `url` whichever we got it from, so that we find it again next time.
 fn ensure_data_url(
-    url: &str,
-    data_dir: &Path,
-    tmp_dir: &Path,
-    agent: &Agent,
+    (url, fallbacks): (&str, &[String]),
+    kind: DataKind,
+    (storage, tmp_dir): (&Storage, &Path),
+    (agent, throttle, bandwidth, warc): (&Agent, &CallingThrottle, &PluginBandwidth, &WarcRecorder),
     log: &mut LogSender,
-    throttle: &CallingThrottle,
     cancellation: &PluginCancellation,
-) -> Result<String, DownloadError> {
-    let (abs_path, rel_path) = get_data_path_for_url(data_dir, url)
-        .map_err(|e| DownloadError::DataDirCreationFailed(data_dir.to_path_buf(), e))?;
-    if abs_path.exists() {
+) -> Result<(String, Option<String>, Option<String>), DownloadError> {
+    // Note: a media page has no extension of its own; we ask yt-dlp for one by the kind.
+    let place_url = match media_page_url(url) {
+        Some((_, kind)) => format!("{url}#.{}", kind.extension()),
+        None => url.to_owned(),
+    };
+    let (abs_path, rel_path) = storage
+        .place_for_url(kind, &place_url)
+        .map_err(|e| DownloadError::DataDirCreationFailed(storage.root_path_for(kind), e))?;
+    if abs_path.exists() || storage.exists(&rel_path).unwrap_or(false) {
         // log.trace(format!("cached: ensure_data_url({url})"));
-        return Ok(rel_path);
+        return Ok((rel_path, None, None));
+    }
+
+    // Note: wait for the schedule and the quota before the throttle, so that we don't hold a
+    //       throttle slot.
+    if !bandwidth.is_in_window() {
+        log.info("Waiting for the download window to open...");
+    }
+    bandwidth
+        .wait_for_window(cancellation)
+        .map_err(|_e| DownloadError::Cancelled)?;
+    if bandwidth.is_over_quota() {
+        log.warn("Over the plugin's storage quota; waiting for more room...");
     }
+    bandwidth
+        .wait_for_quota(cancellation)
+        .map_err(|_e| DownloadError::Cancelled)?;
 
-    // Note: check throttle before opening files, etc, but after we might bail for caching.
-    match throttle.throttle(cancellation) {
-        Ok(_) => {}
-        Err(ThrottleError::Cancelled) => return Err(DownloadError::Cancelled),
+    if let Some((page_url, media_kind)) = media_page_url(url) {
+        let (rel_path, sha256) = ensure_media_url(
+            (page_url, media_kind),
+            (&abs_path, rel_path),
+            (storage, tmp_dir),
+            throttle,
+            log,
+            cancellation,
+        )?;
+        return Ok((rel_path, sha256, None));
+    }
+
+    log.trace(format!("ensure_data_url({url})"));
+    let mut remaining = fallbacks.iter();
+    let mut candidate = url;
+    let (fetched_url, mut resp) = loop {
+        match call_with_backoff(|| agent.get(candidate), throttle, cancellation, log) {
+            Ok(resp) => break (candidate, resp),
+            // Note: only a file that the server says is gone is worth looking for elsewhere.
+            Err(RequestError::Http(ureq::Error::StatusCode(status @ (404 | 410))))
+                if !remaining.as_slice().is_empty() =>
+            {
+                let next = remaining.next().expect("not empty");
+                log.info(format!(
+                    "{candidate} is gone (HTTP {status}); trying {next}"
+                ));
+                candidate = next;
+            }
+            Err(RequestError::Cancelled) => return Err(DownloadError::Cancelled),
+            Err(RequestError::Http(e)) => {
+                metrics::count(metrics::DOWNLOAD_FAILURES_TOTAL, "", 1);
+                return Err(DownloadError::DownloadHeaders(e));
+            }
+        }
     };
 
     let tmp_path = make_temp_path(tmp_dir);
-    {
+    let sha256 = {
         // Note: in a block to Drop, to close the file before renaming it, just for sanity.
         let tmp_fp = fs::File::create(&tmp_path)
             .map_err(|err| DownloadError::TmpFileCreationFailed(tmp_path.clone(), err))?;
-        log.trace(format!("ensure_data_url({url})"));
-        let mut resp = agent
-            .get(url)
-            .call()
-            .map_err(DownloadError::DownloadHeaders)?;
-        io::copy(
-            &mut resp.body_mut().as_reader(),
-            &mut io::BufWriter::new(tmp_fp),
-        )
-        .map_err(DownloadError::DownloadBody)?;
-    }
-    fs::rename(&tmp_path, &abs_path).map_err(|err| {
+        let mut writer =
+            HashingWriter::new(bandwidth.writer(io::BufWriter::new(tmp_fp), cancellation));
+        let bytes = io::copy(&mut resp.body_mut().as_reader(), &mut writer).map_err(|e| {
+            metrics::count(metrics::DOWNLOAD_FAILURES_TOTAL, "", 1);
+            DownloadError::DownloadBody(e)
+        })?;
+        metrics::count(metrics::DOWNLOADS_TOTAL, "", 1);
+        metrics::count(metrics::DOWNLOAD_BYTES_TOTAL, "work", bytes);
+        writer.flush().map_err(DownloadError::DownloadBody)?;
+        writer.finish()
+    };
+    if warc.is_enabled()
+        && let Err(e) = fs::File::open(&tmp_path).and_then(|mut fp| {
+            let len = fp.metadata()?.len();
+            warc.record(fetched_url, &resp, (&mut fp, len, &sha256))
+        })
+    {
+        log.warn(format!("Failed to record {fetched_url} to WARC: {e}"));
+    }
+    // Note: the tmp dir is not necessarily on the same volume as the root.
+    move_file(&tmp_path, &abs_path, false).map_err(|err| {
         DownloadError::TmpFileRenameFailed(tmp_path.clone(), abs_path.clone(), err)
     })?;
-    Ok(rel_path)
+    storage
+        .commit(&rel_path)
+        .map_err(|e| DownloadError::Upload(rel_path.clone(), e.to_string()))?;
+    let fetched_from = (fetched_url != url).then(|| {
+        log.info(format!("Got {url} from {fetched_url}"));
+        fetched_url.to_owned()
+    });
+    Ok((rel_path, Some(sha256), fetched_from))
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::path::PathBuf;
-
-    #[test]
-    fn test_get_data_path_for_url() {
-        let data_dir = PathBuf::from("/tmp/artx/data");
-        let (_abs_path, rel_path) =
-            get_data_path_for_url(&data_dir, "https://example.com/image.jpg").expect("test");
-        assert_eq!(
-            rel_path,
-            "e5/db/82b5bf63d49d80c5533616892d3386f43955369520986d67653c700fc53c.jpg"
-        );
-        let (_abs_path, rel_path) =
-            get_data_path_for_url(&data_dir, "https://example.com/image.jpg?id=1234")
-                .expect("test");
-        assert_eq!(
-            rel_path,
-            "2d/fc/1d0596854b006b8c957f01e07bb0694c77a02cc36efec7bd610ba0409c24.jpg"
-        );
-    }
+// Like ensure_data_url, but for a video that yt-dlp has to find the stream for. There is no
+// single response to record to WARC here, so these are not recorded.
+fn ensure_media_url(
+    (page_url, media_kind): (&str, MediaKind),
+    (abs_path, rel_path): (&Path, String),
+    (storage, tmp_dir): (&Storage, &Path),
+    throttle: &CallingThrottle,
+    log: &mut LogSender,
+    cancellation: &PluginCancellation,
+) -> Result<(String, Option<String>), DownloadError> {
+    throttle
+        .throttle(cancellation)
+        .map_err(|_e| DownloadError::Cancelled)?;
+    log.trace(format!("ensure_media_url({page_url})"));
+    let tmp_path = make_temp_path(tmp_dir);
+    let downloaded =
+        download_media(page_url, media_kind, &tmp_path, cancellation).map_err(|e| {
+            metrics::count(metrics::DOWNLOAD_FAILURES_TOTAL, "", 1);
+            match e {
+                TextFetchError::Cancellation => DownloadError::Cancelled,
+                e => DownloadError::DownloadBody(io::Error::other(e)),
+            }
+        })?;
+    let sha256 = {
+        let mut writer = HashingWriter::new(io::sink());
+        let bytes = io::copy(&mut fs::File::open(&downloaded)?, &mut writer)?;
+        metrics::count(metrics::DOWNLOADS_TOTAL, "", 1);
+        metrics::count(metrics::DOWNLOAD_BYTES_TOTAL, "work", bytes);
+        writer.finish()
+    };
+    move_file(&downloaded, abs_path, false).map_err(|err| {
+        DownloadError::TmpFileRenameFailed(downloaded.clone(), abs_path.to_owned(), err)
+    })?;
+    storage
+        .commit(&rel_path)
+        .map_err(|e| DownloadError::Upload(rel_path.clone(), e.to_string()))?;
+    Ok((rel_path, Some(sha256)))
 }
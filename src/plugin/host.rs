@@ -1,27 +1,38 @@
 use crate::{
     db::{
         models::{
+            log::DbLogLine,
             plugin::{DbPlugin, PluginId},
-            tag::DbTag,
+            tag::{DbTag, TagKindMapping},
         },
         sync::DbSyncHandle,
-        writer::DbWriteHandle,
+        writer::{DbWriteHandle, ImportSettings},
     },
     plugin::client::create_plugin_task,
     shared::{
+        bandwidth::{DownloadGovernor, DownloadLimits},
+        compliance::{Compliance, CompliancePolicy},
         environment::Environment,
+        fetch_policy::{FetchPolicies, FetchPolicy},
+        http_fixtures::{FixtureMode, HttpFixtures},
+        instance_lock::is_read_only,
         plugin::{PluginCancellation, PluginRequest},
+        plugin_health::PluginHealth,
         progress::{Progress, ProgressMonitor, UpdateSource},
+        storage::Storage,
+        tag_exclusion::{TagExclusionFilter, TagExclusions},
+        throttle::{CallingThrottle, ThrottleTraffic},
         update::DataUpdate,
+        warc::WarcRecorder,
     },
 };
 use anyhow::Result;
-use artchiver_sdk::PluginMetadata;
+use artchiver_sdk::{PluginAction, PluginMetadata, RateLimitStatus, TagKind, WorkTagEdit};
 use crossbeam::channel;
-use log::{Level, error};
+use log::{Level, error, info};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     fs,
     path::{Path, PathBuf},
     thread::JoinHandle,
@@ -29,7 +40,7 @@ use std::{
 
 fn search_for_plugins_to_load(env: &Environment) -> Result<Vec<PathBuf>> {
     let mut rv = Vec::new();
-    for dir in &[env.global_plugin_dir(), env.local_plugin_dir()] {
+    for dir in &env.plugin_dirs() {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let is_wasm_ext = entry
@@ -45,14 +56,117 @@ fn search_for_plugins_to_load(env: &Environment) -> Result<Vec<PathBuf>> {
     Ok(rv)
 }
 
+fn plugin_file_name(source: &Path) -> String {
+    source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
 // The PluginHost holds all PluginHandles -- the egui side of the plugin state -- and
 // aggregates across our plugins to provide a unified data layer.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PluginHost {
     plugins: Vec<PluginHandle>,
+    #[serde(default)]
+    download_limits: DownloadLimits,
+    #[serde(default)]
+    compliance_policy: CompliancePolicy,
+    #[serde(default)]
+    record_warc: bool,
+    #[serde(default)]
+    import: ImportSettings,
+    #[serde(default)]
+    http_fixture_mode: FixtureMode,
+    // Refresh works without downloading them, for all tags or for just these tags, by name.
+    #[serde(default)]
+    metadata_only: bool,
+    #[serde(default)]
+    metadata_only_tags: HashSet<String>,
+    // How far into each tag, by name, "fetch more" has got.
+    #[serde(default)]
+    next_work_offsets: HashMap<String, usize>,
+    // Plugins that are left unloaded, by file name, so that they stay off wherever the plugin
+    // directory is.
+    #[serde(default)]
+    disabled_plugins: BTreeSet<String>,
+    // How many plugins may be working on a task at once.
+    #[serde(default)]
+    max_concurrent_plugins: ConcurrentPlugins,
 
+    // Where the next look for a plugin to start should begin, so that every plugin gets its turn.
+    #[serde(skip)]
+    next_dispatch: usize,
     #[serde(skip)]
     db: Option<DbSyncHandle>,
+    #[serde(skip)]
+    db_write: Option<DbWriteHandle>,
+    #[serde(skip)]
+    governor: DownloadGovernor,
+    #[serde(skip)]
+    compliance: Compliance,
+    #[serde(skip)]
+    warc: WarcRecorder,
+    #[serde(skip)]
+    http_fixtures: HttpFixtures,
+    #[serde(skip)]
+    tag_filter: TagExclusionFilter,
+    #[serde(skip)]
+    fetch_policies: FetchPolicies,
+    #[serde(skip)]
+    storage: Option<Storage>,
+    // A refresh of a big tag, waiting for the user to confirm it.
+    #[serde(skip)]
+    pending_refresh: Option<RefreshEstimate>,
+    // A plugin that filled its quota, waiting for the user to say what to do about it, and the
+    // plugins that the user said to leave paused, by name.
+    #[serde(skip)]
+    pending_quota: Option<QuotaPrompt>,
+    #[serde(skip)]
+    quota_dismissed: HashSet<String>,
+}
+
+// The number of plugins that may run a task at the same time. Each has its own thread, rate
+// limiter, and progress, so this only bounds how much we ask of the network and the writer.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ConcurrentPlugins(usize);
+
+impl Default for ConcurrentPlugins {
+    fn default() -> Self {
+        Self(4)
+    }
+}
+
+// Refreshing a tag this big gets a confirmation first, as it can take hours and fill a disk.
+const LARGE_REFRESH_WORKS: u64 = 2_000;
+// Recently downloaded works to look at per plugin when estimating sizes.
+const SIZE_SAMPLE_WORKS: usize = 200;
+// The works for one press of "fetch more".
+pub const FETCH_PAGE_SIZE: usize = 500;
+// Undownloaded works to HEAD per plugin on each link check.
+const LINK_CHECK_SAMPLE: usize = 100;
+
+// What a refresh of a big tag is likely to cost, and what the user wants to do about it.
+#[derive(Clone, Debug)]
+pub struct RefreshEstimate {
+    pub tag: DbTag,
+    pub works: u64,
+    // From the median size of the files we already have from each of the tag's plugins; None
+    // if we have nothing to go on for one of them.
+    pub bytes: Option<u64>,
+    pub metadata_only: bool,
+    pub max_works: Option<usize>,
+}
+
+// A plugin whose downloads are paused because it has filled its storage quota.
+#[derive(Clone, Debug)]
+pub struct QuotaPrompt {
+    pub plugin: String,
+    pub usage: u64,
+    pub quota: u64,
+    // What the user wants to raise the quota to, in GiB.
+    pub raise_to_gib: u64,
 }
 
 impl PluginHost {
@@ -70,7 +184,24 @@ impl PluginHost {
         db_sync: &DbSyncHandle,
         db_write: &DbWriteHandle,
     ) -> Result<()> {
-        for source in search_for_plugins_to_load(env)?.drain(..) {
+        self.apply_compliance_policy();
+        self.warc = WarcRecorder::new(&env.data_dir().join("warc"));
+        self.apply_warc_recording();
+        // Note: before any plugin can start an import.
+        db_write.set_import_settings(self.import.clone())?;
+        self.http_fixtures = HttpFixtures::new(&env.data_dir().join("http-fixtures"));
+        self.apply_http_fixture_mode();
+        // Note: plugins exist to write to the DB, so there is nothing for them to do read-only.
+        let sources = if is_read_only() {
+            info!("Read-only: not loading plugins");
+            Vec::new()
+        } else {
+            search_for_plugins_to_load(env)?
+                .into_iter()
+                .filter(|source| !self.disabled_plugins.contains(&plugin_file_name(source)))
+                .collect()
+        };
+        for source in sources {
             let (tx_to_plugin, rx_from_runner) = channel::unbounded();
 
             match create_plugin_task(
@@ -80,13 +211,21 @@ impl PluginHost {
                 db_write.clone(),
                 rx_from_runner,
                 progress_mon.monitor_channel(),
+                (
+                    (self.governor.clone(), self.compliance.clone()),
+                    (self.warc.clone(), self.http_fixtures.clone()),
+                    self.tag_filter.clone(),
+                    self.fetch_policies.clone(),
+                ),
             ) {
-                Ok((plugin_task, cancellation)) => {
+                Ok((plugin_task, cancellation, throttle)) => {
+                    let remote =
+                        PluginRemote::new(plugin_task, cancellation, throttle, tx_to_plugin);
                     if let Some(plugin) = self.plugins.iter_mut().find(|p| p.source() == source) {
-                        plugin.initialize(&source, plugin_task, cancellation, tx_to_plugin);
+                        plugin.initialize(&source, remote);
                     } else {
                         let mut plugin = PluginHandle::default();
-                        plugin.initialize(&source, plugin_task, cancellation, tx_to_plugin);
+                        plugin.initialize(&source, remote);
                         self.plugins.push(plugin);
                     }
                 }
@@ -105,9 +244,181 @@ impl PluginHost {
         self.plugins.retain(|p| p.remote.is_some());
 
         self.db = Some(db_sync.clone());
+        self.db_write = Some(db_write.clone());
+        self.storage = Some(env.storage().clone());
+        self.apply_download_limits();
+        self.apply_tag_exclusions();
+        self.apply_fetch_policies();
         Ok(())
     }
 
+    pub fn download_limits_mut(&mut self) -> &mut DownloadLimits {
+        &mut self.download_limits
+    }
+
+    // Push the global and per-plugin download limits and storage quotas out to the download
+    // threads.
+    pub fn apply_download_limits(&self) {
+        self.governor.configure(
+            &self.download_limits,
+            self.plugins
+                .iter()
+                .filter_map(|p| Some((p.name(), p.download_limits.as_ref()?))),
+        );
+        self.governor.configure_quotas(
+            self.plugins
+                .iter()
+                .filter_map(|p| Some((p.name(), p.storage_quota_bytes()?))),
+        );
+    }
+
+    // How much disk the plugin's works are using, as far as we know; None until we've counted.
+    pub fn storage_usage(&self, plugin: &PluginHandle) -> Option<u64> {
+        self.governor.usage(&plugin.name())
+    }
+
+    // Count up what the plugin's works take on disk, to correct for files that were removed since
+    // they were downloaded.
+    fn refresh_storage_usage(
+        governor: &DownloadGovernor,
+        db: &DbSyncHandle,
+        plugin: &PluginHandle,
+    ) {
+        let Some(plugin_id) = plugin.id() else {
+            return;
+        };
+        match db.sync_plugin_storage_usage(plugin_id) {
+            Ok(bytes) => governor.set_usage(&plugin.name(), bytes),
+            Err(e) => error!("Failed to count storage used by {}: {e}", plugin.name()),
+        }
+    }
+
+    pub fn pending_quota_mut(&mut self) -> Option<&mut QuotaPrompt> {
+        self.pending_quota.as_mut()
+    }
+
+    // Leave the plugin paused until it is back under its quota.
+    pub fn dismiss_pending_quota(&mut self) {
+        if let Some(prompt) = self.pending_quota.take() {
+            self.quota_dismissed.insert(prompt.plugin);
+        }
+    }
+
+    pub fn raise_pending_quota(&mut self) {
+        let Some(prompt) = self.pending_quota.take() else {
+            return;
+        };
+        if let Some(plugin) = self.plugins.iter_mut().find(|p| p.name() == prompt.plugin) {
+            plugin.storage_quota_gib = Some(prompt.raise_to_gib);
+        }
+        self.apply_download_limits();
+    }
+
+    // Delete the plugin's works that aren't favorites, to make room. Downloads pick back up once
+    // the files are gone and we've counted again.
+    pub fn purge_for_pending_quota(&mut self) -> Result<()> {
+        let Some(prompt) = self.pending_quota.take() else {
+            return Ok(());
+        };
+        let Some(plugin_id) = self
+            .plugins
+            .iter()
+            .find(|p| p.name() == prompt.plugin)
+            .and_then(PluginHandle::id)
+        else {
+            return Ok(());
+        };
+        // Note: don't ask again while the purge runs.
+        self.quota_dismissed.insert(prompt.plugin);
+        self.db_write
+            .as_ref()
+            .expect("uninit")
+            .purge_plugin_works(plugin_id)
+    }
+
+    fn check_quotas(&mut self) {
+        self.quota_dismissed
+            .retain(|name| self.governor.is_over_quota(name));
+        if self.pending_quota.is_some() {
+            return;
+        }
+        self.pending_quota = self.plugins.iter().find_map(|p| {
+            let name = p.name();
+            if !self.governor.is_over_quota(&name) || self.quota_dismissed.contains(&name) {
+                return None;
+            }
+            Some(QuotaPrompt {
+                usage: self.governor.usage(&name).unwrap_or_default(),
+                quota: p.storage_quota_bytes()?,
+                raise_to_gib: p.storage_quota_gib?.saturating_mul(2).max(1),
+                plugin: name,
+            })
+        });
+    }
+
+    pub fn compliance_policy_mut(&mut self) -> &mut CompliancePolicy {
+        &mut self.compliance_policy
+    }
+
+    pub fn apply_compliance_policy(&self) {
+        self.compliance.configure(&self.compliance_policy);
+    }
+
+    pub fn record_warc_mut(&mut self) -> &mut bool {
+        &mut self.record_warc
+    }
+
+    // Push each plugin's tag exclusions out to the plugin threads, for the next tag refresh.
+    pub fn apply_tag_exclusions(&self) {
+        self.tag_filter
+            .configure(self.plugins.iter().map(|p| (p.name(), &p.tag_exclusions)));
+    }
+
+    // Push each plugin's fetch policy out to its download threads.
+    pub fn apply_fetch_policies(&self) {
+        self.fetch_policies
+            .configure(self.plugins.iter().map(|p| (p.name(), p.fetch_policy)));
+    }
+
+    // Save any tag kinds the user picked; they apply from the next tag refresh.
+    pub fn apply_tag_kinds(&mut self) {
+        let db_write = self.db_write.as_ref().expect("uninit");
+        for plugin in &mut self.plugins {
+            let Some(plugin_id) = plugin.id() else {
+                continue;
+            };
+            for (source_type, kind) in plugin.unsaved_tag_kinds.drain(..) {
+                if let Err(e) = db_write.set_tag_kind_mapping(plugin_id, &source_type, kind) {
+                    error!("Failed to save tag kind for {source_type}: {e}");
+                }
+            }
+        }
+    }
+
+    pub fn apply_warc_recording(&self) {
+        self.warc.set_enabled(self.record_warc);
+    }
+
+    pub fn import_settings_mut(&mut self) -> &mut ImportSettings {
+        &mut self.import
+    }
+
+    pub fn apply_import_settings(&self) {
+        if let Some(db_write) = &self.db_write
+            && let Err(e) = db_write.set_import_settings(self.import.clone())
+        {
+            error!("Failed to send import settings to the DB writer: {e}");
+        }
+    }
+
+    pub fn http_fixture_mode_mut(&mut self) -> &mut FixtureMode {
+        &mut self.http_fixture_mode
+    }
+
+    pub fn apply_http_fixture_mode(&self) {
+        self.http_fixtures.set_mode(self.http_fixture_mode);
+    }
+
     pub fn plugins(&self) -> impl Iterator<Item = &PluginHandle> {
         self.plugins.iter()
     }
@@ -116,7 +427,217 @@ impl PluginHost {
         self.plugins.iter_mut()
     }
 
+    pub fn disabled_plugins(&self) -> impl Iterator<Item = &str> {
+        self.disabled_plugins.iter().map(String::as_str)
+    }
+
+    // Stop the plugin now, and leave it unloaded on future runs. Its queued tasks are dropped, as
+    // when its file is removed.
+    pub fn disable_plugin(&mut self, source: &Path) -> Result<()> {
+        self.disabled_plugins.insert(plugin_file_name(source));
+        if let Some(offset) = self.plugins.iter().position(|p| p.source() == source) {
+            self.plugins.remove(offset).cleanup_for_exit()?;
+        }
+        Ok(())
+    }
+
+    // Note: plugins are only loaded at startup, so this takes effect on the next run.
+    pub fn enable_plugin(&mut self, file_name: &str) {
+        self.disabled_plugins.remove(file_name);
+    }
+
+    pub fn max_concurrent_plugins_mut(&mut self) -> &mut usize {
+        &mut self.max_concurrent_plugins.0
+    }
+
+    pub fn metadata_only_mut(&mut self) -> &mut bool {
+        &mut self.metadata_only
+    }
+
+    pub fn is_metadata_only(&self, tag: &DbTag) -> bool {
+        self.metadata_only || self.metadata_only_tags.contains(tag.name())
+    }
+
+    pub fn is_tag_metadata_only(&self, tag: &DbTag) -> bool {
+        self.metadata_only_tags.contains(tag.name())
+    }
+
+    pub fn set_tag_metadata_only(&mut self, tag: &DbTag, metadata_only: bool) {
+        if metadata_only {
+            self.metadata_only_tags.insert(tag.name().to_owned());
+        } else {
+            self.metadata_only_tags.remove(tag.name());
+        }
+    }
+
+    // Fetch the files for works that were recorded without them, and optionally their
+    // originals. `screen_urls` are grouped by the name of the plugin that should fetch them.
+    pub fn download_works(&mut self, screen_urls: HashMap<String, Vec<String>>, archives: bool) {
+        for (name, screen_urls) in screen_urls {
+            if let Some(plugin) = self.plugins.iter_mut().find(|p| p.name() == name) {
+                plugin.task_queue.push_back(PluginRequest::DownloadWorks {
+                    screen_urls,
+                    archives,
+                });
+            } else {
+                error!("No plugin named {name} to download works with");
+            }
+        }
+    }
+
+    // Look for dead links among each plugin's undownloaded works, and try to find them again.
+    pub fn check_links(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.task_queue.push_back(PluginRequest::CheckLinks {
+                sample: LINK_CHECK_SAMPLE,
+            });
+        }
+    }
+
+    // Send the tag edits that the user reviewed back to the works' sources. `edits` are grouped by
+    // the name of the plugin that the works came from.
+    pub fn push_tag_edits(&mut self, edits: HashMap<String, Vec<(String, WorkTagEdit)>>) {
+        for (name, edits) in edits {
+            if let Some(plugin) = self.plugins.iter_mut().find(|p| p.name() == name) {
+                plugin
+                    .task_queue
+                    .push_back(PluginRequest::PushTagEdits { edits });
+            } else {
+                error!("No plugin named {name} to push tag edits with");
+            }
+        }
+    }
+
+    // Queue the files for the works on `tag` that we recorded but never fetched, e.g. from an
+    // interrupted refresh or while the tag was metadata only. Returns how many were queued.
+    pub fn download_missing_for_tag(&mut self, tag: &DbTag) -> Result<usize> {
+        let screen_urls = self
+            .db
+            .as_ref()
+            .expect("uninit")
+            .sync_list_undownloaded_screen_urls(tag.id())?;
+        let Some(plugin) = tag.sources().find(|name| !name.is_empty()) else {
+            return Ok(0);
+        };
+        let count = screen_urls.len();
+        if count > 0 {
+            self.download_works(HashMap::from([(plugin.to_owned(), screen_urls)]), false);
+        }
+        Ok(count)
+    }
+
+    // Refresh from the tags list, asking first if the tag is a big one.
+    pub fn request_refresh_works_for_tag(&mut self, tag: &DbTag) -> Result<()> {
+        if self.is_metadata_only(tag) || tag.network_count() < LARGE_REFRESH_WORKS {
+            return self.refresh_works_for_tag(tag);
+        }
+        self.pending_refresh = Some(self.estimate_refresh(tag)?);
+        Ok(())
+    }
+
+    fn estimate_refresh(&self, tag: &DbTag) -> Result<RefreshEstimate> {
+        let db = self.db.as_ref().expect("uninit");
+        let mut bytes = Some(0u64);
+        for (plugin_id, works) in db.sync_list_presumed_work_counts(tag.id())? {
+            let median = self.median_work_bytes(db, plugin_id)?;
+            bytes = bytes
+                .zip(median)
+                .map(|(total, median)| total.saturating_add(works.saturating_mul(median)));
+        }
+        Ok(RefreshEstimate {
+            tag: tag.clone(),
+            works: tag.network_count(),
+            bytes,
+            metadata_only: false,
+            max_works: None,
+        })
+    }
+
+    // The median size of the files for one work from this plugin, if we have any to look at.
+    fn median_work_bytes(&self, db: &DbSyncHandle, plugin_id: PluginId) -> Result<Option<u64>> {
+        let storage = self.storage.as_ref().expect("uninit");
+        let size = |stored: &str| {
+            fs::metadata(storage.resolve(Path::new(stored)))
+                .map(|meta| meta.len())
+                .ok()
+        };
+        // Note: files on remote roots that aren't in the cache don't count, as we can't see them.
+        let mut sizes = db
+            .sync_list_downloaded_paths_sample(plugin_id, SIZE_SAMPLE_WORKS)?
+            .into_iter()
+            .filter_map(|(preview, screen)| {
+                Some(size(&screen)? + preview.as_deref().and_then(size).unwrap_or(0))
+            })
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        Ok(sizes.get(sizes.len() / 2).copied())
+    }
+
+    pub fn pending_refresh_mut(&mut self) -> Option<&mut RefreshEstimate> {
+        self.pending_refresh.as_mut()
+    }
+
+    pub fn cancel_pending_refresh(&mut self) {
+        self.pending_refresh = None;
+    }
+
+    pub fn confirm_pending_refresh(&mut self) -> Result<()> {
+        if let Some(estimate) = self.pending_refresh.take() {
+            self.queue_refresh_works_for_tag(
+                &estimate.tag,
+                estimate.metadata_only,
+                estimate.max_works,
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn refresh_works_for_tag(&mut self, tag: &DbTag) -> Result<()> {
+        self.queue_refresh_works_for_tag(tag, self.is_metadata_only(tag), None)
+    }
+
+    fn queue_refresh_works_for_tag(
+        &mut self,
+        tag: &DbTag,
+        metadata_only: bool,
+        max_works: Option<usize>,
+    ) -> Result<()> {
+        self.queue_for_tag_plugins(
+            tag,
+            &PluginRequest::RefreshWorksForTag {
+                tag: tag.name().to_owned(),
+                metadata_only,
+                max_works,
+            },
+        )
+    }
+
+    // Where "fetch more" will start from for this tag.
+    pub fn next_work_offset(&self, tag: &DbTag) -> usize {
+        self.next_work_offsets
+            .get(tag.name())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Queue the next page of works for the tag, following on from the last one we fetched.
+    pub fn fetch_more_works_for_tag(&mut self, tag: &DbTag) -> Result<()> {
+        let offset = self.next_work_offset(tag);
+        self.queue_for_tag_plugins(
+            tag,
+            &PluginRequest::FetchWorksForTagRange {
+                tag: tag.name().to_owned(),
+                offset,
+                limit: FETCH_PAGE_SIZE,
+                metadata_only: self.is_metadata_only(tag),
+            },
+        )?;
+        self.next_work_offsets
+            .insert(tag.name().to_owned(), offset + FETCH_PAGE_SIZE);
+        Ok(())
+    }
+
+    fn queue_for_tag_plugins(&mut self, tag: &DbTag, request: &PluginRequest) -> Result<()> {
         let plugin_ids = self
             .db
             .as_ref()
@@ -126,11 +647,7 @@ impl PluginHost {
             if let Some(plugin_id) = plugin.id() {
                 // Only ask for matching works if the tag came from a plugin.
                 if plugin_ids.contains(&plugin_id) {
-                    plugin
-                        .task_queue
-                        .push_back(PluginRequest::RefreshWorksForTag {
-                            tag: tag.name().to_owned(),
-                        });
+                    plugin.task_queue.push_back(request.clone());
                 }
             }
         }
@@ -138,8 +655,93 @@ impl PluginHost {
     }
 
     pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::RefreshTagsRequested => {
+                    for plugin in &mut self.plugins {
+                        plugin.refresh_tags();
+                    }
+                }
+                DataUpdate::RefreshWorksForTagRequested { tag } => {
+                    if let Err(e) = self.refresh_works_for_tag(tag) {
+                        error!("Failed to queue refresh for tag {}: {e}", tag.name());
+                    }
+                }
+                _ => {}
+            }
+        }
+        let db = self.db.as_ref().expect("uninit");
+        let mut new_logs = Vec::new();
         for plugin in &mut self.plugins {
-            plugin.handle_updates(updates);
+            plugin.handle_updates(updates, db);
+            new_logs.append(&mut plugin.unsaved_log_lines);
+        }
+        self.dispatch_tasks();
+        // Note: overrides are keyed by name, which may only now be known.
+        if updates
+            .iter()
+            .any(|u| matches!(u, DataUpdate::PluginInfo { .. }))
+        {
+            self.apply_download_limits();
+            self.apply_tag_exclusions();
+            self.apply_fetch_policies();
+        }
+        // Note: recount a plugin's storage once we know its id, and whenever it might have
+        //       changed by more than its downloads.
+        for update in updates {
+            let recount = |plugin: &PluginHandle| match update {
+                DataUpdate::PluginInfo { source, .. } => source == plugin.source(),
+                DataUpdate::CompletedTask {
+                    source: UpdateSource::Plugin(id),
+                } => Some(*id) == plugin.id(),
+                DataUpdate::PluginWorksPurged { plugin_id, .. } => Some(*plugin_id) == plugin.id(),
+                // Note: restoring works from the trash may have put any plugin's files back.
+                DataUpdate::TrashedWorks(_) => true,
+                _ => false,
+            };
+            for plugin in self.plugins.iter().filter(|p| recount(p)) {
+                Self::refresh_storage_usage(&self.governor, db, plugin);
+                if matches!(update, DataUpdate::PluginWorksPurged { .. }) {
+                    self.quota_dismissed.remove(&plugin.name());
+                }
+            }
+        }
+        self.check_quotas();
+        if !new_logs.is_empty()
+            && let Err(e) = self
+                .db_write
+                .as_ref()
+                .expect("uninit")
+                .append_plugin_logs(new_logs)
+        {
+            error!("Failed to save plugin logs: {e}");
+        }
+    }
+
+    // Start queued tasks on idle plugins, up to the concurrency limit, taking the plugins in turn
+    // so that one with a long queue (or a slow source) can't keep the others waiting.
+    fn dispatch_tasks(&mut self) {
+        let busy = self
+            .plugins
+            .iter()
+            .map(PluginHandle::is_busy)
+            .collect::<Vec<_>>();
+        let waiting = self
+            .plugins
+            .iter()
+            .map(|p| !p.task_queue.is_empty())
+            .collect::<Vec<_>>();
+        let starts = pick_plugins_to_start(
+            &busy,
+            &waiting,
+            self.next_dispatch,
+            self.max_concurrent_plugins.0,
+        );
+        for &offset in &starts {
+            self.plugins[offset].start_next_task();
+        }
+        if let Some(last) = starts.last() {
+            self.next_dispatch = last + 1;
         }
     }
 
@@ -151,10 +753,34 @@ impl PluginHost {
     }
 }
 
+// The plugins to start a task on, in the order to start them: idle plugins with work waiting,
+// round-robin from `start`, until `limit` plugins are busy.
+fn pick_plugins_to_start(
+    busy: &[bool],
+    waiting: &[bool],
+    start: usize,
+    limit: usize,
+) -> Vec<usize> {
+    let mut running = busy.iter().filter(|b| **b).count();
+    let mut starts = Vec::new();
+    for i in 0..busy.len() {
+        if running >= limit {
+            break;
+        }
+        let offset = (start + i) % busy.len();
+        if !busy[offset] && waiting[offset] {
+            starts.push(offset);
+            running += 1;
+        }
+    }
+    starts
+}
+
 #[derive(Debug)]
 struct PluginRemote {
     task: JoinHandle<()>,
     cancellation: PluginCancellation,
+    throttle: CallingThrottle,
     tx_to_plugin: channel::Sender<PluginRequest>,
 }
 
@@ -162,11 +788,13 @@ impl PluginRemote {
     fn new(
         task: JoinHandle<()>,
         cancellation: PluginCancellation,
+        throttle: CallingThrottle,
         tx_to_plugin: channel::Sender<PluginRequest>,
     ) -> Self {
         Self {
             task,
             cancellation,
+            throttle,
             tx_to_plugin,
         }
     }
@@ -191,11 +819,31 @@ pub struct PluginHandle {
     #[serde(skip)]
     progress: Progress,
     #[serde(skip)]
-    log_messages: VecDeque<(Level, String)>,
+    log_messages: VecDeque<DbLogLine>,
+    #[serde(skip)]
+    unsaved_log_lines: Vec<DbLogLine>,
+    // Kept in the DB, so that the writer can apply them as tags come in.
+    #[serde(skip)]
+    tag_kinds: Vec<TagKindMapping>,
+    #[serde(skip)]
+    unsaved_tag_kinds: Vec<(String, TagKind)>,
+    // The active task failed; recorded in its health once it completes.
+    #[serde(skip)]
+    active_task_failed: bool,
 
     // Persistent state that is saved between runs
     active_task: Option<PluginRequest>,
     task_queue: VecDeque<PluginRequest>,
+    download_limits: Option<DownloadLimits>,
+    // GiB of disk the plugin's works may take, if it is limited.
+    #[serde(default)]
+    storage_quota_gib: Option<u64>,
+    #[serde(default)]
+    tag_exclusions: TagExclusions,
+    #[serde(default)]
+    fetch_policy: FetchPolicy,
+    #[serde(default)]
+    health: PluginHealth,
 
     // Maintenance state
     #[serde(skip)]
@@ -203,18 +851,12 @@ pub struct PluginHandle {
 }
 
 impl PluginHandle {
-    const MAX_MESSAGES: usize = 50;
+    const MAX_MESSAGES: usize = 1_000;
 
-    fn initialize(
-        &mut self,
-        source: &Path,
-        task: JoinHandle<()>,
-        cancellation: PluginCancellation,
-        tx_to_plugin: channel::Sender<PluginRequest>,
-    ) {
+    fn initialize(&mut self, source: &Path, remote: PluginRemote) {
         assert!(self.remote.is_none(), "reinitializing a plugin");
         self.source = source.to_owned();
-        self.remote = Some(PluginRemote::new(task, cancellation, tx_to_plugin));
+        self.remote = Some(remote);
     }
 
     fn cleanup_for_exit(mut self) -> Result<()> {
@@ -264,8 +906,9 @@ impl PluginHandle {
         }
     }
 
-    pub fn log_messages(&self) -> impl Iterator<Item = (Level, &str)> {
-        self.log_messages.iter().map(|(lvl, s)| (*lvl, s.as_str()))
+    // Newest first.
+    pub fn log_messages(&self) -> impl Iterator<Item = &DbLogLine> {
+        self.log_messages.iter()
     }
 
     pub fn progress(&self) -> &Progress {
@@ -306,10 +949,85 @@ impl PluginHandle {
             .cancellation
     }
 
+    // Some(..) if this plugin overrides the global download limits.
+    pub fn download_limits_mut(&mut self) -> &mut Option<DownloadLimits> {
+        &mut self.download_limits
+    }
+
+    pub fn storage_quota_gib_mut(&mut self) -> &mut Option<u64> {
+        &mut self.storage_quota_gib
+    }
+
+    pub fn storage_quota_bytes(&self) -> Option<u64> {
+        self.storage_quota_gib
+            .map(|gib| gib.saturating_mul(1024 * 1024 * 1024))
+    }
+
+    pub fn fetch_policy_mut(&mut self) -> &mut FetchPolicy {
+        &mut self.fetch_policy
+    }
+
+    pub fn tag_exclusions_mut(&mut self) -> &mut TagExclusions {
+        &mut self.tag_exclusions
+    }
+
+    // The kind we give to each type of tag the plugin has reported.
+    pub fn tag_kinds(&self) -> &[TagKindMapping] {
+        &self.tag_kinds
+    }
+
+    pub fn set_tag_kind(&mut self, source_type: &str, kind: TagKind) {
+        if let Some(mapping) = self
+            .tag_kinds
+            .iter_mut()
+            .find(|mapping| mapping.source_type == source_type)
+        {
+            mapping.kind = kind;
+            mapping.edited = true;
+        }
+        self.unsaved_tag_kinds.push((source_type.to_owned(), kind));
+    }
+
+    fn load_tag_kinds(&mut self, db: &DbSyncHandle) {
+        let Some(plugin_id) = self.id() else {
+            return;
+        };
+        match db.sync_list_tag_kind_mappings(plugin_id) {
+            Ok(mappings) => self.tag_kinds = mappings,
+            Err(e) => error!("Failed to load tag kinds for {}: {e}", self.name()),
+        }
+    }
+
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.remote.as_ref().map(|remote| remote.throttle.status())
+    }
+
+    pub fn throttle_traffic(&self) -> Option<ThrottleTraffic> {
+        self.remote.as_ref().map(|remote| remote.throttle.traffic())
+    }
+
+    pub fn health(&self) -> &PluginHealth {
+        &self.health
+    }
+
     pub fn refresh_tags(&mut self) {
         self.task_queue.push_back(PluginRequest::RefreshTags);
     }
 
+    pub fn reprocess_sources(&mut self) {
+        self.task_queue.push_back(PluginRequest::ReprocessSources);
+    }
+
+    // Note: the user is waiting on actions, so they go ahead of any queued refreshes; they still
+    //       have to wait for the active task to finish.
+    pub fn run_action(&mut self, action: &PluginAction, work_remote_id: Option<&str>) {
+        self.task_queue.push_front(PluginRequest::RunAction {
+            action_id: action.id().to_owned(),
+            label: action.label().to_owned(),
+            work_remote_id: work_remote_id.map(str::to_owned),
+        });
+    }
+
     pub fn apply_configuration(&self) -> Result<()> {
         // Note: we short cut the queue here, as config needs to apply immediately.
         //       This also doesn't send a return CompletedTask, so the CompletedTask
@@ -328,7 +1046,21 @@ impl PluginHandle {
         Ok(())
     }
 
-    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+    fn push_log(&mut self, id: PluginId, level: Level, message: String) {
+        let line = DbLogLine::new(
+            id,
+            level,
+            self.active_task.as_ref().map(ToString::to_string),
+            message,
+        );
+        self.unsaved_log_lines.push(line.clone());
+        self.log_messages.push_front(line);
+        while self.log_messages.len() > Self::MAX_MESSAGES {
+            self.log_messages.pop_back();
+        }
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate], db: &DbSyncHandle) {
         for update in updates {
             match update {
                 DataUpdate::PluginInfo {
@@ -339,6 +1071,16 @@ impl PluginHandle {
                     self.metadata = Some(metadata.to_owned());
                     self.record = Some(record.to_owned());
 
+                    self.load_tag_kinds(db);
+
+                    // Pick up the log where we left off last run.
+                    if self.log_messages.is_empty() {
+                        match db.sync_list_plugin_logs(record.id(), Self::MAX_MESSAGES) {
+                            Ok(lines) => self.log_messages.extend(lines),
+                            Err(e) => error!("Failed to load logs for {}: {e}", record.name()),
+                        }
+                    }
+
                     // Note: only restart our restored active task once init is finished.
                     if let Some(req) = self.active_task.as_ref() {
                         self.remote
@@ -354,10 +1096,19 @@ impl PluginHandle {
                     level,
                     message,
                 } if Some(*id) == self.id() => {
-                    self.log_messages.push_front((*level, message.to_owned()));
-                    while self.log_messages.len() > Self::MAX_MESSAGES {
-                        self.log_messages.pop_back();
-                    }
+                    self.push_log(*id, *level, message.to_owned());
+                }
+                DataUpdate::PluginWorksPurged { plugin_id, report }
+                    if Some(*plugin_id) == self.id() =>
+                {
+                    self.push_log(
+                        *plugin_id,
+                        Level::Info,
+                        format!(
+                            "Purged {} works to make room, moving {} files to the trash; {} could not be moved",
+                            report.works, report.files, report.failed
+                        ),
+                    );
                 }
                 DataUpdate::Progress {
                     source: UpdateSource::Plugin(id),
@@ -365,14 +1116,32 @@ impl PluginHandle {
                 } if Some(*id) == self.id() => {
                     self.progress = *progress;
                 }
+                DataUpdate::PluginTaskFailed { plugin, .. } if *plugin == self.name() => {
+                    self.active_task_failed = true;
+                }
                 DataUpdate::CompletedTask {
                     source: UpdateSource::Plugin(id),
                 } if Some(*id) == self.id() => {
-                    self.active_task = None;
+                    if let Some(task) = self.active_task.take() {
+                        self.health.record(&task, !self.active_task_failed);
+                    }
+                    self.active_task_failed = false;
+                }
+                // Note: a tag refresh may have turned up new types of tags.
+                DataUpdate::TagsWereRefreshed => {
+                    self.load_tag_kinds(db);
                 }
                 _ => {}
             }
         }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.active_task.is_some()
+    }
+
+    // Note: the PluginHost decides when, so that plugins take turns.
+    fn start_next_task(&mut self) {
         if self.active_task.is_none()
             && let Some(task) = self.task_queue.pop_front()
         {
@@ -386,3 +1155,24 @@ impl PluginHandle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_plugins_to_start() {
+        // Up to the limit, counting the plugins that are already running.
+        let busy = [true, false, false, false];
+        let waiting = [true, true, true, true];
+        assert_eq!(pick_plugins_to_start(&busy, &waiting, 0, 3), vec![1, 2]);
+        assert!(pick_plugins_to_start(&busy, &waiting, 0, 1).is_empty());
+
+        // In turn from where we left off, skipping plugins with nothing to do.
+        let busy = [false, false, false, false];
+        let waiting = [true, false, true, true];
+        assert_eq!(pick_plugins_to_start(&busy, &waiting, 3, 2), vec![3, 0]);
+        assert_eq!(pick_plugins_to_start(&busy, &waiting, 1, 2), vec![2, 3]);
+        assert!(pick_plugins_to_start(&[], &[], 0, 2).is_empty());
+    }
+}
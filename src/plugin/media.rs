@@ -0,0 +1,288 @@
+use crate::shared::{plugin::PluginCancellation, progress::LogSender, throttle::CallingThrottle};
+use anyhow::Result;
+use artchiver_sdk::{MEDIA_URL_PREFIX, MediaEntry, MediaKind, MediaResponse, TextFetchError};
+use jiff::Timestamp;
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+// Video sites change their pages faster than anyone can keep up with, so we leave finding the
+// videos on a page, and the streams for a video, to yt-dlp, which has to be on the PATH.
+const YT_DLP: &str = "yt-dlp";
+
+// Prefer something that every player can play, without needing ffmpeg to merge or convert it,
+// if we can.
+const VIDEO_FORMAT: &str = "b[ext=mp4]/bv*[ext=mp4]+ba[ext=m4a]/b";
+const AUDIO_FORMAT: &str = "ba[ext=mp3]/b[ext=mp3]/ba/b";
+
+// List the videos at a page, channel, or playlist url. Like fetch_text, this goes through the
+// plugin's throttle and the results are cached for the plugin's cache timeout.
+pub fn resolve_media(
+    url: &str,
+    (cache_dir, cache_timeout): (&Path, Duration),
+    (throttle, cancellation): (&CallingThrottle, &PluginCancellation),
+    log: &mut LogSender,
+) -> MediaResponse {
+    let key = Sha256::digest(format!("{MEDIA_URL_PREFIX}{url}"));
+    let key_path = cache_dir.join(format!("{key:x}"));
+    let is_fresh = fs::metadata(&key_path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|mtime| mtime.elapsed().ok())
+        .is_some_and(|staleness| staleness < cache_timeout);
+    let raw = if is_fresh {
+        fs::read_to_string(&key_path)?
+    } else {
+        throttle
+            .throttle(cancellation)
+            .map_err(|_e| TextFetchError::Cancellation)?;
+        log.trace(format!("resolve_media({url})"));
+        // Note: with --ignore-errors we still get the rest of a playlist when a video in it is
+        //       private or removed.
+        let raw = run(
+            Command::new(YT_DLP).args(["-J", "--ignore-errors", "--no-warnings", "--", url]),
+            cancellation,
+        )?;
+        fs::write(&key_path, &raw)?;
+        raw
+    };
+    parse_media(&raw).map_err(|e| TextFetchError::HostError(format!("{url}: {e}")))
+}
+
+// Download the video or audio on a page to a file next to `tmp_path`, returning where it ended up.
+pub fn download_media(
+    page_url: &str,
+    kind: MediaKind,
+    tmp_path: &Path,
+    cancellation: &PluginCancellation,
+) -> Result<PathBuf, TextFetchError> {
+    let template = format!("{}.%(ext)s", tmp_path.display());
+    let out = run(
+        Command::new(YT_DLP).args([
+            "-f",
+            match kind {
+                MediaKind::Video => VIDEO_FORMAT,
+                MediaKind::Audio => AUDIO_FORMAT,
+            },
+            "--remux-video",
+            "mp4",
+            "--no-playlist",
+            "--no-part",
+            "--no-warnings",
+            "--no-simulate",
+            "--print",
+            "after_move:filepath",
+            "-o",
+            &template,
+            "--",
+            page_url,
+        ]),
+        cancellation,
+    )?;
+    match out.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(path) => Ok(PathBuf::from(path.trim())),
+        None => Err(TextFetchError::HostError(format!(
+            "{YT_DLP} did not say where it saved {page_url}"
+        ))),
+    }
+}
+
+// Run yt-dlp to completion, or until cancelled, and return what it printed.
+fn run(command: &mut Command, cancellation: &PluginCancellation) -> Result<String, TextFetchError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                TextFetchError::HostError(format!("{YT_DLP} is not installed, or not on the PATH"))
+            }
+            _ => e.into(),
+        })?;
+    // Note: read both pipes as we go, so that a full pipe cannot stall yt-dlp.
+    let mut stdout = child.stdout.take().expect("piped");
+    let mut stderr = child.stderr.take().expect("piped");
+    let stdout = thread::spawn(move || {
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).map(|_| out)
+    });
+    let stderr = thread::spawn(move || {
+        let mut out = String::new();
+        stderr.read_to_string(&mut out).map(|_| out)
+    });
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancellation.is_cancelled() {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(TextFetchError::Cancellation);
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    let stdout = stdout.join().expect("reader panicked")?;
+    let stderr = stderr.join().expect("reader panicked")?;
+    // Note: yt-dlp exits with an error when it skipped anything, even if it found the rest.
+    if !status.success() && stdout.trim().is_empty() {
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("no output");
+        return Err(TextFetchError::HostError(format!(
+            "{YT_DLP} failed: {reason}"
+        )));
+    }
+    Ok(stdout)
+}
+
+// yt-dlp describes a video as one object, and a channel or playlist as an object with a list of
+// entries, which may themselves be playlists, e.g. the tabs of a channel.
+pub fn parse_media(raw: &str) -> Result<Vec<MediaEntry>> {
+    let doc = serde_json::from_str::<Value>(raw)?;
+    let mut out = Vec::new();
+    collect_entries(&doc, None, &mut out);
+    Ok(out)
+}
+
+fn collect_entries(doc: &Value, playlist: Option<&str>, out: &mut Vec<MediaEntry>) {
+    if let Some(entries) = doc.get("entries").and_then(Value::as_array) {
+        let playlist = string(doc, "title").or(playlist);
+        for entry in entries.iter().filter(|entry| entry.is_object()) {
+            collect_entries(entry, playlist, out);
+        }
+    } else if let Some(entry) = media_entry(doc, playlist) {
+        out.push(entry);
+    }
+}
+
+fn string<'a>(doc: &'a Value, key: &str) -> Option<&'a str> {
+    doc.get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+fn strings(doc: &Value, key: &str) -> Vec<String> {
+    doc.get(key)
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn media_entry(doc: &Value, playlist: Option<&str>) -> Option<MediaEntry> {
+    let id = string(doc, "id")?;
+    let page_url = string(doc, "webpage_url")
+        .or_else(|| string(doc, "original_url"))
+        .or_else(|| string(doc, "url"))?;
+    // Note: the list of thumbnails is sorted worst to best.
+    let thumbnail_url = string(doc, "thumbnail").or_else(|| {
+        doc.get("thumbnails")
+            .and_then(Value::as_array)
+            .and_then(|thumbs| thumbs.iter().rev().find_map(|thumb| string(thumb, "url")))
+    });
+    let upload_date = string(doc, "upload_date").map(str::to_owned).or_else(|| {
+        let timestamp = Timestamp::from_second(doc.get("timestamp")?.as_i64()?).ok()?;
+        Some(timestamp.strftime("%Y%m%d").to_string())
+    });
+    Some(MediaEntry {
+        id: id.to_owned(),
+        title: string(doc, "title").unwrap_or(id).to_owned(),
+        page_url: page_url.to_owned(),
+        thumbnail_url: thumbnail_url.map(str::to_owned),
+        uploader: string(doc, "uploader")
+            .or_else(|| string(doc, "channel"))
+            .map(str::to_owned),
+        upload_date,
+        duration_secs: doc.get("duration").and_then(Value::as_f64),
+        description: string(doc, "description").map(str::to_owned),
+        tags: strings(doc, "tags"),
+        categories: strings(doc, "categories"),
+        playlist: playlist.map(str::to_owned),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_channel() -> Result<()> {
+        let raw = r#"{
+            "_type": "playlist",
+            "id": "UC123",
+            "title": "Studio Talks - Videos",
+            "entries": [
+                {
+                    "id": "abc",
+                    "title": "On Colour",
+                    "webpage_url": "https://www.youtube.com/watch?v=abc",
+                    "thumbnails": [
+                        {"url": "https://i.ytimg.com/vi/abc/default.jpg"},
+                        {"url": "https://i.ytimg.com/vi/abc/maxresdefault.jpg"}
+                    ],
+                    "uploader": "Studio Talks",
+                    "upload_date": "20240102",
+                    "duration": 3600.5,
+                    "tags": ["painting", "colour"]
+                },
+                null,
+                {
+                    "_type": "playlist",
+                    "title": "Studio Talks - Shorts",
+                    "entries": [
+                        {
+                            "id": "def",
+                            "url": "https://www.youtube.com/shorts/def",
+                            "timestamp": 1700000000
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let entries = parse_media(raw)?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "On Colour");
+        assert_eq!(
+            entries[0].thumbnail_url.as_deref(),
+            Some("https://i.ytimg.com/vi/abc/maxresdefault.jpg")
+        );
+        assert_eq!(
+            entries[0].playlist.as_deref(),
+            Some("Studio Talks - Videos")
+        );
+        assert_eq!(entries[0].tags, vec!["painting", "colour"]);
+        assert_eq!(entries[1].title, "def");
+        assert_eq!(entries[1].page_url, "https://www.youtube.com/shorts/def");
+        assert_eq!(entries[1].upload_date.as_deref(), Some("20231114"));
+        assert_eq!(
+            entries[1].playlist.as_deref(),
+            Some("Studio Talks - Shorts")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_single_video() -> Result<()> {
+        let raw =
+            r#"{"id": "42", "title": "Opening", "webpage_url": "https://peertube.example/w/42"}"#;
+        let entries = parse_media(raw)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].playlist, None);
+        Ok(())
+    }
+}
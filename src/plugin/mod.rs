@@ -1,4 +1,5 @@
 pub mod client;
 pub mod download;
 pub mod host;
+pub mod media;
 pub mod thumbnail;
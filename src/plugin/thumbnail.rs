@@ -1,6 +1,17 @@
-use crate::shared::progress::LogSender;
+use crate::{
+    db::models::work::MediaType,
+    shared::{
+        nsfw,
+        progress::LogSender,
+        storage::{Storage, join_stored_path, relative_path_for_url, split_stored_path},
+        vector::{self, is_vector},
+    },
+};
 use anyhow::Result;
-use std::path::Path;
+use image::{
+    DynamicImage, ImageError, ImageFormat, ImageReader, ImageResult, error::DecodingError,
+};
+use std::{fs, io::Cursor, path::Path};
 
 pub fn is_image(path: &Path) -> bool {
     let Some(ext) = path.extension() else {
@@ -11,6 +22,68 @@ pub fn is_image(path: &Path) -> bool {
         "tga", "tiff", "tif", "webp",
     ];
     IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
+        || is_raw(path)
+        || is_vector(path)
+}
+
+// Camera RAW files, which we show by way of the preview the camera embeds in them.
+pub fn is_raw(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    const RAW_EXTENSIONS: &[&str] = &[
+        "3fr", "arw", "cr2", "cr3", "dng", "erf", "kdc", "mrw", "nef", "nrw", "orf", "pef", "raf",
+        "rw2", "sr2", "srf", "srw",
+    ];
+    RAW_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
+}
+
+// The largest JPEG embedded in a RAW file. Nearly every camera writes a full or half size preview
+// next to the sensor data; showing that is far cheaper than developing the sensor data ourselves,
+// and matches what the photographer saw on the back of the camera.
+fn raw_preview(bytes: &[u8]) -> Option<DynamicImage> {
+    // Note: compressed sensor data can contain the marker by chance; don't chase too many.
+    const MAX_CANDIDATES: usize = 64;
+    let mut candidates = bytes
+        .windows(3)
+        .enumerate()
+        .filter(|(_, window)| *window == [0xFF, 0xD8, 0xFF])
+        .take(MAX_CANDIDATES)
+        .filter_map(|(offset, _)| {
+            let reader = ImageReader::with_format(Cursor::new(&bytes[offset..]), ImageFormat::Jpeg);
+            let (width, height) = reader.into_dimensions().ok()?;
+            Some((u64::from(width) * u64::from(height), offset))
+        })
+        .collect::<Vec<_>>();
+    // Note: some formats keep the sensor data itself as a lossless JPEG, which is the largest of
+    //       all but won't decode as a picture, so fall back through the smaller ones.
+    candidates.sort_unstable_by(|a, b| b.cmp(a));
+    candidates.into_iter().find_map(|(_, offset)| {
+        image::load_from_memory_with_format(&bytes[offset..], ImageFormat::Jpeg).ok()
+    })
+}
+
+// The long edge we draw SVG works at when they are opened as an image, e.g. for thumbnails and
+// exports; the slideshow draws them itself, at the size they are shown.
+const VECTOR_EDGE: u32 = 4096;
+
+// Open a work's image file, including the RAW and SVG files that `image` can't read on its own.
+pub fn open_image(path: &Path) -> ImageResult<DynamicImage> {
+    if is_vector(path) {
+        return vector::open(path)
+            .and_then(|tree| vector::rasterize(&tree, VECTOR_EDGE, VECTOR_EDGE))
+            .map(DynamicImage::ImageRgba8)
+            .map_err(|e| ImageError::Decoding(DecodingError::new(path.into(), e.to_string())));
+    }
+    if !is_raw(path) {
+        return image::open(path);
+    }
+    raw_preview(&fs::read(path)?).ok_or_else(|| {
+        ImageError::Decoding(DecodingError::new(
+            path.into(),
+            "no preview image embedded in the RAW file",
+        ))
+    })
 }
 
 pub fn is_audio(path: &Path) -> bool {
@@ -21,6 +94,16 @@ pub fn is_audio(path: &Path) -> bool {
     AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
 }
 
+pub fn is_video(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    const VIDEO_EXTENSIONS: &[&str] = &[
+        "avi", "flv", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "ogv", "webm", "wmv",
+    ];
+    VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
+}
+
 pub fn is_archive(path: &Path) -> bool {
     let Some(ext) = path.extension() else {
         return false;
@@ -40,11 +123,35 @@ pub fn is_pdf(path: &Path) -> bool {
     PDF_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
 }
 
+pub fn is_document(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    const DOCUMENT_EXTENSIONS: &[&str] = &["cbr", "cbz", "djvu", "epub", "pdf"];
+    DOCUMENT_EXTENSIONS.contains(&ext.to_ascii_lowercase().to_str().unwrap_or_default())
+}
+
+// What kind of media a downloaded file is. Stored files keep the extension of the url they came
+// from, and files already on disk have no response headers to look at, so we go by that.
+pub fn media_type_of(path: &Path) -> Option<MediaType> {
+    if is_image(path) {
+        Some(MediaType::Image)
+    } else if is_video(path) {
+        Some(MediaType::Video)
+    } else if is_audio(path) {
+        Some(MediaType::Audio)
+    } else if is_document(path) {
+        Some(MediaType::Document)
+    } else {
+        None
+    }
+}
+
 // If the plugin gives us back a preview path that is not an image -- e.g. a downsampled full video,
 // or an audio podcast sample -- try to get a preview image somehow. The input here is the url and
-// the storage path components. The output needs to be a new path prefix relative to the data dir.
+// the stored path. The output needs to be a new stored path, as understood by Storage::resolve.
 // Typically, this will be obtained by adding something to the preview_url and calling back into
-// get_data_path_for_url to hash the new URL. The DbWork will store the new path we return so that
+// Storage::place_for_url to hash the new URL. The DbWork will store the new path we return so that
 // subsequent usage will see the file we generated instead of the plugin's preview file, but that
 // file can still be found via the preview URL stored in the DbWork.
 //
@@ -53,25 +160,25 @@ pub fn is_pdf(path: &Path) -> bool {
 pub fn make_preview_thumbnail(
     preview_url: &str,
     rel_path: &str,
-    data_dir: &Path,
+    storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     log.trace(format!("make_preview_thumbnail({rel_path})"));
-    let abs_path = data_dir.join(rel_path);
+    let abs_path = storage.resolve(Path::new(rel_path));
     if is_image(&abs_path) {
         return Ok(rel_path.to_owned());
     }
 
     if is_image(&abs_path) {
-        make_image_preview_image(preview_url, &abs_path, rel_path, data_dir, log)
+        make_image_preview_image(preview_url, &abs_path, rel_path, storage, log)
     } else if is_audio(&abs_path) {
-        make_audio_preview_image(preview_url, &abs_path, rel_path, data_dir, log)
+        make_audio_preview_image(preview_url, &abs_path, rel_path, storage, log)
     } else if is_archive(&abs_path) {
-        make_archive_preview_image(preview_url, &abs_path, rel_path, data_dir, log)
+        make_archive_preview_image(preview_url, &abs_path, rel_path, storage, log)
     } else if is_pdf(&abs_path) {
-        make_pdf_preview_image(preview_url, &abs_path, rel_path, data_dir, log)
+        make_pdf_preview_image(preview_url, &abs_path, rel_path, storage, log)
     } else {
-        make_video_preview_image(preview_url, &abs_path, rel_path, data_dir, log)
+        make_video_preview_image(preview_url, &abs_path, rel_path, storage, log)
     }
 }
 
@@ -80,7 +187,7 @@ fn make_image_preview_image(
     _preview_url: &str,
     _abs_path: &Path,
     rel_path: &str,
-    _data_dir: &Path,
+    _storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     log.error("TODO: make a preview image for a full size image");
@@ -92,7 +199,7 @@ fn make_audio_preview_image(
     _preview_url: &str,
     _abs_path: &Path,
     rel_path: &str,
-    _data_dir: &Path,
+    _storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     // We need to do the equivalent of:
@@ -106,7 +213,7 @@ fn make_archive_preview_image(
     _preview_url: &str,
     _abs_path: &Path,
     rel_path: &str,
-    _data_dir: &Path,
+    _storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     // Look for the first image file
@@ -119,7 +226,7 @@ fn make_pdf_preview_image(
     _preview_url: &str,
     _abs_path: &Path,
     rel_path: &str,
-    _data_dir: &Path,
+    _storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     // Print the first page to an image
@@ -132,10 +239,146 @@ fn make_video_preview_image(
     _preview_url: &str,
     _abs_path: &Path,
     rel_path: &str,
-    _data_dir: &Path,
+    _storage: &Storage,
     log: &mut LogSender,
 ) -> Result<String> {
     // Get a random frame from a few seconds into the video, or the first frame if the video is too short.
     log.error("TODO: make a preview image for a video file");
     Ok(rel_path.to_owned())
 }
+
+// The longest edge of the gallery thumbnails; big enough for the largest gallery cells on a
+// high-dpi screen.
+pub const GALLERY_THUMB_SIZE: u32 = 512;
+
+// Where the gallery thumbnail for a work goes, keyed on its screen url like the downloads are.
+// Thumbnails are derived data, so they live on the default root, under the data dir, unless the
+// screen image is on an encrypted root; see `gallery_thumb_stored_path`.
+pub fn gallery_thumb_path(screen_url: &str) -> String {
+    let rel = relative_path_for_url(screen_url);
+    let stem = rel.rsplit_once('.').map_or(rel.as_str(), |(stem, _)| stem);
+    format!("thumbs/{stem}.webp")
+}
+
+// Note: a thumbnail gives away as much as the image, so one of an encrypted work is sealed on
+//       the work's own root, rather than left in the data dir in the clear.
+pub fn gallery_thumb_stored_path(screen_url: &str, screen_path: &str, storage: &Storage) -> String {
+    let root = split_stored_path(screen_path)
+        .0
+        .filter(|_| storage.is_encrypted(screen_path));
+    join_stored_path(root, &gallery_thumb_path(screen_url))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GalleryThumb {
+    // The stored path of the copy.
+    pub path: String,
+    // Width over height of the image, for laying out the gallery.
+    pub aspect_ratio: f32,
+    // What the user's NSFW classifier made of the thumbnail, if they have one.
+    pub nsfw_score: Option<f32>,
+}
+
+// Decoding a 50MP TIFF for every gallery cell is slow, so we decode each screen image once, when
+// it is downloaded, and keep a small copy for the gallery.
+pub fn make_gallery_thumbnail(
+    screen_url: &str,
+    screen_path: &str,
+    storage: &Storage,
+) -> Result<GalleryThumb> {
+    let stored = gallery_thumb_stored_path(screen_url, screen_path, storage);
+    if storage.exists(&stored)? {
+        let thumb_path = storage.ensure_local(Path::new(&stored))?;
+        // Note: the thumbnail keeps the shape of the screen image, so only the header is needed,
+        //       unless there is a classifier to show it to.
+        let (width, height) = image::image_dimensions(&thumb_path)?;
+        let nsfw_score = if nsfw::is_enabled() {
+            nsfw::score(&image::open(&thumb_path)?)
+        } else {
+            None
+        };
+        return Ok(GalleryThumb {
+            path: stored,
+            aspect_ratio: aspect_ratio(width, height),
+            nsfw_score,
+        });
+    }
+    let source = storage.ensure_local(Path::new(screen_path))?;
+    let image = open_image(&source)?;
+    let aspect = aspect_ratio(image.width(), image.height());
+    let thumb = image.thumbnail(GALLERY_THUMB_SIZE, GALLERY_THUMB_SIZE);
+    let nsfw_score = nsfw::score(&thumb);
+    let thumb_path = storage.resolve(Path::new(&stored));
+    if let Some(parent) = thumb_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Note: write then rename, so that the gallery never sees a partial file.
+    let tmp_path = thumb_path.with_extension("webp.tmp");
+    thumb
+        .to_rgba8()
+        .save_with_format(&tmp_path, ImageFormat::WebP)?;
+    fs::rename(&tmp_path, &thumb_path)?;
+    storage.commit(&stored)?;
+    Ok(GalleryThumb {
+        path: stored,
+        aspect_ratio: aspect,
+        nsfw_score,
+    })
+}
+
+fn aspect_ratio(width: u32, height: u32) -> f32 {
+    width.max(1) as f32 / height.max(1) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::{gallery_thumb_path, media_type_of, raw_preview};
+    use crate::db::models::work::MediaType;
+    use image::{DynamicImage, ImageFormat};
+    use std::{io::Cursor, path::Path};
+
+    #[test]
+    fn test_media_type_of() {
+        let kind = |path: &str| media_type_of(Path::new(path));
+        assert_eq!(kind("ab/cd/work.JPG"), Some(MediaType::Image));
+        assert_eq!(kind("ab/cd/work.webm"), Some(MediaType::Video));
+        assert_eq!(kind("ab/cd/episode.mp3"), Some(MediaType::Audio));
+        assert_eq!(kind("ab/cd/catalog.pdf"), Some(MediaType::Document));
+        assert_eq!(kind("ab/cd/bundle.zip"), None);
+        assert_eq!(kind("ab/cd/noextension"), None);
+        assert_eq!(kind("ab/cd/DSC_0001.NEF"), Some(MediaType::Image));
+        assert_eq!(kind("ab/cd/arms.svg"), Some(MediaType::Image));
+    }
+
+    #[test]
+    fn test_raw_preview() {
+        let jpeg = |width, height| {
+            let mut bytes = Vec::new();
+            DynamicImage::new_rgb8(width, height)
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+                .expect("encode");
+            bytes
+        };
+        // A thumbnail and a larger preview, between runs of stand-in sensor data.
+        let mut raw = b"II*\0".to_vec();
+        raw.extend(jpeg(16, 12));
+        raw.extend([0xFF, 0xD8, 0xFF, 0x00, 0x42]);
+        raw.extend(jpeg(64, 48));
+        raw.extend([0u8; 32]);
+        let preview = raw_preview(&raw).expect("preview");
+        assert_eq!((preview.width(), preview.height()), (64, 48));
+        assert!(raw_preview(b"no pictures in here").is_none());
+    }
+
+    #[test]
+    fn test_gallery_thumb_path() {
+        let path = gallery_thumb_path("https://example.com/works/1234.tif");
+        assert!(path.starts_with("thumbs/"));
+        assert!(path.ends_with(".webp"));
+        assert_eq!(path.split('/').count(), 4);
+        assert_eq!(
+            path,
+            gallery_thumb_path("https://example.com/works/1234.tif")
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use crate::shared::{plugin::PluginCancellation, throttle::ThrottleError};
+use jiff::Zoned;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::Arc,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+// User preferences for how hard we are allowed to hit the network when downloading works.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadLimits {
+    // KiB per second; zero for unlimited.
+    max_kib_per_sec: u32,
+
+    // Only start downloads between these local hours. The window may wrap past midnight.
+    window_enabled: bool,
+    window_start_hour: u8,
+    window_end_hour: u8,
+}
+
+impl DownloadLimits {
+    fn bytes_per_sec(&self) -> u64 {
+        u64::from(self.max_kib_per_sec) * 1024
+    }
+
+    pub fn is_in_window(&self, hour: u8) -> bool {
+        if !self.window_enabled || self.window_start_hour == self.window_end_hour {
+            return true;
+        }
+        if self.window_start_hour < self.window_end_hour {
+            (self.window_start_hour..self.window_end_hour).contains(&hour)
+        } else {
+            hour >= self.window_start_hour || hour < self.window_end_hour
+        }
+    }
+
+    // Returns true if anything changed.
+    pub fn ui(&mut self, salt: &str, ui: &mut egui::Ui) -> bool {
+        let prior = self.clone();
+        egui::Grid::new(format!("download_limits_{salt}"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Bandwidth Cap");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.max_kib_per_sec)
+                            .speed(16)
+                            .suffix(" KiB/s"),
+                    );
+                    if self.max_kib_per_sec == 0 {
+                        ui.label("(unlimited)");
+                    }
+                });
+                ui.end_row();
+
+                ui.checkbox(&mut self.window_enabled, "Only Between");
+                ui.add_enabled_ui(self.window_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.window_start_hour)
+                                .range(0..=23)
+                                .suffix(":00"),
+                        );
+                        ui.label("and");
+                        ui.add(
+                            egui::DragValue::new(&mut self.window_end_hour)
+                                .range(0..=23)
+                                .suffix(":00"),
+                        );
+                    });
+                });
+                ui.end_row();
+            });
+        *self != prior
+    }
+}
+
+// Debt-based token bucket: callers take what they need and then sleep off any debt, so
+// that concurrent downloads sharing a bucket add up to the cap.
+#[derive(Debug)]
+struct TokenBucket {
+    bytes_per_sec: u64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            available: bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    // Returns how long the caller must wait before using the bytes it took.
+    fn take(&mut self, bytes: usize) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+        let now = Instant::now();
+        self.available =
+            (self.available + now.duration_since(self.last_refill).as_secs_f64() * rate).min(rate);
+        self.last_refill = now;
+        self.available -= bytes as f64;
+        if self.available < 0. {
+            Duration::from_secs_f64(-self.available / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct GovernorState {
+    global: DownloadLimits,
+    overrides: HashMap<String, DownloadLimits>,
+    global_bucket: Option<TokenBucket>,
+    plugin_buckets: HashMap<String, TokenBucket>,
+
+    // Disk space each plugin may fill, and how much it has, in bytes, by plugin name.
+    quotas: HashMap<String, u64>,
+    usage: HashMap<String, u64>,
+}
+
+impl GovernorState {
+    fn limits_for(&self, plugin: &str) -> &DownloadLimits {
+        self.overrides.get(plugin).unwrap_or(&self.global)
+    }
+
+    fn bucket_for(&mut self, plugin: &str) -> Option<&mut TokenBucket> {
+        if self.overrides.contains_key(plugin) {
+            self.plugin_buckets.get_mut(plugin)
+        } else {
+            self.global_bucket.as_mut()
+        }
+    }
+}
+
+// Shared by the PluginHost, which configures it from preferences, and all download threads,
+// which ask it for permission to proceed.
+#[derive(Clone, Debug, Default)]
+pub struct DownloadGovernor {
+    state: Arc<Mutex<GovernorState>>,
+}
+
+impl DownloadGovernor {
+    pub fn configure<'a>(
+        &self,
+        global: &DownloadLimits,
+        overrides: impl Iterator<Item = (String, &'a DownloadLimits)>,
+    ) {
+        let mut state = self.state.lock();
+        state.global = global.clone();
+        state.global_bucket =
+            (global.max_kib_per_sec > 0).then(|| TokenBucket::new(global.bytes_per_sec()));
+        state.overrides = overrides.map(|(k, v)| (k, v.clone())).collect();
+        let buckets = state
+            .overrides
+            .iter()
+            .filter(|(_, limits)| limits.max_kib_per_sec > 0)
+            .map(|(name, limits)| (name.clone(), TokenBucket::new(limits.bytes_per_sec())))
+            .collect();
+        state.plugin_buckets = buckets;
+    }
+
+    pub fn configure_quotas(&self, quotas: impl Iterator<Item = (String, u64)>) {
+        self.state.lock().quotas = quotas.collect();
+    }
+
+    // Downloads add to this as they go; the host resets it from the DB now and then, to pick up
+    // anything that was removed.
+    pub fn set_usage(&self, plugin: &str, bytes: u64) {
+        self.state.lock().usage.insert(plugin.to_owned(), bytes);
+    }
+
+    pub fn usage(&self, plugin: &str) -> Option<u64> {
+        self.state.lock().usage.get(plugin).copied()
+    }
+
+    pub fn is_over_quota(&self, plugin: &str) -> bool {
+        self.state.lock().is_over_quota(plugin)
+    }
+
+    pub fn for_plugin(&self, plugin: &str) -> PluginBandwidth {
+        PluginBandwidth {
+            governor: self.clone(),
+            plugin: plugin.to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PluginBandwidth {
+    governor: DownloadGovernor,
+    plugin: String,
+}
+
+impl PluginBandwidth {
+    pub fn is_in_window(&self) -> bool {
+        let hour = Zoned::now().hour().try_into().unwrap_or_default();
+        self.governor
+            .state
+            .lock()
+            .limits_for(&self.plugin)
+            .is_in_window(hour)
+    }
+
+    // Block until downloads are allowed by the schedule.
+    pub fn wait_for_window(&self, cancellation: &PluginCancellation) -> Result<(), ThrottleError> {
+        while !self.is_in_window() {
+            if cancellation.is_cancelled() {
+                return Err(ThrottleError::Cancelled);
+            }
+            sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+
+    pub fn is_over_quota(&self) -> bool {
+        self.governor.is_over_quota(&self.plugin)
+    }
+
+    // Block until the plugin is allowed more disk, either because the user raised its quota or
+    // because they cleared some of its works out.
+    pub fn wait_for_quota(&self, cancellation: &PluginCancellation) -> Result<(), ThrottleError> {
+        while self.is_over_quota() {
+            if cancellation.is_cancelled() {
+                return Err(ThrottleError::Cancelled);
+            }
+            sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+
+    pub fn consume(
+        &self,
+        bytes: usize,
+        cancellation: &PluginCancellation,
+    ) -> Result<(), ThrottleError> {
+        let mut wait = {
+            let mut state = self.governor.state.lock();
+            *state.usage.entry(self.plugin.clone()).or_default() += bytes as u64;
+            match state.bucket_for(&self.plugin) {
+                Some(bucket) => bucket.take(bytes),
+                None => return Ok(()),
+            }
+        };
+        while wait > Duration::ZERO {
+            if cancellation.is_cancelled() {
+                return Err(ThrottleError::Cancelled);
+            }
+            let step = wait.min(Duration::from_millis(100));
+            sleep(step);
+            wait -= step;
+        }
+        Ok(())
+    }
+
+    pub fn writer<'a, W: Write>(
+        &'a self,
+        inner: W,
+        cancellation: &'a PluginCancellation,
+    ) -> GovernedWriter<'a, W> {
+        GovernedWriter {
+            inner,
+            bandwidth: self,
+            cancellation,
+        }
+    }
+}
+
+// Slows writes down to whatever the governor allows.
+pub struct GovernedWriter<'a, W: Write> {
+    inner: W,
+    bandwidth: &'a PluginBandwidth,
+    cancellation: &'a PluginCancellation,
+}
+
+impl<W: Write> Write for GovernedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bandwidth
+            .consume(n, self.cancellation)
+            .map_err(io::Error::other)?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_in_window() {
+        let mut limits = DownloadLimits::default();
+        assert!(limits.is_in_window(12));
+
+        limits.window_enabled = true;
+        limits.window_start_hour = 1;
+        limits.window_end_hour = 7;
+        assert!(limits.is_in_window(1));
+        assert!(limits.is_in_window(6));
+        assert!(!limits.is_in_window(7));
+        assert!(!limits.is_in_window(0));
+
+        limits.window_start_hour = 22;
+        limits.window_end_hour = 6;
+        assert!(limits.is_in_window(23));
+        assert!(limits.is_in_window(0));
+        assert!(limits.is_in_window(5));
+        assert!(!limits.is_in_window(12));
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(1000);
+        assert_eq!(bucket.take(500), Duration::ZERO);
+        assert_eq!(bucket.take(500), Duration::ZERO);
+        let wait = bucket.take(500);
+        assert!(wait > Duration::from_millis(400), "{wait:?}");
+        assert!(wait <= Duration::from_millis(500), "{wait:?}");
+    }
+
+    #[test]
+    fn test_storage_quota() -> Result<(), ThrottleError> {
+        let governor = DownloadGovernor::default();
+        let bandwidth = governor.for_plugin("Met");
+        let cancellation = PluginCancellation::default();
+        assert!(!bandwidth.is_over_quota());
+
+        governor.configure_quotas([("Met".to_owned(), 1000)].into_iter());
+        governor.set_usage("Met", 600);
+        assert!(!bandwidth.is_over_quota());
+        bandwidth.consume(400, &cancellation)?;
+        assert!(bandwidth.is_over_quota());
+        assert_eq!(governor.usage("Met"), Some(1000));
+        assert!(!governor.is_over_quota("NGA"));
+
+        governor.set_usage("Met", 0);
+        assert!(!bandwidth.is_over_quota());
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+// Being a good citizen on the web: plugins that scrape web pages, rather than calling an API,
+// have their fetches checked against each site's robots.txt, and every plugin's fetches to a
+// site are spaced out by at least a minimum delay, whatever rate limit the plugin asks for.
+//
+// Every decision is written to the plugin's log, so that the user can see why a fetch was
+// refused, or what we waited for.
+use crate::shared::{plugin::PluginCancellation, progress::LogSender};
+use artchiver_sdk::TextFetchError;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use ureq::{Agent, http::Uri};
+
+// The product token we look for in robots.txt, as in our User-Agent.
+const ROBOTS_AGENT: &str = "artchiver";
+// How long to trust a robots.txt before reading it again.
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// How long to wait before asking again for a robots.txt that we could not get.
+const ROBOTS_RETRY: Duration = Duration::from_secs(10 * 60);
+// Sites may not make us read more than this; RFC 9309 asks crawlers to read at least 500 KiB.
+const ROBOTS_MAX_BYTES: u64 = 512 * 1024;
+// A site asking for more than this between fetches has effectively asked us to stay away, but
+// we leave that to its Disallow rules.
+const MAX_CRAWL_DELAY_SECS: f64 = 60. * 60.;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RobotsMode {
+    Ignore,
+    Warn,
+    #[default]
+    Obey,
+}
+
+// User preferences for how politely plugins treat the sites they fetch from.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompliancePolicy {
+    robots: RobotsMode,
+    // The least time between the starts of two fetches to the same site, across all plugins.
+    min_site_delay_ms: u32,
+}
+
+impl Default for CompliancePolicy {
+    fn default() -> Self {
+        Self {
+            robots: RobotsMode::default(),
+            min_site_delay_ms: 250,
+        }
+    }
+}
+
+impl CompliancePolicy {
+    fn min_site_delay(&self) -> Duration {
+        Duration::from_millis(self.min_site_delay_ms.into())
+    }
+
+    // Returns true if anything changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let prior = self.clone();
+        egui::Grid::new("compliance_policy")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("robots.txt")
+                    .on_hover_text("Applies to plugins that scrape web pages, rather than an API.");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.robots, RobotsMode::Obey, "obey");
+                    ui.selectable_value(&mut self.robots, RobotsMode::Warn, "warn only");
+                    ui.selectable_value(&mut self.robots, RobotsMode::Ignore, "ignore");
+                });
+                ui.end_row();
+
+                ui.label("Per-Site Delay").on_hover_text(
+                    "The least time between fetches to any one site, for all plugins. A \
+                     Crawl-delay in the site's robots.txt is honored if it asks for more.",
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.min_site_delay_ms)
+                        .range(0..=60_000)
+                        .speed(10)
+                        .suffix(" ms"),
+                );
+                ui.end_row();
+            });
+        *self != prior
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsRule {
+    // Patterns match from the start of the path, with `*` for any run of characters and a
+    // trailing `$` to match the end of the path.
+    fn matches(&self, path: &str) -> bool {
+        let (pattern, anchored) = match self.pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (self.pattern.as_str(), false),
+        };
+        let parts = pattern.split('*').collect::<Vec<_>>();
+        let Some(mut rest) = path.strip_prefix(parts[0]) else {
+            return false;
+        };
+        let Some((last, middle)) = parts[1..].split_last() else {
+            return !anchored || rest.is_empty();
+        };
+        for part in middle {
+            match rest.find(part) {
+                Some(at) => rest = &rest[at + part.len()..],
+                None => return false,
+            }
+        }
+        if anchored {
+            rest.ends_with(last)
+        } else {
+            rest.contains(last)
+        }
+    }
+}
+
+// The parts of a site's robots.txt that apply to us.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![RobotsRule {
+                allow: false,
+                pattern: "/".to_owned(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    // Use the groups for `agent` if there are any, else the groups for everyone.
+    pub fn parse(text: &str, agent: &str) -> Self {
+        let mut groups = Vec::<(Vec<String>, Self)>::new();
+        let mut in_agents = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            if key == "user-agent" {
+                if !in_agents {
+                    groups.push(Default::default());
+                }
+                in_agents = true;
+                if let Some((agents, _)) = groups.last_mut() {
+                    agents.push(value.to_ascii_lowercase());
+                }
+                continue;
+            }
+            // Note: records before the first user-agent line belong to no group.
+            let Some((_, group)) = groups.last_mut() else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => {
+                    in_agents = false;
+                    group.rules.push(RobotsRule {
+                        allow: key == "allow",
+                        pattern: value.to_owned(),
+                    });
+                }
+                "allow" | "disallow" => in_agents = false,
+                "crawl-delay" => {
+                    in_agents = false;
+                    group.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs >= 0.)
+                        .map(|secs| Duration::from_secs_f64(secs.min(MAX_CRAWL_DELAY_SECS)));
+                }
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_ascii_lowercase();
+        let for_us = groups
+            .iter()
+            .filter(|(agents, _)| agents.contains(&agent))
+            .collect::<Vec<_>>();
+        let chosen = if for_us.is_empty() {
+            groups
+                .iter()
+                .filter(|(agents, _)| agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            for_us
+        };
+        // Note: groups for the same agent are combined.
+        let mut out = Self::default();
+        for (_, group) in chosen {
+            out.rules.extend(group.rules.iter().cloned());
+            out.crawl_delay = out.crawl_delay.max(group.crawl_delay);
+        }
+        out
+    }
+
+    // The longest matching rule wins; on a tie, allow wins. No matching rule means allowed.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+#[derive(Debug)]
+struct CachedRobots {
+    rules: Arc<RobotsRules>,
+    expires: Instant,
+}
+
+#[derive(Debug, Default)]
+struct ComplianceState {
+    policy: CompliancePolicy,
+    robots: HashMap<String, CachedRobots>,
+    // When the next fetch to each site may start, by origin.
+    next_fetch: HashMap<String, Instant>,
+}
+
+// Shared by the PluginHost, which configures it from preferences, and every plugin, which checks
+// with it before each fetch, so that the per-site delay holds across plugins.
+#[derive(Clone, Debug, Default)]
+pub struct Compliance {
+    state: Arc<Mutex<ComplianceState>>,
+}
+
+impl Compliance {
+    pub fn configure(&self, policy: &CompliancePolicy) {
+        self.state.lock().policy = policy.clone();
+    }
+
+    // Check a fetch of `url` against the site's robots.txt, if `scraper`, then wait out the
+    // delay for the site. Returns an error if the fetch is refused or the wait cancelled.
+    pub fn check(
+        &self,
+        url: &str,
+        scraper: bool,
+        (agent, cancellation): (&Agent, &PluginCancellation),
+        log: &mut LogSender,
+    ) -> Result<(), TextFetchError> {
+        let Ok(uri) = url.parse::<Uri>() else {
+            return Ok(());
+        };
+        let (Some(scheme), Some(authority)) = (uri.scheme_str(), uri.authority()) else {
+            return Ok(());
+        };
+        let origin = format!("{scheme}://{authority}");
+        let policy = self.state.lock().policy.clone();
+        let mut delay = policy.min_site_delay();
+        if scraper && policy.robots != RobotsMode::Ignore {
+            let rules = self.robots_for(&origin, delay, (agent, cancellation), log)?;
+            delay = delay.max(rules.crawl_delay().unwrap_or_default());
+            let path = uri.path_and_query().map_or("/", |path| path.as_str());
+            if rules.is_allowed(path) {
+                log.trace(format!("robots.txt allows {url}"));
+            } else if policy.robots == RobotsMode::Obey {
+                log.warn(format!("robots.txt disallows {url}; not fetching it"));
+                return Err(TextFetchError::HostError(format!(
+                    "{url} is disallowed by {origin}/robots.txt"
+                )));
+            } else {
+                log.warn(format!("robots.txt disallows {url}; fetching it anyway"));
+            }
+        }
+        self.wait_for_site(&origin, delay, cancellation, log)
+    }
+
+    fn robots_for(
+        &self,
+        origin: &str,
+        delay: Duration,
+        (agent, cancellation): (&Agent, &PluginCancellation),
+        log: &mut LogSender,
+    ) -> Result<Arc<RobotsRules>, TextFetchError> {
+        if let Some(cached) = self.state.lock().robots.get(origin)
+            && cached.expires > Instant::now()
+        {
+            return Ok(cached.rules.clone());
+        }
+
+        // Note: the lock is not held while fetching, so two plugins may both read a robots.txt
+        //       that neither had; that is harmless.
+        self.wait_for_site(origin, delay, cancellation, log)?;
+        let robots_url = format!("{origin}/robots.txt");
+        let response = agent
+            .get(&robots_url)
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .call();
+        let (rules, ttl) = match response {
+            Ok(mut response) if response.status().is_success() => {
+                match response
+                    .body_mut()
+                    .with_config()
+                    .limit(ROBOTS_MAX_BYTES)
+                    .read_to_string()
+                {
+                    Ok(text) => {
+                        let rules = RobotsRules::parse(&text, ROBOTS_AGENT);
+                        let crawl_delay = rules
+                            .crawl_delay()
+                            .map(|delay| {
+                                format!(", with a crawl delay of {}s", delay.as_secs_f32())
+                            })
+                            .unwrap_or_default();
+                        log.info(format!(
+                            "Read {robots_url}: {} rules for us{crawl_delay}",
+                            rules.rules.len()
+                        ));
+                        (rules, ROBOTS_TTL)
+                    }
+                    Err(e) => {
+                        log.warn(format!(
+                            "Failed to read {robots_url}; treating the site as disallowed: {e}"
+                        ));
+                        (RobotsRules::disallow_all(), ROBOTS_RETRY)
+                    }
+                }
+            }
+            // Note: as RFC 9309 says, a missing robots.txt allows everything, but one that the
+            //       site fails to serve means we should stay away until it can.
+            Ok(response) if response.status().is_client_error() => {
+                log.info(format!(
+                    "No robots.txt at {origin} (HTTP {}); everything is allowed",
+                    response.status().as_u16()
+                ));
+                (RobotsRules::default(), ROBOTS_TTL)
+            }
+            Ok(response) => {
+                log.warn(format!(
+                    "Failed to get {robots_url} (HTTP {}); treating the site as disallowed",
+                    response.status().as_u16()
+                ));
+                (RobotsRules::disallow_all(), ROBOTS_RETRY)
+            }
+            Err(e) => {
+                log.warn(format!(
+                    "Failed to get {robots_url}; treating the site as disallowed: {e}"
+                ));
+                (RobotsRules::disallow_all(), ROBOTS_RETRY)
+            }
+        };
+        let rules = Arc::new(rules);
+        self.state.lock().robots.insert(
+            origin.to_owned(),
+            CachedRobots {
+                rules: rules.clone(),
+                expires: Instant::now() + ttl,
+            },
+        );
+        Ok(rules)
+    }
+
+    // Take the next turn to fetch from the site, then sleep until it comes around.
+    fn wait_for_site(
+        &self,
+        origin: &str,
+        delay: Duration,
+        cancellation: &PluginCancellation,
+        log: &mut LogSender,
+    ) -> Result<(), TextFetchError> {
+        let turn = {
+            let mut state = self.state.lock();
+            let now = Instant::now();
+            let turn = state
+                .next_fetch
+                .get(origin)
+                .map_or(now, |next| (*next).max(now));
+            state.next_fetch.insert(origin.to_owned(), turn + delay);
+            turn
+        };
+        let wait = turn.saturating_duration_since(Instant::now());
+        if wait >= Duration::from_secs(1) {
+            log.trace(format!(
+                "Waiting {:.1}s to fetch from {origin}",
+                wait.as_secs_f32()
+            ));
+        }
+        loop {
+            if cancellation.is_cancelled() {
+                return Err(TextFetchError::Cancellation);
+            }
+            let now = Instant::now();
+            if now >= turn {
+                return Ok(());
+            }
+            sleep((turn - now).min(Duration::from_millis(100)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# A typical museum site.
+User-agent: *
+Disallow: /search
+Disallow: /*.pdf$
+Allow: /search/about
+Crawl-delay: 2
+
+User-agent: BadBot
+User-agent: artchiver
+Disallow: /private/
+Crawl-delay: 5
+
+User-agent: OtherBot
+Disallow: /
+";
+
+    #[test]
+    fn test_robots_for_everyone() {
+        let rules = RobotsRules::parse(ROBOTS, "somebot");
+        assert!(rules.is_allowed("/"));
+        assert!(rules.is_allowed("/collection/1"));
+        assert!(!rules.is_allowed("/search?q=cats"));
+        assert!(rules.is_allowed("/search/about"));
+        assert!(!rules.is_allowed("/docs/catalog.pdf"));
+        assert!(rules.is_allowed("/docs/catalog.pdf.html"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_robots_for_us() {
+        let rules = RobotsRules::parse(ROBOTS, "Artchiver");
+        assert!(rules.is_allowed("/search?q=cats"));
+        assert!(!rules.is_allowed("/private/1"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_robots_disallow_all() {
+        let rules = RobotsRules::parse(ROBOTS, "OtherBot");
+        assert!(!rules.is_allowed("/"));
+        assert!(rules.is_allowed("/robots.txt"));
+        assert!(!RobotsRules::disallow_all().is_allowed("/index.html"));
+        assert!(RobotsRules::default().is_allowed("/index.html"));
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n", ROBOTS_AGENT);
+        assert!(rules.is_allowed("/anything"));
+    }
+
+    #[test]
+    fn test_robots_wildcards() {
+        let rule = |pattern: &str| RobotsRule {
+            allow: false,
+            pattern: pattern.to_owned(),
+        };
+        assert!(rule("/a*b*c").matches("/a-b-c-d"));
+        assert!(!rule("/a*b*c$").matches("/a-b-c-d"));
+        assert!(rule("/a*b*c$").matches("/a-b-c-c"));
+        assert!(rule("/*").matches("/"));
+        assert!(rule("/exact$").matches("/exact"));
+        assert!(!rule("/exact$").matches("/exactly"));
+    }
+}
@@ -0,0 +1,337 @@
+// Lay works out on a grid of pages and write them to a PDF, for printing out and reviewing on
+// paper. We write the PDF ourself: the format is simple as long as all we need is JPEG images
+// and one of the built-in fonts, and that is all a contact sheet needs.
+//
+// Note: we don't track artists yet (see the Artists tab), so captions are the title and date.
+use crate::plugin::thumbnail::open_image;
+use anyhow::{Result, ensure};
+use image::{DynamicImage, codecs::jpeg::JpegEncoder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Paper {
+    #[default]
+    Letter,
+    A4,
+}
+
+impl Paper {
+    pub const ALL: [Self; 2] = [Self::Letter, Self::A4];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Letter => "US Letter",
+            Self::A4 => "A4",
+        }
+    }
+
+    // Width and height in points.
+    fn size(self) -> (f32, f32) {
+        match self {
+            Self::Letter => (612., 792.),
+            Self::A4 => (595., 842.),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContactSheetSettings {
+    pub columns: u32,
+    pub rows: u32,
+    pub captions: bool,
+    pub paper: Paper,
+}
+
+impl Default for ContactSheetSettings {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            rows: 5,
+            captions: true,
+            paper: Paper::default(),
+        }
+    }
+}
+
+impl ContactSheetSettings {
+    const MARGIN: f32 = 36.;
+    const GUTTER: f32 = 10.;
+    const FONT_SIZE: f32 = 7.;
+    // The pixel size we shrink images to before embedding, to keep the file a sane size.
+    const IMAGE_PIXELS: u32 = 600;
+
+    fn per_page(&self) -> usize {
+        (self.columns.max(1) * self.rows.max(1)) as usize
+    }
+
+    fn caption_height(&self) -> f32 {
+        if self.captions {
+            Self::FONT_SIZE * 2.6
+        } else {
+            0.
+        }
+    }
+
+    // The box for the image in each slot of a page, in PDF coordinates (origin bottom-left),
+    // as (x, y, width, height), in reading order.
+    fn cells(&self) -> Vec<(f32, f32, f32, f32)> {
+        let (width, height) = self.paper.size();
+        let columns = self.columns.max(1);
+        let rows = self.rows.max(1);
+        let cell_w =
+            (width - 2. * Self::MARGIN - (columns - 1) as f32 * Self::GUTTER) / columns as f32;
+        let cell_h = (height - 2. * Self::MARGIN - (rows - 1) as f32 * Self::GUTTER) / rows as f32;
+        let mut cells = Vec::with_capacity(self.per_page());
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = Self::MARGIN + column as f32 * (cell_w + Self::GUTTER);
+                let top = height - Self::MARGIN - row as f32 * (cell_h + Self::GUTTER);
+                let image_h = (cell_h - self.caption_height()).max(1.);
+                cells.push((x, top - image_h, cell_w, image_h));
+            }
+        }
+        cells
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SheetEntry {
+    pub image: Option<PathBuf>,
+    pub title: String,
+    pub date: String,
+}
+
+// The built-in fonts only cover WinAnsi, which matches Latin-1 well enough for captions.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => {
+                write!(out, "\\{:03o}", u32::from(c)).ok();
+            }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+// Cut `text` down to roughly what fits in `width` points; Helvetica averages about half an em.
+fn truncate(text: &str, width: f32, font_size: f32) -> String {
+    let max_chars = (width / (font_size * 0.5)) as usize;
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    let mut out = text
+        .chars()
+        .take(max_chars.saturating_sub(3))
+        .collect::<String>();
+    out.push_str("...");
+    out
+}
+
+fn encode_jpeg(img: &DynamicImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    img.to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, 80))?;
+    Ok(bytes)
+}
+
+#[derive(Default)]
+struct PdfWriter {
+    buf: Vec<u8>,
+    // Byte offset of each object, by object number - 1.
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(None);
+        self.offsets.len()
+    }
+
+    fn write(&mut self, id: usize, body: &[u8]) {
+        self.offsets[id - 1] = Some(self.buf.len());
+        writeln!(self.buf, "{id} 0 obj").ok();
+        self.buf.extend_from_slice(body);
+        self.buf.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn add(&mut self, body: &[u8]) -> usize {
+        let id = self.reserve();
+        self.write(id, body);
+        id
+    }
+
+    fn add_stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let mut body = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.add(&body)
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref = self.buf.len();
+        writeln!(
+            self.buf,
+            "xref\n0 {}\n0000000000 65535 f ",
+            self.offsets.len() + 1
+        )
+        .ok();
+        for offset in &self.offsets {
+            writeln!(self.buf, "{:010} 00000 n ", offset.unwrap_or_default()).ok();
+        }
+        writeln!(
+            self.buf,
+            "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF",
+            self.offsets.len() + 1
+        )
+        .ok();
+        self.buf
+    }
+}
+
+// Write `entries` to a PDF at `out`; returns the number of pages written.
+pub fn write_contact_sheet(
+    entries: &[SheetEntry],
+    settings: &ContactSheetSettings,
+    out: &Path,
+) -> Result<usize> {
+    ensure!(!entries.is_empty(), "nothing to print");
+    let (page_w, page_h) = settings.paper.size();
+    let cells = settings.cells();
+
+    let mut pdf = PdfWriter::default();
+    pdf.buf.extend_from_slice(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n");
+    let catalog = pdf.reserve();
+    let pages = pdf.reserve();
+    let font = pdf
+        .add(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>");
+
+    let mut page_ids = Vec::new();
+    for chunk in entries.chunks(settings.per_page()) {
+        let mut content = String::new();
+        let mut xobjects = String::new();
+        for (i, (entry, &(x, y, w, h))) in chunk.iter().zip(&cells).enumerate() {
+            let img = entry
+                .image
+                .as_deref()
+                .and_then(|path| open_image(path).ok())
+                .map(|img| {
+                    img.thumbnail(
+                        ContactSheetSettings::IMAGE_PIXELS,
+                        ContactSheetSettings::IMAGE_PIXELS,
+                    )
+                });
+            if let Some(img) = img {
+                let jpeg = encode_jpeg(&img)?;
+                let image = pdf.add_stream(
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode",
+                        img.width(),
+                        img.height()
+                    ),
+                    &jpeg,
+                );
+                write!(xobjects, "/Im{i} {image} 0 R ").ok();
+                // Fit the image in its cell, centered, keeping its aspect ratio.
+                let scale = (w / img.width() as f32).min(h / img.height() as f32);
+                let (draw_w, draw_h) = (img.width() as f32 * scale, img.height() as f32 * scale);
+                let (draw_x, draw_y) = (x + (w - draw_w) / 2., y + (h - draw_h) / 2.);
+                writeln!(
+                    content,
+                    "q {draw_w:.2} 0 0 {draw_h:.2} {draw_x:.2} {draw_y:.2} cm /Im{i} Do Q"
+                )
+                .ok();
+            } else {
+                // Note: leave a box where the work would be, so the grid still reads correctly.
+                writeln!(content, "q 0.6 G 0.5 w {x:.2} {y:.2} {w:.2} {h:.2} re S Q").ok();
+            }
+            if settings.captions {
+                let size = ContactSheetSettings::FONT_SIZE;
+                let title = pdf_string(&truncate(&entry.title, w, size));
+                let date = pdf_string(&truncate(&entry.date, w, size));
+                writeln!(
+                    content,
+                    "BT /F1 {size} Tf {x:.2} {:.2} Td {title} Tj 0 {:.2} Td {date} Tj ET",
+                    y - size * 1.2,
+                    -size * 1.2
+                )
+                .ok();
+            }
+        }
+        let content = pdf.add_stream("", content.as_bytes());
+        let page = pdf.add(
+            format!(
+                "<< /Type /Page /Parent {pages} 0 R /MediaBox [0 0 {page_w} {page_h}] /Resources << /Font << /F1 {font} 0 R >> /XObject << {xobjects}>> >> /Contents {content} 0 R >>"
+            )
+            .as_bytes(),
+        );
+        page_ids.push(page);
+    }
+
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>();
+    pdf.write(
+        pages,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_ids.len()
+        )
+        .as_bytes(),
+    );
+    pdf.write(
+        catalog,
+        format!("<< /Type /Catalog /Pages {pages} 0 R >>").as_bytes(),
+    );
+    fs::write(out, pdf.finish(catalog))?;
+    Ok(page_ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_string() {
+        assert_eq!(pdf_string("Nighthawks"), "(Nighthawks)");
+        assert_eq!(pdf_string("a (b) \\c"), "(a \\(b\\) \\\\c)");
+        assert_eq!(pdf_string("Café"), "(Caf\\351)");
+        assert_eq!(pdf_string("東京"), "(??)");
+    }
+
+    #[test]
+    fn test_cells() {
+        let settings = ContactSheetSettings {
+            columns: 3,
+            rows: 2,
+            captions: false,
+            paper: Paper::Letter,
+        };
+        let cells = settings.cells();
+        assert_eq!(cells.len(), 6);
+        // Reading order: left to right, then down the page.
+        assert!(cells[0].0 < cells[1].0, "columns go left to right");
+        assert!(cells[0].1 > cells[3].1, "rows go top to bottom");
+        for (x, y, w, h) in cells {
+            assert!(x >= 36. && x + w <= 612. - 35.9, "cell inside the margins");
+            assert!(y >= 35.9 && y + h <= 792. - 35.9, "cell inside the margins");
+        }
+    }
+}
@@ -0,0 +1,327 @@
+// Links into the archive from other programs, like a notes app or an exported exhibition:
+//
+//     artchiver://work?url=<screen url>
+//     artchiver://tag/<tag name>
+//     artchiver://collection/<collection name>
+//
+// Works are linked by their screen url, as their ids change when a plugin refreshes them.
+//
+// The OS starts a new Artchiver with the link as its argument. If another instance already holds
+// the data dir, the new one drops the link into `<data dir>/links` and exits; the instance that
+// holds the lock polls that folder and opens whatever turns up. Going through the data dir, like
+// the instance lock does, means that there is no socket to secure or clean up.
+use crate::http::server::percent_decode;
+use anyhow::{Context as _, Result, bail, ensure};
+use jiff::Timestamp;
+use log::warn;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+pub const SCHEME: &str = "artchiver";
+const LINKS_DIR_NAME: &str = "links";
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeepLink {
+    Work { screen_url: String },
+    Tag { name: String },
+    Collection { name: String },
+}
+
+impl DeepLink {
+    pub fn parse(link: &str) -> Result<Self> {
+        let rest = link
+            .trim()
+            .strip_prefix(SCHEME)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .with_context(|| format!("{link} is not an {SCHEME}:// link"))?;
+        // Note: some launchers add a trailing slash; a slash in a name is always escaped.
+        let rest = rest.trim_end_matches('/');
+        if let Some(query) = rest.strip_prefix("work?") {
+            let screen_url = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("url="))
+                .map(|url| percent_decode(url, true))
+                .filter(|url| !url.is_empty())
+                .with_context(|| format!("{link} does not say which work to open"))?;
+            return Ok(Self::Work { screen_url });
+        }
+        let (kind, name) = rest
+            .split_once('/')
+            .with_context(|| format!("{link} does not name a work, tag, or collection"))?;
+        let name = percent_decode(name, false);
+        ensure!(!name.is_empty(), "{link} has an empty name");
+        match kind {
+            "tag" => Ok(Self::Tag { name }),
+            "collection" => Ok(Self::Collection { name }),
+            _ => bail!("{link} is for a {kind}, which Artchiver can't open"),
+        }
+    }
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Work { screen_url } => {
+                write!(f, "{SCHEME}://work?url={}", percent_encode(screen_url))
+            }
+            Self::Tag { name } => write!(f, "{SCHEME}://tag/{}", percent_encode(name)),
+            Self::Collection { name } => {
+                write!(f, "{SCHEME}://collection/{}", percent_encode(name))
+            }
+        }
+    }
+}
+
+// Escape everything but the unreserved characters, so that the result is safe in a path or a
+// query, and survives being pasted into markdown.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(char::from(b));
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+// Hand the link to whichever instance holds the data dir, which may be this one.
+pub fn deliver(data_dir: &Path, link: &DeepLink) -> Result<()> {
+    let dir = data_dir.join(LINKS_DIR_NAME);
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    // Note: named so that they sort in the order they were sent.
+    let name = format!(
+        "{}-{}-{}",
+        Timestamp::now().as_nanosecond(),
+        std::process::id(),
+        SENT.fetch_add(1, Ordering::Relaxed)
+    );
+    // Note: written under another name first, so that the poller never reads half a link.
+    let partial = dir.join(format!("{name}.partial"));
+    fs::write(&partial, link.to_string())
+        .with_context(|| format!("writing {}", partial.display()))?;
+    fs::rename(&partial, dir.join(format!("{name}.link")))?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct DeepLinkInbox {
+    // None unless this instance holds the data dir, so that it leaves links for the one that does.
+    dir: Option<PathBuf>,
+    // Set by the watcher thread when links turn up, so that we don't read the dir every frame.
+    arrived: Arc<AtomicBool>,
+}
+
+impl DeepLinkInbox {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    // `wake` is called from another thread when links arrive, to get the UI to poll for them.
+    pub fn new(data_dir: &Path, wake: impl Fn() + Send + 'static) -> Self {
+        let dir = data_dir.join(LINKS_DIR_NAME);
+        // Note: check once up front, for the link that we were started with.
+        let arrived = Arc::new(AtomicBool::new(true));
+        let watch = (dir.clone(), arrived.clone());
+        let spawned = thread::Builder::new()
+            .name("deep-links".to_owned())
+            .spawn(move || {
+                let (dir, arrived) = watch;
+                loop {
+                    thread::sleep(Self::POLL_INTERVAL);
+                    if !link_paths(&dir).is_empty() && !arrived.swap(true, Ordering::AcqRel) {
+                        wake();
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start watching for links; only links at startup will open: {e}");
+        }
+        Self {
+            dir: Some(dir),
+            arrived,
+        }
+    }
+
+    // The links that have arrived since the last poll, oldest first.
+    pub fn poll(&mut self) -> Vec<DeepLink> {
+        let Some(dir) = &self.dir else {
+            return Vec::new();
+        };
+        if !self.arrived.swap(false, Ordering::AcqRel) {
+            return Vec::new();
+        }
+        let mut links = Vec::new();
+        for path in link_paths(dir) {
+            let link = fs::read_to_string(&path);
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove {}: {e}", path.display());
+            }
+            match link
+                .map_err(anyhow::Error::from)
+                .and_then(|link| DeepLink::parse(&link))
+            {
+                Ok(link) => links.push(link),
+                Err(e) => warn!("Ignoring the link in {}: {e:#}", path.display()),
+            }
+        }
+        links
+    }
+}
+
+fn link_paths(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "link"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+// Note: links open in the profile that registered for them.
+#[cfg(not(target_os = "macos"))]
+fn profile_arg() -> String {
+    crate::shared::profile::profile()
+        .map(|name| format!(" --profile {name}"))
+        .unwrap_or_default()
+}
+
+// Make the OS send artchiver:// links to this executable. Links are opened in the data dir that
+// we are running against now, wherever the OS starts the handler from.
+#[cfg(target_os = "windows")]
+pub fn register_scheme() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let dir = std::env::current_dir()?;
+    let key = format!(r"HKCU\Software\Classes\{SCHEME}");
+    let command = format!(
+        "\"{}\" --dir \"{}\"{} \"%1\"",
+        exe.display(),
+        dir.display(),
+        profile_arg()
+    );
+    reg_add(&key, &["/ve", "/d", "URL:Artchiver"])?;
+    reg_add(&key, &["/v", "URL Protocol", "/d", ""])?;
+    reg_add(
+        &format!(r"{key}\shell\open\command"),
+        &["/ve", "/d", &command],
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn reg_add(key: &str, args: &[&str]) -> Result<()> {
+    let status = std::process::Command::new("reg")
+        .args(["add", key])
+        .args(args)
+        .arg("/f")
+        .status()?;
+    ensure!(status.success(), "reg add {key} exited with {status}");
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn register_scheme() -> Result<()> {
+    bail!("macOS only sends {SCHEME}:// links to app bundles that declare them in their Info.plist")
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn register_scheme() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let dir = std::env::current_dir()?;
+    let apps = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .context("neither XDG_DATA_HOME nor HOME is set")?
+        .join("applications");
+    fs::create_dir_all(&apps)?;
+    let desktop_name = format!("{SCHEME}-links.desktop");
+    let desktop = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Artchiver\n\
+         Exec=\"{}\" --dir \"{}\"{} %u\n\
+         MimeType=x-scheme-handler/{SCHEME};\n\
+         NoDisplay=true\n\
+         Terminal=false\n",
+        exe.display(),
+        dir.display(),
+        profile_arg()
+    );
+    let path = apps.join(&desktop_name);
+    fs::write(&path, desktop).with_context(|| format!("writing {}", path.display()))?;
+    let status = std::process::Command::new("xdg-mime")
+        .args(["default", &desktop_name])
+        .arg(format!("x-scheme-handler/{SCHEME}"))
+        .status()
+        .context("running xdg-mime")?;
+    ensure!(status.success(), "xdg-mime exited with {status}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let links = [
+            DeepLink::Work {
+                screen_url: "https://example.org/a b.jpg?size=full&x=1+2".to_owned(),
+            },
+            DeepLink::Tag {
+                name: "AC/DC".to_owned(),
+            },
+            DeepLink::Collection {
+                name: "Venice (Series)".to_owned(),
+            },
+        ];
+        for link in links {
+            assert_eq!(DeepLink::parse(&link.to_string())?, link);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(
+            DeepLink::parse("artchiver://tag/Still%20Life/")?,
+            DeepLink::Tag {
+                name: "Still Life".to_owned()
+            }
+        );
+        assert!(DeepLink::parse("https://tag/Still%20Life").is_err());
+        assert!(DeepLink::parse("artchiver://artist/Monet").is_err());
+        assert!(DeepLink::parse("artchiver://work?id=12").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deliver_and_poll() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("artchiver-links-{}", rand::random::<u32>()));
+        let first = DeepLink::Tag {
+            name: "first".to_owned(),
+        };
+        let second = DeepLink::Tag {
+            name: "second".to_owned(),
+        };
+        deliver(&dir, &first)?;
+        deliver(&dir, &second)?;
+        let mut inbox = DeepLinkInbox::new(&dir, || {});
+        assert_eq!(inbox.poll(), [first, second]);
+        assert!(fs::read_dir(dir.join(LINKS_DIR_NAME))?.next().is_none());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
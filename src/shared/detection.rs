@@ -0,0 +1,441 @@
+// Finding faces and figures in works, to suggest tags like "portrait" that the user can accept
+// or reject, e.g. to get a start on tagging a pile of local imports.
+//
+// The models are the user's own, run locally with ONNX Runtime: YOLO-style detectors, whose
+// output is a box and a score per class for each candidate. YOLOv8 finds figures as its
+// "person" class, and there are YOLOv8 models trained on faces.
+//
+// Note: ONNX Runtime is a large native dependency, so detection is only in builds with the
+//       `detection` feature.
+use serde::{Deserialize, Serialize};
+
+pub const AVAILABLE: bool = cfg!(feature = "detection");
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DetectionKind {
+    #[default]
+    Face,
+    Figure,
+}
+
+impl DetectionKind {
+    pub const ALL: [Self; 2] = [Self::Face, Self::Figure];
+
+    // How this is stored in the kind column.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Face => "face",
+            Self::Figure => "figure",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectorModel {
+    pub path: String,
+    pub kind: DetectionKind,
+    // The output class that is a face or a figure: 0 for face models and for YOLOv8's person.
+    pub class: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionSettings {
+    pub enabled: bool,
+    pub models: Vec<DetectorModel>,
+    pub min_confidence: f32,
+}
+
+impl Default for DetectionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            models: Vec::new(),
+            min_confidence: 0.5,
+        }
+    }
+}
+
+// A face or figure, in fractions of the image, as for annotations.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Detection {
+    pub kind: DetectionKind,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub confidence: f32,
+}
+
+impl Detection {
+    fn area(&self) -> f32 {
+        self.width * self.height
+    }
+}
+
+// Tags to suggest for what we found in a work, each with the reason that we give the user.
+pub fn suggest_tags(detections: &[Detection]) -> Vec<(String, String)> {
+    // Note: a face that is this much of the picture is what the picture is about.
+    const PORTRAIT_FACE_AREA: f32 = 0.02;
+    const PORTRAIT_FIGURE_AREA: f32 = 0.15;
+    const MANY_PEOPLE: usize = 6;
+
+    let count = |kind| detections.iter().filter(|d| d.kind == kind).count();
+    let largest = |kind| {
+        detections
+            .iter()
+            .filter(|d| d.kind == kind)
+            .map(Detection::area)
+            .fold(0., f32::max)
+    };
+    let (faces, figures) = (count(DetectionKind::Face), count(DetectionKind::Figure));
+    let people = faces.max(figures);
+    if people == 0 {
+        return Vec::new();
+    }
+
+    let plural = |n: usize, what: &str| {
+        if n == 1 {
+            format!("1 {what}")
+        } else {
+            format!("{n} {what}s")
+        }
+    };
+    let found = match (faces, figures) {
+        (0, figures) => format!("found {}", plural(figures, "figure")),
+        (faces, 0) => format!("found {}", plural(faces, "face")),
+        (faces, figures) => format!(
+            "found {} and {}",
+            plural(faces, "face"),
+            plural(figures, "figure")
+        ),
+    };
+
+    let mut tags = Vec::new();
+    let count_tag = match people {
+        1 => "1 person".to_owned(),
+        n if n >= MANY_PEOPLE => "many people".to_owned(),
+        n => format!("{n} people"),
+    };
+    tags.push((count_tag, found.clone()));
+    if people == 1
+        && (largest(DetectionKind::Face) >= PORTRAIT_FACE_AREA
+            || largest(DetectionKind::Figure) >= PORTRAIT_FIGURE_AREA)
+    {
+        tags.push(("portrait".to_owned(), format!("{found}, filling the frame")));
+    } else if (2..MANY_PEOPLE).contains(&faces) {
+        tags.push(("group portrait".to_owned(), found));
+    }
+    tags
+}
+
+#[cfg(feature = "detection")]
+pub use onnx::Detector;
+
+#[cfg(feature = "detection")]
+mod onnx {
+    use super::{Detection, DetectionSettings, DetectorModel};
+    use crate::plugin::thumbnail::open_image;
+    use anyhow::{Context as _, Result, bail, ensure};
+    use image::{DynamicImage, imageops::FilterType};
+    use ort::{session::Session, value::Tensor};
+    use std::path::Path;
+
+    // The square that YOLOv8 models take their input in.
+    const INPUT_SIZE: u32 = 640;
+    // Boxes of the same kind that overlap more than this are the same face or figure.
+    const MAX_OVERLAP: f32 = 0.45;
+
+    // Where the image went in the model's square input: how much we scaled it, and how far it
+    // is from the corner, in input pixels.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Letterbox {
+        scale: f32,
+        pad_x: f32,
+        pad_y: f32,
+        width: f32,
+        height: f32,
+    }
+
+    impl Letterbox {
+        fn new(width: u32, height: u32, size: u32) -> Self {
+            let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+            let scale = (size as f32 / width).min(size as f32 / height);
+            Self {
+                scale,
+                pad_x: ((size as f32 - width * scale) / 2.).floor(),
+                pad_y: ((size as f32 - height * scale) / 2.).floor(),
+                width,
+                height,
+            }
+        }
+    }
+
+    // The image, scaled to fit the input square and centered on gray, as planes of red, green,
+    // and blue from 0 to 1.
+    fn prepare(img: &DynamicImage, size: u32) -> (Vec<f32>, Letterbox) {
+        let letterbox = Letterbox::new(img.width(), img.height(), size);
+        let scaled = img
+            .resize_exact(
+                ((letterbox.width * letterbox.scale).round() as u32).clamp(1, size),
+                ((letterbox.height * letterbox.scale).round() as u32).clamp(1, size),
+                FilterType::Triangle,
+            )
+            .to_rgb8();
+        let plane = (size * size) as usize;
+        let mut input = vec![114. / 255.; 3 * plane];
+        for (x, y, pixel) in scaled.enumerate_pixels() {
+            let (x, y) = (x + letterbox.pad_x as u32, y + letterbox.pad_y as u32);
+            if x >= size || y >= size {
+                continue;
+            }
+            let offset = (y * size + x) as usize;
+            for (channel, value) in pixel.0.into_iter().enumerate() {
+                input[channel * plane + offset] = f32::from(value) / 255.;
+            }
+        }
+        (input, letterbox)
+    }
+
+    // YOLOv8 output is [1, 4 + classes, candidates]: the center and size of each candidate's
+    // box in input pixels, then its score for each class.
+    fn parse(
+        dims: &[i64],
+        data: &[f32],
+        model: &DetectorModel,
+        letterbox: Letterbox,
+        min_confidence: f32,
+    ) -> Result<Vec<Detection>> {
+        let [1, rows, candidates] = dims else {
+            bail!("expected output of [1, 4 + classes, candidates], not {dims:?}");
+        };
+        let (rows, candidates) = (usize::try_from(*rows)?, usize::try_from(*candidates)?);
+        ensure!(
+            model.class + 4 < rows,
+            "the model has no class {}",
+            model.class
+        );
+        ensure!(data.len() == rows * candidates, "output is the wrong size");
+
+        let at = |row: usize, i: usize| data[row * candidates + i];
+        let mut found = Vec::new();
+        for i in 0..candidates {
+            let confidence = at(4 + model.class, i);
+            if confidence < min_confidence {
+                continue;
+            }
+            let (center_x, center_y, width, height) = (at(0, i), at(1, i), at(2, i), at(3, i));
+            // Note: undo the letterbox, to get back to fractions of the whole image.
+            let to_x = |x: f32| ((x - letterbox.pad_x) / letterbox.scale / letterbox.width);
+            let to_y = |y: f32| ((y - letterbox.pad_y) / letterbox.scale / letterbox.height);
+            let (left, top) = (
+                to_x(center_x - width / 2.).clamp(0., 1.),
+                to_y(center_y - height / 2.).clamp(0., 1.),
+            );
+            let (right, bottom) = (
+                to_x(center_x + width / 2.).clamp(0., 1.),
+                to_y(center_y + height / 2.).clamp(0., 1.),
+            );
+            found.push(Detection {
+                kind: model.kind,
+                x: left,
+                y: top,
+                width: right - left,
+                height: bottom - top,
+                confidence,
+            });
+        }
+        Ok(found)
+    }
+
+    fn overlap(a: &Detection, b: &Detection) -> f32 {
+        let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+        let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+        if width <= 0. || height <= 0. {
+            return 0.;
+        }
+        let shared = width * height;
+        shared / (a.area() + b.area() - shared)
+    }
+
+    // Models find the same face many times over, in slightly different boxes; keep the most
+    // confident of each cluster.
+    fn suppress_overlaps(mut detections: Vec<Detection>) -> Vec<Detection> {
+        detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let mut kept: Vec<Detection> = Vec::new();
+        for detection in detections {
+            if kept
+                .iter()
+                .all(|k| k.kind != detection.kind || overlap(k, &detection) <= MAX_OVERLAP)
+            {
+                kept.push(detection);
+            }
+        }
+        kept
+    }
+
+    pub struct Detector {
+        models: Vec<(DetectorModel, Session)>,
+        min_confidence: f32,
+    }
+
+    impl Detector {
+        pub fn load(settings: &DetectionSettings) -> Result<Self> {
+            ensure!(
+                !settings.models.is_empty(),
+                "no detection models are set up"
+            );
+            let models = settings
+                .models
+                .iter()
+                .map(|model| {
+                    let session = Session::builder()?
+                        .commit_from_file(&model.path)
+                        .with_context(|| format!("loading {}", model.path))?;
+                    Ok((model.clone(), session))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Self {
+                models,
+                min_confidence: settings.min_confidence,
+            })
+        }
+
+        pub fn detect(&mut self, path: &Path) -> Result<Vec<Detection>> {
+            let img = open_image(path)?;
+            let (input, letterbox) = prepare(&img, INPUT_SIZE);
+            let mut found = Vec::new();
+            for (model, session) in &mut self.models {
+                let size = INPUT_SIZE as usize;
+                let tensor = Tensor::from_array(([1, 3, size, size], input.clone()))?;
+                let outputs = session.run(ort::inputs![tensor])?;
+                let (shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+                found.extend(parse(shape, data, model, letterbox, self.min_confidence)?);
+            }
+            Ok(suppress_overlaps(found))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::shared::detection::DetectionKind;
+
+        #[test]
+        fn test_parse() -> Result<()> {
+            // A 1280x640 image goes in at half size, 160 pixels down from the top.
+            let letterbox = Letterbox::new(1280, 640, 640);
+            assert_eq!(
+                (letterbox.scale, letterbox.pad_x, letterbox.pad_y),
+                (0.5, 0., 160.)
+            );
+
+            // Two candidates, with scores for two classes; only the first is a face.
+            let data = [
+                320., 100., // center x
+                320., 100., // center y
+                64., 10., // width
+                32., 10., // height
+                0.2, 0.9, // class 0
+                0.9, 0.1, // class 1
+            ];
+            let model = DetectorModel {
+                path: String::new(),
+                kind: DetectionKind::Face,
+                class: 1,
+            };
+            let found = parse(&[1, 6, 2], &data, &model, letterbox, 0.5)?;
+            assert_eq!(found.len(), 1);
+            let face = found[0];
+            assert!((face.x - 0.45).abs() < 1e-4);
+            assert!((face.y - 0.45).abs() < 1e-4);
+            assert!((face.width - 0.1).abs() < 1e-4);
+            assert!((face.height - 0.1).abs() < 1e-4);
+
+            assert!(parse(&[1, 6, 3], &data, &model, letterbox, 0.5).is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn test_suppress_overlaps() {
+            let face = |x: f32, confidence: f32| Detection {
+                kind: DetectionKind::Face,
+                x,
+                y: 0.,
+                width: 0.2,
+                height: 0.2,
+                confidence,
+            };
+            let kept = suppress_overlaps(vec![face(0., 0.6), face(0.01, 0.9), face(0.5, 0.7)]);
+            assert_eq!(kept, vec![face(0.01, 0.9), face(0.5, 0.7)]);
+        }
+    }
+}
+
+#[cfg(not(feature = "detection"))]
+pub use stub::Detector;
+
+// Stands in for the detector in builds without ONNX Runtime.
+#[cfg(not(feature = "detection"))]
+mod stub {
+    use super::{Detection, DetectionSettings};
+    use anyhow::{Result, bail};
+    use std::path::Path;
+
+    pub struct Detector;
+
+    impl Detector {
+        pub fn load(_settings: &DetectionSettings) -> Result<Self> {
+            bail!("this build doesn't include detection; build with `--features detection`")
+        }
+
+        pub fn detect(&mut self, _path: &Path) -> Result<Vec<Detection>> {
+            bail!("this build doesn't include detection")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(kind: DetectionKind, size: f32) -> Detection {
+        Detection {
+            kind,
+            x: 0.1,
+            y: 0.1,
+            width: size,
+            height: size,
+            confidence: 0.9,
+        }
+    }
+
+    fn tags(detections: &[Detection]) -> Vec<String> {
+        suggest_tags(detections)
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .collect()
+    }
+
+    #[test]
+    fn test_suggest_tags() {
+        use DetectionKind::{Face, Figure};
+        assert!(tags(&[]).is_empty());
+        assert_eq!(
+            tags(&[found(Face, 0.3), found(Figure, 0.5)]),
+            ["1 person", "portrait"]
+        );
+        // Note: a distant figure in a landscape is not a portrait.
+        assert_eq!(tags(&[found(Figure, 0.05)]), ["1 person"]);
+        assert_eq!(
+            tags(&[found(Face, 0.1), found(Face, 0.1), found(Face, 0.1)]),
+            ["3 people", "group portrait"]
+        );
+        assert_eq!(tags(&[found(Figure, 0.05); 8]), ["many people"]);
+        assert_eq!(
+            suggest_tags(&[found(Face, 0.1), found(Figure, 0.2), found(Figure, 0.2)])[0].1,
+            "found 1 face and 2 figures"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{http::server::HttpServer, plugin::host::PluginHost, shared::metrics};
+use anyhow::Result;
+use artchiver_sdk::ConfigValue;
+use jiff::{Timestamp, tz::TimeZone};
+use serde_json::json;
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Plugin configuration is frequently an API key, so only say whether it is filled in.
+fn redact_config(value: &ConfigValue) -> &'static str {
+    match value {
+        ConfigValue::String(s) if s.is_empty() => "<empty>",
+        ConfigValue::StringList(v) if v.is_empty() => "<empty>",
+        _ => "<set>",
+    }
+}
+
+fn plugins_json(host: &PluginHost) -> serde_json::Value {
+    let plugins = host
+        .plugins()
+        .map(|plugin| {
+            let config = plugin
+                .metadata()
+                .map(|metadata| {
+                    metadata
+                        .configurations()
+                        .iter()
+                        .map(|(key, value)| (key.clone(), json!(redact_config(value))))
+                        .collect::<serde_json::Map<_, _>>()
+                })
+                .unwrap_or_default();
+            json!({
+                "name": plugin.name(),
+                "version": plugin.version(),
+                "source": plugin.source().display().to_string(),
+                "config": config,
+                "active_task": plugin.active_task().map(ToString::to_string),
+                "queued_tasks": plugin.task_queue().map(ToString::to_string).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!(plugins)
+}
+
+fn logs_text(host: &PluginHost, app_logs: &[String]) -> String {
+    let mut out = String::new();
+    writeln!(out, "== artchiver ==").ok();
+    for line in app_logs {
+        writeln!(out, "{line}").ok();
+    }
+    for plugin in host.plugins() {
+        writeln!(out, "\n== {} ==", plugin.name()).ok();
+        // Note: the plugin keeps its newest message first.
+        let messages = plugin.log_messages().collect::<Vec<_>>();
+        for line in messages.iter().rev() {
+            writeln!(
+                out,
+                "{} {:<5} [{}] {}",
+                line.timestamp(),
+                line.level(),
+                line.job().unwrap_or("-"),
+                line.message()
+            )
+            .ok();
+        }
+    }
+    out
+}
+
+// Write everything a bug report needs into a fresh folder under the data dir and return it.
+// Secrets (plugin configuration values and the HTTP token) are left out.
+pub fn export_diagnostics(
+    data_dir: &Path,
+    host: &PluginHost,
+    http: &HttpServer,
+    app_logs: &[String],
+) -> Result<PathBuf> {
+    let now = Timestamp::now();
+    let stamp = now.to_zoned(TimeZone::system()).strftime("%Y%m%d-%H%M%S");
+    let dir = data_dir
+        .join("diagnostics")
+        .join(format!("artchiver-diagnostics-{stamp}"));
+    fs::create_dir_all(&dir)?;
+
+    let system = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "exported_at": now,
+    });
+    fs::write(
+        dir.join("system.json"),
+        serde_json::to_string_pretty(&system)?,
+    )?;
+    let config = json!({
+        "http": http.diagnostics(),
+        "plugins": plugins_json(host),
+    });
+    fs::write(
+        dir.join("config.json"),
+        serde_json::to_string_pretty(&config)?,
+    )?;
+    fs::write(dir.join("metrics.prom"), metrics::render_prometheus())?;
+    fs::write(dir.join("logs.txt"), logs_text(host, app_logs))?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_config() {
+        assert_eq!(
+            redact_config(&ConfigValue::String(String::new())),
+            "<empty>"
+        );
+        assert_eq!(
+            redact_config(&ConfigValue::String("hunter2".to_owned())),
+            "<set>"
+        );
+        assert_eq!(redact_config(&ConfigValue::StringList(vec![])), "<empty>");
+        assert_eq!(
+            redact_config(&ConfigValue::StringList(vec!["a".to_owned()])),
+            "<set>"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+// Pages of PDF and DjVu documents, as images and the words on them, for reading book scans in the
+// slideshow. PDFs go through PDFium, which the user installs, or which ships next to our binary;
+// DjVu goes through DjVuLibre's command line tools, which is how nearly everything else reads it.
+use anyhow::{Context as _, Result, bail, ensure};
+use image::{ImageFormat, RgbaImage};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+use regex::Regex;
+use std::{
+    env,
+    path::Path,
+    process::{Command, Output},
+    sync::LazyLock,
+};
+
+// A word from the document's text layer, with its box as fractions of the page, from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub rect: [f32; 4],
+}
+
+impl Word {
+    fn extend(&mut self, text: &str, rect: [f32; 4]) {
+        self.text.push_str(text);
+        self.rect = [
+            self.rect[0].min(rect[0]),
+            self.rect[1].min(rect[1]),
+            self.rect[2].max(rect[2]),
+            self.rect[3].max(rect[3]),
+        ];
+    }
+}
+
+enum Format {
+    Pdf,
+    Djvu,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_ascii_lowercase();
+        match ext.to_str()? {
+            "pdf" => Some(Self::Pdf),
+            "djvu" | "djv" => Some(Self::Djvu),
+            _ => None,
+        }
+    }
+}
+
+// Whether we can show the pages of the document at `path`.
+pub fn is_paged(path: &Path) -> bool {
+    Format::of(path).is_some()
+}
+
+pub fn page_count(path: &Path) -> Result<usize> {
+    match Format::of(path).context("not a PDF or DjVu document")? {
+        Format::Pdf => {
+            let pdfium = pdfium()?;
+            let document = pdfium.load_pdf_from_file(path, None)?;
+            Ok(usize::from(document.pages().len()))
+        }
+        Format::Djvu => {
+            let out = run(Command::new("djvused").arg("-e").arg("n").arg(path))?;
+            Ok(String::from_utf8_lossy(&out.stdout).trim().parse()?)
+        }
+    }
+}
+
+// Draw page `page`, from 0, to fit in a square `max_edge` pixels on a side.
+pub fn render_page(path: &Path, page: usize, max_edge: u32) -> Result<RgbaImage> {
+    match Format::of(path).context("not a PDF or DjVu document")? {
+        Format::Pdf => {
+            let pdfium = pdfium()?;
+            let document = pdfium.load_pdf_from_file(path, None)?;
+            let page = document.pages().get(u16::try_from(page)?)?;
+            let edge = i32::try_from(max_edge)?;
+            let config = if page.width().value >= page.height().value {
+                PdfRenderConfig::new().set_target_width(edge)
+            } else {
+                PdfRenderConfig::new().set_target_height(edge)
+            };
+            Ok(page.render_with_config(&config)?.as_image().to_rgba8())
+        }
+        Format::Djvu => {
+            let out = run(Command::new("ddjvu")
+                .arg("-format=ppm")
+                .arg(format!("-page={}", page + 1))
+                .arg(format!("-size={max_edge}x{max_edge}"))
+                .arg(path))?;
+            Ok(image::load_from_memory_with_format(&out.stdout, ImageFormat::Pnm)?.to_rgba8())
+        }
+    }
+}
+
+// The words on page `page`; empty for scans with no text layer.
+pub fn page_words(path: &Path, page: usize) -> Result<Vec<Word>> {
+    match Format::of(path).context("not a PDF or DjVu document")? {
+        Format::Pdf => {
+            let pdfium = pdfium()?;
+            let document = pdfium.load_pdf_from_file(path, None)?;
+            let page = document.pages().get(u16::try_from(page)?)?;
+            let (width, height) = (page.width().value, page.height().value);
+            ensure!(width > 0. && height > 0., "the page has no size");
+            let text = page.text()?;
+            let mut words = Vec::new();
+            let mut current: Option<Word> = None;
+            for ch in text.chars().iter() {
+                let bounds = ch.loose_bounds().ok();
+                match (ch.unicode_char(), bounds) {
+                    (Some(c), Some(bounds)) if !c.is_whitespace() => {
+                        let rect = [
+                            bounds.left().value / width,
+                            1. - bounds.top().value / height,
+                            bounds.right().value / width,
+                            1. - bounds.bottom().value / height,
+                        ];
+                        let c = c.to_string();
+                        match current.as_mut() {
+                            Some(word) => word.extend(&c, rect),
+                            None => current = Some(Word { text: c, rect }),
+                        }
+                    }
+                    _ => words.extend(current.take()),
+                }
+            }
+            words.extend(current);
+            Ok(words)
+        }
+        Format::Djvu => {
+            let out = run(Command::new("djvutxt")
+                .arg(format!("--page={}", page + 1))
+                .arg("--detail=word")
+                .arg(path))?;
+            Ok(parse_djvu_words(&String::from_utf8_lossy(&out.stdout)))
+        }
+    }
+}
+
+// Note: PDFium is a large C++ library that few systems have installed, so look next to our own
+//       binary first, where a packaged build would put it.
+fn pdfium() -> Result<Pdfium> {
+    let beside_exe = env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            exe.parent()
+                .map(Pdfium::pdfium_platform_library_name_at_path)
+        })
+        .and_then(|path| Pdfium::bind_to_library(path).ok());
+    let bindings = match beside_exe {
+        Some(bindings) => bindings,
+        None => Pdfium::bind_to_system_library()
+            .context("PDFium is not installed; put the pdfium library next to artchiver")?,
+    };
+    Ok(Pdfium::new(bindings))
+}
+
+fn run(command: &mut Command) -> Result<Output> {
+    let out = command.output().with_context(|| {
+        format!(
+            "running {:?}; is DjVuLibre installed?",
+            command.get_program()
+        )
+    })?;
+    if !out.status.success() {
+        bail!(
+            "{:?} exited with {}: {}",
+            command.get_program(),
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(out)
+}
+
+// djvutxt writes the text layer as an s-expression of nested zones, in pixels from the bottom
+// left of the page, e.g. `(page 0 0 2550 3300 (line ... (word 300 3000 420 3040 "Chapter")))`.
+fn parse_djvu_words(sexpr: &str) -> Vec<Word> {
+    static PAGE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\(page\s+(-?\d+)\s+(-?\d+)\s+(-?\d+)\s+(-?\d+)").expect("valid regex")
+    });
+    static WORD: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"\(word\s+(-?\d+)\s+(-?\d+)\s+(-?\d+)\s+(-?\d+)\s+"((?:[^"\\]|\\.)*)""#)
+            .expect("valid regex")
+    });
+    let number = |caps: &regex::Captures<'_>, i: usize| caps[i].parse::<f32>().unwrap_or(0.);
+    let Some(page) = PAGE.captures(sexpr) else {
+        return Vec::new();
+    };
+    let (width, height) = (number(&page, 3), number(&page, 4));
+    if width <= 0. || height <= 0. {
+        return Vec::new();
+    }
+    WORD.captures_iter(sexpr)
+        .map(|caps| Word {
+            text: unescape(&caps[5]),
+            rect: [
+                number(&caps, 1) / width,
+                1. - number(&caps, 4) / height,
+                number(&caps, 3) / width,
+                1. - number(&caps, 2) / height,
+            ],
+        })
+        .collect()
+}
+
+// Undo djvutxt's C-style escapes; anything past ASCII comes out as octal escapes of its UTF-8.
+fn unescape(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let mut octal = String::new();
+        while octal.len() < 3
+            && let Some(digit) = chars.next_if(|c| c.is_digit(8))
+        {
+            octal.push(digit);
+        }
+        if let Ok(byte) = u8::from_str_radix(&octal, 8) {
+            bytes.push(byte);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_paged, parse_djvu_words, unescape};
+    use std::path::Path;
+
+    #[test]
+    fn test_is_paged() {
+        assert!(is_paged(Path::new("ab/cd/scan.PDF")));
+        assert!(is_paged(Path::new("ab/cd/scan.djvu")));
+        assert!(!is_paged(Path::new("ab/cd/scan.epub")));
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r#"say \"hi\""#), r#"say "hi""#);
+        assert_eq!(unescape(r"caf\303\251"), "café");
+    }
+
+    #[test]
+    fn test_parse_djvu_words() {
+        let sexpr = r#"(page 0 0 1000 2000
+ (line 100 1800 400 1900
+  (word 100 1800 250 1900 "Plate")
+  (word 300 1800 400 1900 "IV.")))"#;
+        let words = parse_djvu_words(sexpr);
+        let texts = words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["Plate", "IV."]);
+        let expect = [0.1, 0.05, 0.25, 0.1];
+        for (got, want) in words[0].rect.iter().zip(expect) {
+            assert!((got - want).abs() < 1e-4, "{:?}", words[0].rect);
+        }
+        assert!(parse_djvu_words("").is_empty());
+    }
+}
@@ -0,0 +1,717 @@
+// Optional encryption at rest, for archives of sensitive material.
+//
+// Everything is encrypted under a random 256 bit data key: the metadata DB with SQLCipher, and
+// the files on encrypted storage roots with AES-256-GCM, in 64 KiB chunks, so that large files
+// never have to fit in memory. The data key is kept in `<data dir>/encryption.json`, wrapped with
+// a key derived from the user's passphrase with Argon2id, so changing the passphrase only re-wraps
+// the data key. Rotating the data key itself re-encrypts the DB and every file, which can take a
+// while. Keys are wiped from memory when they are dropped.
+//
+// Note: a rotation that gets interrupted keeps the old key next to the new one as `retiring`,
+//       so that anything not yet re-encrypted can still be read, and the next unlock finishes
+//       the job.
+//
+// Note: files are decrypted into the remote cache to be viewed, as files on remote roots are
+//       downloaded there; Storage clears those copies at startup and exit. Thumbnails are made
+//       from the decrypted copies and sealed on the same root as the work.
+//
+// Note: only files on encrypted roots are encrypted; the storage rules decide which downloads
+//       go there, and the unlock prompt warns about any that don't.
+use crate::shared::instance_lock::is_read_only;
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit as _, Nonce,
+    aead::{
+        Aead as _,
+        stream::{NewStream as _, StreamBE32, StreamPrimitive as _},
+    },
+};
+use anyhow::{Context as _, Result, anyhow, bail, ensure};
+use argon2::{Algorithm, Argon2, Params, Version};
+use log::{info, warn};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    iter, mem,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+use zeroize::{Zeroize as _, Zeroizing};
+
+const CONFIG_FILE_NAME: &str = "encryption.json";
+const NONCE_LEN: usize = 12;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+// Sealed files are the magic and a nonce prefix, then the chunks, each sealed on its own with the
+// STREAM construction.
+const FILE_MAGIC: &[u8; 4] = b"ACE1";
+const STREAM_NONCE_LEN: usize = 7;
+const HEADER_LEN: usize = FILE_MAGIC.len() + STREAM_NONCE_LEN;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+// The keys for this run, once the user has unlocked them.
+static UNLOCKED: RwLock<Option<DataKeys>> = RwLock::new(None);
+
+pub fn unlocked_keys() -> Option<DataKeys> {
+    UNLOCKED.read().expect("poison").clone()
+}
+
+fn set_unlocked_keys(keys: DataKeys) {
+    *UNLOCKED.write().expect("poison") = Some(keys);
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct DataKey([u8; 32]);
+
+impl DataKey {
+    fn random() -> Self {
+        Self(rand::random())
+    }
+
+    fn hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(to_hex(&self.0))
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0))
+    }
+
+    fn stream(&self, nonce: &[u8]) -> StreamBE32<Aes256Gcm> {
+        StreamBE32::from_aead(self.cipher(), nonce.into())
+    }
+
+    // Start a new sealed file: returns the header to write first, and the stream to seal the
+    // chunks with.
+    fn start_stream(&self) -> ([u8; HEADER_LEN], StreamBE32<Aes256Gcm>) {
+        let nonce: [u8; STREAM_NONCE_LEN] = rand::random();
+        let mut header = [0u8; HEADER_LEN];
+        header[..FILE_MAGIC.len()].copy_from_slice(FILE_MAGIC);
+        header[FILE_MAGIC.len()..].copy_from_slice(&nonce);
+        (header, self.stream(&nonce))
+    }
+
+    fn seal(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let sealed = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_e| anyhow!("encryption failed"))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .ok()
+            .map(Zeroizing::new)
+    }
+
+    // Use the key directly, rather than have SQLCipher derive another from it.
+    fn sqlcipher_key(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!("x'{}'", self.hex().as_str()))
+    }
+}
+
+impl Drop for DataKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DataKey(..)")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DataKeys {
+    current: DataKey,
+    retiring: Option<DataKey>,
+}
+
+impl DataKeys {
+    // Set the key on a new connection, before anything else touches the DB.
+    pub fn key_connection(&self, conn: &Connection) -> rusqlite::Result<()> {
+        key_connection(conn, &self.current)
+    }
+
+    pub fn encrypt_file(&self, src: &Path, dest: &Path) -> Result<()> {
+        let plain = File::open(src).with_context(|| format!("reading {}", src.display()))?;
+        write_atomic(dest, |sealed| self.encrypt(BufReader::new(plain), sealed))
+    }
+
+    pub fn decrypt_file(&self, src: &Path, dest: &Path) -> Result<()> {
+        let sealed = File::open(src).with_context(|| format!("reading {}", src.display()))?;
+        write_atomic(dest, |plain| self.decrypt(BufReader::new(sealed), plain))
+            .with_context(|| format!("decrypting {}", src.display()))?;
+        Ok(())
+    }
+
+    fn encrypt(&self, plain: impl Read, sealed: &mut impl Write) -> Result<()> {
+        let (header, stream) = self.current.start_stream();
+        sealed.write_all(&header)?;
+        let mut chunks = Chunks::new(plain, CHUNK_LEN)?;
+        let mut position = 0u32;
+        loop {
+            let (chunk, last) = chunks.next_chunk()?;
+            sealed.write_all(&seal_chunk(&stream, position, last, &chunk)?)?;
+            if last {
+                return Ok(());
+            }
+            position = position
+                .checked_add(1)
+                .context("the file is too large to encrypt")?;
+        }
+    }
+
+    // Returns whether the file was sealed with the retiring key.
+    fn decrypt(&self, sealed: impl Read, plain: &mut impl Write) -> Result<bool> {
+        let mut opened = OpenStream::new(self, sealed)?;
+        while let Some((_, _, chunk)) = opened.next_chunk()? {
+            plain.write_all(&chunk)?;
+        }
+        Ok(opened.retiring)
+    }
+
+    // Move a file from the retiring key to the current one. Each chunk is re-sealed as it is
+    // read, so the plain text never touches the disk.
+    fn reseal_file(&self, mut opened: OpenStream<impl Read>, path: &Path) -> Result<()> {
+        write_atomic(path, |sealed| {
+            let (header, stream) = self.current.start_stream();
+            sealed.write_all(&header)?;
+            while let Some((position, last, chunk)) = opened.next_chunk()? {
+                sealed.write_all(&seal_chunk(&stream, position, last, &chunk)?)?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+fn seal_chunk(
+    stream: &StreamBE32<Aes256Gcm>,
+    position: u32,
+    last: bool,
+    chunk: &[u8],
+) -> Result<Vec<u8>> {
+    stream
+        .encrypt(position, last, chunk)
+        .map_err(|_e| anyhow!("encryption failed"))
+}
+
+// Reads a stream in fixed size chunks. STREAM seals the last chunk differently, so that a file
+// can't be cut short without us noticing, so we read one chunk ahead to know which is the last.
+struct Chunks<R> {
+    reader: R,
+    size: usize,
+    next: Vec<u8>,
+}
+
+impl<R: Read> Chunks<R> {
+    fn new(mut reader: R, size: usize) -> io::Result<Self> {
+        let next = read_chunk(&mut reader, size)?;
+        Ok(Self { reader, size, next })
+    }
+
+    // Returns the next chunk, and whether it is the last.
+    fn next_chunk(&mut self) -> io::Result<(Vec<u8>, bool)> {
+        let following = read_chunk(&mut self.reader, self.size)?;
+        let chunk = mem::replace(&mut self.next, following);
+        Ok((chunk, self.next.is_empty()))
+    }
+}
+
+fn read_chunk(reader: &mut impl Read, size: usize) -> io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(size);
+    reader.take(size as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+// A sealed file being read back, a chunk at a time. Each chunk is authenticated on its own, so
+// the first one tells us which key the file was sealed with.
+struct OpenStream<R> {
+    chunks: Chunks<R>,
+    stream: StreamBE32<Aes256Gcm>,
+    retiring: bool,
+    first: Option<Vec<u8>>,
+    position: u32,
+    last: bool,
+}
+
+impl<R: Read> OpenStream<R> {
+    fn new(keys: &DataKeys, mut sealed: R) -> Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        sealed
+            .read_exact(&mut header)
+            .context("not an encrypted Artchiver file")?;
+        let nonce = header
+            .strip_prefix(FILE_MAGIC)
+            .context("not an encrypted Artchiver file")?;
+        let mut chunks = Chunks::new(sealed, CHUNK_LEN + TAG_LEN)?;
+        let (first, last) = chunks.next_chunk()?;
+        let (stream, retiring, first) = iter::once((&keys.current, false))
+            .chain(keys.retiring.iter().map(|key| (key, true)))
+            .find_map(|(key, retiring)| {
+                let stream = key.stream(nonce);
+                let plain = stream.decrypt(0, last, first.as_slice()).ok()?;
+                Some((stream, retiring, plain))
+            })
+            .context("the file was not encrypted with our key, or has been damaged")?;
+        Ok(Self {
+            chunks,
+            stream,
+            retiring,
+            first: Some(first),
+            position: 0,
+            last,
+        })
+    }
+
+    // Returns the next chunk of plain text, with its position and whether it is the last.
+    fn next_chunk(&mut self) -> Result<Option<(u32, bool, Vec<u8>)>> {
+        if let Some(first) = self.first.take() {
+            return Ok(Some((0, self.last, first)));
+        }
+        if self.last {
+            return Ok(None);
+        }
+        self.position = self
+            .position
+            .checked_add(1)
+            .context("the file has been damaged")?;
+        let (chunk, last) = self.chunks.next_chunk()?;
+        self.last = last;
+        let plain = self
+            .stream
+            .decrypt(self.position, last, chunk.as_slice())
+            .map_err(|_e| anyhow!("the file has been damaged"))?;
+        Ok(Some((self.position, last, plain)))
+    }
+}
+
+fn key_connection(conn: &Connection, key: &DataKey) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "key", key.sqlcipher_key().as_str())
+}
+
+// Write to `<dest>.sealing` and only swap it in once it is complete, so that an interrupted write
+// never leaves a damaged file behind.
+fn write_atomic<T>(
+    dest: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<T>,
+) -> Result<T> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".sealing");
+    let tmp = PathBuf::from(tmp);
+    let written = File::create(&tmp)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| {
+            let mut out = BufWriter::new(file);
+            let value = write(&mut out)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            Ok(value)
+        })
+        .with_context(|| format!("writing {}", tmp.display()));
+    match written {
+        Ok(value) => {
+            fs::rename(&tmp, dest)?;
+            Ok(value)
+        }
+        Err(e) => {
+            fs::remove_file(&tmp).ok();
+            Err(e)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct KdfParams {
+    salt: String,
+    m_cost_kib: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl KdfParams {
+    fn new() -> Self {
+        Self {
+            salt: to_hex(&rand::random::<[u8; 16]>()),
+            m_cost_kib: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<DataKey> {
+        let params = Params::new(self.m_cost_kib, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| anyhow!("invalid key derivation parameters: {e}"))?;
+        let mut key = DataKey([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &from_hex(&self.salt)?, &mut key.0)
+            .map_err(|e| anyhow!("failed to derive a key from the passphrase: {e}"))?;
+        Ok(key)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    kdf: KdfParams,
+    // The data key, sealed with the passphrase key.
+    wrapped_key: String,
+    // The previous data key, while a rotation is under way.
+    #[serde(default)]
+    wrapped_retiring_key: Option<String>,
+}
+
+impl EncryptionConfig {
+    pub fn is_enabled(data_dir: &Path) -> bool {
+        data_dir.join(CONFIG_FILE_NAME).exists()
+    }
+
+    fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(CONFIG_FILE_NAME);
+        let raw =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    fn save(&self, data_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(&data_dir.join(CONFIG_FILE_NAME), |out| {
+            Ok(out.write_all(json.as_bytes())?)
+        })
+    }
+
+    fn wrap(passphrase: &str, keys: &DataKeys) -> Result<Self> {
+        let kdf = KdfParams::new();
+        let wrapping = kdf.derive(passphrase)?;
+        Ok(Self {
+            wrapped_key: to_hex(&wrapping.seal(&keys.current.0)?),
+            wrapped_retiring_key: keys
+                .retiring
+                .as_ref()
+                .map(|key| wrapping.seal(&key.0).map(|sealed| to_hex(&sealed)))
+                .transpose()?,
+            kdf,
+        })
+    }
+
+    fn unwrap_keys(&self, passphrase: &str) -> Result<DataKeys> {
+        let wrapping = self.kdf.derive(passphrase)?;
+        let unwrap = |wrapped: &str| -> Result<DataKey> {
+            let plain = wrapping
+                .open(&from_hex(wrapped)?)
+                .context("the passphrase is not correct")?;
+            ensure!(plain.len() == 32, "the stored key is the wrong size");
+            let mut key = DataKey([0u8; 32]);
+            key.0.copy_from_slice(&plain);
+            Ok(key)
+        };
+        Ok(DataKeys {
+            current: unwrap(&self.wrapped_key)?,
+            retiring: self
+                .wrapped_retiring_key
+                .as_deref()
+                .map(unwrap)
+                .transpose()?,
+        })
+    }
+}
+
+// Turn on encryption for an archive that doesn't have it yet.
+pub fn set_up(data_dir: &Path, db_path: &Path, passphrase: &str) -> Result<()> {
+    ensure!(
+        !EncryptionConfig::is_enabled(data_dir),
+        "this archive is already encrypted"
+    );
+    ensure!(
+        !is_read_only(),
+        "can't encrypt an archive that another instance has open"
+    );
+    ensure!(
+        passphrase.chars().count() >= MIN_PASSPHRASE_LEN,
+        "please use a passphrase of at least {MIN_PASSPHRASE_LEN} characters"
+    );
+    let keys = DataKeys {
+        current: DataKey::random(),
+        retiring: None,
+    };
+    if db_path.exists() {
+        info!("Encrypting the metadata DB");
+        export_database(db_path, None, &keys.current)?;
+    }
+    EncryptionConfig::wrap(passphrase, &keys)?.save(data_dir)?;
+    set_unlocked_keys(keys);
+    Ok(())
+}
+
+// What the user asked for at the unlock prompt.
+#[derive(Clone, Debug, Default)]
+pub struct UnlockRequest {
+    pub passphrase: String,
+    pub new_passphrase: Option<String>,
+    pub rotate_key: bool,
+}
+
+pub fn unlock(
+    data_dir: &Path,
+    db_path: &Path,
+    encrypted_roots: &[PathBuf],
+    request: &UnlockRequest,
+) -> Result<()> {
+    let config = EncryptionConfig::load(data_dir)?;
+    let mut keys = config.unwrap_keys(&request.passphrase)?;
+    ensure!(
+        !is_read_only() || !(request.rotate_key || request.new_passphrase.is_some()),
+        "can't change the passphrase or rotate the key while another instance has the archive open"
+    );
+    let passphrase = match &request.new_passphrase {
+        Some(new) => {
+            ensure!(
+                new.chars().count() >= MIN_PASSPHRASE_LEN,
+                "please use a passphrase of at least {MIN_PASSPHRASE_LEN} characters"
+            );
+            new.as_str()
+        }
+        None => request.passphrase.as_str(),
+    };
+
+    if request.rotate_key && keys.retiring.is_none() {
+        keys = DataKeys {
+            current: DataKey::random(),
+            retiring: Some(keys.current),
+        };
+        // Save both keys before touching anything, so that nothing is lost if we stop halfway.
+        EncryptionConfig::wrap(passphrase, &keys)?.save(data_dir)?;
+    }
+    if let Some(retiring) = keys.retiring.clone()
+        && !is_read_only()
+    {
+        info!("Rotating the data key");
+        // Note: the DB may already be on the new key, if we stopped partway through the roots.
+        if db_path.exists() && !opens_with(db_path, &keys.current) {
+            export_database(db_path, Some(&retiring), &keys.current)?;
+        }
+        let mut skipped = 0;
+        for root in encrypted_roots {
+            skipped += reencrypt_dir(root, &keys)?;
+        }
+        keys.retiring = None;
+        EncryptionConfig::wrap(passphrase, &keys)?.save(data_dir)?;
+        info!("Finished rotating the data key; skipped {skipped} files that were not sealed by us");
+    } else if request.new_passphrase.is_some() {
+        EncryptionConfig::wrap(passphrase, &keys)?.save(data_dir)?;
+        info!("Changed the passphrase");
+    }
+
+    set_unlocked_keys(keys);
+    Ok(())
+}
+
+fn opens_with(db_path: &Path, key: &DataKey) -> bool {
+    Connection::open(db_path)
+        .and_then(|conn| {
+            key_connection(&conn, key)?;
+            conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        })
+        .is_ok()
+}
+
+// Write a copy of the DB under a new key (or for the first time, from plain text) and swap it in.
+fn export_database(db_path: &Path, from: Option<&DataKey>, to: &DataKey) -> Result<()> {
+    let tmp = db_path.with_extension("rekeying");
+    if tmp.exists() {
+        fs::remove_file(&tmp)?;
+    }
+    {
+        let conn = Connection::open(db_path)?;
+        if let Some(key) = from {
+            key_connection(&conn, key)?;
+        }
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let tmp_str = tmp
+            .to_str()
+            .context("the data dir path is not valid UTF-8")?;
+        conn.execute(
+            "ATTACH DATABASE ? AS rekeyed KEY ?",
+            params![tmp_str, to.sqlcipher_key().as_str()],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))?;
+        conn.execute("DETACH DATABASE rekeyed", [])?;
+    }
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        fs::remove_file(PathBuf::from(path)).ok();
+    }
+    fs::rename(&tmp, db_path)?;
+    Ok(())
+}
+
+// Re-seal every file that is still under the retiring key. Returns how many files were skipped
+// as they are not sealed with either key, e.g. ones that were copied onto the root by hand: we
+// can't read those anyway, so they don't need to hold up the rotation.
+fn reencrypt_dir(dir: &Path, keys: &DataKeys) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut skipped = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            skipped += reencrypt_dir(&path, keys)?;
+            continue;
+        }
+        // Note: left over from an interrupted write; the file it was replacing is still there.
+        if path.extension().is_some_and(|ext| ext == "sealing") {
+            continue;
+        }
+        let opened = match OpenStream::new(keys, BufReader::new(File::open(&path)?)) {
+            Ok(opened) => opened,
+            Err(e) => {
+                warn!("Not re-encrypting {}: {e:#}", path.display());
+                skipped += 1;
+                continue;
+            }
+        };
+        if opened.retiring {
+            keys.reseal_file(opened, &path)
+                .with_context(|| format!("re-encrypting {}", path.display()))?;
+        }
+    }
+    Ok(skipped)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    ensure!(hex.len().is_multiple_of(2), "invalid hex string");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .context("invalid hex string")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal(keys: &DataKeys, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut sealed = Vec::new();
+        keys.encrypt(plain, &mut sealed)?;
+        Ok(sealed)
+    }
+
+    fn open(keys: &DataKeys, sealed: &[u8]) -> Result<(Vec<u8>, bool)> {
+        let mut plain = Vec::new();
+        let retiring = keys.decrypt(sealed, &mut plain)?;
+        Ok((plain, retiring))
+    }
+
+    #[test]
+    fn test_seal_and_open() -> Result<()> {
+        let old = DataKey::random();
+        let keys = DataKeys {
+            current: DataKey::random(),
+            retiring: Some(old.clone()),
+        };
+        for len in [0, 1, CHUNK_LEN, 2 * CHUNK_LEN + 5] {
+            let plain = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            let sealed = seal(&keys, &plain)?;
+            assert_eq!(
+                sealed.len(),
+                HEADER_LEN + len + len.div_ceil(CHUNK_LEN).max(1) * TAG_LEN
+            );
+            assert_eq!(open(&keys, &sealed)?, (plain, false));
+        }
+
+        let legacy = seal(
+            &DataKeys {
+                current: old,
+                retiring: None,
+            },
+            b"Nighthawks",
+        )?;
+        assert_eq!(open(&keys, &legacy)?, (b"Nighthawks".to_vec(), true));
+
+        let mut damaged = seal(&keys, b"Nighthawks")?;
+        if let Some(last) = damaged.last_mut() {
+            *last ^= 1;
+        }
+        assert!(open(&keys, &damaged).is_err());
+        assert!(open(&keys, b"Nighthawks").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_file() -> Result<()> {
+        let keys = DataKeys {
+            current: DataKey::random(),
+            retiring: None,
+        };
+        let sealed = seal(&keys, &vec![7u8; 2 * CHUNK_LEN])?;
+        // Dropping whole chunks from the end must not go unnoticed.
+        let truncated = &sealed[..HEADER_LEN + CHUNK_LEN + TAG_LEN];
+        assert!(open(&keys, truncated).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reencrypt_dir() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("artchiver-reseal-{}", rand::random::<u32>()));
+        let old = DataKeys {
+            current: DataKey::random(),
+            retiring: None,
+        };
+        let keys = DataKeys {
+            current: DataKey::random(),
+            retiring: Some(old.current.clone()),
+        };
+        fs::create_dir_all(dir.join("nested"))?;
+        fs::write(dir.join("nested/work.jpg"), seal(&old, b"Nighthawks")?)?;
+        fs::write(dir.join("notes.txt"), b"not sealed")?;
+
+        assert_eq!(reencrypt_dir(&dir, &keys)?, 1);
+        let resealed = fs::read(dir.join("nested/work.jpg"))?;
+        assert_eq!(open(&keys, &resealed)?, (b"Nighthawks".to_vec(), false));
+        assert_eq!(fs::read(dir.join("notes.txt"))?, b"not sealed");
+        assert!(!dir.join("nested/work.jpg.sealing").exists());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapped_keys() -> Result<()> {
+        let keys = DataKeys {
+            current: DataKey::random(),
+            retiring: None,
+        };
+        let config = EncryptionConfig::wrap("correct horse", &keys)?;
+        assert_eq!(config.unwrap_keys("correct horse")?.current, keys.current);
+        assert!(config.unwrap_keys("wrong horse").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex() -> Result<()> {
+        assert_eq!(from_hex(&to_hex(&[0, 15, 255]))?, vec![0, 15, 255]);
+        assert!(from_hex("abc").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+// Enriching works from Wikidata: for works that a plugin gave a Wikidata id, we ask the Wikidata
+// Query Service what movement and genre the work belongs to, what it depicts, who made it, and
+// what other works it is linked to.
+//
+// Movements, genres, and depictions become tags, and creators become artists, all marked as
+// enriched, so that the user can take every one of them off again at once if they aren't wanted.
+use anyhow::{Result, bail};
+use artchiver_sdk::{Authority, TagKind};
+use jiff::civil::Date;
+use serde::Deserialize;
+use std::{collections::HashMap, time::Duration};
+use ureq::Agent;
+
+const SPARQL_URL: &str = "https://query.wikidata.org/sparql";
+// Items per query; the service answers a few dozen at once comfortably.
+pub const BATCH_SIZE: usize = 25;
+// The service asks that bots not hammer it; one query at a time, with a pause between.
+pub const QUERY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Statement {
+    Movement,
+    Genre,
+    Depicts,
+    Creator,
+    // Works that the work is based on, a pendant of, or part of.
+    Linked,
+}
+
+impl Statement {
+    pub const ALL: [Self; 5] = [
+        Self::Movement,
+        Self::Genre,
+        Self::Depicts,
+        Self::Creator,
+        Self::Linked,
+    ];
+    pub const TAGGED: [Self; 3] = [Self::Movement, Self::Genre, Self::Depicts];
+
+    // How this is stored in the statement column, and asked for in the query.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Movement => "movement",
+            Self::Genre => "genre",
+            Self::Depicts => "depicts",
+            Self::Creator => "creator",
+            Self::Linked => "linked",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    // The kind of tag that the statement's values become, if they become tags at all.
+    pub fn tag_kind(self) -> Option<TagKind> {
+        match self {
+            Self::Movement => Some(TagKind::Style),
+            Self::Genre => Some(TagKind::Theme),
+            Self::Depicts => Some(TagKind::Default),
+            Self::Creator | Self::Linked => None,
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Self::Movement => "wdt:P135",
+            Self::Genre => "wdt:P136",
+            Self::Depicts => "wdt:P180",
+            Self::Creator => "wdt:P170",
+            Self::Linked => "wdt:P144|wdt:P1639|wdt:P361",
+        }
+    }
+}
+
+// One statement about a work: e.g. that it depicts Q7569 (child), labeled "child".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enrichment {
+    pub statement: Statement,
+    pub value: String,
+    pub label: String,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EnrichedArtist {
+    pub wikidata_id: String,
+    pub name: String,
+    pub birthday: Option<Date>,
+    pub deathday: Option<Date>,
+    pub nationality: Option<String>,
+    pub bio: Option<String>,
+}
+
+// What Wikidata told us about one of our works.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EnrichedWork {
+    pub screen_url: String,
+    pub enrichments: Vec<Enrichment>,
+    pub artists: Vec<EnrichedArtist>,
+}
+
+// What we found about each Wikidata item that we asked about, by its id.
+pub type ItemEnrichments = HashMap<String, (Vec<Enrichment>, Vec<EnrichedArtist>)>;
+
+#[derive(Deserialize)]
+struct SparqlResponse {
+    results: SparqlResults,
+}
+
+#[derive(Deserialize)]
+struct SparqlResults {
+    bindings: Vec<HashMap<String, SparqlValue>>,
+}
+
+#[derive(Deserialize)]
+struct SparqlValue {
+    value: String,
+}
+
+// Everything we want to know about the items, one row per statement value.
+// Note: ids that aren't Wikidata's are left out, as one would make the whole query fail.
+pub fn sparql_query(items: &[&str]) -> String {
+    let values = items
+        .iter()
+        .filter(|id| Authority::Wikidata.is_valid_id(id))
+        .map(|id| format!("wd:{id}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let unions = Statement::ALL
+        .map(|statement| {
+            let details = if statement == Statement::Creator {
+                r#"
+        OPTIONAL { ?value wdt:P569 ?born. }
+        OPTIONAL { ?value wdt:P570 ?died. }
+        OPTIONAL { ?value wdt:P27 ?country. }"#
+            } else {
+                ""
+            };
+            format!(
+                "{{ ?item {} ?value. BIND(\"{}\" AS ?statement){details}\n    }}",
+                statement.path(),
+                statement.name()
+            )
+        })
+        .join("\n    UNION ");
+    format!(
+        r#"SELECT ?item ?statement ?value ?valueLabel ?valueDescription ?born ?died ?countryLabel WHERE {{
+    VALUES ?item {{ {values} }}
+    {unions}
+    SERVICE wikibase:label {{ bd:serviceParam wikibase:language "en". }}
+}}"#
+    )
+}
+
+fn wikidata_id(uri: &str) -> Option<String> {
+    match Authority::from_uri(uri)? {
+        (Authority::Wikidata, id) => Some(id),
+        _ => None,
+    }
+}
+
+// Note: Wikidata dates are timestamps, e.g. "1853-03-30T00:00:00Z"; ancient ones don't parse as
+//       civil dates and are dropped.
+fn parse_date(value: &str) -> Option<Date> {
+    value.get(..10)?.parse().ok()
+}
+
+// The statements for each item in a query's results, by the item's id.
+pub fn parse_results(json: &str) -> Result<ItemEnrichments> {
+    let response = serde_json::from_str::<SparqlResponse>(json)?;
+    let mut out = ItemEnrichments::new();
+    for row in response.results.bindings {
+        let get = |name: &str| row.get(name).map(|v| v.value.as_str());
+        let (Some(item), Some(statement), Some(value)) = (
+            get("item").and_then(wikidata_id),
+            get("statement").and_then(Statement::from_name),
+            get("value").and_then(wikidata_id),
+        ) else {
+            continue;
+        };
+        // Note: the label service falls back to the id when there is no English label, which
+        //       makes for a useless tag.
+        let Some(label) = get("valueLabel").filter(|label| *label != value) else {
+            continue;
+        };
+        let (enrichments, artists) = out.entry(item).or_default();
+        // Note: an artist with two citizenships comes back as two rows; the first one wins.
+        if !enrichments
+            .iter()
+            .any(|e| e.statement == statement && e.value == value)
+        {
+            enrichments.push(Enrichment {
+                statement,
+                value: value.clone(),
+                label: label.to_owned(),
+            });
+        }
+        if statement == Statement::Creator && !artists.iter().any(|a| a.wikidata_id == value) {
+            artists.push(EnrichedArtist {
+                wikidata_id: value,
+                name: label.to_owned(),
+                birthday: get("born").and_then(parse_date),
+                deathday: get("died").and_then(parse_date),
+                nationality: get("countryLabel").map(str::to_owned),
+                bio: get("valueDescription").map(str::to_owned),
+            });
+        }
+    }
+    Ok(out)
+}
+
+pub fn make_agent() -> Agent {
+    Agent::new_with_config(
+        Agent::config_builder()
+            .user_agent(format!("Artchiver/{}", env!("CARGO_PKG_VERSION")))
+            .http_status_as_error(false)
+            .timeout_global(Some(Duration::from_secs(60)))
+            .build(),
+    )
+}
+
+pub fn query_items(agent: &Agent, items: &[&str]) -> Result<ItemEnrichments> {
+    let mut response = agent
+        .get(SPARQL_URL)
+        .query("query", sparql_query(items))
+        .header("Accept", "application/sparql-results+json")
+        .call()?;
+    if !response.status().is_success() {
+        bail!("the Wikidata Query Service said {}", response.status());
+    }
+    parse_results(&response.body_mut().read_to_string()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() -> Result<()> {
+        let json = r#"{"results": {"bindings": [
+            {"item": {"type": "uri", "value": "http://www.wikidata.org/entity/Q12418"},
+             "statement": {"type": "literal", "value": "movement"},
+             "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q4692"},
+             "valueLabel": {"type": "literal", "value": "Renaissance"}},
+            {"item": {"type": "uri", "value": "http://www.wikidata.org/entity/Q12418"},
+             "statement": {"type": "literal", "value": "depicts"},
+             "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q99999999"},
+             "valueLabel": {"type": "literal", "value": "Q99999999"}},
+            {"item": {"type": "uri", "value": "http://www.wikidata.org/entity/Q12418"},
+             "statement": {"type": "literal", "value": "creator"},
+             "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q762"},
+             "valueLabel": {"type": "literal", "value": "Leonardo da Vinci"},
+             "valueDescription": {"type": "literal", "value": "Italian Renaissance polymath"},
+             "born": {"type": "literal", "value": "1452-04-15T00:00:00Z"},
+             "died": {"type": "literal", "value": "1519-05-02T00:00:00Z"},
+             "countryLabel": {"type": "literal", "value": "Republic of Florence"}},
+            {"item": {"type": "uri", "value": "http://www.wikidata.org/entity/Q12418"},
+             "statement": {"type": "literal", "value": "creator"},
+             "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q762"},
+             "valueLabel": {"type": "literal", "value": "Leonardo da Vinci"},
+             "countryLabel": {"type": "literal", "value": "Duchy of Milan"}}
+        ]}}"#;
+        let found = parse_results(json)?;
+        let (enrichments, artists) = &found["Q12418"];
+        assert_eq!(enrichments.len(), 2);
+        assert_eq!(enrichments[0].statement, Statement::Movement);
+        assert_eq!(enrichments[0].label, "Renaissance");
+        assert_eq!(enrichments[1].statement, Statement::Creator);
+        assert_eq!(artists.len(), 1);
+        assert_eq!(artists[0].birthday, Some(Date::constant(1452, 4, 15)));
+        assert_eq!(
+            artists[0].nationality.as_deref(),
+            Some("Republic of Florence")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparql_query() {
+        let query = sparql_query(&[
+            "Q1",
+            "Q2",
+            "Q3?uselang=en",
+            "https://www.wikidata.org/wiki/Q4",
+        ]);
+        assert!(query.contains("VALUES ?item { wd:Q1 wd:Q2 }"));
+        for statement in Statement::ALL {
+            assert!(query.contains(statement.path()));
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use crate::shared::{
+    profile::{is_portable, profile_root},
+    storage::Storage,
+};
 use anyhow::Result;
 use log::info;
 use platform_dirs::AppDirs;
@@ -9,21 +13,30 @@ use std::{
 #[derive(Debug)]
 pub struct Environment {
     prefix: PathBuf,
+    // The prefix for the default profile, or the named profile's folder under it.
+    root: PathBuf,
     app_dirs: AppDirs,
+    storage: Storage,
 }
 
 impl Environment {
     pub fn new(prefix: &Path) -> Result<Self> {
+        let root = profile_root(prefix);
+        fs::create_dir_all(&root)?;
         let env = Self {
             prefix: prefix.to_owned(),
             app_dirs: AppDirs::new(Some("artchiver"), false).expect("Failed to create AppDirs"),
+            storage: Storage::load(&root)?,
+            root,
         };
 
-        info!(
-            "Global plugin directory: {}",
-            env.global_plugin_dir().display()
-        );
-        fs::create_dir_all(env.global_plugin_dir())?;
+        if !is_portable() {
+            info!(
+                "Global plugin directory: {}",
+                env.global_plugin_dir().display()
+            );
+            fs::create_dir_all(env.global_plugin_dir())?;
+        }
         info!(
             "Local plugin directory: {}",
             env.local_plugin_dir().display()
@@ -36,13 +49,19 @@ impl Environment {
         info!("Temp directory: {}", env.tmp_dir().display());
         fs::create_dir_all(env.tmp_dir())?;
 
+        Ok(env)
+    }
+
+    // Throw out whatever the last run left lying around. Only safe once we hold the instance
+    // lock, as otherwise the files may belong to an instance that is still running.
+    pub fn clean_up_after_last_run(&self) -> Result<()> {
         info!("Clearing temp directory...");
-        for entry in fs::read_dir(env.tmp_dir())? {
+        for entry in fs::read_dir(self.tmp_dir())? {
             let entry = entry?;
             fs::remove_file(entry.path())?;
         }
-
-        Ok(env)
+        self.storage.clear_decrypted();
+        Ok(())
     }
 
     pub fn prefix(&self) -> &Path {
@@ -50,11 +69,15 @@ impl Environment {
     }
 
     pub fn data_dir(&self) -> PathBuf {
-        self.prefix.join("data")
+        self.storage.data_dir()
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
     }
 
     pub fn cache_dir(&self) -> PathBuf {
-        self.prefix.join("cache")
+        self.root.join("cache")
     }
 
     pub fn tmp_dir(&self) -> PathBuf {
@@ -79,4 +102,13 @@ impl Environment {
     pub fn local_plugin_dir(&self) -> PathBuf {
         self.prefix.join("plugins")
     }
+
+    // Note: a portable install only trusts what it carries with it.
+    pub fn plugin_dirs(&self) -> Vec<PathBuf> {
+        if is_portable() {
+            vec![self.local_plugin_dir()]
+        } else {
+            vec![self.global_plugin_dir(), self.local_plugin_dir()]
+        }
+    }
 }
@@ -0,0 +1,200 @@
+// Write an exhibition out as a single static web page, with the images copied alongside it, so
+// that it can be put up anywhere or opened straight from a thumb drive. Presenter notes are for
+// the presenter, so they stay behind.
+use anyhow::{Context as _, Result, ensure};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug)]
+pub enum ExhibitEntry {
+    Section {
+        title: String,
+        caption: String,
+    },
+    Work {
+        image: Option<PathBuf>,
+        title: String,
+        artist: Option<String>,
+        date: String,
+        caption: String,
+        // An artchiver:// link back to the work, for the title.
+        link: Option<String>,
+    },
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Captions are typed as plain text; keep the user's paragraphs.
+fn caption_html(caption: &str) -> String {
+    caption
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|para| !para.is_empty())
+        .map(|para| format!("<p>{}</p>", escape_html(para).replace('\n', "<br>")))
+        .collect()
+}
+
+const STYLE: &str = r#"
+body { background: #111; color: #ddd; font-family: Georgia, serif; margin: 0 auto; max-width: 60em; padding: 2em; }
+h1, h2 { font-weight: normal; }
+section { margin: 6em 0 3em; text-align: center; }
+figure { margin: 4em 0; text-align: center; }
+figure img { max-width: 100%; max-height: 90vh; }
+figcaption { margin-top: 1em; }
+.byline { color: #999; font-style: italic; }
+a { color: inherit; }
+"#;
+
+// `images` maps each entry to the file name its image was copied to, if it has one.
+fn render_html(name: &str, entries: &[ExhibitEntry], images: &[Option<String>]) -> String {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>").ok();
+    writeln!(html, "<html><head><meta charset=\"utf-8\">").ok();
+    writeln!(html, "<title>{}</title>", escape_html(name)).ok();
+    writeln!(html, "<style>{STYLE}</style></head><body>").ok();
+    writeln!(html, "<h1>{}</h1>", escape_html(name)).ok();
+    for (entry, image) in entries.iter().zip(images) {
+        match entry {
+            ExhibitEntry::Section { title, caption } => {
+                writeln!(
+                    html,
+                    "<section><h2>{}</h2>{}</section>",
+                    escape_html(title),
+                    caption_html(caption)
+                )
+                .ok();
+            }
+            ExhibitEntry::Work {
+                title,
+                artist,
+                date,
+                caption,
+                link,
+                ..
+            } => {
+                writeln!(html, "<figure>").ok();
+                if let Some(image) = image {
+                    writeln!(
+                        html,
+                        "<img src=\"images/{}\" alt=\"{}\" loading=\"lazy\">",
+                        escape_html(image),
+                        escape_html(title)
+                    )
+                    .ok();
+                }
+                let byline = [artist.as_deref().unwrap_or_default(), date.as_str()]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .map(escape_html)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let title = match link {
+                    Some(link) => format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape_html(link),
+                        escape_html(title)
+                    ),
+                    None => escape_html(title),
+                };
+                writeln!(
+                    html,
+                    "<figcaption><strong>{title}</strong><div class=\"byline\">{byline}</div>{}</figcaption>",
+                    caption_html(caption)
+                )
+                .ok();
+                writeln!(html, "</figure>").ok();
+            }
+        }
+    }
+    writeln!(html, "</body></html>").ok();
+    html
+}
+
+// Returns the number of works written.
+pub fn write_exhibition_html(name: &str, entries: &[ExhibitEntry], out: &Path) -> Result<usize> {
+    ensure!(!entries.is_empty(), "the exhibition is empty");
+    let image_dir = out.join("images");
+    fs::create_dir_all(&image_dir).with_context(|| format!("creating {}", image_dir.display()))?;
+
+    // Note: numbered by position, so that two works with the same file name can't collide.
+    let mut images = Vec::with_capacity(entries.len());
+    let mut works = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        let ExhibitEntry::Work { image, .. } = entry else {
+            images.push(None);
+            continue;
+        };
+        works += 1;
+        let Some(image) = image else {
+            images.push(None);
+            continue;
+        };
+        let ext = image
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg")
+            .to_ascii_lowercase();
+        let file_name = format!("{:03}.{ext}", i + 1);
+        fs::copy(image, image_dir.join(&file_name))
+            .with_context(|| format!("copying {}", image.display()))?;
+        images.push(Some(file_name));
+    }
+
+    fs::write(out.join("index.html"), render_html(name, entries, &images))?;
+    Ok(works)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("Nighthawks"), "Nighthawks");
+        assert_eq!(
+            escape_html("<b>\"Tom & Jerry's\"</b>"),
+            "&lt;b&gt;&quot;Tom &amp; Jerry&#39;s&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let entries = vec![
+            ExhibitEntry::Section {
+                title: "Night & Day".to_owned(),
+                caption: "First line\nsecond line\n\nNew paragraph".to_owned(),
+            },
+            ExhibitEntry::Work {
+                image: Some(PathBuf::from("/data/screen/nighthawks.jpg")),
+                title: "Nighthawks".to_owned(),
+                artist: Some("Edward Hopper".to_owned()),
+                date: "1942".to_owned(),
+                caption: String::new(),
+                link: Some("artchiver://work?url=nighthawks".to_owned()),
+            },
+        ];
+        let html = render_html("<Hopper>", &entries, &[None, Some("002.jpg".to_owned())]);
+        assert!(html.contains("<title>&lt;Hopper&gt;</title>"));
+        assert!(html.contains("<h2>Night &amp; Day</h2>"));
+        assert!(html.contains("<p>First line<br>second line</p><p>New paragraph</p>"));
+        assert!(html.contains("<img src=\"images/002.jpg\" alt=\"Nighthawks\""));
+        assert!(html.contains("<div class=\"byline\">Edward Hopper, 1942</div>"));
+        assert!(html.contains("<a href=\"artchiver://work?url=nighthawks\">Nighthawks</a>"));
+    }
+}
@@ -0,0 +1,201 @@
+// Copy works out of the archive to a folder of the user's choosing, optionally shrinking and
+// re-encoding images on the way, e.g. to put a smaller set on a tablet. The archive itself is
+// never touched.
+use crate::plugin::thumbnail::{is_image, open_image};
+use anyhow::{Context as _, Result};
+use image::{
+    DynamicImage, ImageFormat,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+};
+use parking_lot::Mutex;
+use rayon::{
+    ThreadPoolBuilder,
+    iter::{IntoParallelRefIterator as _, ParallelIterator as _},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Original,
+    Jpeg,
+    WebP,
+    Avif,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 4] = [Self::Original, Self::Jpeg, Self::WebP, Self::Avif];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Original => "Keep Original",
+            Self::Jpeg => "JPEG",
+            Self::WebP => "WebP (lossless)",
+            Self::Avif => "AVIF",
+        }
+    }
+
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Original => None,
+            Self::Jpeg => Some("jpg"),
+            Self::WebP => Some("webp"),
+            Self::Avif => Some("avif"),
+        }
+    }
+
+    pub fn has_quality(self) -> bool {
+        matches!(self, Self::Jpeg | Self::Avif)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    // The longest side of exported images, in pixels; 0 leaves them at full size.
+    pub max_dimension: u32,
+    pub quality: u8,
+    pub workers: usize,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Original,
+            max_dimension: 0,
+            quality: 85,
+            workers: thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+}
+
+impl ExportSettings {
+    fn needs_decode(&self) -> bool {
+        self.format != ExportFormat::Original || self.max_dimension > 0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportReport {
+    pub exported: usize,
+    pub failed: Vec<String>,
+}
+
+// Where a file ends up in the export folder, given the format we are writing it as.
+fn target_path(source: &Path, dir: &Path, format: ExportFormat) -> PathBuf {
+    let name = source.file_name().unwrap_or(source.as_os_str());
+    let target = dir.join(name);
+    match format.extension() {
+        Some(ext) if is_image(source) => target.with_extension(ext),
+        _ => target,
+    }
+}
+
+fn shrink(img: DynamicImage, max_dimension: u32) -> DynamicImage {
+    if max_dimension == 0 || img.width().max(img.height()) <= max_dimension {
+        return img;
+    }
+    img.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
+
+fn export_one(source: &Path, dir: &Path, settings: &ExportSettings) -> Result<()> {
+    let target = target_path(source, dir, settings.format);
+    // Note: videos, songs, and anything else we can't decode are copied as-is.
+    if !is_image(source) || !settings.needs_decode() {
+        fs::copy(source, &target)?;
+        return Ok(());
+    }
+
+    let img = shrink(open_image(source)?, settings.max_dimension);
+    let quality = settings.quality.clamp(1, 100);
+    let writer =
+        || -> Result<BufWriter<fs::File>> { Ok(BufWriter::new(fs::File::create(&target)?)) };
+    match settings.format {
+        ExportFormat::Original => {
+            img.save_with_format(&target, ImageFormat::from_path(source)?)?;
+        }
+        ExportFormat::Jpeg => {
+            // Note: JPEG has no alpha channel.
+            img.to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(writer()?, quality))?;
+        }
+        ExportFormat::WebP => {
+            img.to_rgba8()
+                .write_with_encoder(WebPEncoder::new_lossless(writer()?))?;
+        }
+        ExportFormat::Avif => {
+            img.to_rgba8()
+                .write_with_encoder(AvifEncoder::new_with_speed_quality(writer()?, 6, quality))?;
+        }
+    }
+    Ok(())
+}
+
+// Export `sources` into `dir` on a pool of `settings.workers` threads, counting finished files
+// in `done` as we go so that the UX can show progress.
+pub fn export_works(
+    sources: &[PathBuf],
+    dir: &Path,
+    settings: &ExportSettings,
+    done: &AtomicUsize,
+) -> Result<ExportReport> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(settings.workers.max(1))
+        .thread_name(|i| format!("export-{i}"))
+        .build()?;
+    let failed = Mutex::new(Vec::new());
+    pool.install(|| {
+        sources.par_iter().for_each(|source| {
+            if let Err(e) = export_one(source, dir, settings) {
+                failed.lock().push(format!("{}: {e}", source.display()));
+            }
+            done.fetch_add(1, Ordering::Relaxed);
+        });
+    });
+    let failed = failed.into_inner();
+    Ok(ExportReport {
+        exported: sources.len() - failed.len(),
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_path() {
+        let dir = Path::new("/out");
+        assert_eq!(
+            target_path(Path::new("/a/b/work.png"), dir, ExportFormat::WebP),
+            PathBuf::from("/out/work.webp")
+        );
+        assert_eq!(
+            target_path(Path::new("/a/b/work.png"), dir, ExportFormat::Original),
+            PathBuf::from("/out/work.png")
+        );
+        assert_eq!(
+            target_path(Path::new("/a/b/clip.mp4"), dir, ExportFormat::Jpeg),
+            PathBuf::from("/out/clip.mp4")
+        );
+    }
+
+    #[test]
+    fn test_shrink() {
+        let img = DynamicImage::new_rgb8(4000, 1000);
+        let small = shrink(img.clone(), 2048);
+        assert_eq!((small.width(), small.height()), (2048, 512));
+        let same = shrink(img, 0);
+        assert_eq!((same.width(), same.height()), (4000, 1000));
+    }
+}
@@ -0,0 +1,130 @@
+// Hand works off to other programs: the desktop's default viewer, an editor that the user has
+// set up, or the file manager with the file selected.
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExternalEditor {
+    pub name: String,
+    // e.g. `gimp` or `krita --nosplash {}`: the program, then its arguments, split on spaces.
+    // The file goes wherever `{}` is, or on the end if there is no `{}`.
+    pub command: String,
+}
+
+impl ExternalEditor {
+    fn command_line(&self, path: &Path) -> Result<(String, Vec<OsString>)> {
+        let mut words = self.command.split_whitespace();
+        let Some(program) = words.next() else {
+            bail!("no command set for {}", self.name);
+        };
+        let mut args = Vec::new();
+        let mut placed = false;
+        for word in words {
+            if word == "{}" {
+                args.push(path.as_os_str().to_owned());
+                placed = true;
+            } else {
+                args.push(OsString::from(word));
+            }
+        }
+        if !placed {
+            args.push(path.as_os_str().to_owned());
+        }
+        Ok((program.to_owned(), args))
+    }
+
+    pub fn open(&self, path: &Path) -> Result<()> {
+        let (program, args) = self.command_line(path)?;
+        // Note: macOS apps are bundles, which have to go through `open` to start.
+        let mut command = if cfg!(target_os = "macos") && program.ends_with(".app") {
+            let mut command = Command::new("open");
+            command.arg("-a").arg(&program).arg("--args").args(&args);
+            command
+        } else {
+            let mut command = Command::new(&program);
+            command.args(&args);
+            command
+        };
+        // Note: we don't wait on the editor; the user may keep it open for hours.
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+}
+
+pub fn open_in_default_viewer(path: &Path) -> Result<()> {
+    open::that_detached(path)?;
+    Ok(())
+}
+
+// Show the file in the platform's file manager, selected where the file manager allows it.
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let status = if cfg!(target_os = "windows") {
+        let mut select = OsString::from("/select,");
+        select.push(path.as_os_str());
+        Command::new("explorer").arg(select).status()
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(path).status()
+    } else {
+        // Note: most Linux file managers implement the FileManager1 interface; for the rest,
+        //       opening the folder is the best we can do.
+        let uri = format!("file://{}", path.display());
+        Command::new("dbus-send")
+            .args([
+                "--session",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+                &format!("array:string:{uri}"),
+                "string:",
+            ])
+            .status()
+    };
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        // Note: explorer reports failure even when it selected the file.
+        Ok(_) if cfg!(target_os = "windows") => Ok(()),
+        _ => match path.parent() {
+            Some(parent) => Ok(open::that_detached(parent)?),
+            None => bail!("nowhere to reveal {}", path.display()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() -> Result<()> {
+        let path = Path::new("/data/screen/work.tif");
+        let editor = |command: &str| ExternalEditor {
+            name: "editor".to_owned(),
+            command: command.to_owned(),
+        };
+        let (program, args) = editor("gimp").command_line(path)?;
+        assert_eq!(program, "gimp");
+        assert_eq!(args, vec![OsString::from("/data/screen/work.tif")]);
+
+        let (program, args) = editor("krita --nosplash {} --fullscreen").command_line(path)?;
+        assert_eq!(program, "krita");
+        assert_eq!(
+            args,
+            ["--nosplash", "/data/screen/work.tif", "--fullscreen"]
+                .map(OsString::from)
+                .to_vec()
+        );
+
+        assert!(editor("  ").command_line(path).is_err());
+        Ok(())
+    }
+}
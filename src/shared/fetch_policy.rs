@@ -0,0 +1,190 @@
+// Per-plugin policies for which of a work's files to fetch when. Previews are always fetched,
+// as the gallery needs them; screen images can wait until the work is viewed; and the
+// originals, which for IIIF sources can be enormous, can wait until they are asked for. Also,
+// where to look for files that are gone from the source, once the plugin's mirrors run out.
+use jiff::Timestamp;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ScreenPolicy {
+    #[default]
+    Always,
+    OnView,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ArchivePolicy {
+    #[default]
+    OnDemand,
+    Always,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchPolicy {
+    screens: ScreenPolicy,
+    archives: ArchivePolicy,
+    wayback: bool,
+}
+
+// Which files to fetch for a work, beyond the preview.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FetchFiles {
+    pub screen: bool,
+    pub archive: bool,
+    // Whether to look for files in the Wayback Machine when they are gone.
+    pub wayback: bool,
+}
+
+impl FetchPolicy {
+    // What to fetch when refreshing works, rather than at the user's request.
+    pub fn on_refresh(&self) -> FetchFiles {
+        FetchFiles {
+            screen: self.screens == ScreenPolicy::Always,
+            archive: self.archives == ArchivePolicy::Always,
+            wayback: self.wayback,
+        }
+    }
+
+    // What to fetch when the user asks for works, and whether they asked for the originals.
+    pub fn on_request(&self, archives: bool) -> FetchFiles {
+        FetchFiles {
+            screen: true,
+            archive: archives || self.archives == ArchivePolicy::Always,
+            wayback: self.wayback,
+        }
+    }
+
+    // Returns true if anything changed.
+    pub fn ui(&mut self, salt: &str, ui: &mut egui::Ui) -> bool {
+        let prior = *self;
+        egui::Grid::new(format!("fetch_policy_{salt}"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Previews");
+                ui.label("always");
+                ui.end_row();
+
+                ui.label("Screen Images");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.screens, ScreenPolicy::Always, "always");
+                    ui.selectable_value(&mut self.screens, ScreenPolicy::OnView, "when viewed");
+                });
+                ui.end_row();
+
+                ui.label("Originals");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.archives, ArchivePolicy::OnDemand, "on demand");
+                    ui.selectable_value(&mut self.archives, ArchivePolicy::Always, "always");
+                });
+                ui.end_row();
+
+                ui.label("Dead Links");
+                ui.checkbox(&mut self.wayback, "try the Wayback Machine")
+                    .on_hover_text(
+                        "When a file is gone from the source and from any mirrors the plugin \
+                         knows of, look for a copy in the Internet Archive's Wayback Machine.",
+                    );
+                ui.end_row();
+            });
+        *self != prior
+    }
+}
+
+// Shared by the PluginHost, which configures it from each plugin's details, and the download
+// threads, which look up the policy when they start on a batch of works.
+#[derive(Clone, Debug, Default)]
+pub struct FetchPolicies {
+    by_plugin: Arc<Mutex<HashMap<String, FetchPolicy>>>,
+}
+
+impl FetchPolicies {
+    pub fn configure(&self, plugins: impl Iterator<Item = (String, FetchPolicy)>) {
+        *self.by_plugin.lock() = plugins.collect();
+    }
+
+    pub fn for_plugin(&self, plugin: &str) -> PluginFetchPolicy {
+        PluginFetchPolicy {
+            policies: self.clone(),
+            plugin: plugin.to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PluginFetchPolicy {
+    policies: FetchPolicies,
+    plugin: String,
+}
+
+impl PluginFetchPolicy {
+    pub fn current(&self) -> FetchPolicy {
+        self.policies
+            .by_plugin
+            .lock()
+            .get(&self.plugin)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// Image servers like IIIF's hand out a base url for the image, rather than the image itself;
+// the original is the full-size rendition of it.
+pub fn archive_fetch_url(archive_url: &str) -> String {
+    let last = archive_url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    if last.contains('.') {
+        archive_url.to_owned()
+    } else {
+        format!(
+            "{}/full/full/0/default.jpg",
+            archive_url.trim_end_matches('/')
+        )
+    }
+}
+
+// Where the Wayback Machine has its copy of `url` nearest to `when`, as it was served; the `id_`
+// asks for the file itself rather than the archive's page around it.
+pub fn wayback_url(url: &str, when: Timestamp) -> Option<String> {
+    (url.starts_with("http://") || url.starts_with("https://")).then(|| {
+        format!(
+            "https://web.archive.org/web/{}id_/{url}",
+            when.strftime("%Y%m%d%H%M%S")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_fetch_url() {
+        assert_eq!(
+            archive_fetch_url("https://api.nga.gov/iiif/1234-abcd"),
+            "https://api.nga.gov/iiif/1234-abcd/full/full/0/default.jpg"
+        );
+        assert_eq!(
+            archive_fetch_url("https://example.com/works/original.tif"),
+            "https://example.com/works/original.tif"
+        );
+    }
+
+    #[test]
+    fn test_wayback_url() {
+        let when = Timestamp::from_second(1_700_000_000).expect("valid");
+        assert_eq!(
+            wayback_url("https://example.com/a.jpg", when).as_deref(),
+            Some("https://web.archive.org/web/20231114221320id_/https://example.com/a.jpg")
+        );
+        assert_eq!(
+            wayback_url("ytdl://https://example.com/watch?v=1", when),
+            None
+        );
+    }
+}
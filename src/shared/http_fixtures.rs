@@ -0,0 +1,226 @@
+// A debugging aid for plugins: record every fetch_text a plugin makes during a job, then serve
+// the same responses back on later runs of that job, without touching the network or the cache.
+// This turns a provider's intermittent failure, or a parse error on some odd page, into
+// something that happens every time.
+//
+// Each job gets its own directory, `<data dir>/http-fixtures/<plugin>/<job>`, holding the bodies
+// and an index.json of RecordedFetch entries. The layout is shared with the plugin SDK, so that
+// `MockHost::with_recording` can replay a job under `cargo test`, as well.
+use anyhow::{Context as _, Result};
+use artchiver_sdk::{RecordedFetch, TextFetchError, TextResponse};
+use log::info;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum FixtureMode {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+impl FixtureMode {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Record, Self::Replay];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Record => "Record",
+            Self::Replay => "Replay",
+        }
+    }
+}
+
+impl fmt::Display for FixtureMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Default)]
+struct FixtureState {
+    root: PathBuf,
+    mode: FixtureMode,
+}
+
+// Shared by the PluginHost, which sets the mode from preferences, and the plugin threads, which
+// start a session for each job they run.
+#[derive(Clone, Debug, Default)]
+pub struct HttpFixtures {
+    state: Arc<Mutex<FixtureState>>,
+}
+
+impl HttpFixtures {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FixtureState {
+                root: root.to_owned(),
+                mode: FixtureMode::Off,
+            })),
+        }
+    }
+
+    pub fn set_mode(&self, mode: FixtureMode) {
+        self.state.lock().mode = mode;
+    }
+
+    // The session for a job, if we are recording or replaying.
+    pub fn begin(&self, plugin_name: &str, job: &str) -> Option<FixtureSession> {
+        let state = self.state.lock();
+        let dir = state.root.join(slug(plugin_name)).join(slug(job));
+        match state.mode {
+            FixtureMode::Off => None,
+            FixtureMode::Record => Some(FixtureSession::Record {
+                dir,
+                index: Vec::new(),
+            }),
+            FixtureMode::Replay => Some(FixtureSession::Replay { dir, index: None }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FixtureSession {
+    Record {
+        dir: PathBuf,
+        index: Vec<RecordedFetch>,
+    },
+    Replay {
+        dir: PathBuf,
+        // Loaded at the first fetch, so that jobs without fetches don't need a recording.
+        index: Option<HashMap<String, TextResponse>>,
+    },
+}
+
+impl FixtureSession {
+    // The recorded response for `url`, when replaying.
+    pub fn replay(&mut self, url: &str) -> Option<TextResponse> {
+        let Self::Replay { dir, index } = self else {
+            return None;
+        };
+        if index.is_none() {
+            *index = Some(load_recording(dir).unwrap_or_else(|e| {
+                info!("No recording to replay: {e:#}");
+                HashMap::new()
+            }));
+        }
+        let response = index.as_ref().and_then(|index| index.get(url)).cloned();
+        Some(response.unwrap_or_else(|| {
+            Err(TextFetchError::HostError(format!(
+                "{url} is not in the recording at {}",
+                dir.display()
+            )))
+        }))
+    }
+
+    // Save a response, when recording. The index is re-written each time, so that a recording
+    // survives the job crashing.
+    pub fn record(&mut self, url: &str, response: &TextResponse) -> Result<()> {
+        let Self::Record { dir, index } = self else {
+            return Ok(());
+        };
+        if index.is_empty() {
+            // Replace any older recording of the same job, rather than mix the two.
+            if dir.exists() {
+                fs::remove_dir_all(dir.as_path())
+                    .with_context(|| format!("clearing {}", dir.display()))?;
+            }
+            fs::create_dir_all(dir.as_path())
+                .with_context(|| format!("creating {}", dir.display()))?;
+            info!("Recording fetches to {}", dir.display());
+        }
+        let result = match response {
+            Ok(body) => {
+                let file = format!("{:04}.txt", index.len());
+                fs::write(dir.join(&file), body)?;
+                Ok(file)
+            }
+            Err(e) => Err(e.clone()),
+        };
+        index.push(RecordedFetch {
+            url: url.to_owned(),
+            result,
+        });
+        fs::write(dir.join("index.json"), serde_json::to_string_pretty(index)?)?;
+        Ok(())
+    }
+}
+
+fn load_recording(dir: &Path) -> Result<HashMap<String, TextResponse>> {
+    let index_path = dir.join("index.json");
+    let raw = fs::read_to_string(&index_path)
+        .with_context(|| format!("reading {}", index_path.display()))?;
+    let mut out = HashMap::new();
+    for fetch in serde_json::from_str::<Vec<RecordedFetch>>(&raw)? {
+        let response = match fetch.result {
+            Ok(file) => Ok(fs::read_to_string(dir.join(&file))
+                .with_context(|| format!("reading {file} for {}", fetch.url))?),
+            Err(e) => Err(e),
+        };
+        // Note: if a url was fetched more than once, replay the last response.
+        out.insert(fetch.url, response);
+    }
+    Ok(out)
+}
+
+// A name that is safe to use as a directory on every platform.
+fn slug(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_end_matches('-').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(
+            slug("Get Works for Tag Water/Lilies"),
+            "get-works-for-tag-water-lilies"
+        );
+        assert_eq!(slug("  NGA: Refresh Tags!"), "nga-refresh-tags");
+    }
+
+    #[test]
+    fn test_record_then_replay() -> Result<()> {
+        let root =
+            std::env::temp_dir().join(format!("artchiver-fixtures-{}", rand::random::<u32>()));
+        let fixtures = HttpFixtures::new(&root);
+
+        fixtures.set_mode(FixtureMode::Record);
+        let mut session = fixtures.begin("Test", "Refresh Tags").expect("recording");
+        session.record("https://a/1", &Ok("one".to_owned()))?;
+        session.record("https://a/2", &Err(TextFetchError::HttpError(503)))?;
+        assert!(session.replay("https://a/1").is_none());
+
+        fixtures.set_mode(FixtureMode::Replay);
+        let mut session = fixtures.begin("Test", "Refresh Tags").expect("replaying");
+        assert!(matches!(session.replay("https://a/1"), Some(Ok(body)) if body == "one"));
+        assert!(matches!(
+            session.replay("https://a/2"),
+            Some(Err(TextFetchError::HttpError(503)))
+        ));
+        assert!(matches!(
+            session.replay("https://a/3"),
+            Some(Err(TextFetchError::HostError(_)))
+        ));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+// Only one Artchiver may write to a data dir at a time. WAL keeps SQLite itself consistent with
+// two writers, but each instance caches tags and plugin state, moves files around, and cleans up
+// after itself on the assumption that nobody else is doing the same.
+//
+// The first instance holds an exclusive OS lock on `<data dir>/artchiver.lock` until it exits,
+// so a crash never leaves a stale lock behind. A second instance may still open the data dir
+// read-only, which disables the DB writer and the plugins that would feed it.
+use anyhow::{Context as _, Result};
+use log::info;
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+const LOCK_FILE_NAME: &str = "artchiver.lock";
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Acquire)
+}
+
+pub fn set_read_only() {
+    info!("Opening the data dir read-only");
+    READ_ONLY.store(true, Ordering::Release);
+}
+
+// Hold on to this until exit.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+#[derive(Debug)]
+pub enum LockOutcome {
+    Acquired(InstanceLock),
+    // Another instance has the lock; this is what it wrote about itself, if we could read it.
+    Held {
+        path: PathBuf,
+        owner: Option<String>,
+    },
+}
+
+impl InstanceLock {
+    pub fn acquire(data_dir: &Path) -> Result<LockOutcome> {
+        let path = data_dir.join(LOCK_FILE_NAME);
+        // Note: don't truncate until we have the lock, or we would wipe out the owner's note.
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let owner = fs::read_to_string(&path)
+                    .ok()
+                    .map(|owner| owner.trim().to_owned())
+                    .filter(|owner| !owner.is_empty());
+                return Ok(LockOutcome::Held { path, owner });
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("locking {}", path.display()));
+            }
+        }
+        file.set_len(0)?;
+        writeln!(file, "process {}", std::process::id())?;
+        Ok(LockOutcome::Acquired(Self { _file: file }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_lock_is_refused() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("artchiver-lock-{}", rand::random::<u32>()));
+        fs::create_dir_all(&dir)?;
+        let first = InstanceLock::acquire(&dir)?;
+        assert!(matches!(first, LockOutcome::Acquired(_)));
+        assert!(matches!(
+            InstanceLock::acquire(&dir)?,
+            LockOutcome::Held { owner: Some(_), .. }
+        ));
+        drop(first);
+        assert!(matches!(
+            InstanceLock::acquire(&dir)?,
+            LockOutcome::Acquired(_)
+        ));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
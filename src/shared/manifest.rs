@@ -0,0 +1,370 @@
+// Manifests are spreadsheets of works, as CSV, or the same as a JSON array of objects, for small
+// collections that aren't worth a plugin of their own: a museum's accession list, a scan of the
+// family photo albums. The user says what each column means, checks the preview, and we copy in
+// the file or download the url for each row.
+use anyhow::{Context as _, Result, bail, ensure};
+use jiff::civil::Date;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ManifestField {
+    #[default]
+    Ignore,
+    Title,
+    Date,
+    Url,
+    Path,
+    Tags,
+    Artist,
+}
+
+impl ManifestField {
+    pub const ALL: [Self; 7] = [
+        Self::Ignore,
+        Self::Title,
+        Self::Date,
+        Self::Url,
+        Self::Path,
+        Self::Tags,
+        Self::Artist,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ignore => "(ignore)",
+            Self::Title => "Title",
+            Self::Date => "Date",
+            Self::Url => "Url",
+            Self::Path => "Local Path",
+            Self::Tags => "Tags",
+            Self::Artist => "Artist",
+        }
+    }
+
+    // A first guess from the column's name, for the user to correct.
+    pub fn guess(column: &str) -> Self {
+        match column.trim().to_lowercase().as_str() {
+            "title" | "name" | "object name" => Self::Title,
+            "date" | "year" | "created" | "date created" => Self::Date,
+            "url" | "link" | "image" | "image url" | "image_url" | "href" => Self::Url,
+            "path" | "file" | "filename" | "file name" | "local path" => Self::Path,
+            "tags" | "keywords" | "subjects" | "subject" => Self::Tags,
+            "artist" | "creator" | "author" | "maker" | "photographer" => Self::Artist,
+            _ => Self::Ignore,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let (columns, rows) = if is_json {
+            parse_json(&text)?
+        } else {
+            let separator = if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"))
+            {
+                '\t'
+            } else {
+                ','
+            };
+            let mut rows = parse_csv(&text, separator);
+            ensure!(!rows.is_empty(), "{} is empty", path.display());
+            let columns = rows.remove(0);
+            (columns, rows)
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            columns,
+            rows,
+        })
+    }
+
+    // The tag that everything from the manifest goes under, after the manifest's file name.
+    pub fn tag(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Manifest".to_owned())
+    }
+
+    // Each row as a work, or why it can't be one. Relative paths are relative to the manifest.
+    pub fn plan(&self, fields: &[ManifestField]) -> Vec<Result<ManifestWork, String>> {
+        let base = self.path.parent().unwrap_or(Path::new("."));
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(offset, row)| self.plan_row(offset, row, fields, base))
+            .collect()
+    }
+
+    fn plan_row(
+        &self,
+        offset: usize,
+        row: &[String],
+        fields: &[ManifestField],
+        base: &Path,
+    ) -> Result<ManifestWork, String> {
+        let cell = |field: ManifestField| {
+            fields
+                .iter()
+                .zip(row)
+                .find(|(f, value)| **f == field && !value.trim().is_empty())
+                .map(|(_, value)| value.trim())
+        };
+        let source = if let Some(url) = cell(ManifestField::Url) {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!("{url} is not a web url"));
+            }
+            ManifestSource::Url(url.to_owned())
+        } else if let Some(path) = cell(ManifestField::Path) {
+            ManifestSource::Path(base.join(path))
+        } else {
+            return Err("no url or path".to_owned());
+        };
+        let date = match cell(ManifestField::Date) {
+            Some(date) => Some(parse_date(date).ok_or_else(|| format!("can't read date {date}"))?),
+            None => None,
+        };
+        let mut tags = Vec::new();
+        for (field, value) in fields.iter().zip(row) {
+            if *field == ManifestField::Tags {
+                tags.extend(
+                    value
+                        .split([';', '|', ','])
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_owned),
+                );
+            }
+        }
+        let title = cell(ManifestField::Title)
+            .map(str::to_owned)
+            .unwrap_or_else(|| source.file_name());
+        let record = self
+            .columns
+            .iter()
+            .zip(row)
+            .map(|(column, value)| (column.clone(), Value::String(value.clone())))
+            .collect::<Map<_, _>>();
+        Ok(ManifestWork {
+            row: offset + 1,
+            title,
+            date,
+            source,
+            tags,
+            artist: cell(ManifestField::Artist).map(str::to_owned),
+            record: Value::Object(record).to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ManifestSource {
+    Url(String),
+    Path(PathBuf),
+}
+
+impl ManifestSource {
+    fn file_name(&self) -> String {
+        match self {
+            Self::Url(url) => url
+                .rsplit('/')
+                .find(|part| !part.is_empty())
+                .unwrap_or(url)
+                .to_owned(),
+            Self::Path(path) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ManifestWork {
+    // Counting from 1, after the header, as a spreadsheet would show it.
+    pub row: usize,
+    pub title: String,
+    pub date: Option<Date>,
+    pub source: ManifestSource,
+    pub tags: Vec<String>,
+    pub artist: Option<String>,
+    // The row as a JSON object, kept as the work's source record.
+    pub record: String,
+}
+
+// Full dates, or just the year, which is all that most collections know.
+fn parse_date(date: &str) -> Option<Date> {
+    if let Ok(date) = date.parse::<Date>() {
+        return Some(date);
+    }
+    let year = date.trim_start_matches(['c', 'a', '.', ' ']);
+    let year = year.get(..4).unwrap_or(year);
+    Date::new(year.parse().ok()?, 1, 1).ok()
+}
+
+// RFC 4180, more or less: quoted fields may hold separators, newlines, and doubled quotes.
+fn parse_csv(text: &str, separator: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+        } else if c == '"' && field.is_empty() {
+            quoted = true;
+        } else if c == separator {
+            row.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(std::mem::take(&mut field));
+            if row.iter().any(|field| !field.is_empty()) {
+                rows.push(std::mem::take(&mut row));
+            }
+            row.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    row.push(field);
+    if row.iter().any(|field| !field.is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+// An array of objects, or an object with one array of objects in it, e.g. `{"works": [...]}`.
+// The columns are the keys from all of the objects.
+fn parse_json(text: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let value: Value = serde_json::from_str(text)?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(object) => match object.into_iter().find(|(_, v)| v.is_array()) {
+            Some((_, Value::Array(items))) => items,
+            _ => bail!("expected a list of works"),
+        },
+        _ => bail!("expected a list of works"),
+    };
+    let mut columns = Vec::<String>::new();
+    for item in &items {
+        let Value::Object(object) = item else {
+            bail!("expected each work to be an object");
+        };
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.to_owned());
+            }
+        }
+    }
+    let rows = items
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| item.get(column).map(json_cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    Ok((columns, rows))
+}
+
+fn json_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_owned(),
+        Value::Array(items) => items.iter().map(json_cell).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv(
+            "title,tags\r\n\"Haystacks, Morning\",\"monet;\"\"impressionism\"\"\"\n\nPoplars,\n",
+            ',',
+        );
+        assert_eq!(
+            rows,
+            [
+                vec!["title", "tags"],
+                vec!["Haystacks, Morning", "monet;\"impressionism\""],
+                vec!["Poplars", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let (columns, rows) = parse_json(
+            r#"{"works": [
+                {"Title": "Haystacks", "Year": 1891, "Image": "https://example.org/h.jpg",
+                 "Keywords": ["monet", "hay"]},
+                {"Title": "Poplars", "Year": "c. 1891", "File": "scans/poplars.tif"},
+                {"Title": "Lost", "Year": "unknown", "File": "lost.jpg"},
+                {"Title": "Nothing"}
+            ]}"#,
+        )
+        .expect("valid json");
+        let manifest = Manifest {
+            path: PathBuf::from("/archive/monet.json"),
+            columns,
+            rows,
+        };
+        let fields = manifest
+            .columns
+            .iter()
+            .map(|column| ManifestField::guess(column))
+            .collect::<Vec<_>>();
+        let plan = manifest.plan(&fields);
+
+        let haystacks = plan[0].as_ref().expect("a url row");
+        assert_eq!(
+            haystacks.source,
+            ManifestSource::Url("https://example.org/h.jpg".to_owned())
+        );
+        assert_eq!(haystacks.date, Date::new(1891, 1, 1).ok());
+        assert_eq!(haystacks.tags, ["monet", "hay"]);
+        let poplars = plan[1].as_ref().expect("a path row");
+        assert_eq!(
+            poplars.source,
+            ManifestSource::Path(PathBuf::from("/archive/scans/poplars.tif"))
+        );
+        assert_eq!(poplars.date, Date::new(1891, 1, 1).ok());
+        assert!(plan[2].is_err());
+        assert!(plan[3].is_err());
+        assert_eq!(manifest.tag(), "monet");
+    }
+}
@@ -0,0 +1,160 @@
+// Process-wide counters and timings, for the /metrics endpoint and diagnostics bundles.
+//
+// Unlike PerfTrack, which keeps a short history for plotting on the UX thread, these are
+// cumulative and may be bumped from any thread, so they live in a global registry.
+use parking_lot::Mutex;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+// A metric name and the name of the label that splits it out, if any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Metric {
+    name: &'static str,
+    label: &'static str,
+}
+
+impl Metric {
+    const fn new(name: &'static str, label: &'static str) -> Self {
+        Self { name, label }
+    }
+}
+
+// Everything we export, kept together so that it is easy to see what is available.
+pub const DOWNLOADS_TOTAL: Metric = Metric::new("artchiver_downloads_total", "");
+pub const DOWNLOAD_FAILURES_TOTAL: Metric = Metric::new("artchiver_download_failures_total", "");
+pub const DOWNLOAD_BYTES_TOTAL: Metric = Metric::new("artchiver_download_bytes_total", "kind");
+pub const FETCH_TEXT_TOTAL: Metric = Metric::new("artchiver_fetch_text_total", "");
+pub const FETCH_TEXT_CACHE_HITS_TOTAL: Metric =
+    Metric::new("artchiver_fetch_text_cache_hits_total", "");
+pub const HTTP_REQUESTS_TOTAL: Metric = Metric::new("artchiver_http_requests_total", "status");
+pub const DB_QUERY_SECONDS: Metric = Metric::new("artchiver_db_query_seconds", "query");
+pub const PLUGIN_CALL_SECONDS: Metric = Metric::new("artchiver_plugin_call_seconds", "function");
+pub const UI_SECONDS: Metric = Metric::new("artchiver_ui_seconds", "section");
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Timing {
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: BTreeMap<(Metric, String), u64>,
+    timings: BTreeMap<(Metric, String), Timing>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Default::default);
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+// Add `n` to the counter, split out by `label` if the metric has one.
+pub fn count(metric: Metric, label: &str, n: u64) {
+    *REGISTRY
+        .lock()
+        .counters
+        .entry((metric, label.to_owned()))
+        .or_default() += n;
+}
+
+pub fn time(metric: Metric, label: &str, elapsed: Duration) {
+    let mut registry = REGISTRY.lock();
+    let timing = registry
+        .timings
+        .entry((metric, label.to_owned()))
+        .or_default();
+    timing.count += 1;
+    timing.sum += elapsed;
+    timing.max = timing.max.max(elapsed);
+}
+
+fn labels(metric: Metric, label: &str) -> String {
+    if metric.label.is_empty() {
+        String::new()
+    } else {
+        let escaped = label
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        format!("{{{}=\"{escaped}\"}}", metric.label)
+    }
+}
+
+// Render everything in the Prometheus text exposition format.
+pub fn render_prometheus() -> String {
+    let registry = REGISTRY.lock();
+    let mut out = String::new();
+    writeln!(out, "# TYPE artchiver_uptime_seconds gauge").ok();
+    writeln!(
+        out,
+        "artchiver_uptime_seconds {}",
+        STARTED.elapsed().as_secs_f64()
+    )
+    .ok();
+
+    let mut last_name = "";
+    for ((metric, label), value) in &registry.counters {
+        let name = metric.name;
+        if name != last_name {
+            writeln!(out, "# TYPE {name} counter").ok();
+            last_name = name;
+        }
+        writeln!(out, "{name}{} {value}", labels(*metric, label)).ok();
+    }
+
+    last_name = "";
+    for ((metric, label), timing) in &registry.timings {
+        let name = metric.name;
+        if name != last_name {
+            writeln!(out, "# TYPE {name} summary").ok();
+            last_name = name;
+        }
+        let labels = labels(*metric, label);
+        writeln!(out, "{name}_count{labels} {}", timing.count).ok();
+        writeln!(out, "{name}_sum{labels} {}", timing.sum.as_secs_f64()).ok();
+    }
+
+    // Note: summaries may only carry _count and _sum, so the max gets its own gauge.
+    last_name = "";
+    for ((metric, label), timing) in &registry.timings {
+        let name = metric.name;
+        if name != last_name {
+            writeln!(out, "# TYPE {name}_max gauge").ok();
+            last_name = name;
+        }
+        let labels = labels(*metric, label);
+        writeln!(out, "{name}_max{labels} {}", timing.max.as_secs_f64()).ok();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus() {
+        const COUNTER: Metric = Metric::new("test_counter_total", "");
+        const LABELED: Metric = Metric::new("test_labeled_total", "kind");
+        const TIMING: Metric = Metric::new("test_seconds", "query");
+        count(COUNTER, "", 2);
+        count(COUNTER, "", 3);
+        count(LABELED, "a\"b", 1);
+        time(TIMING, "q", Duration::from_millis(500));
+        time(TIMING, "q", Duration::from_millis(1500));
+        let out = render_prometheus();
+        assert!(out.contains("# TYPE test_counter_total counter\n"), "{out}");
+        assert!(out.contains("test_counter_total 5\n"), "{out}");
+        assert!(
+            out.contains("test_labeled_total{kind=\"a\\\"b\"} 1\n"),
+            "{out}"
+        );
+        assert!(out.contains("# TYPE test_seconds summary\n"), "{out}");
+        assert!(out.contains("test_seconds_count{query=\"q\"} 2\n"), "{out}");
+        assert!(out.contains("test_seconds_sum{query=\"q\"} 2\n"), "{out}");
+        assert!(out.contains("test_seconds_max{query=\"q\"} 1.5\n"), "{out}");
+    }
+}
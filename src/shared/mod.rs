@@ -1,7 +1,36 @@
+pub mod bandwidth;
+pub mod blob;
+pub mod compliance;
+pub mod contact_sheet;
+pub mod deep_link;
+pub mod detection;
+pub mod diagnostics;
+pub mod document;
+pub mod encryption;
+pub mod enrichment;
 pub mod environment;
+pub mod exhibition;
+pub mod export;
+pub mod external;
+pub mod fetch_policy;
+pub mod http_fixtures;
+pub mod instance_lock;
+pub mod manifest;
+pub mod metrics;
+pub mod nsfw;
 pub mod performance;
 pub mod plugin;
+pub mod plugin_health;
+pub mod profile;
 pub mod progress;
+pub mod storage;
 pub mod tag;
+pub mod tag_exclusion;
 pub mod throttle;
+pub mod transcript;
 pub mod update;
+pub mod variants;
+pub mod vector;
+pub mod vocabulary;
+pub mod wallpaper;
+pub mod warc;
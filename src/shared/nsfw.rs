@@ -0,0 +1,217 @@
+// Scores works for how likely they are to be NSFW, with the user's own image classifier, as we
+// make their gallery thumbnails; content filters then hide or blur the works that score high.
+//
+// Classifiers differ in their labels, so the user says which of the model's outputs are NSFW,
+// e.g. 1 for a two-class normal/nsfw model, or 1, 3, 4 for the five-class drawings, hentai,
+// neutral, porn, sexy models; the score is the sum of their probabilities.
+//
+// Note: thumbnails are made on many threads, from downloads, imports, and the backfill, so the
+//       loaded model is shared by all of them, and they take turns with it.
+use anyhow::{Result, bail, ensure};
+use image::DynamicImage;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+pub const AVAILABLE: bool = cfg!(feature = "detection");
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NsfwSettings {
+    pub enabled: bool,
+    pub model_path: String,
+    // The model takes square images of this size, with red, green, and blue from 0 to 1.
+    pub input_size: u32,
+    // As [1, height, width, 3], as Keras models take them, rather than [1, 3, height, width].
+    pub channels_last: bool,
+    // The outputs that are NSFW, by index, separated by commas.
+    pub nsfw_outputs: String,
+}
+
+impl Default for NsfwSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model_path: String::new(),
+            input_size: 224,
+            channels_last: false,
+            nsfw_outputs: "1".to_owned(),
+        }
+    }
+}
+
+pub fn parse_outputs(outputs: &str) -> Result<Vec<usize>> {
+    let outputs = outputs
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()?;
+    ensure!(!outputs.is_empty(), "no NSFW outputs are set");
+    Ok(outputs)
+}
+
+// Models end in either probabilities or raw scores; the latter we have to make probabilities of.
+pub fn nsfw_probability(outputs: &[f32], nsfw: &[usize]) -> Result<f32> {
+    if let Some(missing) = nsfw.iter().find(|i| **i >= outputs.len()) {
+        bail!(
+            "the model has {} outputs, so no output {missing}",
+            outputs.len()
+        );
+    }
+    let sum = outputs.iter().sum::<f32>();
+    let is_probabilities =
+        outputs.iter().all(|p| (0. ..=1.).contains(p)) && (sum - 1.).abs() < 0.01;
+    let probabilities = if is_probabilities {
+        outputs.to_vec()
+    } else {
+        let max = outputs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exp = outputs.iter().map(|x| (x - max).exp()).collect::<Vec<_>>();
+        let total = exp.iter().sum::<f32>();
+        exp.into_iter().map(|x| x / total).collect()
+    };
+    Ok(nsfw
+        .iter()
+        .map(|i| probabilities[*i])
+        .sum::<f32>()
+        .clamp(0., 1.))
+}
+
+static CLASSIFIER: Mutex<Option<Classifier>> = Mutex::new(None);
+
+// Load the model, or drop it if the user has turned classification off.
+pub fn configure(settings: &NsfwSettings) -> Result<()> {
+    let classifier = if settings.enabled {
+        Some(Classifier::load(settings)?)
+    } else {
+        None
+    };
+    *CLASSIFIER.lock().expect("poison") = classifier;
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    CLASSIFIER.lock().expect("poison").is_some()
+}
+
+// The chance that the image is NSFW, if the user has a classifier loaded.
+pub fn score(image: &DynamicImage) -> Option<f32> {
+    let mut classifier = CLASSIFIER.lock().expect("poison");
+    match classifier.as_mut()?.score(image) {
+        Ok(score) => Some(score),
+        Err(e) => {
+            warn!("Failed to classify an image: {e:#}");
+            None
+        }
+    }
+}
+
+#[cfg(feature = "detection")]
+use onnx::Classifier;
+
+#[cfg(feature = "detection")]
+mod onnx {
+    use super::{NsfwSettings, nsfw_probability, parse_outputs};
+    use anyhow::{Context as _, Result};
+    use image::{DynamicImage, imageops::FilterType};
+    use ort::{session::Session, value::Tensor};
+
+    pub struct Classifier {
+        session: Session,
+        input_size: u32,
+        channels_last: bool,
+        nsfw_outputs: Vec<usize>,
+    }
+
+    impl Classifier {
+        pub fn load(settings: &NsfwSettings) -> Result<Self> {
+            let nsfw_outputs = parse_outputs(&settings.nsfw_outputs)?;
+            let session = Session::builder()?
+                .commit_from_file(&settings.model_path)
+                .with_context(|| format!("loading {}", settings.model_path))?;
+            Ok(Self {
+                session,
+                input_size: settings.input_size.max(1),
+                channels_last: settings.channels_last,
+                nsfw_outputs,
+            })
+        }
+
+        pub fn score(&mut self, image: &DynamicImage) -> Result<f32> {
+            // Note: classifiers are trained on squashed images, not letterboxed ones.
+            let rgb = image
+                .resize_exact(self.input_size, self.input_size, FilterType::Triangle)
+                .to_rgb8();
+            let size = self.input_size as usize;
+            let plane = size * size;
+            let mut input = vec![0.; 3 * plane];
+            for (i, pixel) in rgb.pixels().enumerate() {
+                for (channel, value) in pixel.0.into_iter().enumerate() {
+                    let at = if self.channels_last {
+                        i * 3 + channel
+                    } else {
+                        channel * plane + i
+                    };
+                    input[at] = f32::from(value) / 255.;
+                }
+            }
+            let tensor = if self.channels_last {
+                Tensor::from_array(([1, size, size, 3], input))?
+            } else {
+                Tensor::from_array(([1, 3, size, size], input))?
+            };
+            let outputs = self.session.run(ort::inputs![tensor])?;
+            let (_shape, probabilities) = outputs[0].try_extract_tensor::<f32>()?;
+            nsfw_probability(probabilities, &self.nsfw_outputs)
+        }
+    }
+}
+
+#[cfg(not(feature = "detection"))]
+use stub::Classifier;
+
+// Stands in for the classifier in builds without ONNX Runtime.
+#[cfg(not(feature = "detection"))]
+mod stub {
+    use super::NsfwSettings;
+    use anyhow::{Result, bail};
+    use image::DynamicImage;
+
+    pub struct Classifier;
+
+    impl Classifier {
+        pub fn load(_settings: &NsfwSettings) -> Result<Self> {
+            bail!("this build doesn't include classification; build with `--features detection`")
+        }
+
+        pub fn score(&mut self, _image: &DynamicImage) -> Result<f32> {
+            bail!("this build doesn't include classification")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_outputs() -> Result<()> {
+        assert_eq!(parse_outputs("1, 3,4")?, vec![1, 3, 4]);
+        assert!(parse_outputs(" , ").is_err());
+        assert!(parse_outputs("porn").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nsfw_probability() -> Result<()> {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+        assert!(close(
+            nsfw_probability(&[0.1, 0.2, 0.3, 0.3, 0.1], &[1, 3, 4])?,
+            0.6
+        ));
+        // Raw scores of 0 and ln(3) are odds of 1 to 3.
+        assert!(close(nsfw_probability(&[0., 3f32.ln()], &[1])?, 0.75));
+        assert!(nsfw_probability(&[0.4, 0.6], &[2]).is_err());
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::shared::metrics;
 use egui_plot::{Line, Plot, PlotPoints};
 use ringbuffer::{AllocRingBuffer, RingBuffer as _};
 use std::{
@@ -22,6 +23,7 @@ impl Default for PerfTrack {
 
 impl PerfTrack {
     pub fn sample(&mut self, name: &str, elapsed: Duration) {
+        metrics::time(metrics::UI_SECONDS, name, elapsed);
         self.perf
             .entry(name.to_owned())
             .or_insert_with(|| {
@@ -1,13 +1,53 @@
-use artchiver_sdk::ConfigValue;
+use artchiver_sdk::{ConfigValue, WorkTagEdit};
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::{fmt, sync::Arc};
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum PluginRequest {
-    ApplyConfiguration { config: Vec<(String, ConfigValue)> },
+    ApplyConfiguration {
+        config: Vec<(String, ConfigValue)>,
+    },
     RefreshTags,
-    RefreshWorksForTag { tag: String },
+    RefreshWorksForTag {
+        tag: String,
+        // Record the works, but leave fetching their files until the user asks for them.
+        #[serde(default)]
+        metadata_only: bool,
+        // Only download the files for the first this many works; the rest are recorded.
+        #[serde(default)]
+        max_works: Option<usize>,
+    },
+    // One page of a refresh, for exploring a big tag without taking all of it.
+    FetchWorksForTagRange {
+        tag: String,
+        offset: usize,
+        limit: usize,
+        #[serde(default)]
+        metadata_only: bool,
+    },
+    DownloadWorks {
+        screen_urls: Vec<String>,
+        // Also fetch the originals, whatever the plugin's fetch policy says.
+        #[serde(default)]
+        archives: bool,
+    },
+    ReprocessSources,
+    // The tag edits the user reviewed, each with the screen url of its work.
+    PushTagEdits {
+        edits: Vec<(String, WorkTagEdit)>,
+    },
+    // One of the custom actions from the plugin's metadata.
+    RunAction {
+        action_id: String,
+        label: String,
+        work_remote_id: Option<String>,
+    },
+    // Look for dead links among the works we have not downloaded yet, and ask the plugin
+    // for fresh ones where it can.
+    CheckLinks {
+        sample: usize,
+    },
     Shutdown,
 }
 
@@ -16,7 +56,37 @@ impl fmt::Display for PluginRequest {
         match self {
             Self::ApplyConfiguration { .. } => write!(f, "Apply Configuration"),
             Self::RefreshTags => write!(f, "Refresh Tags"),
-            Self::RefreshWorksForTag { tag } => write!(f, "Get Works for Tag {tag}"),
+            Self::RefreshWorksForTag {
+                tag,
+                metadata_only: true,
+                ..
+            } => write!(f, "Get Works for Tag {tag} (metadata only)"),
+            Self::RefreshWorksForTag {
+                tag,
+                max_works: Some(max_works),
+                ..
+            } => write!(f, "Get Works for Tag {tag} (first {max_works})"),
+            Self::RefreshWorksForTag { tag, .. } => write!(f, "Get Works for Tag {tag}"),
+            Self::FetchWorksForTagRange {
+                tag, offset, limit, ..
+            } => write!(
+                f,
+                "Get Works {}-{} for Tag {tag}",
+                offset + 1,
+                offset + limit
+            ),
+            Self::DownloadWorks {
+                screen_urls,
+                archives: false,
+            } => write!(f, "Download {} Works", screen_urls.len()),
+            Self::DownloadWorks {
+                screen_urls,
+                archives: true,
+            } => write!(f, "Download {} Works with Originals", screen_urls.len()),
+            Self::ReprocessSources => write!(f, "Reprocess Sources"),
+            Self::PushTagEdits { edits } => write!(f, "Push Tag Edits for {} Works", edits.len()),
+            Self::RunAction { label, .. } => write!(f, "{label}"),
+            Self::CheckLinks { .. } => write!(f, "Check Links"),
             Self::Shutdown => write!(f, "Shutdown"),
         }
     }
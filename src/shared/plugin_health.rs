@@ -0,0 +1,88 @@
+// How well a plugin's source is doing: when its refreshes last worked, and how many of its recent
+// jobs failed. Kept with the plugin's state, so that a source that has been failing for days
+// still shows as failing after a restart.
+use crate::shared::plugin::PluginRequest;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// The jobs that the error rate is over.
+pub const RECENT_JOBS: usize = 50;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginHealth {
+    last_tags_refresh: Option<Timestamp>,
+    last_works_refresh: Option<Timestamp>,
+    // Whether each of the most recent jobs succeeded, newest last.
+    recent_jobs: VecDeque<bool>,
+}
+
+impl PluginHealth {
+    pub fn record(&mut self, task: &PluginRequest, ok: bool) {
+        match task {
+            // Note: these are ours, not the source's; they say nothing about its health.
+            PluginRequest::Shutdown | PluginRequest::ApplyConfiguration { .. } => return,
+            PluginRequest::RefreshTags if ok => {
+                self.last_tags_refresh = Some(Timestamp::now());
+            }
+            PluginRequest::RefreshWorksForTag { .. }
+            | PluginRequest::FetchWorksForTagRange { .. }
+                if ok =>
+            {
+                self.last_works_refresh = Some(Timestamp::now());
+            }
+            _ => {}
+        }
+        self.recent_jobs.push_back(ok);
+        while self.recent_jobs.len() > RECENT_JOBS {
+            self.recent_jobs.pop_front();
+        }
+    }
+
+    pub fn last_tags_refresh(&self) -> Option<Timestamp> {
+        self.last_tags_refresh
+    }
+
+    pub fn last_works_refresh(&self) -> Option<Timestamp> {
+        self.last_works_refresh
+    }
+
+    pub fn job_count(&self) -> usize {
+        self.recent_jobs.len()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.recent_jobs.iter().filter(|ok| !**ok).count()
+    }
+
+    // The fraction of recent jobs that failed, if there have been any.
+    pub fn error_rate(&self) -> Option<f32> {
+        (!self.recent_jobs.is_empty())
+            .then(|| self.failed_count() as f32 / self.recent_jobs.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut health = PluginHealth::default();
+        assert_eq!(health.error_rate(), None);
+        health.record(&PluginRequest::RefreshTags, false);
+        assert_eq!(health.last_tags_refresh(), None);
+        health.record(&PluginRequest::RefreshTags, true);
+        assert!(health.last_tags_refresh().is_some());
+        health.record(&PluginRequest::Shutdown, false);
+        assert_eq!(health.error_rate(), Some(0.5));
+
+        for _ in 0..RECENT_JOBS {
+            health.record(&PluginRequest::ReprocessSources, true);
+        }
+        assert_eq!(health.job_count(), RECENT_JOBS);
+        assert_eq!(health.failed_count(), 0);
+        assert_eq!(health.last_works_refresh(), None);
+    }
+}
@@ -0,0 +1,122 @@
+// Profiles keep separate archives side by side, e.g. for work and for home, each with its own
+// database, data dir, and UI state. The default profile is the prefix itself, as it was before
+// profiles; named profiles go under `<prefix>/profiles/<name>`. All profiles share the plugins.
+//
+// Portable mode runs from the executable's folder, wherever we were started from, and only
+// loads plugins from there, so that the whole install can be carried around on a thumb drive.
+//
+// Like read-only mode, these are picked once at startup; switching profiles starts a new
+// instance, which picks up the instance lock for its own data dir.
+use anyhow::{Context as _, Result, ensure};
+use log::info;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+const PROFILES_DIR_NAME: &str = "profiles";
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// The named profile that we are running, if any.
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+pub fn set_profile(name: &str) -> Result<()> {
+    ensure!(
+        is_valid_profile_name(name),
+        "profile names may only use letters, numbers, - and _"
+    );
+    info!("Using profile {name}");
+    PROFILE.set(name.to_owned()).ok();
+    Ok(())
+}
+
+pub fn is_portable() -> bool {
+    PORTABLE.load(Ordering::Acquire)
+}
+
+// Returns the folder that portable mode runs from.
+pub fn set_portable() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let dir = exe
+        .parent()
+        .with_context(|| format!("{} is not in a folder", exe.display()))?
+        .to_owned();
+    info!("Running portable from {}", dir.display());
+    PORTABLE.store(true, Ordering::Release);
+    Ok(dir)
+}
+
+// Where the profile's storage config, data dir, and cache go.
+pub fn profile_root(prefix: &Path) -> PathBuf {
+    match profile() {
+        Some(name) => prefix.join(PROFILES_DIR_NAME).join(name),
+        None => prefix.to_owned(),
+    }
+}
+
+// The named profiles under the prefix, sorted; the default profile is not included.
+pub fn list_profiles(prefix: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(prefix.join(PROFILES_DIR_NAME)) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_owned))
+        .filter(|name| is_valid_profile_name(name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+// Start another instance on the profile, or on the default profile for None. The caller is
+// expected to close this one.
+pub fn launch_profile(prefix: &Path, name: Option<&str>) -> Result<()> {
+    let mut command = Command::new(std::env::current_exe()?);
+    if is_portable() {
+        command.arg("--portable");
+    } else {
+        command.arg("--dir").arg(prefix);
+    }
+    if let Some(name) = name {
+        ensure!(
+            is_valid_profile_name(name),
+            "{name} is not a valid profile name"
+        );
+        command.args(["--profile", name]);
+    }
+    command
+        .spawn()
+        .with_context(|| format!("starting {}", name.unwrap_or("the default profile")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names() {
+        assert!(is_valid_profile_name("research"));
+        assert!(is_valid_profile_name("home_2"));
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("../data"));
+        assert!(!is_valid_profile_name("my archive"));
+    }
+}
@@ -1,13 +1,32 @@
 use crate::{
-    db::models::{
-        plugin::{DbPlugin, PluginId},
-        tag::{DbTag, TagId},
-        work::{DbWork, WorkId},
+    db::{
+        maintenance::OptimizeReport,
+        metadata_sync::SyncReport,
+        models::{
+            annotation::DbAnnotation,
+            collection::DbCollection,
+            detection::{DbTagSuggestion, PendingScan},
+            enrichment::PendingEnrichment,
+            exhibition::DbExhibition,
+            link_check::LostLink,
+            note::{DbWorkNote, NoteHit},
+            plugin::{DbPlugin, PluginId},
+            tag::{CoTags, DbTag, TagId},
+            tag_edit::DbTagEdit,
+            transcript::{DbTranscript, TranscriptHit},
+            trash::TrashedWork,
+            work::{
+                DbWork, DbWorkIdentity, DbWorkRevision, DisplayTransform, MissingThumb, WorkId,
+            },
+        },
+        purge::PurgeReport,
+        relocate::RelocateReport,
+        scrub::ScrubReport,
     },
     shared::update::DataUpdate,
 };
 use anyhow::Result;
-use artchiver_sdk::PluginMetadata;
+use artchiver_sdk::{Authority, PluginMetadata};
 use crossbeam::channel::{self, Receiver, Sender};
 use log::{Level, debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
@@ -136,9 +155,18 @@ impl HostUpdateSender {
         Ok(())
     }
 
-    pub fn note_works_were_refreshed(&mut self, for_tag: String) -> Result<()> {
+    pub fn note_works_were_refreshed(&mut self, for_tag: String, new_works: usize) -> Result<()> {
         self.tx_to_runner
-            .send(DataUpdate::WorksWereUpdatedForTag { for_tag })?;
+            .send(DataUpdate::WorksWereUpdatedForTag { for_tag, new_works })?;
+        Ok(())
+    }
+
+    pub fn note_task_failed(&mut self, plugin: &str, task: String, error: String) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::PluginTaskFailed {
+            plugin: plugin.to_owned(),
+            task,
+            error,
+        })?;
         Ok(())
     }
 
@@ -158,6 +186,26 @@ impl HostUpdateSender {
         Ok(())
     }
 
+    pub fn note_work_rating_changed(&mut self, work_id: WorkId, rating: u8) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkRatingChanged { work_id, rating })?;
+        Ok(())
+    }
+
+    pub fn note_work_nsfw_scored(&mut self, work_id: WorkId, score: f32) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkNsfwScored { work_id, score })?;
+        Ok(())
+    }
+
+    pub fn note_work_model_downloaded(&mut self, work_id: WorkId, model_path: &str) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::WorkModelDownloaded {
+            work_id,
+            model_path: model_path.to_owned(),
+        })?;
+        Ok(())
+    }
+
     pub fn note_tag_favorite_status_changed(
         &mut self,
         tag_id: TagId,
@@ -174,18 +222,69 @@ impl HostUpdateSender {
         Ok(())
     }
 
+    pub fn note_metadata_sync_completed(&mut self, report: SyncReport) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::MetadataSyncCompleted(report))?;
+        Ok(())
+    }
+
+    pub fn note_storage_relocated(&mut self, report: RelocateReport) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::StorageRelocated(report))?;
+        Ok(())
+    }
+
+    pub fn note_plugin_works_purged(
+        &mut self,
+        plugin_id: PluginId,
+        report: PurgeReport,
+    ) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::PluginWorksPurged { plugin_id, report })?;
+        Ok(())
+    }
+
+    pub fn note_trash(&mut self, works: Vec<TrashedWork>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::TrashedWorks(works))?;
+        Ok(())
+    }
+
+    pub fn note_database_optimized(&mut self, report: OptimizeReport) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::DatabaseOptimized(report))?;
+        Ok(())
+    }
+
+    pub fn note_lost_links(&mut self, lost: Vec<LostLink>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::LostLinks(lost))?;
+        Ok(())
+    }
+
+    pub fn note_scrub_completed(&mut self, report: ScrubReport) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::ScrubCompleted(report))?;
+        Ok(())
+    }
+
+    pub fn note_file_repaired(&mut self, stored_path: String, error: Option<String>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::FileRepaired { stored_path, error })?;
+        Ok(())
+    }
+
     pub fn note_completed_download(
         &mut self,
         id: WorkId,
         preview_path: &str,
-        screen_path: &str,
-        archive_path: Option<&str>,
+        (screen_path, archive_path, thumb_path): (Option<&str>, Option<&str>, Option<&str>),
+        aspect_ratio: Option<f32>,
     ) -> Result<()> {
         self.tx_to_runner.send(DataUpdate::WorkDownloadCompleted {
             id,
             preview_path: preview_path.to_owned(),
-            screen_path: screen_path.to_owned(),
+            screen_path: screen_path.map(|s| s.to_owned()),
             archive_path: archive_path.map(|s| s.to_owned()),
+            thumb_path: thumb_path.map(|s| s.to_owned()),
+            aspect_ratio,
         })?;
         Ok(())
     }
@@ -204,12 +303,201 @@ impl HostUpdateSender {
         Ok(())
     }
 
+    pub fn return_work_source(&mut self, work_id: WorkId, source: Option<String>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkSource { work_id, source })?;
+        Ok(())
+    }
+
+    pub fn return_work_history(
+        &mut self,
+        work_id: WorkId,
+        revisions: Vec<DbWorkRevision>,
+    ) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkHistory { work_id, revisions })?;
+        Ok(())
+    }
+
+    pub fn return_work_identity(
+        &mut self,
+        work_id: WorkId,
+        identity: DbWorkIdentity,
+    ) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkIdentity { work_id, identity })?;
+        Ok(())
+    }
+
+    pub fn return_display_transforms(
+        &mut self,
+        transforms: HashMap<String, DisplayTransform>,
+    ) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::DisplayTransforms(transforms))?;
+        Ok(())
+    }
+
+    pub fn return_co_tags(&mut self, co_tags: CoTags) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::CoTags(co_tags))?;
+        Ok(())
+    }
+
+    pub fn return_collections(&mut self, collections: Vec<DbCollection>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::Collections(collections))?;
+        Ok(())
+    }
+
+    pub fn return_work_variants(&mut self, work_id: WorkId, variants: Vec<String>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkVariants { work_id, variants })?;
+        Ok(())
+    }
+
+    pub fn return_annotations(
+        &mut self,
+        screen_url: String,
+        annotations: Vec<DbAnnotation>,
+    ) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::Annotations {
+            screen_url,
+            annotations,
+        })?;
+        Ok(())
+    }
+
+    pub fn return_transcript(
+        &mut self,
+        screen_url: String,
+        transcript: Option<DbTranscript>,
+    ) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::Transcript {
+            screen_url,
+            transcript,
+        })?;
+        Ok(())
+    }
+
+    pub fn return_transcript_search(
+        &mut self,
+        query: String,
+        hits: Vec<TranscriptHit>,
+    ) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::TranscriptSearch { query, hits })?;
+        Ok(())
+    }
+
+    pub fn return_work_note(&mut self, work_id: WorkId, note: Option<DbWorkNote>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorkNote { work_id, note })?;
+        Ok(())
+    }
+
+    pub fn return_note_search(&mut self, query: String, hits: Vec<NoteHit>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::NoteSearch { query, hits })?;
+        Ok(())
+    }
+
+    pub fn return_exhibitions(&mut self, exhibitions: Vec<DbExhibition>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::Exhibitions(exhibitions))?;
+        Ok(())
+    }
+
+    pub fn return_inbox_works(&mut self, works: Vec<(String, DbWork)>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::InboxWorks(works))?;
+        Ok(())
+    }
+
+    pub fn return_works_to_enrich(&mut self, works: Vec<PendingEnrichment>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::WorksToEnrich(works))?;
+        Ok(())
+    }
+
+    pub fn return_vocabulary_to_fetch(&mut self, terms: Vec<(Authority, String)>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::VocabularyToFetch(terms))?;
+        Ok(())
+    }
+
+    pub fn return_works_to_detect(&mut self, works: Vec<PendingScan>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::WorksToDetect(works))?;
+        Ok(())
+    }
+
+    pub fn return_tag_suggestions(&mut self, suggestions: Vec<DbTagSuggestion>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::TagSuggestions(suggestions))?;
+        Ok(())
+    }
+
+    pub fn note_tag_suggestions_changed(&mut self) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::TagSuggestionsChanged)?;
+        Ok(())
+    }
+
+    pub fn return_pending_tag_edits(&mut self, edits: Vec<DbTagEdit>) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::PendingTagEdits(edits))?;
+        Ok(())
+    }
+
+    pub fn note_tag_edits_changed(&mut self) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::TagEditsChanged)?;
+        Ok(())
+    }
+
+    pub fn return_linked_work(&mut self, screen_url: String, work: Option<DbWork>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::LinkedWork { screen_url, work })?;
+        Ok(())
+    }
+
+    pub fn return_works_missing_thumbs(&mut self, works: Vec<MissingThumb>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WorksMissingThumbs(works))?;
+        Ok(())
+    }
+
     pub fn fetch_tags_initial_complete(&mut self, tags: HashMap<TagId, DbTag>) -> Result<()> {
         self.tx_to_runner.send(DataUpdate::InitialTags(tags))?;
         Ok(())
     }
 
-    pub fn fetch_tags_local_counts_complete(&mut self, counts: Vec<(TagId, u64)>) -> Result<()> {
+    pub fn return_tags_window(
+        &mut self,
+        generation: u64,
+        total: usize,
+        offset: usize,
+        tag_ids: Vec<TagId>,
+    ) -> Result<()> {
+        self.tx_to_runner.send(DataUpdate::TagsWindow {
+            generation,
+            total,
+            offset,
+            tag_ids,
+        })?;
+        Ok(())
+    }
+
+    pub fn return_watched_tag_counts(&mut self, counts: Vec<(TagId, u64)>) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::WatchedTagCounts(counts))?;
+        Ok(())
+    }
+
+    pub fn note_tag_watched_status_changed(&mut self, tag_id: TagId, watched: bool) -> Result<()> {
+        self.tx_to_runner
+            .send(DataUpdate::TagWatchedStatusChanged { tag_id, watched })?;
+        Ok(())
+    }
+
+    pub fn fetch_tags_local_counts_complete(
+        &mut self,
+        counts: Vec<(TagId, u64, u64)>,
+    ) -> Result<()> {
         self.tx_to_runner
             .send(DataUpdate::TagsLocalCounts(counts))?;
         Ok(())
@@ -0,0 +1,996 @@
+// Where downloaded files live on disk.
+//
+// Everything used to go under the data dir. Users with big collections want to split things
+// up, e.g. previews on a fast SSD and full size works on a NAS, so files are now placed on one
+// of several named storage roots according to per-kind rules. Paths in the DB are stored
+// relative to their root: `xx/yy/<hash>.ext` is on the default root (the data dir), and
+// `<root>:xx/yy/<hash>.ext` is on the named root. Keeping the root name rather than the
+// absolute path means that remounting a volume elsewhere only needs a config change.
+//
+// The config lives next to the binary in `storage.json`, or in the profile's folder for a named
+// profile, rather than in the app state, as we need it to find the data dir, which is where the
+// app state is saved.
+//
+// Roots can also be remote (S3-compatible object storage or WebDAV). Files on remote roots are
+// pulled into a size-limited cache under the data dir when they are viewed; the metadata DB and
+// app state always stay local.
+//
+// Note: we have no OS keyring integration, so the credentials for remote roots (the S3 secret
+//       key, the WebDAV password) are saved in plain text in `storage.json`. On unix the file is
+//       only readable by its owner; elsewhere it is protected only by the folder it is in. Don't
+//       put it anywhere that is shared or synced.
+//
+// Encrypted roots are local directories of sealed files. They go through the same cache, so the
+// plain text copies are only around while the app is running: we clear them out at startup and
+// exit.
+use crate::shared::blob::{
+    BlobStore, EncryptedStore, LocalStore, S3Config, S3Store, WebDavConfig, WebDavStore, move_file,
+};
+use anyhow::{Result, bail, ensure};
+use crossbeam::channel::{self, Sender};
+use jiff::{SignedDuration, Timestamp};
+use log::{info, warn};
+use lru::LruCache;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::SystemTime,
+};
+
+const CONFIG_FILE_NAME: &str = "storage.json";
+
+// The config holds remote credentials, so keep it private to the user where we can. The
+// permissions are tightened before anything is written, as an older file may be readable.
+fn write_config(path: &Path, json: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::{
+            io::Write as _,
+            os::unix::fs::{OpenOptionsExt as _, PermissionsExt as _},
+        };
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(json.as_bytes())
+    }
+    #[cfg(not(unix))]
+    fs::write(path, json)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum DataKind {
+    Preview,
+    Screen,
+    Archive,
+    Model3D,
+}
+
+impl DataKind {
+    pub const ALL: [Self; 4] = [Self::Preview, Self::Screen, Self::Archive, Self::Model3D];
+
+    // The works column that stores paths for this kind of data.
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::Preview => "preview_path",
+            Self::Screen => "screen_path",
+            Self::Archive => "archive_path",
+            Self::Model3D => "model_path",
+        }
+    }
+
+    // The works column with the url that this kind of data was downloaded from.
+    pub fn url_column(&self) -> &'static str {
+        match self {
+            Self::Preview => "preview_url",
+            Self::Screen => "screen_url",
+            Self::Archive => "archive_url",
+            Self::Model3D => "model_url",
+        }
+    }
+
+    pub fn from_column(column: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.column() == column)
+    }
+}
+
+impl fmt::Display for DataKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preview => write!(f, "Previews"),
+            Self::Screen => write!(f, "Screen Images"),
+            Self::Archive => write!(f, "Archives"),
+            Self::Model3D => write!(f, "3D Models"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RootBackend {
+    // Files live under the root's path.
+    #[default]
+    Local,
+    S3(S3Config),
+    WebDav(WebDavConfig),
+    // Files live under the root's path, sealed with the archive's data key.
+    Encrypted,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StorageRoot {
+    name: String,
+    // Empty for remote roots, other than encrypted ones.
+    path: PathBuf,
+    #[serde(default)]
+    backend: RootBackend,
+}
+
+impl StorageRoot {
+    pub fn new(name: &str, path: &Path) -> Self {
+        Self {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            backend: RootBackend::Local,
+        }
+    }
+
+    pub fn remote(name: &str, backend: RootBackend) -> Self {
+        Self {
+            name: name.to_owned(),
+            path: PathBuf::new(),
+            backend,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Whether files are opened from the cache, rather than in place.
+    pub fn is_remote(&self) -> bool {
+        self.backend != RootBackend::Local
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.backend == RootBackend::Encrypted
+    }
+
+    // A short description of where the files are, for display.
+    pub fn describe(&self) -> String {
+        match &self.backend {
+            RootBackend::Local => self.path.display().to_string(),
+            RootBackend::S3(s3) => format!("s3://{}/{} at {}", s3.bucket, s3.prefix, s3.endpoint),
+            RootBackend::WebDav(dav) => dav.url.clone(),
+            RootBackend::Encrypted => format!("{} (encrypted)", self.path.display()),
+        }
+    }
+
+    fn open(&self) -> Result<Arc<dyn BlobStore>> {
+        Ok(match &self.backend {
+            RootBackend::Local => Arc::new(LocalStore::new(&self.path)),
+            RootBackend::S3(s3) => Arc::new(S3Store::new(s3)?),
+            RootBackend::WebDav(dav) => Arc::new(WebDavStore::new(dav)?),
+            RootBackend::Encrypted => Arc::new(EncryptedStore::new(&self.path)?),
+        })
+    }
+}
+
+// Note: names must be longer than one character so that they can never be confused with a
+//       Windows drive letter.
+pub fn is_valid_root_name(name: &str) -> bool {
+    name.len() > 1
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Split a stored path into its root name, if it is not on the default root, and the path
+// relative to that root.
+pub fn split_stored_path(stored: &str) -> (Option<&str>, &str) {
+    if let Some((root, rel)) = stored.split_once(':')
+        && is_valid_root_name(root)
+    {
+        return (Some(root), rel);
+    }
+    (None, stored)
+}
+
+pub fn join_stored_path(root: Option<&str>, rel: &str) -> String {
+    match root {
+        Some(root) => format!("{root}:{rel}"),
+        None => rel.to_owned(),
+    }
+}
+
+// The root-relative location for a downloaded url: the url hash, split into two directory
+// levels so that no directory gets too big, plus the url's extension so that we know how to
+// open it.
+pub fn relative_path_for_url(url: &str) -> String {
+    let ext = url
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    let key = Sha256::digest(url.as_bytes());
+    let key = format!("{key:x}");
+    let level1 = &key[0..2];
+    let level2 = &key[2..4];
+    let file_base = &key[4..];
+    format!("{level1}/{level2}/{file_base}.{ext}")
+}
+
+// Off-load full size files for works that nobody has looked at in a while to a slower root, e.g.
+// an external drive. Previews stay where they are so that the gallery still works; the rest
+// comes back automatically the next time the work is viewed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColdPolicy {
+    enabled: bool,
+    root: String,
+    after_months: u32,
+    keep_favorites: bool,
+}
+
+impl Default for ColdPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            root: String::new(),
+            after_months: 6,
+            keep_favorites: true,
+        }
+    }
+}
+
+impl ColdPolicy {
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.root.is_empty()
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn keep_favorites(&self) -> bool {
+        self.keep_favorites
+    }
+
+    // Works last accessed before this are cold.
+    pub fn cutoff(&self, now: Timestamp) -> Timestamp {
+        let age = SignedDuration::from_hours(24 * 30 * i64::from(self.after_months));
+        now.checked_sub(age).unwrap_or(Timestamp::MIN)
+    }
+
+    // Returns true if anything changed.
+    pub fn ui(&mut self, roots: &[StorageRoot], ui: &mut egui::Ui) -> bool {
+        let prior = self.clone();
+        ui.checkbox(
+            &mut self.enabled,
+            "Move full size files for unviewed works to cold storage",
+        );
+        ui.add_enabled_ui(self.enabled, |ui| {
+            egui::Grid::new("cold_policy_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Cold Root");
+                    egui::ComboBox::new("cold_policy_root", "")
+                        .selected_text(self.root.as_str())
+                        .show_ui(ui, |ui| {
+                            for root in roots {
+                                ui.selectable_value(
+                                    &mut self.root,
+                                    root.name().to_owned(),
+                                    root.name(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("Not Viewed For");
+                    ui.add(
+                        egui::DragValue::new(&mut self.after_months)
+                            .range(1..=120)
+                            .suffix(" months"),
+                    );
+                    ui.end_row();
+                });
+            ui.checkbox(&mut self.keep_favorites, "Keep favorites local");
+        });
+        *self != prior
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    // Where the metadata DB, app state, and default root live; None for `<prefix>/data`.
+    data_dir: Option<PathBuf>,
+
+    // Extra named roots, in addition to the data dir.
+    roots: Vec<StorageRoot>,
+
+    // Which root each kind of data goes on; missing kinds go in the data dir.
+    rules: BTreeMap<DataKind, String>,
+
+    cold: ColdPolicy,
+
+    // How much space files from remote roots may take up locally.
+    cache_max_mb: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            roots: Vec::new(),
+            rules: BTreeMap::new(),
+            cold: ColdPolicy::default(),
+            cache_max_mb: 4096,
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.data_dir.as_deref()
+    }
+
+    pub fn roots(&self) -> &[StorageRoot] {
+        &self.roots
+    }
+
+    pub fn root(&self, name: &str) -> Option<&StorageRoot> {
+        self.roots.iter().find(|root| root.name == name)
+    }
+
+    fn check_new_root_name(&self, name: &str) -> Result<()> {
+        ensure!(
+            is_valid_root_name(name),
+            "root names must be at least two letters, numbers, '-', or '_'"
+        );
+        ensure!(
+            self.root(name).is_none(),
+            "there is already a root named {name}"
+        );
+        Ok(())
+    }
+
+    pub fn add_root(&mut self, name: &str, path: &Path) -> Result<()> {
+        self.check_new_root_name(name)?;
+        ensure!(path.is_absolute(), "root paths must be absolute");
+        self.roots.push(StorageRoot::new(name, path));
+        Ok(())
+    }
+
+    pub fn add_encrypted_root(&mut self, name: &str, path: &Path) -> Result<()> {
+        self.check_new_root_name(name)?;
+        ensure!(path.is_absolute(), "root paths must be absolute");
+        let root = StorageRoot {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            backend: RootBackend::Encrypted,
+        };
+        // Note: this fails if the archive isn't encrypted, as there is no key to seal files with.
+        root.open()?;
+        self.roots.push(root);
+        Ok(())
+    }
+
+    pub fn add_remote_root(&mut self, name: &str, backend: RootBackend) -> Result<()> {
+        self.check_new_root_name(name)?;
+        let root = StorageRoot::remote(name, backend);
+        ensure!(root.is_remote(), "remote roots need a remote backend");
+        // Catch obviously broken settings now, rather than on the first download.
+        root.open()?;
+        self.roots.push(root);
+        Ok(())
+    }
+
+    pub fn cache_max_mb(&self) -> u64 {
+        self.cache_max_mb
+    }
+
+    pub fn set_cache_max_mb(&mut self, cache_max_mb: u64) {
+        self.cache_max_mb = cache_max_mb;
+    }
+
+    fn cache_max_bytes(&self) -> u64 {
+        self.cache_max_mb.saturating_mul(1024 * 1024)
+    }
+
+    // Note: files already on the root stay there, so refuse while the rules still send
+    //       anything to it; the user needs to re-home those files first.
+    pub fn remove_root(&mut self, name: &str) -> Result<()> {
+        if let Some((kind, _)) = self.rules.iter().find(|(_, root)| *root == name) {
+            bail!("{kind} are still stored on {name}");
+        }
+        if self.cold.enabled && self.cold.root == name {
+            bail!("{name} is used for cold storage");
+        }
+        self.roots.retain(|root| root.name != name);
+        Ok(())
+    }
+
+    // Point the config at a new data dir. Roots that lived inside the old data dir were copied
+    // along with it, so rewrite them to point at their copies.
+    pub fn move_data_dir(&mut self, from: &Path, to: &Path) {
+        for root in &mut self.roots {
+            if let Ok(rel) = root.path.strip_prefix(from) {
+                root.path = to.join(rel);
+            }
+        }
+        self.data_dir = Some(to.to_owned());
+    }
+
+    pub fn rule(&self, kind: DataKind) -> Option<&str> {
+        self.rules.get(&kind).map(String::as_str)
+    }
+
+    pub fn cold_policy(&self) -> &ColdPolicy {
+        &self.cold
+    }
+
+    pub fn cold_policy_mut(&mut self) -> &mut ColdPolicy {
+        &mut self.cold
+    }
+
+    pub fn set_rule(&mut self, kind: DataKind, root: Option<String>) {
+        match root {
+            Some(root) => self.rules.insert(kind, root),
+            None => self.rules.remove(&kind),
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct StorageState {
+    prefix: PathBuf,
+    // Note: the data dir is fixed for the lifetime of the process; a new one in the config
+    //       only takes effect on restart.
+    data_dir: PathBuf,
+    config: StorageConfig,
+    // One per named root that could be opened; the default root is always the data dir.
+    stores: HashMap<String, Arc<dyn BlobStore>>,
+}
+
+impl StorageState {
+    fn apply(&mut self, config: StorageConfig) {
+        self.stores = config
+            .roots
+            .iter()
+            .filter_map(|root| match root.open() {
+                Ok(store) => Some((root.name.clone(), store)),
+                Err(e) => {
+                    warn!("Storage root {} is unavailable: {e}", root.name);
+                    None
+                }
+            })
+            .collect();
+        self.config = config;
+    }
+
+    fn cache_dir(&self) -> PathBuf {
+        self.data_dir.join("remote-cache")
+    }
+
+    fn root_path(&self, root: Option<&str>) -> PathBuf {
+        match root.and_then(|name| self.config.root(name)) {
+            Some(root) if root.is_remote() => self.cache_dir().join(&root.name),
+            Some(root) => root.path.clone(),
+            None => self.data_dir.clone(),
+        }
+    }
+
+    // Where a root-relative path can be opened: in place for local roots, or in the cache for
+    // remote roots.
+    fn local_path(&self, root: Option<&str>, rel: &str) -> PathBuf {
+        match root.and_then(|name| self.stores.get(name).map(|store| (name, store))) {
+            Some((name, store)) => store
+                .local_path(rel)
+                .unwrap_or_else(|| self.cache_dir().join(name).join(rel)),
+            None => self.root_path(root).join(rel),
+        }
+    }
+
+    fn remote(&self, root: Option<&str>, rel: &str) -> Option<Arc<dyn BlobStore>> {
+        root.and_then(|name| self.stores.get(name))
+            .filter(|store| store.local_path(rel).is_none())
+            .cloned()
+    }
+}
+
+// Files from remote roots that we have pulled down, most recently used last, so that we can
+// throw out the oldest when the cache gets bigger than the user wants.
+#[derive(Debug)]
+struct RemoteCache {
+    max_bytes: u64,
+    total_bytes: u64,
+    entries: LruCache<PathBuf, u64>,
+}
+
+impl Default for RemoteCache {
+    fn default() -> Self {
+        Self {
+            max_bytes: u64::MAX,
+            total_bytes: 0,
+            entries: LruCache::unbounded(),
+        }
+    }
+}
+
+impl RemoteCache {
+    fn scan(dir: &Path, out: &mut Vec<(SystemTime, PathBuf, u64)>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if meta.is_dir() {
+                Self::scan(&entry.path(), out)?;
+            } else if entry.path().extension().is_some_and(|ext| ext == "partial") {
+                // Left over from an interrupted fetch.
+                fs::remove_file(entry.path())?;
+            } else {
+                out.push((meta.modified()?, entry.path(), meta.len()));
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, dir: &Path, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+        if !dir.exists() {
+            return;
+        }
+        let mut files = Vec::new();
+        if let Err(e) = Self::scan(dir, &mut files) {
+            warn!("Failed to scan the remote cache at {}: {e}", dir.display());
+        }
+        files.sort();
+        for (_, path, size) in files {
+            self.insert(path, size);
+        }
+    }
+
+    fn touch(&mut self, path: &Path) {
+        self.entries.get(path);
+    }
+
+    fn insert(&mut self, path: PathBuf, size: u64) {
+        if let Some(prior) = self.entries.put(path, size) {
+            self.total_bytes -= prior;
+        }
+        self.total_bytes += size;
+        self.evict();
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(size) = self.entries.pop(path) {
+            self.total_bytes -= size;
+        }
+        fs::remove_file(path).ok();
+    }
+
+    // Note: never evict the last entry, even if it is over budget on its own; it is the one
+    //       that somebody is about to look at.
+    fn evict(&mut self) {
+        while self.total_bytes > self.max_bytes && self.entries.len() > 1 {
+            if let Some((path, size)) = self.entries.pop_lru() {
+                self.total_bytes -= size;
+                fs::remove_file(&path).ok();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Prefetcher {
+    tx: Option<Sender<String>>,
+    pending: HashSet<String>,
+}
+
+// Shared handle to the storage layout. Anything that turns a stored path into a real path, or
+// decides where a new file goes, should go through here rather than joining on the data dir.
+#[derive(Clone, Debug, Default)]
+pub struct Storage {
+    state: Arc<RwLock<StorageState>>,
+    cache: Arc<Mutex<RemoteCache>>,
+    prefetcher: Arc<Mutex<Prefetcher>>,
+}
+
+impl Storage {
+    pub fn load(prefix: &Path) -> Result<Self> {
+        let path = prefix.join(CONFIG_FILE_NAME);
+        let config: StorageConfig = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            StorageConfig::default()
+        };
+        let data_dir = config
+            .data_dir
+            .clone()
+            .unwrap_or_else(|| prefix.join("data"));
+        for root in &config.roots {
+            info!("Storage root {}: {}", root.name, root.describe());
+        }
+        let mut state = StorageState {
+            prefix: prefix.to_owned(),
+            data_dir,
+            ..StorageState::default()
+        };
+        let mut cache = RemoteCache::default();
+        cache.load(&state.cache_dir(), config.cache_max_bytes());
+        state.apply(config);
+        Ok(Self {
+            state: Arc::new(RwLock::new(state)),
+            cache: Arc::new(Mutex::new(cache)),
+            prefetcher: Arc::default(),
+        })
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.state.read().data_dir.clone()
+    }
+
+    // Throw out the plain text copies of files from encrypted roots.
+    pub fn clear_decrypted(&self) {
+        let state = self.state.read();
+        let mut cache = self.cache.lock();
+        for root in state.config.roots.iter().filter(|root| root.is_encrypted()) {
+            let dir = state.cache_dir().join(&root.name);
+            let cached = cache
+                .entries
+                .iter()
+                .map(|(path, _)| path.clone())
+                .filter(|path| path.starts_with(&dir))
+                .collect::<Vec<_>>();
+            for path in cached {
+                cache.remove(&path);
+            }
+            if dir.exists()
+                && let Err(e) = fs::remove_dir_all(&dir)
+            {
+                warn!(
+                    "Failed to clear decrypted files from {}: {e}",
+                    dir.display()
+                );
+            }
+        }
+    }
+
+    pub fn config(&self) -> StorageConfig {
+        self.state.read().config.clone()
+    }
+
+    pub fn set_config(&self, config: StorageConfig) -> Result<()> {
+        for name in config.rules.values() {
+            ensure!(config.root(name).is_some(), "no storage root named {name}");
+        }
+        if config.cold.is_active() {
+            ensure!(
+                config.root(&config.cold.root).is_some(),
+                "no storage root named {}",
+                config.cold.root
+            );
+        }
+        let mut state = self.state.write();
+        write_config(
+            &state.prefix.join(CONFIG_FILE_NAME),
+            &serde_json::to_string_pretty(&config)?,
+        )?;
+        {
+            let mut cache = self.cache.lock();
+            cache.max_bytes = config.cache_max_bytes();
+            cache.evict();
+        }
+        state.apply(config);
+        Ok(())
+    }
+
+    // The directory that new files of the given kind should go in.
+    pub fn root_path_for(&self, kind: DataKind) -> PathBuf {
+        let state = self.state.read();
+        state.root_path(state.config.rule(kind))
+    }
+
+    // Whether the stored path is on a root whose files are sealed with the archive's key.
+    pub fn is_encrypted(&self, stored: &str) -> bool {
+        let (root, _) = split_stored_path(stored);
+        let state = self.state.read();
+        root.and_then(|name| state.config.root(name))
+            .is_some_and(StorageRoot::is_encrypted)
+    }
+
+    // Turn a path from the DB into a path we can open. For files on remote roots this is where
+    // the file will be in the cache; check `is_available` before opening it.
+    pub fn resolve(&self, stored: &Path) -> PathBuf {
+        if stored.is_absolute() {
+            return stored.to_owned();
+        }
+        let Some(stored_str) = stored.to_str() else {
+            return self.data_dir().join(stored);
+        };
+        let (root, rel) = split_stored_path(stored_str);
+        let state = self.state.read();
+        if let Some(name) = root
+            && state.config.root(name).is_none()
+        {
+            warn!("No storage root named {name}; looking in the data dir for {rel}");
+        }
+        let path = state.local_path(root, rel);
+        if state.remote(root, rel).is_some() {
+            self.cache.lock().touch(&path);
+        }
+        path
+    }
+
+    fn remote(&self, stored: &str) -> Option<(Arc<dyn BlobStore>, String)> {
+        let (root, rel) = split_stored_path(stored);
+        let store = self.state.read().remote(root, rel)?;
+        Some((store, rel.to_owned()))
+    }
+
+    // True if the file can be opened right now without going to the network.
+    pub fn is_available(&self, stored: &Path) -> bool {
+        match stored.to_str() {
+            Some(stored_str) if self.remote(stored_str).is_some() => self.resolve(stored).exists(),
+            _ => true,
+        }
+    }
+
+    // Start pulling a remote file into the cache in the background.
+    pub fn prefetch(&self, stored: &Path) {
+        let Some(stored) = stored.to_str() else {
+            return;
+        };
+        if self.is_available(Path::new(stored)) {
+            return;
+        }
+        let mut prefetcher = self.prefetcher.lock();
+        if !prefetcher.pending.insert(stored.to_owned()) {
+            return;
+        }
+        let tx = prefetcher.tx.get_or_insert_with(|| self.spawn_prefetcher());
+        tx.send(stored.to_owned()).ok();
+    }
+
+    fn spawn_prefetcher(&self) -> Sender<String> {
+        let (tx, rx) = channel::unbounded::<String>();
+        let storage = self.clone();
+        thread::spawn(move || {
+            for stored in rx {
+                if let Err(e) = storage.ensure_local(Path::new(&stored)) {
+                    warn!("Failed to fetch {stored}: {e}");
+                }
+                storage.prefetcher.lock().pending.remove(&stored);
+            }
+        });
+        tx
+    }
+
+    // Returns a path that can be opened for the stored path, fetching it into the cache first
+    // if it is on a remote root. This blocks on the network, so keep it off the UX thread.
+    pub fn ensure_local(&self, stored: &Path) -> Result<PathBuf> {
+        let path = self.resolve(stored);
+        let Some((store, key)) = stored.to_str().and_then(|stored| self.remote(stored)) else {
+            return Ok(path);
+        };
+        if path.exists() {
+            return Ok(path);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("partial");
+        store.fetch(&key, &partial)?;
+        fs::rename(&partial, &path)?;
+        self.cache
+            .lock()
+            .insert(path.clone(), fs::metadata(&path)?.len());
+        Ok(path)
+    }
+
+    // Note: this goes to the network for remote files that are not in the cache.
+    pub fn exists(&self, stored: &str) -> Result<bool> {
+        if self.resolve(Path::new(stored)).exists() {
+            return Ok(true);
+        }
+        match self.remote(stored) {
+            Some((store, key)) => store.exists(&key),
+            None => Ok(false),
+        }
+    }
+
+    // Downloads for remote roots are written to the cache path from `place_for_url`; this
+    // uploads them to the remote.
+    pub fn commit(&self, stored: &str) -> Result<()> {
+        let Some((store, key)) = self.remote(stored) else {
+            return Ok(());
+        };
+        let path = self.resolve(Path::new(stored));
+        store.store(&key, &path)?;
+        self.cache
+            .lock()
+            .insert(path.clone(), fs::metadata(&path)?.len());
+        Ok(())
+    }
+
+    // Move a file from one stored path to another, on whatever roots they are on.
+    pub fn transfer(&self, from: &str, to: &str, keep_source: bool) -> Result<()> {
+        // Note: a move within a remote root stays there, so that an encrypted file is never
+        //       decrypted just to be moved.
+        if !keep_source
+            && split_stored_path(from).0 == split_stored_path(to).0
+            && let Some((store, from_key)) = self.remote(from)
+            && let Some((_, to_key)) = self.remote(to)
+            && store.rename(&from_key, &to_key)?
+        {
+            self.cache.lock().remove(&self.resolve(Path::new(from)));
+            return Ok(());
+        }
+        let src = self.ensure_local(Path::new(from))?;
+        let from_remote = self.remote(from);
+        match self.remote(to) {
+            Some((store, key)) => {
+                store.store(&key, &src)?;
+                if !keep_source && from_remote.is_none() {
+                    fs::remove_file(&src)?;
+                }
+            }
+            // Note: the source is only a cache entry if it is remote, so leave it to the cache.
+            None => move_file(
+                &src,
+                &self.resolve(Path::new(to)),
+                keep_source || from_remote.is_some(),
+            )?,
+        }
+        if !keep_source && let Some((store, key)) = from_remote {
+            store.remove(&key)?;
+            self.cache.lock().remove(&src);
+        }
+        Ok(())
+    }
+
+    // Delete the file at the stored path, from whatever root it is on.
+    pub fn remove(&self, stored: &str) -> Result<()> {
+        let path = self.resolve(Path::new(stored));
+        match self.remote(stored) {
+            Some((store, key)) => {
+                store.remove(&key)?;
+                self.cache.lock().remove(&path);
+            }
+            None if path.exists() => fs::remove_file(&path)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    // Decide where to put the data for the url. Returns the absolute path for I/O and the
+    // stored path for the DB. For remote roots the file must be passed to `commit` once it
+    // has been written.
+    pub fn place_for_url(&self, kind: DataKind, url: &str) -> io::Result<(PathBuf, String)> {
+        let rel = relative_path_for_url(url);
+        let state = self.state.read();
+        let root = state.config.rule(kind);
+        let abs_path = state.local_path(root, &rel);
+        if let Some(parent) = abs_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok((abs_path, join_stored_path(root, &rel)))
+    }
+
+    // If the rules say that the stored path belongs on a different root, returns where it
+    // should be stored instead.
+    pub fn placement_for(&self, kind: DataKind, stored: &str) -> Option<String> {
+        let (current, rel) = split_stored_path(stored);
+        let state = self.state.read();
+        let wanted = state.config.rule(kind);
+        (current != wanted).then(|| join_stored_path(wanted, rel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path_for_url() {
+        assert_eq!(
+            relative_path_for_url("https://example.com/image.jpg"),
+            "e5/db/82b5bf63d49d80c5533616892d3386f43955369520986d67653c700fc53c.jpg"
+        );
+        assert_eq!(
+            relative_path_for_url("https://example.com/image.jpg?id=1234"),
+            "2d/fc/1d0596854b006b8c957f01e07bb0694c77a02cc36efec7bd610ba0409c24.jpg"
+        );
+    }
+
+    #[test]
+    fn test_stored_paths() {
+        assert_eq!(split_stored_path("e5/db/abc.jpg"), (None, "e5/db/abc.jpg"));
+        assert_eq!(
+            split_stored_path("nas:e5/db/abc.jpg"),
+            (Some("nas"), "e5/db/abc.jpg")
+        );
+        assert_eq!(split_stored_path("C:foo/abc.jpg"), (None, "C:foo/abc.jpg"));
+        assert_eq!(split_stored_path("a b:abc.jpg"), (None, "a b:abc.jpg"));
+        assert_eq!(
+            join_stored_path(Some("nas"), "e5/abc.jpg"),
+            "nas:e5/abc.jpg"
+        );
+        assert_eq!(join_stored_path(None, "e5/abc.jpg"), "e5/abc.jpg");
+    }
+
+    #[test]
+    fn test_cold_policy_cutoff() -> Result<()> {
+        let now: Timestamp = "2025-07-01T00:00:00Z".parse()?;
+        let policy = ColdPolicy::default();
+        assert_eq!(
+            policy.cutoff(now),
+            "2025-01-02T00:00:00Z".parse::<Timestamp>()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_and_placement() -> Result<()> {
+        let storage = Storage::load(Path::new("/nonexistent/artchiver"))?;
+        let mut config = storage.config();
+        config.add_root("nas", Path::new("/mnt/nas/art"))?;
+        config.set_rule(DataKind::Screen, Some("nas".to_owned()));
+        assert!(config.remove_root("nas").is_err());
+        config.add_root("ssd", Path::new("/nonexistent/artchiver/data/ssd"))?;
+        let mut moved = config.clone();
+        moved.move_data_dir(
+            Path::new("/nonexistent/artchiver/data"),
+            Path::new("/mnt/big/data"),
+        );
+        assert_eq!(moved.data_dir(), Some(Path::new("/mnt/big/data")));
+        assert_eq!(
+            moved.root("ssd").map(StorageRoot::path),
+            Some(Path::new("/mnt/big/data/ssd"))
+        );
+        assert_eq!(
+            moved.root("nas").map(StorageRoot::path),
+            Some(Path::new("/mnt/nas/art"))
+        );
+        storage.state.write().apply(config);
+
+        assert_eq!(
+            storage.resolve(Path::new("e5/abc.jpg")),
+            Path::new("/nonexistent/artchiver/data/e5/abc.jpg")
+        );
+        assert_eq!(
+            storage.resolve(Path::new("nas:e5/abc.jpg")),
+            Path::new("/mnt/nas/art/e5/abc.jpg")
+        );
+        assert_eq!(
+            storage.placement_for(DataKind::Screen, "e5/abc.jpg"),
+            Some("nas:e5/abc.jpg".to_owned())
+        );
+        assert_eq!(
+            storage.placement_for(DataKind::Screen, "nas:e5/abc.jpg"),
+            None
+        );
+        assert_eq!(
+            storage.placement_for(DataKind::Preview, "nas:e5/abc.jpg"),
+            Some("e5/abc.jpg".to_owned())
+        );
+        Ok(())
+    }
+}
@@ -6,11 +6,12 @@ use crate::{
         },
         writer::DbWriteHandle,
     },
-    plugin::host::PluginHost,
+    plugin::host::{FETCH_PAGE_SIZE, PluginHost},
+    shared::deep_link::DeepLink,
     ux::tutorial::{Tutorial, TutorialStep},
 };
 use itertools::Itertools as _;
-use log::{trace, warn};
+use log::{error, trace, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -150,6 +151,18 @@ impl TagSet {
         self.changed = true;
     }
 
+    // Put back a selection from the navigation history.
+    pub fn restore(&mut self, enabled: &[TagId], disabled: &[TagId]) {
+        self.enabled = enabled.iter().copied().collect();
+        self.disabled = disabled.iter().copied().collect();
+        self.changed = true;
+    }
+
+    // Whether the works view has yet to catch up with a change to the selection.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
     pub fn is_empty(&self) -> bool {
         self.enabled.is_empty()
     }
@@ -266,6 +279,7 @@ impl TagSet {
             {
                 self.clear();
                 self.enable(tag);
+                mark_seen(tag, db_write);
             }
             if tutorial
                 .add_step(
@@ -283,6 +297,7 @@ impl TagSet {
                     self.unselect(tag);
                 } else {
                     self.enable(tag);
+                    mark_seen(tag, db_write);
                 }
             }
             if tutorial
@@ -317,6 +332,20 @@ impl TagSet {
                     .set_tag_favorite(tag.id(), !tag.favorite())
                     .expect("database closed");
             }
+            if ui
+                .add(
+                    egui::Button::new("👁")
+                        .small()
+                        .selected(tag.watched())
+                        .corner_radius(egui::CornerRadius::same(0)),
+                )
+                .on_hover_text("toggle watching for new works")
+                .clicked()
+            {
+                db_write
+                    .set_tag_watched(tag.id(), !tag.watched())
+                    .expect("database closed");
+            }
 
             ui.label("  ");
 
@@ -330,12 +359,42 @@ impl TagSet {
             } else {
                 format!("{} ([loading...] of {})", tag.name(), tag.network_count())
             };
-            if status.disabled() {
-                ui.label(egui::RichText::new(content).strikethrough());
+            let label = if status.disabled() {
+                ui.label(egui::RichText::new(content).strikethrough())
             } else if status.enabled() {
-                ui.label(egui::RichText::new(content).strong());
+                ui.label(egui::RichText::new(content).strong())
             } else {
-                ui.label(content);
+                ui.label(content)
+            };
+            if tag.watched() && tag.new_count() > 0 {
+                ui.label(
+                    egui::RichText::new(format!(" {} new ", tag.new_count()))
+                        .strong()
+                        .color(ui.visuals().strong_text_color())
+                        .background_color(ui.visuals().selection.bg_fill),
+                )
+                .on_hover_text("works that arrived since you last viewed this tag");
+            }
+            label.context_menu(|ui| {
+                if ui.button("Copy Link").clicked() {
+                    let link = DeepLink::Tag {
+                        name: tag.name().to_owned(),
+                    };
+                    ui.ctx().copy_text(link.to_string());
+                    ui.close();
+                }
+            });
+
+            if let (Some(local_count), Some(downloaded)) =
+                (tag.local_count(), tag.downloaded_count())
+                && local_count > 0
+            {
+                ui.add(
+                    egui::ProgressBar::new(downloaded as f32 / local_count as f32)
+                        .desired_width(40.)
+                        .desired_height(ui.spacing().interact_size.y * 0.5),
+                )
+                .on_hover_text(format!("{downloaded} of {local_count} works downloaded"));
             }
 
             ui.label("  ");
@@ -349,7 +408,39 @@ impl TagSet {
                 .on_hover_text("refresh works")
                 .clicked()
             {
-                host.refresh_works_for_tag(tag).ok();
+                if let Err(e) = host.request_refresh_works_for_tag(tag) {
+                    error!("Failed to refresh works for {}: {e}", tag.name());
+                }
+            }
+            let offset = host.next_work_offset(tag);
+            if ui
+                .small_button("⏩")
+                .on_hover_text(format!(
+                    "fetch works {}-{}, rather than all of them",
+                    offset + 1,
+                    offset + FETCH_PAGE_SIZE
+                ))
+                .clicked()
+                && let Err(e) = host.fetch_more_works_for_tag(tag)
+            {
+                error!("Failed to fetch more works for {}: {e}", tag.name());
+            }
+            let missing = tag.missing_count();
+            if ui
+                .add_enabled(missing > 0, egui::Button::new("⬇").small())
+                .on_hover_text(format!("download the {missing} missing works"))
+                .clicked()
+                && let Err(e) = host.download_missing_for_tag(tag)
+            {
+                error!("Failed to queue downloads for {}: {e}", tag.name());
+            }
+            let metadata_only = host.is_tag_metadata_only(tag);
+            if ui
+                .add(egui::Button::new("📄").small().selected(metadata_only))
+                .on_hover_text("metadata only: record works for this tag without downloading them")
+                .clicked()
+            {
+                host.set_tag_metadata_only(tag, !metadata_only);
             }
             if ui
                 .add_enabled(tag.wiki_url().is_some(), egui::Button::new("🔗").small())
@@ -369,3 +460,13 @@ impl TagSet {
         });
     }
 }
+
+// Viewing a watched tag's works clears its count of new ones.
+fn mark_seen(tag: &DbTag, db_write: &DbWriteHandle) {
+    if tag.watched()
+        && tag.new_count() > 0
+        && let Err(e) = db_write.mark_tag_seen(tag.id())
+    {
+        error!("Failed to mark {} as seen: {e}", tag.name());
+    }
+}
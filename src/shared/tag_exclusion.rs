@@ -0,0 +1,161 @@
+// Per-plugin patterns for tags that we never want to see, e.g. the thousands of location tags
+// that some sources attach to their works. Matching tags are dropped when refreshing a
+// plugin's tags, before they ever hit the database.
+use anyhow::Result;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PatternKind {
+    // `*` matches anything and `?` matches any one character.
+    #[default]
+    Glob,
+    Regex,
+}
+
+impl PatternKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Glob => "Glob",
+            Self::Regex => "Regex",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagPattern {
+    kind: PatternKind,
+    pattern: String,
+}
+
+impl TagPattern {
+    // Both kinds match the whole tag name, ignoring case.
+    fn compile(&self) -> Result<Regex> {
+        let source = match self.kind {
+            PatternKind::Glob => regex::escape(self.pattern.trim())
+                .replace(r"\*", ".*")
+                .replace(r"\?", "."),
+            PatternKind::Regex => self.pattern.trim().to_owned(),
+        };
+        Ok(RegexBuilder::new(&format!("^(?:{source})$"))
+            .case_insensitive(true)
+            .build()?)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagExclusions {
+    patterns: Vec<TagPattern>,
+}
+
+impl TagExclusions {
+    // Returns true if anything changed.
+    pub fn ui(&mut self, salt: &str, ui: &mut egui::Ui) -> bool {
+        let prior = self.clone();
+        let mut remove = None;
+        for (i, pattern) in self.patterns.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(format!("tag_exclusion_kind_{salt}_{i}"))
+                    .selected_text(pattern.kind.label())
+                    .show_ui(ui, |ui| {
+                        for kind in [PatternKind::Glob, PatternKind::Regex] {
+                            ui.selectable_value(&mut pattern.kind, kind, kind.label());
+                        }
+                    });
+                ui.text_edit_singleline(&mut pattern.pattern);
+                if let Err(e) = pattern.compile() {
+                    ui.colored_label(ui.visuals().error_fg_color, "invalid")
+                        .on_hover_text(e.to_string());
+                }
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.patterns.remove(i);
+        }
+        if ui.button("Add Pattern").clicked() {
+            self.patterns.push(TagPattern::default());
+        }
+        *self != prior
+    }
+
+    // Note: blank and invalid patterns are skipped; the UX flags the invalid ones.
+    fn compile(&self) -> Vec<Regex> {
+        self.patterns
+            .iter()
+            .filter(|pattern| !pattern.pattern.trim().is_empty())
+            .filter_map(|pattern| pattern.compile().ok())
+            .collect()
+    }
+}
+
+// Shared by the PluginHost, which configures it from each plugin's preferences, and the plugin
+// threads, which check refreshed tags against it.
+#[derive(Clone, Debug, Default)]
+pub struct TagExclusionFilter {
+    by_plugin: Arc<Mutex<HashMap<String, Vec<Regex>>>>,
+}
+
+impl TagExclusionFilter {
+    pub fn configure<'a>(&self, plugins: impl Iterator<Item = (String, &'a TagExclusions)>) {
+        *self.by_plugin.lock() = plugins
+            .map(|(name, exclusions)| (name, exclusions.compile()))
+            .filter(|(_, patterns)| !patterns.is_empty())
+            .collect();
+    }
+
+    pub fn is_excluded(&self, plugin: &str, tag: &str) -> bool {
+        self.by_plugin
+            .lock()
+            .get(plugin)
+            .is_some_and(|patterns| patterns.iter().any(|re| re.is_match(tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exclusions(patterns: &[(PatternKind, &str)]) -> TagExclusionFilter {
+        let exclusions = TagExclusions {
+            patterns: patterns
+                .iter()
+                .map(|(kind, pattern)| TagPattern {
+                    kind: *kind,
+                    pattern: (*pattern).to_owned(),
+                })
+                .collect(),
+        };
+        let filter = TagExclusionFilter::default();
+        filter.configure([("nga".to_owned(), &exclusions)].into_iter());
+        filter
+    }
+
+    #[test]
+    fn test_glob() {
+        let filter = exclusions(&[(PatternKind::Glob, "place: *"), (PatternKind::Glob, "c?t")]);
+        assert!(filter.is_excluded("nga", "Place: Paris"));
+        assert!(filter.is_excluded("nga", "cat"));
+        assert!(!filter.is_excluded("nga", "cats"));
+        assert!(!filter.is_excluded("nga", "Landscape"));
+        assert!(!filter.is_excluded("met", "Place: Paris"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let filter = exclusions(&[
+            (PatternKind::Regex, r"\d{4}s?"),
+            (PatternKind::Regex, "(unclosed"),
+            (PatternKind::Glob, " "),
+        ]);
+        assert!(filter.is_excluded("nga", "1890s"));
+        assert!(!filter.is_excluded("nga", "Painted in 1890"));
+        assert!(!filter.is_excluded("nga", "(unclosed"));
+    }
+}
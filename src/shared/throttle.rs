@@ -1,5 +1,7 @@
 use crate::shared::plugin::PluginCancellation;
-use parking_lot::Mutex;
+use artchiver_sdk::RateLimitStatus;
+use jiff::Timestamp;
+use parking_lot::{Mutex, MutexGuard};
 use std::{
     sync::Arc,
     thread::sleep,
@@ -13,11 +15,56 @@ pub enum ThrottleError {
     Cancelled,
 }
 
+// How long to wait after a 429/503 that doesn't say how long to wait; doubles on each
+// consecutive refusal, up to the max.
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+// Parse a Retry-After header value, which may be either a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str, now: Timestamp) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let when = jiff::fmt::rfc2822::parse(value).ok()?.timestamp();
+    Some(
+        now.duration_until(when)
+            .try_into()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+pub fn is_rate_limit_status(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+// What has gone through a throttle since the plugin started, for the plugin's health.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ThrottleTraffic {
+    pub requests: u64,
+    // Summed over all requests, from sending to the response's headers.
+    pub latency: Duration,
+    // Summed over all requests, waiting for the rate limit or for the remote's back off.
+    pub throttled: Duration,
+}
+
+impl ThrottleTraffic {
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.requests > 0).then(|| self.latency / self.requests.min(u32::MAX as u64) as u32)
+    }
+}
+
 #[derive(Debug)]
 struct CallingThrottleData {
     nb_call_times_limit: usize,
     expired_time: Duration,
     timestamps: Vec<Instant>,
+
+    // Set when the remote tells us to back off.
+    paused_until: Option<Instant>,
+    consecutive_backoffs: u32,
+
+    traffic: ThrottleTraffic,
 }
 
 #[derive(Clone, Debug)]
@@ -38,30 +85,96 @@ impl CallingThrottle {
                 nb_call_times_limit,
                 expired_time,
                 timestamps: Vec::new(),
+                paused_until: None,
+                consecutive_backoffs: 0,
+                traffic: ThrottleTraffic::default(),
             })),
         }
     }
 
+    // Note: change the limits in place, rather than making a new throttle, so that everyone
+    //       holding a clone (e.g. the UX, showing status) sees the change.
+    pub fn set_limits(&self, nb_call_times_limit: usize, expired_time: Duration) {
+        let mut data = self.lock.lock();
+        data.nb_call_times_limit = nb_call_times_limit;
+        data.expired_time = expired_time;
+    }
+
     pub fn throttle(&self, cancellation: &PluginCancellation) -> Result<(), ThrottleError> {
+        let start = Instant::now();
         let mut data = self.lock.lock();
-        while data.timestamps.len() == data.nb_call_times_limit {
+        loop {
             if cancellation.is_cancelled() {
                 return Err(ThrottleError::Cancelled);
             }
             let now = Instant::now();
             let timeout = data.expired_time;
             data.timestamps.retain(|&x| now.duration_since(x) < timeout);
-            if data.timestamps.len() >= data.nb_call_times_limit {
-                let mut time_to_sleep = data.timestamps[0] + data.expired_time - now;
-                if time_to_sleep > Duration::from_millis(100) {
-                    time_to_sleep = Duration::from_millis(100);
-                }
-                sleep(time_to_sleep);
-            }
+            let time_to_sleep = if let Some(until) = data.paused_until
+                && until > now
+            {
+                until - now
+            } else if data.timestamps.len() >= data.nb_call_times_limit.max(1) {
+                data.timestamps[0] + data.expired_time - now
+            } else {
+                break;
+            };
+            // Note: sleep unlocked so that status queries from the UX don't stall behind us.
+            MutexGuard::unlocked(&mut data, || {
+                sleep(time_to_sleep.min(Duration::from_millis(100)));
+            });
         }
+        data.paused_until = None;
         data.timestamps.push(Instant::now());
+        data.traffic.throttled += start.elapsed();
         Ok(())
     }
+
+    // Call when the remote refuses us with a 429 or 503; pauses all requests through this
+    // throttle until the remote says we can try again. Returns how long we will wait.
+    pub fn back_off(&self, retry_after: Option<&str>) -> Duration {
+        let mut data = self.lock.lock();
+        let delay = retry_after
+            .and_then(|v| parse_retry_after(v, Timestamp::now()))
+            .unwrap_or_else(|| MIN_BACKOFF.saturating_mul(1 << data.consecutive_backoffs.min(16)))
+            .min(MAX_BACKOFF);
+        data.consecutive_backoffs = data.consecutive_backoffs.saturating_add(1);
+        let until = Instant::now() + delay;
+        data.paused_until = Some(data.paused_until.map_or(until, |prior| prior.max(until)));
+        delay
+    }
+
+    // Call when the remote accepts a request, to reset the adaptive backoff.
+    pub fn note_success(&self) {
+        self.lock.lock().consecutive_backoffs = 0;
+    }
+
+    // Call with how long the remote took to answer each request that went through the throttle.
+    pub fn note_latency(&self, latency: Duration) {
+        let mut data = self.lock.lock();
+        data.traffic.requests += 1;
+        data.traffic.latency += latency;
+    }
+
+    pub fn traffic(&self) -> ThrottleTraffic {
+        self.lock.lock().traffic
+    }
+
+    pub fn status(&self) -> RateLimitStatus {
+        let data = self.lock.lock();
+        let now = Instant::now();
+        RateLimitStatus::new(
+            data.nb_call_times_limit,
+            data.expired_time,
+            data.timestamps
+                .iter()
+                .filter(|&&x| now.duration_since(x) < data.expired_time)
+                .count(),
+            data.paused_until
+                .map(|until| until.saturating_duration_since(now))
+                .unwrap_or_default(),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -99,4 +212,49 @@ mod tests {
         }
         assert!(start.elapsed() > Duration::from_secs(3));
     }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now: Timestamp = "2015-10-21T07:28:00Z".parse().expect("test");
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_traffic() {
+        let throttle = CallingThrottle::default();
+        assert_eq!(throttle.traffic().average_latency(), None);
+        throttle.note_latency(Duration::from_millis(100));
+        throttle.note_latency(Duration::from_millis(300));
+        assert_eq!(
+            throttle.traffic().average_latency(),
+            Some(Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn test_back_off() {
+        let cancellation = PluginCancellation::default();
+        let throttle = CallingThrottle::new(100, Duration::from_secs(1));
+        assert_eq!(throttle.back_off(None), MIN_BACKOFF);
+        assert_eq!(throttle.back_off(None), MIN_BACKOFF * 2);
+        throttle.note_success();
+        assert_eq!(throttle.back_off(Some("1")), Duration::from_secs(1));
+        assert!(throttle.status().paused_for() > Duration::ZERO);
+
+        // Note: the earlier backoffs extend the pause, so cancel to check that we bail.
+        cancellation.cancel();
+        assert!(throttle.throttle(&cancellation).is_err());
+    }
 }
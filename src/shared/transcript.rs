@@ -0,0 +1,398 @@
+// Transcripts and subtitles for audio and video works, e.g. lectures and podcast episodes. They
+// come from the feed, as `<podcast:transcript>`, from subtitle files next to a local import, from
+// the subtitle stream in the file itself, by way of ffmpeg, or from a local Whisper run. Whatever
+// the source, we keep them as a list of timed cues, which is what the slideshow shows under the
+// player and what the transcript panel seeks by.
+use anyhow::{Context as _, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Where a work's transcript came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TranscriptSource {
+    Feed,
+    Subtitles,
+    Embedded,
+    Whisper,
+}
+
+impl fmt::Display for TranscriptSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txt = match self {
+            Self::Feed => "feed",
+            Self::Subtitles => "subtitles",
+            Self::Embedded => "embedded",
+            Self::Whisper => "whisper",
+        };
+        write!(f, "{txt}")
+    }
+}
+
+impl TryFrom<&str> for TranscriptSource {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "feed" => Self::Feed,
+            "subtitles" => Self::Subtitles,
+            "embedded" => Self::Embedded,
+            "whisper" => Self::Whisper,
+            _ => bail!("not a known TranscriptSource name: {value}"),
+        })
+    }
+}
+
+impl TranscriptSource {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Feed => "From the feed",
+            Self::Subtitles => "From a subtitle file",
+            Self::Embedded => "From the file's subtitles",
+            Self::Whisper => "Transcribed by Whisper",
+        }
+    }
+}
+
+// A line of the transcript, in seconds from the start. Plain text transcripts have no times, so
+// their cues all sit at zero.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+impl Cue {
+    pub fn is_timed(&self) -> bool {
+        self.end > self.start
+    }
+}
+
+// The cue to show as a subtitle at `time`, if any.
+pub fn cue_at(cues: &[Cue], time: f64) -> Option<&Cue> {
+    cues.iter()
+        .find(|cue| cue.is_timed() && cue.start <= time && time < cue.end)
+}
+
+// Format `secs` as a time to show next to a cue, e.g. 1:02:03 or 2:03.
+pub fn format_time(secs: f64) -> String {
+    let secs = secs.max(0.) as u64;
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+// Parse a transcript in any of the formats we know, going by its content: WebVTT, SRT, the JSON
+// of the podcast namespace, or, failing those, plain text or HTML.
+pub fn parse(text: &str) -> Result<Vec<Cue>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let trimmed = text.trim_start();
+    let cues = if trimmed.starts_with('{') {
+        parse_json(trimmed)?
+    } else if trimmed.starts_with("WEBVTT") {
+        parse_timed_text(text)
+    } else {
+        // Note: HTML comments have arrows too, so it's only SRT if we find cues in it.
+        let cues = parse_timed_text(text);
+        if cues.is_empty() {
+            parse_plain(text)
+        } else {
+            cues
+        }
+    };
+    if cues.is_empty() {
+        bail!("the transcript is empty");
+    }
+    Ok(cues)
+}
+
+// `hh:mm:ss.mmm`, or `mm:ss.mmm`, as in WebVTT; SRT uses a comma for the point.
+fn parse_timestamp(stamp: &str) -> Option<f64> {
+    let stamp = stamp.trim().replace(',', ".");
+    let mut secs = 0.;
+    for part in stamp.split(':') {
+        secs = secs * 60. + part.parse::<f64>().ok()?;
+    }
+    Some(secs)
+}
+
+// Take out the markup that subtitles use for voices, italics, and the like.
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+// WebVTT and SRT are both blocks of a timing line and some text, between blank lines; SRT numbers
+// each block, and WebVTT may name them, which we skip, along with WebVTT's NOTE and STYLE blocks.
+fn parse_timed_text(text: &str) -> Vec<Cue> {
+    let text = text.replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let Some((start, rest)) = timing.split_once("-->") else {
+            continue;
+        };
+        // Note: WebVTT puts cue settings, e.g. `align:start`, after the end time.
+        let end = rest.split_whitespace().next().unwrap_or_default();
+        let (Some(start), Some(end)) = (parse_timestamp(start), parse_timestamp(end)) else {
+            continue;
+        };
+        let text = lines
+            .map(|line| strip_tags(line).trim().to_owned())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            cues.push(Cue { start, end, text });
+        }
+    }
+    cues
+}
+
+// The podcast namespace's JSON transcripts often have a segment per word, so we join segments by
+// the same speaker into cues of up to this long.
+const MAX_JOINED_SECS: f64 = 8.;
+
+fn parse_json(text: &str) -> Result<Vec<Cue>> {
+    let json: Value = serde_json::from_str(text)?;
+    let segments = json
+        .get("segments")
+        .and_then(Value::as_array)
+        .context("no segments in the JSON transcript")?;
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut last_speaker = None;
+    for segment in segments {
+        let (Some(start), Some(end), Some(body)) = (
+            segment.get("startTime").and_then(Value::as_f64),
+            segment.get("endTime").and_then(Value::as_f64),
+            segment.get("body").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let speaker = segment.get("speaker").and_then(Value::as_str);
+        let body = body.trim();
+        match cues.last_mut() {
+            Some(cue) if speaker == last_speaker && end - cue.start <= MAX_JOINED_SECS => {
+                cue.end = end;
+                cue.text.push(' ');
+                cue.text.push_str(body);
+            }
+            _ => cues.push(Cue {
+                start,
+                end,
+                text: body.to_owned(),
+            }),
+        }
+        last_speaker = speaker;
+    }
+    Ok(cues)
+}
+
+// An untimed transcript, a cue per paragraph.
+fn parse_plain(text: &str) -> Vec<Cue> {
+    let text = strip_tags(&text.replace("</p>", "\n\n").replace("<br>", "\n"));
+    text.replace("\r\n", "\n")
+        .split("\n\n")
+        .map(|para| para.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|para| !para.is_empty())
+        .map(|text| Cue {
+            start: 0.,
+            end: 0.,
+            text,
+        })
+        .collect()
+}
+
+const SUBTITLE_EXTENSIONS: &[&str] = &["vtt", "srt"];
+
+// A subtitle file next to `media`, named for it, e.g. `lecture.vtt` or `lecture.en.srt`.
+pub fn sidecar(media: &Path) -> Option<PathBuf> {
+    let stem = media.file_stem()?.to_str()?;
+    let mut found = fs::read_dir(media.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let ext = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let name = path.file_stem().and_then(|stem| stem.to_str());
+            SUBTITLE_EXTENSIONS.contains(&ext.as_str())
+                && name.is_some_and(|name| {
+                    name == stem || name.strip_prefix(stem).is_some_and(|s| s.starts_with('.'))
+                })
+        })
+        .collect::<Vec<_>>();
+    // Note: the plain name first, then by language code, so that we pick the same one each time.
+    found.sort_by_key(|path| (path.file_stem().map(|s| s.len()), path.clone()));
+    found.into_iter().next()
+}
+
+// The first subtitle stream in `media`, by way of ffmpeg, or None if it has none.
+pub fn embedded(media: &Path) -> Result<Option<Vec<Cue>>> {
+    let out = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(media)
+        .args(["-map", "0:s:0", "-f", "webvtt", "-"])
+        .output()
+        .context("running ffmpeg; is it installed?")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if stderr.contains("matches no streams") {
+            return Ok(None);
+        }
+        bail!("ffmpeg exited with {}: {}", out.status, stderr.trim());
+    }
+    Ok(Some(parse_timed_text(&String::from_utf8_lossy(
+        &out.stdout,
+    ))))
+}
+
+// Look for a transcript of a local file: a subtitle file next to it, then its own subtitles.
+pub fn find_local(media: &Path) -> Result<Option<(TranscriptSource, Vec<Cue>)>> {
+    if let Some(path) = sidecar(media) {
+        let cues = parse(&fs::read_to_string(&path)?)?;
+        return Ok(Some((TranscriptSource::Subtitles, cues)));
+    }
+    Ok(embedded(media)?
+        .filter(|cues| !cues.is_empty())
+        .map(|cues| (TranscriptSource::Embedded, cues)))
+}
+
+// Transcribe `media` with the Whisper command line, e.g. `whisper` from openai-whisper, which
+// takes any file ffmpeg can read and writes `<stem>.vtt` into the output directory.
+pub fn transcribe(media: &Path, whisper: &str, model: &str) -> Result<Vec<Cue>> {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let out_dir = std::env::temp_dir().join(format!(
+        "artchiver-whisper-{}-{}",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&out_dir)?;
+    let mut command = Command::new(whisper);
+    command
+        .arg(media)
+        .args(["--output_format", "vtt", "--output_dir"])
+        .arg(&out_dir);
+    if !model.trim().is_empty() {
+        command.args(["--model", model.trim()]);
+    }
+    let result = command
+        .output()
+        .with_context(|| format!("running {whisper}; is Whisper installed?"))
+        .and_then(|out| {
+            if !out.status.success() {
+                bail!(
+                    "{whisper} exited with {}: {}",
+                    out.status,
+                    String::from_utf8_lossy(&out.stderr).trim()
+                );
+            }
+            let stem = media.file_stem().context("the file has no name")?;
+            let vtt = out_dir.join(stem).with_extension("vtt");
+            parse(&fs::read_to_string(&vtt).context("Whisper wrote no transcript")?)
+        });
+    fs::remove_dir_all(&out_dir).ok();
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Cue, cue_at, format_time, parse};
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_vtt() {
+        let vtt = "WEBVTT\n\nNOTE a comment\n\nintro\n00:00:01.000 --> 00:00:04.500 align:start\n<v Host>Welcome to the show</v>\n\n01:02.250 --> 01:03.000\nLine one\n<i>Line two</i>\n";
+        assert_eq!(
+            parse(vtt).expect("parses"),
+            vec![
+                cue(1., 4.5, "Welcome to the show"),
+                cue(62.25, 63., "Line one\nLine two"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_srt() {
+        let srt = "1\r\n00:00:01,500 --> 00:00:02,000\r\nHello &amp; welcome\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nGoodbye\r\n";
+        assert_eq!(
+            parse(srt).expect("parses"),
+            vec![cue(1.5, 2., "Hello & welcome"), cue(3., 4., "Goodbye")]
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{"version": "1.0.0", "segments": [
+            {"speaker": "Ann", "startTime": 0.5, "endTime": 1.0, "body": "Hello"},
+            {"speaker": "Ann", "startTime": 1.0, "endTime": 1.5, "body": "there"},
+            {"speaker": "Bob", "startTime": 1.5, "endTime": 2.0, "body": "Hi"},
+            {"speaker": "Bob", "startTime": 2.0, "endTime": 12.0, "body": "again"}
+        ]}"#;
+        assert_eq!(
+            parse(json).expect("parses"),
+            vec![
+                cue(0.5, 1.5, "Hello there"),
+                cue(1.5, 2., "Hi"),
+                cue(2., 12., "again"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        let html = "<p>First  paragraph</p>\n<p>Second</p>";
+        assert_eq!(
+            parse(html).expect("parses"),
+            vec![cue(0., 0., "First paragraph"), cue(0., 0., "Second")]
+        );
+        assert!(parse("  \n").is_err());
+    }
+
+    #[test]
+    fn test_cue_at() {
+        let cues = vec![cue(1., 2., "a"), cue(3., 4., "b"), cue(0., 0., "untimed")];
+        assert_eq!(cue_at(&cues, 1.5).map(|c| c.text.as_str()), Some("a"));
+        assert_eq!(cue_at(&cues, 2.5), None);
+        assert_eq!(cue_at(&cues, 3.).map(|c| c.text.as_str()), Some("b"));
+        assert_eq!(cue_at(&cues, 0.), None);
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(62.9), "1:02");
+        assert_eq!(format_time(3723.), "1:02:03");
+    }
+}
@@ -1,12 +1,31 @@
 use crate::{
-    db::models::{
-        plugin::DbPlugin,
-        tag::{DbTag, TagId},
-        work::{DbWork, WorkId},
+    db::{
+        maintenance::OptimizeReport,
+        metadata_sync::SyncReport,
+        models::{
+            annotation::DbAnnotation,
+            collection::DbCollection,
+            detection::{DbTagSuggestion, PendingScan},
+            enrichment::PendingEnrichment,
+            exhibition::DbExhibition,
+            link_check::LostLink,
+            note::{DbWorkNote, NoteHit},
+            plugin::{DbPlugin, PluginId},
+            tag::{CoTags, DbTag, TagId},
+            tag_edit::DbTagEdit,
+            transcript::{DbTranscript, TranscriptHit},
+            trash::TrashedWork,
+            work::{
+                DbWork, DbWorkIdentity, DbWorkRevision, DisplayTransform, MissingThumb, WorkId,
+            },
+        },
+        purge::PurgeReport,
+        relocate::RelocateReport,
+        scrub::ScrubReport,
     },
     shared::progress::{Progress, UpdateSource},
 };
-use artchiver_sdk::PluginMetadata;
+use artchiver_sdk::{Authority, PluginMetadata};
 use log::Level;
 use std::{collections::HashMap, path::PathBuf};
 
@@ -27,6 +46,14 @@ pub enum DataUpdate {
     // this doesn't include progress on downloading any of the images associated with those works.
     WorksWereUpdatedForTag {
         for_tag: String,
+        new_works: usize,
+    },
+
+    // A plugin task failed; the details are in the plugin's log.
+    PluginTaskFailed {
+        plugin: String,
+        task: String,
+        error: String,
     },
 
     // Notify the UX that a specific work's image downloads have completed and it can now present
@@ -34,11 +61,13 @@ pub enum DataUpdate {
     WorkDownloadCompleted {
         id: WorkId,
         preview_path: String,
-        screen_path: String,
+        screen_path: Option<String>,
         archive_path: Option<String>,
+        thumb_path: Option<String>,
+        aspect_ratio: Option<f32>,
     },
 
-    // Status change for favorite and hidden flags.
+    // Status change for favorite and hidden flags, and work ratings.
     WorkFavoriteStatusChanged {
         work_id: WorkId,
         favorite: bool,
@@ -47,6 +76,20 @@ pub enum DataUpdate {
         work_id: WorkId,
         hidden: bool,
     },
+    WorkRatingChanged {
+        work_id: WorkId,
+        rating: u8,
+    },
+    // The user's classifier scored a work's thumbnail.
+    WorkNsfwScored {
+        work_id: WorkId,
+        score: f32,
+    },
+    // We have the work's 3D model on disk.
+    WorkModelDownloaded {
+        work_id: WorkId,
+        model_path: String,
+    },
     TagFavoriteStatusChanged {
         tag_id: TagId,
         favorite: bool,
@@ -55,6 +98,44 @@ pub enum DataUpdate {
         tag_id: TagId,
         hidden: bool,
     },
+    TagWatchedStatusChanged {
+        tag_id: TagId,
+        watched: bool,
+    },
+
+    // The writer finished merging user metadata with the sync folder.
+    MetadataSyncCompleted(SyncReport),
+
+    // The writer finished moving files between storage roots, or copying the data dir.
+    StorageRelocated(RelocateReport),
+
+    // The writer deleted the files of a plugin's works to bring it back under its quota.
+    PluginWorksPurged {
+        plugin_id: PluginId,
+        report: PurgeReport,
+    },
+
+    // Everything in the trash, whenever something goes into it or comes out.
+    TrashedWorks(Vec<TrashedWork>),
+
+    // The writer finished analyzing and vacuuming the database.
+    DatabaseOptimized(OptimizeReport),
+
+    // The writer finished an integrity scrub, or an attempt to replace a corrupt file.
+    ScrubCompleted(ScrubReport),
+    FileRepaired {
+        stored_path: String,
+        error: Option<String>,
+    },
+
+    // The writer recorded a batch of link checks; these are all the works we know are lost.
+    LostLinks(Vec<LostLink>),
+
+    // Requests from outside the UX (e.g. the HTTP API) for the PluginHost to queue work.
+    RefreshTagsRequested,
+    RefreshWorksForTagRequested {
+        tag: DbTag,
+    },
 
     // Notify the PluginHost that the source has completed a task and needs to be fed new work.
     CompletedTask {
@@ -74,7 +155,21 @@ pub enum DataUpdate {
 
     // Fulfills a request by the UX to get the current list of tags.
     InitialTags(HashMap<TagId, DbTag>),
-    TagsLocalCounts(Vec<(TagId, u64)>),
+    // The number of works we have for each tag, and how many of those have their screen file.
+    TagsLocalCounts(Vec<(TagId, u64, u64)>),
+
+    // Fulfills a request by the UX for the works that are new on each watched tag since the user
+    // last looked at it. Also sent with a zero count when the user looks at one.
+    WatchedTagCounts(Vec<(TagId, u64)>),
+
+    // Fulfills a request by the UX for one window of the filtered, sorted tags list. The
+    // generation identifies the query, so that the UX can drop answers to stale ones.
+    TagsWindow {
+        generation: u64,
+        total: usize,
+        offset: usize,
+        tag_ids: Vec<TagId>,
+    },
 
     // Fulfills a request by the UX to get the current list of works for a tag.
     ListWorksChunk {
@@ -82,4 +177,105 @@ pub enum DataUpdate {
         works: HashMap<WorkId, DbWork>,
         finished: bool,
     },
+
+    // Fulfills a request by the UX for the raw record a work was built from.
+    WorkSource {
+        work_id: WorkId,
+        source: Option<String>,
+    },
+
+    // Fulfills a request by the UX for the changes plugins have made to a work.
+    WorkHistory {
+        work_id: WorkId,
+        revisions: Vec<DbWorkRevision>,
+    },
+
+    // Fulfills a request by the UX for the external ids of a work, and its twins from other plugins.
+    WorkIdentity {
+        work_id: WorkId,
+        identity: DbWorkIdentity,
+    },
+
+    // Fulfills a request by the UX for the stored paths of the user's edits of a work.
+    WorkVariants {
+        work_id: WorkId,
+        variants: Vec<String>,
+    },
+
+    // The annotations on a work, on request by the UX or after the user changes them.
+    Annotations {
+        screen_url: String,
+        annotations: Vec<DbAnnotation>,
+    },
+
+    // Fulfills a request by the UX for the user's note on a work, if they have written one.
+    WorkNote {
+        work_id: WorkId,
+        note: Option<DbWorkNote>,
+    },
+
+    // Fulfills a search of the user's notes.
+    NoteSearch {
+        query: String,
+        hits: Vec<NoteHit>,
+    },
+
+    // The transcript of a work, on request by the UX or after we store a new one.
+    Transcript {
+        screen_url: String,
+        transcript: Option<DbTranscript>,
+    },
+
+    // Fulfills a search of the transcripts.
+    TranscriptSearch {
+        query: String,
+        hits: Vec<TranscriptHit>,
+    },
+
+    // Fulfills a request by the UX for the works waiting in the inbox, with the tag (or failing
+    // that, the plugin) each one arrived under.
+    InboxWorks(Vec<(String, DbWork)>),
+
+    // Fulfills a request by the UX for the works with a Wikidata id that we haven't looked up.
+    WorksToEnrich(Vec<PendingEnrichment>),
+
+    // Fulfills a request by the UX for the Getty terms that tags stand for, or that are broader
+    // than those, that we haven't fetched.
+    VocabularyToFetch(Vec<(Authority, String)>),
+
+    // Fulfills a request by the UX for the downloaded works that detection hasn't looked at.
+    WorksToDetect(Vec<PendingScan>),
+
+    // Fulfills a request by the UX for the tag suggestions waiting for review.
+    TagSuggestions(Vec<DbTagSuggestion>),
+
+    // Detection made new suggestions, or the user reviewed some; the review queue is stale.
+    TagSuggestionsChanged,
+
+    // Fulfills a request by the UX for the tag edits that can be pushed to the works' sources.
+    PendingTagEdits(Vec<DbTagEdit>),
+
+    // The user edited a work's tags, or a plugin pushed some edits; the pending edits are stale.
+    TagEditsChanged,
+
+    // Fulfills a request by the UX for the work that an artchiver:// link points at, if we have it.
+    LinkedWork {
+        screen_url: String,
+        work: Option<DbWork>,
+    },
+
+    // Fulfills a request by the UX for the downloaded works that need a gallery thumbnail.
+    WorksMissingThumbs(Vec<MissingThumb>),
+
+    // Fulfills a request by the UX for every work's display transform, by screen url.
+    DisplayTransforms(HashMap<String, DisplayTransform>),
+
+    // Fulfills a request by the UX for the tags that most often appear alongside a tag.
+    CoTags(CoTags),
+
+    // Fulfills a request by the UX for the collections that plugins have reported.
+    Collections(Vec<DbCollection>),
+
+    // Fulfills a request by the UX for the user's exhibitions, with the works that they show.
+    Exhibitions(Vec<DbExhibition>),
 }
@@ -0,0 +1,152 @@
+// Notice when the user saves an edited copy of a work next to its file, e.g. `<hash>_edited.png`
+// from GIMP, so that we can keep it with the work.
+//
+// We don't have a file system watcher, so we poll instead: only the folders of works that the
+// user has handed to another program, and only for a while after, which keeps it cheap.
+use crate::plugin::thumbnail::is_image;
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+const WATCH_FOR: Duration = Duration::from_secs(12 * 60 * 60);
+
+#[derive(Debug)]
+struct Watch {
+    screen_url: String,
+    stored: String,
+    local: PathBuf,
+    known: HashSet<OsString>,
+    until: Instant,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FoundVariant {
+    pub screen_url: String,
+    // The stored path of the variant, next to the stored path of the original.
+    pub stored: String,
+}
+
+#[derive(Debug, Default)]
+pub struct VariantWatcher {
+    watches: Vec<Watch>,
+    last_poll: Option<Instant>,
+}
+
+// An edit keeps the original's name and adds to it: `abc.jpg` -> `abc_edited.png`, `abc-1.tif`.
+pub fn is_derivative(original: &Path, candidate: &Path) -> bool {
+    let (Some(stem), Some(name)) = (original.file_stem(), candidate.file_name()) else {
+        return false;
+    };
+    let (stem, name) = (stem.to_string_lossy(), name.to_string_lossy());
+    candidate != original
+        && !name.ends_with(".tmp")
+        && !name.starts_with('.')
+        && is_image(candidate)
+        && name.starts_with(stem.as_ref())
+        && name[stem.len()..].starts_with(['_', '-', ' ', '.'])
+}
+
+fn sibling_stored_path(stored: &str, name: &str) -> String {
+    match stored.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/{name}"),
+        None => match stored.split_once(':') {
+            Some((root, _)) => format!("{root}:{name}"),
+            None => name.to_owned(),
+        },
+    }
+}
+
+impl VariantWatcher {
+    // `stored` is the stored path of the file we handed off, and `local` where that is on disk.
+    pub fn watch(&mut self, screen_url: &str, stored: &str, local: &Path) {
+        self.watches.retain(|watch| watch.local != local);
+        let known = local
+            .parent()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+            .unwrap_or_default();
+        self.watches.push(Watch {
+            screen_url: screen_url.to_owned(),
+            stored: stored.to_owned(),
+            local: local.to_owned(),
+            known,
+            until: Instant::now() + WATCH_FOR,
+        });
+    }
+
+    pub fn poll(&mut self) -> Vec<FoundVariant> {
+        let now = Instant::now();
+        if self.watches.is_empty()
+            || self
+                .last_poll
+                .is_some_and(|last| now.duration_since(last) < POLL_INTERVAL)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+        self.watches.retain(|watch| watch.until > now);
+
+        let mut found = Vec::new();
+        for watch in &mut self.watches {
+            let Some(entries) = watch.local.parent().and_then(|dir| fs::read_dir(dir).ok()) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                if watch.known.contains(&name) {
+                    continue;
+                }
+                // Note: editors often write a temp file first; a name we skip now may be the
+                //       real file by the next poll, so only remember names we act on.
+                if is_derivative(&watch.local, &entry.path()) {
+                    found.push(FoundVariant {
+                        screen_url: watch.screen_url.clone(),
+                        stored: sibling_stored_path(&watch.stored, &name.to_string_lossy()),
+                    });
+                    watch.known.insert(name);
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_derivative() {
+        let original = Path::new("/data/ab/cd/1234.jpg");
+        let derivative = |name: &str| is_derivative(original, &original.with_file_name(name));
+        assert!(derivative("1234_edited.png"));
+        assert!(derivative("1234-1.tif"));
+        assert!(derivative("1234 copy.jpg"));
+        assert!(!derivative("1234.jpg"), "the original itself");
+        assert!(!derivative("12345.jpg"), "a different work");
+        assert!(!derivative("1234_edited.xcf"), "not something we can show");
+        assert!(!derivative("1234_edited.png.tmp"));
+        assert!(!derivative("other.png"));
+    }
+
+    #[test]
+    fn test_sibling_stored_path() {
+        assert_eq!(
+            sibling_stored_path("ab/cd/1234.jpg", "1234_edited.png"),
+            "ab/cd/1234_edited.png"
+        );
+        assert_eq!(
+            sibling_stored_path("nas:ab/cd/1234.jpg", "1234_edited.png"),
+            "nas:ab/cd/1234_edited.png"
+        );
+        assert_eq!(
+            sibling_stored_path("nas:1234.jpg", "1234-1.jpg"),
+            "nas:1234-1.jpg"
+        );
+    }
+}
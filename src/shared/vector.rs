@@ -0,0 +1,96 @@
+// SVG works, e.g. diagrams, heraldry, and maps, drawn to bitmaps with resvg. A vector file has no
+// resolution of its own, so whoever shows one picks the size to draw it at.
+use anyhow::{Context as _, Result};
+use image::RgbaImage;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{Options, Tree, fontdb::Database},
+};
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+pub fn is_vector(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    matches!(
+        ext.to_ascii_lowercase().to_str().unwrap_or_default(),
+        "svg" | "svgz"
+    )
+}
+
+// Note: loading the system's fonts takes a while, and labels on maps and diagrams need them, so
+//       we do it once and share them.
+static FONTS: LazyLock<Arc<Database>> = LazyLock::new(|| {
+    let mut fonts = Database::new();
+    fonts.load_system_fonts();
+    Arc::new(fonts)
+});
+
+// Parse an SVG, or a gzipped SVGZ.
+pub fn parse(bytes: &[u8]) -> Result<Tree> {
+    let options = Options {
+        fontdb: FONTS.clone(),
+        ..Options::default()
+    };
+    Ok(Tree::from_data(bytes, &options)?)
+}
+
+pub fn open(path: &Path) -> Result<Tree> {
+    parse(&fs::read(path)?)
+}
+
+// Draw `tree` as large as fits in `max_width` by `max_height` pixels.
+pub fn rasterize(tree: &Tree, max_width: u32, max_height: u32) -> Result<RgbaImage> {
+    let size = tree.size();
+    let scale = (max_width as f32 / size.width()).min(max_height as f32 / size.height());
+    let width = (size.width() * scale).round().max(1.) as u32;
+    let height = (size.height() * scale).round().max(1.) as u32;
+    let mut pixmap = Pixmap::new(width, height).context("the drawing is too large")?;
+    resvg::render(
+        tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    // Note: resvg works in premultiplied alpha, where everything else we have expects straight.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let pixel = pixel.demultiply();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels).context("the drawing has the wrong size")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_vector, parse, rasterize};
+    use std::path::Path;
+
+    #[test]
+    fn test_is_vector() {
+        assert!(is_vector(Path::new("ab/cd/arms.SVG")));
+        assert!(is_vector(Path::new("ab/cd/map.svgz")));
+        assert!(!is_vector(Path::new("ab/cd/map.png")));
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+            <rect width="10" height="10" fill="red"/>
+        </svg>"#;
+        let image = rasterize(&parse(svg).expect("valid svg"), 400, 400).expect("draws");
+        assert_eq!(image.dimensions(), (400, 200));
+        assert_eq!(image.get_pixel(100, 100).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(300, 100).0[3], 0);
+
+        // Note: a wide box is limited by the height instead.
+        let image = rasterize(&parse(svg).expect("valid svg"), 1000, 100).expect("draws");
+        assert_eq!(image.dimensions(), (200, 100));
+    }
+}
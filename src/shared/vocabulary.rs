@@ -0,0 +1,180 @@
+// The Getty vocabularies: plugins that know which AAT concept or ULAN name a tag stands for pass
+// it along, and we ask Getty's SPARQL endpoint for the term's preferred and other names, and for
+// the broader term above it in the hierarchy.
+//
+// Other names become aliases of the tag, so that searching for "oil paint" finds "Oil paintings".
+// Broader AAT terms that we also have tags for become implications: a work tagged "Portraits"
+// is also tagged "Visual works", if there is such a tag. ULAN's hierarchy is all facets and
+// roles, so only AAT terms imply anything.
+use anyhow::{Result, bail};
+use artchiver_sdk::Authority;
+use serde::Deserialize;
+use std::{collections::HashMap, time::Duration};
+use ureq::Agent;
+
+const SPARQL_URL: &str = "http://vocab.getty.edu/sparql.json";
+const TERM_BASE: &str = "http://vocab.getty.edu";
+// Terms per query.
+pub const BATCH_SIZE: usize = 40;
+// Getty's endpoint is shared with the world; one query at a time, with a pause between.
+pub const QUERY_DELAY: Duration = Duration::from_secs(1);
+// Where the aliases and implications we derive are marked as coming from.
+pub const SOURCE: &str = "getty";
+
+// The vocabularies that Getty's endpoint serves.
+pub fn is_getty(authority: Authority) -> bool {
+    matches!(authority, Authority::Aat | Authority::Ulan)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VocabularyTerm {
+    pub authority: Authority,
+    pub value: String,
+    pub label: String,
+    pub alt_labels: Vec<String>,
+    pub broader: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SparqlResponse {
+    results: SparqlResults,
+}
+
+#[derive(Deserialize)]
+struct SparqlResults {
+    bindings: Vec<HashMap<String, SparqlValue>>,
+}
+
+#[derive(Deserialize)]
+struct SparqlValue {
+    value: String,
+}
+
+fn term_uri(authority: Authority, value: &str) -> String {
+    format!("<{TERM_BASE}/{authority}/{value}>")
+}
+
+// Note: ids that aren't Getty's are left out, as one would make the whole query fail; the
+//       worker counts them as missing.
+pub fn sparql_query(terms: &[(Authority, &str)]) -> String {
+    let values = terms
+        .iter()
+        .filter(|(authority, value)| authority.is_valid_id(value))
+        .map(|(authority, value)| term_uri(*authority, value))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        r#"PREFIX gvp: <http://vocab.getty.edu/ontology#>
+PREFIX xl: <http://www.w3.org/2008/05/skos-xl#>
+SELECT ?term ?label ?altLabel ?broader WHERE {{
+    VALUES ?term {{ {values} }}
+    ?term gvp:prefLabelGVP/xl:literalForm ?label.
+    OPTIONAL {{
+        ?term xl:altLabel/xl:literalForm ?altLabel.
+        FILTER (lang(?altLabel) = "" || langMatches(lang(?altLabel), "en"))
+    }}
+    OPTIONAL {{ ?term gvp:broaderPreferred ?broader. }}
+}}"#
+    )
+}
+
+fn getty_id(uri: &str) -> Option<(Authority, String)> {
+    Authority::from_uri(uri).filter(|(authority, _)| is_getty(*authority))
+}
+
+pub fn parse_results(json: &str) -> Result<Vec<VocabularyTerm>> {
+    let response = serde_json::from_str::<SparqlResponse>(json)?;
+    let mut terms = Vec::<VocabularyTerm>::new();
+    for row in response.results.bindings {
+        let get = |name: &str| row.get(name).map(|v| v.value.as_str());
+        let (Some((authority, value)), Some(label)) =
+            (get("term").and_then(getty_id), get("label"))
+        else {
+            continue;
+        };
+        // Note: there is a row for each of the term's other names.
+        let term = match terms
+            .iter_mut()
+            .position(|t| t.authority == authority && t.value == value)
+        {
+            Some(offset) => &mut terms[offset],
+            None => {
+                terms.push(VocabularyTerm {
+                    authority,
+                    value,
+                    label: label.to_owned(),
+                    alt_labels: Vec::new(),
+                    broader: None,
+                });
+                terms.last_mut().expect("just pushed")
+            }
+        };
+        if let Some(alt) = get("altLabel")
+            && alt != term.label
+            && !term.alt_labels.iter().any(|a| a == alt)
+        {
+            term.alt_labels.push(alt.to_owned());
+        }
+        if let Some((broader_authority, broader)) = get("broader").and_then(getty_id)
+            && broader_authority == authority
+        {
+            term.broader = Some(broader);
+        }
+    }
+    Ok(terms)
+}
+
+pub fn query_terms(agent: &Agent, terms: &[(Authority, &str)]) -> Result<Vec<VocabularyTerm>> {
+    let mut response = agent
+        .get(SPARQL_URL)
+        .query("query", sparql_query(terms))
+        .call()?;
+    if !response.status().is_success() {
+        bail!("Getty's SPARQL endpoint said {}", response.status());
+    }
+    parse_results(&response.body_mut().read_to_string()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() -> Result<()> {
+        let json = r#"{"results": {"bindings": [
+            {"term": {"type": "uri", "value": "http://vocab.getty.edu/aat/300033618"},
+             "label": {"type": "literal", "value": "paintings (visual works)"},
+             "altLabel": {"type": "literal", "value": "painting (visual work)"},
+             "broader": {"type": "uri", "value": "http://vocab.getty.edu/aat/300033637"}},
+            {"term": {"type": "uri", "value": "http://vocab.getty.edu/aat/300033618"},
+             "label": {"type": "literal", "value": "paintings (visual works)"},
+             "altLabel": {"type": "literal", "value": "paintings (visual works)"},
+             "broader": {"type": "uri", "value": "http://vocab.getty.edu/aat/300033637"}},
+            {"term": {"type": "uri", "value": "http://vocab.getty.edu/ulan/500010879"},
+             "label": {"type": "literal", "value": "Rembrandt van Rijn"},
+             "altLabel": {"type": "literal", "value": "Rembrandt"}},
+            {"term": {"type": "uri", "value": "http://www.wikidata.org/entity/Q5598"},
+             "label": {"type": "literal", "value": "Rembrandt"}}
+        ]}}"#;
+        let terms = parse_results(json)?;
+        assert_eq!(terms.len(), 2);
+        assert_eq!(terms[0].authority, Authority::Aat);
+        assert_eq!(terms[0].alt_labels, vec!["painting (visual work)"]);
+        assert_eq!(terms[0].broader.as_deref(), Some("300033637"));
+        assert_eq!(terms[1].authority, Authority::Ulan);
+        assert_eq!(terms[1].broader, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparql_query() {
+        let query = sparql_query(&[
+            (Authority::Aat, "300033618"),
+            (Authority::Ulan, "500010879"),
+        ]);
+        assert!(query.contains(
+            "VALUES ?term { <http://vocab.getty.edu/aat/300033618> \
+             <http://vocab.getty.edu/ulan/500010879> }"
+        ));
+    }
+}
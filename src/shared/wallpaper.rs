@@ -0,0 +1,145 @@
+// Put a work on the desktop. We crop and scale it for each monitor ourself, since the desktops'
+// own "fill" modes all crop a spanned image as one big rectangle, which cuts the subject in
+// half on multi-monitor setups; then we hand the result to whatever the platform uses to set
+// the wallpaper.
+use crate::plugin::thumbnail::open_image;
+use anyhow::{Result, bail};
+use image::{DynamicImage, RgbImage, imageops::FilterType};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// Scale and crop `img` to fill each monitor in turn, from left to right, and lay them side by
+// side, for desktops that stretch one image across every monitor.
+fn compose(img: &DynamicImage, monitors: &[[u32; 2]]) -> (Vec<RgbImage>, RgbImage) {
+    let per_monitor = monitors
+        .iter()
+        .map(|[width, height]| {
+            img.resize_to_fill((*width).max(1), (*height).max(1), FilterType::Lanczos3)
+                .to_rgb8()
+        })
+        .collect::<Vec<_>>();
+    let width = per_monitor.iter().map(RgbImage::width).sum::<u32>();
+    let height = per_monitor
+        .iter()
+        .map(RgbImage::height)
+        .max()
+        .unwrap_or_default();
+    let mut spanned = RgbImage::new(width.max(1), height.max(1));
+    let mut x = 0;
+    for monitor in &per_monitor {
+        image::imageops::replace(&mut spanned, monitor, i64::from(x), 0);
+        x += monitor.width();
+    }
+    (per_monitor, spanned)
+}
+
+// Write the wallpaper for `source` into `dir` and tell the desktop to show it. `generation` is
+// used to alternate file names, since some desktops ignore a change to a file they are already
+// showing.
+pub fn set_wallpaper(
+    source: &Path,
+    monitors: &[[u32; 2]],
+    dir: &Path,
+    generation: u64,
+) -> Result<()> {
+    if monitors.is_empty() {
+        bail!("no monitors configured");
+    }
+    fs::create_dir_all(dir)?;
+    let (per_monitor, spanned) = compose(&open_image(source)?, monitors);
+    let slot = generation % 2;
+    let mut monitor_paths = Vec::new();
+    for (i, img) in per_monitor.iter().enumerate() {
+        let path = dir.join(format!("monitor-{i}-{slot}.jpg"));
+        img.save(&path)?;
+        monitor_paths.push(path);
+    }
+    let spanned_path = dir.join(format!("spanned-{slot}.jpg"));
+    spanned.save(&spanned_path)?;
+    apply(&spanned_path, &monitor_paths)
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        bail!("{:?} exited with {status}", command.get_program());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn apply(_spanned: &Path, monitors: &[PathBuf]) -> Result<()> {
+    // Note: macOS sets each desktop separately, so it gets the per-monitor crops.
+    for (i, path) in monitors.iter().enumerate() {
+        let script = format!(
+            "tell application \"System Events\" to tell desktop {} to set picture to {:?}",
+            i + 1,
+            path.display().to_string()
+        );
+        run(Command::new("osascript").args(["-e", &script]))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn apply(spanned: &Path, monitors: &[PathBuf]) -> Result<()> {
+    let style = if monitors.len() > 1 { 22 } else { 10 };
+    let script = format!(
+        r#"Set-ItemProperty -Path 'HKCU:\Control Panel\Desktop' -Name WallpaperStyle -Value {style}
+Add-Type -TypeDefinition 'using System.Runtime.InteropServices; public class Wallpaper {{ [DllImport("user32.dll")] public static extern int SystemParametersInfo(int a, int b, string c, int d); }}'
+[Wallpaper]::SystemParametersInfo(20, 0, '{}', 3)"#,
+        spanned.display()
+    );
+    run(Command::new("powershell").args(["-NoProfile", "-Command", &script]))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn apply(spanned: &Path, monitors: &[PathBuf]) -> Result<()> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if desktop.contains("kde") {
+        return run(Command::new("plasma-apply-wallpaperimage").arg(spanned));
+    }
+    if desktop.contains("gnome") || desktop.contains("unity") || desktop.contains("cinnamon") {
+        let uri = format!("file://{}", spanned.display());
+        let options = if monitors.len() > 1 {
+            "spanned"
+        } else {
+            "zoom"
+        };
+        for key in ["picture-uri", "picture-uri-dark"] {
+            run(Command::new("gsettings").args([
+                "set",
+                "org.gnome.desktop.background",
+                key,
+                &uri,
+            ]))?;
+        }
+        return run(Command::new("gsettings").args([
+            "set",
+            "org.gnome.desktop.background",
+            "picture-options",
+            options,
+        ]));
+    }
+    // Note: feh takes one image per monitor, which covers most bare window managers.
+    run(Command::new("feh").arg("--bg-fill").args(monitors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose() {
+        let img = DynamicImage::new_rgb8(3000, 2000);
+        let (per_monitor, spanned) = compose(&img, &[[1920, 1080], [1080, 1920]]);
+        assert_eq!(per_monitor[0].dimensions(), (1920, 1080));
+        assert_eq!(per_monitor[1].dimensions(), (1080, 1920));
+        assert_eq!(spanned.dimensions(), (3000, 1920));
+    }
+}
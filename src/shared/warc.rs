@@ -0,0 +1,253 @@
+// Optional WARC (ISO 28500) recording of everything we fetch, so that the provenance of the
+// archive can be checked, or the fetches replayed, later with standard web-archiving tools.
+//
+// Files go in `<data dir>/warc` and roll over when they get big. Records are written
+// uncompressed; `warcio recompress` or similar can gzip them after the fact.
+//
+// Note: ureq hands us decoded bodies, so we drop the Content-Encoding and Transfer-Encoding
+//       headers and record the length of the body we actually have, rather than claim to
+//       have seen bytes that we never did.
+use jiff::{Timestamp, tz::TimeZone};
+use log::{info, warn};
+use parking_lot::Mutex;
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Read, Write as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use ureq::{
+    Body,
+    http::{HeaderMap, Response, StatusCode},
+};
+
+const MAX_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug)]
+struct WarcFile {
+    name: String,
+    fp: io::BufWriter<fs::File>,
+    written: u64,
+}
+
+#[derive(Debug, Default)]
+struct WarcState {
+    dir: PathBuf,
+    enabled: bool,
+    file: Option<WarcFile>,
+}
+
+impl WarcState {
+    fn open_file(&mut self) -> io::Result<&mut WarcFile> {
+        if self
+            .file
+            .as_ref()
+            .is_some_and(|file| file.written >= MAX_FILE_BYTES)
+        {
+            self.close();
+        }
+        if self.file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let name = format!(
+                "artchiver-{}-{:08x}.warc",
+                Timestamp::now()
+                    .to_zoned(TimeZone::UTC)
+                    .strftime("%Y%m%d%H%M%S"),
+                rand::random::<u32>()
+            );
+            info!("Recording responses to {name}");
+            let mut file = WarcFile {
+                fp: io::BufWriter::new(fs::File::create(self.dir.join(&name))?),
+                name,
+                written: 0,
+            };
+            let info = format!(
+                "software: Artchiver/{}\r\nformat: WARC File Format 1.1\r\n",
+                env!("CARGO_PKG_VERSION")
+            );
+            let fields = [
+                ("WARC-Filename", file.name.clone()),
+                ("Content-Type", "application/warc-fields".to_owned()),
+            ];
+            write_record(
+                &mut file,
+                "warcinfo",
+                &fields,
+                (&mut info.as_bytes(), info.len() as u64),
+            )?;
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("opened above"))
+    }
+
+    fn close(&mut self) {
+        if let Some(mut file) = self.file.take()
+            && let Err(e) = file.fp.flush()
+        {
+            warn!("Failed to finish {}: {e}", file.name);
+        }
+    }
+}
+
+impl Drop for WarcState {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// A urn:uuid with random (v4) bits, as WARC wants.
+fn record_id() -> String {
+    let bits = (rand::random::<u128>() & !(0xf << 76)) | (0x4 << 76);
+    let bits = (bits & !(0x3 << 62)) | (0x2 << 62);
+    let hex = format!("{bits:032x}");
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn write_record(
+    file: &mut WarcFile,
+    kind: &str,
+    fields: &[(&str, String)],
+    (block, block_len): (&mut dyn Read, u64),
+) -> io::Result<()> {
+    let mut head = format!(
+        "WARC/1.1\r\nWARC-Type: {kind}\r\nWARC-Record-ID: {}\r\nWARC-Date: {}\r\n",
+        record_id(),
+        Timestamp::now().strftime("%Y-%m-%dT%H:%M:%SZ")
+    );
+    for (name, value) in fields {
+        write!(head, "{name}: {value}\r\n").ok();
+    }
+    write!(head, "Content-Length: {block_len}\r\n\r\n").ok();
+    file.fp.write_all(head.as_bytes())?;
+    let copied = io::copy(&mut block.take(block_len), &mut file.fp)?;
+    if copied != block_len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "body was shorter than promised",
+        ));
+    }
+    file.fp.write_all(b"\r\n\r\n")?;
+    file.written += head.len() as u64 + block_len + 4;
+    Ok(())
+}
+
+// The status line and headers as they will appear in the record.
+fn http_head(status: StatusCode, headers: &HeaderMap, body_len: u64) -> String {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    for (name, value) in headers {
+        if matches!(
+            name.as_str(),
+            "content-encoding" | "transfer-encoding" | "content-length"
+        ) {
+            continue;
+        }
+        write!(
+            head,
+            "{name}: {}\r\n",
+            String::from_utf8_lossy(value.as_bytes())
+        )
+        .ok();
+    }
+    write!(head, "content-length: {body_len}\r\n\r\n").ok();
+    head
+}
+
+// Shared by the PluginHost, which turns it on and off from preferences, and every thread that
+// fetches anything for a plugin.
+#[derive(Clone, Debug, Default)]
+pub struct WarcRecorder {
+    state: Arc<Mutex<WarcState>>,
+}
+
+impl WarcRecorder {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WarcState {
+                dir: dir.to_owned(),
+                ..WarcState::default()
+            })),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock();
+        state.enabled = enabled;
+        if !enabled {
+            state.close();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().enabled
+    }
+
+    // Record a response. The body is read from `body`, which must hold exactly `body_len`
+    // bytes with the given hash.
+    pub fn record(
+        &self,
+        url: &str,
+        response: &Response<Body>,
+        (body, body_len, sha256): (&mut dyn Read, u64, &str),
+    ) -> io::Result<()> {
+        let mut state = self.state.lock();
+        if !state.enabled {
+            return Ok(());
+        }
+        let head = http_head(response.status(), response.headers(), body_len);
+        let file = state.open_file()?;
+        write_record(
+            file,
+            "response",
+            &[
+                ("WARC-Target-URI", url.to_owned()),
+                ("WARC-Payload-Digest", format!("sha256:{sha256}")),
+                (
+                    "Content-Type",
+                    "application/http;msgtype=response".to_owned(),
+                ),
+            ],
+            (
+                &mut head.as_bytes().chain(body),
+                head.len() as u64 + body_len,
+            ),
+        )?;
+        file.fp.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ureq::http::HeaderValue;
+
+    #[test]
+    fn test_http_head() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("image/jpeg"));
+        headers.insert("content-encoding", HeaderValue::from_static("gzip"));
+        headers.insert("content-length", HeaderValue::from_static("10"));
+        assert_eq!(
+            http_head(StatusCode::OK, &headers, 25),
+            "HTTP/1.1 200 OK\r\ncontent-type: image/jpeg\r\ncontent-length: 25\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_record_id() {
+        let id = record_id();
+        assert_eq!(id.len(), "<urn:uuid:>".len() + 36, "{id}");
+        assert_eq!(&id[24..25], "4", "{id}");
+    }
+}
@@ -0,0 +1,71 @@
+// Shown when another Artchiver already has the data dir open, so that the user can pick between
+// a read-only look at the archive and leaving the other instance to it.
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+struct AlreadyRunning {
+    lock_path: PathBuf,
+    owner: Option<String>,
+    read_only: Arc<AtomicBool>,
+}
+
+impl eframe::App for AlreadyRunning {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(16.);
+                ui.heading("Artchiver is already running");
+                ui.add_space(8.);
+                ui.label(format!(
+                    "Another instance{} has this archive open; see {}.",
+                    self.owner
+                        .as_ref()
+                        .map(|owner| format!(" ({owner})"))
+                        .unwrap_or_default(),
+                    self.lock_path.display()
+                ));
+                ui.label(
+                    "You can open it anyway in read-only mode: browsing works, but plugins, \
+                     downloads, and any changes are disabled.",
+                );
+                ui.add_space(16.);
+                ui.horizontal(|ui| {
+                    if ui.button("Open Read-Only").clicked() {
+                        self.read_only.store(true, Ordering::Release);
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                    if ui.button("Quit").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+            });
+        });
+    }
+}
+
+// Returns whether the user asked to open the archive read-only.
+pub fn run_already_running_prompt(
+    lock_path: PathBuf,
+    owner: Option<String>,
+) -> eframe::Result<bool> {
+    let read_only = Arc::new(AtomicBool::new(false));
+    let prompt = AlreadyRunning {
+        lock_path,
+        owner,
+        read_only: read_only.clone(),
+    };
+    eframe::run_native(
+        "Artchiver",
+        eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default().with_inner_size([560.0, 220.0]),
+            ..Default::default()
+        },
+        Box::new(move |_cc| Ok(Box::new(prompt))),
+    )?;
+    Ok(read_only.load(Ordering::Acquire))
+}
@@ -0,0 +1,305 @@
+// Labeled points and rectangles that the user marks on works in the slideshow, e.g. "signature
+// here" or "pentimento", shown over the work as an overlay.
+//
+// Note: marks are stored in fractions of the untransformed image, so we map them through the
+//       work's display transform both ways: to draw them, and to place new ones.
+use crate::{
+    db::{
+        models::{annotation::DbAnnotation, work::DisplayTransform},
+        reader::DbReadHandle,
+        writer::DbWriteHandle,
+    },
+    shared::update::DataUpdate,
+    ux::display::{image_to_screen, screen_to_image},
+};
+use egui::{Align2, Color32, FontId, Key, Modifiers, Pos2, Rect, RichText, Stroke, Vec2, pos2};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// A region that the user has drawn and has not labeled yet.
+#[derive(Debug)]
+struct Unlabeled {
+    screen_url: String,
+    region: Rect,
+    label: String,
+    // Where to ask for the label, on screen.
+    at: Pos2,
+    has_focused: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxAnnotations {
+    show_overlay: bool,
+
+    #[serde(skip)]
+    marking: bool,
+    // By screen url; fetched the first time that each work is shown.
+    #[serde(skip)]
+    by_work: HashMap<String, Vec<DbAnnotation>>,
+    #[serde(skip)]
+    requested: HashSet<String>,
+    // Where on the image the current drag started.
+    #[serde(skip)]
+    drag_from: Option<Pos2>,
+    #[serde(skip)]
+    unlabeled: Option<Unlabeled>,
+}
+
+impl UxAnnotations {
+    // Drags shorter than this, in screen pixels, mark a point rather than a rectangle.
+    const MIN_DRAG: f32 = 6.;
+    const COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+
+    pub fn toggle_overlay(&mut self) {
+        self.show_overlay = !self.show_overlay;
+    }
+
+    pub fn toggle_marking(&mut self) {
+        self.marking = !self.marking;
+        self.drag_from = None;
+        // Note: marking blind would be no use.
+        if self.marking {
+            self.show_overlay = true;
+        }
+    }
+
+    // While marking, drags draw rather than pan.
+    pub fn is_marking(&self) -> bool {
+        self.marking
+    }
+
+    // While the user types a label, keys are for the label rather than the slideshow.
+    pub fn is_labeling(&self) -> bool {
+        self.unlabeled.is_some()
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::Annotations {
+                screen_url,
+                annotations,
+            } = update
+            {
+                self.by_work.insert(screen_url.clone(), annotations.clone());
+            }
+        }
+    }
+
+    fn get(&mut self, screen_url: &str, db: &DbReadHandle) -> Option<&[DbAnnotation]> {
+        if !self.by_work.contains_key(screen_url) && self.requested.insert(screen_url.to_owned()) {
+            db.get_annotations(screen_url);
+        }
+        self.by_work.get(screen_url).map(Vec::as_slice)
+    }
+
+    // `rect` is where the work was shown, with `transform` applied.
+    pub fn slideshow_ui(
+        &mut self,
+        screen_url: &str,
+        transform: &DisplayTransform,
+        rect: Rect,
+        (db_read, db_write): (&DbReadHandle, &DbWriteHandle),
+        ui: &egui::Ui,
+    ) {
+        if self.show_overlay
+            && let Some(annotations) = self.get(screen_url, db_read)
+        {
+            for annotation in annotations {
+                Self::paint(annotation, transform, rect, ui.painter());
+            }
+        }
+        if self.marking && self.unlabeled.is_none() {
+            self.mark(screen_url, transform, rect, ui);
+        }
+        self.label_ui(db_write, ui.ctx());
+    }
+
+    fn paint(
+        annotation: &DbAnnotation,
+        transform: &DisplayTransform,
+        rect: Rect,
+        painter: &egui::Painter,
+    ) {
+        let stroke = Stroke::new(2., Self::COLOR);
+        let corner = image_to_screen(transform, rect, pos2(annotation.x, annotation.y));
+        let label_at = if annotation.is_point() {
+            painter.circle_stroke(corner, 6., stroke);
+            corner + Vec2::new(8., -8.)
+        } else {
+            let far = pos2(
+                annotation.x + annotation.width,
+                annotation.y + annotation.height,
+            );
+            let region = Rect::from_two_pos(corner, image_to_screen(transform, rect, far));
+            painter.rect_stroke(region, 0., stroke, egui::StrokeKind::Outside);
+            region.left_top() - Vec2::new(0., 2.)
+        };
+        let galley = painter.layout_no_wrap(
+            annotation.label.clone(),
+            FontId::proportional(14.),
+            Color32::BLACK,
+        );
+        let tag = Align2::LEFT_BOTTOM
+            .anchor_size(label_at, galley.size())
+            .expand(3.);
+        painter.rect_filled(tag, 3., Self::COLOR);
+        painter.galley(tag.min + Vec2::splat(3.), galley, Color32::BLACK);
+    }
+
+    fn mark(&mut self, screen_url: &str, transform: &DisplayTransform, rect: Rect, ui: &egui::Ui) {
+        let (pressed, down, pos) = ui.input(|input| {
+            (
+                input.pointer.primary_pressed(),
+                input.pointer.primary_down(),
+                input.pointer.interact_pos(),
+            )
+        });
+        let Some(pos) = pos else {
+            return;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+        let to_image = |pos| {
+            let uv = screen_to_image(transform, rect, pos);
+            pos2(uv.x.clamp(0., 1.), uv.y.clamp(0., 1.))
+        };
+        if pressed && rect.contains(pos) {
+            self.drag_from = Some(to_image(pos));
+        }
+        let Some(from) = self.drag_from else {
+            return;
+        };
+
+        let start = image_to_screen(transform, rect, from);
+        let is_point = start.distance(pos) < Self::MIN_DRAG;
+        if down {
+            if !is_point {
+                ui.painter().rect_stroke(
+                    Rect::from_two_pos(start, pos),
+                    0.,
+                    Stroke::new(1., Self::COLOR),
+                    egui::StrokeKind::Outside,
+                );
+            }
+            return;
+        }
+        self.drag_from = None;
+        let region = if is_point {
+            Rect::from_min_size(from, Vec2::ZERO)
+        } else {
+            Rect::from_two_pos(from, to_image(pos))
+        };
+        self.unlabeled = Some(Unlabeled {
+            screen_url: screen_url.to_owned(),
+            region,
+            label: String::new(),
+            at: pos,
+            has_focused: false,
+        });
+    }
+
+    fn label_ui(&mut self, db_write: &DbWriteHandle, ctx: &egui::Context) {
+        let Some(unlabeled) = &mut self.unlabeled else {
+            return;
+        };
+        let mut done = None;
+        egui::Area::new(egui::Id::new("annotation_label"))
+            .fixed_pos(unlabeled.at)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let resp = ui.add(
+                        egui::TextEdit::singleline(&mut unlabeled.label)
+                            .hint_text("Label, then Enter; Escape to drop it")
+                            .desired_width(240.),
+                    );
+                    if !unlabeled.has_focused {
+                        resp.request_focus();
+                        unlabeled.has_focused = true;
+                    } else if resp.lost_focus() {
+                        done = Some(ui.input(|input| input.key_pressed(Key::Enter)));
+                    }
+                });
+            });
+
+        match done {
+            Some(true) if !unlabeled.label.trim().is_empty() => {
+                let region = unlabeled.region;
+                if let Err(e) = db_write.add_annotation(
+                    &unlabeled.screen_url,
+                    unlabeled.label.trim(),
+                    [region.min.x, region.min.y, region.width(), region.height()],
+                ) {
+                    error!("Failed to save the annotation: {e}");
+                }
+                self.unlabeled = None;
+            }
+            Some(_) => {
+                // Note: the Escape that dropped the label should not also leave the slideshow.
+                ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Escape));
+                self.unlabeled = None;
+            }
+            None => {}
+        }
+    }
+
+    // The annotations on a work, in Work Info.
+    pub fn info_ui(
+        &mut self,
+        screen_url: &str,
+        db_read: &DbReadHandle,
+        db_write: &DbWriteHandle,
+        ui: &mut egui::Ui,
+    ) {
+        let Some(annotations) = self.get(screen_url, db_read) else {
+            ui.spinner();
+            return;
+        };
+        if annotations.is_empty() {
+            ui.label("Press M in the slideshow, then click to mark a point or drag to mark a region. Press O to show or hide the marks.");
+            return;
+        }
+        let mut delete = None;
+        egui::Grid::new("work_info_annotations")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for annotation in annotations {
+                    ui.label(&annotation.label);
+                    let at = format!("{:.0}%, {:.0}%", annotation.x * 100., annotation.y * 100.);
+                    let place = if annotation.is_point() {
+                        format!("point at {at}")
+                    } else {
+                        format!(
+                            "{:.0}% x {:.0}% at {at}",
+                            annotation.width * 100.,
+                            annotation.height * 100.
+                        )
+                    };
+                    ui.label(RichText::new(place).weak());
+                    if ui.button("🗑").on_hover_text("Delete").clicked() {
+                        delete = Some(annotation.id);
+                    }
+                    ui.end_row();
+                }
+            });
+        if ui
+            .button("JSON 📋")
+            .on_hover_text(
+                "Copy the annotations as JSON; they are also in the HTTP API's work metadata",
+            )
+            .clicked()
+        {
+            match serde_json::to_string_pretty(annotations) {
+                Ok(json) => ui.ctx().copy_text(json),
+                Err(e) => error!("Failed to write the annotations as JSON: {e}"),
+            }
+        }
+        if let Some(id) = delete
+            && let Err(e) = db_write.delete_annotation(screen_url, id)
+        {
+            error!("Failed to delete the annotation: {e}");
+        }
+    }
+}
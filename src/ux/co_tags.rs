@@ -0,0 +1,292 @@
+// Explore the tags that show up alongside the selected tag, either as a graph around it or as a
+// heatmap of how often each pair of them appear together. Clicking on a tag pivots the gallery
+// to it.
+use crate::{
+    db::{
+        models::tag::{CoTags, DbTag, TagId},
+        reader::DbReadHandle,
+    },
+    shared::{tag::TagSet, update::DataUpdate},
+};
+use egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, f32::consts::TAU};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+enum CoTagView {
+    #[default]
+    Graph,
+    Heatmap,
+}
+
+// What the user clicked on: a tag to pivot to, and whether to add it to the selection instead.
+struct Pivot {
+    tags: Vec<TagId>,
+    add: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxCoTags {
+    view: CoTagView,
+
+    #[serde(skip)]
+    requested: Option<TagId>,
+    #[serde(skip)]
+    co_tags: Option<CoTags>,
+}
+
+impl UxCoTags {
+    const LABEL_WIDTH: f32 = 140.;
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::CoTags(co_tags) if Some(co_tags.tag_id) == self.requested => {
+                    self.co_tags = Some(co_tags.clone());
+                }
+                // Note: new works may have changed which tags go together.
+                DataUpdate::WorksWereUpdatedForTag { new_works, .. } if *new_works > 0 => {
+                    self.requested = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        selection: &mut TagSet,
+        db: &DbReadHandle,
+        ui: &mut egui::Ui,
+    ) {
+        let focus = selection.enabled().next();
+        if focus != self.requested {
+            self.requested = focus;
+            if let Some(tag_id) = focus {
+                db.get_co_tags(tag_id);
+            }
+        }
+        let (Some(tags), Some(focus)) = (tags, focus) else {
+            ui.label("Select a tag to see the tags that go with it.");
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.heading(Self::name(tags, focus));
+            ui.separator();
+            ui.selectable_value(&mut self.view, CoTagView::Graph, "Graph");
+            ui.selectable_value(&mut self.view, CoTagView::Heatmap, "Heatmap");
+        });
+        let Some(co_tags) = self.co_tags.as_ref().filter(|c| c.tag_id == focus) else {
+            ui.spinner();
+            return;
+        };
+        if co_tags.tags.is_empty() {
+            ui.label("No other tags appear on these works.");
+            return;
+        }
+        ui.weak("Click a tag to switch to it; shift-click to narrow the gallery down to both.");
+
+        let pivot = match self.view {
+            CoTagView::Graph => Self::graph_ui(co_tags, tags, ui),
+            CoTagView::Heatmap => Self::heatmap_ui(co_tags, tags, ui),
+        };
+        if let Some(pivot) = pivot {
+            if !pivot.add {
+                selection.clear();
+            }
+            for tag in pivot.tags.iter().filter_map(|id| tags.get(id)) {
+                selection.enable(tag);
+            }
+        }
+    }
+
+    fn name(tags: &HashMap<TagId, DbTag>, id: TagId) -> &str {
+        tags.get(&id).map_or("?", DbTag::name)
+    }
+
+    fn pair_count(co_tags: &CoTags, a: TagId, b: TagId) -> usize {
+        let key = if a < b { (a, b) } else { (b, a) };
+        co_tags.pairs.get(&key).copied().unwrap_or_default()
+    }
+
+    // The selected tag in the middle, with the tags that go with it around it; closer and
+    // bigger the more works they share.
+    fn graph_ui(
+        co_tags: &CoTags,
+        tags: &HashMap<TagId, DbTag>,
+        ui: &mut egui::Ui,
+    ) -> Option<Pivot> {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click());
+        let rect = response.rect;
+        let center = rect.center();
+        let reach = (rect.width().min(rect.height()) / 2. - 40.).max(40.);
+        let most = co_tags.tags.first().map_or(1, |(_, n)| *n).max(1) as f32;
+        let font = FontId::proportional(12.);
+        let text_color = ui.visuals().text_color();
+        let node_color = ui.visuals().selection.bg_fill;
+
+        let nodes = co_tags
+            .tags
+            .iter()
+            .enumerate()
+            .map(|(i, (id, shared))| {
+                let strength = *shared as f32 / most;
+                let angle = i as f32 * TAU / co_tags.tags.len() as f32;
+                let distance = reach * (1. - 0.6 * strength);
+                let pos = center + Vec2::angled(angle) * distance;
+                (*id, *shared, pos, 4. + 12. * strength.sqrt())
+            })
+            .collect::<Vec<_>>();
+
+        let most_pair = co_tags.pairs.values().copied().max().unwrap_or(1).max(1) as f32;
+        for (i, (a, _, a_pos, _)) in nodes.iter().enumerate() {
+            for (b, _, b_pos, _) in &nodes[i + 1..] {
+                let count = Self::pair_count(co_tags, *a, *b);
+                if count > 0 {
+                    let weight = count as f32 / most_pair;
+                    painter.line_segment(
+                        [*a_pos, *b_pos],
+                        Stroke::new(
+                            0.5 + 2. * weight,
+                            text_color.gamma_multiply(0.1 + 0.3 * weight),
+                        ),
+                    );
+                }
+            }
+        }
+        for (_, shared, pos, _) in &nodes {
+            let weight = *shared as f32 / most;
+            painter.line_segment(
+                [center, *pos],
+                Stroke::new(
+                    1. + 3. * weight,
+                    node_color.gamma_multiply(0.3 + 0.5 * weight),
+                ),
+            );
+        }
+        painter.circle_filled(center, 18., node_color);
+        painter.text(
+            center,
+            Align2::CENTER_CENTER,
+            Self::name(tags, co_tags.tag_id),
+            font.clone(),
+            text_color,
+        );
+
+        let hover = response.hover_pos();
+        let mut hovered = None;
+        for (id, shared, pos, radius) in &nodes {
+            let is_hovered = hover.is_some_and(|p| p.distance(*pos) <= radius.max(8.));
+            if is_hovered {
+                hovered = Some((*id, *shared));
+            }
+            let fill = if is_hovered { text_color } else { node_color };
+            painter.circle_filled(*pos, *radius, fill);
+            painter.text(
+                *pos + Vec2::new(0., radius + 2.),
+                Align2::CENTER_TOP,
+                Self::name(tags, *id),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let (id, shared) = hovered?;
+        response.clone().on_hover_text(format!(
+            "{}: on {shared} of {} works ({:.0}%)",
+            Self::name(tags, id),
+            co_tags.work_count,
+            shared as f32 / co_tags.work_count.max(1) as f32 * 100.
+        ));
+        response.clicked().then(|| Pivot {
+            tags: vec![id],
+            add: ui.input(|i| i.modifiers.shift),
+        })
+    }
+
+    // How often each pair of the related tags appear together on the selected tag's works,
+    // with each tag's overlap with the selected tag on the diagonal.
+    fn heatmap_ui(
+        co_tags: &CoTags,
+        tags: &HashMap<TagId, DbTag>,
+        ui: &mut egui::Ui,
+    ) -> Option<Pivot> {
+        let n = co_tags.tags.len();
+        let available = ui.available_size();
+        let cell = ((available.x - Self::LABEL_WIDTH).min(available.y) / n as f32).clamp(6., 28.);
+        let size = Vec2::new(Self::LABEL_WIDTH + cell * n as f32, cell * n as f32);
+        let (response, painter) = ui.allocate_painter(size, Sense::click());
+        let origin = response.rect.min + Vec2::new(Self::LABEL_WIDTH, 0.);
+        let font = FontId::proportional(cell.clamp(8., 12.));
+        let text_color = ui.visuals().text_color();
+        let hot = ui.visuals().selection.bg_fill;
+        let most = co_tags.tags.first().map_or(1, |(_, n)| *n).max(1) as f32;
+
+        let count = |row: usize, column: usize| {
+            let (a, shared) = co_tags.tags[row];
+            if row == column {
+                shared
+            } else {
+                Self::pair_count(co_tags, a, co_tags.tags[column].0)
+            }
+        };
+        let cell_rect = |row: usize, column: usize| {
+            Rect::from_min_size(
+                origin + Vec2::new(column as f32 * cell, row as f32 * cell),
+                Vec2::splat(cell),
+            )
+        };
+
+        for (row, (id, _)) in co_tags.tags.iter().enumerate() {
+            painter.text(
+                Pos2::new(origin.x - 4., origin.y + (row as f32 + 0.5) * cell),
+                Align2::RIGHT_CENTER,
+                Self::name(tags, *id),
+                font.clone(),
+                text_color,
+            );
+            for column in 0..n {
+                let heat = count(row, column) as f32 / most;
+                painter.rect_filled(
+                    cell_rect(row, column).shrink(0.5),
+                    1.,
+                    Color32::from_black_alpha(40).lerp_to_gamma(hot, heat.sqrt()),
+                );
+            }
+        }
+
+        let pos = response.hover_pos()?;
+        let offset = (pos - origin) / cell;
+        if offset.x < 0. || offset.y < 0. {
+            return None;
+        }
+        let (row, column) = (offset.y as usize, offset.x as usize);
+        if row >= n || column >= n {
+            return None;
+        }
+        painter.rect_stroke(
+            cell_rect(row, column),
+            1.,
+            Stroke::new(1., text_color),
+            egui::StrokeKind::Inside,
+        );
+        let (a, b) = (co_tags.tags[row].0, co_tags.tags[column].0);
+        let tip = if row == column {
+            format!("{}: on {} works", Self::name(tags, a), count(row, column))
+        } else {
+            format!(
+                "{} and {}: together on {} works",
+                Self::name(tags, a),
+                Self::name(tags, b),
+                count(row, column)
+            )
+        };
+        response.clone().on_hover_text(tip);
+        response.clicked().then(|| Pivot {
+            tags: if a == b { vec![a] } else { vec![a, b] },
+            add: ui.input(|i| i.modifiers.shift),
+        })
+    }
+}
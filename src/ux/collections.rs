@@ -0,0 +1,108 @@
+// Browse the portfolios, series, and other groupings that plugins report for their works. These
+// are kept as the plugin says they are, so there is nothing to edit here; clicking one shows its
+// works in the gallery.
+use crate::{
+    db::{
+        models::{
+            collection::DbCollection,
+            tag::{DbTag, TagId},
+        },
+        reader::DbReadHandle,
+    },
+    shared::{tag::TagSet, update::DataUpdate},
+};
+use itertools::Itertools as _;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct UxCollections {
+    filter: String,
+    requested: bool,
+    collections: Option<Vec<DbCollection>>,
+}
+
+impl UxCollections {
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::Collections(collections) => {
+                    self.collections = Some(collections.clone());
+                }
+                // Note: new collections arrive as tags, and new works may join old collections.
+                DataUpdate::TagsWereRefreshed | DataUpdate::WorksWereUpdatedForTag { .. } => {
+                    self.requested = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // All of the collections, once they have loaded.
+    pub fn collections(&mut self, db: &DbReadHandle) -> Option<&[DbCollection]> {
+        if !self.requested {
+            self.requested = true;
+            db.get_collections();
+        }
+        self.collections.as_deref()
+    }
+
+    pub fn ui(
+        &mut self,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        selection: &mut TagSet,
+        db: &DbReadHandle,
+        ui: &mut egui::Ui,
+    ) {
+        if !self.requested {
+            self.requested = true;
+            db.get_collections();
+        }
+        let (Some(tags), Some(collections)) = (tags, self.collections.as_ref()) else {
+            ui.spinner();
+            return;
+        };
+        if collections.is_empty() {
+            ui.label("No plugin has reported any collections yet.");
+            return;
+        }
+        ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("filter"));
+        ui.weak("Click a collection to show it; shift-click to narrow the gallery down to it.");
+
+        let filter = self.filter.to_lowercase();
+        let mut picked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (plugin, collections) in &collections
+                .iter()
+                .filter(|c| filter.is_empty() || c.name().to_lowercase().contains(&filter))
+                .chunk_by(|c| c.plugin())
+            {
+                egui::CollapsingHeader::new(plugin)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for collection in collections {
+                            let Some(tag) = tags.get(&collection.tag_id()) else {
+                                continue;
+                            };
+                            ui.horizontal(|ui| {
+                                let selected = selection.status(tag).enabled();
+                                if ui.selectable_label(selected, collection.name()).clicked() {
+                                    picked = Some(tag);
+                                }
+                                ui.weak(format!(
+                                    "{}, {} works",
+                                    collection.kind(),
+                                    collection.work_count()
+                                ));
+                            });
+                        }
+                    });
+            }
+        });
+        if let Some(tag) = picked {
+            if !ui.input(|i| i.modifiers.shift) {
+                selection.clear();
+            }
+            selection.enable(tag);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+// Color management for the images we show. Museum scans often carry an ICC profile, e.g. Adobe
+// RGB or ProPhoto, that egui's own loaders ignore, so the colors come out dull or shifted. This
+// puts a loader in front of egui's that converts files with an embedded profile to sRGB, or to
+// the user's display profile, and leaves every other file to the usual loaders.
+use crate::{
+    plugin::thumbnail::{is_raw, open_image},
+    shared::vector::is_vector,
+    ux::tone_map,
+};
+use egui::{
+    ColorImage, SizeHint,
+    load::{ImageLoadResult, ImageLoader, ImagePoll, LoadError},
+};
+use image::{DynamicImage, ImageDecoder as _, ImageReader, RgbaImage};
+use log::warn;
+use parking_lot::Mutex;
+use qcms::{DataType, Intent, Profile, Transform};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+// What we did to an image on its way to the screen.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Conversion {
+    pub profile: bool,
+    pub tone_mapped: bool,
+    pub raw_preview: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Converted {
+    Pending,
+    Ready(Arc<ColorImage>, Conversion),
+    // Nothing for us to do, or a file we can't read; the usual loaders get these, and report any
+    // errors.
+    Unmanaged,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Settings {
+    profiles: bool,
+    // The profile to convert to; sRGB when empty.
+    display_profile: Option<Vec<u8>>,
+    high_depth: bool,
+    exposure: f32,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    settings: Settings,
+    images: HashMap<String, Converted>,
+}
+
+fn profile_transform(icc: &[u8], settings: &Settings, path: &Path) -> Option<Transform> {
+    let Some(input) = Profile::new_from_slice(icc, false) else {
+        warn!(
+            "Ignoring the unreadable color profile in {}",
+            path.display()
+        );
+        return None;
+    };
+    let mut output = settings
+        .display_profile
+        .as_deref()
+        .and_then(|profile| Profile::new_from_slice(profile, false))
+        .unwrap_or_else(Profile::new_sRGB);
+    output.precache_output_transform();
+    // Note: qcms only converts between RGB profiles; CMYK and gray scans look as they always did.
+    Transform::new(&input, &output, DataType::RGBA8, Intent::default())
+}
+
+// Decode the file, bringing deep images down to 8 bits and converting from any embedded profile,
+// or None if the file needs neither.
+fn convert(path: &Path, settings: &Settings) -> Result<Option<(ColorImage, Conversion)>, String> {
+    if is_raw(path) {
+        let image = open_image(path).map_err(|e| e.to_string())?;
+        let conversion = Conversion {
+            raw_preview: true,
+            ..Conversion::default()
+        };
+        return Ok(Some((color_image(&image.to_rgba8()), conversion)));
+    }
+
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let transform = settings
+        .profiles
+        .then(|| decoder.icc_profile().ok().flatten())
+        .flatten()
+        .and_then(|icc| profile_transform(&icc, settings, path));
+    let deep = settings.high_depth && tone_map::is_high_depth(decoder.color_type());
+    if transform.is_none() && !deep {
+        return Ok(None);
+    }
+
+    let image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    let mut pixels = if deep {
+        tone_map::to_display(&image, settings.exposure)
+    } else {
+        image.to_rgba8()
+    };
+    if let Some(transform) = &transform {
+        transform.apply(&mut pixels);
+    }
+    let conversion = Conversion {
+        profile: transform.is_some(),
+        tone_mapped: deep,
+        raw_preview: false,
+    };
+    Ok(Some((color_image(&pixels), conversion)))
+}
+
+fn color_image(pixels: &RgbaImage) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied(
+        [pixels.width() as usize, pixels.height() as usize],
+        pixels.as_raw(),
+    )
+}
+
+#[derive(Clone, Default)]
+struct ColorManagedLoader {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl ImageLoader for ColorManagedLoader {
+    fn id(&self) -> &str {
+        concat!(module_path!(), "::ColorManagedLoader")
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, _size_hint: SizeHint) -> ImageLoadResult {
+        let Some(path) = uri.strip_prefix("file://") else {
+            return Err(LoadError::NotSupported);
+        };
+        // Note: the slideshow draws SVG works itself, at the size they are shown.
+        if is_vector(Path::new(path)) {
+            return Err(LoadError::NotSupported);
+        }
+        let mut shared = self.shared.lock();
+        let nothing_to_do = !shared.settings.profiles && !shared.settings.high_depth;
+        if nothing_to_do && !is_raw(Path::new(path)) {
+            return Err(LoadError::NotSupported);
+        }
+        match shared.images.get(uri) {
+            Some(Converted::Pending) => return Ok(ImagePoll::Pending { size: None }),
+            Some(Converted::Ready(image, _)) => {
+                return Ok(ImagePoll::Ready {
+                    image: image.clone(),
+                });
+            }
+            Some(Converted::Unmanaged) => return Err(LoadError::NotSupported),
+            None => {}
+        }
+
+        shared.images.insert(uri.to_owned(), Converted::Pending);
+        let settings = shared.settings.clone();
+        let (path, uri, ctx) = (PathBuf::from(path), uri.to_owned(), ctx.clone());
+        let inner = self.shared.clone();
+        thread::spawn(move || {
+            let converted = match convert(&path, &settings) {
+                Ok(Some((image, conversion))) => Converted::Ready(Arc::new(image), conversion),
+                Ok(None) | Err(_) => Converted::Unmanaged,
+            };
+            let mut shared = inner.lock();
+            // Note: the settings may have changed, and dropped this one, while we worked.
+            if let Some(entry) = shared.images.get_mut(&uri) {
+                *entry = converted;
+            }
+            ctx.request_repaint();
+        });
+        Ok(ImagePoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.shared.lock().images.remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.shared.lock().images.clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.shared
+            .lock()
+            .images
+            .values()
+            .map(|converted| match converted {
+                Converted::Ready(image, _) => image.pixels.len() * 4,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn has_pending(&self) -> bool {
+        self.shared
+            .lock()
+            .images
+            .values()
+            .any(|converted| matches!(converted, Converted::Pending))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorManagement {
+    enabled: bool,
+    // The path to an ICC profile for the monitor, from the OS's calibration tools; empty for sRGB.
+    display_profile: String,
+    // Tone map float images and dither 16-bit ones, rather than cutting them to 8 bits.
+    high_depth: bool,
+    // Stops of exposure on top of the automatic exposure for float images.
+    exposure: f32,
+
+    #[serde(skip)]
+    loader: ColorManagedLoader,
+    #[serde(skip)]
+    display_profile_error: Option<String>,
+}
+
+impl Default for ColorManagement {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            display_profile: String::new(),
+            high_depth: true,
+            exposure: 0.,
+            loader: ColorManagedLoader::default(),
+            display_profile_error: None,
+        }
+    }
+}
+
+impl ColorManagement {
+    // Note: egui tries the most recently added loader first, so this must come after egui_extras'.
+    pub fn install(&mut self, ctx: &egui::Context) {
+        self.apply();
+        ctx.add_image_loader(Arc::new(self.loader.clone()));
+    }
+
+    fn apply(&mut self) {
+        self.display_profile_error = None;
+        let path = self.display_profile.trim();
+        let display_profile = if path.is_empty() {
+            None
+        } else {
+            let loaded = fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| {
+                Profile::new_from_slice(&bytes, false)
+                    .map(|_| bytes)
+                    .ok_or_else(|| "not a usable ICC profile".to_owned())
+            });
+            loaded
+                .map_err(|e| self.display_profile_error = Some(e))
+                .ok()
+        };
+        let mut shared = self.loader.shared.lock();
+        shared.settings = Settings {
+            profiles: self.enabled,
+            display_profile,
+            high_depth: self.high_depth,
+            exposure: self.exposure,
+        };
+        shared.images.clear();
+    }
+
+    // What was done to the image at `uri` to show it, if anything.
+    pub fn conversion(&self, uri: &str) -> Option<Conversion> {
+        match self.loader.shared.lock().images.get(uri) {
+            Some(Converted::Ready(_, conversion)) => Some(*conversion),
+            _ => None,
+        }
+    }
+
+    pub fn target_name(&self) -> &'static str {
+        if self.loader.shared.lock().settings.display_profile.is_some() {
+            "the display profile"
+        } else {
+            "sRGB"
+        }
+    }
+
+    pub fn preferences_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = ui
+            .checkbox(
+                &mut self.enabled,
+                "Convert images with an embedded color profile for display",
+            )
+            .changed();
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Display profile");
+                let resp = ui.add(
+                    egui::TextEdit::singleline(&mut self.display_profile)
+                        .hint_text("sRGB; or the path to an .icc"),
+                );
+                changed |= resp.lost_focus();
+            });
+            if let Some(e) = &self.display_profile_error {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Using sRGB; the display profile failed to load: {e}"),
+                );
+            }
+        });
+        changed |= ui
+            .checkbox(
+                &mut self.high_depth,
+                "Tone map HDR images, and dither 16-bit ones, instead of cutting them to 8 bits",
+            )
+            .changed();
+        ui.add_enabled_ui(self.high_depth, |ui| {
+            let resp = ui.add(
+                egui::Slider::new(&mut self.exposure, -4f32..=4f32)
+                    .text("HDR exposure (stops)")
+                    .step_by(0.25),
+            );
+            // Note: every change re-decodes the slide, so wait for the drag to finish.
+            changed |= resp.drag_stopped() || (resp.changed() && !resp.dragged());
+        });
+        if changed {
+            self.apply();
+            // Note: everything loaded so far was made under the old settings.
+            ui.ctx().forget_all_images();
+        }
+    }
+}
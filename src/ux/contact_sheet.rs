@@ -0,0 +1,109 @@
+use crate::shared::contact_sheet::{ContactSheetSettings, Paper, SheetEntry, write_contact_sheet};
+use crossbeam::channel::{Receiver, bounded};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, thread};
+
+// The contact sheet window: prints the works in the gallery to a PDF.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxContactSheet {
+    settings: ContactSheetSettings,
+    out: String,
+
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    pending: Option<Receiver<Result<usize, String>>>,
+    #[serde(skip)]
+    last_result: Option<Result<usize, String>>,
+}
+
+impl UxContactSheet {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    // `entries` is only called when the user asks for the sheet, since it touches every work.
+    pub fn window(
+        &mut self,
+        count: usize,
+        entries: impl FnOnce() -> Vec<SheetEntry>,
+        ctx: &egui::Context,
+    ) {
+        if let Some(rx) = &self.pending
+            && let Ok(result) = rx.try_recv()
+        {
+            self.pending = None;
+            self.last_result = Some(result);
+        }
+
+        let mut is_open = self.open;
+        let mut start = false;
+        egui::Window::new("Print Contact Sheet")
+            .open(&mut is_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Lay out the {count} works in the gallery as a PDF."
+                ));
+                ui.add(egui::TextEdit::singleline(&mut self.out).hint_text("contact-sheet.pdf"));
+                egui::ComboBox::new("contact_sheet_paper", "Paper")
+                    .selected_text(self.settings.paper.label())
+                    .show_ui(ui, |ui| {
+                        for paper in Paper::ALL {
+                            ui.selectable_value(&mut self.settings.paper, paper, paper.label());
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut self.settings.columns, 1..=10).text("Columns"));
+                ui.add(egui::Slider::new(&mut self.settings.rows, 1..=12).text("Rows"));
+                ui.checkbox(&mut self.settings.captions, "Caption with title and date");
+
+                if self.pending.is_some() {
+                    ui.spinner();
+                } else if ui
+                    .add_enabled(
+                        count > 0 && !self.out.trim().is_empty(),
+                        egui::Button::new("Print to PDF"),
+                    )
+                    .clicked()
+                {
+                    start = true;
+                }
+
+                match &self.last_result {
+                    Some(Ok(pages)) => {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Wrote {pages} pages"));
+                            if ui.button("Open").clicked()
+                                && let Err(e) = open::that(self.out.trim())
+                            {
+                                error!("Failed to open the contact sheet: {e}");
+                            }
+                        });
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {e}"));
+                    }
+                    None => {}
+                }
+            });
+        self.open = is_open;
+
+        if start {
+            let entries = entries();
+            let out = PathBuf::from(self.out.trim());
+            let settings = self.settings.clone();
+            let ctx = ctx.clone();
+            let (tx, rx) = bounded(1);
+            self.pending = Some(rx);
+            self.last_result = None;
+            thread::spawn(move || {
+                let result =
+                    write_contact_sheet(&entries, &settings, &out).map_err(|e| format!("{e:#}"));
+                tx.send(result).ok();
+                ctx.request_repaint();
+            });
+        }
+    }
+}
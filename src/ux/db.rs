@@ -1,22 +1,61 @@
-use crate::shared::{
-    progress::{Progress, UpdateSource},
-    update::DataUpdate,
+use crate::{
+    db::{maintenance::OptimizeReport, writer::DbWriteHandle},
+    shared::{
+        progress::{Progress, UpdateSource},
+        update::DataUpdate,
+    },
 };
-use log::log;
+use jiff::{SignedDuration, Timestamp, tz::TimeZone};
+use log::{error, log};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct UxDb {
     messages: VecDeque<String>,
 
+    // Preferences
+    optimize_daily: bool,
+
+    last_optimize: Option<Timestamp>,
+    last_optimize_report: Option<OptimizeReport>,
+
+    #[serde(skip)]
+    optimizing: bool,
+    // When the user last touched anything; we only run maintenance while they are away.
+    #[serde(skip)]
+    last_input: Option<Instant>,
+
     #[serde(skip)]
     progress: Progress,
 }
 
+impl Default for UxDb {
+    fn default() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            optimize_daily: true,
+            last_optimize: None,
+            last_optimize_report: None,
+            optimizing: false,
+            last_input: None,
+            progress: Progress::None,
+        }
+    }
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024. * 1024.)
+}
+
 impl UxDb {
     const MAX_MESSAGES: usize = 20;
+    const OPTIMIZE_INTERVAL: SignedDuration = SignedDuration::from_hours(24);
+    const IDLE_BEFORE_OPTIMIZE: Duration = Duration::from_secs(5 * 60);
 
     pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
         for update in updates {
@@ -35,12 +74,86 @@ impl UxDb {
                 DataUpdate::Progress { source, progress } if source == &UpdateSource::DbWriter => {
                     self.progress = *progress;
                 }
+                DataUpdate::DatabaseOptimized(report) => {
+                    self.optimizing = false;
+                    self.last_optimize_report = Some(report.clone());
+                }
                 _ => {}
             }
         }
     }
 
-    pub fn ui(&self, ui: &mut egui::Ui) {
+    fn optimize_now(&mut self, db_write: &DbWriteHandle, manual: bool) {
+        self.last_optimize = Some(Timestamp::now());
+        match db_write.optimize_database(manual) {
+            Ok(()) => self.optimizing = true,
+            Err(e) => error!("Failed to request database maintenance: {e}"),
+        }
+    }
+
+    pub fn tick(&mut self, db_write: &DbWriteHandle, ctx: &egui::Context) {
+        if self.last_input.is_none() || ctx.input(|input| !input.events.is_empty()) {
+            self.last_input = Some(Instant::now());
+        }
+        if !self.optimize_daily
+            || self.optimizing
+            || self
+                .last_optimize
+                .is_some_and(|last| Timestamp::now().duration_since(last) < Self::OPTIMIZE_INTERVAL)
+        {
+            return;
+        }
+        // Note: we don't repaint while idle, so wake up to check again once the user is away.
+        let idle = self.last_input.map_or(Duration::ZERO, |at| at.elapsed());
+        if idle < Self::IDLE_BEFORE_OPTIMIZE {
+            ctx.request_repaint_after(Self::IDLE_BEFORE_OPTIMIZE - idle);
+            return;
+        }
+        self.optimize_now(db_write, false);
+    }
+
+    pub fn ui(&mut self, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.optimize_daily,
+            "Optimize the database once a day, while Artchiver is idle",
+        );
+        ui.horizontal(|ui| {
+            match self.last_optimize {
+                Some(last) => ui.label(format!(
+                    "Last optimized {}",
+                    last.to_zoned(TimeZone::system()).strftime("%Y-%m-%d %H:%M")
+                )),
+                None => ui.label("Never optimized"),
+            };
+            if ui
+                .add_enabled(!self.optimizing, egui::Button::new("Optimize Now"))
+                .on_hover_text(
+                    "Refresh query statistics and release unused space; the first time may take a while on large archives",
+                )
+                .clicked()
+            {
+                self.optimize_now(db_write, true);
+            }
+            if self.optimizing {
+                ui.spinner();
+            }
+        });
+        if let Some(report) = &self.last_optimize_report {
+            if let Some(err) = &report.error {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {err}"));
+            } else {
+                ui.label(format!(
+                    "{:.1} MiB → {:.1} MiB ({:.1} MiB released{}) in {:.1}s",
+                    mib(report.before_bytes),
+                    mib(report.after_bytes),
+                    mib(report.before_bytes.saturating_sub(report.after_bytes)),
+                    if report.vacuumed { ", rebuilt" } else { "" },
+                    report.elapsed_ms as f64 / 1000.,
+                ));
+            }
+        }
+        ui.separator();
+
         self.progress.ui(ui);
         for message in &self.messages {
             ui.label(message);
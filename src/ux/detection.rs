@@ -0,0 +1,513 @@
+// Runs the user's face and figure models over downloaded works in the background, and the review
+// queue for the tags that we suggest from what they find.
+use crate::{
+    db::{
+        models::detection::{DbTagSuggestion, PendingScan, SuggestionId},
+        reader::DbReadHandle,
+        writer::DbWriteHandle,
+    },
+    plugin::thumbnail::is_image,
+    shared::{
+        detection::{
+            self, Detection, DetectionKind, DetectionSettings, Detector, DetectorModel,
+            suggest_tags,
+        },
+        progress::Progress,
+        storage::Storage,
+        update::DataUpdate,
+    },
+};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use egui::include_image;
+use itertools::Itertools as _;
+use log::{error, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+enum Outcome {
+    Found(Vec<Detection>),
+    Unreadable(String),
+    // The work's file is on storage that isn't mounted right now; try again another time.
+    Offline,
+}
+
+enum DetectorEvent {
+    Scanned {
+        screen_url: String,
+        outcome: Outcome,
+    },
+    Stopped {
+        error: Option<String>,
+    },
+}
+
+struct DetectionRun {
+    storage: Storage,
+    ctx: egui::Context,
+
+    queue: Arc<Mutex<VecDeque<PendingScan>>>,
+    tx_event: Sender<DetectorEvent>,
+    rx_event: Receiver<DetectorEvent>,
+    running: bool,
+
+    scanning: bool,
+    last_scan: Option<Instant>,
+    // A download finished since the last scan, so there may be new works to look at.
+    stale: bool,
+
+    total: usize,
+    done: usize,
+    failed: usize,
+    error: Option<String>,
+}
+
+impl Default for DetectionRun {
+    fn default() -> Self {
+        let (tx_event, rx_event) = unbounded();
+        Self {
+            storage: Storage::default(),
+            ctx: egui::Context::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            tx_event,
+            rx_event,
+            running: false,
+            scanning: false,
+            last_scan: None,
+            stale: false,
+            total: 0,
+            done: 0,
+            failed: 0,
+            error: None,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxDetection {
+    settings: DetectionSettings,
+
+    #[serde(skip)]
+    run: DetectionRun,
+}
+
+impl UxDetection {
+    // Downloads arrive in bursts; look for new works at most this often.
+    const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+    fn is_enabled(&self) -> bool {
+        detection::AVAILABLE && self.settings.enabled && !self.settings.models.is_empty()
+    }
+
+    pub fn startup(&mut self, ctx: &egui::Context, storage: &Storage, db: &DbReadHandle) {
+        self.run.ctx = ctx.clone();
+        self.run.storage = storage.clone();
+        if self.is_enabled() {
+            self.scan(db);
+        }
+    }
+
+    fn scan(&mut self, db: &DbReadHandle) {
+        self.run.scanning = true;
+        self.run.stale = false;
+        self.run.last_scan = Some(Instant::now());
+        db.get_works_to_detect();
+    }
+
+    fn stop(&mut self) {
+        // Note: the worker finishes the work it is on, then finds the queue empty and exits.
+        let dropped = {
+            let mut queue = self.run.queue.lock();
+            let dropped = queue.len();
+            queue.clear();
+            dropped
+        };
+        self.run.total -= dropped;
+    }
+
+    pub fn handle_updates(&mut self, db: &DbReadHandle, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::WorksToDetect(works) => {
+                    self.run.scanning = false;
+                    if self.is_enabled() {
+                        self.enqueue(works);
+                    }
+                }
+                // Note: local imports are stored before the update goes out, so we can look at
+                //       them straight away.
+                DataUpdate::WorksWereUpdatedForTag { new_works, .. }
+                    if *new_works > 0 && self.is_enabled() && !self.run.scanning =>
+                {
+                    self.scan(db);
+                }
+                DataUpdate::WorkDownloadCompleted {
+                    screen_path: Some(_),
+                    ..
+                } => {
+                    self.run.stale = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn enqueue(&mut self, works: &[PendingScan]) {
+        {
+            let mut queue = self.run.queue.lock();
+            let queued = queue
+                .iter()
+                .map(|job| job.screen_url.clone())
+                .collect::<HashSet<_>>();
+            let fresh = works
+                .iter()
+                .filter(|work| !queued.contains(&work.screen_url))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !self.run.running {
+                self.run.total = 0;
+                self.run.done = 0;
+                self.run.failed = 0;
+                self.run.error = None;
+            }
+            self.run.total += fresh.len();
+            queue.extend(fresh);
+        }
+        if !self.run.running && !self.run.queue.lock().is_empty() {
+            self.spawn_worker();
+        }
+    }
+
+    // Note: one worker is plenty; ONNX Runtime already spreads each model run over the cores.
+    fn spawn_worker(&mut self) {
+        let settings = self.settings.clone();
+        let queue = self.run.queue.clone();
+        let storage = self.run.storage.clone();
+        let tx = self.run.tx_event.clone();
+        let ctx = self.run.ctx.clone();
+        let spawned = thread::Builder::new()
+            .name("Detector".to_owned())
+            .spawn(move || {
+                let mut detector = match Detector::load(&settings) {
+                    Ok(detector) => detector,
+                    Err(e) => {
+                        queue.lock().clear();
+                        tx.send(DetectorEvent::Stopped {
+                            error: Some(format!("{e:#}")),
+                        })
+                        .ok();
+                        ctx.request_repaint();
+                        return;
+                    }
+                };
+                loop {
+                    let Some(job) = queue.lock().pop_front() else {
+                        break;
+                    };
+                    let outcome = Self::detect(&mut detector, &job, &storage);
+                    let event = DetectorEvent::Scanned {
+                        screen_url: job.screen_url,
+                        outcome,
+                    };
+                    if tx.send(event).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+                tx.send(DetectorEvent::Stopped { error: None }).ok();
+                ctx.request_repaint();
+            });
+        match spawned {
+            Ok(_) => self.run.running = true,
+            Err(e) => error!("Failed to start the detector: {e}"),
+        }
+    }
+
+    fn detect(detector: &mut Detector, job: &PendingScan, storage: &Storage) -> Outcome {
+        let stored = Path::new(&job.screen_path);
+        if !storage.is_available(stored) {
+            return Outcome::Offline;
+        }
+        let path = storage.resolve(stored);
+        // Note: there are no faces to find in a song, and videos would need a frame picked.
+        if !is_image(&path) {
+            return Outcome::Found(Vec::new());
+        }
+        match detector.detect(&path) {
+            Ok(found) => Outcome::Found(found),
+            Err(e) => Outcome::Unreadable(format!("{e:#}")),
+        }
+    }
+
+    pub fn tick(&mut self, db: &DbReadHandle, db_write: &DbWriteHandle) {
+        while let Ok(event) = self.run.rx_event.try_recv() {
+            match event {
+                DetectorEvent::Scanned {
+                    screen_url,
+                    outcome,
+                } => {
+                    self.run.done += 1;
+                    let detections = match outcome {
+                        Outcome::Found(found) => found,
+                        // Note: record it as looked at anyway; a file that we can't read now
+                        //       won't be any more readable next time.
+                        Outcome::Unreadable(e) => {
+                            self.run.failed += 1;
+                            warn!("Failed to look for faces in {screen_url}: {e}");
+                            Vec::new()
+                        }
+                        Outcome::Offline => continue,
+                    };
+                    let suggestions = suggest_tags(&detections);
+                    if let Err(e) = db_write.save_detections(&screen_url, detections, suggestions) {
+                        error!("Failed to save what we found in {screen_url}: {e}");
+                    }
+                }
+                DetectorEvent::Stopped { error } => {
+                    self.run.running = false;
+                    if let Some(e) = &error {
+                        error!("Detection stopped: {e}");
+                    }
+                    // Note: works may have been queued after the worker found the queue empty.
+                    if error.is_none() && !self.run.queue.lock().is_empty() {
+                        self.spawn_worker();
+                    }
+                    self.run.error = error;
+                }
+            }
+        }
+        let due = self
+            .run
+            .last_scan
+            .is_none_or(|at| at.elapsed() >= Self::RESCAN_INTERVAL);
+        if self.run.stale && due && !self.run.running && !self.run.scanning && self.is_enabled() {
+            self.scan(db);
+        }
+    }
+
+    pub fn preferences_ui(&mut self, db: &DbReadHandle, ui: &mut egui::Ui) {
+        if !detection::AVAILABLE {
+            ui.label(
+                "This build can't look for faces and figures; build Artchiver with \
+                 `--features detection` to turn it on.",
+            );
+            return;
+        }
+        let was_enabled = self.is_enabled();
+        ui.checkbox(
+            &mut self.settings.enabled,
+            "Look for faces and figures in downloaded works and suggest tags for them",
+        );
+        ui.label(
+            "Detection runs YOLO-style ONNX models on this computer. Set which class of each \
+             model's output is a face or a figure: 0 for most face models, and for a YOLOv8 \
+             model's person class.",
+        );
+
+        let mut remove = None;
+        egui::Grid::new("detection_models")
+            .num_columns(4)
+            .show(ui, |ui| {
+                for (i, model) in self.settings.models.iter_mut().enumerate() {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut model.path)
+                            .hint_text("/path/to/model.onnx")
+                            .desired_width(280.),
+                    );
+                    egui::ComboBox::new(("detection_model_kind", i), "")
+                        .selected_text(model.kind.name())
+                        .show_ui(ui, |ui| {
+                            for kind in DetectionKind::ALL {
+                                ui.selectable_value(&mut model.kind, kind, kind.name());
+                            }
+                        });
+                    ui.add(egui::DragValue::new(&mut model.class).prefix("class "));
+                    if ui.button("🗑").on_hover_text("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = remove {
+            self.settings.models.remove(i);
+        }
+        if ui.button("Add Model").clicked() {
+            self.settings.models.push(DetectorModel::default());
+        }
+        ui.add(
+            egui::Slider::new(&mut self.settings.min_confidence, 0.1..=0.95)
+                .text("Minimum confidence"),
+        );
+
+        ui.horizontal(|ui| {
+            if self.run.scanning {
+                ui.spinner();
+            } else if self.run.running {
+                Progress::Percent {
+                    current: self.run.done,
+                    total: self.run.total.max(1),
+                }
+                .ui(ui);
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else {
+                if self.run.total > 0 {
+                    ui.label(format!("looked at {} works", self.run.done));
+                }
+                if ui
+                    .add_enabled(self.is_enabled(), egui::Button::new("Scan Now"))
+                    .on_hover_text(
+                        "Look at downloaded works that haven't been looked at; changes to the \
+                         models apply from the next scan",
+                    )
+                    .clicked()
+                {
+                    self.scan(db);
+                }
+            }
+            if self.run.failed > 0 {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{} unreadable", self.run.failed),
+                )
+                .on_hover_text("See the log for details");
+            }
+        });
+        if let Some(e) = &self.run.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        if self.is_enabled() && !was_enabled {
+            self.scan(db);
+        }
+    }
+}
+
+// Tags that detection suggests, for the user to accept or reject, grouped by tag.
+#[derive(Default)]
+pub struct UxTagSuggestions {
+    storage: Storage,
+    suggestions: Vec<DbTagSuggestion>,
+    is_loading: bool,
+    // Suggestions changed since we last read them; we read them again when the tab is shown.
+    stale: bool,
+}
+
+impl UxTagSuggestions {
+    const THUMB_SIZE: f32 = 96.;
+
+    pub fn startup(&mut self, storage: &Storage) {
+        self.storage = storage.clone();
+        self.stale = true;
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::TagSuggestions(suggestions) => {
+                    self.is_loading = false;
+                    self.suggestions = suggestions.clone();
+                }
+                DataUpdate::TagSuggestionsChanged => self.stale = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn review(&mut self, ids: Vec<SuggestionId>, accept: bool, db_write: &DbWriteHandle) {
+        self.suggestions
+            .retain(|suggestion| !ids.contains(&suggestion.id));
+        if let Err(e) = db_write.review_tag_suggestions(ids, accept) {
+            error!("Failed to review tag suggestions: {e}");
+        }
+    }
+
+    fn thumbnail<'a>(&self, suggestion: &DbTagSuggestion) -> egui::Image<'a> {
+        match suggestion.work.preview_path() {
+            Some(path) if self.storage.is_available(path) => {
+                egui::Image::new(format!("file://{}", self.storage.resolve(path).display()))
+            }
+            _ => egui::Image::new(include_image!("../../assets/loading-preview.png")),
+        }
+    }
+
+    pub fn ui(&mut self, db: &DbReadHandle, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        if self.stale && !self.is_loading {
+            self.stale = false;
+            self.is_loading = true;
+            db.get_tag_suggestions();
+        }
+        ui.horizontal(|ui| {
+            ui.label(format!("{} suggestions", self.suggestions.len()));
+            if self.is_loading {
+                ui.spinner();
+            }
+        });
+        ui.separator();
+        if self.suggestions.is_empty() {
+            ui.label(
+                "Nothing to review. Turn on detection in Preferences to get tag suggestions for \
+                 the faces and figures in your works.",
+            );
+            return;
+        }
+
+        let mut reviewed = None;
+        let groups = self
+            .suggestions
+            .iter()
+            .into_group_map_by(|suggestion| suggestion.tag.clone());
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (tag, suggestions) in groups.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+                egui::CollapsingHeader::new(format!("{tag} ({})", suggestions.len()))
+                    .id_salt(("tag_suggestions", &tag))
+                    .show(ui, |ui| {
+                        let ids = || suggestions.iter().map(|s| s.id).collect::<Vec<_>>();
+                        ui.horizontal(|ui| {
+                            if ui.button("✔ Accept All").clicked() {
+                                reviewed = Some((ids(), true));
+                            }
+                            if ui.button("✖ Reject All").clicked() {
+                                reviewed = Some((ids(), false));
+                            }
+                        });
+                        for suggestion in &suggestions {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    self.thumbnail(suggestion)
+                                        .fit_to_exact_size(egui::vec2(
+                                            Self::THUMB_SIZE,
+                                            Self::THUMB_SIZE,
+                                        ))
+                                        .maintain_aspect_ratio(true),
+                                );
+                                ui.vertical(|ui| {
+                                    ui.label(suggestion.work.name());
+                                    ui.weak(&suggestion.reason);
+                                    ui.horizontal(|ui| {
+                                        if ui.button(format!("✔ Tag {tag}")).clicked() {
+                                            reviewed = Some((vec![suggestion.id], true));
+                                        }
+                                        if ui.button("✖ Reject").clicked() {
+                                            reviewed = Some((vec![suggestion.id], false));
+                                        }
+                                    });
+                                });
+                            });
+                        }
+                    });
+            }
+        });
+
+        if let Some((ids, accept)) = reviewed {
+            self.review(ids, accept, db_write);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// Drawing works with the user's display transforms applied. Crops, flips, and turns are just
+// texture coordinates, so they cost nothing; brightness and contrast need the pixels
+// re-processed, so we do that off of the UX thread and keep a few of the results around.
+use crate::{
+    db::{models::work::DisplayTransform, writer::DbWriteHandle},
+    plugin::thumbnail::open_image,
+    shared::update::DataUpdate,
+};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use egui::{ColorImage, Pos2, Rect, TextureHandle, TextureOptions, Vec2, load::SizedTexture, pos2};
+use log::{error, warn};
+use lru::LruCache;
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::FRAC_PI_2,
+    mem,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread,
+};
+
+// A file, and the brightness and contrast it was re-processed with.
+type AdjustKey = (PathBuf, i32, i32);
+
+// Fit `size` into `bounds`, keeping its aspect ratio.
+pub fn fit(size: Vec2, bounds: Vec2) -> Vec2 {
+    if size.x <= 0. || size.y <= 0. {
+        return bounds;
+    }
+    size * (bounds.x / size.x).min(bounds.y / size.y)
+}
+
+// The shape of the image once cropped and turned, at the scale of `natural`.
+pub fn displayed_size(transform: &DisplayTransform, natural: Vec2) -> Vec2 {
+    let [left, top, right, bottom] = transform.crop;
+    let size = natural * Vec2::new((1. - left - right).max(0.01), (1. - top - bottom).max(0.01));
+    if transform.quarter_turns % 2 == 1 {
+        Vec2::new(size.y, size.x)
+    } else {
+        size
+    }
+}
+
+// Crop, flip, and turn `img`. Returns the rect to paint it at so that, once turned, it lands
+// exactly in `rect`.
+pub fn apply<'a>(
+    transform: &DisplayTransform,
+    img: egui::Image<'a>,
+    rect: Rect,
+) -> (egui::Image<'a>, Rect) {
+    let [left, top, right, bottom] = transform.crop;
+    let mut uv = Rect::from_min_max(pos2(left, top), pos2(1. - right, 1. - bottom));
+    if transform.flip_horizontal {
+        mem::swap(&mut uv.min.x, &mut uv.max.x);
+    }
+    if transform.flip_vertical {
+        mem::swap(&mut uv.min.y, &mut uv.max.y);
+    }
+    let paint_rect = if transform.quarter_turns % 2 == 1 {
+        Rect::from_center_size(rect.center(), Vec2::new(rect.height(), rect.width()))
+    } else {
+        rect
+    };
+    let img = img.uv(uv).rotate(
+        f32::from(transform.quarter_turns) * FRAC_PI_2,
+        Vec2::splat(0.5),
+    );
+    (img, paint_rect)
+}
+
+// Where `uv`, a point on the whole image as fractions of its width and height, is shown once the
+// transform is applied and the image drawn into `rect`.
+pub fn image_to_screen(transform: &DisplayTransform, rect: Rect, uv: Pos2) -> Pos2 {
+    let [left, top, right, bottom] = transform.crop;
+    let mut pos = pos2(
+        (uv.x - left) / (1. - left - right).max(0.01),
+        (uv.y - top) / (1. - top - bottom).max(0.01),
+    );
+    if transform.flip_horizontal {
+        pos.x = 1. - pos.x;
+    }
+    if transform.flip_vertical {
+        pos.y = 1. - pos.y;
+    }
+    for _ in 0..transform.quarter_turns % 4 {
+        pos = pos2(1. - pos.y, pos.x);
+    }
+    rect.min + pos.to_vec2() * rect.size()
+}
+
+// The inverse of image_to_screen, for finding where on the image the user clicked.
+pub fn screen_to_image(transform: &DisplayTransform, rect: Rect, pos: Pos2) -> Pos2 {
+    let mut pos = ((pos - rect.min) / rect.size()).to_pos2();
+    for _ in 0..transform.quarter_turns % 4 {
+        pos = pos2(pos.y, 1. - pos.x);
+    }
+    if transform.flip_horizontal {
+        pos.x = 1. - pos.x;
+    }
+    if transform.flip_vertical {
+        pos.y = 1. - pos.y;
+    }
+    let [left, top, right, bottom] = transform.crop;
+    pos2(
+        left + pos.x * (1. - left - right).max(0.01),
+        top + pos.y * (1. - top - bottom).max(0.01),
+    )
+}
+
+fn adjust(path: &Path, brightness: i32, contrast: i32) -> Result<ColorImage, String> {
+    let img = open_image(path)
+        .map_err(|e| e.to_string())?
+        .brighten(brightness * 255 / 100)
+        .adjust_contrast(contrast as f32)
+        .to_rgba8();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [img.width() as usize, img.height() as usize],
+        img.as_raw(),
+    ))
+}
+
+pub struct UxDisplay {
+    // By screen url, like the rest of the per-work data we keep across plugin refreshes.
+    transforms: HashMap<String, DisplayTransform>,
+    // Changes from the editor that the user is still dragging a slider for; we don't save, or
+    // re-process the image, until they let go.
+    editing: Option<(String, DisplayTransform)>,
+
+    adjusted: LruCache<AdjustKey, TextureHandle>,
+    pending: HashSet<AdjustKey>,
+    tx_adjusted: Sender<(AdjustKey, Result<ColorImage, String>)>,
+    rx_adjusted: Receiver<(AdjustKey, Result<ColorImage, String>)>,
+}
+
+impl Default for UxDisplay {
+    fn default() -> Self {
+        let (tx_adjusted, rx_adjusted) = unbounded();
+        Self {
+            transforms: HashMap::new(),
+            editing: None,
+            adjusted: LruCache::new(NonZeroUsize::new(Self::ADJUSTED_CACHE_SIZE).expect("nonzero")),
+            pending: HashSet::new(),
+            tx_adjusted,
+            rx_adjusted,
+        }
+    }
+}
+
+impl UxDisplay {
+    const ADJUSTED_CACHE_SIZE: usize = 64;
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::DisplayTransforms(transforms) = update {
+                self.transforms = transforms.clone();
+            }
+        }
+    }
+
+    pub fn get(&self, screen_url: &str) -> Option<DisplayTransform> {
+        self.transforms.get(screen_url).copied()
+    }
+
+    // A copy of the image at `path` with the transform's brightness and contrast applied, once
+    // it is ready.
+    pub fn adjusted_image<'a>(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        transform: &DisplayTransform,
+    ) -> Option<egui::Image<'a>> {
+        while let Ok((key, result)) = self.rx_adjusted.try_recv() {
+            self.pending.remove(&key);
+            match result {
+                Ok(pixels) => {
+                    let name = format!("adjusted://{}", key.0.display());
+                    let handle = ctx.load_texture(name, pixels, TextureOptions::LINEAR);
+                    self.adjusted.put(key, handle);
+                }
+                Err(e) => warn!("Failed to adjust {}: {e}", key.0.display()),
+            }
+        }
+
+        let key = (path.to_owned(), transform.brightness, transform.contrast);
+        if let Some(handle) = self.adjusted.get(&key) {
+            return Some(egui::Image::from_texture(SizedTexture::from_handle(handle)));
+        }
+        if self.pending.insert(key.clone()) {
+            let tx = self.tx_adjusted.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let result = adjust(&key.0, key.1, key.2);
+                tx.send((key, result)).ok();
+                ctx.request_repaint();
+            });
+        }
+        None
+    }
+
+    // The editor in the work info panel.
+    pub fn ui(&mut self, screen_url: &str, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        let stored = self.get(screen_url).unwrap_or_default();
+        let mut transform = match &self.editing {
+            Some((url, editing)) if url == screen_url => *editing,
+            _ => stored,
+        };
+        let mut dragging = false;
+        ui.horizontal(|ui| {
+            if ui.button("⟲").on_hover_text("Rotate left").clicked() {
+                transform.rotate_counter_clockwise();
+            }
+            if ui.button("⟳").on_hover_text("Rotate right").clicked() {
+                transform.rotate_clockwise();
+            }
+            ui.toggle_value(&mut transform.flip_horizontal, "⬌ Flip");
+            ui.toggle_value(&mut transform.flip_vertical, "⬍ Flip");
+            if ui
+                .add_enabled(!transform.is_identity(), egui::Button::new("Reset"))
+                .clicked()
+            {
+                transform = DisplayTransform::default();
+            }
+        });
+        ui.label("Crop");
+        let labels = ["Left", "Top", "Right", "Bottom"];
+        for (edge, label) in transform.crop.iter_mut().zip(labels) {
+            dragging |= ui
+                .add(
+                    egui::Slider::new(edge, 0f32..=0.45)
+                        .text(label)
+                        .custom_formatter(|value, _| format!("{:.0}%", value * 100.)),
+                )
+                .dragged();
+        }
+        dragging |= ui
+            .add(egui::Slider::new(&mut transform.brightness, -100..=100).text("Brightness"))
+            .dragged();
+        dragging |= ui
+            .add(egui::Slider::new(&mut transform.contrast, -100..=100).text("Contrast"))
+            .dragged();
+
+        if dragging {
+            self.editing = Some((screen_url.to_owned(), transform));
+            return;
+        }
+        self.editing = None;
+        if transform != stored {
+            if transform.is_identity() {
+                self.transforms.remove(screen_url);
+            } else {
+                self.transforms.insert(screen_url.to_owned(), transform);
+            }
+            if let Err(e) = db_write.set_display_transform(screen_url, transform) {
+                error!("Failed to save display settings: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_to_screen() {
+        let rect = Rect::from_min_size(pos2(100., 100.), Vec2::new(200., 100.));
+        let turned = DisplayTransform {
+            quarter_turns: 1,
+            ..DisplayTransform::default()
+        };
+        // Note: a clockwise turn takes the top left corner to the top right.
+        assert_eq!(
+            image_to_screen(&turned, rect, pos2(0., 0.)),
+            pos2(300., 100.)
+        );
+        let cropped = DisplayTransform {
+            crop: [0.5, 0., 0., 0.],
+            ..DisplayTransform::default()
+        };
+        assert_eq!(
+            image_to_screen(&cropped, rect, pos2(0.75, 1.)),
+            pos2(200., 200.)
+        );
+    }
+
+    #[test]
+    fn test_screen_to_image_round_trip() {
+        let rect = Rect::from_min_size(pos2(10., 20.), Vec2::new(300., 400.));
+        let uv = pos2(0.6, 0.3);
+        for quarter_turns in 0..4 {
+            for (flip_horizontal, flip_vertical) in [(false, false), (true, false), (true, true)] {
+                let transform = DisplayTransform {
+                    quarter_turns,
+                    flip_horizontal,
+                    flip_vertical,
+                    crop: [0.1, 0.2, 0.05, 0.],
+                    ..DisplayTransform::default()
+                };
+                let back = screen_to_image(&transform, rect, image_to_screen(&transform, rect, uv));
+                assert!((back - uv).length() < 1e-4, "{transform:?}: {back:?}");
+            }
+        }
+    }
+}
@@ -1,23 +1,63 @@
 use crate::db::writer::DbWriteHandle;
 use crate::{
-    db::reader::DbReadHandle,
+    db::{
+        models::{
+            collection::DbCollection,
+            tag::{DbTag, TagId},
+            work::DbWork,
+        },
+        reader::DbReadHandle,
+    },
+    http::server::HttpServer,
     plugin::host::PluginHost,
-    shared::{performance::PerfTrack, progress::UpdateSource, update::DataUpdate},
+    shared::{
+        deep_link::{self, DeepLink, DeepLinkInbox},
+        diagnostics::export_diagnostics,
+        http_fixtures::FixtureMode,
+        instance_lock::is_read_only,
+        performance::PerfTrack,
+        profile::{is_valid_profile_name, launch_profile, list_profiles, profile},
+        progress::UpdateSource,
+        storage::{Storage, StorageRoot},
+        update::DataUpdate,
+    },
     ux::{
+        co_tags::UxCoTags,
+        collections::UxCollections,
         db::UxDb,
+        detection::{UxDetection, UxTagSuggestions},
+        enrichment::UxEnrichment,
+        exhibition::UxExhibitions,
+        first_run::UxFirstRun,
+        health::UxHealth,
+        image_info::format_size,
+        inbox::UxInbox,
+        manifest::UxManifest,
+        notes::UxNotes,
+        notify::{NotifyTarget, UxNotifications},
         plugin::UxPlugin,
+        storage::UxStorage,
+        sync::UxSync,
         tag::UxTag,
+        tag_push::UxTagPush,
         theme::Theme,
+        thumbnails::UxThumbnails,
+        trash::UxTrash,
         tutorial::{Tutorial, TutorialStep},
+        vocabulary::UxVocabulary,
         work::UxWork,
     },
 };
 use anyhow::Result;
 use egui::{self, Key, Modifiers};
 use egui_dock::{DockArea, DockState, NodeIndex, Style, TabViewer};
-use log::log;
+use log::{error, log};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, path::Path, time::Instant};
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TabMetadata {
@@ -85,17 +125,51 @@ pub struct UxState {
     mode: UxMode,
     show_preferences: bool,
     show_performance: bool,
+    show_health: bool,
+    #[serde(skip)]
+    show_hydrus_import: bool,
+    show_notifications: bool,
     show_about: bool,
     tutorial_step: TutorialStep,
+    #[serde(default = "UxFirstRun::finished")]
+    first_run: UxFirstRun,
+    #[serde(skip)]
+    export_diagnostics: bool,
 
     // Preferences
     theme: Theme,
+    sync_ux: UxSync,
+    #[serde(skip)]
+    storage_ux: UxStorage,
+    health_ux: UxHealth,
+    notifications: UxNotifications,
+    #[serde(skip)]
+    thumbnails_ux: UxThumbnails,
+    detection_ux: UxDetection,
+    enrichment_ux: UxEnrichment,
+    vocabulary_ux: UxVocabulary,
 
     // Sub-UX
     db_ux: UxDb,
     plugin_ux: UxPlugin,
     tag_ux: UxTag,
     work_ux: UxWork,
+    #[serde(skip)]
+    inbox_ux: UxInbox,
+    co_tags_ux: UxCoTags,
+    #[serde(skip)]
+    collections_ux: UxCollections,
+    #[serde(skip)]
+    exhibitions_ux: UxExhibitions,
+    #[serde(skip)]
+    notes_ux: UxNotes,
+    #[serde(skip)]
+    tag_suggestions_ux: UxTagSuggestions,
+    #[serde(skip)]
+    tag_push_ux: UxTagPush,
+    #[serde(skip)]
+    manifest_ux: UxManifest,
+    trash_ux: UxTrash,
 
     #[serde(skip)]
     perf: PerfTrack,
@@ -107,6 +181,7 @@ impl UxState {
     fn show_tags_list(
         &mut self,
         host: &mut PluginHost,
+        db_read: &DbReadHandle,
         db_write: &DbWriteHandle,
         ui: &mut egui::Ui,
     ) {
@@ -114,6 +189,7 @@ impl UxState {
             self.work_ux.tag_selection_mut(),
             host,
             Tutorial::new(&mut self.tutorial_step, &self.theme, ui.style().clone()),
+            db_read,
             db_write,
             ui,
         );
@@ -123,7 +199,6 @@ impl UxState {
 struct SyncViewer<'a> {
     sync: &'a mut PluginHost,
     state: &'a mut UxState,
-    #[expect(unused)]
     db_read: &'a DbReadHandle,
     db_write: &'a DbWriteHandle,
 }
@@ -155,13 +230,16 @@ impl<'a> SyncViewer<'a> {
         );
     }
 
-    fn show_database(&self, ui: &mut egui::Ui) {
-        self.state.db_ux.ui(ui);
+    fn show_database(&mut self, ui: &mut egui::Ui) {
+        self.state.thumbnails_ux.ui(self.db_read, ui);
+        ui.separator();
+        self.state.db_ux.ui(self.db_write, ui);
     }
 
     fn show_tags(&mut self, ui: &mut egui::Ui) {
         let start = Instant::now();
-        self.state.show_tags_list(self.sync, self.db_write, ui);
+        self.state
+            .show_tags_list(self.sync, self.db_read, self.db_write, ui);
         self.state.perf.sample("Show Tags", start.elapsed());
     }
 
@@ -175,6 +253,7 @@ impl<'a> SyncViewer<'a> {
                 ui.style().clone(),
             ),
             self.db_write,
+            self.sync,
             &mut self.state.perf,
             ui,
         );
@@ -189,18 +268,86 @@ impl<'a> SyncViewer<'a> {
                 &self.state.theme,
                 ui.style().clone(),
             ),
+            self.db_read,
             self.db_write,
             self.sync,
             ui,
         );
     }
 
+    fn show_inbox(&mut self, ui: &mut egui::Ui) {
+        self.state.inbox_ux.ui(self.db_read, self.db_write, ui);
+    }
+
+    fn show_related_tags(&mut self, ui: &mut egui::Ui) {
+        self.state.co_tags_ux.ui(
+            self.state.tag_ux.tags(),
+            self.state.work_ux.tag_selection_mut(),
+            self.db_read,
+            ui,
+        );
+    }
+
+    fn show_collections(&mut self, ui: &mut egui::Ui) {
+        self.state.collections_ux.ui(
+            self.state.tag_ux.tags(),
+            self.state.work_ux.tag_selection_mut(),
+            self.db_read,
+            ui,
+        );
+    }
+
+    fn show_exhibitions(&mut self, ui: &mut egui::Ui) {
+        let present =
+            self.state
+                .exhibitions_ux
+                .ui(self.state.work_ux.get_selected_work(), self.db_write, ui);
+        if present {
+            self.state.mode = UxMode::Exhibition;
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+        }
+    }
+
+    fn show_notes(&mut self, ui: &mut egui::Ui) {
+        self.state
+            .notes_ux
+            .ui(&mut self.state.work_ux, self.db_read, ui);
+    }
+
+    fn show_queue(&mut self, ui: &mut egui::Ui) {
+        if self.state.work_ux.queue_ui(ui) {
+            self.state.work_ux.set_peeking(false);
+            self.state.mode = UxMode::Slideshow;
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+        }
+    }
+
+    fn show_trash(&mut self, ui: &mut egui::Ui) {
+        self.state.trash_ux.ui(self.db_write, ui);
+    }
+
+    fn show_tag_suggestions(&mut self, ui: &mut egui::Ui) {
+        self.state
+            .tag_suggestions_ux
+            .ui(self.db_read, self.db_write, ui);
+    }
+
     fn render_slideshow(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        // Bail back to the browser if we lose our selection.
+        // Bail back to the browser if we lose our selection, unless the queue is moving on.
         if !self.state.work_ux.has_selection() {
-            self.state.mode = UxMode::Browser;
+            if self.state.work_ux.is_awaiting_queue() {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.centered_and_justified(|ui| ui.spinner());
+                });
+            } else {
+                self.state.mode = UxMode::Browser;
+            }
             return;
         }
+        // Note: the collections tell the slideshow which works are pages of the same volume.
+        self.state.collections_ux.collections(self.db_read);
         self.state.work_ux.slideshow_ui(
             self.state.tag_ux.tags(),
             Tutorial::new(
@@ -208,7 +355,8 @@ impl<'a> SyncViewer<'a> {
                 &self.state.theme,
                 ctx.style().clone(),
             ),
-            self.db_write,
+            self.sync,
+            (self.db_read, self.db_write),
             ctx,
             frame,
         );
@@ -229,6 +377,14 @@ impl TabViewer for SyncViewer<'_> {
             "Tags" => self.show_tags(ui),
             "Works" => self.show_works(ui),
             "Work Info" => self.show_info(ui),
+            "Inbox" => self.show_inbox(ui),
+            "Related Tags" => self.show_related_tags(ui),
+            "Collections" => self.show_collections(ui),
+            "Exhibitions" => self.show_exhibitions(ui),
+            "Notes" => self.show_notes(ui),
+            "Queue" => self.show_queue(ui),
+            "Trash" => self.show_trash(ui),
+            "Tag Suggestions" => self.show_tag_suggestions(ui),
             "Artists" => {
                 // TODO: implement artists too!
                 ui.label("TODO");
@@ -247,6 +403,7 @@ pub enum UxMode {
     #[default]
     Browser,
     Slideshow,
+    Exhibition,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -254,11 +411,33 @@ pub struct UxToplevel {
     dock_state: DockState<TabMetadata>,
     state: UxState,
     errors: Vec<String>,
+
+    // Kept for diagnostics bundles; plugins keep their own logs.
+    #[serde(skip)]
+    data_dir: PathBuf,
+    #[serde(skip)]
+    recent_logs: VecDeque<String>,
+
+    // Set when the content filters need resolving against the tags again for the web gallery.
+    #[serde(skip, default = "UxToplevel::stale")]
+    public_view_stale: bool,
+
+    // Links from other programs, and one that is waiting on the tags or collections to load.
+    #[serde(skip)]
+    links: DeepLinkInbox,
+    #[serde(skip)]
+    pending_link: Option<DeepLink>,
+
+    #[serde(skip)]
+    new_profile: String,
+    #[serde(skip)]
+    hydrus_path: String,
 }
 
 impl Default for UxToplevel {
     fn default() -> Self {
-        let mut dock_state = DockState::new(vec![TabMetadata::new("Works")]);
+        let mut dock_state =
+            DockState::new(vec![TabMetadata::new("Works"), TabMetadata::new("Inbox")]);
         let surface = dock_state.main_surface_mut();
         let [right_node, galleries_node] = surface.split_left(
             NodeIndex::root(),
@@ -276,36 +455,124 @@ impl Default for UxToplevel {
             dock_state,
             state: UxState::default(),
             errors: Vec::new(),
+            data_dir: PathBuf::new(),
+            recent_logs: VecDeque::new(),
+            public_view_stale: true,
+            links: DeepLinkInbox::default(),
+            pending_link: None,
+            new_profile: String::new(),
+            hydrus_path: String::new(),
         }
     }
 }
 
 impl UxToplevel {
+    const MAX_RECENT_LOGS: usize = 500;
+
+    fn stale() -> bool {
+        true
+    }
+
     pub fn startup(
         &mut self,
         ctx: &egui::Context,
-        data_dir: &Path,
+        storage: &Storage,
         db: &DbReadHandle,
+        db_write: &DbWriteHandle,
         cc: &eframe::CreationContext<'_>,
     ) {
+        self.data_dir = storage.data_dir();
+        if !is_read_only() {
+            let ctx = ctx.clone();
+            self.links = DeepLinkInbox::new(&self.data_dir, move || ctx.request_repaint());
+        }
         self.state.theme.apply(ctx);
         self.state.tag_ux.startup(db);
+        self.state.sync_ux.startup(db_write);
+        self.state.storage_ux.startup(storage);
+        if storage
+            .config()
+            .roots()
+            .iter()
+            .any(StorageRoot::is_encrypted)
+            && let Err(e) = db_write.seal_clear_thumbnails()
+        {
+            error!("Failed to queue sealing thumbnails: {e}");
+        }
+        self.state.inbox_ux.startup(storage, db);
+        self.state.exhibitions_ux.startup(storage, db);
+        self.state.detection_ux.startup(ctx, storage, db);
+        self.state.enrichment_ux.startup(ctx, db);
+        self.state.vocabulary_ux.startup(ctx, db);
+        self.state.tag_suggestions_ux.startup(storage);
         self.state
             .work_ux
-            .startup(data_dir, db, cc)
+            .startup(storage, db, cc)
             .expect("Failed to load works ui");
+        // Note: after the works UX, which loads the NSFW classifier that thumbnailing uses.
+        self.state.thumbnails_ux.startup(ctx, storage, db);
     }
 
     pub fn handle_updates(&mut self, updates: &[DataUpdate], db: &DbReadHandle) {
         // self.state.plugin_ux.handle_updates(updates);
         self.state.db_ux.handle_updates(updates);
+        self.state.sync_ux.handle_updates(updates);
+        self.state.storage_ux.handle_updates(updates);
+        self.state.health_ux.handle_updates(updates);
+        self.state
+            .notifications
+            .handle_updates(self.state.tag_ux.tags(), updates);
         self.state.tag_ux.handle_updates(db, updates);
+        self.state.inbox_ux.handle_updates(db, updates);
+        self.state.co_tags_ux.handle_updates(updates);
+        self.state.collections_ux.handle_updates(updates);
+        self.state.exhibitions_ux.handle_updates(updates);
+        self.state.notes_ux.handle_updates(updates);
+        self.state.thumbnails_ux.handle_updates(updates);
+        self.state.detection_ux.handle_updates(db, updates);
+        self.state.enrichment_ux.handle_updates(updates);
+        self.state.vocabulary_ux.handle_updates(updates);
+        self.state.tag_suggestions_ux.handle_updates(updates);
+        self.state.tag_push_ux.handle_updates(updates);
+        self.state.trash_ux.handle_updates(updates);
         self.state
             .work_ux
             .handle_updates(self.state.tag_ux.tags(), db, updates);
+        if updates
+            .iter()
+            .any(|update| matches!(update, DataUpdate::ListWorksChunk { finished: true, .. }))
+        {
+            self.state
+                .thumbnails_ux
+                .prioritize(&self.state.work_ux.works_without_thumbs());
+        }
 
         // Note: we need this to live above the dock impl for clarity, so do it here.
         for update in updates {
+            if matches!(update, DataUpdate::InitialTags(_)) {
+                self.public_view_stale = true;
+            }
+            if let DataUpdate::Log {
+                source,
+                level,
+                message,
+            } = update
+                && !matches!(source, UpdateSource::Plugin(_))
+            {
+                self.recent_logs
+                    .push_back(format!("{level:<5} {source:?}: {message}"));
+                while self.recent_logs.len() > Self::MAX_RECENT_LOGS {
+                    self.recent_logs.pop_front();
+                }
+            }
+            if let DataUpdate::LinkedWork { screen_url, work } = update {
+                match work {
+                    Some(work) => self.show_linked_work(work),
+                    None => self.errors.push(format!(
+                        "The link is to {screen_url}, which is not in the archive"
+                    )),
+                }
+            }
             if let DataUpdate::Log {
                 source: UpdateSource::Unknown,
                 level,
@@ -323,14 +590,44 @@ impl UxToplevel {
         db: &DbReadHandle,
         db_write: &DbWriteHandle,
         host: &mut PluginHost,
+        http: &mut HttpServer,
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
     ) -> Result<()> {
         let frame_start = Instant::now();
+        self.open_links(db, ctx);
+        if let Some(screen_url) = self.state.work_ux.poll_queue() {
+            db.get_linked_work(&screen_url);
+        }
+        self.state.storage_ux.tick(db_write);
+        self.state.trash_ux.tick(db_write);
+        self.state.health_ux.tick(db_write, host);
+        self.state.tag_ux.tick(host);
+        self.state.thumbnails_ux.tick(db_write);
+        self.state.detection_ux.tick(db, db_write);
+        self.state.enrichment_ux.tick(db, db_write);
+        self.state.vocabulary_ux.tick(db, db_write);
+        self.state.db_ux.tick(db_write, ctx);
+        db_write.tick()?;
+        if db_write.has_pending_work_flags() {
+            ctx.request_repaint_after(DbWriteHandle::FLAG_FLUSH_DELAY);
+        }
+        self.state.work_ux.tick(ctx);
+        if self.public_view_stale
+            && let Some(tags) = self.state.tag_ux.tags()
+        {
+            http.set_public_view(self.state.work_ux.public_view(tags));
+            self.public_view_stale = false;
+        }
+        self.state.work_ux.poll_variants(db_write);
 
         match self.state.mode {
             UxMode::Browser => {
                 self.render_menu(ctx);
+                if self.state.export_diagnostics {
+                    self.state.export_diagnostics = false;
+                    self.export_diagnostics(host, http);
+                }
                 egui::CentralPanel::default()
                     .frame(egui::Frame::central_panel(&ctx.style()).inner_margin(0.))
                     .show(ctx, |ui| {
@@ -358,23 +655,44 @@ impl UxToplevel {
                     });
 
                 // Show any windows that are open
-                self.render_tutorial(ctx);
-                self.render_preferences(ctx);
+                // Note: the tutorial picks up once setup is done.
+                if self.state.first_run.is_active() {
+                    self.render_first_run(host, ctx);
+                } else {
+                    self.render_tutorial(ctx);
+                }
+                self.render_preferences((db, db_write), host, http, ctx);
+                self.state.sync_ux.conflicts_ui(ctx);
                 self.render_performance(ctx);
+                self.render_health(db_write, host, ctx);
+                self.render_hydrus_import(db_write, ctx);
+                self.state.manifest_ux.window(db_write, ctx);
+                self.state.tag_push_ux.window(db, host, ctx);
+                self.render_notifications(ctx);
+                Self::render_refresh_confirmation(host, ctx);
+                Self::render_quota_prompt(host, ctx);
                 self.render_about(ctx);
+                Self::handle_dropped_files(db_write, ctx);
             }
             UxMode::Slideshow => {
                 SyncViewer::wrap(host, &mut self.state, db, db_write).render_slideshow(ctx, frame);
             }
+            UxMode::Exhibition => {
+                // Note: as with the slideshow, bail back to the browser if there is nothing to show.
+                if !self.state.exhibitions_ux.present_ui(ctx) {
+                    self.state.mode = UxMode::Browser;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+                }
+            }
         }
 
-        self.handle_shortcuts(ctx);
+        self.handle_shortcuts(db_write, ctx);
 
         self.state.perf.sample("Total", frame_start.elapsed());
         Ok(())
     }
 
-    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+    fn handle_shortcuts(&mut self, db_write: &DbWriteHandle, ctx: &egui::Context) {
         let mut focus = None;
         ctx.memory(|mem| focus = mem.focused());
 
@@ -412,17 +730,25 @@ impl UxToplevel {
         // Each of the modes interprets keys a bit differently, out of necessity.
         match self.state.mode {
             UxMode::Browser => {
-                if pressed.contains(&Key::F11) || pressed.contains(&Key::Space) {
+                if pressed.contains(&Key::F11) {
                     if self.state.work_ux.has_selection() {
+                        self.state.work_ux.set_peeking(false);
                         self.state.mode = UxMode::Slideshow;
                         if self.state.tutorial_step == TutorialStep::WorkInfo {
                             self.state.tutorial_step = self.state.tutorial_step.next();
                         }
                         ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
                     }
+                } else if pressed.contains(&Key::Space) && focus.is_none() {
+                    let peeking = self.state.work_ux.is_peeking();
+                    self.state.work_ux.set_peeking(!peeking);
                 } else if pressed.contains(&Key::Escape) {
-                    if self.state.show_about {
+                    if self.state.work_ux.is_peeking() {
+                        self.state.work_ux.set_peeking(false);
+                    } else if self.state.show_about {
                         self.state.show_about = false;
+                    } else if self.state.show_notifications {
+                        self.state.show_notifications = false;
                     } else if self.state.show_performance {
                         self.state.show_performance = false;
                     } else if self.state.show_preferences {
@@ -435,11 +761,16 @@ impl UxToplevel {
                 }
             }
             UxMode::Slideshow => {
+                // Note: a space typed into an annotation's label is not a request to leave.
                 if pressed.contains(&Key::Escape)
                     || pressed.contains(&Key::F11)
-                    || pressed.contains(&Key::Space)
+                    || (pressed.contains(&Key::Space) && focus.is_none())
                 {
                     self.state.work_ux.on_leave_slideshow();
+                    // Note: triage mostly happens in the slideshow, so save it on the way out.
+                    if let Err(e) = db_write.flush_work_flags() {
+                        error!("Failed to write favorites and hidden works: {e}");
+                    }
                     self.state.mode = UxMode::Browser;
                     if self.state.tutorial_step == TutorialStep::WorksSlideshow {
                         self.state.tutorial_step = self.state.tutorial_step.next();
@@ -447,6 +778,12 @@ impl UxToplevel {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
                 }
             }
+            UxMode::Exhibition => {
+                if pressed.contains(&Key::Escape) || pressed.contains(&Key::F11) {
+                    self.state.mode = UxMode::Browser;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+                }
+            }
         }
     }
 
@@ -454,6 +791,21 @@ impl UxToplevel {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    ui.menu_button("Profile", |ui| self.profile_menu(ctx, ui));
+                    if ui
+                        .add_enabled(!is_read_only(), egui::Button::new("Import from Hydrus..."))
+                        .clicked()
+                    {
+                        self.state.show_hydrus_import = true;
+                    }
+                    if ui
+                        .add_enabled(!is_read_only(), egui::Button::new("Import Manifest..."))
+                        .on_hover_text("Import works listed in a CSV or JSON file")
+                        .clicked()
+                    {
+                        self.state.manifest_ux.open();
+                    }
+                    ui.separator();
                     if ui.button("Quit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
@@ -462,10 +814,33 @@ impl UxToplevel {
                     if ui.button("Preferences...").clicked() {
                         self.state.show_preferences = true;
                     }
+                    if ui
+                        .button("Sync Tag Edits Upstream...")
+                        .on_hover_text(
+                            "Review the tags you have edited, then send them to the works' sources",
+                        )
+                        .clicked()
+                    {
+                        self.state.tag_push_ux.open();
+                    }
                 });
                 ui.menu_button("View", |ui| {
-                    const TABS: [&str; 6] =
-                        ["Plugins", "Tags", "Works", "Work Info", "Artists", "Data"];
+                    const TABS: [&str; 14] = [
+                        "Plugins",
+                        "Tags",
+                        "Works",
+                        "Work Info",
+                        "Inbox",
+                        "Related Tags",
+                        "Collections",
+                        "Exhibitions",
+                        "Notes",
+                        "Queue",
+                        "Tag Suggestions",
+                        "Trash",
+                        "Artists",
+                        "Data",
+                    ];
                     let mut have_section = false;
                     for name in &TABS {
                         let closed = self
@@ -474,7 +849,7 @@ impl UxToplevel {
                             .is_none();
                         if closed {
                             have_section = true;
-                            if ui.button("Plugins").clicked() {
+                            if ui.button(*name).clicked() {
                                 self.dock_state.push_to_focused_leaf(TabMetadata::new(name));
                             }
                         }
@@ -485,22 +860,152 @@ impl UxToplevel {
                     if ui.button("Performance Monitor...").clicked() {
                         self.state.show_performance = true;
                     }
+                    let corrupt = self.state.health_ux.corrupt_count();
+                    let health = if corrupt > 0 {
+                        format!("Health ({corrupt} corrupt)...")
+                    } else {
+                        "Health...".to_owned()
+                    };
+                    if ui.button(health).clicked() {
+                        self.state.show_health = true;
+                    }
                 });
                 ui.menu_button("Help", |ui| {
+                    if !self.state.first_run.is_active() && ui.button("Set Up Again...").clicked() {
+                        self.state.first_run = UxFirstRun::default();
+                    }
                     if self.state.tutorial_step != TutorialStep::Beginning
                         && ui.button("Restart Tutorial...").clicked()
                     {
                         self.state.tutorial_step = TutorialStep::Beginning;
                     }
                     ui.separator();
+                    if ui.button("Export Diagnostics...").clicked() {
+                        self.state.export_diagnostics = true;
+                    }
+                    if ui
+                        .button("Open artchiver:// Links Here")
+                        .on_hover_text(
+                            "Have links to works, tags, and collections, e.g. in your notes, \
+                             open in this archive",
+                        )
+                        .clicked()
+                    {
+                        match deep_link::register_scheme() {
+                            Ok(()) => self
+                                .errors
+                                .push("artchiver:// links will now open here.".to_owned()),
+                            Err(e) => self
+                                .errors
+                                .push(format!("Failed to register artchiver:// links: {e:#}")),
+                        }
+                    }
+                    ui.separator();
                     if ui.button("About...").clicked() {
                         self.state.show_about = true;
                     }
                 });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let unread = self.state.notifications.unread_count();
+                    let bell = if unread > 0 {
+                        format!("🔔 {unread}")
+                    } else {
+                        "🔔".to_owned()
+                    };
+                    if ui.button(bell).on_hover_text("Notifications").clicked() {
+                        self.state.show_notifications = !self.state.show_notifications;
+                    }
+                    if is_read_only() {
+                        ui.colored_label(ui.visuals().warn_fg_color, "🔒 Read-only")
+                            .on_hover_text(
+                                "Another instance has this archive open, so plugins, downloads, \
+                                 and changes are disabled here.",
+                            );
+                    }
+                });
             });
         });
     }
 
+    fn export_diagnostics(&mut self, host: &PluginHost, http: &HttpServer) {
+        let logs = self.recent_logs.iter().cloned().collect::<Vec<_>>();
+        match export_diagnostics(&self.data_dir, host, http, &logs) {
+            Ok(dir) => {
+                self.errors.push(format!(
+                    "Diagnostics written to {}; please attach this folder to your bug report.",
+                    dir.display()
+                ));
+                if let Err(e) = open::that(&dir) {
+                    error!("Failed to open {}: {e}", dir.display());
+                }
+            }
+            Err(e) => self
+                .errors
+                .push(format!("Failed to export diagnostics: {e}")),
+        }
+    }
+
+    // Each profile is its own archive, so switching starts another Artchiver on it and closes us.
+    fn profile_menu(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let prefix = match std::env::current_dir() {
+            Ok(prefix) => prefix,
+            Err(e) => {
+                ui.label(format!("Can't find the profiles: {e}"));
+                return;
+            }
+        };
+        let current = profile();
+        let mut switch_to = None;
+        if ui.selectable_label(current.is_none(), "Default").clicked() && current.is_some() {
+            switch_to = Some(None);
+        }
+        let profiles = list_profiles(&prefix);
+        for name in &profiles {
+            let is_current = current == Some(name.as_str());
+            if ui.selectable_label(is_current, name).clicked() && !is_current {
+                switch_to = Some(Some(name.clone()));
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile)
+                    .hint_text("new profile")
+                    .desired_width(120.0),
+            );
+            let name = self.new_profile.trim();
+            let valid = is_valid_profile_name(name) && !profiles.iter().any(|p| p == name);
+            if ui
+                .add_enabled(valid, egui::Button::new("Create"))
+                .on_hover_text("Letters, numbers, - and _")
+                .clicked()
+            {
+                switch_to = Some(Some(name.to_owned()));
+            }
+        });
+        if let Some(name) = switch_to {
+            match launch_profile(&prefix, name.as_deref()) {
+                Ok(()) => {
+                    self.new_profile.clear();
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                Err(e) => self
+                    .errors
+                    .push(format!("Failed to switch profiles: {e:#}")),
+            }
+        }
+    }
+
+    fn render_first_run(&mut self, host: &mut PluginHost, ctx: &egui::Context) {
+        let tags = self.state.tag_ux.tags();
+        if let Some(tag) = self.state.first_run.ui(&self.data_dir, host, tags, ctx) {
+            let selection = self.state.work_ux.tag_selection_mut();
+            selection.clear();
+            selection.enable(&tag);
+            self.focus_tab("Works");
+        }
+    }
+
     fn render_tutorial(&mut self, ctx: &egui::Context) {
         if self.state.tutorial_step == TutorialStep::Beginning {
             egui::Window::new("Welcome to Artchiver")
@@ -551,11 +1056,130 @@ impl UxToplevel {
         }
     }
 
-    fn render_preferences(&mut self, ctx: &egui::Context) {
+    fn render_preferences(
+        &mut self,
+        (db, db_write): (&DbReadHandle, &DbWriteHandle),
+        host: &mut PluginHost,
+        http: &mut HttpServer,
+        ctx: &egui::Context,
+    ) {
         egui::Window::new("Preferences")
             .open(&mut self.state.show_preferences)
             .show(ctx, |ui| {
                 self.state.theme.ui(ui);
+                ui.separator();
+                ui.heading("Downloads");
+                ui.label("Limits apply to all plugins, unless overridden in the plugin's details.");
+                if host.download_limits_mut().ui("global", ui) {
+                    host.apply_download_limits();
+                }
+                ui.label(
+                    "Plugins that scrape websites follow each site's robots.txt, and no site is \
+                     fetched from more often than the per-site delay.",
+                );
+                if host.compliance_policy_mut().ui(ui) {
+                    host.apply_compliance_policy();
+                }
+                if ui
+                    .checkbox(
+                        host.record_warc_mut(),
+                        "Record every fetched response to WARC files in the data directory",
+                    )
+                    .changed()
+                {
+                    host.apply_warc_recording();
+                }
+                if ui
+                    .checkbox(
+                        &mut host.import_settings_mut().keep_sources,
+                        "Keep the raw record each work was built from (compressed, in the database)",
+                    )
+                    .changed()
+                {
+                    host.apply_import_settings();
+                }
+                let chunk_size = ui
+                    .horizontal(|ui| {
+                        ui.label("Write imported works in batches of");
+                        ui.add(
+                            egui::DragValue::new(&mut host.import_settings_mut().chunk_size)
+                                .range(100..=50_000)
+                                .speed(100),
+                        )
+                        .on_hover_text(
+                            "Larger batches import faster, but keep the database busy for longer",
+                        )
+                    })
+                    .inner;
+                if chunk_size.changed() {
+                    host.apply_import_settings();
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Plugin HTTP fixtures:");
+                    let mut changed = false;
+                    for mode in FixtureMode::ALL {
+                        changed |= ui
+                            .radio_value(host.http_fixture_mode_mut(), mode, mode.name())
+                            .changed();
+                    }
+                    if changed {
+                        host.apply_http_fixture_mode();
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Record saves each job's plugin fetches under http-fixtures in the data \
+                     directory; Replay serves them back instead of going to the network.",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Plugins working at once:");
+                    ui.add(egui::DragValue::new(host.max_concurrent_plugins_mut()).range(1..=16))
+                        .on_hover_text(
+                            "Plugins past this wait their turn; each keeps its own rate limit",
+                        );
+                });
+                ui.checkbox(
+                    host.metadata_only_mut(),
+                    "Only record works when refreshing tags; download them on demand",
+                );
+                ui.separator();
+                self.state.storage_ux.ui(db_write, ui);
+                ui.separator();
+                ui.heading("Slideshow");
+                self.state.work_ux.slideshow_preferences_ui(ui);
+                self.state.work_ux.image_cache_preferences_ui(ui);
+                self.state.work_ux.color_preferences_ui(ui);
+                ui.separator();
+                ui.heading("Transcripts");
+                self.state.work_ux.transcript_preferences_ui(ui);
+                ui.separator();
+                ui.heading("External Editors");
+                self.state.work_ux.external_editors_preferences_ui(ui);
+                ui.separator();
+                ui.heading("Content Filters");
+                self.public_view_stale |= self
+                    .state
+                    .work_ux
+                    .filter_preferences_ui(self.state.tag_ux.tags(), ui);
+                ui.separator();
+                ui.heading("Notifications");
+                self.state.notifications.preferences_ui(ui);
+                ui.separator();
+                ui.heading("Watched Tags");
+                self.state.tag_ux.watch_preferences_ui(ui);
+                ui.separator();
+                ui.heading("Face and Figure Detection");
+                self.state.detection_ux.preferences_ui(db, ui);
+                ui.separator();
+                ui.heading("Wikidata Enrichment");
+                self.state.enrichment_ux.preferences_ui(db, db_write, ui);
+                ui.separator();
+                ui.heading("Getty Vocabularies");
+                self.state.vocabulary_ux.preferences_ui(db, ui);
+                ui.separator();
+                self.state.sync_ux.ui(db_write, ui);
+                ui.separator();
+                http.ui(ui);
             });
     }
 
@@ -563,10 +1187,319 @@ impl UxToplevel {
         egui::Window::new("Performance")
             .open(&mut self.state.show_performance)
             .show(ctx, |ui| {
+                self.state.work_ux.image_cache_usage_ui(ui);
+                ui.separator();
                 self.state.perf.show(ui);
             });
     }
 
+    fn render_health(
+        &mut self,
+        db_write: &DbWriteHandle,
+        host: &mut PluginHost,
+        ctx: &egui::Context,
+    ) {
+        egui::Window::new("Health")
+            .open(&mut self.state.show_health)
+            .show(ctx, |ui| {
+                self.state.health_ux.ui(db_write, host, ui);
+            });
+    }
+
+    fn render_hydrus_import(&mut self, db_write: &DbWriteHandle, ctx: &egui::Context) {
+        let mut import = false;
+        egui::Window::new("Import from Hydrus")
+            .open(&mut self.state.show_hydrus_import)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("Copies the files from Hydrus into the archive, with their tags, ratings, and urls. Files that are already here get the tags from Hydrus added.");
+                ui.label("");
+                ui.label("Point this at Hydrus' db folder, the one with client.db in it, with Hydrus closed; or at a folder of files exported from Hydrus with .txt sidecars.");
+                ui.horizontal(|ui| {
+                    ui.label("Folder");
+                    ui.text_edit_singleline(&mut self.hydrus_path);
+                });
+                let path = Path::new(self.hydrus_path.trim());
+                if ui
+                    .add_enabled(path.is_dir(), egui::Button::new("Import"))
+                    .clicked()
+                {
+                    import = true;
+                }
+            });
+        if import {
+            self.state.show_hydrus_import = false;
+            let path = PathBuf::from(self.hydrus_path.trim());
+            if let Err(e) = db_write.import_hydrus(path) {
+                self.errors.push(format!("Failed to start the import: {e}"));
+            }
+        }
+    }
+
+    // Files dropped on the window are copied into the archive under the Imported tag.
+    // Note: the windowing layer only tells us about dropped files, not dropped links or text.
+    fn handle_dropped_files(db_write: &DbWriteHandle, ctx: &egui::Context) {
+        let (hovering, dropped) = ctx.input(|input| {
+            (
+                !input.raw.hovered_files.is_empty(),
+                input
+                    .raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|file| file.path.clone())
+                    .collect::<Vec<_>>(),
+            )
+        });
+        if hovering {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("file_drop_target"),
+            ));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0., egui::Color32::from_black_alpha(192));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                if is_read_only() {
+                    "This archive is read-only here"
+                } else {
+                    "Drop to import"
+                },
+                egui::FontId::proportional(32.),
+                egui::Color32::WHITE,
+            );
+        }
+        if !dropped.is_empty()
+            && !is_read_only()
+            && let Err(e) = db_write.import_local_files(dropped)
+        {
+            error!("Failed to import dropped files: {e}");
+        }
+    }
+
+    fn render_notifications(&mut self, ctx: &egui::Context) {
+        let mut target = NotifyTarget::None;
+        egui::Window::new("Notifications")
+            .open(&mut self.state.show_notifications)
+            .show(ctx, |ui| {
+                target = self.state.notifications.ui(ui);
+            });
+        match target {
+            NotifyTarget::None => {}
+            NotifyTarget::Tag(name) => {
+                let tag = self
+                    .state
+                    .tag_ux
+                    .tags()
+                    .and_then(|tags| tags.values().find(|tag| tag.name() == name));
+                if let Some(tag) = tag {
+                    let selection = self.state.work_ux.tag_selection_mut();
+                    selection.clear();
+                    selection.enable(tag);
+                }
+                self.focus_tab("Works");
+            }
+            NotifyTarget::Plugin(_) => self.focus_tab("Plugins"),
+        }
+    }
+
+    fn render_refresh_confirmation(host: &mut PluginHost, ctx: &egui::Context) {
+        let Some(estimate) = host.pending_refresh_mut() else {
+            return;
+        };
+        let mut open = true;
+        let (mut confirmed, mut cancelled) = (false, false);
+        egui::Window::new("Refresh a Large Tag")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} has about {} works.",
+                    estimate.tag.name(),
+                    estimate.works
+                ));
+                let to_download = match estimate.max_works {
+                    Some(max_works) if !estimate.metadata_only => {
+                        estimate.works.min(max_works as u64)
+                    }
+                    _ => estimate.works,
+                };
+                match estimate.bytes {
+                    Some(bytes) if estimate.works > 0 => ui.label(format!(
+                        "Downloading {to_download} of them will take roughly {}, going by the \
+                         files we already have from the same plugins.",
+                        format_size(bytes / estimate.works * to_download)
+                    )),
+                    _ => ui.label(
+                        "We don't have any files from this tag's plugins yet, so can't say how \
+                         much space it will take.",
+                    ),
+                };
+                ui.checkbox(
+                    &mut estimate.metadata_only,
+                    "Metadata only: record the works, but don't download them",
+                );
+                ui.add_enabled_ui(!estimate.metadata_only, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut capped = estimate.max_works.is_some();
+                        if ui
+                            .checkbox(&mut capped, "Only download the first")
+                            .changed()
+                        {
+                            estimate.max_works = capped.then_some(500);
+                        }
+                        if let Some(max_works) = &mut estimate.max_works {
+                            ui.add(egui::DragValue::new(max_works).range(1..=100_000));
+                            ui.label("works");
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("Refresh").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        if confirmed {
+            if let Err(e) = host.confirm_pending_refresh() {
+                error!("Failed to queue the refresh: {e}");
+            }
+        } else if cancelled || !open {
+            host.cancel_pending_refresh();
+        }
+    }
+
+    fn render_quota_prompt(host: &mut PluginHost, ctx: &egui::Context) {
+        let Some(prompt) = host.pending_quota_mut() else {
+            return;
+        };
+        let mut open = true;
+        let (mut raised, mut purged, mut dismissed) = (false, false, false);
+        egui::Window::new("Storage Quota Reached")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{}'s works are using {} of the {} it is allowed, so its downloads are paused.",
+                    prompt.plugin,
+                    format_size(prompt.usage),
+                    format_size(prompt.quota)
+                ));
+                ui.horizontal(|ui| {
+                    raised = ui.button("Raise the quota to").clicked();
+                    ui.add(
+                        egui::DragValue::new(&mut prompt.raise_to_gib)
+                            .range(1..=100_000)
+                            .suffix(" GiB"),
+                    );
+                });
+                purged = ui
+                    .button("Purge works that aren't favorites")
+                    .on_hover_text(
+                        "Move their files to the trash; the works stay in the gallery, and can be downloaded again",
+                    )
+                    .clicked();
+                dismissed = ui.button("Leave it paused").clicked();
+            });
+        if raised {
+            host.raise_pending_quota();
+        } else if purged {
+            if let Err(e) = host.purge_for_pending_quota() {
+                error!("Failed to start the purge: {e}");
+            }
+        } else if dismissed || !open {
+            host.dismiss_pending_quota();
+        }
+    }
+
+    // Follow the artchiver:// links that other programs have sent us.
+    fn open_links(&mut self, db: &DbReadHandle, ctx: &egui::Context) {
+        // Note: if several arrive at once, the last is where the user wants to end up.
+        if let Some(link) = self.links.poll().pop() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            if self.state.mode == UxMode::Slideshow {
+                self.state.work_ux.on_leave_slideshow();
+            }
+            if self.state.mode != UxMode::Browser {
+                self.state.mode = UxMode::Browser;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+            }
+            self.pending_link = Some(link);
+        }
+        let Some(link) = self.pending_link.take() else {
+            return;
+        };
+        let found = match &link {
+            DeepLink::Work { screen_url } => {
+                db.get_linked_work(screen_url);
+                return;
+            }
+            DeepLink::Tag { name } => self
+                .state
+                .tag_ux
+                .tags()
+                .map(|tags| tags.values().find(|tag| tag.name() == name).map(DbTag::id)),
+            DeepLink::Collection { name } => {
+                self.state
+                    .collections_ux
+                    .collections(db)
+                    .map(|collections| {
+                        collections
+                            .iter()
+                            .find(|collection| collection.name() == name)
+                            .map(DbCollection::tag_id)
+                    })
+            }
+        };
+        match found {
+            Some(Some(tag_id)) => self.show_linked_tag(tag_id),
+            Some(None) => self
+                .errors
+                .push(format!("Nothing in the archive matches the link {link}")),
+            // Note: still loading; whatever we are waiting on will wake us up again.
+            None => self.pending_link = Some(link),
+        }
+    }
+
+    fn show_linked_tag(&mut self, tag_id: TagId) {
+        let Some(tag) = self.state.tag_ux.tags().and_then(|tags| tags.get(&tag_id)) else {
+            return;
+        };
+        let selection = self.state.work_ux.tag_selection_mut();
+        selection.clear();
+        selection.enable(tag);
+        self.focus_tab("Works");
+    }
+
+    fn show_linked_work(&mut self, work: &DbWork) {
+        self.focus_tab("Works");
+        if self.state.work_ux.select_screen_url(work.screen_url()) {
+            return;
+        }
+        // Note: show the work among the fewest others, so that it is easy to spot.
+        let Some(tag_id) = self.state.tag_ux.tags().and_then(|tags| {
+            work.tags()
+                .filter_map(|tag_id| tags.get(&tag_id))
+                .min_by_key(|tag| tag.local_count().unwrap_or(u64::MAX))
+                .map(DbTag::id)
+        }) else {
+            self.errors.push(format!(
+                "{} has no tags to find it by in the gallery",
+                work.name()
+            ));
+            return;
+        };
+        self.show_linked_tag(tag_id);
+        self.state.work_ux.select_when_loaded(work.screen_url());
+    }
+
+    fn focus_tab(&mut self, name: &str) {
+        match self.dock_state.find_tab_from(|tab| tab.title == name) {
+            Some(path) => self.dock_state.set_active_tab(path),
+            None => self.dock_state.push_to_focused_leaf(TabMetadata::new(name)),
+        }
+    }
+
     fn render_about(&mut self, ctx: &egui::Context) {
         egui::Window::new("About")
             .open(&mut self.state.show_about)
@@ -0,0 +1,445 @@
+// Reading PDF and DjVu works in the slideshow, a page at a time, with a column of page thumbnails
+// to jump around by. Where the document has a text layer, dragging over the page selects the
+// words under the drag, for copying out.
+use crate::shared::document::{Word, page_count, page_words, render_page};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use egui::{
+    Color32, ColorImage, Key, Modifiers, Pos2, Rect, Sense, TextureHandle, TextureOptions, Vec2,
+    load::SizedTexture,
+};
+use image::RgbaImage;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    thread,
+};
+
+// The long edge of the page thumbnails, in pixels, and the column they go in, in points.
+const THUMB_EDGE: u32 = 160;
+const THUMB_COLUMN: f32 = 112.;
+const THUMB_ROW: f32 = 152.;
+// How many pages to either side of the current one we keep drawn, for flipping back and forth.
+const KEEP_PAGES: usize = 2;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Job {
+    Count,
+    Page(usize, u32),
+    Thumb(usize),
+    Words(usize),
+}
+
+enum Done {
+    Count(Result<usize, String>),
+    Image(Result<ColorImage, String>),
+    Words(Result<Vec<Word>, String>),
+}
+
+type JobResult = (PathBuf, Job, Done);
+
+fn color_image(image: &RgbaImage) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    )
+}
+
+// The largest rect with the aspect of `size` that fits in `outer`, centered.
+fn fit_rect(outer: Rect, size: Vec2) -> Rect {
+    let scale = (outer.width() / size.x).min(outer.height() / size.y);
+    Rect::from_center_size(outer.center(), size * scale)
+}
+
+pub struct UxDocument {
+    path: Option<PathBuf>,
+    page: usize,
+    pages: Option<Result<usize, String>>,
+    // Drawn pages, with the edge length they were drawn at.
+    rendered: HashMap<usize, (u32, Result<TextureHandle, String>)>,
+    thumbs: HashMap<usize, Result<TextureHandle, String>>,
+    words: HashMap<usize, Result<Vec<Word>, String>>,
+    pending: HashSet<Job>,
+    // The drag over the page, as fractions of the page, from the top left.
+    selection: Option<(Pos2, Pos2)>,
+
+    tx: Sender<JobResult>,
+    rx: Receiver<JobResult>,
+}
+
+impl Default for UxDocument {
+    fn default() -> Self {
+        let (tx, rx) = unbounded();
+        Self {
+            path: None,
+            page: 0,
+            pages: None,
+            rendered: HashMap::new(),
+            thumbs: HashMap::new(),
+            words: HashMap::new(),
+            pending: HashSet::new(),
+            selection: None,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl UxDocument {
+    fn open(&mut self, path: &Path) {
+        *self = Self {
+            path: Some(path.to_owned()),
+            tx: self.tx.clone(),
+            rx: self.rx.clone(),
+            ..Self::default()
+        };
+    }
+
+    fn request(&mut self, job: Job, ctx: &egui::Context) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        if !self.pending.insert(job) {
+            return;
+        }
+        let (tx, ctx) = (self.tx.clone(), ctx.clone());
+        thread::spawn(move || {
+            let image = |page, edge| {
+                render_page(&path, page, edge)
+                    .map(|image| color_image(&image))
+                    .map_err(|e| e.to_string())
+            };
+            let done = match job {
+                Job::Count => Done::Count(page_count(&path).map_err(|e| e.to_string())),
+                Job::Page(page, edge) => Done::Image(image(page, edge)),
+                Job::Thumb(page) => Done::Image(image(page, THUMB_EDGE)),
+                Job::Words(page) => Done::Words(page_words(&path, page).map_err(|e| e.to_string())),
+            };
+            tx.send((path, job, done)).ok();
+            ctx.request_repaint();
+        });
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        while let Ok((path, job, done)) = self.rx.try_recv() {
+            // Note: jobs for a document we have since moved on from still finish; drop them.
+            if self.path.as_ref() != Some(&path) {
+                continue;
+            }
+            self.pending.remove(&job);
+            let upload = |page: usize, result: Result<ColorImage, String>| {
+                let name = format!("document://{}#{page}", path.display());
+                result.map(|image| ctx.load_texture(name, image, TextureOptions::LINEAR))
+            };
+            match (job, done) {
+                (Job::Count, Done::Count(count)) => self.pages = Some(count),
+                (Job::Page(page, edge), Done::Image(result)) => {
+                    self.rendered.insert(page, (edge, upload(page, result)));
+                }
+                (Job::Thumb(page), Done::Image(result)) => {
+                    self.thumbs.insert(page, upload(page, result));
+                }
+                (Job::Words(page), Done::Words(words)) => {
+                    self.words.insert(page, words);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn go_to(&mut self, page: usize, count: usize) {
+        let page = page.min(count.saturating_sub(1));
+        if page != self.page {
+            self.page = page;
+            self.selection = None;
+        }
+    }
+
+    fn selected_words(&self) -> Vec<&Word> {
+        let (Some((start, end)), Some(Ok(words))) = (self.selection, self.words.get(&self.page))
+        else {
+            return Vec::new();
+        };
+        let selection = Rect::from_two_pos(start, end);
+        words
+            .iter()
+            .filter(|word| {
+                let [left, top, right, bottom] = word.rect;
+                selection.intersects(Rect::from_min_max(
+                    Pos2::new(left, top),
+                    Pos2::new(right, bottom),
+                ))
+            })
+            .collect()
+    }
+
+    // Whether there are words selected, so that the copy shortcut should take them.
+    pub fn has_selection(&self) -> bool {
+        !self.selected_words().is_empty()
+    }
+
+    fn copy_selection(&self, ctx: &egui::Context) {
+        let text = self
+            .selected_words()
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        ctx.copy_text(text);
+    }
+
+    pub fn ui(&mut self, path: &Path, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        if self.path.as_deref() != Some(path) {
+            self.open(path);
+        }
+        self.receive(&ctx);
+
+        let count = match &self.pages {
+            None => {
+                self.request(Job::Count, &ctx);
+                ui.centered_and_justified(|ui| ui.spinner());
+                return;
+            }
+            Some(Err(e)) => {
+                let e = format!("Failed to open the document: {e}");
+                ui.centered_and_justified(|ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                });
+                return;
+            }
+            Some(Ok(count)) => *count,
+        };
+        if count == 0 {
+            ui.centered_and_justified(|ui| ui.label("The document has no pages"));
+            return;
+        }
+
+        let (next, prev, first, last, copy) = ui.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::NONE, Key::PageDown),
+                input.consume_key(Modifiers::NONE, Key::PageUp),
+                input.consume_key(Modifiers::NONE, Key::Home),
+                input.consume_key(Modifiers::NONE, Key::End),
+                input.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+            )
+        });
+        if next {
+            self.go_to(self.page + 1, count);
+        }
+        if prev {
+            self.go_to(self.page.saturating_sub(1), count);
+        }
+        if first {
+            self.go_to(0, count);
+        }
+        if last {
+            self.go_to(count - 1, count);
+        }
+        if copy && self.has_selection() {
+            self.copy_selection(&ctx);
+        }
+
+        let area = ui.available_rect_before_wrap();
+        let (column, page_area) = area.split_left_right_at_x(area.left() + THUMB_COLUMN + 16.);
+        self.thumbnails_ui(count, column, ui);
+        self.page_ui(count, page_area.shrink(8.), ui);
+
+        egui::Area::new(egui::Id::new("document_controls"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8., -8.])
+            .show(&ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("⏴").on_hover_text("Page Up").clicked() {
+                            self.go_to(self.page.saturating_sub(1), count);
+                        }
+                        ui.label(format!("Page {} of {count}", self.page + 1));
+                        if ui.button("⏵").on_hover_text("Page Down").clicked() {
+                            self.go_to(self.page + 1, count);
+                        }
+                        match self.words.get(&self.page) {
+                            Some(Ok(words)) if words.is_empty() => {
+                                ui.weak("No text layer");
+                            }
+                            Some(Err(e)) => {
+                                ui.weak("No text layer").on_hover_text(e.as_str());
+                            }
+                            _ => {}
+                        }
+                        if self.has_selection() && ui.button("📋 Copy text").clicked() {
+                            self.copy_selection(ui.ctx());
+                        }
+                    });
+                });
+            });
+    }
+
+    fn thumbnails_ui(&mut self, count: usize, column: Rect, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        ui.scope_builder(egui::UiBuilder::new().max_rect(column), |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("document_pages")
+                .show_rows(ui, THUMB_ROW, count, |ui, visible| {
+                    for page in visible {
+                        let (rect, resp) = ui.allocate_exact_size(
+                            Vec2::new(THUMB_COLUMN, THUMB_ROW - 4.),
+                            Sense::click(),
+                        );
+                        if page == self.page {
+                            ui.painter().rect_filled(
+                                rect.expand(2.),
+                                4.,
+                                ui.visuals().selection.bg_fill,
+                            );
+                        }
+                        let label = rect.with_min_y(rect.max.y - 16.);
+                        match self.thumbs.get(&page) {
+                            Some(Ok(handle)) => {
+                                let fitted = fit_rect(
+                                    rect.with_max_y(label.min.y).shrink(2.),
+                                    handle.size_vec2(),
+                                );
+                                egui::Image::from_texture(SizedTexture::from_handle(handle))
+                                    .paint_at(ui, fitted);
+                            }
+                            Some(Err(e)) => {
+                                ui.painter().text(
+                                    rect.center(),
+                                    egui::Align2::CENTER_CENTER,
+                                    "⚠",
+                                    egui::FontId::proportional(24.),
+                                    ui.visuals().error_fg_color,
+                                );
+                                resp.clone().on_hover_text(e.as_str());
+                            }
+                            None => self.request(Job::Thumb(page), &ctx),
+                        }
+                        ui.painter().text(
+                            label.center(),
+                            egui::Align2::CENTER_CENTER,
+                            (page + 1).to_string(),
+                            egui::FontId::proportional(12.),
+                            ui.visuals().text_color(),
+                        );
+                        if resp.clicked() {
+                            self.go_to(page, count);
+                        }
+                    }
+                });
+        });
+    }
+
+    fn page_ui(&mut self, count: usize, area: Rect, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        // Note: draw at the resolution of the screen, in steps, so a resize doesn't redraw every
+        //       page, and only ever redraw to go larger.
+        let wanted = (area.size().max_elem() * ctx.pixels_per_point() / 256.).ceil() as u32 * 256;
+        let wanted = wanted.max(512);
+        let current = self.page;
+        self.rendered
+            .retain(|page, _| page.abs_diff(current) <= KEEP_PAGES);
+        // Note: draw the next page ahead of time, since that is where readers go.
+        for page in [current, current + 1]
+            .into_iter()
+            .filter(|page| *page < count)
+        {
+            let drawn = self.rendered.get(&page).map_or(0, |(edge, _)| *edge);
+            if drawn < wanted {
+                self.request(Job::Page(page, wanted), &ctx);
+            }
+        }
+        if !self.words.contains_key(&self.page) {
+            self.request(Job::Words(self.page), &ctx);
+        }
+
+        let handle = match self.rendered.get(&self.page) {
+            Some((_, Ok(handle))) => Some(handle.clone()),
+            Some((_, Err(e))) => {
+                let e = format!("Failed to draw the page: {e}");
+                ui.scope_builder(egui::UiBuilder::new().max_rect(area), |ui| {
+                    ui.centered_and_justified(|ui| {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    });
+                });
+                return;
+            }
+            // Note: stretch the thumbnail over the page until the page itself is drawn.
+            None => self
+                .thumbs
+                .get(&self.page)
+                .and_then(|thumb| thumb.as_ref().ok().cloned()),
+        };
+        let Some(handle) = handle else {
+            egui::Spinner::new()
+                .paint_at(ui, Rect::from_center_size(area.center(), Vec2::splat(32.)));
+            return;
+        };
+        let page_rect = fit_rect(area, handle.size_vec2());
+        egui::Image::from_texture(SizedTexture::from_handle(&handle)).paint_at(ui, page_rect);
+
+        // Select words by dragging over them; a click clears the selection.
+        let resp = ui.interact(
+            page_rect,
+            egui::Id::new("document_page"),
+            Sense::click_and_drag(),
+        );
+        let to_page = |pos: Pos2| {
+            let offset = (pos - page_rect.min) / page_rect.size();
+            Pos2::new(offset.x.clamp(0., 1.), offset.y.clamp(0., 1.))
+        };
+        if resp.drag_started()
+            && let Some(pos) = resp.interact_pointer_pos()
+        {
+            self.selection = Some((to_page(pos), to_page(pos)));
+        } else if resp.dragged()
+            && let Some(pos) = resp.interact_pointer_pos()
+            && let Some((_, end)) = self.selection.as_mut()
+        {
+            *end = to_page(pos);
+        } else if resp.clicked() {
+            self.selection = None;
+        }
+        if self
+            .words
+            .get(&self.page)
+            .is_some_and(|words| words.as_ref().is_ok_and(|words| !words.is_empty()))
+        {
+            resp.on_hover_cursor(egui::CursorIcon::Text);
+        }
+        let highlight = ui.visuals().selection.bg_fill.gamma_multiply(0.5);
+        for word in self.selected_words() {
+            let [left, top, right, bottom] = word.rect;
+            let rect = Rect::from_min_max(
+                page_rect.lerp_inside(Vec2::new(left, top)),
+                page_rect.lerp_inside(Vec2::new(right, bottom)),
+            );
+            ui.painter().rect_filled(rect, 0., highlight);
+        }
+        if let Some((start, end)) = self.selection {
+            let rect = Rect::from_two_pos(
+                page_rect.lerp_inside(start.to_vec2()),
+                page_rect.lerp_inside(end.to_vec2()),
+            );
+            ui.painter().rect_stroke(
+                rect,
+                0.,
+                egui::Stroke::new(1., Color32::from_white_alpha(96)),
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_rect() {
+        let outer = Rect::from_min_size(Pos2::ZERO, Vec2::new(200., 100.));
+        let fitted = fit_rect(outer, Vec2::new(50., 100.));
+        assert_eq!(
+            fitted,
+            Rect::from_min_size(Pos2::new(75., 0.), Vec2::new(50., 100.))
+        );
+    }
+}
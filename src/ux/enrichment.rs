@@ -0,0 +1,319 @@
+// Looks works up on Wikidata in the background, for those that a plugin gave a Wikidata id, and
+// the preferences to turn it on, or to take everything it added back off again.
+use crate::{
+    db::{models::enrichment::PendingEnrichment, reader::DbReadHandle, writer::DbWriteHandle},
+    shared::{
+        enrichment::{self, BATCH_SIZE, EnrichedWork, ItemEnrichments, QUERY_DELAY},
+        progress::Progress,
+        update::DataUpdate,
+    },
+};
+use artchiver_sdk::Authority;
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use log::error;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+enum EnricherEvent {
+    Enriched(Vec<EnrichedWork>),
+    Stopped { error: Option<String> },
+}
+
+struct EnrichmentRun {
+    ctx: egui::Context,
+
+    queue: Arc<Mutex<VecDeque<PendingEnrichment>>>,
+    tx_event: Sender<EnricherEvent>,
+    rx_event: Receiver<EnricherEvent>,
+    running: bool,
+
+    scanning: bool,
+    last_scan: Option<Instant>,
+    // A plugin sent works since the last scan, so there may be new ids to look up.
+    stale: bool,
+
+    total: usize,
+    done: usize,
+    error: Option<String>,
+}
+
+impl Default for EnrichmentRun {
+    fn default() -> Self {
+        let (tx_event, rx_event) = unbounded();
+        Self {
+            ctx: egui::Context::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            tx_event,
+            rx_event,
+            running: false,
+            scanning: false,
+            last_scan: None,
+            stale: false,
+            total: 0,
+            done: 0,
+            error: None,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxEnrichment {
+    enabled: bool,
+
+    // Taking the enriched data off can't be undone, short of looking everything up again.
+    #[serde(skip)]
+    confirm_remove: bool,
+    #[serde(skip)]
+    run: EnrichmentRun,
+}
+
+impl UxEnrichment {
+    // Refreshes arrive in bursts; look for new works at most this often.
+    const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn startup(&mut self, ctx: &egui::Context, db: &DbReadHandle) {
+        self.run.ctx = ctx.clone();
+        if self.enabled {
+            self.scan(db);
+        }
+    }
+
+    fn scan(&mut self, db: &DbReadHandle) {
+        self.run.scanning = true;
+        self.run.stale = false;
+        self.run.last_scan = Some(Instant::now());
+        db.get_works_to_enrich();
+    }
+
+    fn stop(&mut self) {
+        // Note: the worker finishes the query it is on, then finds the queue empty and exits.
+        let dropped = {
+            let mut queue = self.run.queue.lock();
+            let dropped = queue.len();
+            queue.clear();
+            dropped
+        };
+        self.run.total -= dropped;
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::WorksToEnrich(works) => {
+                    self.run.scanning = false;
+                    if self.enabled {
+                        self.enqueue(works);
+                    }
+                }
+                DataUpdate::WorksWereUpdatedForTag { .. } => {
+                    self.run.stale = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn enqueue(&mut self, works: &[PendingEnrichment]) {
+        {
+            let mut queue = self.run.queue.lock();
+            let queued = queue
+                .iter()
+                .map(|job| job.screen_url.clone())
+                .collect::<HashSet<_>>();
+            let fresh = works
+                .iter()
+                .filter(|work| !queued.contains(&work.screen_url))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !self.run.running {
+                self.run.total = 0;
+                self.run.done = 0;
+                self.run.error = None;
+            }
+            self.run.total += fresh.len();
+            queue.extend(fresh);
+        }
+        if !self.run.running && !self.run.queue.lock().is_empty() {
+            self.spawn_worker();
+        }
+    }
+
+    // Note: one worker, one query at a time, as the Query Service asks of bots.
+    fn spawn_worker(&mut self) {
+        let queue = self.run.queue.clone();
+        let tx = self.run.tx_event.clone();
+        let ctx = self.run.ctx.clone();
+        let spawned = thread::Builder::new()
+            .name("Enricher".to_owned())
+            .spawn(move || {
+                let agent = enrichment::make_agent();
+                loop {
+                    let batch = {
+                        let mut queue = queue.lock();
+                        let count = queue.len().min(BATCH_SIZE);
+                        queue.drain(..count).collect::<Vec<_>>()
+                    };
+                    if batch.is_empty() {
+                        break;
+                    }
+                    // Note: ids that aren't Wikidata's can never be looked up; they are sent
+                    //       back with nothing found, so that they count as scanned.
+                    let ids = batch
+                        .iter()
+                        .map(|job| job.wikidata_id.as_str())
+                        .filter(|id| Authority::Wikidata.is_valid_id(id))
+                        .collect::<HashSet<_>>()
+                        .into_iter()
+                        .collect::<Vec<_>>();
+                    let found = if ids.is_empty() {
+                        Ok(ItemEnrichments::new())
+                    } else {
+                        enrichment::query_items(&agent, &ids)
+                    };
+                    let found = match found {
+                        Ok(found) => found,
+                        Err(e) => {
+                            // Note: these works stay un-looked-up, so the next scan tries again.
+                            queue.lock().clear();
+                            tx.send(EnricherEvent::Stopped {
+                                error: Some(format!("{e:#}")),
+                            })
+                            .ok();
+                            ctx.request_repaint();
+                            return;
+                        }
+                    };
+                    let works = batch
+                        .into_iter()
+                        .map(|job| {
+                            let (enrichments, artists) =
+                                found.get(&job.wikidata_id).cloned().unwrap_or_default();
+                            EnrichedWork {
+                                screen_url: job.screen_url,
+                                enrichments,
+                                artists,
+                            }
+                        })
+                        .collect();
+                    if tx.send(EnricherEvent::Enriched(works)).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                    thread::sleep(QUERY_DELAY);
+                }
+                tx.send(EnricherEvent::Stopped { error: None }).ok();
+                ctx.request_repaint();
+            });
+        match spawned {
+            Ok(_) => self.run.running = true,
+            Err(e) => error!("Failed to start Wikidata enrichment: {e}"),
+        }
+    }
+
+    pub fn tick(&mut self, db: &DbReadHandle, db_write: &DbWriteHandle) {
+        while let Ok(event) = self.run.rx_event.try_recv() {
+            match event {
+                EnricherEvent::Enriched(works) => {
+                    self.run.done += works.len();
+                    if let Err(e) = db_write.save_enrichments(works) {
+                        error!("Failed to save what Wikidata told us: {e}");
+                    }
+                }
+                EnricherEvent::Stopped { error } => {
+                    self.run.running = false;
+                    if let Some(e) = &error {
+                        error!("Wikidata enrichment stopped: {e}");
+                    }
+                    // Note: works may have been queued after the worker found the queue empty.
+                    if error.is_none() && !self.run.queue.lock().is_empty() {
+                        self.spawn_worker();
+                    }
+                    self.run.error = error;
+                }
+            }
+        }
+        let due = self
+            .run
+            .last_scan
+            .is_none_or(|at| at.elapsed() >= Self::RESCAN_INTERVAL);
+        if self.run.stale && due && !self.run.running && !self.run.scanning && self.enabled {
+            self.scan(db);
+        }
+    }
+
+    pub fn preferences_ui(
+        &mut self,
+        db: &DbReadHandle,
+        db_write: &DbWriteHandle,
+        ui: &mut egui::Ui,
+    ) {
+        let was_enabled = self.enabled;
+        ui.checkbox(
+            &mut self.enabled,
+            "Look up works on Wikidata, for plugins that give us their Wikidata ids",
+        );
+        ui.label(
+            "Works get tags for their movement, genre, and what they depict, and their artists' \
+             dates and nationality. Everything found this way is marked as enriched, and can be \
+             taken off again below.",
+        );
+
+        ui.horizontal(|ui| {
+            if self.run.scanning {
+                ui.spinner();
+            } else if self.run.running {
+                Progress::Percent {
+                    current: self.run.done,
+                    total: self.run.total.max(1),
+                }
+                .ui(ui);
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else {
+                if self.run.total > 0 {
+                    ui.label(format!("looked up {} works", self.run.done));
+                }
+                if ui
+                    .add_enabled(self.enabled, egui::Button::new("Look Up Now"))
+                    .on_hover_text("Look up works with a Wikidata id that haven't been looked up")
+                    .clicked()
+                {
+                    self.scan(db);
+                }
+            }
+            if self.confirm_remove {
+                ui.label("Take every enriched tag and artist off?");
+                if ui.button("Remove").clicked() {
+                    self.confirm_remove = false;
+                    // Note: otherwise the next scan would put it all back.
+                    self.enabled = false;
+                    self.stop();
+                    if let Err(e) = db_write.remove_enrichments() {
+                        error!("Failed to request removing enriched data: {e}");
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_remove = false;
+                }
+            } else if ui.button("Remove Enriched Data").clicked() {
+                self.confirm_remove = true;
+            }
+        });
+        if let Some(e) = &self.run.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        if self.enabled && !was_enabled {
+            self.scan(db);
+        } else if !self.enabled && was_enabled {
+            self.stop();
+        }
+    }
+}
@@ -0,0 +1,547 @@
+// Build exhibitions: ordered runs of works with captions, broken up into sections, and then show
+// them full screen with the captions over the works and the presenter's notes in a second window.
+use crate::{
+    db::{
+        models::{
+            exhibition::{DbExhibition, ExhibitionItem},
+            work::DbWork,
+        },
+        reader::DbReadHandle,
+        writer::DbWriteHandle,
+    },
+    plugin::thumbnail::is_image,
+    shared::{
+        deep_link::DeepLink,
+        exhibition::{ExhibitEntry, write_exhibition_html},
+        storage::Storage,
+        update::DataUpdate,
+    },
+};
+use crossbeam::channel::{Receiver, bounded};
+use egui::{Align2, Color32, Key, Modifiers, RichText, ViewportBuilder, ViewportId};
+use log::error;
+use std::{
+    path::{Path, PathBuf},
+    thread,
+};
+
+#[derive(Default)]
+pub struct UxExhibitions {
+    storage: Storage,
+    exhibitions: Option<Vec<DbExhibition>>,
+    current: Option<String>,
+    dirty: bool,
+    new_name: String,
+    new_section: String,
+    confirm_delete: bool,
+
+    // Presenting
+    slide: usize,
+    show_notes: bool,
+
+    // Exporting
+    export_dir: String,
+    link_to_archive: bool,
+    pending_export: Option<Receiver<Result<usize, String>>>,
+    last_export: Option<Result<usize, String>>,
+}
+
+impl UxExhibitions {
+    const THUMB_SIZE: f32 = 64.;
+
+    pub fn startup(&mut self, storage: &Storage, db: &DbReadHandle) {
+        self.storage = storage.clone();
+        db.get_exhibitions();
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::Exhibitions(exhibitions) = update {
+                let mut exhibitions = exhibitions.clone();
+                // Note: a metadata sync sends these too; don't lose an edit that isn't saved yet.
+                if self.dirty
+                    && let Some(editing) = self.current().cloned()
+                {
+                    exhibitions.retain(|e| e.name != editing.name);
+                    exhibitions.push(editing);
+                    exhibitions.sort_by(|a, b| a.name.cmp(&b.name));
+                }
+                self.exhibitions = Some(exhibitions);
+                if self.current().is_none() {
+                    self.current = exhibitions.first().map(|e| e.name.clone());
+                }
+            }
+        }
+    }
+
+    fn current(&self) -> Option<&DbExhibition> {
+        let name = self.current.as_ref()?;
+        self.exhibitions.as_ref()?.iter().find(|e| &e.name == name)
+    }
+
+    fn current_mut(&mut self) -> Option<&mut DbExhibition> {
+        let name = self.current.as_ref()?;
+        self.exhibitions
+            .as_mut()?
+            .iter_mut()
+            .find(|e| &e.name == name)
+    }
+
+    fn save(&mut self, db_write: &DbWriteHandle) {
+        self.dirty = false;
+        if let Some(exhibition) = self.current()
+            && let Err(e) = db_write.save_exhibition(&exhibition.name, exhibition.items.clone())
+        {
+            error!("Failed to save exhibition {}: {e}", exhibition.name);
+        }
+    }
+
+    // The best local image we have for the work: the screen image if we have it, else the preview.
+    fn image_path(&self, work: &DbWork) -> Option<PathBuf> {
+        let local = |path: Option<&Path>| {
+            path.filter(|path| is_image(path) && self.storage.is_available(path))
+                .map(|path| self.storage.resolve(path))
+        };
+        local(work.screen_path()).or_else(|| local(work.preview_path()))
+    }
+
+    fn image_uri(&self, work: &DbWork) -> Option<String> {
+        self.image_path(work)
+            .map(|path| format!("file://{}", path.display()))
+    }
+
+    // Returns true if the user asked to start presenting.
+    pub fn ui(
+        &mut self,
+        selected: Option<&DbWork>,
+        db_write: &DbWriteHandle,
+        ui: &mut egui::Ui,
+    ) -> bool {
+        self.poll_export();
+        let Some(exhibitions) = self.exhibitions.as_mut() else {
+            ui.spinner();
+            return false;
+        };
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::new("exhibition_select", "")
+                .selected_text(self.current.as_deref().unwrap_or("No exhibition"))
+                .show_ui(ui, |ui| {
+                    for exhibition in exhibitions.iter() {
+                        ui.selectable_value(
+                            &mut self.current,
+                            Some(exhibition.name.clone()),
+                            &exhibition.name,
+                        );
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_name)
+                    .hint_text("new exhibition")
+                    .desired_width(140.),
+            );
+            let name = self.new_name.trim().to_owned();
+            let is_new = !name.is_empty() && !exhibitions.iter().any(|e| e.name == name);
+            if ui
+                .add_enabled(is_new, egui::Button::new("Create"))
+                .clicked()
+            {
+                exhibitions.push(DbExhibition::new(&name));
+                exhibitions.sort_by(|a, b| a.name.cmp(&b.name));
+                self.current = Some(name);
+                self.new_name.clear();
+                self.dirty = true;
+            }
+        });
+        let Some(name) = self.current.clone() else {
+            ui.label("Create an exhibition to start arranging works.");
+            return false;
+        };
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(selected.is_some(), egui::Button::new("Add Selected Work"))
+                .on_hover_text("Add the work selected in the gallery to the end")
+                .clicked()
+                && let Some(work) = selected
+                && let Some(exhibition) = self.current_mut()
+            {
+                exhibition.items.push(ExhibitionItem::work(work));
+                exhibition
+                    .works
+                    .insert(work.screen_url().to_owned(), work.clone());
+                self.dirty = true;
+            }
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_section)
+                    .hint_text("section title")
+                    .desired_width(140.),
+            );
+            let title = self.new_section.trim().to_owned();
+            if ui
+                .add_enabled(!title.is_empty(), egui::Button::new("Add Section"))
+                .clicked()
+                && let Some(exhibition) = self.current_mut()
+            {
+                exhibition.items.push(ExhibitionItem::section(&title));
+                self.new_section.clear();
+                self.dirty = true;
+            }
+        });
+        ui.separator();
+
+        let mut present = false;
+        ui.horizontal(|ui| {
+            let count = self.current().map(|e| e.items.len()).unwrap_or_default();
+            if ui
+                .add_enabled(count > 0, egui::Button::new("▶ Present"))
+                .on_hover_text("Arrows or space to move between slides; escape to stop")
+                .clicked()
+            {
+                present = true;
+            }
+            ui.checkbox(&mut self.show_notes, "Presenter notes window");
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut self.export_dir)
+                    .hint_text("export folder")
+                    .desired_width(140.),
+            );
+            ui.checkbox(&mut self.link_to_archive, "Link to archive")
+                .on_hover_text(
+                    "Link each work's title back to it here, with an artchiver:// link; \
+                     only useful on machines with this archive",
+                );
+            if self.pending_export.is_some() {
+                ui.spinner();
+            } else if ui
+                .add_enabled(
+                    count > 0 && !self.export_dir.trim().is_empty(),
+                    egui::Button::new("Export HTML"),
+                )
+                .clicked()
+            {
+                self.start_export(ui.ctx());
+            }
+            match &self.last_export {
+                Some(Ok(works)) => {
+                    ui.label(format!("Wrote {works} works"));
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {e}"));
+                }
+                None => {}
+            }
+            ui.separator();
+            if self.confirm_delete {
+                ui.label(format!("Delete {name}?"));
+                if ui.button("Yes").clicked() {
+                    if let Err(e) = db_write.delete_exhibition(&name) {
+                        error!("Failed to delete exhibition {name}: {e}");
+                    }
+                    if let Some(exhibitions) = self.exhibitions.as_mut() {
+                        exhibitions.retain(|e| e.name != name);
+                    }
+                    self.current = None;
+                    self.dirty = false;
+                    self.confirm_delete = false;
+                }
+                if ui.button("No").clicked() {
+                    self.confirm_delete = false;
+                }
+            } else if ui.button("Delete").clicked() {
+                self.confirm_delete = true;
+            }
+        });
+        ui.separator();
+
+        self.items_ui(ui);
+
+        // Note: hold off writing while the user is still typing a caption.
+        if self.dirty && ui.ctx().memory(|mem| mem.focused().is_none()) {
+            self.save(db_write);
+        }
+        if present {
+            self.slide = 0;
+        }
+        present
+    }
+
+    fn items_ui(&mut self, ui: &mut egui::Ui) {
+        let uris = self
+            .current()
+            .map(|exhibition| {
+                exhibition
+                    .items
+                    .iter()
+                    .map(|item| exhibition.work_for(item).and_then(|w| self.image_uri(w)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let Some(exhibition) = self.current_mut() else {
+            return;
+        };
+        let mut moved = None;
+        let mut removed = None;
+        let mut changed = false;
+        let count = exhibition.items.len();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (offset, uri) in uris.into_iter().enumerate() {
+                let label = match &exhibition.items[offset] {
+                    ExhibitionItem::Section { title, .. } => RichText::new(title).heading(),
+                    item => RichText::new(
+                        exhibition
+                            .work_for(item)
+                            .map(DbWork::name)
+                            .unwrap_or("(missing work)"),
+                    )
+                    .strong(),
+                };
+                let item = &mut exhibition.items[offset];
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        if ui
+                            .add_enabled(offset > 0, egui::Button::new("⏶").small())
+                            .clicked()
+                        {
+                            moved = Some((offset, offset - 1));
+                        }
+                        if ui
+                            .add_enabled(offset + 1 < count, egui::Button::new("⏷").small())
+                            .clicked()
+                        {
+                            moved = Some((offset, offset + 1));
+                        }
+                        if ui.small_button("🗑").clicked() {
+                            removed = Some(offset);
+                        }
+                    });
+                    if let Some(uri) = uri {
+                        ui.add(
+                            egui::Image::new(uri)
+                                .max_size(egui::vec2(Self::THUMB_SIZE, Self::THUMB_SIZE)),
+                        );
+                    }
+                    ui.vertical(|ui| {
+                        ui.label(label);
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::multiline(item.caption_mut())
+                                    .hint_text("caption")
+                                    .desired_rows(2),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::multiline(item.notes_mut())
+                                    .hint_text("presenter notes")
+                                    .desired_rows(1),
+                            )
+                            .changed();
+                    });
+                });
+                ui.separator();
+            }
+        });
+        if let Some((from, to)) = moved {
+            exhibition.items.swap(from, to);
+            changed = true;
+        }
+        if let Some(offset) = removed {
+            exhibition.items.remove(offset);
+            changed = true;
+        }
+        self.dirty |= changed;
+    }
+
+    fn start_export(&mut self, ctx: &egui::Context) {
+        let Some(exhibition) = self.current() else {
+            return;
+        };
+        let name = exhibition.name.clone();
+        let entries = exhibition
+            .items
+            .iter()
+            .map(|item| match item {
+                ExhibitionItem::Section { title, caption, .. } => ExhibitEntry::Section {
+                    title: title.clone(),
+                    caption: caption.clone(),
+                },
+                ExhibitionItem::Work { caption, .. } => {
+                    let work = exhibition.work_for(item);
+                    ExhibitEntry::Work {
+                        image: work.and_then(|w| self.image_path(w)),
+                        title: work.map(|w| w.name().to_owned()).unwrap_or_default(),
+                        artist: work
+                            .and_then(|w| w.history())
+                            .and_then(|h| h.attribution())
+                            .map(str::to_owned),
+                        date: work.map(|w| w.date().to_string()).unwrap_or_default(),
+                        caption: caption.clone(),
+                        link: work.filter(|_| self.link_to_archive).map(|w| {
+                            DeepLink::Work {
+                                screen_url: w.screen_url().to_owned(),
+                            }
+                            .to_string()
+                        }),
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let out = PathBuf::from(self.export_dir.trim());
+        let ctx = ctx.clone();
+        let (tx, rx) = bounded(1);
+        self.pending_export = Some(rx);
+        self.last_export = None;
+        thread::spawn(move || {
+            let result = write_exhibition_html(&name, &entries, &out).map_err(|e| format!("{e:#}"));
+            tx.send(result).ok();
+            ctx.request_repaint();
+        });
+    }
+
+    fn poll_export(&mut self) {
+        if let Some(rx) = &self.pending_export
+            && let Ok(result) = rx.try_recv()
+        {
+            self.pending_export = None;
+            self.last_export = Some(result);
+        }
+    }
+
+    // Returns false if there is nothing left to present.
+    pub fn present_ui(&mut self, ctx: &egui::Context) -> bool {
+        let count = self.current().map(|e| e.items.len()).unwrap_or_default();
+        if count == 0 {
+            return false;
+        }
+        ctx.input_mut(|input| {
+            for key in [Key::ArrowRight, Key::Space, Key::PageDown] {
+                if input.consume_key(Modifiers::NONE, key) {
+                    self.slide = (self.slide + 1).min(count - 1);
+                }
+            }
+            for key in [Key::ArrowLeft, Key::Backspace, Key::PageUp] {
+                if input.consume_key(Modifiers::NONE, key) {
+                    self.slide = self.slide.saturating_sub(1);
+                }
+            }
+            if input.consume_key(Modifiers::NONE, Key::Home) {
+                self.slide = 0;
+            }
+            if input.consume_key(Modifiers::NONE, Key::End) {
+                self.slide = count - 1;
+            }
+        });
+        self.slide = self.slide.min(count - 1);
+
+        let Some(exhibition) = self.current() else {
+            return false;
+        };
+        let item = &exhibition.items[self.slide];
+        let work = exhibition.work_for(item);
+        let uri = work.and_then(|w| self.image_uri(w));
+        egui::CentralPanel::default()
+            .frame(egui::Frame::new().fill(Color32::BLACK))
+            .show(ctx, |ui| match item {
+                ExhibitionItem::Section { title, caption, .. } => {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() / 3.);
+                        ui.label(RichText::new(title).size(48.).color(Color32::WHITE));
+                        ui.add_space(16.);
+                        ui.label(RichText::new(caption).size(20.).color(Color32::LIGHT_GRAY));
+                    });
+                }
+                ExhibitionItem::Work { .. } => {
+                    ui.centered_and_justified(|ui| match uri {
+                        Some(uri) => {
+                            ui.add(egui::Image::new(uri).shrink_to_fit());
+                        }
+                        None => {
+                            ui.label("This work has not been downloaded.");
+                        }
+                    });
+                }
+            });
+
+        if let ExhibitionItem::Work { caption, .. } = item {
+            egui::Area::new(egui::Id::new("exhibition_caption"))
+                .anchor(Align2::CENTER_BOTTOM, [0., -32.])
+                .show(ctx, |ui| {
+                    egui::Frame::new()
+                        .fill(Color32::from_black_alpha(180))
+                        .inner_margin(12)
+                        .corner_radius(6.0)
+                        .show(ui, |ui| {
+                            ui.set_max_width(ctx.screen_rect().width() * 0.6);
+                            if let Some(work) = work {
+                                ui.label(
+                                    RichText::new(work.name())
+                                        .size(22.)
+                                        .strong()
+                                        .color(Color32::WHITE),
+                                );
+                                let attribution = work.history().and_then(|h| h.attribution());
+                                ui.label(
+                                    RichText::new(match attribution {
+                                        Some(artist) => format!("{artist}, {}", work.date()),
+                                        None => work.date().to_string(),
+                                    })
+                                    .italics()
+                                    .color(Color32::LIGHT_GRAY),
+                                );
+                            }
+                            if !caption.is_empty() {
+                                ui.label(RichText::new(caption).size(16.).color(Color32::WHITE));
+                            }
+                        });
+                });
+        }
+
+        if self.show_notes {
+            let notes = item.notes().to_owned();
+            let title = match item {
+                ExhibitionItem::Section { title, .. } => title.clone(),
+                ExhibitionItem::Work { .. } => {
+                    work.map(|w| w.name().to_owned()).unwrap_or_default()
+                }
+            };
+            let next = exhibition.items.get(self.slide + 1).map(|next| match next {
+                ExhibitionItem::Section { title, .. } => title.clone(),
+                ExhibitionItem::Work { .. } => exhibition
+                    .work_for(next)
+                    .map(|w| w.name().to_owned())
+                    .unwrap_or_default(),
+            });
+            let slide = self.slide;
+            let mut close = false;
+            ctx.show_viewport_immediate(
+                ViewportId::from_hash_of("exhibition_notes"),
+                ViewportBuilder::default()
+                    .with_title("Presenter Notes")
+                    .with_inner_size([480., 360.]),
+                |ctx, _class| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        ui.weak(format!("Slide {} of {count}", slide + 1));
+                        ui.heading(&title);
+                        ui.separator();
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.label(RichText::new(&notes).size(18.));
+                        });
+                        ui.separator();
+                        match &next {
+                            Some(next) => ui.weak(format!("Next: {next}")),
+                            None => ui.weak("This is the last slide."),
+                        };
+                    });
+                    close = ctx.input(|input| input.viewport().close_requested());
+                },
+            );
+            if close {
+                self.show_notes = false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,168 @@
+use crate::shared::export::{ExportFormat, ExportReport, ExportSettings, export_works};
+use crossbeam::channel::{Receiver, bounded};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug)]
+struct ExportJob {
+    total: usize,
+    done: Arc<AtomicUsize>,
+    rx: Receiver<Result<ExportReport, String>>,
+}
+
+// The export window: copies the works in the gallery out to a folder, converting as it goes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxExport {
+    settings: ExportSettings,
+    dir: String,
+
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    job: Option<ExportJob>,
+    #[serde(skip)]
+    last_report: Option<Result<ExportReport, String>>,
+    // Works that aren't downloaded, or are only in remote storage, have nothing to export.
+    #[serde(skip)]
+    skipped: usize,
+}
+
+impl UxExport {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    fn start(&mut self, sources: Vec<PathBuf>, ctx: &egui::Context) {
+        let (tx, rx) = bounded(1);
+        let done = Arc::new(AtomicUsize::new(0));
+        self.job = Some(ExportJob {
+            total: sources.len(),
+            done: done.clone(),
+            rx,
+        });
+        self.last_report = None;
+        let dir = PathBuf::from(self.dir.trim());
+        let settings = self.settings.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result =
+                export_works(&sources, &dir, &settings, &done).map_err(|e| format!("{e:#}"));
+            tx.send(result).ok();
+            ctx.request_repaint();
+        });
+    }
+
+    // `count` is the number of works in the gallery; `sources` lists the files of those that
+    // are available locally, and is only called when the user starts an export.
+    pub fn window(
+        &mut self,
+        count: usize,
+        sources: impl FnOnce() -> Vec<PathBuf>,
+        ctx: &egui::Context,
+    ) {
+        if let Some(job) = &self.job
+            && let Ok(report) = job.rx.try_recv()
+        {
+            self.job = None;
+            self.last_report = Some(report);
+        }
+
+        let mut open = self.open;
+        let mut start = false;
+        egui::Window::new("Export Works")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Export the {count} works in the gallery."));
+                ui.add(egui::TextEdit::singleline(&mut self.dir).hint_text("export directory"));
+                egui::ComboBox::new("export_format", "Format")
+                    .selected_text(self.settings.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut self.settings.format, format, format.label());
+                        }
+                    });
+                ui.add(
+                    egui::Slider::new(&mut self.settings.max_dimension, 0..=8192)
+                        .text("Longest side")
+                        .suffix("px")
+                        .custom_formatter(|value, _| {
+                            if value == 0. {
+                                "Full size".to_owned()
+                            } else {
+                                format!("{value:.0}")
+                            }
+                        }),
+                );
+                ui.add_enabled(
+                    self.settings.format.has_quality(),
+                    egui::Slider::new(&mut self.settings.quality, 1..=100).text("Quality"),
+                );
+                ui.add(egui::Slider::new(&mut self.settings.workers, 1..=32).text("Workers"));
+
+                if let Some(job) = &self.job {
+                    let done = job.done.load(Ordering::Relaxed);
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / job.total.max(1) as f32)
+                            .text(format!("{done} of {}", job.total)),
+                    );
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                } else if ui
+                    .add_enabled(
+                        count > 0 && !self.dir.trim().is_empty(),
+                        egui::Button::new("Export"),
+                    )
+                    .clicked()
+                {
+                    start = true;
+                }
+
+                match &self.last_report {
+                    Some(Ok(report)) => {
+                        ui.label(format!(
+                            "Exported {} works to {}",
+                            report.exported,
+                            Path::new(self.dir.trim()).display()
+                        ));
+                        if self.skipped > 0 {
+                            ui.label(format!(
+                                "Skipped {} works that aren't available locally",
+                                self.skipped
+                            ));
+                        }
+                        if !report.failed.is_empty() {
+                            egui::CollapsingHeader::new(format!(
+                                "{} could not be exported",
+                                report.failed.len()
+                            ))
+                            .show(ui, |ui| {
+                                for failure in &report.failed {
+                                    ui.label(failure);
+                                }
+                            });
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {e}"));
+                    }
+                    None => {}
+                }
+            });
+        self.open = open;
+
+        if start {
+            let sources = sources();
+            self.skipped = count.saturating_sub(sources.len());
+            self.start(sources, ctx);
+        }
+    }
+}
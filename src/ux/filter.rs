@@ -0,0 +1,381 @@
+// Content filters: rules that hide works with a tag outright, or blur their previews in the
+// gallery until clicked on. Rules name tags rather than using ids, so that they read sensibly
+// and survive the tag being re-fetched. Works that the user's NSFW classifier scores highly can
+// be hidden or blurred the same way.
+use crate::{
+    db::{
+        models::{
+            tag::{DbTag, TagId},
+            work::{DbWork, WorkId},
+        },
+        reader::PublicView,
+    },
+    plugin::thumbnail::open_image,
+    shared::nsfw::{self, NsfwSettings},
+};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use egui::{ColorImage, TextureHandle, TextureOptions, load::SizedTexture};
+use log::{error, warn};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FilterAction {
+    #[default]
+    Hide,
+    Blur,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterRule {
+    enabled: bool,
+    tag: String,
+    action: FilterAction,
+    // Note: favorites are the works the user has explicitly chosen to keep, so they are the
+    //       natural exception to a blanket rule.
+    except_favorites: bool,
+}
+
+// The rules, resolved against the current tags.
+#[derive(Debug, Default)]
+pub struct CompiledFilters {
+    hide: HashMap<TagId, bool>,
+    blur: HashMap<TagId, bool>,
+    // What to do with works that score at least the threshold.
+    nsfw: Option<(FilterAction, f32)>,
+}
+
+impl CompiledFilters {
+    fn applies(rules: &HashMap<TagId, bool>, work: &DbWork) -> bool {
+        work.tags().any(|tag_id| {
+            rules
+                .get(&tag_id)
+                .is_some_and(|except_favorites| !(*except_favorites && work.favorite()))
+        })
+    }
+
+    // Note: favorites are exempt, as with the tag rules that allow it.
+    fn scored(&self, action: FilterAction, work: &DbWork) -> bool {
+        self.nsfw.is_some_and(|(nsfw_action, threshold)| {
+            nsfw_action == action
+                && !work.favorite()
+                && work.nsfw_score().is_some_and(|score| score >= threshold)
+        })
+    }
+
+    pub fn hides(&self, work: &DbWork) -> bool {
+        Self::applies(&self.hide, work) || self.scored(FilterAction::Hide, work)
+    }
+
+    pub fn blurs(&self, work: &DbWork) -> bool {
+        Self::applies(&self.blur, work) || self.scored(FilterAction::Blur, work)
+    }
+
+    // Note: the web gallery can't blur a preview until it is clicked, so visitors don't get to
+    //       see blurred works, or works the classifier would blur, either.
+    pub fn public_view(&self) -> PublicView {
+        let mut withheld = self.hide.clone();
+        for (tag_id, except_favorites) in &self.blur {
+            *withheld.entry(*tag_id).or_insert(*except_favorites) &= *except_favorites;
+        }
+        PublicView::new(
+            withheld.into_iter().collect(),
+            self.nsfw.map(|(_, threshold)| threshold),
+        )
+    }
+}
+
+type BlurResult = (PathBuf, Result<ColorImage, String>);
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentFilters {
+    rules: Vec<FilterRule>,
+    classifier: NsfwSettings,
+    // None leaves scored works alone.
+    nsfw_action: Option<FilterAction>,
+    nsfw_threshold: f32,
+    #[serde(skip)]
+    classifier_error: Option<String>,
+
+    // Works whose previews the user has clicked through this session.
+    #[serde(skip)]
+    revealed: HashSet<WorkId>,
+    #[serde(skip)]
+    new_tag: String,
+
+    #[serde(skip)]
+    blurred: LruCache<PathBuf, TextureHandle>,
+    #[serde(skip)]
+    pending: HashSet<PathBuf>,
+    #[serde(skip)]
+    tx_blurred: Sender<BlurResult>,
+    #[serde(skip)]
+    rx_blurred: Receiver<BlurResult>,
+}
+
+impl Default for ContentFilters {
+    fn default() -> Self {
+        let (tx_blurred, rx_blurred) = unbounded();
+        Self {
+            rules: Vec::new(),
+            classifier: NsfwSettings::default(),
+            nsfw_action: None,
+            nsfw_threshold: 0.8,
+            classifier_error: None,
+            revealed: HashSet::new(),
+            new_tag: String::new(),
+            blurred: LruCache::new(NonZeroUsize::new(Self::BLUR_CACHE_SIZE).expect("nonzero")),
+            pending: HashSet::new(),
+            tx_blurred,
+            rx_blurred,
+        }
+    }
+}
+
+fn blur(path: &Path) -> Result<ColorImage, String> {
+    let img = open_image(path)
+        .map_err(|e| e.to_string())?
+        .thumbnail(256, 256);
+    // Note: enough to make out the overall colors, but not what the work shows.
+    let img = img
+        .fast_blur(img.width().max(img.height()) as f32 / 12.)
+        .to_rgba8();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [img.width() as usize, img.height() as usize],
+        img.as_raw(),
+    ))
+}
+
+impl ContentFilters {
+    const BLUR_CACHE_SIZE: usize = 256;
+
+    pub fn compile(&self, tags: Option<&HashMap<TagId, DbTag>>) -> CompiledFilters {
+        let mut compiled = CompiledFilters {
+            nsfw: self.nsfw_action.map(|action| (action, self.nsfw_threshold)),
+            ..CompiledFilters::default()
+        };
+        let Some(tags) = tags else {
+            return compiled;
+        };
+        let by_name = tags
+            .values()
+            .map(|tag| (tag.name(), tag.id()))
+            .collect::<HashMap<_, _>>();
+        for rule in self.rules.iter().filter(|rule| rule.enabled) {
+            if let Some(tag_id) = by_name.get(rule.tag.as_str()) {
+                let target = match rule.action {
+                    FilterAction::Hide => &mut compiled.hide,
+                    FilterAction::Blur => &mut compiled.blur,
+                };
+                // Note: if two rules disagree, the one without the exception wins.
+                let except = target.entry(*tag_id).or_insert(rule.except_favorites);
+                *except &= rule.except_favorites;
+            }
+        }
+        compiled
+    }
+
+    // Note: thumbnails are scored as they are made, so the model has to be ready before any are.
+    pub fn load_classifier(&mut self) {
+        self.classifier_error = nsfw::configure(&self.classifier)
+            .err()
+            .map(|e| format!("{e:#}"));
+        if let Some(e) = &self.classifier_error {
+            error!("Failed to load the NSFW classifier: {e}");
+        }
+    }
+
+    pub fn is_revealed(&self, work_id: WorkId) -> bool {
+        self.revealed.contains(&work_id)
+    }
+
+    pub fn reveal(&mut self, work_id: WorkId) {
+        self.revealed.insert(work_id);
+    }
+
+    // A blurred copy of the preview at `path`, once it is ready.
+    pub fn blurred_image<'a>(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+    ) -> Option<egui::Image<'a>> {
+        while let Ok((path, result)) = self.rx_blurred.try_recv() {
+            self.pending.remove(&path);
+            match result {
+                Ok(pixels) => {
+                    let name = format!("blurred://{}", path.display());
+                    let handle = ctx.load_texture(name, pixels, TextureOptions::LINEAR);
+                    self.blurred.put(path, handle);
+                }
+                Err(e) => warn!("Failed to blur {}: {e}", path.display()),
+            }
+        }
+
+        if let Some(handle) = self.blurred.get(path) {
+            return Some(egui::Image::from_texture(SizedTexture::from_handle(handle)));
+        }
+        if self.pending.insert(path.to_owned()) {
+            let path = path.to_owned();
+            let tx = self.tx_blurred.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let result = blur(&path);
+                tx.send((path, result)).ok();
+                ctx.request_repaint();
+            });
+        }
+        None
+    }
+
+    // Returns true if the rules changed, and the gallery needs to be re-filtered.
+    pub fn ui(&mut self, tags: Option<&HashMap<TagId, DbTag>>, ui: &mut egui::Ui) -> bool {
+        let prior = self.rules.clone();
+        let mut remove = None;
+        egui::Grid::new("content_filter_rules")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, rule) in self.rules.iter_mut().enumerate() {
+                    ui.checkbox(&mut rule.enabled, "");
+                    let known = tags.is_none_or(|tags| tags.values().any(|t| t.name() == rule.tag));
+                    if known {
+                        ui.label(&rule.tag);
+                    } else {
+                        ui.label(&rule.tag)
+                            .on_hover_text("No tag with this name yet");
+                    }
+                    egui::ComboBox::from_id_salt(("content_filter_action", i))
+                        .selected_text(match rule.action {
+                            FilterAction::Hide => "Hide works",
+                            FilterAction::Blur => "Blur previews",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut rule.action, FilterAction::Hide, "Hide works");
+                            ui.selectable_value(
+                                &mut rule.action,
+                                FilterAction::Blur,
+                                "Blur previews",
+                            );
+                        });
+                    ui.checkbox(&mut rule.except_favorites, "unless a favorite");
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = remove {
+            self.rules.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_tag).hint_text("tag name"));
+            let name = self.new_tag.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Add Rule"))
+                .clicked()
+            {
+                self.rules.push(FilterRule {
+                    enabled: true,
+                    tag: name.to_owned(),
+                    action: FilterAction::Hide,
+                    except_favorites: true,
+                });
+                self.new_tag.clear();
+            }
+        });
+        let nsfw_changed = self.classifier_ui(ui);
+        self.rules != prior || nsfw_changed
+    }
+
+    // Returns true if scored works should be handled differently.
+    fn classifier_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.add_space(4.);
+        ui.label(egui::RichText::new("NSFW Classifier").strong());
+        if !nsfw::AVAILABLE {
+            ui.label(
+                "This build can't classify works; build Artchiver with `--features detection` \
+                 to turn it on.",
+            );
+            return false;
+        }
+        let prior = (self.nsfw_action, self.nsfw_threshold);
+        if ui
+            .checkbox(
+                &mut self.classifier.enabled,
+                "Score works with an NSFW classifier as their thumbnails are made",
+            )
+            .changed()
+        {
+            self.load_classifier();
+        }
+        egui::Grid::new("nsfw_classifier")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Model");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.classifier.model_path)
+                        .hint_text("/path/to/classifier.onnx")
+                        .desired_width(280.),
+                );
+                ui.end_row();
+                ui.label("Input size");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.classifier.input_size).range(32..=1024));
+                    ui.checkbox(&mut self.classifier.channels_last, "channels last")
+                        .on_hover_text("For Keras models, which take [1, height, width, 3]");
+                });
+                ui.end_row();
+                ui.label("NSFW outputs");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.classifier.nsfw_outputs)
+                        .hint_text("e.g. 1, 3, 4")
+                        .desired_width(120.),
+                )
+                .on_hover_text("Which of the model's outputs are NSFW, counting from 0");
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.classifier.enabled, egui::Button::new("Load Model"))
+                .clicked()
+            {
+                self.load_classifier();
+            }
+            match &self.classifier_error {
+                Some(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                None if nsfw::is_enabled() => {
+                    ui.label("loaded; rescan thumbnails in Data to score older works");
+                }
+                None => {}
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Works scoring at least");
+            ui.add(egui::Slider::new(&mut self.nsfw_threshold, 0.05..=1.0));
+            let label = |action: Option<FilterAction>| match action {
+                None => "are left alone",
+                Some(FilterAction::Hide) => "are hidden",
+                Some(FilterAction::Blur) => "are blurred until clicked",
+            };
+            egui::ComboBox::from_id_salt("nsfw_action")
+                .selected_text(label(self.nsfw_action))
+                .show_ui(ui, |ui| {
+                    for action in [None, Some(FilterAction::Blur), Some(FilterAction::Hide)] {
+                        ui.selectable_value(&mut self.nsfw_action, action, label(action));
+                    }
+                });
+        });
+        prior != (self.nsfw_action, self.nsfw_threshold)
+    }
+}
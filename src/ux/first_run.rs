@@ -0,0 +1,280 @@
+use crate::{
+    db::models::tag::{DbTag, TagId},
+    plugin::host::PluginHost,
+    ux::plugin::configuration_rows,
+};
+use artchiver_sdk::PluginMetadata;
+use itertools::Itertools as _;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FirstRunStep {
+    #[default]
+    DataDir,
+    Plugins,
+    Configure,
+    StarterTag,
+    Finished,
+}
+
+impl FirstRunStep {
+    fn next(&self) -> Self {
+        match self {
+            Self::DataDir => Self::Plugins,
+            Self::Plugins => Self::Configure,
+            Self::Configure => Self::StarterTag,
+            Self::StarterTag | Self::Finished => Self::Finished,
+        }
+    }
+}
+
+// Sets up a new archive: where it lives, which plugins to use, and something to look at. The
+// tutorial takes over from there, to show how to get around.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UxFirstRun {
+    step: FirstRunStep,
+    #[serde(skip)]
+    tag_search: String,
+    #[serde(skip)]
+    fetching_tags: bool,
+    // Plugins turned back on here, which won't show up until they are loaded on restart.
+    #[serde(skip)]
+    enabled_on_restart: Vec<String>,
+}
+
+impl UxFirstRun {
+    const MAX_STARTER_TAGS: usize = 20;
+
+    // Archives from before the wizard existed have already been set up.
+    pub fn finished() -> Self {
+        Self {
+            step: FirstRunStep::Finished,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step != FirstRunStep::Finished
+    }
+
+    // Returns the tag picked to start with, for the gallery to show.
+    pub fn ui(
+        &mut self,
+        data_dir: &Path,
+        host: &mut PluginHost,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        ctx: &egui::Context,
+    ) -> Option<DbTag> {
+        let mut picked = None;
+        egui::Window::new("Set Up Artchiver")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                match self.step {
+                    FirstRunStep::DataDir => Self::data_dir_ui(data_dir, ui),
+                    FirstRunStep::Plugins => self.plugins_ui(host, ui),
+                    FirstRunStep::Configure => Self::configure_ui(host, ui),
+                    FirstRunStep::StarterTag => picked = self.starter_tag_ui(host, tags, ui),
+                    FirstRunStep::Finished => {}
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if self.step != FirstRunStep::StarterTag && ui.button("Next").clicked() {
+                        if self.step == FirstRunStep::Configure {
+                            Self::apply_configuration(host);
+                        }
+                        self.step = self.step.next();
+                    }
+                    let skip = if self.step == FirstRunStep::StarterTag {
+                        "Skip"
+                    } else {
+                        "Skip Setup"
+                    };
+                    if ui.button(skip).clicked() {
+                        self.step = FirstRunStep::Finished;
+                    }
+                });
+            });
+        if picked.is_some() {
+            self.step = FirstRunStep::Finished;
+        }
+        picked
+    }
+
+    fn data_dir_ui(data_dir: &Path, ui: &mut egui::Ui) {
+        ui.heading("Welcome to Artchiver");
+        ui.separator();
+        ui.label("Artchiver keeps everything it collects in its data directory: the database of works and tags, your settings, and the downloaded files.");
+        ui.label("");
+        ui.horizontal(|ui| {
+            ui.label(format!("Data directory: {}", data_dir.display()));
+            if ui.small_button("Open").clicked()
+                && let Err(e) = open::that(data_dir)
+            {
+                error!("Failed to open {}: {e}", data_dir.display());
+            }
+        });
+        ui.label("");
+        ui.label("Collections get big. If this drive is short on space, move the data directory, or put downloads on other drives, from Preferences > Storage; you can do this at any time.");
+        ui.label("");
+        ui.label("To keep separate archives, e.g. for work and for home, add a profile from File > Profile.");
+    }
+
+    fn plugins_ui(&mut self, host: &mut PluginHost, ui: &mut egui::Ui) {
+        ui.heading("Plugins");
+        ui.separator();
+        ui.label("Plugins fetch art from each source. These came with Artchiver; turn off any that you don't want.");
+        ui.label("");
+        let mut disable = None;
+        for plugin in host.plugins() {
+            let mut enabled = true;
+            if ui.checkbox(&mut enabled, plugin.name()).changed() {
+                disable = Some(plugin.source().to_owned());
+            }
+            ui.weak(plugin.description());
+        }
+        let mut enable = None;
+        for file_name in host.disabled_plugins() {
+            let mut enabled = false;
+            if ui.checkbox(&mut enabled, file_name).changed() {
+                enable = Some(file_name.to_owned());
+            }
+        }
+        for file_name in &self.enabled_on_restart {
+            ui.add_enabled(false, egui::Checkbox::new(&mut true, file_name))
+                .on_disabled_hover_text("Loads the next time Artchiver starts");
+        }
+        if host.plugins().next().is_none()
+            && host.disabled_plugins().next().is_none()
+            && self.enabled_on_restart.is_empty()
+        {
+            ui.label("No plugins were found. Put them in the plugins folder next to Artchiver and restart.");
+        }
+        if let Some(source) = disable
+            && let Err(e) = host.disable_plugin(&source)
+        {
+            error!("Failed to stop the plugin at {}: {e}", source.display());
+        }
+        if let Some(file_name) = enable {
+            host.enable_plugin(&file_name);
+            self.enabled_on_restart.push(file_name);
+        }
+    }
+
+    fn has_required(meta: &PluginMetadata) -> bool {
+        meta.configurations()
+            .iter()
+            .any(|(key, _)| meta.is_required(key))
+    }
+
+    fn configure_ui(host: &mut PluginHost, ui: &mut egui::Ui) {
+        ui.heading("API Keys");
+        ui.separator();
+        if !host
+            .plugins()
+            .any(|plugin| plugin.metadata().is_some_and(Self::has_required))
+        {
+            ui.label("None of your plugins need anything else to get started.");
+            return;
+        }
+        ui.label("Some sources need an API key or an account before their plugin can fetch anything. The source's website says how to get one.");
+        ui.label("");
+        egui::Grid::new("first_run_configuration")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for plugin in host.plugins_mut() {
+                    let name = plugin.name();
+                    let Some(meta) = plugin.metadata_mut() else {
+                        continue;
+                    };
+                    if !Self::has_required(meta) {
+                        continue;
+                    }
+                    ui.strong(name);
+                    ui.end_row();
+                    configuration_rows(meta, true, ui);
+                }
+            });
+        ui.label("");
+        if host.plugins().any(|plugin| {
+            plugin
+                .metadata()
+                .is_some_and(PluginMetadata::is_missing_configuration)
+        }) {
+            ui.weak("Plugins without the starred settings won't be able to fetch anything.");
+        }
+        ui.weak("You can change these later under each plugin's Details.");
+    }
+
+    fn apply_configuration(host: &PluginHost) {
+        for plugin in host.plugins() {
+            if plugin.metadata().is_some_and(Self::has_required)
+                && let Err(e) = plugin.apply_configuration()
+            {
+                error!("Failed to configure {}: {e}", plugin.name());
+            }
+        }
+    }
+
+    fn starter_tag_ui(
+        &mut self,
+        host: &mut PluginHost,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        ui: &mut egui::Ui,
+    ) -> Option<DbTag> {
+        ui.heading("Something to Look At");
+        ui.separator();
+        ui.label("Works are found by their tags: an artist, a subject, a style. Pick a tag to fetch the works for, to start your archive with.");
+        ui.label("");
+        let tags = tags.filter(|tags| !tags.is_empty());
+        let Some(tags) = tags else {
+            if self.fetching_tags {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Fetching the tags that your plugins know about...");
+                });
+            } else if ui.button("⟳ Fetch Tags").clicked() {
+                for plugin in host.plugins_mut() {
+                    plugin.refresh_tags();
+                }
+                self.fetching_tags = true;
+            }
+            return None;
+        };
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            ui.text_edit_singleline(&mut self.tag_search);
+        });
+        let search = self.tag_search.to_lowercase();
+        let mut picked = None;
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                // Note: the biggest tags first, as the likeliest to have something good in them.
+                for tag in tags
+                    .values()
+                    .filter(|tag| !tag.hidden() && tag.name().to_lowercase().contains(&search))
+                    .sorted_by_key(|tag| std::cmp::Reverse(tag.network_count()))
+                    .take(Self::MAX_STARTER_TAGS)
+                {
+                    if ui
+                        .button(format!("{} ({})", tag.name(), tag.network_count()))
+                        .clicked()
+                    {
+                        picked = Some(tag.clone());
+                    }
+                }
+            });
+        if let Some(tag) = &picked
+            && let Err(e) = host.request_refresh_works_for_tag(tag)
+        {
+            error!("Failed to refresh {}: {e}", tag.name());
+        }
+        picked
+    }
+}
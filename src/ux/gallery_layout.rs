@@ -0,0 +1,269 @@
+// Where each work goes in the gallery. Every mode places works top to bottom, so the top edges
+// of the cells never decrease with the offset, and we can find the visible works with a pair of
+// binary searches however many works there are.
+use egui::{Rangef, Rect, Vec2, pos2};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GalleryMode {
+    // Square cells, with the work fit inside.
+    #[default]
+    Grid,
+    // Rows of equal height, with the widths of the works stretched to fill each row.
+    Justified,
+    // Columns of equal width, with each work going into the shortest column.
+    Masonry,
+}
+
+impl GalleryMode {
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut selected = match self {
+            Self::Grid => 0,
+            Self::Justified => 1,
+            Self::Masonry => 2,
+        };
+        let labels = ["Grid", "Justified", "Masonry"];
+        egui::ComboBox::new("gallery_mode", "")
+            .wrap_mode(egui::TextWrapMode::Truncate)
+            .show_index(ui, &mut selected, labels.len(), |i| labels[i]);
+        let next = match selected {
+            0 => Self::Grid,
+            1 => Self::Justified,
+            2 => Self::Masonry,
+            _ => panic!("invalid gallery mode selected"),
+        };
+        let changed = *self != next;
+        *self = next;
+        changed
+    }
+}
+
+// What the layout needs to know about each work, in gallery order.
+pub struct LayoutItem {
+    // The heading of the group the work is in, if the gallery is grouped.
+    pub group: Option<String>,
+    // Width over height, if we know it.
+    pub aspect: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GalleryLayout {
+    mode: GalleryMode,
+    width: f32,
+    size: f32,
+
+    // The cell of each work, relative to the top left of the gallery.
+    cells: Vec<Rect>,
+    tallest: f32,
+    // The group headings, in order.
+    headings: Vec<(Rect, String)>,
+    height: f32,
+}
+
+impl GalleryLayout {
+    pub const HEADING_HEIGHT: f32 = 28.;
+    // Note: keep panoramas and scrolls from taking over a whole row or column.
+    const ASPECT_RANGE: (f32, f32) = (0.25, 4.);
+
+    pub fn build(
+        mode: GalleryMode,
+        items: impl Iterator<Item = LayoutItem>,
+        width: f32,
+        size: f32,
+    ) -> Self {
+        let mut layout = Self {
+            mode,
+            width,
+            size,
+            ..Default::default()
+        };
+        let n_wide = (width / size).floor().max(1.) as usize;
+        let col_width = width / n_wide as f32;
+
+        // The works placed but not yet laid out in the current row, for grid and justified.
+        let mut row: Vec<f32> = Vec::new();
+        // The bottom of each masonry column.
+        let mut columns = vec![0f32; n_wide];
+        let mut top = 0f32;
+        let mut current: Option<String> = None;
+        for item in items {
+            if item.group.is_some() && item.group != current {
+                top = layout
+                    .finish_row(&mut row, top, false)
+                    .max(columns_bottom(&columns));
+                if let Some(group) = item.group {
+                    let rect =
+                        Rect::from_min_size(pos2(0., top), Vec2::new(width, Self::HEADING_HEIGHT));
+                    layout.headings.push((rect, group.clone()));
+                    current = Some(group);
+                }
+                top += Self::HEADING_HEIGHT;
+                columns.fill(top);
+            }
+            let aspect = item
+                .aspect
+                .filter(|aspect| aspect.is_finite() && *aspect > 0.)
+                .unwrap_or(1.)
+                .clamp(Self::ASPECT_RANGE.0, Self::ASPECT_RANGE.1);
+            match mode {
+                GalleryMode::Grid => {
+                    row.push(1.);
+                    if row.len() >= n_wide {
+                        top = layout.finish_row(&mut row, top, true);
+                    }
+                }
+                GalleryMode::Justified => {
+                    row.push(aspect);
+                    if row.iter().sum::<f32>() * size >= width {
+                        top = layout.finish_row(&mut row, top, true);
+                    }
+                }
+                GalleryMode::Masonry => {
+                    let (column, column_top) = columns
+                        .iter()
+                        .copied()
+                        .enumerate()
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .unwrap_or((0, top));
+                    let height = col_width / aspect;
+                    layout.push_cell(Rect::from_min_size(
+                        pos2(column as f32 * col_width, column_top),
+                        Vec2::new(col_width, height),
+                    ));
+                    columns[column] = column_top + height;
+                }
+            }
+        }
+        top = layout.finish_row(&mut row, top, false);
+        layout.height = top.max(columns_bottom(&columns));
+        layout
+    }
+
+    fn push_cell(&mut self, cell: Rect) {
+        self.tallest = self.tallest.max(cell.height());
+        self.cells.push(cell);
+    }
+
+    // Lay out the pending row at `top`; returns the top of the next row. Rows that end early,
+    // at the end of a group or of the gallery, keep their natural size rather than stretching.
+    fn finish_row(&mut self, row: &mut Vec<f32>, top: f32, full: bool) -> f32 {
+        if row.is_empty() {
+            return top;
+        }
+        let height = match self.mode {
+            GalleryMode::Justified if full => self.width / row.iter().sum::<f32>(),
+            _ => self.size,
+        };
+        let mut left = 0.;
+        for aspect in row.drain(..) {
+            let width = match self.mode {
+                GalleryMode::Grid => self.size,
+                _ => aspect * height,
+            };
+            self.push_cell(Rect::from_min_size(
+                pos2(left, top),
+                Vec2::new(width, height),
+            ));
+            left += width;
+        }
+        top + height
+    }
+
+    pub fn matches(&self, mode: GalleryMode, width: f32, size: f32, n_works: usize) -> bool {
+        self.mode == mode && self.width == width && self.size == size && self.cells.len() == n_works
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn cell(&self, offset: usize) -> Option<Rect> {
+        self.cells.get(offset).copied()
+    }
+
+    // The offsets of the works that may be in the vertical span, in order.
+    pub fn works_in(&self, span: Rangef) -> Range<usize> {
+        let start = self
+            .cells
+            .partition_point(|cell| cell.top() + self.tallest < span.min);
+        let end = self.cells.partition_point(|cell| cell.top() <= span.max);
+        start..end.max(start)
+    }
+
+    pub fn headings_in(&self, span: Rangef) -> impl Iterator<Item = &(Rect, String)> {
+        self.headings
+            .iter()
+            .filter(move |(rect, _)| rect.y_range().intersects(span))
+    }
+
+    // The heading of the group that is showing at the given height, for pinning to the top.
+    pub fn group_at(&self, y: f32) -> Option<&str> {
+        let i = self.headings.partition_point(|(rect, _)| rect.top() <= y);
+        i.checked_sub(1).map(|i| self.headings[i].1.as_str())
+    }
+}
+
+fn columns_bottom(columns: &[f32]) -> f32 {
+    columns.iter().copied().fold(0., f32::max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GalleryLayout, GalleryMode, LayoutItem};
+    use egui::Rangef;
+
+    fn items(groups: &[&str], aspect: f32) -> impl Iterator<Item = LayoutItem> {
+        groups.iter().map(move |group| LayoutItem {
+            group: (!group.is_empty()).then(|| (*group).to_owned()),
+            aspect: Some(aspect),
+        })
+    }
+
+    #[test]
+    fn test_grid_groups_start_new_rows() {
+        let groups = ["a", "a", "a", "b", "c", "c"];
+        let layout = GalleryLayout::build(GalleryMode::Grid, items(&groups, 2.), 200., 100.);
+        let tops = (0..groups.len())
+            .map(|i| layout.cell(i).map(|cell| cell.top()))
+            .collect::<Vec<_>>();
+        let h = GalleryLayout::HEADING_HEIGHT;
+        assert_eq!(
+            tops,
+            [0., 0., 100., 200. + h, 300. + 2. * h, 300. + 2. * h]
+                .map(|top| Some(top + h))
+                .to_vec()
+        );
+        assert_eq!(layout.height(), 400. + 3. * h);
+        assert_eq!(layout.group_at(250. + h), Some("b"));
+        assert_eq!(layout.works_in(Rangef::new(0., 50.)), 0..2);
+    }
+
+    #[test]
+    fn test_justified_rows_fill_the_width() {
+        let layout = GalleryLayout::build(GalleryMode::Justified, items(&[""; 5], 1.5), 400., 100.);
+        // Three at 150px overflow 400px, so the row shrinks to fit them exactly.
+        let row = (0..3).filter_map(|i| layout.cell(i)).collect::<Vec<_>>();
+        let width = row.iter().map(|cell| cell.width()).sum::<f32>();
+        assert!((width - 400.).abs() < 0.01);
+        assert!(row.iter().all(|cell| cell.height() < 100.));
+        // The last row is left at its natural size.
+        assert_eq!(layout.cell(4).map(|cell| cell.height()), Some(100.));
+    }
+
+    #[test]
+    fn test_masonry_tops_are_ordered() {
+        let items = [1., 0.5, 2., 1., 0.3, 1.]
+            .into_iter()
+            .map(|aspect| LayoutItem {
+                group: None,
+                aspect: Some(aspect),
+            });
+        let layout = GalleryLayout::build(GalleryMode::Masonry, items, 300., 100.);
+        let tops = (0..6)
+            .filter_map(|i| layout.cell(i).map(|cell| cell.top()))
+            .collect::<Vec<_>>();
+        assert!(tops.is_sorted());
+        assert_eq!(layout.works_in(Rangef::new(0., 1.)), 0..3);
+    }
+}
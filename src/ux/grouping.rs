@@ -0,0 +1,84 @@
+// Optional sections for the works gallery. Each group starts on a fresh row under a heading;
+// see GalleryLayout for where those go.
+use crate::db::models::{
+    tag::{DbTag, TagId},
+    work::DbWork,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum WorkGrouping {
+    #[default]
+    None,
+    Year,
+    Decade,
+    Artist,
+    Plugin,
+}
+
+impl WorkGrouping {
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut selected = match self {
+            Self::None => 0,
+            Self::Year => 1,
+            Self::Decade => 2,
+            Self::Artist => 3,
+            Self::Plugin => 4,
+        };
+        let labels = ["None", "Year", "Decade", "Artist", "Source"];
+        egui::ComboBox::new("work_grouping", "")
+            .wrap_mode(egui::TextWrapMode::Truncate)
+            .show_index(ui, &mut selected, labels.len(), |i| labels[i]);
+        let next = match selected {
+            0 => Self::None,
+            1 => Self::Year,
+            2 => Self::Decade,
+            3 => Self::Artist,
+            4 => Self::Plugin,
+            _ => panic!("invalid grouping selected"),
+        };
+        let changed = *self != next;
+        *self = next;
+        changed
+    }
+
+    // The heading for the group that the work belongs in.
+    pub fn label(&self, work: &DbWork, tags: Option<&HashMap<TagId, DbTag>>) -> Option<String> {
+        match self {
+            Self::None => None,
+            Self::Year => Some(work.date().year().to_string()),
+            Self::Decade => Some(format!("{}s", work.date().year().div_euclid(10) * 10)),
+            Self::Artist => Some(
+                work.history()
+                    .and_then(|history| history.attribution())
+                    .filter(|artist| !artist.is_empty())
+                    .unwrap_or("Unknown Artist")
+                    .to_owned(),
+            ),
+            Self::Plugin => Some(source(work, tags).unwrap_or("Unknown Source").to_owned()),
+        }
+    }
+
+    // Dates already sort into years and decades; other groupings have to be gathered together
+    // before the sort order applies within them.
+    pub fn compare(
+        &self,
+        a: &DbWork,
+        b: &DbWork,
+        tags: Option<&HashMap<TagId, DbTag>>,
+    ) -> Ordering {
+        match self {
+            Self::None | Self::Year | Self::Decade => Ordering::Equal,
+            Self::Artist => a.artist_sort_key().cmp(&b.artist_sort_key()),
+            Self::Plugin => source(a, tags).cmp(&source(b, tags)),
+        }
+    }
+}
+
+// The plugin that the work came from, going by its tags.
+fn source<'a>(work: &DbWork, tags: Option<&'a HashMap<TagId, DbTag>>) -> Option<&'a str> {
+    work.tags()
+        .filter_map(|id| tags?.get(&id))
+        .find_map(|tag| tag.sources().find(|name| !name.is_empty()))
+}
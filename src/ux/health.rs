@@ -0,0 +1,283 @@
+use crate::{
+    db::{
+        models::link_check::LostLink,
+        scrub::{SCRUB_CYCLE_WEEKS, ScrubReport},
+        writer::DbWriteHandle,
+    },
+    plugin::host::PluginHost,
+    shared::{
+        progress::{Progress, UpdateSource},
+        update::DataUpdate,
+    },
+};
+use jiff::{SignedDuration, Timestamp, tz::TimeZone};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxHealth {
+    // Preferences
+    scrub_weekly: bool,
+    check_links_weekly: bool,
+
+    last_scrub: Option<Timestamp>,
+    last_report: Option<ScrubReport>,
+    last_link_check: Option<Timestamp>,
+    // Works whose urls are dead, that no plugin could find again.
+    lost_links: Vec<LostLink>,
+
+    #[serde(skip)]
+    in_progress: bool,
+    #[serde(skip)]
+    progress: Progress,
+    // Repairs we have asked for, by stored path, and any error from the last attempt.
+    #[serde(skip)]
+    repairs: HashMap<String, Option<String>>,
+}
+
+impl Default for UxHealth {
+    fn default() -> Self {
+        Self {
+            scrub_weekly: true,
+            check_links_weekly: true,
+            last_scrub: None,
+            last_report: None,
+            last_link_check: None,
+            lost_links: Vec::new(),
+            in_progress: false,
+            progress: Progress::None,
+            repairs: HashMap::new(),
+        }
+    }
+}
+
+impl UxHealth {
+    const SCRUB_INTERVAL: SignedDuration = SignedDuration::from_hours(24 * 7);
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::ScrubCompleted(report) => {
+                    self.in_progress = false;
+                    self.progress = Progress::None;
+                    self.last_report = Some(report.clone());
+                }
+                DataUpdate::FileRepaired {
+                    stored_path,
+                    error: None,
+                } => {
+                    if let Some(report) = &mut self.last_report {
+                        report
+                            .corrupt
+                            .retain(|file| &file.stored_path != stored_path);
+                    }
+                    self.repairs.remove(stored_path);
+                }
+                DataUpdate::FileRepaired {
+                    stored_path,
+                    error: Some(err),
+                } => {
+                    self.repairs.insert(stored_path.clone(), Some(err.clone()));
+                }
+                DataUpdate::LostLinks(lost) => {
+                    self.lost_links = lost.clone();
+                }
+                DataUpdate::Progress { source, progress }
+                    if self.in_progress && source == &UpdateSource::DbWriter =>
+                {
+                    self.progress = *progress;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn corrupt_count(&self) -> usize {
+        self.last_report
+            .as_ref()
+            .map_or(0, |report| report.corrupt.len())
+    }
+
+    fn scrub_now(&mut self, db_write: &DbWriteHandle) {
+        self.last_scrub = Some(Timestamp::now());
+        match db_write.scrub_files() {
+            Ok(()) => self.in_progress = true,
+            Err(e) => error!("Failed to request integrity scrub: {e}"),
+        }
+    }
+
+    fn check_links_now(&mut self, host: &mut PluginHost) {
+        self.last_link_check = Some(Timestamp::now());
+        host.check_links();
+    }
+
+    pub fn tick(&mut self, db_write: &DbWriteHandle, host: &mut PluginHost) {
+        let due = |last: Option<Timestamp>| {
+            !last.is_some_and(|last| Timestamp::now().duration_since(last) < Self::SCRUB_INTERVAL)
+        };
+        if self.scrub_weekly && !self.in_progress && due(self.last_scrub) {
+            self.scrub_now(db_write);
+        }
+        // Note: wait for the plugins to load, or the check would be used up on nothing.
+        if self.check_links_weekly && due(self.last_link_check) && host.plugins().next().is_some() {
+            self.check_links_now(host);
+        }
+    }
+
+    pub fn ui(&mut self, db_write: &DbWriteHandle, host: &mut PluginHost, ui: &mut egui::Ui) {
+        self.links_ui(host, ui);
+        ui.separator();
+        self.integrity_ui(db_write, ui);
+    }
+
+    fn links_ui(&mut self, host: &mut PluginHost, ui: &mut egui::Ui) {
+        ui.heading("Links");
+        ui.checkbox(
+            &mut self.check_links_weekly,
+            "Check a sample of links for works that are not downloaded every week",
+        );
+        ui.horizontal(|ui| {
+            match self.last_link_check {
+                Some(last) => ui.label(format!(
+                    "Last checked {}",
+                    last.to_zoned(TimeZone::system()).strftime("%Y-%m-%d %H:%M")
+                )),
+                None => ui.label("Never checked"),
+            };
+            if ui.button("Check Now").clicked() {
+                self.check_links_now(host);
+            }
+        });
+        if self.lost_links.is_empty() {
+            ui.label("No lost works found.");
+            return;
+        }
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!(
+                "{} works are gone from their source and were never downloaded",
+                self.lost_links.len()
+            ),
+        );
+        egui::ScrollArea::vertical()
+            .id_salt("lost_links_scroll")
+            .max_height(200.)
+            .show(ui, |ui| {
+                egui::Grid::new("lost_links_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for lost in &self.lost_links {
+                            ui.label(&lost.work_name);
+                            ui.label(lost.plugin.as_deref().unwrap_or("unknown plugin"));
+                            match lost.http_status {
+                                Some(status) => ui.label(format!("HTTP {status}")),
+                                None => ui.label(""),
+                            };
+                            ui.add(egui::Label::new(&lost.screen_url).truncate())
+                                .on_hover_text(format!(
+                                    "Checked {}",
+                                    lost.checked_at
+                                        .to_zoned(TimeZone::system())
+                                        .strftime("%Y-%m-%d %H:%M")
+                                ));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn integrity_ui(&mut self, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.heading("Integrity");
+        ui.checkbox(
+            &mut self.scrub_weekly,
+            format!(
+                "Check a slice of downloaded files every week (all files every {SCRUB_CYCLE_WEEKS} weeks)"
+            ),
+        );
+        ui.horizontal(|ui| {
+            match self.last_scrub {
+                Some(last) => ui.label(format!(
+                    "Last checked {}",
+                    last.to_zoned(TimeZone::system()).strftime("%Y-%m-%d %H:%M")
+                )),
+                None => ui.label("Never checked"),
+            };
+            if ui
+                .add_enabled(!self.in_progress, egui::Button::new("Check Now"))
+                .clicked()
+            {
+                self.scrub_now(db_write);
+            }
+        });
+        if self.in_progress {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                self.progress.ui(ui);
+            });
+        }
+
+        let Some(report) = &self.last_report else {
+            return;
+        };
+        if let Some(err) = &report.error {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {err}"));
+        }
+        ui.label(format!(
+            "Checked {} files last time; {} could not be reached. {} files have a known good hash.",
+            report.checked, report.skipped, report.hashed
+        ));
+        if report.corrupt.is_empty() {
+            ui.label("No corrupt files found.");
+            return;
+        }
+
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("{} files no longer match their hash", report.corrupt.len()),
+        );
+        let mut repair = None;
+        egui::ScrollArea::vertical()
+            .max_height(300.)
+            .show(ui, |ui| {
+                egui::Grid::new("corrupt_files_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for file in &report.corrupt {
+                            ui.label(&file.work_name);
+                            ui.label(file.kind.to_string());
+                            ui.add(egui::Label::new(&file.stored_path).truncate());
+                            match self.repairs.get(&file.stored_path) {
+                                Some(None) => {
+                                    ui.spinner();
+                                }
+                                Some(Some(err)) => {
+                                    if ui.small_button("Retry").on_hover_text(err).clicked() {
+                                        repair = Some(file.clone());
+                                    }
+                                }
+                                None => {
+                                    if ui
+                                        .small_button("Re-download")
+                                        .on_hover_text("Fetch a fresh copy from the original url")
+                                        .clicked()
+                                    {
+                                        repair = Some(file.clone());
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(file) = repair {
+            self.repairs.insert(file.stored_path.clone(), None);
+            if let Err(e) = db_write.repair_file(file) {
+                error!("Failed to request repair: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::db::models::tag::TagId;
+
+// Where the gallery was: the tags selected, the work selected, and how far down it was scrolled.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavEntry {
+    // Sorted, so that entries for the same tags compare equal.
+    pub enabled: Vec<TagId>,
+    pub disabled: Vec<TagId>,
+    pub selected: Option<String>,
+    pub scroll: f32,
+}
+
+// Back and forward through the tag selections, like a browser. Each change of tags is a step;
+// moving around within the same tags only updates where we are in the current step.
+#[derive(Clone, Debug, Default)]
+pub struct NavHistory {
+    back: Vec<NavEntry>,
+    forward: Vec<NavEntry>,
+    current: Option<NavEntry>,
+}
+
+impl NavHistory {
+    const MAX_DEPTH: usize = 100;
+
+    // Called each frame with where the gallery is. The selection and scroll are None while the
+    // gallery is loading, so that we don't remember the empty gallery in between.
+    pub fn observe(
+        &mut self,
+        mut enabled: Vec<TagId>,
+        mut disabled: Vec<TagId>,
+        selected: Option<&str>,
+        scroll: Option<f32>,
+    ) {
+        enabled.sort();
+        disabled.sort();
+        if let Some(current) = &mut self.current
+            && current.enabled == enabled
+            && current.disabled == disabled
+        {
+            if let Some(selected) = selected {
+                current.selected = Some(selected.to_owned());
+            }
+            if let Some(scroll) = scroll {
+                current.scroll = scroll;
+            }
+            return;
+        }
+        if let Some(prior) = self.current.take() {
+            self.back.push(prior);
+            if self.back.len() > Self::MAX_DEPTH {
+                self.back.remove(0);
+            }
+            self.forward.clear();
+        }
+        // Note: the selection from the prior tags may still be around for this frame.
+        self.current = Some(NavEntry {
+            enabled,
+            disabled,
+            selected: None,
+            scroll: 0.,
+        });
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    // The entry to restore; it becomes the current entry, so that restoring it is not a new step.
+    pub fn go_back(&mut self) -> Option<NavEntry> {
+        let entry = self.back.pop()?;
+        if let Some(current) = self.current.replace(entry.clone()) {
+            self.forward.push(current);
+        }
+        Some(entry)
+    }
+
+    pub fn go_forward(&mut self) -> Option<NavEntry> {
+        let entry = self.forward.pop()?;
+        if let Some(current) = self.current.replace(entry.clone()) {
+            self.back.push(current);
+        }
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[i64]) -> Vec<TagId> {
+        ids.iter().copied().map(TagId::wrap).collect()
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = NavHistory::default();
+        history.observe(ids(&[1]), vec![], None, None);
+        history.observe(ids(&[1]), vec![], Some("a.jpg"), Some(120.));
+        history.observe(ids(&[2, 1]), vec![], Some("a.jpg"), Some(0.));
+        history.observe(ids(&[1, 2]), ids(&[3]), None, None);
+        assert!(!history.can_go_forward());
+
+        let entry = history.go_back().expect("two steps back");
+        assert_eq!(entry.enabled, ids(&[1, 2]));
+        // The selection from the first tags was not carried into the second step.
+        assert_eq!(entry.selected, None);
+        let entry = history.go_back().expect("one step back");
+        assert_eq!(entry.selected.as_deref(), Some("a.jpg"));
+        assert_eq!(entry.scroll, 120.);
+        assert!(!history.can_go_back());
+
+        // Seeing the restored tags is not a new step.
+        history.observe(ids(&[1]), vec![], Some("a.jpg"), Some(120.));
+        assert!(history.can_go_forward());
+        assert_eq!(
+            history.go_forward().map(|entry| entry.enabled),
+            Some(ids(&[1, 2]))
+        );
+
+        // Going somewhere new drops the steps ahead.
+        history.go_back();
+        history.observe(ids(&[4]), vec![], None, None);
+        assert!(!history.can_go_forward());
+        assert!(history.can_go_back());
+    }
+}
@@ -0,0 +1,117 @@
+// The images we have asked egui to load, budgeted by their decoded size rather than by count,
+// so that a gallery of large thumbnails can't run away with the GPU's memory. egui keeps both
+// the decoded pixels and the texture for each one, so the real usage is about twice this.
+use egui::{SizeHint, load::ImagePoll};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageCache {
+    budget_mb: usize,
+
+    // The decoded size of each image; zero until it finishes loading.
+    #[serde(skip, default = "LruCache::unbounded")]
+    entries: LruCache<String, usize>,
+    #[serde(skip)]
+    loading: HashMap<String, SizeHint>,
+    #[serde(skip)]
+    bytes: usize,
+    #[serde(skip)]
+    evicted: usize,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self {
+            budget_mb: Self::DEFAULT_BUDGET_MB,
+            entries: LruCache::unbounded(),
+            loading: HashMap::new(),
+            bytes: 0,
+            evicted: 0,
+        }
+    }
+}
+
+impl ImageCache {
+    const DEFAULT_BUDGET_MB: usize = 1024;
+    // Note: never evict below this many, whatever the budget, so that at least the visible
+    //       works stay loaded.
+    const MIN_ENTRIES: usize = 64;
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.entries.contains(uri)
+    }
+
+    // Marks the image as recently used; returns false if we haven't loaded it.
+    pub fn touch(&mut self, uri: &str) -> bool {
+        self.entries.get(uri).is_some()
+    }
+
+    pub fn load(&mut self, ctx: &egui::Context, uri: String, hint: SizeHint) {
+        ctx.try_load_image(&uri, hint).ok();
+        self.loading.insert(uri.clone(), hint);
+        self.entries.put(uri, 0);
+    }
+
+    // Find out how big the images that finished loading are, then evict down to the budget.
+    pub fn flush(&mut self, ctx: &egui::Context) {
+        let mut finished = Vec::new();
+        for (uri, hint) in &self.loading {
+            // Note: the loaders cache by uri and hint, so this is only a lookup.
+            match ctx.try_load_image(uri, *hint) {
+                Ok(ImagePoll::Ready { image }) => {
+                    finished.push((uri.clone(), image.pixels.len() * 4))
+                }
+                Ok(ImagePoll::Pending { .. }) => {}
+                Err(_) => finished.push((uri.clone(), 0)),
+            }
+        }
+        for (uri, bytes) in finished {
+            self.loading.remove(&uri);
+            if let Some(entry) = self.entries.peek_mut(&uri) {
+                *entry = bytes;
+                self.bytes += bytes;
+            }
+        }
+
+        let budget = self.budget_mb.saturating_mul(1024 * 1024);
+        while self.bytes > budget && self.entries.len() > Self::MIN_ENTRIES {
+            let Some((uri, bytes)) = self.entries.pop_lru() else {
+                break;
+            };
+            self.bytes = self.bytes.saturating_sub(bytes);
+            self.loading.remove(&uri);
+            self.evicted += 1;
+            ctx.forget_image(&uri);
+        }
+    }
+
+    pub fn preferences_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Image cache budget");
+            ui.add(
+                egui::DragValue::new(&mut self.budget_mb)
+                    .range(64..=16 * 1024)
+                    .speed(16)
+                    .suffix(" MiB"),
+            )
+            .on_hover_text("Decoded image memory; the GPU holds about as much again in textures");
+        });
+    }
+
+    pub fn usage_ui(&self, ui: &mut egui::Ui) {
+        let used_mb = self.bytes as f32 / (1024. * 1024.);
+        ui.label(format!(
+            "Image cache: {used_mb:.0} of {} MiB in {} images ({} loading, {} evicted)",
+            self.budget_mb,
+            self.entries.len(),
+            self.loading.len(),
+            self.evicted,
+        ));
+        ui.add(egui::ProgressBar::new(
+            (used_mb / self.budget_mb.max(1) as f32).min(1.),
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+// The slideshow's info overlay: the resolution, format, and histogram of the work on screen, and
+// a loupe for peeking at individual pixels, for judging the quality of a scan.
+//
+// Note: decoding big scans takes a while, so it happens off of the UX thread, and only while the
+//       overlay is up. We keep the decoded image around for the pixel peek; only the current
+//       work's, so this is bounded to the largest work you look at with the overlay open.
+use crate::plugin::thumbnail::open_image;
+use crossbeam::channel::{Receiver, bounded};
+use egui::{Color32, Pos2, Rect, Shape, Stroke, Vec2};
+use image::{DynamicImage, GenericImageView as _, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+#[derive(Debug)]
+struct ImageInfo {
+    format: String,
+    file_size: u64,
+    image: DynamicImage,
+    histogram: [[u32; 256]; 3],
+}
+
+impl ImageInfo {
+    fn load(path: &Path) -> Result<Self, String> {
+        let file_size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+        let format = ImageFormat::from_path(path)
+            .map(|format| format!("{format:?}"))
+            .unwrap_or_else(|_| "Unknown".to_owned());
+        let image = open_image(path).map_err(|e| e.to_string())?;
+        let mut histogram = [[0u32; 256]; 3];
+        for pixel in image.to_rgb8().pixels() {
+            for (channel, value) in pixel.0.into_iter().enumerate() {
+                histogram[channel][usize::from(value)] += 1;
+            }
+        }
+        Ok(Self {
+            format,
+            file_size,
+            image,
+            histogram,
+        })
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxImageInfo {
+    show_overlay: bool,
+    show_loupe: bool,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    pending: Option<Receiver<Result<ImageInfo, String>>>,
+    #[serde(skip)]
+    info: Option<Arc<Result<ImageInfo, String>>>,
+}
+
+impl UxImageInfo {
+    const LOUPE_SIZE: f32 = 160.;
+    // Image pixels across the loupe.
+    const LOUPE_PIXELS: f32 = 32.;
+
+    pub fn toggle_overlay(&mut self) {
+        self.show_overlay = !self.show_overlay;
+    }
+
+    pub fn toggle_loupe(&mut self) {
+        self.show_loupe = !self.show_loupe;
+    }
+
+    fn ensure_loaded(&mut self, path: Option<&Path>, ctx: &egui::Context) {
+        if self.path.as_deref() != path {
+            self.path = path.map(Path::to_owned);
+            self.info = None;
+            self.pending = path.map(|path| {
+                let (tx, rx) = bounded(1);
+                let path = path.to_owned();
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    tx.send(ImageInfo::load(&path)).ok();
+                    ctx.request_repaint();
+                });
+                rx
+            });
+        }
+        if let Some(rx) = &self.pending
+            && let Ok(info) = rx.try_recv()
+        {
+            self.info = Some(Arc::new(info));
+            self.pending = None;
+        }
+    }
+
+    // `path` is the screen image on disk, if the work is an image; `rect` is where it was
+    // painted and `uri` what it was painted from.
+    pub fn ui(
+        &mut self,
+        (path, uri): (Option<&Path>, Option<&str>),
+        rect: Rect,
+        zoom: f32,
+        ctx: &egui::Context,
+    ) {
+        if !self.show_overlay && !self.show_loupe {
+            // Note: don't hang on to big images that nobody is looking at.
+            self.path = None;
+            self.pending = None;
+            self.info = None;
+            return;
+        }
+        self.ensure_loaded(path, ctx);
+        let info = self.info.clone();
+        let info = info.as_deref().and_then(|info| info.as_ref().ok());
+        let hover = ctx
+            .pointer_hover_pos()
+            .filter(|pos| rect.contains(*pos) && info.is_some());
+
+        if self.show_overlay {
+            egui::Area::new(egui::Id::new("image_info_overlay"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-8., -8.])
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        self.overlay_ui(info, (rect, zoom, hover), ui);
+                    });
+                });
+        }
+        if self.show_loupe
+            && let Some(pos) = hover
+            && let Some(info) = info
+            && let Some(uri) = uri
+        {
+            Self::loupe_ui(info, uri, rect, pos, ctx);
+        }
+    }
+
+    fn overlay_ui(
+        &self,
+        info: Option<&ImageInfo>,
+        (rect, zoom, hover): (Rect, f32, Option<Pos2>),
+        ui: &mut egui::Ui,
+    ) {
+        let Some(info) = info else {
+            match self.info.as_deref() {
+                Some(Err(e)) => ui.label(format!("Failed to read the image: {e}")),
+                _ if self.path.is_none() => ui.label("Not an image"),
+                _ => ui.spinner(),
+            };
+            return;
+        };
+        let (width, height) = info.image.dimensions();
+        ui.label(format!(
+            "{width} x {height}, {}, {}",
+            info.format,
+            format_size(info.file_size)
+        ));
+        ui.label(format!(
+            "{:?}, zoom {:.0}%, shown at {:.0}% of full size",
+            info.image.color(),
+            zoom * 100.,
+            rect.width() / width as f32 * 100.
+        ));
+        if let Some(pos) = hover {
+            let (x, y) = Self::pixel_at(info, rect, pos);
+            let [r, g, b, a] = info.image.get_pixel(x, y).0;
+            ui.label(format!("{x}, {y}: #{r:02x}{g:02x}{b:02x} alpha {a}"));
+        }
+
+        let (response, painter) = ui.allocate_painter(Vec2::new(256., 80.), egui::Sense::hover());
+        let bounds = response.rect;
+        painter.rect_filled(bounds, 2., Color32::from_black_alpha(160));
+        // Note: scale to the tallest bin that isn't at either end, since blown highlights and
+        //       crushed shadows would otherwise flatten everything else.
+        let tallest = info
+            .histogram
+            .iter()
+            .flat_map(|channel| channel[1..255].iter().copied())
+            .max()
+            .unwrap_or_default()
+            .max(1) as f32;
+        let colors = [Color32::RED, Color32::GREEN, Color32::LIGHT_BLUE];
+        for (channel, color) in info.histogram.iter().zip(colors) {
+            let points = channel
+                .iter()
+                .enumerate()
+                .map(|(i, count)| {
+                    let height = (*count as f32 / tallest).min(1.) * bounds.height();
+                    Pos2::new(bounds.left() + i as f32, bounds.bottom() - height)
+                })
+                .collect();
+            painter.add(Shape::line(points, Stroke::new(1., color)));
+        }
+    }
+
+    fn pixel_at(info: &ImageInfo, rect: Rect, pos: Pos2) -> (u32, u32) {
+        let (width, height) = info.image.dimensions();
+        let frac = (pos - rect.min) / rect.size();
+        (
+            ((frac.x * width as f32) as u32).min(width.saturating_sub(1)),
+            ((frac.y * height as f32) as u32).min(height.saturating_sub(1)),
+        )
+    }
+
+    fn loupe_ui(info: &ImageInfo, uri: &str, rect: Rect, pos: Pos2, ctx: &egui::Context) {
+        let (width, height) = info.image.dimensions();
+        let center = ((pos - rect.min) / rect.size()).to_pos2();
+        let uv = Rect::from_center_size(
+            center,
+            Vec2::new(
+                Self::LOUPE_PIXELS / width as f32,
+                Self::LOUPE_PIXELS / height as f32,
+            ),
+        );
+        egui::Area::new(egui::Id::new("image_info_loupe"))
+            .fixed_pos(pos + Vec2::splat(16.))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.add(
+                        egui::Image::new(uri)
+                            .uv(uv)
+                            .fit_to_exact_size(Vec2::splat(Self::LOUPE_SIZE)),
+                    );
+                });
+            });
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    db::{models::work::DbWork, reader::DbReadHandle, writer::DbWriteHandle},
+    shared::{storage::Storage, update::DataUpdate},
+};
+use egui::include_image;
+use itertools::Itertools as _;
+use log::error;
+
+enum InboxAction {
+    Keep,
+    Favorite,
+    Hide,
+}
+
+// Works that arrived since the user last looked, grouped by the tag they came in under.
+#[derive(Default)]
+pub struct UxInbox {
+    storage: Storage,
+    works: Vec<(String, DbWork)>,
+    is_loading: bool,
+}
+
+impl UxInbox {
+    const THUMB_SIZE: f32 = 96.;
+
+    pub fn startup(&mut self, storage: &Storage, db: &DbReadHandle) {
+        self.storage = storage.clone();
+        self.refresh(db);
+    }
+
+    fn refresh(&mut self, db: &DbReadHandle) {
+        self.is_loading = true;
+        db.get_inbox_works();
+    }
+
+    pub fn handle_updates(&mut self, db: &DbReadHandle, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::InboxWorks(works) => {
+                    self.is_loading = false;
+                    self.works = works.clone();
+                }
+                DataUpdate::WorksWereUpdatedForTag { new_works, .. } if *new_works > 0 => {
+                    self.refresh(db);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn review(&mut self, screen_urls: Vec<String>, db_write: &DbWriteHandle) {
+        self.works
+            .retain(|(_, work)| !screen_urls.iter().any(|url| url == work.screen_url()));
+        if let Err(e) = db_write.review_inbox_works(screen_urls) {
+            error!("Failed to mark works as reviewed: {e}");
+        }
+    }
+
+    fn apply(&mut self, work: &DbWork, action: &InboxAction, db_write: &DbWriteHandle) {
+        let result = match action {
+            InboxAction::Keep => Ok(()),
+            InboxAction::Favorite => db_write.set_work_favorite(work.id(), true),
+            InboxAction::Hide => db_write.set_work_hidden(work.id(), true),
+        };
+        if let Err(e) = result {
+            error!("Failed to update {}: {e}", work.name());
+        }
+        self.review(vec![work.screen_url().to_owned()], db_write);
+    }
+
+    fn thumbnail<'a>(&self, work: &DbWork) -> egui::Image<'a> {
+        match work.preview_path() {
+            Some(path) if self.storage.is_available(path) => {
+                egui::Image::new(format!("file://{}", self.storage.resolve(path).display()))
+            }
+            _ => egui::Image::new(include_image!("../../assets/loading-preview.png")),
+        }
+    }
+
+    pub fn ui(&mut self, db: &DbReadHandle, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} new works", self.works.len()));
+            if self.is_loading {
+                ui.spinner();
+            }
+            if ui.button("⟳ Refresh").clicked() {
+                self.refresh(db);
+            }
+            if ui
+                .add_enabled(!self.works.is_empty(), egui::Button::new("Clear Inbox"))
+                .on_hover_text("Keep everything and mark it all as reviewed")
+                .clicked()
+            {
+                self.works.clear();
+                if let Err(e) = db_write.clear_inbox() {
+                    error!("Failed to clear the inbox: {e}");
+                }
+            }
+        });
+        ui.separator();
+        if self.works.is_empty() {
+            ui.label("Nothing new since you last looked.");
+            return;
+        }
+
+        let mut action = None;
+        let mut keep_group = None;
+        let groups = self
+            .works
+            .iter()
+            .enumerate()
+            .into_group_map_by(|(_, (group, _))| group.clone());
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (group, works) in groups.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+                let title = if group.is_empty() { "Other" } else { &group };
+                egui::CollapsingHeader::new(format!("{title} ({})", works.len()))
+                    .id_salt(&group)
+                    .show(ui, |ui| {
+                        if ui.button("Keep All").clicked() {
+                            keep_group = Some(group.clone());
+                        }
+                        for (offset, (_, work)) in works {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    self.thumbnail(work)
+                                        .fit_to_exact_size(egui::vec2(
+                                            Self::THUMB_SIZE,
+                                            Self::THUMB_SIZE,
+                                        ))
+                                        .maintain_aspect_ratio(true),
+                                );
+                                ui.vertical(|ui| {
+                                    ui.label(work.name());
+                                    ui.weak(work.date().to_string());
+                                    ui.horizontal(|ui| {
+                                        if ui.button("✔ Keep").clicked() {
+                                            action = Some((offset, InboxAction::Keep));
+                                        }
+                                        if ui.button("✨ Favorite").clicked() {
+                                            action = Some((offset, InboxAction::Favorite));
+                                        }
+                                        if ui.button("🗑 Hide").clicked() {
+                                            action = Some((offset, InboxAction::Hide));
+                                        }
+                                    });
+                                });
+                            });
+                        }
+                    });
+            }
+        });
+
+        if let Some((offset, action)) = action {
+            let work = self.works[offset].1.clone();
+            self.apply(&work, &action, db_write);
+        }
+        if let Some(group) = keep_group {
+            let screen_urls = self
+                .works
+                .iter()
+                .filter(|(g, _)| *g == group)
+                .map(|(_, work)| work.screen_url().to_owned())
+                .collect();
+            self.review(screen_urls, db_write);
+        }
+    }
+}
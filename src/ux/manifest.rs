@@ -0,0 +1,197 @@
+use crate::{
+    db::writer::DbWriteHandle,
+    shared::manifest::{Manifest, ManifestField, ManifestSource, ManifestWork},
+};
+use log::error;
+use std::path::Path;
+
+// The manifest import window: load a CSV or JSON file, say what its columns are, and check what
+// would be imported before importing it.
+#[derive(Debug, Default)]
+pub struct UxManifest {
+    open: bool,
+    path: String,
+    manifest: Option<Manifest>,
+    fields: Vec<ManifestField>,
+    load_error: Option<String>,
+    // What importing with the current fields would do; rebuilt whenever they change.
+    plan: Vec<Result<ManifestWork, String>>,
+}
+
+impl UxManifest {
+    const MAX_PREVIEW_ROWS: usize = 200;
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    fn load(&mut self) {
+        match Manifest::read(Path::new(self.path.trim())) {
+            Ok(manifest) => {
+                self.fields = manifest
+                    .columns
+                    .iter()
+                    .map(|column| ManifestField::guess(column))
+                    .collect();
+                self.plan = manifest.plan(&self.fields);
+                self.manifest = Some(manifest);
+                self.load_error = None;
+            }
+            Err(e) => {
+                self.manifest = None;
+                self.plan.clear();
+                self.load_error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    pub fn window(&mut self, db_write: &DbWriteHandle, ctx: &egui::Context) {
+        let mut open = self.open;
+        let mut import = false;
+        egui::Window::new("Import Manifest")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.label("Import works from a CSV or JSON file that lists them, one row or object per work, with a url to download or a path to copy for each.");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.path)
+                            .hint_text("manifest.csv or manifest.json"),
+                    );
+                    if ui
+                        .add_enabled(!self.path.trim().is_empty(), egui::Button::new("Load"))
+                        .clicked()
+                    {
+                        self.load();
+                    }
+                });
+                if let Some(e) = &self.load_error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                if self.manifest.is_some() {
+                    ui.separator();
+                    self.columns_ui(ui);
+                    ui.separator();
+                    import = self.preview_ui(ui);
+                }
+            });
+        self.open = open;
+
+        if import && let Some(manifest) = self.manifest.take() {
+            let works = self
+                .plan
+                .drain(..)
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+            if let Err(e) = db_write.import_manifest(manifest.tag(), works) {
+                error!("Failed to start the manifest import: {e}");
+            }
+            self.open = false;
+        }
+    }
+
+    fn columns_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(manifest) = &self.manifest else {
+            return;
+        };
+        let mut changed = false;
+        egui::Grid::new("manifest_columns")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Column");
+                ui.strong("Is the work's");
+                ui.strong("First row");
+                ui.end_row();
+                for (offset, column) in manifest.columns.iter().enumerate() {
+                    ui.label(column);
+                    let field = &mut self.fields[offset];
+                    egui::ComboBox::new(("manifest_field", offset), "")
+                        .selected_text(field.label())
+                        .show_ui(ui, |ui| {
+                            for option in ManifestField::ALL {
+                                changed |=
+                                    ui.selectable_value(field, option, option.label()).changed();
+                            }
+                        });
+                    let first = manifest
+                        .rows
+                        .first()
+                        .and_then(|row| row.get(offset))
+                        .map(String::as_str)
+                        .unwrap_or_default();
+                    ui.weak(first);
+                    ui.end_row();
+                }
+            });
+        if changed {
+            self.plan = manifest.plan(&self.fields);
+        }
+    }
+
+    // Returns true if the user asked to import.
+    fn preview_ui(&self, ui: &mut egui::Ui) -> bool {
+        let Some(manifest) = &self.manifest else {
+            return false;
+        };
+        let ready = self.plan.iter().filter(|row| row.is_ok()).count();
+        let skipped = self.plan.len() - ready;
+        ui.label(format!(
+            "{ready} works will be imported under the tag {}; {skipped} rows will be skipped.",
+            manifest.tag()
+        ));
+        egui::ScrollArea::vertical()
+            .max_height(280.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                egui::Grid::new("manifest_preview")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Row");
+                        ui.strong("Title");
+                        ui.strong("Date");
+                        ui.strong("From");
+                        ui.strong("Tags");
+                        ui.end_row();
+                        for (offset, row) in
+                            self.plan.iter().take(Self::MAX_PREVIEW_ROWS).enumerate()
+                        {
+                            ui.label((offset + 1).to_string());
+                            match row {
+                                Ok(work) => {
+                                    ui.label(&work.title);
+                                    ui.label(
+                                        work.date.map(|date| date.to_string()).unwrap_or_default(),
+                                    );
+                                    match &work.source {
+                                        ManifestSource::Url(url) => ui.label(url),
+                                        ManifestSource::Path(path) => {
+                                            ui.label(path.display().to_string())
+                                        }
+                                    };
+                                    let mut tags = work.tags.clone();
+                                    tags.extend(work.artist.clone());
+                                    ui.label(tags.join(", "));
+                                }
+                                Err(e) => {
+                                    ui.colored_label(ui.visuals().warn_fg_color, e);
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if self.plan.len() > Self::MAX_PREVIEW_ROWS {
+                    ui.weak(format!(
+                        "...and {} more rows",
+                        self.plan.len() - Self::MAX_PREVIEW_ROWS
+                    ));
+                }
+            });
+        ui.add_enabled(
+            ready > 0,
+            egui::Button::new(format!("Import {ready} Works")),
+        )
+        .clicked()
+    }
+}
@@ -0,0 +1,193 @@
+// Just enough markdown for the user's notes: headings, lists, quotes, code, emphasis, and links.
+// Anything we don't understand is shown as written, which is fine for notes that the user
+// wrote themselves.
+use egui::RichText;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Span<'a> {
+    Text(&'a str),
+    Strong(&'a str),
+    Emphasis(&'a str),
+    Code(&'a str),
+    Link { text: &'a str, url: &'a str },
+}
+
+// The first span in `text` with markup, as (where it starts, the span, where it ends).
+fn next_span(text: &str) -> Option<(usize, Span<'_>, usize)> {
+    for (i, c) in text.char_indices() {
+        let rest = &text[i..];
+        match c {
+            '*' if rest.starts_with("**") => {
+                if let Some(end) = rest[2..].find("**").filter(|&end| end > 0) {
+                    return Some((i, Span::Strong(&rest[2..2 + end]), i + end + 4));
+                }
+            }
+            '`' => {
+                if let Some(end) = rest[1..].find('`').filter(|&end| end > 0) {
+                    return Some((i, Span::Code(&rest[1..1 + end]), i + end + 2));
+                }
+            }
+            // Note: an underscore inside a word, as in a file name, is not emphasis.
+            '*' | '_'
+                if !rest[1..].starts_with(char::is_whitespace)
+                    && (c == '*'
+                        || !text[..i].ends_with(|prior: char| prior.is_alphanumeric())) =>
+            {
+                if let Some(end) = rest[1..].find(c).filter(|&end| end > 0) {
+                    return Some((i, Span::Emphasis(&rest[1..1 + end]), i + end + 2));
+                }
+            }
+            '[' => {
+                if let Some(mid) = rest.find("](")
+                    && let Some(end) = rest[mid + 2..].find(')')
+                {
+                    let link = Span::Link {
+                        text: &rest[1..mid],
+                        url: &rest[mid + 2..mid + 2 + end],
+                    };
+                    return Some((i, link, i + mid + end + 3));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_inline(mut text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    while !text.is_empty() {
+        let Some((start, span, end)) = next_span(text) else {
+            spans.push(Span::Text(text));
+            break;
+        };
+        if start > 0 {
+            spans.push(Span::Text(&text[..start]));
+        }
+        spans.push(span);
+        text = &text[end..];
+    }
+    spans
+}
+
+fn inline_ui(ui: &mut egui::Ui, text: &str, quoted: bool) {
+    ui.spacing_mut().item_spacing.x = 0.;
+    for span in parse_inline(text) {
+        let text = match span {
+            Span::Text(text) => RichText::new(text),
+            Span::Strong(text) => RichText::new(text).strong(),
+            Span::Emphasis(text) => RichText::new(text).italics(),
+            Span::Code(text) => RichText::new(text).code(),
+            Span::Link { text, url } => {
+                ui.hyperlink_to(text, url);
+                continue;
+            }
+        };
+        ui.label(if quoted { text.weak() } else { text });
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+fn code_block(ui: &mut egui::Ui, code: &str) {
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.add(egui::Label::new(RichText::new(code.trim_end()).monospace()).selectable(true));
+    });
+}
+
+pub fn markdown_ui(ui: &mut egui::Ui, text: &str) {
+    let mut code = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            match code.take() {
+                Some(block) => code_block(ui, &block),
+                None => code = Some(String::new()),
+            }
+            continue;
+        }
+        if let Some(block) = code.as_mut() {
+            block.push_str(line);
+            block.push('\n');
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            ui.add_space(4.);
+        } else if let Some((level, text)) = heading(trimmed) {
+            let text = RichText::new(text).strong();
+            ui.label(match level {
+                1 => text.heading(),
+                2 => text.size(16.),
+                _ => text,
+            });
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("  • ");
+                inline_ui(ui, item, false);
+            });
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(RichText::new("▍ ").weak());
+                inline_ui(ui, quote.trim_start(), true);
+            });
+        } else {
+            ui.horizontal_wrapped(|ui| inline_ui(ui, trimmed, false));
+        }
+    }
+    // Note: show an unclosed block rather than dropping what the user wrote.
+    if let Some(block) = code {
+        code_block(ui, &block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inline() {
+        assert_eq!(parse_inline("plain"), vec![Span::Text("plain")]);
+        assert_eq!(
+            parse_inline("see **plate 4** in `cat_1921.pdf`, *not* plate 5"),
+            vec![
+                Span::Text("see "),
+                Span::Strong("plate 4"),
+                Span::Text(" in "),
+                Span::Code("cat_1921.pdf"),
+                Span::Text(", "),
+                Span::Emphasis("not"),
+                Span::Text(" plate 5"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("[the catalog](https://example.com/cat) says so"),
+            vec![
+                Span::Link {
+                    text: "the catalog",
+                    url: "https://example.com/cat"
+                },
+                Span::Text(" says so"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("work_edited_final.png and 2 * 3 * 4"),
+            vec![Span::Text("work_edited_final.png and 2 * 3 * 4")]
+        );
+        assert_eq!(parse_inline("**unclosed"), vec![Span::Text("**unclosed")]);
+    }
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(heading("## Provenance"), Some((2, "Provenance")));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("####### too deep"), None);
+    }
+}
@@ -1,7 +1,51 @@
+pub mod already_running;
+pub mod annotations;
+pub mod co_tags;
+pub mod collections;
+pub mod color;
+pub mod contact_sheet;
 pub mod db;
+pub mod detection;
+pub mod display;
 pub mod dock;
+pub mod document;
+pub mod enrichment;
+pub mod exhibition;
+pub mod export;
+pub mod filter;
+pub mod first_run;
+pub mod gallery_layout;
+pub mod grouping;
+pub mod health;
+pub mod history;
+pub mod image_cache;
+pub mod image_info;
+pub mod inbox;
+pub mod manifest;
+pub mod markdown;
+pub mod model;
+pub mod notes;
+pub mod notify;
+pub mod playback;
 pub mod plugin;
+pub mod prefetch;
+pub mod projection;
+pub mod queue;
+pub mod reference;
+pub mod slideshow;
+pub mod startup_error;
+pub mod storage;
+pub mod sync;
 pub mod tag;
+pub mod tag_push;
 pub mod theme;
+pub mod thumbnails;
+pub mod tone_map;
+pub mod transcript;
+pub mod trash;
 pub mod tutorial;
+pub mod unlock;
+pub mod vector;
+pub mod vocabulary;
+pub mod wallpaper;
 pub mod work;
@@ -0,0 +1,514 @@
+// Looking at the 3D models of works, e.g. the Smithsonian's sculpture scans, in the slideshow:
+// drag to orbit around the model and scroll to zoom. We draw with egui's own painter, sorting the
+// triangles back to front whenever the view moves, rather than keeping GL state of our own; that
+// is plenty for the web-sized models that museums publish alongside their full scans.
+use anyhow::{Context as _, Result, ensure};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use egui::{
+    Color32, ColorImage, Mesh, Pos2, Rect, Rgba, Sense, Shape, TextureHandle, TextureId,
+    TextureOptions, Vec2,
+    epaint::{Vertex, WHITE_UV},
+};
+use glam::{Mat3, Mat4, Vec3};
+use gltf::{image::Format, mesh::Mode};
+use std::{
+    f32::consts::FRAC_PI_4,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+const FOV_Y: f32 = FRAC_PI_4;
+// How much light faces get from the sky, rather than from the lamp at the camera.
+const AMBIENT: f32 = 0.25;
+
+// The triangles of the model that share a material, in world space.
+struct Part {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    // Empty if the material has no base color texture.
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+    // Linear, as glTF gives it.
+    base_color: [f32; 4],
+    texture: Option<ColorImage>,
+    double_sided: bool,
+}
+
+struct Model {
+    parts: Vec<Part>,
+    center: Vec3,
+    radius: f32,
+}
+
+fn load(path: &Path) -> Result<Model> {
+    let (document, buffers, images) = gltf::import(path)?;
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .context("the model has no scenes")?;
+    let mut parts = Vec::new();
+    let mut nodes = scene
+        .nodes()
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect::<Vec<_>>();
+    while let Some((node, parent)) = nodes.pop() {
+        let world = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        for primitive in node.mesh().into_iter().flat_map(|mesh| mesh.primitives()) {
+            if primitive.mode() == Mode::Triangles
+                && let Some(part) = load_part(&primitive, world, &buffers, &images)
+            {
+                parts.push(part);
+            }
+        }
+        nodes.extend(node.children().map(|child| (child, world)));
+    }
+    ensure!(
+        parts.iter().any(|part| !part.indices.is_empty()),
+        "the model has no triangles"
+    );
+    let (center, radius) = bounds(parts.iter().flat_map(|part| part.positions.iter().copied()));
+    Ok(Model {
+        parts,
+        center,
+        radius,
+    })
+}
+
+fn load_part(
+    primitive: &gltf::Primitive<'_>,
+    world: Mat4,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
+) -> Option<Part> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+    let positions = reader
+        .read_positions()?
+        .map(|position| world.transform_point3(Vec3::from(position)))
+        .collect::<Vec<_>>();
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+        None => (0..u32::try_from(positions.len()).ok()?).collect(),
+    };
+    if indices.iter().any(|i| *i as usize >= positions.len()) {
+        return None;
+    }
+    let to_world = Mat3::from_mat4(world).inverse().transpose();
+    let normals = reader
+        .read_normals()
+        .map(|normals| {
+            normals
+                .map(|normal| (to_world * Vec3::from(normal)).normalize_or_zero())
+                .collect::<Vec<_>>()
+        })
+        .filter(|normals| normals.len() == positions.len())
+        // Note: scans often come without normals, as the renderer is expected to make them.
+        .unwrap_or_else(|| smooth_normals(&positions, &indices));
+
+    let material = primitive.material();
+    let pbr = material.pbr_metallic_roughness();
+    let base_color_texture = pbr.base_color_texture();
+    let uvs = base_color_texture
+        .as_ref()
+        .and_then(|info| reader.read_tex_coords(info.tex_coord()))
+        .map(|uvs| uvs.into_f32().collect::<Vec<_>>())
+        .filter(|uvs| uvs.len() == positions.len())
+        .unwrap_or_default();
+    let texture = base_color_texture
+        .filter(|_| !uvs.is_empty())
+        .and_then(|info| images.get(info.texture().source().index()))
+        .and_then(color_image);
+    Some(Part {
+        positions,
+        normals,
+        uvs: if texture.is_some() { uvs } else { Vec::new() },
+        indices,
+        base_color: pbr.base_color_factor(),
+        texture,
+        double_sided: material.double_sided(),
+    })
+}
+
+fn color_image(image: &gltf::image::Data) -> Option<ColorImage> {
+    let size = [image.width as usize, image.height as usize];
+    let rgba = match image.format {
+        Format::R8G8B8A8 => image.pixels.clone(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|l| [*l, *l, *l, 255])
+            .collect(),
+        // Note: deep color textures are rare enough that the material's flat color will do.
+        _ => return None,
+    };
+    (rgba.len() == size[0] * size[1] * 4).then(|| ColorImage::from_rgba_unmultiplied(size, &rgba))
+}
+
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        // Note: the cross product is as long as twice the triangle's area, so big faces count for
+        //       more in the average.
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        for i in [a, b, c] {
+            normals[i] += normal;
+        }
+    }
+    normals.into_iter().map(Vec3::normalize_or_zero).collect()
+}
+
+// The middle of the points' box, and the distance from there to the farthest of them.
+fn bounds(points: impl Iterator<Item = Vec3> + Clone) -> (Vec3, f32) {
+    let (min, max) = points
+        .clone()
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), point| {
+            (min.min(point), max.max(point))
+        });
+    if !min.is_finite() || !max.is_finite() {
+        return (Vec3::ZERO, 1.);
+    }
+    let center = (min + max) / 2.;
+    let radius = points
+        .map(|point| point.distance(center))
+        .fold(0f32, f32::max);
+    (center, radius.max(f32::EPSILON))
+}
+
+// Where the camera sits around the model; it always looks at the model's center.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Orbit {
+    yaw: f32,
+    pitch: f32,
+    // From the center, in model radii.
+    distance: f32,
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Self {
+            yaw: 0.,
+            pitch: 0.15,
+            distance: Self::fit_distance(),
+        }
+    }
+}
+
+impl Orbit {
+    const MIN_DISTANCE: f32 = 1.05;
+    const MAX_DISTANCE: f32 = 20.;
+
+    // Close enough that the whole model just fits in the view.
+    fn fit_distance() -> f32 {
+        1.1 / (FOV_Y / 2.).sin()
+    }
+
+    fn eye(&self, center: Vec3, radius: f32) -> Vec3 {
+        let direction = Vec3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        );
+        center + direction * radius * self.distance
+    }
+
+    fn view_projection(&self, center: Vec3, radius: f32, aspect: f32) -> Mat4 {
+        // Note: the camera never enters the model's bounding sphere, so the near plane can sit
+        //       just outside it, which keeps the depth sort precise for small models.
+        let near = (radius * (self.distance - 1.)).max(radius * 0.01);
+        let far = radius * (self.distance + 1.);
+        let view = Mat4::look_at_rh(self.eye(center, radius), center, Vec3::Y);
+        Mat4::perspective_rh_gl(FOV_Y, aspect, near, far) * view
+    }
+
+    fn rotate(&mut self, drag: Vec2) {
+        self.yaw -= drag.x * 0.01;
+        self.pitch = (self.pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+    }
+
+    fn zoom(&mut self, scroll: f32) {
+        self.distance =
+            (self.distance * (-scroll * 0.002).exp()).clamp(Self::MIN_DISTANCE, Self::MAX_DISTANCE);
+    }
+}
+
+type Loaded = (PathBuf, Result<Model, String>);
+
+#[derive(Clone, Copy, PartialEq)]
+struct View {
+    orbit: Orbit,
+    rect: Rect,
+}
+
+pub struct UxModel {
+    path: Option<PathBuf>,
+    model: Option<Result<Model, String>>,
+    textures: Vec<Option<TextureHandle>>,
+    orbit: Orbit,
+    // The triangles as last drawn, so that a still view costs nothing.
+    drawn: Option<(View, Vec<Shape>)>,
+
+    tx: Sender<Loaded>,
+    rx: Receiver<Loaded>,
+}
+
+impl Default for UxModel {
+    fn default() -> Self {
+        let (tx, rx) = unbounded();
+        Self {
+            path: None,
+            model: None,
+            textures: Vec::new(),
+            orbit: Orbit::default(),
+            drawn: None,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl UxModel {
+    fn open(&mut self, path: &Path, ctx: &egui::Context) {
+        *self = Self {
+            path: Some(path.to_owned()),
+            tx: self.tx.clone(),
+            rx: self.rx.clone(),
+            ..Self::default()
+        };
+        let (path, tx, ctx) = (path.to_owned(), self.tx.clone(), ctx.clone());
+        thread::spawn(move || {
+            let model = load(&path).map_err(|e| e.to_string());
+            tx.send((path, model)).ok();
+            ctx.request_repaint();
+        });
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        while let Ok((path, mut model)) = self.rx.try_recv() {
+            // Note: a model we have since moved on from still finishes loading; drop it.
+            if self.path.as_ref() != Some(&path) {
+                continue;
+            }
+            if let Ok(model) = model.as_mut() {
+                self.textures = model
+                    .parts
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, part)| {
+                        let name = format!("model://{}#{i}", path.display());
+                        part.texture.take().map(|image| {
+                            ctx.load_texture(name, image, TextureOptions::LINEAR_REPEAT)
+                        })
+                    })
+                    .collect();
+            }
+            self.model = Some(model);
+        }
+    }
+
+    pub fn ui(&mut self, path: &Path, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        if self.path.as_deref() != Some(path) {
+            self.open(path, &ctx);
+        }
+        self.receive(&ctx);
+
+        let rect = ui.available_rect_before_wrap();
+        let model = match &self.model {
+            None => {
+                egui::Spinner::new()
+                    .paint_at(ui, Rect::from_center_size(rect.center(), Vec2::splat(32.)));
+                return;
+            }
+            Some(Err(e)) => {
+                let e = format!("Failed to open the model: {e}");
+                ui.centered_and_justified(|ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                });
+                return;
+            }
+            Some(Ok(model)) => model,
+        };
+
+        let resp = ui.interact(rect, egui::Id::new("model_view"), Sense::click_and_drag());
+        if resp.dragged() {
+            self.orbit.rotate(resp.drag_delta());
+        }
+        if resp.double_clicked() {
+            self.orbit = Orbit::default();
+        }
+        if resp.hovered() {
+            let scroll = ui.input(|input| input.smooth_scroll_delta.y);
+            if scroll != 0. {
+                self.orbit.zoom(scroll);
+            }
+        }
+
+        let view = View {
+            orbit: self.orbit,
+            rect,
+        };
+        if self.drawn.as_ref().is_none_or(|(drawn, _)| *drawn != view) {
+            let shapes = draw(model, &self.textures, view);
+            self.drawn = Some((view, shapes));
+        }
+        if let Some((_, shapes)) = &self.drawn {
+            ui.painter()
+                .with_clip_rect(rect)
+                .extend(shapes.iter().cloned());
+        }
+
+        egui::Area::new(egui::Id::new("model_controls"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8., -8.])
+            .show(&ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.weak("Drag to turn; scroll to zoom");
+                        if ui.button("⟲ Reset view").clicked() {
+                            self.orbit = Orbit::default();
+                        }
+                    });
+                });
+            });
+    }
+}
+
+struct Triangle {
+    depth: f32,
+    part: usize,
+    corners: [u32; 3],
+}
+
+// Project the model into `view.rect` and paint it back to front, so that near faces cover far
+// ones without a depth buffer.
+fn draw(model: &Model, textures: &[Option<TextureHandle>], view: View) -> Vec<Shape> {
+    let View { orbit, rect } = view;
+    let (center, radius) = (model.center, model.radius);
+    let eye = orbit.eye(center, radius);
+    let view_projection = orbit.view_projection(center, radius, rect.aspect_ratio());
+
+    let mut projected = Vec::with_capacity(model.parts.len());
+    let mut triangles = Vec::new();
+    for (part_index, part) in model.parts.iter().enumerate() {
+        let corners = part
+            .positions
+            .iter()
+            .zip(&part.normals)
+            .map(|(position, normal)| {
+                let clip = view_projection * position.extend(1.);
+                if clip.w <= 0. {
+                    return None;
+                }
+                let ndc = clip.truncate() / clip.w;
+                let pos = rect.center() + Vec2::new(ndc.x, -ndc.y) * rect.size() / 2.;
+                let facing = (eye - *position).normalize_or_zero().dot(*normal);
+                let light = if part.double_sided {
+                    facing.abs()
+                } else {
+                    facing.max(0.)
+                };
+                let [r, g, b, a] = part.base_color;
+                let shade = AMBIENT + (1. - AMBIENT) * light;
+                let color = Rgba::from_rgba_unmultiplied(r * shade, g * shade, b * shade, a);
+                Some((pos, ndc.z, Color32::from(color)))
+            })
+            .collect::<Vec<_>>();
+        for triangle in part.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+            let (Some(pa), Some(pb), Some(pc)) = (
+                corners[a as usize],
+                corners[b as usize],
+                corners[c as usize],
+            ) else {
+                continue;
+            };
+            // Note: glTF fronts wind counter-clockwise, which is clockwise once y points down.
+            let winding = (pb.0 - pa.0).x * (pc.0 - pa.0).y - (pb.0 - pa.0).y * (pc.0 - pa.0).x;
+            if winding == 0. || (!part.double_sided && winding > 0.) {
+                continue;
+            }
+            triangles.push(Triangle {
+                depth: pa.1 + pb.1 + pc.1,
+                part: part_index,
+                corners: [a, b, c],
+            });
+        }
+        projected.push(corners);
+    }
+    triangles.sort_unstable_by(|a, b| b.depth.total_cmp(&a.depth));
+
+    // Note: each texture needs its own mesh, so a run of triangles that share one goes together.
+    let mut shapes = Vec::new();
+    let mut mesh = Mesh::default();
+    for triangle in triangles {
+        let texture = textures
+            .get(triangle.part)
+            .and_then(Option::as_ref)
+            .map_or(TextureId::default(), TextureHandle::id);
+        if mesh.texture_id != texture {
+            if !mesh.is_empty() {
+                shapes.push(Shape::Mesh(Arc::new(mesh)));
+            }
+            mesh = Mesh::with_texture(texture);
+        }
+        let part = &model.parts[triangle.part];
+        let vertices = triangle.corners.map(|corner| {
+            let (pos, _, color) = projected[triangle.part][corner as usize]?;
+            // Note: untextured parts draw with the white pixel of egui's default texture.
+            let uv = part
+                .uvs
+                .get(corner as usize)
+                .map_or(WHITE_UV, |[u, v]| Pos2::new(*u, *v));
+            Some(Vertex { pos, uv, color })
+        });
+        let [Some(a), Some(b), Some(c)] = vertices else {
+            continue;
+        };
+        let base = mesh.vertices.len() as u32;
+        mesh.vertices.extend([a, b, c]);
+        mesh.add_triangle(base, base + 1, base + 2);
+    }
+    if !mesh.is_empty() {
+        shapes.push(Shape::Mesh(Arc::new(mesh)));
+    }
+    shapes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        let points = [
+            Vec3::new(-1., 0., 0.),
+            Vec3::new(3., 2., 0.),
+            Vec3::new(1., 1., 0.),
+        ];
+        let (center, radius) = bounds(points.into_iter());
+        assert_eq!(center, Vec3::new(1., 1., 0.));
+        assert!((radius - 5f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_default_orbit_fits_the_model() {
+        let (center, radius) = (Vec3::new(10., 0., -4.), 2.);
+        let view_projection = Orbit::default().view_projection(center, radius, 1.);
+        let middle = view_projection.project_point3(center);
+        assert!(middle.x.abs() < 1e-4 && middle.y.abs() < 1e-4);
+        for offset in [Vec3::X, Vec3::Y, Vec3::NEG_X, Vec3::NEG_Y] {
+            let edge = view_projection.project_point3(center + offset * radius);
+            assert!(edge.x.abs() < 1. && edge.y.abs() < 1. && edge.z.abs() < 1.);
+        }
+    }
+}
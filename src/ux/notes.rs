@@ -0,0 +1,248 @@
+use crate::{
+    db::{
+        models::{
+            note::{DbWorkNote, NoteHit},
+            transcript::TranscriptHit,
+            work::WorkId,
+        },
+        reader::DbReadHandle,
+        writer::DbWriteHandle,
+    },
+    shared::{transcript, update::DataUpdate},
+    ux::{markdown::markdown_ui, work::UxWork},
+};
+use egui::RichText;
+use jiff::{Timestamp, tz::TimeZone};
+use log::error;
+
+fn format_time(time: Timestamp) -> String {
+    time.to_zoned(TimeZone::system())
+        .strftime("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+// The user's note on the selected work, in Work Info.
+#[derive(Default)]
+pub struct UxWorkNote {
+    // The work that the note below belongs to.
+    work: Option<(WorkId, String)>,
+    note: Option<DbWorkNote>,
+    is_loading: bool,
+
+    // What the user is typing, while they are editing.
+    draft: Option<String>,
+}
+
+impl UxWorkNote {
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::WorkNote { work_id, note } = update
+                && self.work.as_ref().is_some_and(|(id, _)| id == work_id)
+            {
+                self.is_loading = false;
+                self.note = note.clone();
+            }
+        }
+    }
+
+    fn save(&mut self, db_write: &DbWriteHandle) {
+        let (Some((_, screen_url)), Some(body)) = (&self.work, self.draft.take()) else {
+            return;
+        };
+        if self
+            .note
+            .as_ref()
+            .map_or(body.trim().is_empty(), |note| note.body == body)
+        {
+            return;
+        }
+        if let Err(e) = db_write.set_work_note(screen_url, &body) {
+            error!("Failed to save the note on {screen_url}: {e}");
+            return;
+        }
+        // Note: we know what the writer will store, so there is no need to read it back.
+        let now = Timestamp::now();
+        self.note = (!body.trim().is_empty()).then(|| DbWorkNote {
+            body,
+            created_at: self.note.as_ref().map_or(now, |note| note.created_at),
+            updated_at: now,
+        });
+    }
+
+    pub fn ui(
+        &mut self,
+        work_id: WorkId,
+        screen_url: &str,
+        db_read: &DbReadHandle,
+        db_write: &DbWriteHandle,
+        ui: &mut egui::Ui,
+    ) {
+        if self.work.as_ref().is_none_or(|(id, _)| *id != work_id) {
+            // Note: moving on to another work keeps what the user was writing.
+            self.save(db_write);
+            self.work = Some((work_id, screen_url.to_owned()));
+            self.note = None;
+            self.is_loading = true;
+            db_read.get_work_note(work_id);
+        }
+        if self.is_loading {
+            ui.spinner();
+            return;
+        }
+
+        if let Some(draft) = &mut self.draft {
+            ui.add(
+                egui::TextEdit::multiline(draft)
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(8)
+                    .hint_text("# Headings, - lists, **bold**, *italic*, `code`, [links](url)"),
+            );
+            let mut done = false;
+            ui.horizontal(|ui| {
+                done = ui.button("Save").clicked();
+                if ui.button("Cancel").clicked() {
+                    self.draft = None;
+                }
+            });
+            if done {
+                self.save(db_write);
+            }
+            return;
+        }
+
+        match &self.note {
+            Some(note) => {
+                markdown_ui(ui, &note.body);
+                let mut written = format!("Written {}", format_time(note.created_at));
+                if note.updated_at != note.created_at {
+                    written += &format!(", edited {}", format_time(note.updated_at));
+                }
+                ui.label(RichText::new(written).small().weak());
+            }
+            None => {
+                ui.label(
+                    "Notes that you write here are kept with the work and found by Notes search.",
+                );
+            }
+        }
+        let label = if self.note.is_some() {
+            "Edit"
+        } else {
+            "Add Notes"
+        };
+        if ui.button(label).clicked() {
+            self.draft = Some(
+                self.note
+                    .as_ref()
+                    .map(|note| note.body.clone())
+                    .unwrap_or_default(),
+            );
+        }
+    }
+}
+
+// Full-text search over the user's notes on every work, and what is said in them.
+#[derive(Default)]
+pub struct UxNotes {
+    query: String,
+    hits: Vec<NoteHit>,
+    transcript_hits: Vec<TranscriptHit>,
+    is_searching: bool,
+
+    // The last hit that the user picked and that isn't in the gallery.
+    not_shown: Option<String>,
+}
+
+impl UxNotes {
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            // Note: we search as the user types, so answers for what they typed before are stale.
+            if let DataUpdate::NoteSearch { query, hits } = update
+                && *query == self.query
+            {
+                self.is_searching = false;
+                self.hits = hits.clone();
+            }
+            if let DataUpdate::TranscriptSearch { query, hits } = update
+                && *query == self.query
+            {
+                self.transcript_hits = hits.clone();
+            }
+        }
+    }
+
+    pub fn ui(&mut self, work_ux: &mut UxWork, db: &DbReadHandle, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .hint_text("Search notes")
+                    .desired_width(f32::INFINITY),
+            );
+            if resp.changed() {
+                self.not_shown = None;
+                if self.query.trim().is_empty() {
+                    self.is_searching = false;
+                    self.hits.clear();
+                    self.transcript_hits.clear();
+                } else {
+                    self.is_searching = true;
+                    db.search_work_notes(&self.query);
+                    db.search_transcripts(&self.query);
+                }
+            }
+        });
+        if self.is_searching {
+            ui.spinner();
+        } else if self.hits.is_empty()
+            && self.transcript_hits.is_empty()
+            && !self.query.trim().is_empty()
+        {
+            ui.label("No notes match.");
+        }
+        if let Some(name) = &self.not_shown {
+            ui.label(
+                RichText::new(format!(
+                    "{name} isn't in the gallery; select one of its tags to find it."
+                ))
+                .weak(),
+            );
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for hit in &self.hits {
+                let name = hit.work_name.as_deref().unwrap_or(&hit.screen_url);
+                ui.horizontal(|ui| {
+                    if ui.link(RichText::new(name).strong()).clicked()
+                        && !work_ux.select_screen_url(&hit.screen_url)
+                    {
+                        self.not_shown = Some(name.to_owned());
+                    }
+                    ui.label(RichText::new(format_time(hit.updated_at)).small().weak());
+                });
+                markdown_ui(ui, &hit.snippet);
+                ui.separator();
+            }
+            if !self.transcript_hits.is_empty() {
+                ui.heading("In transcripts");
+            }
+            for hit in &self.transcript_hits {
+                let name = hit.work_name.as_deref().unwrap_or(&hit.screen_url);
+                ui.horizontal(|ui| {
+                    if ui.link(RichText::new(name).strong()).clicked()
+                        && !work_ux.select_screen_url_at(&hit.screen_url, hit.start)
+                    {
+                        self.not_shown = Some(name.to_owned());
+                    }
+                    ui.label(
+                        RichText::new(transcript::format_time(hit.start))
+                            .small()
+                            .weak(),
+                    );
+                });
+                markdown_ui(ui, &hit.snippet);
+                ui.separator();
+            }
+        });
+    }
+}
@@ -0,0 +1,176 @@
+// A tray of things that happened while the user was looking elsewhere: refreshes that finished,
+// new works on watched tags, and plugin tasks that failed. Optionally mirrored to the desktop's
+// own notifications.
+use crate::{
+    db::models::tag::{DbTag, TagId},
+    shared::update::DataUpdate,
+};
+use jiff::{Timestamp, tz::TimeZone};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    process::Command,
+    thread,
+};
+
+const MAX_NOTIFICATIONS: usize = 100;
+
+// Where clicking on a notification should take the user.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum NotifyTarget {
+    #[default]
+    None,
+    Tag(String),
+    Plugin(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Notification {
+    at: Timestamp,
+    message: String,
+    target: NotifyTarget,
+    read: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxNotifications {
+    // Preferences
+    os_notifications: bool,
+
+    items: VecDeque<Notification>,
+}
+
+impl UxNotifications {
+    pub fn handle_updates(&mut self, tags: Option<&HashMap<TagId, DbTag>>, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                // Note: an empty tag is a reprocess or other bulk update, not something the
+                //       user asked to hear about.
+                DataUpdate::WorksWereUpdatedForTag { for_tag, new_works }
+                    if !for_tag.is_empty() =>
+                {
+                    let plural = if *new_works == 1 { "work" } else { "works" };
+                    let watched = tags.is_some_and(|tags| {
+                        tags.values()
+                            .any(|tag| tag.watched() && tag.name() == for_tag)
+                    });
+                    let message = if watched && *new_works > 0 {
+                        format!("{new_works} new {plural} on watched tag \"{for_tag}\"")
+                    } else {
+                        format!("Refresh of \"{for_tag}\" finished: {new_works} new {plural}")
+                    };
+                    self.push(message, NotifyTarget::Tag(for_tag.clone()));
+                }
+                DataUpdate::PluginTaskFailed {
+                    plugin,
+                    task,
+                    error,
+                } => {
+                    self.push(
+                        format!("{plugin} failed to {task}: {error}"),
+                        NotifyTarget::Plugin(plugin.clone()),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push(&mut self, message: String, target: NotifyTarget) {
+        if self.os_notifications {
+            send_os_notification(message.clone());
+        }
+        self.items.push_front(Notification {
+            at: Timestamp::now(),
+            message,
+            target,
+            read: false,
+        });
+        self.items.truncate(MAX_NOTIFICATIONS);
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.items.iter().filter(|item| !item.read).count()
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for item in &mut self.items {
+            item.read = true;
+        }
+    }
+
+    pub fn preferences_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.os_notifications,
+            "Also show notifications on the desktop",
+        );
+    }
+
+    // Returns the target of any notification that was clicked on.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> NotifyTarget {
+        let mut clicked = NotifyTarget::None;
+        ui.horizontal(|ui| {
+            if ui.button("Mark All Read").clicked() {
+                self.mark_all_read();
+            }
+            if ui.button("Clear").clicked() {
+                self.items.clear();
+            }
+        });
+        ui.separator();
+        if self.items.is_empty() {
+            ui.label("Nothing to report.");
+            return clicked;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(400.)
+            .show(ui, |ui| {
+                for item in &mut self.items {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            item.at
+                                .to_zoned(TimeZone::system())
+                                .strftime("%m-%d %H:%M")
+                                .to_string(),
+                        );
+                        let text = if item.read {
+                            egui::RichText::new(&item.message)
+                        } else {
+                            egui::RichText::new(&item.message).strong()
+                        };
+                        let resp = if item.target == NotifyTarget::None {
+                            ui.label(text)
+                        } else {
+                            ui.link(text)
+                        };
+                        if resp.clicked() {
+                            item.read = true;
+                            clicked = item.target.clone();
+                        }
+                    });
+                }
+            });
+        clicked
+    }
+}
+
+// Note: fire and forget; there is nothing useful to do if the desktop doesn't want to hear it.
+fn send_os_notification(message: String) {
+    thread::spawn(move || {
+        let result = if cfg!(target_os = "linux") {
+            Command::new("notify-send")
+                .args(["Artchiver", &message])
+                .status()
+        } else if cfg!(target_os = "macos") {
+            let script = format!("display notification {message:?} with title \"Artchiver\"");
+            Command::new("osascript").args(["-e", &script]).status()
+        } else {
+            return;
+        };
+        if let Err(e) = result {
+            warn!("Failed to show desktop notification: {e}");
+        }
+    });
+}
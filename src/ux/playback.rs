@@ -0,0 +1,182 @@
+// Playback speed and the sleep timer, for listening to podcasts. Each feed remembers the speed it
+// was last played at, by the name of its Series tag, so that the next episode picks it up.
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const SPEEDS: [f64; 8] = [0.75, 1., 1.25, 1.5, 1.75, 2., 2.5, 3.];
+const SLEEP_MINUTES: [u64; 6] = [5, 10, 15, 30, 45, 60];
+
+fn speed_label(speed: f64) -> String {
+    format!("{speed}×")
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SleepTimer {
+    #[default]
+    Off,
+    At(Instant),
+    EndOfEpisode,
+    // The timer went off; nothing plays on its own until the user starts it again.
+    Asleep,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxPlayback {
+    feed_speeds: HashMap<String, f64>,
+
+    #[serde(skip)]
+    sleep: SleepTimer,
+    // The speed we last gave the player for the media that's loaded, if any.
+    #[serde(skip)]
+    applied: Option<f64>,
+}
+
+impl UxPlayback {
+    fn speed_for(&self, feed: Option<&str>) -> f64 {
+        feed.and_then(|feed| self.feed_speeds.get(feed))
+            .copied()
+            .unwrap_or(1.)
+    }
+
+    // Newly loaded media gets the speed of its feed.
+    pub fn on_load(&mut self) {
+        self.applied = None;
+    }
+
+    // The speed to give the player, if it doesn't have it already.
+    pub fn speed_to_apply(&mut self, feed: Option<&str>) -> Option<f64> {
+        if self.applied.is_some() {
+            return None;
+        }
+        let speed = self.speed_for(feed);
+        self.applied = Some(speed);
+        Some(speed)
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.sleep == SleepTimer::Asleep
+    }
+
+    pub fn wake(&mut self) {
+        if self.is_asleep() {
+            self.sleep = SleepTimer::Off;
+        }
+    }
+
+    // Returns true when the sleep timer goes off, and playback should stop.
+    pub fn tick(&mut self, played_out: bool, ctx: &egui::Context) -> bool {
+        match self.sleep {
+            SleepTimer::At(when) => {
+                let now = Instant::now();
+                if now >= when {
+                    self.sleep = SleepTimer::Asleep;
+                    return true;
+                }
+                ctx.request_repaint_after(when.duration_since(now));
+                false
+            }
+            SleepTimer::EndOfEpisode if played_out => {
+                self.sleep = SleepTimer::Asleep;
+                true
+            }
+            SleepTimer::Off | SleepTimer::EndOfEpisode | SleepTimer::Asleep => false,
+        }
+    }
+
+    // The speed and sleep timer controls, for the player's toolbar. Returns the speed to change
+    // to, if the user picked one.
+    pub fn controls_ui(&mut self, feed: Option<&str>, ui: &mut egui::Ui) -> Option<f64> {
+        let current = self.applied.unwrap_or_else(|| self.speed_for(feed));
+        let mut speed = current;
+        egui::ComboBox::new("playback_speed", "")
+            .selected_text(speed_label(speed))
+            .show_ui(ui, |ui| {
+                for choice in SPEEDS {
+                    ui.selectable_value(&mut speed, choice, speed_label(choice));
+                }
+            })
+            .response
+            .on_hover_text(match feed {
+                Some(feed) => format!("Episodes of {feed} play at this speed"),
+                None => "Playback speed".to_owned(),
+            });
+
+        let sleep_label = match self.sleep {
+            SleepTimer::At(when) => {
+                let left = when.saturating_duration_since(Instant::now()).as_secs();
+                format!("💤 {}:{:02}", left / 60, left % 60)
+            }
+            SleepTimer::EndOfEpisode => "💤 End".to_owned(),
+            SleepTimer::Off | SleepTimer::Asleep => "💤".to_owned(),
+        };
+        ui.menu_button(sleep_label, |ui| {
+            for minutes in SLEEP_MINUTES {
+                if ui.button(format!("{minutes} minutes")).clicked() {
+                    self.sleep = SleepTimer::At(Instant::now() + Duration::from_secs(minutes * 60));
+                    ui.close();
+                }
+            }
+            if ui.button("End of episode").clicked() {
+                self.sleep = SleepTimer::EndOfEpisode;
+                ui.close();
+            }
+            if !matches!(self.sleep, SleepTimer::Off | SleepTimer::Asleep)
+                && ui.button("Cancel").clicked()
+            {
+                self.sleep = SleepTimer::Off;
+                ui.close();
+            }
+        })
+        .response
+        .on_hover_text("Sleep timer");
+
+        if speed.total_cmp(&current).is_eq() {
+            return None;
+        }
+        if let Some(feed) = feed {
+            self.feed_speeds.insert(feed.to_owned(), speed);
+        }
+        self.applied = Some(speed);
+        Some(speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_per_feed() {
+        let mut playback = UxPlayback::default();
+        playback.feed_speeds.insert("Radiolab".to_owned(), 1.5);
+
+        playback.on_load();
+        assert_eq!(playback.speed_to_apply(Some("Radiolab")), Some(1.5));
+        assert_eq!(playback.speed_to_apply(Some("Radiolab")), None);
+
+        playback.on_load();
+        assert_eq!(playback.speed_to_apply(Some("Other Feed")), Some(1.));
+        playback.on_load();
+        assert_eq!(playback.speed_to_apply(None), Some(1.));
+    }
+
+    #[test]
+    fn test_sleep_at_end_of_episode() {
+        let ctx = egui::Context::default();
+        let mut playback = UxPlayback {
+            sleep: SleepTimer::EndOfEpisode,
+            ..Default::default()
+        };
+        assert!(!playback.tick(false, &ctx));
+        assert!(playback.tick(true, &ctx));
+        assert!(playback.is_asleep());
+        // It only goes off once.
+        assert!(!playback.tick(true, &ctx));
+        playback.wake();
+        assert!(!playback.is_asleep());
+    }
+}
@@ -1,12 +1,59 @@
 use crate::{
+    db::models::log::DbLogLine,
     plugin::host::{PluginHandle, PluginHost},
-    ux::tutorial::{NextButton, Tutorial, TutorialStep},
+    shared::bandwidth::DownloadLimits,
+    ux::{
+        image_info::format_size,
+        tutorial::{NextButton, Tutorial, TutorialStep},
+    },
 };
-use artchiver_sdk::ConfigValue;
+use artchiver_sdk::{ActionScope, ConfigValue, PluginMetadata, TagKind};
 use egui::{Margin, TextWrapMode};
 use egui_dnd::{DragUpdate, dnd};
-use log::Level;
+use itertools::Itertools as _;
+use jiff::{Timestamp, tz::TimeZone};
+use log::{Level, error};
 use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+// The rows of a two column grid for editing the plugin's configuration; required ones are starred.
+pub fn configuration_rows(meta: &mut PluginMetadata, required_only: bool, ui: &mut egui::Ui) {
+    let required = meta
+        .configurations()
+        .iter()
+        .map(|(key, _)| meta.is_required(key))
+        .collect::<Vec<_>>();
+    for ((config_key, config_val), required) in meta.configurations_mut().zip(required) {
+        if required_only && !required {
+            continue;
+        }
+        let label = if required {
+            format!("{config_key} *")
+        } else {
+            config_key.to_owned()
+        };
+        match config_val {
+            ConfigValue::String(s) => {
+                ui.label(label);
+                ui.text_edit_singleline(s);
+                ui.end_row();
+            }
+            ConfigValue::StringList(v) => {
+                ui.label(label);
+                if ui.button("Add Item").clicked() {
+                    v.push(String::new());
+                }
+                ui.end_row();
+
+                for (i, s) in v.iter_mut().enumerate() {
+                    ui.label(format!("Item {i}"));
+                    ui.text_edit_singleline(s);
+                    ui.end_row();
+                }
+            }
+        }
+    }
+}
 
 // Utility function to get an egui margin inset from the left.
 fn indented(px: i8) -> Margin {
@@ -15,11 +62,27 @@ fn indented(px: i8) -> Margin {
     m
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct UxPlugin {}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxPlugin {
+    // Log viewer filters; shared by all plugins so that searching for a url finds it anywhere.
+    log_level: usize,
+    log_search: String,
+    log_timestamps: bool,
+}
+
+impl Default for UxPlugin {
+    fn default() -> Self {
+        Self {
+            log_level: Level::Trace as usize,
+            log_search: String::new(),
+            log_timestamps: true,
+        }
+    }
+}
 
 impl UxPlugin {
-    pub fn ui(&self, sync: &mut PluginHost, mut tutorial: Tutorial<'_>, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, sync: &mut PluginHost, mut tutorial: Tutorial<'_>, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
             .show(ui, |ui| {
@@ -36,7 +99,10 @@ impl UxPlugin {
                     });
                 }
 
-                for plugin in sync.plugins_mut() {
+                let (mut limits_changed, mut exclusions_changed) = (false, false);
+                let mut disable = None;
+                let usages = sync.plugins().map(|p| sync.storage_usage(p)).collect::<Vec<_>>();
+                for (plugin, usage) in sync.plugins_mut().zip(usages) {
                     let name = plugin.name();
                     if tutorial.is_plugin_refresh_step(&name) {
                         tutorial.frame(ui, |ui, tutorial| {
@@ -52,22 +118,117 @@ impl UxPlugin {
                         if tutorial.add(tutorial.is_plugin_refresh_step(&name), ui, egui::Button::new("⟳ Tags")).clicked() {
                             plugin.refresh_tags();
                         }
+                        if ui
+                            .button("⟳ Reprocess")
+                            .on_hover_text("Re-build works from their kept source records, without fetching them again")
+                            .clicked()
+                        {
+                            plugin.reprocess_sources();
+                        }
+                        let actions = plugin
+                            .metadata()
+                            .map(|metadata| {
+                                metadata
+                                    .actions()
+                                    .iter()
+                                    .filter(|action| action.scope() == ActionScope::Plugin)
+                                    .cloned()
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or_default();
+                        for action in &actions {
+                            let button = ui.button(action.label());
+                            let button = if action.description().is_empty() {
+                                button
+                            } else {
+                                button.on_hover_text(action.description())
+                            };
+                            if button.clicked() {
+                                plugin.run_action(action, None);
+                            }
+                        }
 
                         plugin.progress().ui(ui);
+                        Self::show_plugin_health_badge(ui, plugin);
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .small_button("Disable")
+                                .on_hover_text("Stop this plugin and don't load it again; its queued tasks are dropped")
+                                .clicked()
+                            {
+                                disable = Some(plugin.source().to_owned());
+                            }
+                        });
+
+                        if let Some(status) = plugin.rate_limit_status()
+                            && status.is_paused()
+                        {
+                            let until = Timestamp::now()
+                                .checked_add(status.paused_for())
+                                .unwrap_or_else(|_| Timestamp::now())
+                                .to_zoned(TimeZone::system())
+                                .strftime("%H:%M:%S")
+                                .to_string();
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!("Throttled until {until}"),
+                            )
+                            .on_hover_text("The source asked us to slow down; downloads will resume automatically.");
+                        }
                     });
                     egui::Frame::new()
                         .inner_margin(indented(16))
                         .show(ui, |ui| {
-                            Self::show_plugin_details(ui, plugin);
+                            let (limits, exclusions) = Self::show_plugin_details(ui, plugin, usage);
+                            limits_changed |= limits;
+                            exclusions_changed |= exclusions;
+                            Self::show_plugin_tag_kinds(ui, plugin);
                             Self::show_plugin_tasks(ui, plugin);
-                            Self::show_plugin_logs(ui, plugin);
+                            self.show_plugin_logs(ui, plugin);
                         });
                     ui.separator();
                 }
+                if let Some(source) = disable
+                    && let Err(e) = sync.disable_plugin(&source)
+                {
+                    error!("Failed to stop the plugin at {}: {e}", source.display());
+                }
+                let disabled = sync.disabled_plugins().map(str::to_owned).collect::<Vec<_>>();
+                if !disabled.is_empty() {
+                    ui.heading("Disabled");
+                    for file_name in disabled {
+                        ui.horizontal(|ui| {
+                            ui.label(&file_name);
+                            if ui
+                                .small_button("Enable")
+                                .on_hover_text("Loads the next time Artchiver starts")
+                                .clicked()
+                            {
+                                sync.enable_plugin(&file_name);
+                            }
+                        });
+                    }
+                }
+                if limits_changed {
+                    sync.apply_download_limits();
+                    sync.apply_fetch_policies();
+                }
+                if exclusions_changed {
+                    sync.apply_tag_exclusions();
+                }
+                sync.apply_tag_kinds();
             });
     }
 
-    fn show_plugin_details(ui: &mut egui::Ui, plugin: &mut PluginHandle) {
+    // Returns whether the plugin's download settings (limits and fetch policy) and its tag
+    // exclusions changed.
+    fn show_plugin_details(
+        ui: &mut egui::Ui,
+        plugin: &mut PluginHandle,
+        usage: Option<u64>,
+    ) -> (bool, bool) {
+        let (mut limits_changed, mut exclusions_changed) = (false, false);
         egui::CollapsingHeader::new("Details")
             .id_salt(format!("details_section_{}", plugin.name()))
             .show(ui, |ui| -> anyhow::Result<()> {
@@ -81,29 +242,32 @@ impl UxPlugin {
                         ui.label("Version");
                         ui.label(plugin.version());
                         ui.end_row();
+                        if let Some(status) = plugin.rate_limit_status() {
+                            ui.label("Rate");
+                            ui.label(status.to_string());
+                            ui.end_row();
+                        }
+                        Self::show_plugin_health_rows(ui, plugin);
+                        if let Some(usage) = usage {
+                            ui.label("Storage");
+                            match plugin.storage_quota_bytes() {
+                                Some(quota) if usage >= quota => ui
+                                    .colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!("{} of {}", format_size(usage), format_size(quota)),
+                                    )
+                                    .on_hover_text("Downloads are paused until there is room"),
+                                Some(quota) => ui.label(format!(
+                                    "{} of {}",
+                                    format_size(usage),
+                                    format_size(quota)
+                                )),
+                                None => ui.label(format_size(usage)),
+                            };
+                            ui.end_row();
+                        }
                         if let Some(meta) = plugin.metadata_mut() {
-                            for (config_key, config_val) in meta.configurations_mut() {
-                                match config_val {
-                                    ConfigValue::String(s) => {
-                                        ui.label(config_key);
-                                        ui.text_edit_singleline(s);
-                                        ui.end_row();
-                                    }
-                                    ConfigValue::StringList(v) => {
-                                        ui.label(config_key);
-                                        if ui.button("Add Item").clicked() {
-                                            v.push(String::new());
-                                        }
-                                        ui.end_row();
-
-                                        for (i, s) in v.iter_mut().enumerate() {
-                                            ui.label(format!("Item {i}"));
-                                            ui.text_edit_singleline(s);
-                                            ui.end_row();
-                                        }
-                                    }
-                                }
-                            }
+                            configuration_rows(meta, false, ui);
                             if !meta.configurations().is_empty() {
                                 ui.label("");
                                 if ui.button("Update").clicked() {
@@ -114,8 +278,163 @@ impl UxPlugin {
                         Ok(())
                     })
                     .inner?;
+
+                let name = plugin.name();
+                let limits = plugin.download_limits_mut();
+                let mut has_override = limits.is_some();
+                if ui
+                    .checkbox(&mut has_override, "Override download limits")
+                    .changed()
+                {
+                    *limits = has_override.then(DownloadLimits::default);
+                    limits_changed = true;
+                }
+                if let Some(limits) = limits {
+                    limits_changed |= limits.ui(&name, ui);
+                }
+
+                ui.horizontal(|ui| {
+                    let quota = plugin.storage_quota_gib_mut();
+                    let mut has_quota = quota.is_some();
+                    if ui
+                        .checkbox(&mut has_quota, "Limit storage to")
+                        .on_hover_text(
+                            "Downloads pause once the plugin's works fill this much disk",
+                        )
+                        .changed()
+                    {
+                        *quota = has_quota.then_some(10);
+                        limits_changed = true;
+                    }
+                    if let Some(gib) = quota {
+                        limits_changed |= ui
+                            .add(egui::DragValue::new(gib).range(1..=100_000).suffix(" GiB"))
+                            .changed();
+                    }
+                });
+
+                ui.label("Fetch");
+                limits_changed |= plugin.fetch_policy_mut().ui(&name, ui);
+
+                ui.label("Skip tags matching")
+                    .on_hover_text("Matching tags are left out the next time tags are refreshed");
+                exclusions_changed |= plugin.tag_exclusions_mut().ui(&name, ui);
                 Ok(())
             });
+        (limits_changed, exclusions_changed)
+    }
+
+    // A dot that is red when most of the plugin's recent jobs failed, and yellow when some did.
+    fn show_plugin_health_badge(ui: &mut egui::Ui, plugin: &PluginHandle) {
+        let health = plugin.health();
+        let Some(error_rate) = health.error_rate() else {
+            return;
+        };
+        let color = if error_rate >= 0.5 {
+            ui.visuals().error_fg_color
+        } else if error_rate > 0. {
+            ui.visuals().warn_fg_color
+        } else {
+            egui::Color32::from_rgb(0x40, 0xa0, 0x40)
+        };
+        ui.colored_label(color, "●").on_hover_text(format!(
+            "{} of the last {} jobs failed",
+            health.failed_count(),
+            health.job_count()
+        ));
+    }
+
+    fn show_plugin_health_rows(ui: &mut egui::Ui, plugin: &PluginHandle) {
+        let when = |at: Option<Timestamp>| {
+            at.map(|at| {
+                at.to_zoned(TimeZone::system())
+                    .strftime("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "never".to_owned())
+        };
+        let health = plugin.health();
+        ui.label("Tags refreshed");
+        ui.label(when(health.last_tags_refresh()));
+        ui.end_row();
+        ui.label("Works refreshed");
+        ui.label(when(health.last_works_refresh()));
+        ui.end_row();
+        if let Some(error_rate) = health.error_rate() {
+            ui.label("Errors");
+            let text = format!(
+                "{:.0}% ({} of the last {} jobs)",
+                error_rate * 100.,
+                health.failed_count(),
+                health.job_count()
+            );
+            if error_rate > 0. {
+                ui.colored_label(ui.visuals().warn_fg_color, text);
+            } else {
+                ui.label(text);
+            }
+            ui.end_row();
+        }
+        if let Some(traffic) = plugin.throttle_traffic()
+            && let Some(latency) = traffic.average_latency()
+        {
+            ui.label("Latency");
+            ui.label(format!(
+                "{}ms average over {} requests",
+                latency.as_millis(),
+                traffic.requests
+            ))
+            .on_hover_text("Since the plugin started");
+            ui.end_row();
+            ui.label("Throttled");
+            ui.label(format!("{}s", traffic.throttled.as_secs()))
+                .on_hover_text(
+                    "Time spent waiting on the rate limit, or for the source to let us back in, \
+                     since the plugin started",
+                );
+            ui.end_row();
+        }
+    }
+
+    fn show_plugin_tag_kinds(ui: &mut egui::Ui, plugin: &mut PluginHandle) {
+        if plugin.tag_kinds().is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new("Tag Kinds")
+            .id_salt(format!("tag_kinds_section_{}", plugin.name()))
+            .show(ui, |ui| {
+                ui.label("Applied the next time tags are refreshed");
+                let mut changed = None;
+                egui::Grid::new(format!("tag_kinds_grid_{}", plugin.name()))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for mapping in plugin.tag_kinds() {
+                            let label = ui.label(&mapping.source_type);
+                            if !mapping.edited {
+                                label.on_hover_text("As guessed by the plugin");
+                            }
+                            let mut kind = mapping.kind;
+                            egui::ComboBox::from_id_salt(format!(
+                                "tag_kind_{}_{}",
+                                plugin.name(),
+                                mapping.source_type
+                            ))
+                            .selected_text(kind.to_string())
+                            .show_ui(ui, |ui| {
+                                for option in TagKind::ALL {
+                                    ui.selectable_value(&mut kind, option, option.to_string());
+                                }
+                            });
+                            if kind != mapping.kind {
+                                changed = Some((mapping.source_type.clone(), kind));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some((source_type, kind)) = changed {
+                    plugin.set_tag_kind(&source_type, kind);
+                }
+            });
     }
 
     fn show_plugin_tasks(ui: &mut egui::Ui, plugin: &mut PluginHandle) {
@@ -175,21 +494,86 @@ impl UxPlugin {
             });
     }
 
-    fn show_plugin_logs(ui: &mut egui::Ui, plugin: &PluginHandle) {
+    fn show_plugin_logs(&mut self, ui: &mut egui::Ui, plugin: &PluginHandle) {
         egui::CollapsingHeader::new("Logs")
             .id_salt(format!("logs_section_{}", plugin.name()))
             .show(ui, |ui| {
-                for (level, message) in plugin.log_messages() {
-                    let msg = egui::RichText::new(message);
-                    let msg = match level {
-                        Level::Error => msg.strong().color(egui::Color32::RED),
-                        Level::Warn => msg.color(egui::Color32::YELLOW),
-                        Level::Info => msg.color(egui::Color32::GREEN),
-                        Level::Debug => msg.color(egui::Color32::LIGHT_BLUE),
-                        Level::Trace => msg.color(egui::Color32::LIGHT_GRAY),
-                    };
-                    ui.add(egui::Label::new(msg).wrap_mode(TextWrapMode::Truncate));
+                ui.horizontal(|ui| {
+                    egui::ComboBox::new(format!("log_level_{}", plugin.name()), "")
+                        .selected_text(
+                            Level::iter()
+                                .nth(self.log_level.saturating_sub(1))
+                                .unwrap_or(Level::Trace)
+                                .as_str(),
+                        )
+                        .show_ui(ui, |ui| {
+                            for level in Level::iter() {
+                                ui.selectable_value(
+                                    &mut self.log_level,
+                                    level as usize,
+                                    level.as_str(),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.log_search)
+                            .hint_text("Search")
+                            .desired_width(120.),
+                    );
+                    ui.checkbox(&mut self.log_timestamps, "Times");
+                });
+
+                let search = self.log_search.to_lowercase();
+                let lines = plugin
+                    .log_messages()
+                    .filter(|line| line.level() as usize <= self.log_level)
+                    .filter(|line| {
+                        search.is_empty() || line.message().to_lowercase().contains(&search)
+                    })
+                    .collect::<Vec<_>>();
+                let format_line = |line: &DbLogLine| {
+                    let mut out = String::new();
+                    if self.log_timestamps {
+                        let when = line.timestamp().to_zoned(TimeZone::system());
+                        write!(out, "{} ", when.strftime("%Y-%m-%d %H:%M:%S")).ok();
+                    }
+                    if let Some(job) = line.job() {
+                        write!(out, "[{job}] ").ok();
+                    }
+                    out.push_str(line.message());
+                    out
+                };
+
+                if ui
+                    .small_button(format!("Copy {} Lines", lines.len()))
+                    .clicked()
+                {
+                    // Note: oldest first, the way people expect to read a log file.
+                    ui.ctx().copy_text(
+                        lines
+                            .iter()
+                            .rev()
+                            .map(|line| format!("{:<5} {}", line.level(), format_line(line)))
+                            .join("\n"),
+                    );
                 }
+
+                egui::ScrollArea::vertical()
+                    .id_salt(format!("logs_scroll_{}", plugin.name()))
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            let msg = egui::RichText::new(format_line(line));
+                            let msg = match line.level() {
+                                Level::Error => msg.strong().color(egui::Color32::RED),
+                                Level::Warn => msg.color(egui::Color32::YELLOW),
+                                Level::Info => msg.color(egui::Color32::GREEN),
+                                Level::Debug => msg.color(egui::Color32::LIGHT_BLUE),
+                                Level::Trace => msg.color(egui::Color32::LIGHT_GRAY),
+                            };
+                            ui.add(egui::Label::new(msg).wrap_mode(TextWrapMode::Truncate));
+                        }
+                    });
             });
     }
 }
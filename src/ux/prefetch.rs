@@ -0,0 +1,99 @@
+// Adaptive prefetch for the gallery. We track how fast, and in which direction, the user is
+// scrolling, and reach further ahead of the motion than behind it, so that fast scrolls don't
+// outrun the cache and slow browsing doesn't spend uploads on rows we will never see.
+use std::{ops::Range, time::Instant};
+
+#[derive(Debug, Default)]
+pub struct ScrollPrefetch {
+    // The scroll position, in rows, when we last looked.
+    last: Option<(f32, Instant)>,
+
+    // Smoothed velocity in rows per second; positive is down the gallery.
+    velocity: f32,
+}
+
+impl ScrollPrefetch {
+    // How many seconds of motion to fetch ahead of.
+    const LOOKAHEAD_SECS: f32 = 0.75;
+    const SMOOTHING: f32 = 0.3;
+    // Past this, we would just be churning the LRU.
+    const MAX_AHEAD_ROWS: usize = 24;
+    // Note: we don't repaint while idle, so a long gap means the scroll stopped.
+    const IDLE_SECS: f32 = 0.5;
+
+    pub fn observe(&mut self, row: f32, now: Instant) {
+        if let Some((prior_row, prior_time)) = self.last {
+            let dt = now.saturating_duration_since(prior_time).as_secs_f32();
+            if dt > Self::IDLE_SECS {
+                self.velocity = 0.;
+            } else if dt > 0. {
+                let current = (row - prior_row) / dt;
+                self.velocity += (current - self.velocity) * Self::SMOOTHING;
+            }
+        }
+        self.last = Some((row, now));
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    // The rows we want cached, most urgent first: the visible rows, then the rows about to
+    // scroll into view, then a few behind us in case the user turns around.
+    pub fn plan(&self, visible_rows: Range<usize>, n_rows: usize) -> Vec<usize> {
+        let page = visible_rows.len().max(1);
+        let speed = self.velocity.abs();
+        let ahead = (page + (speed * Self::LOOKAHEAD_SECS).ceil() as usize)
+            .min(Self::MAX_AHEAD_ROWS.max(page));
+        let behind = if speed > page as f32 { 1 } else { page };
+
+        let moving_up = self.velocity < 0.;
+        let (n_below, n_above) = if moving_up {
+            (behind, ahead)
+        } else {
+            (ahead, behind)
+        };
+        let below = visible_rows.end..visible_rows.end.saturating_add(n_below).min(n_rows);
+        let above = (visible_rows.start.saturating_sub(n_above)..visible_rows.start).rev();
+        let (leading, trailing) = if moving_up {
+            (above.collect::<Vec<_>>(), below.collect::<Vec<_>>())
+        } else {
+            (below.collect(), above.collect())
+        };
+
+        visible_rows.chain(leading).chain(trailing).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ScrollPrefetch;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_idle_plan_is_symmetric() {
+        let prefetch = ScrollPrefetch::default();
+        let plan = prefetch.plan(2..4, 50);
+        assert_eq!(plan, vec![2, 3, 4, 5, 1, 0]);
+    }
+
+    #[test]
+    fn test_plan_leads_motion() {
+        let mut prefetch = ScrollPrefetch::default();
+        let start = Instant::now();
+        for i in 0..10 {
+            prefetch.observe(i as f32, start + Duration::from_millis(i * 100));
+        }
+        assert!(prefetch.velocity() > 5.);
+
+        // Scrolling down: visible first, then well ahead, then only one row behind.
+        let plan = prefetch.plan(10..12, 100);
+        assert_eq!(&plan[..4], &[10, 11, 12, 13]);
+        assert_eq!(plan.last(), Some(&9));
+        assert!(plan.len() > 5);
+
+        // Stopping for a while resets the motion.
+        prefetch.observe(10., start + Duration::from_secs(5));
+        assert_eq!(prefetch.plan(10..12, 14), vec![10, 11, 12, 13, 9, 8]);
+    }
+}
@@ -0,0 +1,101 @@
+// Keeps a filtered and sorted projection of a larger set up to date as members change, without
+// re-filtering and re-sorting everything. Imports deliver works in chunks and downloads finish
+// one at a time, so a full rebuild on every change stalls the UX once the set gets large.
+use std::{cmp::Ordering, collections::HashSet, hash::Hash, mem};
+
+// Drops the `changed` items from `projection`, then merges back the ones in `fresh`: those that
+// still pass the filter. The rest of `projection` must still be in order under `cmp`.
+pub fn apply_changes<T, F>(
+    projection: &mut Vec<T>,
+    changed: &HashSet<T>,
+    mut fresh: Vec<T>,
+    mut cmp: F,
+) where
+    T: Copy + Eq + Hash,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if !changed.is_empty() {
+        projection.retain(|item| !changed.contains(item));
+    }
+    if fresh.is_empty() {
+        return;
+    }
+    fresh.sort_by(&mut cmp);
+
+    // Note: the common case is appending a chunk that sorts after everything we have.
+    if projection
+        .last()
+        .is_none_or(|last| cmp(last, &fresh[0]) != Ordering::Greater)
+    {
+        projection.extend(fresh);
+        return;
+    }
+
+    let prior = mem::take(projection);
+    projection.reserve(prior.len() + fresh.len());
+    let mut prior = prior.into_iter().peekable();
+    let mut fresh = fresh.into_iter().peekable();
+    while let (Some(a), Some(b)) = (prior.peek(), fresh.peek()) {
+        if cmp(a, b) == Ordering::Greater {
+            projection.extend(fresh.next());
+        } else {
+            projection.extend(prior.next());
+        }
+    }
+    projection.extend(prior);
+    projection.extend(fresh);
+}
+
+#[cfg(test)]
+mod test {
+    use super::apply_changes;
+    use std::{collections::HashSet, time::Instant};
+
+    #[test]
+    fn test_apply_changes_matches_full_sort() {
+        let mut projection = vec![2, 4, 6, 8];
+        // 4 went away, 6 still passes, and 5 and 9 are new.
+        let changed = HashSet::from([4, 5, 6, 9]);
+        apply_changes(&mut projection, &changed, vec![9, 6, 5], |a, b| a.cmp(b));
+        assert_eq!(projection, vec![2, 5, 6, 8, 9]);
+
+        // Descending, appended past the end.
+        let mut projection = vec![9, 7];
+        apply_changes(&mut projection, &HashSet::new(), vec![1, 3], |a, b| {
+            b.cmp(a)
+        });
+        assert_eq!(projection, vec![9, 7, 3, 1]);
+    }
+
+    // A benchmark for the import path: run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn bench_chunked_import() {
+        const CHUNK: u64 = 1_000;
+        const CHUNKS: u64 = 200;
+        // Interleave the chunks so that every merge has to walk the whole projection.
+        let chunk = |c: u64| (0..CHUNK).map(move |i| i * CHUNKS + c).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut projection = Vec::new();
+        for c in 0..CHUNKS {
+            // Note: new works were never in the projection, so there is nothing to drop.
+            apply_changes(&mut projection, &HashSet::new(), chunk(c), |a, b| a.cmp(b));
+        }
+        let incremental = start.elapsed();
+
+        let start = Instant::now();
+        let mut all = Vec::new();
+        for c in 0..CHUNKS {
+            all.extend(chunk(c));
+            let mut resorted = all.clone();
+            resorted.sort_unstable();
+            projection = resorted;
+        }
+        let full = start.elapsed();
+
+        println!("incremental: {incremental:?}, full re-sort: {full:?}");
+        assert!(projection.is_sorted());
+        assert!(incremental < full);
+    }
+}
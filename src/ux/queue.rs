@@ -0,0 +1,194 @@
+// The play queue: episodes and videos picked out of the gallery to play one after another in the
+// slideshow. It is kept across runs, so that a half-listened feed picks up where it left off.
+use crate::db::models::work::{DbWork, MediaType};
+use egui_dnd::dnd;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedWork {
+    screen_url: String,
+    name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayQueue {
+    items: Vec<QueuedWork>,
+    // The item we are playing from the queue, which comes off of it once it has played out.
+    playing: Option<String>,
+
+    // An item to show in the slideshow, for the gallery to find.
+    #[serde(skip)]
+    request: Option<String>,
+}
+
+impl PlayQueue {
+    // Only things that play can go in the queue.
+    pub fn can_enqueue(work: &DbWork) -> bool {
+        matches!(work.media_type(), Some(MediaType::Video | MediaType::Audio))
+    }
+
+    pub fn contains(&self, screen_url: &str) -> bool {
+        self.items.iter().any(|item| item.screen_url == screen_url)
+    }
+
+    pub fn enqueue(&mut self, work: &DbWork) {
+        if !self.contains(work.screen_url()) {
+            self.items.push(QueuedWork {
+                screen_url: work.screen_url().to_owned(),
+                name: work.name().to_owned(),
+            });
+        }
+    }
+
+    pub fn remove(&mut self, screen_url: &str) {
+        self.items.retain(|item| item.screen_url != screen_url);
+        if self.playing.as_deref() == Some(screen_url) {
+            self.playing = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.playing = None;
+    }
+
+    pub fn playing(&self) -> Option<&str> {
+        self.playing.as_deref()
+    }
+
+    // Start playing the queue from the given item, skipping over any ahead of it.
+    pub fn play(&mut self, screen_url: &str) {
+        let Some(offset) = self
+            .items
+            .iter()
+            .position(|item| item.screen_url == screen_url)
+        else {
+            return;
+        };
+        // Note: the item playing stays at the head of the queue until it is done.
+        let item = self.items.remove(offset);
+        self.items.insert(0, item);
+        self.playing = Some(screen_url.to_owned());
+        self.request = Some(screen_url.to_owned());
+    }
+
+    // Called when a work plays out. If it was the one playing from the queue, it comes off and the
+    // next one is requested; returns whether we moved on.
+    pub fn finished(&mut self, screen_url: &str) -> bool {
+        if self.playing.as_deref() != Some(screen_url) {
+            return false;
+        }
+        self.items.retain(|item| item.screen_url != screen_url);
+        self.playing = self.items.first().map(|item| item.screen_url.clone());
+        self.request.clone_from(&self.playing);
+        self.playing.is_some()
+    }
+
+    // The work to show next, if we've moved along the queue since the last call.
+    pub fn take_request(&mut self) -> Option<String> {
+        self.request.take()
+    }
+
+    // Returns true if the user asked to start playing.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(first) = self.items.first().map(|item| item.screen_url.clone()) else {
+            ui.label("Nothing queued. Right-click an episode or video in the gallery to add it.");
+            return false;
+        };
+        let mut started = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} queued", self.items.len()));
+            if ui.button("▶ Play").clicked() {
+                self.play(&first);
+                started = true;
+            }
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+        ui.separator();
+
+        let mut removed = None;
+        let mut play = None;
+        let resp = egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                dnd(ui, "play_queue").show(self.items.iter(), |ui, item, handle, _state| {
+                    handle.ui(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                                removed = Some(item.screen_url.clone());
+                            }
+                            if ui
+                                .small_button("▶")
+                                .on_hover_text("Play from here")
+                                .clicked()
+                            {
+                                play = Some(item.screen_url.clone());
+                            }
+                            let mut name = egui::RichText::new(&item.name);
+                            if self.playing.as_deref() == Some(item.screen_url.as_str()) {
+                                name = name.strong();
+                            }
+                            ui.add(egui::Label::new(name).truncate())
+                                .on_hover_text(&item.screen_url);
+                        });
+                    });
+                })
+            })
+            .inner;
+        if let Some(screen_url) = removed {
+            self.remove(&screen_url);
+        } else if let Some(screen_url) = play {
+            self.play(&screen_url);
+            started = true;
+        } else {
+            resp.update_vec(&mut self.items);
+        }
+        started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(urls: &[&str]) -> PlayQueue {
+        PlayQueue {
+            items: urls
+                .iter()
+                .map(|url| QueuedWork {
+                    screen_url: (*url).to_owned(),
+                    name: (*url).to_owned(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_play_and_advance() {
+        let mut queue = queue(&["a", "b", "c"]);
+        // Works that were not started from the queue don't move it along.
+        assert!(!queue.finished("a"));
+        assert_eq!(queue.take_request(), None);
+
+        queue.play("b");
+        assert_eq!(queue.take_request().as_deref(), Some("b"));
+        assert_eq!(queue.playing(), Some("b"));
+        assert!(queue.finished("b"));
+        assert_eq!(queue.take_request().as_deref(), Some("a"));
+        assert!(!queue.contains("b"));
+
+        queue.remove("a");
+        assert_eq!(queue.playing(), None);
+        assert!(!queue.finished("c"));
+        assert!(queue.contains("c"));
+
+        queue.play("c");
+        assert!(!queue.finished("c"));
+        assert!(!queue.contains("c"));
+        assert_eq!(queue.playing(), None);
+    }
+}
@@ -0,0 +1,257 @@
+// Compare the work in the slideshow against an image from outside the archive, e.g. a photograph
+// of a print, to check its state against a known scan. The reference is painted over the work,
+// into the same rect, so that it follows the slideshow's zoom and pan.
+//
+// Note: the reference is stretched to the work's shape; a photo of a print will never line up
+//       exactly, but stretching keeps the two close enough to see what differs.
+use crate::plugin::thumbnail::open_image;
+use crossbeam::channel::{Receiver, bounded};
+use egui::{Color32, ColorImage, Rect, TextureHandle, TextureOptions, load::SizedTexture};
+use image::{RgbaImage, imageops::FilterType};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    thread,
+};
+
+// The long edge we compute differences at; the pixels beyond this are more noise than signal
+// when comparing against a photograph.
+const DIFFERENCE_SIZE: u32 = 2048;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CompareMode {
+    #[default]
+    Blend,
+    Difference,
+}
+
+// The absolute difference of each channel, made opaque, so that matching areas go black and
+// changes show up bright. `reference` must already be the size of `work`.
+fn difference(work: &RgbaImage, reference: &RgbaImage) -> RgbaImage {
+    let mut out = work.clone();
+    for (pixel, other) in out.pixels_mut().zip(reference.pixels()) {
+        for (value, other) in pixel.0.iter_mut().zip(other.0).take(3) {
+            *value = value.abs_diff(other);
+        }
+        pixel.0[3] = u8::MAX;
+    }
+    out
+}
+
+fn load_difference(work: &Path, reference: &Path) -> Result<ColorImage, String> {
+    let work = open_image(work)
+        .map_err(|e| e.to_string())?
+        .thumbnail(DIFFERENCE_SIZE, DIFFERENCE_SIZE)
+        .to_rgba8();
+    let reference = open_image(reference)
+        .map_err(|e| e.to_string())?
+        .resize_exact(work.width(), work.height(), FilterType::Triangle)
+        .to_rgba8();
+    let diff = difference(&work, &reference);
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [diff.width() as usize, diff.height() as usize],
+        diff.as_raw(),
+    ))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxReference {
+    mode: CompareMode,
+    opacity: f32,
+
+    #[serde(skip)]
+    active: bool,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    // The difference image, for the work and reference files it was made from.
+    #[serde(skip)]
+    difference: Option<((PathBuf, PathBuf), Result<TextureHandle, String>)>,
+    #[serde(skip)]
+    pending: Option<((PathBuf, PathBuf), Receiver<Result<ColorImage, String>>)>,
+}
+
+impl Default for UxReference {
+    fn default() -> Self {
+        Self {
+            mode: CompareMode::default(),
+            opacity: 0.5,
+            active: false,
+            path: None,
+            difference: None,
+            pending: None,
+        }
+    }
+}
+
+impl UxReference {
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    fn clear(&mut self, ctx: &egui::Context) {
+        if let Some(path) = self.path.take() {
+            ctx.forget_image(&format!("file://{}", path.display()));
+        }
+        self.difference = None;
+        self.pending = None;
+    }
+
+    // Any image file dropped on the slideshow becomes the reference, and opens the comparison.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (hovering, dropped) = ctx.input(|input| {
+            (
+                !input.raw.hovered_files.is_empty(),
+                input
+                    .raw
+                    .dropped_files
+                    .iter()
+                    .find_map(|file| file.path.clone()),
+            )
+        });
+        if hovering {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("reference_drop_target"),
+            ));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0., Color32::from_black_alpha(192));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop to compare with this work",
+                egui::FontId::proportional(32.),
+                Color32::WHITE,
+            );
+        }
+        if let Some(path) = dropped {
+            self.clear(ctx);
+            self.path = Some(path);
+            self.active = true;
+        }
+    }
+
+    fn difference_texture(&mut self, work: &Path, ctx: &egui::Context) -> Option<TextureHandle> {
+        let reference = self.path.clone()?;
+        let key = (work.to_owned(), reference);
+        if let Some((pending_key, rx)) = &self.pending
+            && let Ok(result) = rx.try_recv()
+        {
+            let name = format!("difference://{}", pending_key.1.display());
+            let result =
+                result.map(|pixels| ctx.load_texture(name, pixels, TextureOptions::LINEAR));
+            if let Err(e) = &result {
+                warn!("Failed to compare {}: {e}", pending_key.0.display());
+            }
+            self.difference = Some((pending_key.clone(), result));
+            self.pending = None;
+        }
+        if let Some((made_from, result)) = &self.difference
+            && *made_from == key
+        {
+            return result.as_ref().ok().cloned();
+        }
+        if self
+            .pending
+            .as_ref()
+            .is_none_or(|(pending, _)| *pending != key)
+        {
+            let (tx, rx) = bounded(1);
+            let (work, reference) = key.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                tx.send(load_difference(&work, &reference)).ok();
+                ctx.request_repaint();
+            });
+            self.pending = Some((key, rx));
+        }
+        None
+    }
+
+    // Paint the reference over the work at `work_path`, which the slideshow painted into `rect`.
+    pub fn ui(&mut self, work_path: Option<&Path>, rect: Rect, ui: &egui::Ui) {
+        let ctx = ui.ctx().clone();
+        self.handle_dropped_files(&ctx);
+        if !self.active {
+            return;
+        }
+
+        if let Some(reference) = self.path.clone() {
+            match (self.mode, work_path) {
+                (CompareMode::Blend, _) => {
+                    egui::Image::new(format!("file://{}", reference.display()))
+                        .show_loading_spinner(false)
+                        .tint(Color32::WHITE.gamma_multiply(self.opacity))
+                        .paint_at(ui, rect);
+                }
+                (CompareMode::Difference, Some(work_path)) => {
+                    if let Some(handle) = self.difference_texture(work_path, &ctx) {
+                        egui::Image::from_texture(SizedTexture::from_handle(&handle))
+                            .paint_at(ui, rect);
+                    } else if self.pending.is_some() {
+                        egui::Spinner::new().paint_at(
+                            ui,
+                            Rect::from_center_size(rect.center(), egui::Vec2::splat(32.)),
+                        );
+                    }
+                }
+                // Note: a work that is not an image file has no pixels to compare against.
+                (CompareMode::Difference, None) => {}
+            }
+        }
+
+        egui::Area::new(egui::Id::new("reference_controls"))
+            .anchor(egui::Align2::CENTER_TOP, [0., 8.])
+            .show(&ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let Some(reference) = &self.path else {
+                            ui.label("Drop a photograph or scan here to compare it with the work");
+                            return;
+                        };
+                        let name = reference
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        ui.label(name)
+                            .on_hover_text(reference.display().to_string());
+                        ui.selectable_value(&mut self.mode, CompareMode::Blend, "Blend");
+                        ui.selectable_value(&mut self.mode, CompareMode::Difference, "Difference");
+                        if self.mode == CompareMode::Blend {
+                            ui.add(
+                                egui::Slider::new(&mut self.opacity, 0f32..=1f32)
+                                    .text("Reference")
+                                    .show_value(false),
+                            );
+                        }
+                        if let Some(Err(e)) = self.difference.as_ref().map(|(_, result)| result)
+                            && self.mode == CompareMode::Difference
+                        {
+                            ui.colored_label(ui.visuals().error_fg_color, e);
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.clear(ui.ctx());
+                        }
+                    });
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_difference() {
+        let work = RgbaImage::from_pixel(2, 1, Rgba([200, 100, 0, 255]));
+        let mut reference = work.clone();
+        reference.put_pixel(1, 0, Rgba([100, 150, 0, 255]));
+        let diff = difference(&work, &reference);
+        assert_eq!(diff.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(diff.get_pixel(1, 0), &Rgba([100, 50, 0, 255]));
+    }
+}
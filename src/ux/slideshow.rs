@@ -0,0 +1,453 @@
+// Auto-advance and transitions for the slideshow. Navigation itself still lives in UxWork; this
+// only decides when to move on, and how to paint the move.
+use crate::plugin::thumbnail::open_image;
+use egui::{Color32, Rect, Vec2};
+use rand::seq::SliceRandom as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::Range,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// The grid that we look for the busiest part of an image in.
+const SALIENCY_GRID: u32 = 8;
+
+// The size of a cell in the filmstrip.
+pub const FILMSTRIP_THUMB: f32 = 96.;
+
+// The offsets of the `count` works to put in the filmstrip, with the current work in the middle
+// where there are enough works to either side of it.
+pub fn filmstrip_window(current: usize, len: usize, count: usize) -> Range<usize> {
+    let count = count.min(len);
+    let start = current.saturating_sub(count / 2).min(len - count);
+    start..start + count
+}
+
+// Find the busiest cell of the image, by edge energy, as a point from (0,0) to (1,1). This is
+// a crude stand-in for real subject detection, but it steers well away from empty sky and
+// backgrounds, which is most of what makes a pan look aimless.
+pub fn salient_point(path: &Path) -> Option<Vec2> {
+    let gray = open_image(path)
+        .ok()?
+        .thumbnail(SALIENCY_GRID * 8, SALIENCY_GRID * 8)
+        .to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return None;
+    }
+    let px = |x: u32, y: u32| f32::from(gray.get_pixel(x, y).0[0]);
+    let mut energy = [0f32; (SALIENCY_GRID * SALIENCY_GRID) as usize];
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let gx = px(x + 1, y) - px(x - 1, y);
+            let gy = px(x, y + 1) - px(x, y - 1);
+            let cell = (y * SALIENCY_GRID / height) * SALIENCY_GRID + x * SALIENCY_GRID / width;
+            energy[cell as usize] += gx.abs() + gy.abs();
+        }
+    }
+    let (best, _) = energy
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    let grid = SALIENCY_GRID as usize;
+    Some(Vec2::new(
+        ((best % grid) as f32 + 0.5) / grid as f32,
+        ((best / grid) as f32 + 0.5) / grid as f32,
+    ))
+}
+
+// A slow zoom and pan across a work, from one framing to another, over the course of a slide.
+// Framings are a zoom and a point in the image, from (0,0) to (1,1), to center on.
+#[derive(Clone, Copy, Debug)]
+struct KenBurns {
+    from: (f32, Vec2),
+    to: (f32, Vec2),
+}
+
+impl KenBurns {
+    const ZOOM: f32 = 1.3;
+
+    // Pan along the long axis of the image, relative to the screen, since that is the axis with
+    // the most to see once we zoom in; or push in on the salient point, if we have one.
+    fn plan(fill: Vec2, salient: Option<Vec2>) -> Self {
+        let center = Vec2::splat(0.5);
+        let plan = if let Some(point) = salient {
+            Self {
+                from: (1., center),
+                to: (Self::ZOOM + 0.1, point),
+            }
+        } else if fill.x >= fill.y {
+            Self {
+                from: (Self::ZOOM, Vec2::new(0.3, 0.5)),
+                to: (Self::ZOOM, Vec2::new(0.7, 0.5)),
+            }
+        } else {
+            Self {
+                from: (Self::ZOOM, Vec2::new(0.5, 0.3)),
+                to: (Self::ZOOM, Vec2::new(0.5, 0.7)),
+            }
+        };
+        if rand::random::<bool>() {
+            Self {
+                from: plan.to,
+                to: plan.from,
+            }
+        } else {
+            plan
+        }
+    }
+
+    fn at(&self, t: f32) -> (f32, Vec2) {
+        // Ease in and out, so that slides don't start or stop with a jolt.
+        let t = t * t * (3. - 2. * t);
+        (
+            self.from.0 + (self.to.0 - self.from.0) * t,
+            self.from.1 + (self.to.1 - self.from.1) * t,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Transition {
+    None,
+    #[default]
+    Crossfade,
+    Slide,
+}
+
+impl Transition {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::new("slideshow_transition", "Transition")
+            .selected_text(format!("{self:?}"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(self, Self::None, "None");
+                ui.selectable_value(self, Self::Crossfade, "Crossfade");
+                ui.selectable_value(self, Self::Slide, "Slide");
+            });
+    }
+}
+
+// Which way the pages of a spread read.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SpreadDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+impl SpreadDirection {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::new("slideshow_spread_direction", "Page order")
+            .selected_text(match self {
+                Self::LeftToRight => "Left to right",
+                Self::RightToLeft => "Right to left",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(self, Self::LeftToRight, "Left to right");
+                ui.selectable_value(self, Self::RightToLeft, "Right to left");
+            });
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Slideshow {
+    // Preferences
+    auto_advance: bool,
+    interval_secs: f32,
+    transition: Transition,
+    transition_secs: f32,
+    shuffle: bool,
+    ken_burns: bool,
+    ken_burns_salient: bool,
+    filmstrip: bool,
+    // Show pages of the same volume or portfolio side by side, like an open book.
+    spread: bool,
+    spread_direction: SpreadDirection,
+
+    // Whether we are auto-advancing right now; starts out as auto_advance each time the
+    // slideshow is entered and is toggled from the HUD.
+    #[serde(skip)]
+    playing: bool,
+    #[serde(skip)]
+    shown: Option<usize>,
+    #[serde(skip)]
+    shown_at: Instant,
+
+    // The image we are transitioning away from, and where it was painted.
+    #[serde(skip)]
+    last_painted: Option<(usize, String, Rect)>,
+    #[serde(skip)]
+    outgoing: Option<(String, Rect)>,
+    #[serde(skip)]
+    transition_start: Instant,
+
+    #[serde(skip)]
+    plan: Option<(usize, KenBurns)>,
+
+    // A random permutation of the works, so that shuffle visits everything once per cycle.
+    #[serde(skip)]
+    order: Vec<usize>,
+}
+
+impl Default for Slideshow {
+    fn default() -> Self {
+        Self {
+            auto_advance: false,
+            interval_secs: 8.,
+            transition: Transition::default(),
+            transition_secs: 0.6,
+            shuffle: false,
+            ken_burns: false,
+            ken_burns_salient: true,
+            filmstrip: true,
+            spread: false,
+            spread_direction: SpreadDirection::default(),
+            playing: false,
+            shown: None,
+            shown_at: Instant::now(),
+            last_painted: None,
+            outgoing: None,
+            transition_start: Instant::now(),
+            plan: None,
+            order: Vec::new(),
+        }
+    }
+}
+
+impl Slideshow {
+    pub fn on_leave(&mut self) {
+        self.shown = None;
+        self.last_painted = None;
+        self.outgoing = None;
+        self.plan = None;
+    }
+
+    pub fn shows_filmstrip(&self) -> bool {
+        self.filmstrip
+    }
+
+    pub fn shows_spread(&self) -> bool {
+        self.spread
+    }
+
+    pub fn toggle_spread(&mut self) {
+        self.spread = !self.spread;
+    }
+
+    pub fn spread_direction(&self) -> SpreadDirection {
+        self.spread_direction
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f32(self.interval_secs.max(1.))
+    }
+
+    fn next_offset(&mut self, current: usize, len: usize) -> usize {
+        if !self.shuffle {
+            return (current + 1) % len;
+        }
+        if self.order.len() != len {
+            self.order = (0..len).collect();
+            self.order.shuffle(&mut rand::rng());
+        }
+        let pos = self
+            .order
+            .iter()
+            .position(|offset| *offset == current)
+            .unwrap_or_default();
+        self.order[(pos + 1) % len]
+    }
+
+    // Call once per frame with the work being shown. `ready` is false while a video or song is
+    // still playing, so that we don't cut it off. Returns the offset to move to, if it is time.
+    pub fn tick(
+        &mut self,
+        current: usize,
+        len: usize,
+        ready: bool,
+        ctx: &egui::Context,
+    ) -> Option<usize> {
+        if self.shown != Some(current) {
+            if self.shown.is_none() {
+                self.playing = self.auto_advance;
+            }
+            self.shown = Some(current);
+            self.shown_at = Instant::now();
+        }
+        if !self.playing || len < 2 {
+            return None;
+        }
+        let elapsed = self.shown_at.elapsed();
+        if elapsed < self.interval() {
+            ctx.request_repaint_after(self.interval().saturating_sub(elapsed));
+            return None;
+        }
+        if !ready {
+            ctx.request_repaint_after(Duration::from_millis(250));
+            return None;
+        }
+        let next = self.next_offset(current, len);
+        self.shown = Some(next);
+        self.shown_at = Instant::now();
+        Some(next)
+    }
+
+    // While auto-advancing with Ken Burns on, returns the zoom and focus point that the current
+    // work should be framed with this frame. `fill` is the fraction of the screen the image
+    // covers, unzoomed; `salient` is only called once per work.
+    pub fn ken_burns(
+        &mut self,
+        current: usize,
+        fill: Vec2,
+        salient: impl FnOnce() -> Option<Vec2>,
+        ctx: &egui::Context,
+    ) -> Option<(f32, Vec2)> {
+        if !self.ken_burns || !self.playing {
+            self.plan = None;
+            return None;
+        }
+        let plan = match self.plan {
+            Some((offset, plan)) if offset == current => plan,
+            _ => {
+                let salient = if self.ken_burns_salient {
+                    salient()
+                } else {
+                    None
+                };
+                let plan = KenBurns::plan(fill, salient);
+                self.plan = Some((current, plan));
+                plan
+            }
+        };
+        ctx.request_repaint();
+        let t = self.shown_at.elapsed().as_secs_f32() / self.interval().as_secs_f32();
+        Some(plan.at(t.min(1.)))
+    }
+
+    // Paint the current image, blending in the prior one if we are mid-transition. Images
+    // without a uri (e.g. video frames) are painted as-is and cut rather than transition.
+    pub fn paint(&mut self, ui: &egui::Ui, current: usize, img: egui::Image<'_>, rect: Rect) {
+        let uri = img.source(ui.ctx()).uri().map(str::to_owned);
+        if let Some((offset, prior_uri, prior_rect)) = self.last_painted.take()
+            && offset != current
+            && self.transition != Transition::None
+        {
+            self.outgoing = Some((prior_uri, prior_rect));
+            self.transition_start = Instant::now();
+        }
+
+        let duration = self.transition_secs.max(0.05);
+        let t = (self.transition_start.elapsed().as_secs_f32() / duration).min(1.);
+        match self.outgoing.as_ref() {
+            Some((prior_uri, prior_rect)) if t < 1. => {
+                let prior = egui::Image::new(prior_uri.as_str())
+                    .show_loading_spinner(false)
+                    .maintain_aspect_ratio(true);
+                // Ease out, so the motion settles gently.
+                let t = 1. - (1. - t) * (1. - t);
+                match self.transition {
+                    Transition::Slide => {
+                        let width = ui.max_rect().width();
+                        prior.paint_at(ui, prior_rect.translate(Vec2::new(-t * width, 0.)));
+                        img.paint_at(ui, rect.translate(Vec2::new((1. - t) * width, 0.)));
+                    }
+                    Transition::None | Transition::Crossfade => {
+                        prior
+                            .tint(Color32::WHITE.gamma_multiply(1. - t))
+                            .paint_at(ui, *prior_rect);
+                        img.tint(Color32::WHITE.gamma_multiply(t))
+                            .paint_at(ui, rect);
+                    }
+                }
+                ui.ctx().request_repaint();
+            }
+            _ => {
+                self.outgoing = None;
+                img.paint_at(ui, rect);
+            }
+        }
+        self.last_painted = uri.map(|uri| (current, uri, rect));
+    }
+
+    // The on-screen controls; only shown while the mouse is active, like the cursor.
+    pub fn hud_ui(&mut self, ctx: &egui::Context) {
+        egui::Area::new(egui::Id::new("slideshow_hud"))
+            .anchor(egui::Align2::RIGHT_TOP, [-8., 8.])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let label = if self.playing { "⏸" } else { "▶" };
+                        if ui
+                            .button(label)
+                            .on_hover_text("Advance automatically")
+                            .clicked()
+                        {
+                            self.playing = !self.playing;
+                            self.shown_at = Instant::now();
+                        }
+                        if self.playing {
+                            let progress = self.shown_at.elapsed().as_secs_f32()
+                                / self.interval().as_secs_f32();
+                            ui.add(egui::ProgressBar::new(progress.min(1.)).desired_width(80.));
+                            ui.ctx().request_repaint_after(Duration::from_millis(100));
+                        }
+                        ui.toggle_value(&mut self.shuffle, "🔀")
+                            .on_hover_text("Shuffle");
+                        ui.toggle_value(&mut self.spread, "📖")
+                            .on_hover_text("Show pages of a volume side by side (B)");
+                    });
+                });
+            });
+    }
+
+    pub fn preferences_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.auto_advance, "Advance automatically");
+        ui.add(
+            egui::Slider::new(&mut self.interval_secs, 1f32..=120f32)
+                .text("Seconds per work")
+                .logarithmic(true),
+        );
+        ui.checkbox(&mut self.shuffle, "Shuffle");
+        ui.checkbox(
+            &mut self.ken_burns,
+            "Slowly zoom and pan across works while advancing automatically",
+        );
+        ui.add_enabled(
+            self.ken_burns,
+            egui::Checkbox::new(
+                &mut self.ken_burns_salient,
+                "Zoom in on the busiest part of the work",
+            ),
+        );
+        ui.checkbox(
+            &mut self.filmstrip,
+            "Show a strip of thumbnails along the bottom while the mouse moves",
+        );
+        ui.checkbox(
+            &mut self.spread,
+            "Show the pages of volumes and portfolios as two-page spreads",
+        );
+        ui.add_enabled_ui(self.spread, |ui| self.spread_direction.ui(ui));
+        self.transition.ui(ui);
+        ui.add_enabled(
+            self.transition != Transition::None,
+            egui::Slider::new(&mut self.transition_secs, 0.1f32..=3f32).text("Transition seconds"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::filmstrip_window;
+
+    #[test]
+    fn test_filmstrip_window() {
+        assert_eq!(filmstrip_window(50, 100, 9), 46..55);
+        assert_eq!(filmstrip_window(2, 100, 9), 0..9);
+        assert_eq!(filmstrip_window(98, 100, 9), 91..100);
+        assert_eq!(filmstrip_window(3, 5, 9), 0..5);
+        assert_eq!(filmstrip_window(0, 0, 9), 0..0);
+    }
+}
@@ -0,0 +1,32 @@
+// Shown in place of the app when we can't safely start, so that the user gets an explanation
+// rather than a crash.
+pub struct StartupError {
+    heading: String,
+    message: String,
+}
+
+impl StartupError {
+    pub fn new(heading: impl ToString, message: impl ToString) -> Self {
+        Self {
+            heading: heading.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl eframe::App for StartupError {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(16.);
+                ui.heading(&self.heading);
+                ui.add_space(8.);
+                ui.label(&self.message);
+                ui.add_space(16.);
+                if ui.button("Quit").clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    db::{relocate::RelocateReport, writer::DbWriteHandle},
+    shared::{
+        blob::{S3Config, WebDavConfig},
+        progress::{Progress, UpdateSource},
+        storage::{DataKind, RootBackend, Storage, StorageConfig},
+        update::DataUpdate,
+    },
+};
+use log::error;
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// Note: the storage layout is saved by Storage itself, not with the rest of the UX state, as
+//       we need it before we can find the UX state.
+#[derive(Debug, Default)]
+pub struct UxStorage {
+    storage: Storage,
+    config: StorageConfig,
+
+    new_root_name: String,
+    new_root_path: String,
+    new_root_backend: RootBackend,
+    move_to: String,
+
+    in_progress: bool,
+    progress: Progress,
+    last_report: Option<RelocateReport>,
+    last_error: Option<String>,
+    last_offload: Option<Instant>,
+}
+
+impl UxStorage {
+    const OFFLOAD_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub fn startup(&mut self, storage: &Storage) {
+        self.storage = storage.clone();
+        self.config = storage.config();
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::StorageRelocated(report) => {
+                    self.in_progress = false;
+                    self.progress = Progress::None;
+                    self.config = self.storage.config();
+                    self.last_report = Some(report.clone());
+                }
+                DataUpdate::Progress { source, progress }
+                    if self.in_progress && source == &UpdateSource::DbWriter =>
+                {
+                    self.progress = *progress;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Apply the cold storage policy at startup and then once a day.
+    pub fn tick(&mut self, db_write: &DbWriteHandle) {
+        if self.in_progress
+            || !self.config.cold_policy().is_active()
+            || self
+                .last_offload
+                .is_some_and(|last| last.elapsed() < Self::OFFLOAD_INTERVAL)
+        {
+            return;
+        }
+        self.last_offload = Some(Instant::now());
+        self.start_job(db_write.offload_cold_files());
+    }
+
+    fn save_config(&mut self, config: StorageConfig) {
+        match self.storage.set_config(config) {
+            Ok(()) => self.last_error = None,
+            Err(e) => self.last_error = Some(e.to_string()),
+        }
+        self.config = self.storage.config();
+    }
+
+    fn start_job(&mut self, result: anyhow::Result<()>) {
+        match result {
+            Ok(()) => {
+                self.in_progress = true;
+                self.last_report = None;
+            }
+            Err(e) => error!("Failed to request storage job: {e}"),
+        }
+    }
+
+    // Note: credentials are saved in storage.json in the clear, same as the rest of the config.
+    fn backend_ui(backend: &mut RootBackend, path: &mut String, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(*backend == RootBackend::Local, "Local")
+                .clicked()
+            {
+                *backend = RootBackend::Local;
+            }
+            if ui
+                .selectable_label(matches!(backend, RootBackend::S3(_)), "S3")
+                .clicked()
+                && !matches!(backend, RootBackend::S3(_))
+            {
+                *backend = RootBackend::S3(S3Config::default());
+            }
+            if ui
+                .selectable_label(matches!(backend, RootBackend::WebDav(_)), "WebDAV")
+                .clicked()
+                && !matches!(backend, RootBackend::WebDav(_))
+            {
+                *backend = RootBackend::WebDav(WebDavConfig::default());
+            }
+            if ui
+                .selectable_label(*backend == RootBackend::Encrypted, "Encrypted")
+                .on_hover_text("A local directory, with each file sealed by the archive's key")
+                .clicked()
+            {
+                *backend = RootBackend::Encrypted;
+            }
+        });
+        let field = |ui: &mut egui::Ui, value: &mut String, hint: &str| {
+            ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+        };
+        let secret = |ui: &mut egui::Ui, value: &mut String, hint: &str| {
+            ui.add(
+                egui::TextEdit::singleline(value)
+                    .hint_text(hint)
+                    .password(true),
+            )
+            .on_hover_text("Saved unencrypted in storage.json, next to the app");
+        };
+        match backend {
+            RootBackend::Local | RootBackend::Encrypted => field(ui, path, "/mnt/nas/art"),
+            RootBackend::S3(s3) => {
+                field(ui, &mut s3.endpoint, "https://s3.us-west-2.amazonaws.com");
+                field(ui, &mut s3.bucket, "bucket");
+                field(ui, &mut s3.region, "us-west-2");
+                field(ui, &mut s3.prefix, "prefix (optional)");
+                field(ui, &mut s3.access_key, "access key");
+                secret(ui, &mut s3.secret_key, "secret key");
+            }
+            RootBackend::WebDav(dav) => {
+                field(ui, &mut dav.url, "https://cloud.example.com/dav/art");
+                field(ui, &mut dav.username, "username");
+                secret(ui, &mut dav.password, "password");
+            }
+        }
+    }
+
+    // Shown in the preferences window.
+    pub fn ui(&mut self, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.heading("Storage");
+        ui.label(format!(
+            "Data directory: {}",
+            self.storage.data_dir().display()
+        ));
+        if let Some(next) = self.config.data_dir()
+            && next != self.storage.data_dir()
+        {
+            ui.label(format!("Will use {} after restarting", next.display()));
+        }
+
+        let mut config = self.config.clone();
+        let mut remove = None;
+        egui::Grid::new("storage_roots_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for root in config.roots() {
+                    ui.label(root.name());
+                    ui.label(root.describe());
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(root.name().to_owned());
+                    }
+                    ui.end_row();
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_root_name)
+                        .hint_text("name")
+                        .desired_width(80.),
+                );
+                ui.vertical(|ui| {
+                    Self::backend_ui(&mut self.new_root_backend, &mut self.new_root_path, ui);
+                });
+                if ui.small_button("Add Root").clicked() {
+                    let name = self.new_root_name.trim();
+                    let added = match &self.new_root_backend {
+                        RootBackend::Local => {
+                            config.add_root(name, Path::new(self.new_root_path.trim()))
+                        }
+                        RootBackend::Encrypted => {
+                            config.add_encrypted_root(name, Path::new(self.new_root_path.trim()))
+                        }
+                        remote => config.add_remote_root(name, remote.clone()),
+                    };
+                    match added {
+                        Ok(()) => {
+                            self.new_root_name.clear();
+                            self.new_root_path.clear();
+                            self.new_root_backend = RootBackend::Local;
+                            self.save_config(config.clone());
+                        }
+                        Err(e) => self.last_error = Some(e.to_string()),
+                    }
+                }
+                ui.end_row();
+            });
+        if let Some(name) = remove {
+            match config.remove_root(&name) {
+                Ok(()) => self.save_config(config.clone()),
+                Err(e) => self.last_error = Some(e.to_string()),
+            }
+        }
+
+        ui.label("New downloads are stored on:");
+        egui::Grid::new("storage_rules_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for kind in DataKind::ALL {
+                    let mut rule = config.rule(kind).map(ToOwned::to_owned);
+                    ui.label(kind.to_string());
+                    egui::ComboBox::new(format!("storage_rule_{kind:?}"), "")
+                        .selected_text(rule.as_deref().unwrap_or("Data Directory"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut rule, None, "Data Directory");
+                            for root in config.roots() {
+                                ui.selectable_value(
+                                    &mut rule,
+                                    Some(root.name().to_owned()),
+                                    root.name(),
+                                );
+                            }
+                        });
+                    if rule.as_deref() != config.rule(kind) {
+                        let mut next = config.clone();
+                        next.set_rule(kind, rule);
+                        self.save_config(next);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        let mut next = config.clone();
+        if next.cold_policy_mut().ui(config.roots(), ui) {
+            self.save_config(next);
+        }
+
+        if config.roots().iter().any(|root| root.is_remote()) {
+            let mut cache_max_mb = config.cache_max_mb();
+            ui.horizontal(|ui| {
+                ui.label("Keep Up To");
+                ui.add(
+                    egui::DragValue::new(&mut cache_max_mb)
+                        .range(64..=1024 * 1024)
+                        .speed(64)
+                        .suffix(" MiB"),
+                );
+                ui.label("of remote files on this computer");
+            });
+            if cache_max_mb != config.cache_max_mb() {
+                let mut next = config.clone();
+                next.set_cache_max_mb(cache_max_mb);
+                self.save_config(next);
+            }
+        }
+
+        ui.add_enabled_ui(!self.in_progress, |ui| {
+            if config.cold_policy().is_active()
+                && ui
+                    .button("Move Cold Files Now")
+                    .on_hover_text("Move files for works that haven't been viewed recently")
+                    .clicked()
+            {
+                self.last_offload = Some(Instant::now());
+                self.start_job(db_write.offload_cold_files());
+            }
+            if ui
+                .button("Apply Rules to Existing Files")
+                .on_hover_text("Move files that were downloaded before the rules changed")
+                .clicked()
+            {
+                self.start_job(db_write.apply_storage_rules());
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.move_to).hint_text("new data directory"),
+                );
+                if ui
+                    .add_enabled(
+                        !self.move_to.trim().is_empty(),
+                        egui::Button::new("Move Data Directory"),
+                    )
+                    .on_hover_text(
+                        "Copy the database and everything in the data directory; the old copy is left in place",
+                    )
+                    .clicked()
+                {
+                    self.start_job(db_write.move_data_dir(Path::new(self.move_to.trim())));
+                }
+            });
+        });
+
+        if self.in_progress {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                self.progress.ui(ui);
+            });
+        }
+        if let Some(err) = &self.last_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+        if let Some(report) = &self.last_report {
+            if let Some(err) = &report.error {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {err}"));
+            } else if let Some(dir) = &report.new_data_dir {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Copied to {}; restart Artchiver to finish the move.",
+                        dir.display()
+                    ));
+                    if ui.button("Quit Now").clicked() {
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+            } else {
+                ui.label(format!(
+                    "Moved {} files; {} could not be moved",
+                    report.moved, report.failed
+                ));
+            }
+        }
+    }
+}
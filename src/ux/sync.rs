@@ -0,0 +1,174 @@
+use crate::{
+    db::{
+        metadata_sync::{SyncConflict, SyncReport},
+        writer::DbWriteHandle,
+    },
+    shared::update::DataUpdate,
+};
+use jiff::tz::TimeZone;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+fn default_device_name() -> String {
+    let host = gethostname::gethostname().to_string_lossy().into_owned();
+    if host.is_empty() {
+        format!("device-{:08x}", rand::random::<u32>())
+    } else {
+        host
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxSync {
+    // Preferences
+    folder: String,
+    device_name: String,
+    sync_on_startup: bool,
+
+    // The most recent result, so the user can review anything we overrode.
+    last_report: Option<SyncReport>,
+    show_conflicts: bool,
+
+    #[serde(skip)]
+    in_progress: bool,
+}
+
+impl Default for UxSync {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            device_name: default_device_name(),
+            sync_on_startup: false,
+            last_report: None,
+            show_conflicts: false,
+            in_progress: false,
+        }
+    }
+}
+
+impl UxSync {
+    pub fn startup(&mut self, db_write: &DbWriteHandle) {
+        if self.sync_on_startup {
+            self.sync_now(db_write);
+        }
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::MetadataSyncCompleted(report) = update {
+                self.in_progress = false;
+                if !report.conflicts.is_empty() {
+                    self.show_conflicts = true;
+                }
+                self.last_report = Some(report.clone());
+            }
+        }
+    }
+
+    fn sync_now(&mut self, db_write: &DbWriteHandle) {
+        if self.folder.trim().is_empty() || self.device_name.trim().is_empty() {
+            return;
+        }
+        match db_write.sync_user_metadata(Path::new(self.folder.trim()), self.device_name.trim()) {
+            Ok(()) => self.in_progress = true,
+            Err(e) => error!("Failed to request metadata sync: {e}"),
+        }
+    }
+
+    // Shown in the preferences window.
+    pub fn ui(&mut self, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.heading("Sync");
+        ui.label("Share favorites, ratings, hidden flags, and exhibitions with other devices through a synced folder (Dropbox, Syncthing, etc).");
+        egui::Grid::new("sync_preferences_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Folder");
+                ui.text_edit_singleline(&mut self.folder);
+                ui.end_row();
+                ui.label("Device Name");
+                ui.text_edit_singleline(&mut self.device_name);
+                ui.end_row();
+            });
+        ui.checkbox(&mut self.sync_on_startup, "Sync on startup");
+        ui.horizontal(|ui| {
+            let can_sync = !self.in_progress && !self.folder.trim().is_empty();
+            if ui
+                .add_enabled(can_sync, egui::Button::new("Sync Now"))
+                .clicked()
+            {
+                self.sync_now(db_write);
+            }
+            if self.in_progress {
+                ui.spinner();
+            } else if let Some(err) = self.last_report.as_ref().and_then(|r| r.error.as_ref()) {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Sync failed: {err}"));
+            } else if let Some(report) = &self.last_report {
+                let when = report
+                    .finished_at
+                    .map(|t| {
+                        t.to_zoned(TimeZone::system())
+                            .strftime("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                ui.label(format!(
+                    "Last sync {when}: {} changes from {} devices",
+                    report.applied,
+                    report.devices.len()
+                ));
+                if !report.conflicts.is_empty()
+                    && ui
+                        .button(format!("{} Conflicts...", report.conflicts.len()))
+                        .clicked()
+                {
+                    self.show_conflicts = true;
+                }
+            }
+        });
+    }
+
+    pub fn conflicts_ui(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.last_report else {
+            return;
+        };
+        egui::Window::new("Sync Conflicts")
+            .open(&mut self.show_conflicts)
+            .show(ctx, |ui| {
+                ui.label("These were changed on this device, but a newer change from another device replaced them.");
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("sync_conflicts_grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Item");
+                            ui.strong("Field");
+                            ui.strong("Change");
+                            ui.strong("From");
+                            ui.end_row();
+                            for SyncConflict {
+                                table,
+                                key,
+                                field,
+                                local_value,
+                                remote_value,
+                                remote_device,
+                            } in &report.conflicts
+                            {
+                                ui.label(format!("{table}: {key}"));
+                                ui.label(field.to_string());
+                                ui.label(format!(
+                                    "{} → {}",
+                                    field.format_value(*local_value),
+                                    field.format_value(*remote_value)
+                                ));
+                                ui.label(remote_device);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     db::{
-        model::OrderDir,
+        model::{TagListQuery, TagOrder, TagSource},
         models::tag::{DbTag, TagId},
         reader::DbReadHandle,
         writer::DbWriteHandle,
@@ -11,54 +11,14 @@ use crate::{
 };
 use artchiver_sdk::TagKind;
 use itertools::Itertools as _;
-use log::trace;
+use jiff::{SignedDuration, Timestamp};
+use log::{error, trace};
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, collections::HashMap};
-
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
-pub enum TagSortCol {
-    #[default]
-    Name,
-    LocalCount,
-    NetworkCount,
-}
-
-impl TagSortCol {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        let mut selected = match self {
-            Self::Name => 0,
-            Self::LocalCount => 1,
-            Self::NetworkCount => 2,
-        };
-        let labels = ["Name", "Works Downloaded", "Total Works"];
-        egui::ComboBox::new("tag_order_column", "Column")
-            .wrap_mode(egui::TextWrapMode::Truncate)
-            .show_index(ui, &mut selected, labels.len(), |i| labels[i]);
-        *self = match selected {
-            0 => Self::Name,
-            1 => Self::LocalCount,
-            2 => Self::NetworkCount,
-            _ => panic!("invalid column selected"),
-        };
-    }
-}
-
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
-pub struct TagOrder {
-    column: TagSortCol,
-    order: OrderDir,
-}
-
-impl TagOrder {
-    pub fn new(column: TagSortCol, order: OrderDir) -> Self {
-        Self { column, order }
-    }
-
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        self.column.ui(ui);
-        self.order.ui("tags", ui);
-    }
-}
+use std::{
+    collections::HashMap,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TagSourceFilter {
@@ -149,11 +109,13 @@ impl TagKindFilter {
 
 /// Tag caching strategy:
 ///
-/// Plan for O(100-500k) tags -- the approximate size of the English vocabulary with misspelling --
-/// so we just store all of them in memory and refresh from the database after a refresh finishes.
+/// Plan for O(100-500k) tags -- the approximate size of the English vocabulary with misspelling.
+/// We keep all of them in memory for looking tags up by id, which the rest of the UX does
+/// constantly, and refresh from the database after a refresh finishes.
 ///
-/// When a filter is applied, we map over the full set of tags into a filtered tag
-/// set. We do this immediately: todo: check if we can do live updates here?
+/// Filtering and sorting that many on every keystroke is too slow, however, so the list itself
+/// is a window onto a query in the database. We fetch the rows around the visible ones, and go
+/// back for more when the user scrolls outside of them or changes the filters.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct UxTag {
     // A substring matcher over tag names
@@ -162,12 +124,33 @@ pub struct UxTag {
     kind_filter: TagKindFilter,
     order: TagOrder,
 
+    // How often to refresh the watched tags, if at all, and when we last did.
+    watch_refresh_hours: Option<u64>,
+    last_watch_refresh: Option<Timestamp>,
+
     #[serde(skip, default)]
     tag_all: Option<HashMap<TagId, DbTag>>,
 
-    // Ordered subset of DbTag id's to actually draw each frame.
+    // Bumped whenever the list needs to be re-queried, so that we can drop stale windows.
+    #[serde(skip, default)]
+    generation: u64,
+    // When to re-query; typing in the filter waits a moment for the next keystroke.
+    #[serde(skip, default)]
+    requery_at: Option<Instant>,
+    #[serde(skip, default)]
+    requested: Option<(u64, usize)>,
+
+    // The number of tags matching the filters, and the ordered ids of the window we have.
+    #[serde(skip, default)]
+    tag_total: usize,
+    #[serde(skip, default)]
+    window_offset: usize,
     #[serde(skip, default)]
-    tag_filtered: Vec<TagId>,
+    window: Vec<TagId>,
+
+    // When to recount the works on each tag, to pick up downloads that finished since.
+    #[serde(skip, default)]
+    recount_at: Option<Instant>,
 }
 
 impl UxTag {
@@ -179,39 +162,72 @@ impl UxTag {
     }
 
     pub fn handle_updates(&mut self, db: &DbReadHandle, updates: &[DataUpdate]) {
+        if self.recount_at.is_some_and(|at| at <= Instant::now()) {
+            self.recount_at = None;
+            db.get_tag_local_counts();
+        }
         for update in updates {
             match update {
                 DataUpdate::InitialTags(tags) => {
                     trace!("Received {} initial tags", tags.len());
                     self.tag_all = Some(tags.clone());
-                    self.reproject_tags();
+                    self.reproject_tags(Duration::ZERO);
+                    db.get_watched_tag_counts();
                 }
                 DataUpdate::TagsLocalCounts(counts) => {
                     if let Some(tags) = &mut self.tag_all {
-                        for (tag_id, count) in counts {
+                        for (tag_id, count, downloaded) in counts {
+                            if let Some(tag) = tags.get_mut(tag_id) {
+                                tag.set_local_count(*count, *downloaded);
+                            }
+                        }
+                    }
+                    self.reproject_tags(Duration::ZERO);
+                }
+                DataUpdate::WatchedTagCounts(counts) => {
+                    if let Some(tags) = &mut self.tag_all {
+                        for (tag_id, new_count) in counts {
                             if let Some(tag) = tags.get_mut(tag_id) {
-                                tag.set_local_count(*count);
+                                tag.set_new_count(*new_count);
                             }
                         }
                     }
-                    self.reproject_tags();
                 }
                 DataUpdate::TagsWereRefreshed => {
                     self.tag_all = None;
-                    self.tag_filtered = vec![];
+                    self.window.clear();
                     db.get_tags();
                 }
+                DataUpdate::TagsWindow {
+                    generation,
+                    total,
+                    offset,
+                    tag_ids,
+                } => {
+                    if *generation == self.generation {
+                        self.tag_total = *total;
+                        self.window_offset = *offset;
+                        self.window = tag_ids.clone();
+                    }
+                }
                 DataUpdate::WorksWereUpdatedForTag { .. } => {
                     // Note: whenever we fetch more works, the tag counts on unrelated tags will
                     //       change. We need to do a full recount.
                     db.get_tag_local_counts();
+                    db.get_watched_tag_counts();
+                }
+                DataUpdate::WorkDownloadCompleted { .. } => {
+                    // Note: downloads come in bursts, and the recount is a full scan, so we
+                    //       batch them up.
+                    self.recount_at
+                        .get_or_insert_with(|| Instant::now() + Self::RECOUNT_DELAY);
                 }
                 DataUpdate::TagFavoriteStatusChanged { tag_id, favorite } => {
                     if let Some(tags) = &mut self.tag_all
                         && let Some(tag) = tags.get_mut(tag_id)
                     {
                         tag.set_favorite(*favorite);
-                        self.reproject_tags();
+                        self.reproject_tags(Duration::ZERO);
                     }
                 }
                 DataUpdate::TagHiddenStatusChanged { tag_id, hidden } => {
@@ -219,7 +235,15 @@ impl UxTag {
                         && let Some(tag) = tags.get_mut(tag_id)
                     {
                         tag.set_hidden(*hidden);
-                        self.reproject_tags();
+                        self.reproject_tags(Duration::ZERO);
+                    }
+                }
+                DataUpdate::TagWatchedStatusChanged { tag_id, watched } => {
+                    if let Some(tags) = &mut self.tag_all
+                        && let Some(tag) = tags.get_mut(tag_id)
+                    {
+                        tag.set_watched(*watched);
+                        tag.set_new_count(0);
                     }
                 }
                 _ => {}
@@ -231,56 +255,97 @@ impl UxTag {
         self.tag_all.as_ref()
     }
 
-    fn reproject_tags(&mut self) {
-        if let Some(tags) = &self.tag_all {
-            self.tag_filtered = tags
-                .iter()
-                // Apply the direct name filter, case-insensitive
-                .filter(|(_id, t)| {
-                    t.name()
-                        .to_lowercase()
-                        .contains(&self.name_filter.to_lowercase())
-                })
-                // include only selected plugin sources in the tags list response
-                .filter(|(_, t)| {
-                    self.source_filter.source.is_none() // All
-                        || (self.source_filter.source.as_deref() == Some("Hidden") && t.hidden())
-                        || t.sources().contains(self.source_filter.source.as_deref().expect("checked"))
-                })
-                // include only tags with the selected kind
-                .filter(|(_, t)| {
-                    self.kind_filter.kind.is_none() ||
-                        self.kind_filter.kind == Some(t.kind())
-                })
-                .sorted_by(
-                    |(_, a), (_, b)| match a.favorite().cmp(&b.favorite()).reverse() {
-                        Ordering::Equal => {
-                            let inner = match self.order.column {
-                                TagSortCol::Name => a.name().cmp(b.name()),
-                                TagSortCol::LocalCount => {
-                                    match a.local_count().cmp(&b.local_count()) {
-                                        Ordering::Equal => a.name().cmp(b.name()),
-                                        v => v,
-                                    }
-                                }
-                                TagSortCol::NetworkCount => {
-                                    match a.network_count().cmp(&b.network_count()) {
-                                        Ordering::Equal => a.name().cmp(b.name()),
-                                        v => v,
-                                    }
-                                }
-                            };
-                            match self.order.order {
-                                OrderDir::Asc => inner,
-                                OrderDir::Desc => inner.reverse(),
-                            }
-                        }
-                        v => v,
-                    },
+    // Refresh the watched tags when they are due; their new works show up as notifications and
+    // as counts on the tags.
+    pub fn tick(&mut self, host: &mut PluginHost) {
+        let (Some(hours), Some(tags)) = (self.watch_refresh_hours, &self.tag_all) else {
+            return;
+        };
+        let now = Timestamp::now();
+        let interval = SignedDuration::from_hours(hours.max(1) as i64);
+        if self
+            .last_watch_refresh
+            .is_some_and(|at| now.duration_since(at) < interval)
+        {
+            return;
+        }
+        self.last_watch_refresh = Some(now);
+        for tag in tags.values().filter(|tag| tag.watched()) {
+            if let Err(e) = host.refresh_works_for_tag(tag) {
+                error!("Failed to refresh watched tag {}: {e}", tag.name());
+            }
+        }
+    }
+
+    pub fn watch_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        let mut scheduled = self.watch_refresh_hours.is_some();
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut scheduled, "Refresh watched tags every")
+                .changed()
+            {
+                self.watch_refresh_hours = scheduled.then_some(24);
+            }
+            let mut hours = self.watch_refresh_hours.unwrap_or(24);
+            if ui
+                .add_enabled(
+                    scheduled,
+                    egui::DragValue::new(&mut hours).range(1..=24 * 7),
                 )
-                .map(|(id, _)| *id)
-                .collect();
+                .changed()
+            {
+                self.watch_refresh_hours = Some(hours);
+            }
+            ui.label("hours");
+        });
+        ui.label(
+            "Watch a tag with the 👁 button next to it. New works that a refresh finds for it \
+             raise a notification, and are counted next to the tag until you next view it.",
+        );
+    }
+
+    const FILTER_DEBOUNCE: Duration = Duration::from_millis(250);
+    const RECOUNT_DELAY: Duration = Duration::from_secs(5);
+    // Rows to fetch past each end of the visible ones.
+    const WINDOW_PAD: usize = 200;
+
+    fn reproject_tags(&mut self, delay: Duration) {
+        self.requery_at = Some(Instant::now() + delay);
+    }
+
+    fn query(&self) -> TagListQuery {
+        TagListQuery {
+            name: self.name_filter.clone(),
+            source: match self.source_filter.source.as_deref() {
+                None => TagSource::All,
+                Some("Hidden") => TagSource::Hidden,
+                Some(name) => TagSource::Plugin(name.to_owned()),
+            },
+            kind: self.kind_filter.kind,
+            order: self.order,
+        }
+    }
+
+    // Ask for the rows around `visible`, unless we have them or have already asked.
+    fn ensure_window(&mut self, db: &DbReadHandle, visible: Range<usize>) {
+        if self.requery_at.is_some_and(|at| at <= Instant::now()) {
+            self.requery_at = None;
+            self.generation += 1;
         }
+        let have = self.window_offset..self.window_offset + self.window.len();
+        let fresh = self
+            .requested
+            .is_some_and(|(generation, _)| generation == self.generation);
+        if fresh && have.start <= visible.start && visible.end.min(self.tag_total) <= have.end {
+            return;
+        }
+        let start = visible.start.saturating_sub(Self::WINDOW_PAD);
+        if self.requested == Some((self.generation, start)) {
+            return;
+        }
+        self.requested = Some((self.generation, start));
+        let end = visible.end.saturating_add(Self::WINDOW_PAD);
+        db.get_tags_window(self.generation, self.query(), start..end);
     }
 
     pub fn ui(
@@ -288,6 +353,7 @@ impl UxTag {
         tag_set: &mut TagSet,
         host: &mut PluginHost,
         mut tutorial: Tutorial<'_>,
+        db_read: &DbReadHandle,
         db_write: &DbWriteHandle,
         ui: &mut egui::Ui,
     ) {
@@ -327,21 +393,23 @@ impl UxTag {
         // Main textual filter bar
         ui.horizontal(|ui| {
             if ui.text_edit_singleline(&mut self.name_filter).changed() {
-                self.reproject_tags();
+                // Note: wait for the user to stop typing before hitting the database.
+                self.reproject_tags(Self::FILTER_DEBOUNCE);
+                ui.ctx().request_repaint_after(Self::FILTER_DEBOUNCE);
             }
             if ui.button("x").clicked() {
                 self.name_filter.clear();
-                self.reproject_tags();
+                self.reproject_tags(Duration::ZERO);
             }
-            ui.label(format!("({})", self.tag_filtered.len()));
+            ui.label(format!("({})", self.tag_total));
         });
         // Sub-filters bar
         ui.horizontal(|ui| {
             if self.source_filter.ui(host, ui) {
-                self.reproject_tags();
+                self.reproject_tags(Duration::ZERO);
             }
             if self.kind_filter.ui(ui) {
-                self.reproject_tags();
+                self.reproject_tags(Duration::ZERO);
             }
         });
         // Sorting bar
@@ -349,7 +417,7 @@ impl UxTag {
             let prior = self.order;
             self.order.ui(ui);
             if prior != self.order {
-                self.reproject_tags();
+                self.reproject_tags(Duration::ZERO);
             }
         });
 
@@ -406,18 +474,29 @@ impl UxTag {
         let row_height = ui.text_style_height(&text_style);
         egui::ScrollArea::vertical()
             .auto_shrink([false; 2])
-            .show_rows(ui, row_height, self.tag_filtered.len(), |ui, row_range| {
+            .show_rows(ui, row_height, self.tag_total, |ui, row_range| {
+                self.ensure_window(db_read, row_range.clone());
                 let width = ui.available_width();
                 egui::Grid::new("tag_grid")
                     .num_columns(1)
                     .min_col_width(width)
-                    .show(ui, move |ui| -> Option<()> {
-                        for tag_id in &self.tag_filtered[row_range] {
-                            let tag = self.tag_all.as_ref()?.get(tag_id)?;
-                            tag_set.tag_row_ui(tag, host, db_write, ui, &mut tutorial);
+                    .show(ui, move |ui| {
+                        for row in row_range {
+                            // Rows outside the window we have are blank until it arrives.
+                            let tag = row
+                                .checked_sub(self.window_offset)
+                                .and_then(|i| self.window.get(i))
+                                .and_then(|id| self.tag_all.as_ref()?.get(id));
+                            match tag {
+                                Some(tag) => {
+                                    tag_set.tag_row_ui(tag, host, db_write, ui, &mut tutorial);
+                                }
+                                None => {
+                                    ui.label("");
+                                }
+                            }
                             ui.end_row();
                         }
-                        None
                     });
             });
     }
@@ -0,0 +1,225 @@
+// The review window for sending the user's tag edits back to the works' sources, so that the
+// curation done here helps everyone else who uses the source. Plugins for sources that take edits,
+// like Danbooru-compatible boorus, implement `push_work_tags`; the rest log that they can't.
+use crate::{
+    db::{models::tag_edit::DbTagEdit, reader::DbReadHandle},
+    plugin::host::PluginHost,
+    shared::update::DataUpdate,
+};
+use artchiver_sdk::WorkTagEdit;
+use egui::{Color32, RichText};
+use itertools::Itertools as _;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Default)]
+pub struct UxTagPush {
+    open: bool,
+    is_loading: bool,
+    stale: bool,
+    edits: Vec<DbTagEdit>,
+    // The screen urls of the works that the user has left out of this push.
+    skipped: HashSet<String>,
+}
+
+// One edit per work, by the name of the plugin that the work came from. `edits` are as listed by
+// the reader, in order of plugin then work.
+fn group_edits(
+    edits: &[DbTagEdit],
+    skipped: &HashSet<String>,
+) -> HashMap<String, Vec<(String, WorkTagEdit)>> {
+    let mut by_plugin: HashMap<String, Vec<(String, WorkTagEdit)>> = HashMap::new();
+    for ((plugin, screen_url), edits) in &edits
+        .iter()
+        .filter(|edit| !skipped.contains(&edit.screen_url))
+        .chunk_by(|edit| (&edit.plugin, &edit.screen_url))
+    {
+        let edits = edits.collect::<Vec<_>>();
+        let Some(first) = edits.first() else {
+            continue;
+        };
+        let work_edit = edits
+            .iter()
+            .fold(WorkTagEdit::new(&first.remote_id), |work_edit, edit| {
+                if edit.added {
+                    work_edit.with_added(&edit.tag)
+                } else {
+                    work_edit.with_removed(&edit.tag)
+                }
+            });
+        by_plugin
+            .entry(plugin.to_owned())
+            .or_default()
+            .push((screen_url.to_owned(), work_edit));
+    }
+    by_plugin
+}
+
+impl UxTagPush {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.stale = true;
+        self.skipped.clear();
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::PendingTagEdits(edits) => {
+                    self.is_loading = false;
+                    self.edits = edits.clone();
+                }
+                DataUpdate::TagEditsChanged => self.stale = true,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn window(&mut self, db: &DbReadHandle, host: &mut PluginHost, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+        if self.stale && !self.is_loading {
+            self.stale = false;
+            self.is_loading = true;
+            db.get_pending_tag_edits();
+        }
+
+        let mut is_open = self.open;
+        let mut push = false;
+        egui::Window::new("Sync Tag Edits Upstream")
+            .open(&mut is_open)
+            .default_height(400.)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Send the tags you added to or removed from works back to where the works \
+                     came from. Only sources that take edits will change; the edits for the \
+                     rest stay here.",
+                );
+                ui.separator();
+                if self.is_loading && self.edits.is_empty() {
+                    ui.spinner();
+                    return;
+                }
+                if self.edits.is_empty() {
+                    ui.label(
+                        "No tag edits to push. Tags that you accept from suggestions, or remove \
+                         from a work in Work Info, show up here.",
+                    );
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(320.)
+                    .show(ui, |ui| {
+                        for (plugin, edits) in &self.edits.iter().chunk_by(|edit| &edit.plugin) {
+                            egui::CollapsingHeader::new(plugin)
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for (screen_url, edits) in
+                                        &edits.chunk_by(|edit| &edit.screen_url)
+                                    {
+                                        Self::work_ui(screen_url, edits, &mut self.skipped, ui);
+                                    }
+                                });
+                        }
+                    });
+                ui.separator();
+                let works = self
+                    .edits
+                    .iter()
+                    .map(|edit| &edit.screen_url)
+                    .unique()
+                    .filter(|screen_url| !self.skipped.contains(*screen_url))
+                    .count();
+                if ui
+                    .add_enabled(
+                        works > 0,
+                        egui::Button::new(format!("⇪ Push Edits for {works} Works")),
+                    )
+                    .clicked()
+                {
+                    push = true;
+                }
+            });
+
+        if push {
+            host.push_tag_edits(group_edits(&self.edits, &self.skipped));
+            is_open = false;
+        }
+        self.open = is_open;
+    }
+
+    fn work_ui<'a>(
+        screen_url: &str,
+        edits: impl Iterator<Item = &'a DbTagEdit>,
+        skipped: &mut HashSet<String>,
+        ui: &mut egui::Ui,
+    ) {
+        let edits = edits.collect::<Vec<_>>();
+        ui.horizontal_wrapped(|ui| {
+            let mut include = !skipped.contains(screen_url);
+            let name = edits.first().map_or("", |edit| edit.work_name.as_str());
+            if ui.checkbox(&mut include, name).changed() {
+                if include {
+                    skipped.remove(screen_url);
+                } else {
+                    skipped.insert(screen_url.to_owned());
+                }
+            }
+            for edit in edits {
+                if edit.added {
+                    ui.label(RichText::new(format!("+{}", edit.tag)).color(Color32::LIGHT_GREEN));
+                } else {
+                    ui.label(
+                        RichText::new(format!("−{}", edit.tag))
+                            .color(Color32::LIGHT_RED)
+                            .strikethrough(),
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(screen_url: &str, tag: &str, added: bool) -> DbTagEdit {
+        DbTagEdit {
+            screen_url: screen_url.to_owned(),
+            work_name: screen_url.to_owned(),
+            remote_id: format!("id-{screen_url}"),
+            plugin: "Booru".to_owned(),
+            tag: tag.to_owned(),
+            added,
+        }
+    }
+
+    #[test]
+    fn test_group_edits() {
+        let edits = [
+            edit("a", "1 person", true),
+            edit("a", "landscape", false),
+            edit("b", "portrait", true),
+            edit("c", "group portrait", true),
+        ];
+        let skipped = HashSet::from(["c".to_owned()]);
+        let grouped = group_edits(&edits, &skipped);
+        assert_eq!(
+            grouped["Booru"],
+            vec![
+                (
+                    "a".to_owned(),
+                    WorkTagEdit::new("id-a")
+                        .with_added("1 person")
+                        .with_removed("landscape")
+                ),
+                (
+                    "b".to_owned(),
+                    WorkTagEdit::new("id-b").with_added("portrait")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+// Backfills gallery thumbnails for works that were downloaded before we made them, on a small
+// pool of worker threads. Works in the current gallery go to the front of the queue, so that
+// the part of the archive the user is actually looking at gets fast first. While the user has an
+// NSFW classifier loaded, works that it hasn't scored yet get another pass, to score them.
+use crate::{
+    db::{
+        models::work::{MissingThumb, WorkId},
+        reader::DbReadHandle,
+        writer::DbWriteHandle,
+    },
+    plugin::thumbnail::{GalleryThumb, make_gallery_thumbnail},
+    shared::{nsfw, progress::Progress, storage::Storage, update::DataUpdate},
+};
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use log::{error, warn};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    thread,
+};
+
+type ThumbResult = (MissingThumb, Result<GalleryThumb, String>);
+
+pub struct UxThumbnails {
+    storage: Storage,
+    ctx: egui::Context,
+
+    queue: Arc<Mutex<VecDeque<MissingThumb>>>,
+    workers: usize,
+    tx_result: Sender<ThumbResult>,
+    rx_result: Receiver<ThumbResult>,
+
+    scanning: bool,
+    total: usize,
+    done: usize,
+    failed: usize,
+}
+
+impl Default for UxThumbnails {
+    fn default() -> Self {
+        let (tx_result, rx_result) = unbounded();
+        Self {
+            storage: Storage::default(),
+            ctx: egui::Context::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            workers: 0,
+            tx_result,
+            rx_result,
+            scanning: false,
+            total: 0,
+            done: 0,
+            failed: 0,
+        }
+    }
+}
+
+impl UxThumbnails {
+    pub fn startup(&mut self, ctx: &egui::Context, storage: &Storage, db: &DbReadHandle) {
+        self.ctx = ctx.clone();
+        self.storage = storage.clone();
+        self.scan(db);
+    }
+
+    fn scan(&mut self, db: &DbReadHandle) {
+        self.scanning = true;
+        db.get_works_missing_thumbs(nsfw::is_enabled());
+    }
+
+    fn is_running(&self) -> bool {
+        self.scanning || self.workers > 0
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::WorksMissingThumbs(works) = update {
+                self.scanning = false;
+                self.enqueue(works);
+            }
+        }
+    }
+
+    fn enqueue(&mut self, works: &[MissingThumb]) {
+        {
+            let mut queue = self.queue.lock();
+            let queued = queue.iter().map(|job| job.work_id).collect::<HashSet<_>>();
+            let fresh = works
+                .iter()
+                .filter(|work| !queued.contains(&work.work_id))
+                .cloned()
+                .collect::<Vec<_>>();
+            if self.workers == 0 {
+                self.total = 0;
+                self.done = 0;
+                self.failed = 0;
+            }
+            self.total += fresh.len();
+            queue.extend(fresh);
+        }
+
+        // Note: leave some cores for the UX and any downloads that are running.
+        let want = thread::available_parallelism()
+            .map_or(2, |n| n.get() / 2)
+            .clamp(1, 8)
+            .min(self.queue.lock().len());
+        while self.workers < want {
+            self.spawn_worker();
+        }
+    }
+
+    fn spawn_worker(&mut self) {
+        let queue = self.queue.clone();
+        let storage = self.storage.clone();
+        let tx = self.tx_result.clone();
+        let ctx = self.ctx.clone();
+        let spawned = thread::Builder::new()
+            .name("Thumbnailer".to_owned())
+            .spawn(move || {
+                loop {
+                    let Some(job) = queue.lock().pop_front() else {
+                        break;
+                    };
+                    let result =
+                        make_gallery_thumbnail(&job.screen_url, &job.screen_path, &storage)
+                            .map_err(|e| e.to_string());
+                    if tx.send((job, result)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        match spawned {
+            Ok(_) => self.workers += 1,
+            Err(e) => error!("Failed to start a thumbnail worker: {e}"),
+        }
+    }
+
+    // Move any of the given works that are still waiting to the front of the queue.
+    pub fn prioritize(&self, works: &HashSet<WorkId>) {
+        let mut queue = self.queue.lock();
+        if queue.is_empty() || works.is_empty() {
+            return;
+        }
+        let (urgent, rest): (VecDeque<_>, VecDeque<_>) = queue
+            .drain(..)
+            .partition(|job| works.contains(&job.work_id));
+        queue.extend(urgent);
+        queue.extend(rest);
+    }
+
+    pub fn tick(&mut self, db_write: &DbWriteHandle) {
+        while let Ok((job, result)) = self.rx_result.try_recv() {
+            match result {
+                Ok(thumb) => {
+                    self.done += 1;
+                    if let Err(e) =
+                        db_write.set_work_thumb_path(job.work_id, (thumb.path, thumb.aspect_ratio))
+                    {
+                        error!("Failed to record thumbnail: {e}");
+                    }
+                    if let Some(score) = thumb.nsfw_score
+                        && let Err(e) = db_write.set_nsfw_score(&job.screen_url, score)
+                    {
+                        error!("Failed to record the NSFW score: {e}");
+                    }
+                }
+                Err(e) => {
+                    self.failed += 1;
+                    warn!("Failed to make a thumbnail for {:?}: {e}", job.work_id);
+                }
+            }
+        }
+        // Note: workers exit as soon as they find the queue empty.
+        if self.done + self.failed >= self.total {
+            self.workers = 0;
+        }
+    }
+
+    pub fn ui(&mut self, db: &DbReadHandle, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Gallery Thumbnails");
+            if self.scanning {
+                ui.spinner();
+            } else if self.workers > 0 {
+                Progress::Percent {
+                    current: self.done + self.failed,
+                    total: self.total.max(1),
+                }
+                .ui(ui);
+            } else if self.total > 0 {
+                ui.label(format!("made {} thumbnails", self.done));
+            } else {
+                ui.label("up to date");
+            }
+            if self.failed > 0 {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{} failed", self.failed),
+                )
+                .on_hover_text("See the log for details");
+            }
+            if ui
+                .add_enabled(!self.is_running(), egui::Button::new("Rescan"))
+                .on_hover_text(
+                    "Look for downloaded works without a thumbnail, or without an NSFW score",
+                )
+                .clicked()
+            {
+                self.scan(db);
+            }
+        });
+    }
+}
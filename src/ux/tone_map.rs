@@ -0,0 +1,136 @@
+// Bring images with more than 8 bits per channel down to what egui can show. 16-bit scans are
+// already display-referred, so they only need dithering on the way down to hide banding in smooth
+// gradients. Float images, from EXR and HDR files, hold scene light with no upper bound, so they
+// get a global tone map first.
+use image::{ColorType, DynamicImage, Rgba32FImage, RgbaImage};
+
+// How many luminance samples we take to find the white point; a sort of every pixel in a large
+// EXR would cost more than the tone map itself.
+const WHITE_POINT_SAMPLES: usize = 1 << 16;
+
+// 4x4 Bayer thresholds, scaled to 0..1.
+const BAYER: [[f32; 4]; 4] = [
+    [0. / 16., 8. / 16., 2. / 16., 10. / 16.],
+    [12. / 16., 4. / 16., 14. / 16., 6. / 16.],
+    [3. / 16., 11. / 16., 1. / 16., 9. / 16.],
+    [15. / 16., 7. / 16., 13. / 16., 5. / 16.],
+];
+
+pub fn is_high_depth(color_type: ColorType) -> bool {
+    matches!(
+        color_type,
+        ColorType::L16
+            | ColorType::La16
+            | ColorType::Rgb16
+            | ColorType::Rgba16
+            | ColorType::Rgb32F
+            | ColorType::Rgba32F
+    )
+}
+
+fn is_float(color_type: ColorType) -> bool {
+    matches!(color_type, ColorType::Rgb32F | ColorType::Rgba32F)
+}
+
+// Convert `image` to 8 bits for display, with `exposure` in stops on top of the automatic one
+// for float images.
+pub fn to_display(image: &DynamicImage, exposure: f32) -> RgbaImage {
+    if is_float(image.color()) {
+        tone_map(&image.to_rgba32f(), exposure)
+    } else {
+        let wide = image.to_rgba16();
+        RgbaImage::from_fn(wide.width(), wide.height(), |x, y| {
+            let pixel = wide.get_pixel(x, y).0;
+            image::Rgba(pixel.map(|value| quantize(f32::from(value) / 65535., x, y)))
+        })
+    }
+}
+
+// Round to 8 bits against the ordered dither threshold for the pixel.
+fn quantize(value: f32, x: u32, y: u32) -> u8 {
+    let threshold = BAYER[y as usize % 4][x as usize % 4];
+    (value * 255. + threshold).floor().clamp(0., 255.) as u8
+}
+
+fn luminance(pixel: [f32; 4]) -> f32 {
+    0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2]
+}
+
+fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+// Reinhard's photographic operator: expose so the log-average luminance lands on middle gray,
+// then roll the highlights off so the white point, rather than the hottest pixel, goes to white.
+fn tone_map(image: &Rgba32FImage, exposure: f32) -> RgbaImage {
+    let lums = image
+        .pixels()
+        .map(|pixel| luminance(pixel.0))
+        .filter(|lum| lum.is_finite() && *lum > 0.)
+        .collect::<Vec<_>>();
+    if lums.is_empty() {
+        return RgbaImage::new(image.width(), image.height());
+    }
+    let log_average =
+        (lums.iter().map(|lum| (lum + 1e-4).ln()).sum::<f32>() / lums.len() as f32).exp();
+    let scale = 0.18 / log_average * exposure.exp2();
+
+    let mut sample = lums
+        .iter()
+        .step_by(lums.len().div_ceil(WHITE_POINT_SAMPLES))
+        .map(|lum| lum * scale)
+        .collect::<Vec<_>>();
+    sample.sort_by(f32::total_cmp);
+    let white = sample[sample.len() * 999 / 1000].max(1.);
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y).0;
+        let lum = luminance(pixel) * scale;
+        let mapped = lum * (1. + lum / (white * white)) / (1. + lum);
+        // Note: scaling the channels together keeps the hue, where mapping each would wash it out.
+        let gain = if lum > 0. { mapped / lum * scale } else { 0. };
+        let [r, g, b, a] = pixel;
+        image::Rgba([
+            quantize(srgb_encode((r * gain).clamp(0., 1.)), x, y),
+            quantize(srgb_encode((g * gain).clamp(0., 1.)), x, y),
+            quantize(srgb_encode((b * gain).clamp(0., 1.)), x, y),
+            quantize(a.clamp(0., 1.), x, y),
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn test_dither_keeps_the_average() {
+        // A level that sits between two 8-bit steps should come out as a mix of both.
+        let level = (100.5 * 257.) as u16;
+        let wide: ImageBuffer<Rgba<u16>, _> =
+            ImageBuffer::from_pixel(4, 4, Rgba([level, level, level, u16::MAX]));
+        let out = to_display(&DynamicImage::ImageRgba16(wide), 0.);
+        let reds = out.pixels().map(|pixel| pixel.0[0]).collect::<Vec<_>>();
+        assert!(reds.contains(&100) && reds.contains(&101));
+        assert!(out.pixels().all(|pixel| pixel.0[3] == 255));
+    }
+
+    #[test]
+    fn test_tone_map_keeps_highlights_in_range() {
+        let mut hdr = Rgba32FImage::from_pixel(8, 8, Rgba([0.2, 0.2, 0.2, 1.]));
+        hdr.put_pixel(0, 0, Rgba([500., 250., 100., 1.]));
+        let out = tone_map(&hdr, 0.);
+        let hot = out.get_pixel(0, 0).0;
+        let base = out.get_pixel(1, 1).0;
+        assert!(hot[0] > hot[1] && hot[1] > hot[2], "hue survives: {hot:?}");
+        assert!(
+            base[0] > 64 && base[0] < 192,
+            "mid tones stay mid: {base:?}"
+        );
+    }
+}
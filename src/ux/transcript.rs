@@ -0,0 +1,321 @@
+// Subtitles and a transcript panel for audio and video works in the slideshow, e.g. lectures and
+// podcast episodes. Transcripts come to us from the DB; for local files without one, we look for
+// subtitles next to the file and in it, once, and the user can ask Whisper to transcribe the rest.
+use crate::{
+    db::{models::transcript::DbTranscript, reader::DbReadHandle, writer::DbWriteHandle},
+    plugin::thumbnail::is_video,
+    shared::{
+        transcript::{self, Cue, TranscriptSource, cue_at, format_time},
+        update::DataUpdate,
+    },
+};
+use crossbeam::channel::{Receiver, bounded};
+use egui::{Align, Align2, Color32, FontId, Rect, RichText, pos2};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    thread,
+};
+
+type Found = Result<Option<(TranscriptSource, Vec<Cue>)>, String>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Job {
+    Search,
+    Transcribe,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxTranscript {
+    show_subtitles: bool,
+    show_panel: bool,
+    // The Whisper command line, and the model to ask it for; empty for Whisper's default.
+    whisper: String,
+    whisper_model: String,
+
+    // The work that the transcript below belongs to.
+    #[serde(skip)]
+    screen_url: Option<String>,
+    #[serde(skip)]
+    transcript: Option<DbTranscript>,
+    #[serde(skip)]
+    is_loading: bool,
+    #[serde(skip)]
+    job: Option<(String, Job, Receiver<Found>)>,
+    #[serde(skip)]
+    error: Option<String>,
+    // The works we have looked for local subtitles for; we only look the once.
+    #[serde(skip)]
+    searched: HashSet<String>,
+    // The cue we last scrolled the panel to.
+    #[serde(skip)]
+    scrolled_to: Option<usize>,
+}
+
+impl Default for UxTranscript {
+    fn default() -> Self {
+        Self {
+            show_subtitles: true,
+            show_panel: false,
+            whisper: "whisper".to_owned(),
+            whisper_model: String::new(),
+            screen_url: None,
+            transcript: None,
+            is_loading: false,
+            job: None,
+            error: None,
+            searched: HashSet::new(),
+            scrolled_to: None,
+        }
+    }
+}
+
+impl UxTranscript {
+    const SUBTITLE_SIZE: f32 = 24.;
+
+    pub fn toggle_subtitles(&mut self) {
+        self.show_subtitles = !self.show_subtitles;
+    }
+
+    pub fn toggle_panel(&mut self) {
+        self.show_panel = !self.show_panel;
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::Transcript {
+                screen_url,
+                transcript,
+            } = update
+                && self.screen_url.as_ref() == Some(screen_url)
+            {
+                self.is_loading = false;
+                self.transcript = transcript.clone();
+                self.scrolled_to = None;
+            }
+        }
+    }
+
+    fn select(&mut self, screen_url: &str, db_read: &DbReadHandle) {
+        if self.screen_url.as_deref() == Some(screen_url) {
+            return;
+        }
+        self.screen_url = Some(screen_url.to_owned());
+        self.transcript = None;
+        self.is_loading = true;
+        self.error = None;
+        self.scrolled_to = None;
+        db_read.get_transcript(screen_url);
+    }
+
+    fn start(&mut self, screen_url: &str, job: Job, media: PathBuf, ctx: &egui::Context) {
+        let (tx, rx) = bounded(1);
+        let (whisper, model, ctx) = (
+            self.whisper.clone(),
+            self.whisper_model.clone(),
+            ctx.clone(),
+        );
+        thread::spawn(move || {
+            let found = match job {
+                Job::Search => transcript::find_local(&media),
+                Job::Transcribe => transcript::transcribe(&media, &whisper, &model)
+                    .map(|cues| Some((TranscriptSource::Whisper, cues))),
+            };
+            tx.send(found.map_err(|e| e.to_string())).ok();
+            ctx.request_repaint();
+        });
+        self.job = Some((screen_url.to_owned(), job, rx));
+    }
+
+    fn poll_job(&mut self, db_write: &DbWriteHandle) {
+        let Some((screen_url, job, rx)) = &self.job else {
+            return;
+        };
+        let Ok(found) = rx.try_recv() else {
+            return;
+        };
+        match found {
+            // Note: the writer sends the stored transcript back, which is when we show it.
+            Ok(Some((source, cues))) => {
+                if let Err(e) = db_write.set_work_transcript(screen_url, source, cues) {
+                    error!("Failed to save the transcript of {screen_url}: {e}");
+                }
+            }
+            Ok(None) => {}
+            Err(e) if *job == Job::Search => {
+                warn!("Failed to look for subtitles for {screen_url}: {e}");
+            }
+            Err(e) => {
+                if self.screen_url.as_ref() == Some(screen_url) {
+                    self.error = Some(e);
+                }
+            }
+        }
+        self.job = None;
+    }
+
+    fn is_busy(&self, screen_url: &str) -> bool {
+        self.job
+            .as_ref()
+            .is_some_and(|(job_url, _, _)| job_url == screen_url)
+    }
+
+    // Show the subtitle for `time` over `rect`, and the transcript panel if it's open, for the
+    // work playing from `media`. Returns the time to seek to, if the user clicked on a cue.
+    pub fn slideshow_ui(
+        &mut self,
+        (screen_url, media): (&str, Option<&Path>),
+        time: f64,
+        rect: Rect,
+        (db_read, db_write): (&DbReadHandle, &DbWriteHandle),
+        ui: &egui::Ui,
+    ) -> Option<f64> {
+        self.select(screen_url, db_read);
+        self.poll_job(db_write);
+        if !self.is_loading
+            && self.transcript.is_none()
+            && self.job.is_none()
+            && let Some(media) = media
+            && self.searched.insert(screen_url.to_owned())
+        {
+            self.start(screen_url, Job::Search, media.to_owned(), ui.ctx());
+        }
+
+        // Note: mpv already draws the subtitles in a video file, and those next to it.
+        if self.show_subtitles
+            && let Some(transcript) = &self.transcript
+            && !(matches!(
+                transcript.source,
+                TranscriptSource::Subtitles | TranscriptSource::Embedded
+            ) && media.is_some_and(is_video))
+            && let Some(cue) = cue_at(&transcript.cues, time)
+        {
+            Self::paint_subtitle(&cue.text, rect, ui);
+        }
+
+        if !self.show_panel {
+            return None;
+        }
+        let mut seek = None;
+        let mut open = true;
+        egui::Window::new("Transcript")
+            .open(&mut open)
+            .anchor(Align2::RIGHT_TOP, [-8., 8.])
+            .default_width(360.)
+            .default_height(rect.height() * 0.6)
+            .show(ui.ctx(), |ui| {
+                seek = self.panel_ui(screen_url, media, time, ui);
+            });
+        self.show_panel = open;
+        seek
+    }
+
+    fn paint_subtitle(text: &str, rect: Rect, ui: &egui::Ui) {
+        let painter = ui.painter();
+        let galley = painter.layout(
+            text.to_owned(),
+            FontId::proportional(Self::SUBTITLE_SIZE),
+            Color32::WHITE,
+            rect.width() * 0.8,
+        );
+        // Note: leave room for the player's controls along the bottom.
+        let bottom = rect.bottom() - Self::SUBTITLE_SIZE * 3.;
+        let pos = pos2(
+            rect.center().x - galley.size().x / 2.,
+            bottom - galley.size().y,
+        );
+        let back = Rect::from_min_size(pos, galley.size()).expand(6.);
+        painter.rect_filled(back, 4., Color32::from_black_alpha(176));
+        painter.galley(pos, galley, Color32::WHITE);
+    }
+
+    fn panel_ui(
+        &mut self,
+        screen_url: &str,
+        media: Option<&Path>,
+        time: f64,
+        ui: &mut egui::Ui,
+    ) -> Option<f64> {
+        if self.is_loading {
+            ui.spinner();
+            return None;
+        }
+        let Some(transcript) = &self.transcript else {
+            if self.is_busy(screen_url) {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Looking for a transcript...");
+                });
+                return None;
+            }
+            ui.label("This work has no transcript.");
+            if let Some(e) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            if let Some(media) = media
+                && ui
+                    .add_enabled(
+                        !self.whisper.trim().is_empty(),
+                        egui::Button::new("Transcribe with Whisper"),
+                    )
+                    .on_disabled_hover_text("Set up Whisper under Preferences")
+                    .clicked()
+            {
+                self.error = None;
+                self.start(screen_url, Job::Transcribe, media.to_owned(), ui.ctx());
+            }
+            return None;
+        };
+
+        ui.label(RichText::new(transcript.source.describe()).small().weak());
+        ui.separator();
+        let current = transcript
+            .cues
+            .iter()
+            .position(|cue| cue.is_timed() && cue.start <= time && time < cue.end);
+        let mut seek = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (i, cue) in transcript.cues.iter().enumerate() {
+                ui.horizontal_wrapped(|ui| {
+                    if cue.is_timed() && ui.link(format_time(cue.start)).clicked() {
+                        seek = Some(cue.start);
+                    }
+                    let text = if current == Some(i) {
+                        RichText::new(&cue.text).strong()
+                    } else {
+                        RichText::new(&cue.text)
+                    };
+                    let resp = ui.label(text);
+                    if current == Some(i) && self.scrolled_to != current {
+                        resp.scroll_to_me(Some(Align::Center));
+                    }
+                });
+            }
+        });
+        if current.is_some() {
+            self.scrolled_to = current;
+        }
+        seek
+    }
+
+    pub fn preferences_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.show_subtitles,
+            "Show subtitles under audio and video works",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Whisper command");
+            ui.add(egui::TextEdit::singleline(&mut self.whisper).hint_text("whisper"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Whisper model");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.whisper_model)
+                    .hint_text("Whisper's default; e.g. small.en"),
+            );
+        });
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    db::{models::trash::TrashedWork, writer::DbWriteHandle},
+    shared::update::DataUpdate,
+    ux::image_info::format_size,
+};
+use jiff::{SignedDuration, Timestamp, tz::TimeZone};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxTrash {
+    // Preferences
+    // How long files stay in the trash before they are deleted for good.
+    retention_days: u32,
+
+    #[serde(skip)]
+    works: Vec<TrashedWork>,
+    // Emptying the trash can't be undone, so it takes a second click.
+    #[serde(skip)]
+    confirm_empty: bool,
+    #[serde(skip)]
+    last_expiry: Option<Instant>,
+}
+
+impl Default for UxTrash {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            works: Vec::new(),
+            confirm_empty: false,
+            last_expiry: None,
+        }
+    }
+}
+
+impl UxTrash {
+    const EXPIRY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    // Files trashed before this have been there long enough to delete.
+    fn expiry_cutoff(&self, now: Timestamp) -> Timestamp {
+        let age = SignedDuration::from_hours(24 * i64::from(self.retention_days));
+        now.checked_sub(age).unwrap_or(Timestamp::MIN)
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            if let DataUpdate::TrashedWorks(works) = update {
+                self.works = works.clone();
+            }
+        }
+    }
+
+    // Delete what has been in the trash too long at startup and then once a day. The writer
+    // answers with what is left, which is also how we get the list to begin with.
+    pub fn tick(&mut self, db_write: &DbWriteHandle) {
+        if self
+            .last_expiry
+            .is_some_and(|last| last.elapsed() < Self::EXPIRY_INTERVAL)
+        {
+            return;
+        }
+        self.last_expiry = Some(Instant::now());
+        if let Err(e) = db_write.empty_trash(self.expiry_cutoff(Timestamp::now())) {
+            error!("Failed to request trash expiry: {e}");
+        }
+    }
+
+    fn restore(db_write: &DbWriteHandle, works: Vec<TrashedWork>) {
+        let screen_urls = works.into_iter().map(|work| work.screen_url).collect();
+        if let Err(e) = db_write.restore_trashed_works(screen_urls) {
+            error!("Failed to request restore from the trash: {e}");
+        }
+    }
+
+    pub fn ui(&mut self, db_write: &DbWriteHandle, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Keep trashed files for");
+            let changed = ui
+                .add(egui::DragValue::new(&mut self.retention_days).range(1..=3650))
+                .on_hover_text("Then they are deleted for good")
+                .changed();
+            ui.label("days");
+            // Note: a shorter retention period may have expired some files already.
+            if changed {
+                self.last_expiry = None;
+            }
+        });
+        ui.separator();
+
+        if self.works.is_empty() {
+            ui.label("The trash is empty.");
+            return;
+        }
+        let bytes = self.works.iter().map(|work| work.bytes).sum::<u64>();
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} works, {}",
+                self.works.len(),
+                format_size(bytes)
+            ));
+            if ui.button("Restore All").clicked() {
+                Self::restore(db_write, self.works.clone());
+            }
+            if self.confirm_empty {
+                ui.label("Delete these files for good?");
+                if ui.button("Delete").clicked() {
+                    self.confirm_empty = false;
+                    if let Err(e) = db_write.empty_trash(Timestamp::now()) {
+                        error!("Failed to request emptying the trash: {e}");
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_empty = false;
+                }
+            } else if ui.button("Empty Trash").clicked() {
+                self.confirm_empty = true;
+            }
+        });
+        ui.separator();
+
+        let mut restore = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                egui::Grid::new("trash_grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for work in &self.works {
+                            if ui.small_button("Restore").clicked() {
+                                restore = Some(work.clone());
+                            }
+                            ui.add(egui::Label::new(&work.work_name).truncate());
+                            ui.label(&work.reason);
+                            ui.label(
+                                work.trashed_at
+                                    .to_zoned(TimeZone::system())
+                                    .strftime("%Y-%m-%d %H:%M")
+                                    .to_string(),
+                            );
+                            ui.label(format_size(work.bytes))
+                                .on_hover_text(format!("{} files", work.files));
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(work) = restore {
+            Self::restore(db_write, vec![work]);
+        }
+    }
+}
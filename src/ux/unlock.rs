@@ -0,0 +1,222 @@
+// Asks for the passphrase of an encrypted archive before the app starts, or for a new one when
+// the user asked to turn encryption on. Runs as its own little app, as we can't open the DB, or
+// any of the files on encrypted roots, until we have the key.
+use crate::shared::{
+    encryption::{self, MIN_PASSPHRASE_LEN, UnlockRequest},
+    environment::Environment,
+    storage::{DataKind, StorageConfig},
+};
+use anyhow::{Result, ensure};
+use crossbeam::channel::{self, Receiver};
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+struct UnlockPrompt {
+    data_dir: PathBuf,
+    db_path: PathBuf,
+    encrypted_roots: Vec<PathBuf>,
+    // The kinds of download that the storage rules put somewhere other than an encrypted root.
+    unencrypted_kinds: Vec<DataKind>,
+    set_up: bool,
+    passphrase: String,
+    confirm: String,
+    change_passphrase: bool,
+    new_passphrase: String,
+    new_confirm: String,
+    rotate_key: bool,
+    error: Option<String>,
+    // Set while the key derivation, or a key rotation, runs in the background.
+    working: Option<Receiver<Result<()>>>,
+    unlocked: Arc<AtomicBool>,
+}
+
+impl UnlockPrompt {
+    fn submit(&mut self) -> Result<()> {
+        let data_dir = self.data_dir.clone();
+        let db_path = self.db_path.clone();
+        let job: Box<dyn FnOnce() -> Result<()> + Send> = if self.set_up {
+            ensure!(
+                self.passphrase == self.confirm,
+                "the passphrases don't match"
+            );
+            let passphrase = self.passphrase.clone();
+            Box::new(move || encryption::set_up(&data_dir, &db_path, &passphrase))
+        } else {
+            let new_passphrase = if self.change_passphrase {
+                ensure!(
+                    self.new_passphrase == self.new_confirm,
+                    "the new passphrases don't match"
+                );
+                Some(self.new_passphrase.clone())
+            } else {
+                None
+            };
+            let request = UnlockRequest {
+                passphrase: self.passphrase.clone(),
+                new_passphrase,
+                rotate_key: self.rotate_key,
+            };
+            let roots = self.encrypted_roots.clone();
+            Box::new(move || encryption::unlock(&data_dir, &db_path, &roots, &request))
+        };
+        let (tx, rx) = channel::bounded(1);
+        thread::spawn(move || tx.send(job()));
+        self.working = Some(rx);
+        Ok(())
+    }
+
+    fn form_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let password = |ui: &mut egui::Ui, value: &mut String, hint: &str| {
+            ui.add(
+                egui::TextEdit::singleline(value)
+                    .hint_text(hint)
+                    .password(true),
+            )
+        };
+        let mut submit = false;
+        if self.set_up {
+            ui.label(format!(
+                "Choose a passphrase of at least {MIN_PASSPHRASE_LEN} characters. There is no way \
+                 to recover the archive without it."
+            ));
+            password(ui, &mut self.passphrase, "passphrase");
+            submit |= password(ui, &mut self.confirm, "confirm passphrase").lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        } else {
+            ui.label("This archive is encrypted.");
+            submit |= password(ui, &mut self.passphrase, "passphrase").lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.checkbox(&mut self.change_passphrase, "Change passphrase");
+            if self.change_passphrase {
+                password(ui, &mut self.new_passphrase, "new passphrase");
+                password(ui, &mut self.new_confirm, "confirm new passphrase");
+            }
+            ui.checkbox(&mut self.rotate_key, "Rotate the data key")
+                .on_hover_text(
+                    "Re-encrypt the database and every file on encrypted roots with a new key. \
+                     This can take a long time for big archives; if it is interrupted, it picks \
+                     up where it left off at the next unlock.",
+                );
+        }
+        if !self.unencrypted_kinds.is_empty() {
+            let kinds = self
+                .unencrypted_kinds
+                .iter()
+                .map(|kind| kind.to_string().to_lowercase())
+                .collect::<Vec<_>>()
+                .join(", ");
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "Only the database and files on encrypted storage roots are encrypted. New \
+                     {kinds} will be stored in the clear: add an encrypted root in Preferences > \
+                     Storage and point the storage rules at it."
+                ),
+            );
+        }
+        let label = if self.set_up { "Encrypt" } else { "Unlock" };
+        submit |= ui.button(label).clicked();
+        submit
+    }
+}
+
+impl eframe::App for UnlockPrompt {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(rx) = &self.working
+            && let Ok(result) = rx.try_recv()
+        {
+            self.working = None;
+            match result {
+                Ok(()) => {
+                    self.unlocked.store(true, Ordering::Release);
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                Err(e) => self.error = Some(format!("{e:#}")),
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(16.);
+                ui.heading(if self.set_up {
+                    "Encrypt Archive"
+                } else {
+                    "Unlock Archive"
+                });
+                ui.add_space(8.);
+                if self.working.is_some() {
+                    ui.spinner();
+                    ui.label(if self.rotate_key {
+                        "Re-encrypting the archive..."
+                    } else {
+                        "Unlocking..."
+                    });
+                    ctx.request_repaint();
+                    return;
+                }
+                if self.form_ui(ui) {
+                    self.error = self.submit().err().map(|e| format!("{e:#}"));
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        });
+    }
+}
+
+// The kinds of download that the storage rules don't put on an encrypted root.
+fn unencrypted_kinds(config: &StorageConfig) -> Vec<DataKind> {
+    DataKind::ALL
+        .into_iter()
+        .filter(|kind| {
+            !config
+                .rule(*kind)
+                .and_then(|name| config.root(name))
+                .is_some_and(|root| root.is_encrypted())
+        })
+        .collect()
+}
+
+// Returns whether the archive was unlocked (or newly encrypted), rather than the user closing
+// the window.
+pub fn run_unlock_prompt(env: &Environment, set_up: bool) -> eframe::Result<bool> {
+    let unlocked = Arc::new(AtomicBool::new(false));
+    let config = env.storage().config();
+    let prompt = UnlockPrompt {
+        data_dir: env.data_dir(),
+        db_path: env.metadata_file_path(),
+        unencrypted_kinds: unencrypted_kinds(&config),
+        encrypted_roots: config
+            .roots()
+            .iter()
+            .filter(|root| root.is_encrypted())
+            .map(|root| root.path().to_owned())
+            .collect(),
+        set_up,
+        passphrase: String::new(),
+        confirm: String::new(),
+        change_passphrase: false,
+        new_passphrase: String::new(),
+        new_confirm: String::new(),
+        rotate_key: false,
+        error: None,
+        working: None,
+        unlocked: unlocked.clone(),
+    };
+    eframe::run_native(
+        "Artchiver",
+        eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default().with_inner_size([420.0, 340.0]),
+            ..Default::default()
+        },
+        Box::new(move |_cc| Ok(Box::new(prompt))),
+    )?;
+    Ok(unlocked.load(Ordering::Acquire))
+}
@@ -0,0 +1,152 @@
+// SVG works in the slideshow. A bitmap of a drawing goes soft as soon as it is zoomed, so we draw
+// the work again whenever the size it is shown at changes, off of the UX thread, and keep showing
+// the last drawing, scaled, until the new one is ready.
+//
+// Note: egui's texture cache keys SVGs by their size hint, and everything else by uri alone, so
+//       neither lets us swap in a sharper drawing behind a uri; we hold the texture ourselves.
+use crate::shared::vector;
+use crossbeam::channel::{Receiver, bounded};
+use egui::{ColorImage, TextureHandle, TextureOptions, Vec2, load::SizedTexture};
+use log::warn;
+use resvg::usvg::Tree;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+// The largest we will draw on either axis; past this the texture gets too big for many GPUs,
+// and zooming further scales up this drawing.
+const MAX_EDGE: f32 = 8192.;
+
+// How far the shown size may drift from the drawn size before we draw again. Zooming moves in
+// steps of 10%, so each step gets a fresh drawing, where a Ken Burns pan doesn't redraw every
+// frame.
+const REDRAW_RATIO: f32 = 1.05;
+
+type Drawn = Result<(Arc<Tree>, [u32; 2], ColorImage), String>;
+
+fn draw(path: &Path, tree: Option<Arc<Tree>>, size: [u32; 2]) -> Drawn {
+    let tree = match tree {
+        Some(tree) => tree,
+        None => Arc::new(vector::open(path).map_err(|e| e.to_string())?),
+    };
+    let pixels = vector::rasterize(&tree, size[0], size[1]).map_err(|e| e.to_string())?;
+    let image = ColorImage::from_rgba_unmultiplied(
+        [pixels.width() as usize, pixels.height() as usize],
+        pixels.as_raw(),
+    );
+    Ok((tree, size, image))
+}
+
+// The pixel size to draw at to fill `size` points, capped to what a texture can hold.
+fn pixel_size(size: Vec2, pixels_per_point: f32) -> [u32; 2] {
+    let size = size * pixels_per_point;
+    let size = size * (MAX_EDGE / size.max_elem()).min(1.);
+    [size.x.round().max(1.) as u32, size.y.round().max(1.) as u32]
+}
+
+fn needs_redraw(drawn: [u32; 2], wanted: [u32; 2]) -> bool {
+    let (drawn, wanted) = (
+        drawn[0].max(drawn[1]) as f32,
+        wanted[0].max(wanted[1]) as f32,
+    );
+    drawn * REDRAW_RATIO < wanted || wanted * REDRAW_RATIO < drawn
+}
+
+#[derive(Default)]
+pub struct UxVector {
+    path: Option<PathBuf>,
+    // Parsed once per work, so that each zoom step only has to draw.
+    tree: Option<Arc<Tree>>,
+    texture: Option<(TextureHandle, [u32; 2])>,
+    pending: Option<Receiver<Drawn>>,
+    error: bool,
+}
+
+impl UxVector {
+    // Ask for the drawing at `path` to fill `size`, the slideshow's zoomed viewport, starting a
+    // new drawing if the one we have is the wrong size for it.
+    pub fn request(&mut self, path: &Path, size: Vec2, ctx: &egui::Context) {
+        if self.path.as_deref() != Some(path) {
+            *self = Self {
+                path: Some(path.to_owned()),
+                ..Self::default()
+            };
+        }
+        if let Some(rx) = &self.pending
+            && let Ok(drawn) = rx.try_recv()
+        {
+            self.pending = None;
+            match drawn {
+                Ok((tree, size, image)) => {
+                    self.tree = Some(tree);
+                    match &mut self.texture {
+                        Some((handle, drawn)) => {
+                            handle.set(image, TextureOptions::LINEAR);
+                            *drawn = size;
+                        }
+                        None => {
+                            let name = format!("vector://{}", path.display());
+                            let handle = ctx.load_texture(name, image, TextureOptions::LINEAR);
+                            self.texture = Some((handle, size));
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to draw {}: {e}", path.display());
+                    self.error = true;
+                }
+            }
+        }
+        if self.error || self.pending.is_some() {
+            return;
+        }
+
+        let wanted = pixel_size(size, ctx.pixels_per_point());
+        if self
+            .texture
+            .as_ref()
+            .is_some_and(|(_, drawn)| !needs_redraw(*drawn, wanted))
+        {
+            return;
+        }
+        let (tx, rx) = bounded(1);
+        let (path, tree, ctx) = (path.to_owned(), self.tree.clone(), ctx.clone());
+        thread::spawn(move || {
+            tx.send(draw(&path, tree, wanted)).ok();
+            ctx.request_repaint();
+        });
+        self.pending = Some(rx);
+    }
+
+    // The latest drawing of the work at `path`, if one has finished.
+    pub fn texture(&self, path: &Path) -> Option<SizedTexture> {
+        if self.path.as_deref() != Some(path) {
+            return None;
+        }
+        self.texture
+            .as_ref()
+            .map(|(handle, _)| SizedTexture::from_handle(handle))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{needs_redraw, pixel_size};
+    use egui::Vec2;
+
+    #[test]
+    fn test_pixel_size() {
+        assert_eq!(pixel_size(Vec2::new(800., 600.), 2.), [1600, 1200]);
+        // Note: deep zooms stop at the texture limit, keeping the shape.
+        assert_eq!(pixel_size(Vec2::new(20000., 10000.), 1.), [8192, 4096]);
+    }
+
+    #[test]
+    fn test_needs_redraw() {
+        assert!(!needs_redraw([1000, 500], [1020, 510]));
+        assert!(needs_redraw([1000, 500], [1100, 550]));
+        assert!(needs_redraw([1000, 500], [900, 450]));
+    }
+}
@@ -0,0 +1,301 @@
+// Fetches the Getty AAT and ULAN terms that tags stand for in the background, and the broader
+// terms above them, and the preferences to turn it on.
+use crate::{
+    db::{reader::DbReadHandle, writer::DbWriteHandle},
+    shared::{
+        enrichment,
+        progress::Progress,
+        update::DataUpdate,
+        vocabulary::{self, BATCH_SIZE, QUERY_DELAY, VocabularyTerm},
+    },
+};
+use artchiver_sdk::Authority;
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use log::error;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+enum FetcherEvent {
+    Fetched {
+        asked: Vec<(Authority, String)>,
+        terms: Vec<VocabularyTerm>,
+    },
+    Stopped {
+        error: Option<String>,
+    },
+}
+
+struct VocabularyRun {
+    ctx: egui::Context,
+
+    queue: Arc<Mutex<VecDeque<(Authority, String)>>>,
+    tx_event: Sender<FetcherEvent>,
+    rx_event: Receiver<FetcherEvent>,
+    running: bool,
+
+    scanning: bool,
+    last_scan: Option<Instant>,
+    // A plugin sent tags since the last scan, so there may be new terms to fetch.
+    stale: bool,
+    // We fetched terms with broader terms above them, which are the next level up to fetch.
+    climbing: bool,
+
+    // Terms that Getty didn't know, which we don't ask about again until restarted.
+    missing: HashSet<(Authority, String)>,
+
+    total: usize,
+    done: usize,
+    error: Option<String>,
+}
+
+impl Default for VocabularyRun {
+    fn default() -> Self {
+        let (tx_event, rx_event) = unbounded();
+        Self {
+            ctx: egui::Context::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            tx_event,
+            rx_event,
+            running: false,
+            scanning: false,
+            last_scan: None,
+            stale: false,
+            climbing: false,
+            missing: HashSet::new(),
+            total: 0,
+            done: 0,
+            error: None,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxVocabulary {
+    enabled: bool,
+
+    #[serde(skip)]
+    run: VocabularyRun,
+}
+
+impl UxVocabulary {
+    // Tag refreshes arrive in bursts; look for new terms at most this often.
+    const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn startup(&mut self, ctx: &egui::Context, db: &DbReadHandle) {
+        self.run.ctx = ctx.clone();
+        if self.enabled {
+            self.scan(db);
+        }
+    }
+
+    fn scan(&mut self, db: &DbReadHandle) {
+        self.run.scanning = true;
+        self.run.stale = false;
+        self.run.climbing = false;
+        self.run.last_scan = Some(Instant::now());
+        db.get_vocabulary_to_fetch();
+    }
+
+    fn stop(&mut self) {
+        // Note: the worker finishes the query it is on, then finds the queue empty and exits.
+        let dropped = {
+            let mut queue = self.run.queue.lock();
+            let dropped = queue.len();
+            queue.clear();
+            dropped
+        };
+        self.run.total -= dropped;
+    }
+
+    pub fn handle_updates(&mut self, updates: &[DataUpdate]) {
+        for update in updates {
+            match update {
+                DataUpdate::VocabularyToFetch(terms) => {
+                    self.run.scanning = false;
+                    if self.enabled {
+                        self.enqueue(terms);
+                    }
+                }
+                DataUpdate::TagsWereRefreshed => {
+                    self.run.stale = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn enqueue(&mut self, terms: &[(Authority, String)]) {
+        {
+            let mut queue = self.run.queue.lock();
+            let queued = queue.iter().cloned().collect::<HashSet<_>>();
+            let fresh = terms
+                .iter()
+                .filter(|term| !queued.contains(*term) && !self.run.missing.contains(*term))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !self.run.running {
+                self.run.total = 0;
+                self.run.done = 0;
+                self.run.error = None;
+            }
+            self.run.total += fresh.len();
+            queue.extend(fresh);
+        }
+        if !self.run.running && !self.run.queue.lock().is_empty() {
+            self.spawn_worker();
+        }
+    }
+
+    fn spawn_worker(&mut self) {
+        let queue = self.run.queue.clone();
+        let tx = self.run.tx_event.clone();
+        let ctx = self.run.ctx.clone();
+        let spawned = thread::Builder::new()
+            .name("Vocabulary".to_owned())
+            .spawn(move || {
+                let agent = enrichment::make_agent();
+                loop {
+                    let batch = {
+                        let mut queue = queue.lock();
+                        let count = queue.len().min(BATCH_SIZE);
+                        queue.drain(..count).collect::<Vec<_>>()
+                    };
+                    if batch.is_empty() {
+                        break;
+                    }
+                    let terms = batch
+                        .iter()
+                        .map(|(authority, value)| (*authority, value.as_str()))
+                        .collect::<Vec<_>>();
+                    let fetched = match vocabulary::query_terms(&agent, &terms) {
+                        Ok(fetched) => fetched,
+                        Err(e) => {
+                            // Note: these terms stay unfetched, so the next scan tries again.
+                            queue.lock().clear();
+                            tx.send(FetcherEvent::Stopped {
+                                error: Some(format!("{e:#}")),
+                            })
+                            .ok();
+                            ctx.request_repaint();
+                            return;
+                        }
+                    };
+                    let event = FetcherEvent::Fetched {
+                        asked: batch,
+                        terms: fetched,
+                    };
+                    if tx.send(event).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                    thread::sleep(QUERY_DELAY);
+                }
+                tx.send(FetcherEvent::Stopped { error: None }).ok();
+                ctx.request_repaint();
+            });
+        match spawned {
+            Ok(_) => self.run.running = true,
+            Err(e) => error!("Failed to start fetching Getty vocabularies: {e}"),
+        }
+    }
+
+    pub fn tick(&mut self, db: &DbReadHandle, db_write: &DbWriteHandle) {
+        while let Ok(event) = self.run.rx_event.try_recv() {
+            match event {
+                FetcherEvent::Fetched { asked, terms } => {
+                    self.run.done += asked.len();
+                    for term in asked {
+                        if !terms
+                            .iter()
+                            .any(|t| t.authority == term.0 && t.value == term.1)
+                        {
+                            self.run.missing.insert(term);
+                        }
+                    }
+                    if terms.iter().any(|term| term.broader.is_some()) {
+                        self.run.climbing = true;
+                    }
+                    if let Err(e) = db_write.save_vocabulary_terms(terms) {
+                        error!("Failed to save what Getty told us: {e}");
+                    }
+                }
+                FetcherEvent::Stopped { error } => {
+                    self.run.running = false;
+                    if let Some(e) = &error {
+                        error!("Fetching Getty vocabularies stopped: {e}");
+                        self.run.climbing = false;
+                    }
+                    // Note: terms may have been queued after the worker found the queue empty.
+                    if error.is_none() && !self.run.queue.lock().is_empty() {
+                        self.spawn_worker();
+                    }
+                    self.run.error = error;
+                }
+            }
+        }
+        // Note: a finished run goes straight on to the next level of broader terms.
+        let due = self.run.climbing
+            || (self.run.stale
+                && self
+                    .run
+                    .last_scan
+                    .is_none_or(|at| at.elapsed() >= Self::RESCAN_INTERVAL));
+        if due && !self.run.running && !self.run.scanning && self.enabled {
+            self.scan(db);
+        }
+    }
+
+    pub fn preferences_ui(&mut self, db: &DbReadHandle, ui: &mut egui::Ui) {
+        let was_enabled = self.enabled;
+        ui.checkbox(
+            &mut self.enabled,
+            "Look up tags in the Getty vocabularies, for plugins that give us their AAT or ULAN ids",
+        );
+        ui.label(
+            "Tags get the other names that Getty knows them by as aliases to search by, and imply \
+             the broader tags above them: e.g. works tagged \"Portraits\" are also tagged \
+             \"Paintings\", if there is such a tag.",
+        );
+
+        ui.horizontal(|ui| {
+            if self.run.scanning {
+                ui.spinner();
+            } else if self.run.running {
+                Progress::Percent {
+                    current: self.run.done,
+                    total: self.run.total.max(1),
+                }
+                .ui(ui);
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else {
+                if self.run.total > 0 {
+                    ui.label(format!("fetched {} terms", self.run.done));
+                }
+                if ui
+                    .add_enabled(self.enabled, egui::Button::new("Fetch Now"))
+                    .on_hover_text("Fetch the terms for tags that haven't been fetched")
+                    .clicked()
+                {
+                    self.scan(db);
+                }
+            }
+        });
+        if let Some(e) = &self.run.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        if self.enabled && !was_enabled {
+            self.scan(db);
+        } else if !self.enabled && was_enabled {
+            self.stop();
+        }
+    }
+}
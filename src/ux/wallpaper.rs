@@ -0,0 +1,220 @@
+use crate::shared::{storage::Storage, wallpaper::set_wallpaper};
+use crossbeam::channel::{Receiver, bounded};
+use jiff::{SignedDuration, Timestamp};
+use log::error;
+use rand::seq::SliceRandom as _;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, thread, time::Duration};
+
+// Rotate the desktop wallpaper through a set of works, e.g. the user's favorites.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UxWallpaper {
+    // Preferences
+    enabled: bool,
+    interval_mins: u32,
+    shuffle: bool,
+    // Width and height in pixels, left to right.
+    monitors: Vec<[u32; 2]>,
+
+    // Stored paths of the screen images to rotate through, so that moving storage around
+    // doesn't break the rotation.
+    playlist: Vec<PathBuf>,
+    position: usize,
+    last_change: Option<Timestamp>,
+
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    pending: Option<Receiver<Result<(), String>>>,
+    #[serde(skip)]
+    last_error: Option<String>,
+}
+
+impl Default for UxWallpaper {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_mins: 30,
+            shuffle: true,
+            monitors: Vec::new(),
+            playlist: Vec::new(),
+            position: 0,
+            last_change: None,
+            open: false,
+            pending: None,
+            last_error: None,
+        }
+    }
+}
+
+impl UxWallpaper {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    fn interval(&self) -> SignedDuration {
+        SignedDuration::from_mins(i64::from(self.interval_mins.max(1)))
+    }
+
+    fn current_monitor(ctx: &egui::Context) -> Option<[u32; 2]> {
+        let size = ctx.input(|i| i.viewport().monitor_size)? * ctx.pixels_per_point();
+        Some([size.x.round() as u32, size.y.round() as u32])
+    }
+
+    // Move on to the next wallpaper if it is time; call once per frame.
+    pub fn tick(&mut self, storage: &Storage, ctx: &egui::Context) {
+        if let Some(rx) = &self.pending
+            && let Ok(result) = rx.try_recv()
+        {
+            self.pending = None;
+            self.last_error = result.err();
+        }
+        if !self.enabled || self.playlist.is_empty() || self.pending.is_some() {
+            return;
+        }
+        if let Some(last) = self.last_change {
+            let remaining = self.interval() - Timestamp::now().duration_since(last);
+            if remaining.is_positive() {
+                ctx.request_repaint_after(
+                    Duration::try_from(remaining).unwrap_or(Duration::from_secs(60)),
+                );
+                return;
+            }
+        }
+        self.advance(storage, ctx);
+    }
+
+    fn advance(&mut self, storage: &Storage, ctx: &egui::Context) {
+        if self.playlist.is_empty() {
+            return;
+        }
+        if self.monitors.is_empty()
+            && let Some(monitor) = Self::current_monitor(ctx)
+        {
+            self.monitors.push(monitor);
+        }
+        let stored = self.playlist[self.position % self.playlist.len()].clone();
+        self.position = (self.position + 1) % self.playlist.len();
+        if self.position == 0 && self.shuffle {
+            self.playlist.shuffle(&mut rand::rng());
+        }
+        self.last_change = Some(Timestamp::now());
+
+        if !storage.is_available(&stored) {
+            // Note: try again next time around, once it has been fetched.
+            storage.prefetch(&stored);
+            self.last_error = Some(format!("{} is not available yet", stored.display()));
+            return;
+        }
+        let source = storage.resolve(&stored);
+        let dir = storage.data_dir().join("wallpaper");
+        let monitors = self.monitors.clone();
+        let generation = self.position as u64;
+        let (tx, rx) = bounded(1);
+        self.pending = Some(rx);
+        thread::spawn(move || {
+            let result =
+                set_wallpaper(&source, &monitors, &dir, generation).map_err(|e| format!("{e:#}"));
+            if let Err(e) = &result {
+                error!("Failed to set the wallpaper: {e}");
+            }
+            tx.send(result).ok();
+        });
+    }
+
+    // `count` is the number of works in the gallery; `works` gets the stored paths of their
+    // screen images, and is only called if the user picks them for the rotation.
+    pub fn window(
+        &mut self,
+        count: usize,
+        works: impl FnOnce() -> Vec<PathBuf>,
+        storage: &Storage,
+        ctx: &egui::Context,
+    ) {
+        let mut is_open = self.open;
+        let mut advance = false;
+        egui::Window::new("Wallpaper Rotation")
+            .open(&mut is_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} works in the rotation", self.playlist.len()));
+                    if ui
+                        .add_enabled(count > 0, egui::Button::new("Use the Gallery's Works"))
+                        .on_hover_text(
+                            "Replace the rotation with the works in the gallery, e.g. your favorites",
+                        )
+                        .clicked()
+                    {
+                        self.playlist = works();
+                        if self.shuffle {
+                            self.playlist.shuffle(&mut rand::rng());
+                        }
+                        self.position = 0;
+                    }
+                });
+                ui.checkbox(&mut self.enabled, "Rotate the desktop wallpaper");
+                ui.add(
+                    egui::Slider::new(&mut self.interval_mins, 1..=1440)
+                        .text("Minutes per work")
+                        .logarithmic(true),
+                );
+                ui.checkbox(&mut self.shuffle, "Shuffle");
+
+                ui.separator();
+                ui.label("Monitors, left to right; each gets its own crop of the work.");
+                let mut remove = None;
+                for (i, [width, height]) in self.monitors.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(width).range(1..=16384).suffix("px"));
+                        ui.label("x");
+                        ui.add(egui::DragValue::new(height).range(1..=16384).suffix("px"));
+                        if ui.small_button("x").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.monitors.remove(i);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add Monitor").clicked() {
+                        self.monitors.push([1920, 1080]);
+                    }
+                    if let Some(monitor) = Self::current_monitor(ctx)
+                        && ui
+                            .button("Add This Monitor")
+                            .on_hover_text(format!("{} x {}", monitor[0], monitor[1]))
+                            .clicked()
+                    {
+                        self.monitors.push(monitor);
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.playlist.is_empty() && self.pending.is_none(),
+                            egui::Button::new("Next Wallpaper"),
+                        )
+                        .clicked()
+                    {
+                        advance = true;
+                    }
+                    if self.pending.is_some() {
+                        ui.spinner();
+                    }
+                });
+                if let Some(e) = &self.last_error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+            });
+        self.open = is_open;
+
+        if advance {
+            self.advance(storage, ctx);
+        }
+    }
+}
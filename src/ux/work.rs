@@ -1,31 +1,77 @@
 use crate::{
     db::{
         models::{
+            collection::DbCollection,
             tag::{DbTag, TagId},
-            work::{DbWork, WorkId},
+            work::{DbWork, DbWorkIdentity, DbWorkRevision, MediaType, WorkId},
+        },
+        {
+            model::OrderDir,
+            reader::{DbReadHandle, PublicView},
+            writer::DbWriteHandle,
         },
-        {model::OrderDir, reader::DbReadHandle, writer::DbWriteHandle},
     },
-    plugin::{host::PluginHost, thumbnail::is_image},
+    plugin::{
+        host::PluginHost,
+        thumbnail::{is_image, open_image},
+    },
     shared::{
+        contact_sheet::SheetEntry,
+        deep_link::DeepLink,
+        document::is_paged,
+        enrichment::Statement,
+        external::{ExternalEditor, open_in_default_viewer, reveal_in_file_manager},
         performance::PerfTrack,
+        storage::Storage,
         tag::{TagRefresh, TagSet},
         update::DataUpdate,
+        variants::VariantWatcher,
+        vector::is_vector,
+    },
+    ux::{
+        annotations::UxAnnotations,
+        color::ColorManagement,
+        contact_sheet::UxContactSheet,
+        display::{UxDisplay, apply, displayed_size, fit},
+        document::UxDocument,
+        export::UxExport,
+        filter::{CompiledFilters, ContentFilters},
+        gallery_layout::{GalleryLayout, GalleryMode, LayoutItem},
+        grouping::WorkGrouping,
+        history::{NavEntry, NavHistory},
+        image_cache::ImageCache,
+        image_info::UxImageInfo,
+        model::UxModel,
+        notes::UxWorkNote,
+        playback::UxPlayback,
+        prefetch::ScrollPrefetch,
+        projection::apply_changes,
+        queue::PlayQueue,
+        reference::UxReference,
+        slideshow::{FILMSTRIP_THUMB, Slideshow, SpreadDirection, filmstrip_window, salient_point},
+        transcript::UxTranscript,
+        tutorial::{NextButton, Tutorial, TutorialStep},
+        vector::UxVector,
+        wallpaper::UxWallpaper,
     },
-    ux::tutorial::{NextButton, Tutorial, TutorialStep},
 };
 use anyhow::Result;
-use egui::{Key, Margin, Modifiers, PointerButton, Rect, Sense, SizeHint, Vec2, include_image};
+use artchiver_sdk::{ActionScope, Authority, GroupKind, TagKind};
+use egui::{
+    Color32, Key, Modifiers, PointerButton, Rangef, Rect, Sense, SizeHint, Vec2, include_image,
+};
 use egui_mpv_glow::MpvPlayer;
 use itertools::Itertools as _;
-use log::{info, trace};
-use lru::LruCache;
+use jiff::tz::TimeZone;
+use log::{error, info, trace};
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash as _, Hasher as _},
     iter::once,
+    ops::Range,
     path::{Path, PathBuf},
     time::{Duration, Instant},
 };
@@ -40,19 +86,41 @@ pub enum DisplayKind<'a> {
 pub enum WorkSortCol {
     #[default]
     Date,
+    Title,
+    Artist,
+    DateAdded,
+    Random,
+    FileSize,
 }
 
 impl WorkSortCol {
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         let mut selected = match self {
             Self::Date => 0,
+            Self::Title => 1,
+            Self::Artist => 2,
+            Self::DateAdded => 3,
+            Self::Random => 4,
+            Self::FileSize => 5,
         };
-        let labels = ["Date"];
+        let labels = [
+            "Date",
+            "Title",
+            "Artist",
+            "Date Added",
+            "Random",
+            "File Size",
+        ];
         egui::ComboBox::new("tag_order_column", "")
             .wrap_mode(egui::TextWrapMode::Truncate)
             .show_index(ui, &mut selected, labels.len(), |i| labels[i]);
         *self = match selected {
             0 => Self::Date,
+            1 => Self::Title,
+            2 => Self::Artist,
+            3 => Self::DateAdded,
+            4 => Self::Random,
+            5 => Self::FileSize,
             _ => panic!("invalid column selected"),
         };
     }
@@ -62,15 +130,41 @@ impl WorkSortCol {
 pub struct WorkOrder {
     column: WorkSortCol,
     order: OrderDir,
+    // Random order is fixed by this, so that the gallery doesn't reshuffle on every change.
+    #[serde(default)]
+    seed: u64,
+    // Shuffle over the top of the chosen sort, so that turning it off gets that back.
+    #[serde(default)]
+    shuffle: bool,
 }
 
 impl WorkOrder {
     pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
         let prior = *self;
-        self.column.ui(ui);
-        self.order.ui("tags", ui);
+        ui.add_enabled_ui(!self.shuffle, |ui| {
+            self.column.ui(ui);
+            self.order.ui("tags", ui);
+        });
+        ui.toggle_value(&mut self.shuffle, "🔀")
+            .on_hover_text("Shuffle the works");
+        if self.column() == WorkSortCol::Random
+            && ui
+                .small_button("🎲")
+                .on_hover_text("Shuffle again")
+                .clicked()
+        {
+            self.seed = rand::random();
+        }
         *self != prior
     }
+
+    fn column(&self) -> WorkSortCol {
+        if self.shuffle {
+            WorkSortCol::Random
+        } else {
+            self.column
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -157,6 +251,28 @@ impl ZoomPan {
     pub fn pan(&mut self, delta: Vec2) {
         self.pan += delta;
     }
+
+    // Zoom and pan so that `focus`, a point in the image from (0,0) to (1,1), is as close to the
+    // middle of the viewport as the edges of the image allow. `fill` is the fraction of the
+    // viewport that the image covers on each axis when unzoomed.
+    pub fn frame(&mut self, zoom: f32, focus: Vec2, fill: Vec2, viewport: Vec2) {
+        self.zoom = zoom.max(1.);
+        let full = viewport * self.zoom;
+        let image = full * fill;
+        let margin = (full - image) / 2.;
+        let pan = viewport / 2. - (margin + image * focus);
+        let clamp = |pan: f32, margin: f32, image: f32, view: f32| {
+            if image <= view {
+                (view - image) / 2. - margin
+            } else {
+                pan.clamp(view - image - margin, -margin)
+            }
+        };
+        self.pan = Vec2::new(
+            clamp(pan.x, margin.x, image.x, viewport.x),
+            clamp(pan.y, margin.y, image.y, viewport.y),
+        );
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -187,6 +303,41 @@ pub struct UxWork {
     // Filter state for the works gallery
     tag_selection: TagSet,
     order: WorkOrder,
+    grouping: WorkGrouping,
+    gallery_mode: GalleryMode,
+
+    slideshow: Slideshow,
+    image_info: UxImageInfo,
+    reference: UxReference,
+    #[serde(skip)]
+    document: UxDocument,
+    #[serde(skip)]
+    model: UxModel,
+    #[serde(skip)]
+    vector: UxVector,
+    transcript: UxTranscript,
+    // Show a work's 3D model in the slideshow, where it has one, rather than its image.
+    show_models: bool,
+    color: ColorManagement,
+    export: UxExport,
+    contact_sheet: UxContactSheet,
+    wallpaper: UxWallpaper,
+    filters: ContentFilters,
+    // Show works that were only recorded, and have nothing downloaded yet.
+    show_undownloaded: bool,
+    // Only show works of these kinds; all of them when empty.
+    media_types: HashSet<MediaType>,
+    // Programs offered under "Open With" on a work's context menu.
+    external_editors: Vec<ExternalEditor>,
+    queue: PlayQueue,
+    playback: UxPlayback,
+
+    #[serde(skip)]
+    display: UxDisplay,
+
+    // The works in work_filtered that a content filter says to blur in the gallery.
+    #[serde(skip)]
+    work_blurred: HashSet<WorkId>,
 
     #[serde(skip)]
     scroll_to_selected: ScrollRequestKind,
@@ -200,13 +351,13 @@ pub struct UxWork {
     #[serde(skip)]
     slide_xform: ZoomPan,
 
-    #[serde(skip)]
-    work_reproject_timer: Option<Instant>,
-
     // Don't cache things that are too long or only last one frame
     #[serde(skip)]
     per_frame_work_upload_count: usize,
 
+    #[serde(skip)]
+    scroll_prefetch: ScrollPrefetch,
+
     // The cached set of works is everything selected by the tag_set.
     #[serde(skip)]
     work_matching_tag: Option<HashMap<WorkId, DbWork>>,
@@ -214,11 +365,14 @@ pub struct UxWork {
     #[serde(skip)]
     work_filtered: Vec<WorkId>,
 
+    // Where each work and group heading goes in the gallery; rebuilt when work_filtered changes.
+    #[serde(skip)]
+    gallery_layout: Option<GalleryLayout>,
+
     #[serde(skip)]
-    data_dir: PathBuf,
+    storage: Storage,
 
-    #[serde(skip, default = "LruCache::unbounded")]
-    works_lru: LruCache<String, u32>,
+    image_cache: ImageCache,
 
     #[serde(skip, default)]
     mpv: MpvPlayer,
@@ -230,6 +384,72 @@ pub struct UxWork {
     // Show a spinner while works are loading async and incrementally
     #[serde(skip, default)]
     is_loading_works: bool,
+
+    // The last work shown in the slideshow, so that we only record each view once.
+    #[serde(skip, default)]
+    last_viewed: Option<WorkId>,
+
+    // Works whose screen image we have asked a plugin for, so that we only ask once.
+    #[serde(skip, default)]
+    requested_screens: HashSet<WorkId>,
+
+    #[serde(skip, default)]
+    work_source: WorkDetail<Option<String>>,
+
+    #[serde(skip, default)]
+    work_history: WorkDetail<Vec<DbWorkRevision>>,
+
+    #[serde(skip, default)]
+    work_variants: WorkDetail<Vec<String>>,
+
+    #[serde(skip, default)]
+    work_identity: WorkDetail<DbWorkIdentity>,
+
+    #[serde(skip, default)]
+    work_note: UxWorkNote,
+
+    annotations: UxAnnotations,
+
+    // The folders of works we handed to other programs, to catch edits saved next to them.
+    #[serde(skip, default)]
+    variant_watcher: VariantWatcher,
+
+    // A linked work to select once the gallery has finished loading the works for its tag.
+    #[serde(skip, default)]
+    select_when_loaded: Option<String>,
+
+    // Where to start playing a work from, once mpv has it loaded, as after a transcript search.
+    #[serde(skip, default)]
+    seek_when_loaded: Option<(String, f64)>,
+
+    // Whether the selected work is shown large over the gallery, as with Space.
+    #[serde(skip, default)]
+    peeking: bool,
+
+    // Back and forward through the tag selections, with where we were in each.
+    #[serde(skip, default)]
+    history: NavHistory,
+
+    #[serde(skip, default)]
+    gallery_scroll: f32,
+
+    // The scroll offset to put back once the gallery has loaded, after going back or forward.
+    #[serde(skip, default)]
+    restore_scroll: Option<f32>,
+
+    // The tags of the volumes and portfolios that plugins report, whose works read as pages.
+    #[serde(skip, default)]
+    book_tags: HashSet<TagId>,
+
+    // Whether the slideshow is pairing up pages, so that moving goes a spread at a time.
+    #[serde(skip, default)]
+    in_spread_view: bool,
+    // Whether the slideshow is reading a document, which takes the drags and scrolls itself.
+    #[serde(skip, default)]
+    in_document_view: bool,
+    // Whether the slideshow is showing a 3D model, which takes the drags and scrolls to turn it.
+    #[serde(skip, default)]
+    in_model_view: bool,
 }
 
 impl Default for UxWork {
@@ -240,35 +460,91 @@ impl Default for UxWork {
             scroll_to_selected: ScrollRequestKind::None,
             tag_selection: TagSet::default(),
             order: WorkOrder::default(),
+            grouping: WorkGrouping::default(),
+            gallery_mode: GalleryMode::default(),
+            slideshow: Slideshow::default(),
+            image_info: UxImageInfo::default(),
+            reference: UxReference::default(),
+            document: UxDocument::default(),
+            model: UxModel::default(),
+            vector: UxVector::default(),
+            transcript: UxTranscript::default(),
+            show_models: true,
+            color: ColorManagement::default(),
+            export: UxExport::default(),
+            contact_sheet: UxContactSheet::default(),
+            wallpaper: UxWallpaper::default(),
+            filters: ContentFilters::default(),
+            show_undownloaded: false,
+            media_types: HashSet::new(),
+            external_editors: Vec::new(),
+            queue: PlayQueue::default(),
+            playback: UxPlayback::default(),
+            display: UxDisplay::default(),
+            work_blurred: HashSet::new(),
             last_mouse_motion: Instant::now(),
             showing: WorkVisibility::default(),
             slide_xform: ZoomPan::default(),
-            work_reproject_timer: None,
             per_frame_work_upload_count: 0,
+            scroll_prefetch: ScrollPrefetch::default(),
             work_matching_tag: None,
             work_filtered: Vec::new(),
-            data_dir: PathBuf::new(),
-            works_lru: LruCache::unbounded(),
+            gallery_layout: None,
+            storage: Storage::default(),
+            image_cache: ImageCache::default(),
             mpv: MpvPlayer::default(),
             has_loaded_media: false,
             is_loading_works: true,
+            last_viewed: None,
+            requested_screens: HashSet::new(),
+            work_source: WorkDetail::Unloaded,
+            work_history: WorkDetail::Unloaded,
+            work_variants: WorkDetail::Unloaded,
+            work_identity: WorkDetail::Unloaded,
+            work_note: UxWorkNote::default(),
+            annotations: UxAnnotations::default(),
+            variant_watcher: VariantWatcher::default(),
+            select_when_loaded: None,
+            seek_when_loaded: None,
+            peeking: false,
+            history: NavHistory::default(),
+            gallery_scroll: 0.,
+            restore_scroll: None,
+            book_tags: HashSet::new(),
+            in_spread_view: false,
+            in_document_view: false,
+            in_model_view: false,
         }
     }
 }
 
+// What the user picked from a work's context menu that the gallery has to follow up on.
+enum WorkMenuAction {
+    // We handed the file, stored here, to another program, which may edit it.
+    HandedOff(PathBuf),
+    Enqueue,
+}
+
+fn work_link(work: &DbWork) -> String {
+    DeepLink::Work {
+        screen_url: work.screen_url().to_owned(),
+    }
+    .to_string()
+}
+
 impl UxWork {
-    const LRU_CACHE_SIZE: usize = 500;
     const MAX_PER_FRAME_UPLOADS: usize = 3;
 
     pub fn startup(
         &mut self,
-        data_dir: &Path,
+        storage: &Storage,
         db: &DbReadHandle,
         cc: &eframe::CreationContext<'_>,
     ) -> Result<()> {
         trace!("Starting up work UX");
 
-        self.data_dir = data_dir.to_owned();
+        self.storage = storage.clone();
+        self.filters.load_classifier();
 
         // FIXME: this is going to fetch the wrong thing. We want the smallest tag, as selected elsewhere.
         self.is_loading_works = true;
@@ -277,7 +553,9 @@ impl UxWork {
         } else if self.tag_selection.is_empty() {
             db.get_favorite_works();
         }
+        db.get_display_transforms();
 
+        self.color.install(&cc.egui_ctx);
         self.mpv.init_with_eframe(cc)?;
 
         Ok(())
@@ -289,15 +567,16 @@ impl UxWork {
         db: &DbReadHandle,
         updates: &[DataUpdate],
     ) {
-        // Note: we only care about reprojection cost incurred _not_ by the user: e.g. through
-        //       messages (e.g. database changes). We always need to record the changes, but we
-        //       don't have to immediately show the changes if it's going to lag the UX.
-        if let Some(start) = self.work_reproject_timer
-            && start.elapsed() > Duration::from_secs(4)
-        {
-            self.work_reproject_timer = None;
-            self.reproject_work(tags);
-        }
+        self.display.handle_updates(updates);
+        self.work_note.handle_updates(updates);
+        self.annotations.handle_updates(updates);
+        self.transcript.handle_updates(updates);
+
+        // Note: changes that arrive as messages, rather than from the user, can come thick and
+        //       fast during an import, so we only re-filter the works that they touch, once
+        //       per batch of updates.
+        let mut changed = HashSet::new();
+        let mut added = Vec::new();
 
         for update in updates {
             match update {
@@ -309,12 +588,14 @@ impl UxWork {
                     if *tag_id == self.tag_selection.last_fetched() {
                         trace!("Received {} works for tag {tag_id:?}", works.len());
                         self.is_loading_works = !finished;
-                        if let Some(local) = self.work_matching_tag.as_mut() {
-                            local.extend(works.iter().map(|(id, work)| (*id, work.to_owned())));
-                        } else {
-                            self.work_matching_tag = Some(works.to_owned());
+                        let local = self.work_matching_tag.get_or_insert_default();
+                        for (id, work) in works {
+                            if local.insert(*id, work.to_owned()).is_some() {
+                                changed.insert(*id);
+                            } else {
+                                added.push(*id);
+                            }
                         }
-                        self.reproject_work(tags);
                     } else {
                         trace!(
                             "Ignoring works for tag {tag_id:?} (expected {:?})",
@@ -322,14 +603,56 @@ impl UxWork {
                         );
                     }
                 }
-                DataUpdate::InitialTags(_) => {
+                DataUpdate::WorkSource { work_id, source } => {
+                    if self.work_source.is_loading(*work_id) {
+                        // Note: most sources are JSON on one line, which is unreadable as-is.
+                        let source = source.as_ref().map(|source| {
+                            serde_json::from_str::<serde_json::Value>(source)
+                                .and_then(|value| serde_json::to_string_pretty(&value))
+                                .unwrap_or_else(|_| source.clone())
+                        });
+                        self.work_source = WorkDetail::Loaded(*work_id, source);
+                    }
+                }
+                DataUpdate::Collections(collections) => {
+                    self.book_tags = collections
+                        .iter()
+                        .filter(|c| matches!(c.kind(), GroupKind::Volume | GroupKind::Portfolio))
+                        .map(DbCollection::tag_id)
+                        .collect();
+                }
+                DataUpdate::WorkHistory { work_id, revisions } => {
+                    if self.work_history.is_loading(*work_id) {
+                        self.work_history = WorkDetail::Loaded(*work_id, revisions.clone());
+                    }
+                }
+                DataUpdate::WorkIdentity { work_id, identity } => {
+                    if self.work_identity.is_loading(*work_id) {
+                        self.work_identity = WorkDetail::Loaded(*work_id, identity.clone());
+                    }
+                }
+                DataUpdate::WorkVariants { work_id, variants } => {
+                    if self.work_variants.is_loading(*work_id) {
+                        self.work_variants = WorkDetail::Loaded(*work_id, variants.clone());
+                    }
+                }
+                // Note: paths changed under us, so re-fetch works to pick up the new ones.
+                DataUpdate::InitialTags(_)
+                | DataUpdate::StorageRelocated(_)
+                | DataUpdate::TrashedWorks(_) => {
                     self.tag_selection.force_refresh();
                 }
-                DataUpdate::WorksWereUpdatedForTag { for_tag } => {
-                    if self.tag_selection.enabled().any(|id| {
-                        tags.and_then(|tags| tags.get(&id)).map(|tag| tag.name())
-                            == Some(for_tag.as_str())
-                    }) {
+                DataUpdate::WorksWereUpdatedForTag { for_tag, .. } => {
+                    // Note: the plugin may have changed the selected work.
+                    self.work_history = WorkDetail::Unloaded;
+                    self.work_source = WorkDetail::Unloaded;
+                    self.work_identity = WorkDetail::Unloaded;
+                    if for_tag.is_empty()
+                        || self.tag_selection.enabled().any(|id| {
+                            tags.and_then(|tags| tags.get(&id)).map(|tag| tag.name())
+                                == Some(for_tag.as_str())
+                        })
+                    {
                         self.tag_selection.force_refresh();
                     }
                 }
@@ -338,28 +661,111 @@ impl UxWork {
                     preview_path,
                     screen_path,
                     archive_path,
+                    thumb_path,
+                    aspect_ratio,
                 } => {
                     if let Some(works) = self.work_matching_tag.as_mut()
                         && let Some(work) = works.get_mut(id)
                     {
-                        let preview_path = self.data_dir.join(preview_path);
-                        let screen_path = self.data_dir.join(screen_path);
-                        let archive_path = archive_path.as_ref().map(|a| self.data_dir.join(a));
-                        work.set_paths(preview_path, screen_path, archive_path);
-                        if self.work_reproject_timer.is_none() {
-                            self.work_reproject_timer = Some(Instant::now());
-                        }
+                        work.set_paths(
+                            PathBuf::from(preview_path),
+                            (
+                                screen_path.as_ref().map(PathBuf::from),
+                                archive_path.as_ref().map(PathBuf::from),
+                                thumb_path.as_ref().map(PathBuf::from),
+                            ),
+                            *aspect_ratio,
+                        );
+                        changed.insert(*id);
+                    }
+                }
+                DataUpdate::WorkNsfwScored { work_id, score } => {
+                    if let Some(works) = self.work_matching_tag.as_mut()
+                        && let Some(work) = works.get_mut(work_id)
+                    {
+                        work.set_nsfw_score(*score);
+                        changed.insert(*work_id);
+                    }
+                }
+                DataUpdate::WorkModelDownloaded {
+                    work_id,
+                    model_path,
+                } => {
+                    if let Some(works) = self.work_matching_tag.as_mut()
+                        && let Some(work) = works.get_mut(work_id)
+                    {
+                        work.set_model_path(PathBuf::from(model_path));
+                        changed.insert(*work_id);
                     }
                 }
                 DataUpdate::TagHiddenStatusChanged { .. } => {
+                    // Note: this re-filters everything, including anything changed so far.
                     self.reproject_work(tags);
+                    changed.clear();
+                    added.clear();
+                }
+                DataUpdate::WorkRatingChanged { work_id, rating } => {
+                    if let Some(works) = self.work_matching_tag.as_mut()
+                        && let Some(work) = works.get_mut(work_id)
+                    {
+                        work.set_rating(*rating);
+                    }
                 }
                 _ => {}
             }
         }
 
+        if !changed.is_empty() || !added.is_empty() {
+            self.reproject_changed_works(&changed, &added, tags);
+        }
+
         // Check tag freshness
         self.ensure_works_up_to_date_with_tag_selection(tags, db);
+
+        if !self.is_loading_works
+            && let Some(screen_url) = self.select_when_loaded.take()
+            && !self.select_screen_url(&screen_url)
+        {
+            info!("The linked work at {screen_url} is hidden by the gallery's filters");
+        }
+
+        let loaded = !self.is_loading_works && self.restore_scroll.is_none();
+        let selected = self
+            .get_selected_work()
+            .filter(|_| loaded)
+            .map(|work| work.screen_url().to_owned());
+        self.history.observe(
+            self.tag_selection.enabled_vec(),
+            self.tag_selection.disabled().collect(),
+            selected.as_deref(),
+            loaded.then_some(self.gallery_scroll),
+        );
+    }
+
+    pub fn tick(&mut self, ctx: &egui::Context) {
+        self.wallpaper.tick(&self.storage, ctx);
+    }
+
+    pub fn poll_variants(&mut self, db_write: &DbWriteHandle) {
+        for found in self.variant_watcher.poll() {
+            if let Err(e) = db_write.add_work_variant(&found.screen_url, &found.stored) {
+                error!("Failed to record an edit of {}: {e}", found.screen_url);
+            }
+            self.work_variants = WorkDetail::Unloaded;
+        }
+    }
+
+    // The works for the current tags that are waiting on a gallery thumbnail.
+    pub fn works_without_thumbs(&self) -> HashSet<WorkId> {
+        self.work_matching_tag
+            .iter()
+            .flat_map(|works| works.values())
+            .filter(|work| {
+                work.screen_path().is_some()
+                    && (work.thumb_path().is_none() || work.aspect_ratio().is_none())
+            })
+            .map(DbWork::id)
+            .collect()
     }
 
     pub fn tag_selection(&self) -> &TagSet {
@@ -379,17 +785,111 @@ impl UxWork {
         self.slide_xform = ZoomPan::default();
         self.mpv.pause_async().ok();
         self.has_loaded_media = false;
+        // Note: picking something else to play is as good as pressing play.
+        self.playback.wake();
     }
 
     pub fn clear_selected(&mut self) {
         self.selected = None;
+        self.peeking = false;
         self.slide_xform = ZoomPan::default();
         self.mpv.pause_async().ok();
         self.has_loaded_media = false;
     }
 
+    // Select the work with the given url, if the gallery is showing it.
+    pub fn select_screen_url(&mut self, screen_url: &str) -> bool {
+        let Some(works) = self.work_matching_tag.as_ref() else {
+            return false;
+        };
+        let Some(offset) = self.work_filtered.iter().position(|id| {
+            works
+                .get(id)
+                .is_some_and(|work| work.screen_url() == screen_url)
+        }) else {
+            return false;
+        };
+        self.set_selected(offset);
+        // Note: the work may be anywhere in the gallery, so center it, as for a slideshow.
+        self.scroll_to_selected = ScrollRequestKind::LeaveSlideshow;
+        true
+    }
+
+    // As select_screen_url, then play the work from `secs` in.
+    pub fn select_screen_url_at(&mut self, screen_url: &str, secs: f64) -> bool {
+        if !self.select_screen_url(screen_url) {
+            return false;
+        }
+        self.seek_when_loaded = Some((screen_url.to_owned(), secs));
+        true
+    }
+
+    pub fn is_peeking(&self) -> bool {
+        self.peeking
+    }
+
+    pub fn set_peeking(&mut self, peeking: bool) {
+        self.peeking = peeking && self.selected.is_some();
+    }
+
+    // Select the work once the works for the tag selection are in, as after following a link.
+    pub fn select_when_loaded(&mut self, screen_url: &str) {
+        self.select_when_loaded = Some(screen_url.to_owned());
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.history.can_go_back()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.history.can_go_forward()
+    }
+
+    pub fn go_back(&mut self) {
+        if let Some(entry) = self.history.go_back() {
+            self.restore_nav(entry);
+        }
+    }
+
+    pub fn go_forward(&mut self) {
+        if let Some(entry) = self.history.go_forward() {
+            self.restore_nav(entry);
+        }
+    }
+
+    fn restore_nav(&mut self, entry: NavEntry) {
+        self.tag_selection.restore(&entry.enabled, &entry.disabled);
+        self.select_when_loaded = entry.selected;
+        self.restore_scroll = Some(entry.scroll);
+    }
+
+    // Returns true if the user asked to play the queue.
+    pub fn queue_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        self.queue.ui(ui)
+    }
+
+    // Show the work the queue has moved on to. Returns its url if it isn't in the gallery, for the
+    // caller to go and find, as with a link.
+    pub fn poll_queue(&mut self) -> Option<String> {
+        let screen_url = self.queue.take_request()?;
+        if self.select_screen_url(&screen_url) {
+            return None;
+        }
+        Some(screen_url)
+    }
+
+    // Whether the slideshow is waiting for the gallery to load the work playing from the queue.
+    pub fn is_awaiting_queue(&self) -> bool {
+        self.queue.playing().is_some_and(|screen_url| {
+            self.get_selected_work()
+                .is_none_or(|work| work.screen_url() != screen_url)
+        })
+    }
+
     pub fn on_leave_slideshow(&mut self) {
         trace!("Leaving slideshow");
+        self.slideshow.on_leave();
+        self.in_spread_view = false;
         self.scroll_to_selected = ScrollRequestKind::LeaveSlideshow;
         self.mpv.pause_async().ok();
         self.has_loaded_media = false;
@@ -422,49 +922,85 @@ impl UxWork {
         }
     }
 
+    // Whether the work belongs in the gallery under the current filters.
+    fn shows_work(
+        &self,
+        work: &DbWork,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        filters: &CompiledFilters,
+    ) -> bool {
+        // Only show works that we can actually show, unless asked to show the rest.
+        // Note: works whose screen image is fetched on view only have a preview.
+        (work.screen_path().is_some() || work.preview_path().is_some() || self.show_undownloaded)
+            // Filter out hidden or favorite works if we're not showing them.
+            && ((self.showing == WorkVisibility::Normal && !work.hidden())
+                || (self.showing == WorkVisibility::Favorites && work.favorite())
+                || (self.showing == WorkVisibility::RecycleBin && work.hidden())
+                || self.showing == WorkVisibility::All)
+            // Only show works that match the current tag selection.
+            && self.tag_selection.matches(work)
+            // Filter our any works with tags that have been hidden.
+            && !tags.is_some_and(|tags| {
+                work.tags()
+                    .any(|tag_id| tags.get(&tag_id).is_some_and(|tag| tag.hidden()))
+            })
+            // Only show the kinds of media that were picked, if any were.
+            && (self.media_types.is_empty()
+                || work.media_type().is_some_and(|kind| self.media_types.contains(&kind)))
+            // Filter out any works that the user's content filters say to hide.
+            && !filters.hides(work)
+    }
+
+    fn compare_works(
+        (order, grouping): (WorkOrder, WorkGrouping),
+        tags: Option<&HashMap<TagId, DbTag>>,
+        a: &DbWork,
+        b: &DbWork,
+    ) -> Ordering {
+        let ord = match order.column() {
+            WorkSortCol::Date => a.date().cmp(b.date()),
+            WorkSortCol::Title => a.name().cmp(b.name()),
+            WorkSortCol::Artist => a.artist_sort_key().cmp(&b.artist_sort_key()),
+            WorkSortCol::DateAdded => a.created_at().cmp(&b.created_at()),
+            WorkSortCol::Random => {
+                shuffle_key(order.seed, a.id()).cmp(&shuffle_key(order.seed, b.id()))
+            }
+            WorkSortCol::FileSize => a.file_size().cmp(&b.file_size()),
+        }
+        .then_with(|| a.id().cmp(&b.id()));
+        let ord = match order.order {
+            OrderDir::Desc if !order.shuffle => ord.reverse(),
+            _ => ord,
+        };
+        // Works without an artist or a size go last, whichever way we are sorting.
+        let unknown = |work: &DbWork| match order.column() {
+            WorkSortCol::Artist => work.artist_sort_key().is_none(),
+            WorkSortCol::FileSize => work.file_size().is_none(),
+            _ => false,
+        };
+        grouping
+            .compare(a, b, tags)
+            .then(unknown(a).cmp(&unknown(b)))
+            .then(ord)
+    }
+
     fn reproject_work(&mut self, tags: Option<&HashMap<TagId, DbTag>>) {
+        self.gallery_layout = None;
         if let Some(works) = self.work_matching_tag.as_ref() {
             let selected = self.get_selected_work().map(|w| w.id());
+            let filters = self.filters.compile(tags);
             self.work_filtered = works
                 .values()
-                // Only show works that we can actually show.
-                .filter(|work| work.screen_path().is_some())
-                // Filter out hidden or favorite works if we're not showing them.
-                .filter(|work| {
-                    (self.showing == WorkVisibility::Normal && !work.hidden())
-                        || (self.showing == WorkVisibility::Favorites && work.favorite())
-                        || (self.showing == WorkVisibility::RecycleBin && work.hidden())
-                        || self.showing == WorkVisibility::All
-                })
-                // Only show works that match the current tag selection.
-                .filter(|work| self.tag_selection.matches(work))
-                // Filter our any works with tags that have been hidden.
-                .filter(|work| {
-                    if let Some(tags) = tags {
-                        for tag_id in work.tags() {
-                            if let Some(tag) = tags.get(&tag_id)
-                                && tag.hidden()
-                            {
-                                return false;
-                            }
-                        }
-                    }
-                    true
-                })
-                .sorted_by(|a, b| {
-                    let ord = match self.order.column {
-                        WorkSortCol::Date => match a.date().cmp(b.date()) {
-                            Ordering::Equal => a.id().cmp(&b.id()),
-                            v => v,
-                        },
-                    };
-                    match self.order.order {
-                        OrderDir::Asc => ord,
-                        OrderDir::Desc => ord.reverse(),
-                    }
-                })
+                .filter(|work| self.shows_work(work, tags, &filters))
+                .sorted_by(|a, b| Self::compare_works((self.order, self.grouping), tags, a, b))
                 .map(|work| work.id())
                 .collect();
+            self.work_blurred = self
+                .work_filtered
+                .iter()
+                .filter(|id| filters.blurs(&works[*id]))
+                .copied()
+                .collect();
             info!(
                 "Showing {} of {} matching works",
                 self.work_filtered.len(),
@@ -477,9 +1013,48 @@ impl UxWork {
                 selected.and_then(|id| self.work_filtered.iter().position(|i| *i == id));
         } else {
             self.work_filtered = Vec::new();
+            self.work_blurred.clear();
         }
     }
 
+    // Re-filter only the given works and merge them into the gallery, rather than rebuilding it.
+    // `changed` are works that may already be shown; new works need only be in `added`.
+    fn reproject_changed_works(
+        &mut self,
+        changed: &HashSet<WorkId>,
+        added: &[WorkId],
+        tags: Option<&HashMap<TagId, DbTag>>,
+    ) {
+        let Some(works) = self.work_matching_tag.as_ref() else {
+            return;
+        };
+        self.gallery_layout = None;
+        let selected = self.get_selected_work().map(|w| w.id());
+        let filters = self.filters.compile(tags);
+        let mut fresh = Vec::new();
+        for id in changed.iter().chain(added) {
+            self.work_blurred.remove(id);
+            if let Some(work) = works.get(id)
+                && self.shows_work(work, tags, &filters)
+            {
+                if filters.blurs(work) {
+                    self.work_blurred.insert(*id);
+                }
+                fresh.push(*id);
+            }
+        }
+        let order = (self.order, self.grouping);
+        apply_changes(&mut self.work_filtered, changed, fresh, |a, b| {
+            Self::compare_works(order, tags, &works[a], &works[b])
+        });
+        trace!(
+            "Showing {} of {} matching works",
+            self.work_filtered.len(),
+            works.len()
+        );
+        self.selected = selected.and_then(|id| self.work_filtered.iter().position(|i| *i == id));
+    }
+
     fn get_pressed_keys(ui: &egui::Ui, keys: &[Key]) -> HashSet<Key> {
         Self::get_pressed_keys_with_mods(ui, Modifiers::NONE, keys)
     }
@@ -519,6 +1094,7 @@ impl UxWork {
                 Key::F6,
                 Key::F7,
                 Key::Delete,
+                Key::R,
             ],
         );
 
@@ -531,13 +1107,33 @@ impl UxWork {
         let pressed_right = pressed.contains(&Key::ArrowRight)
             || pressed.contains(&Key::N)
             || pressed.contains(&Key::D);
+        // Surprise me: jump anywhere in the current selection.
+        if pressed.contains(&Key::R) && !self.work_filtered.is_empty() {
+            self.set_selected(rand::random_range(0..self.work_filtered.len()));
+            self.scroll_to_selected = ScrollRequestKind::Movement;
+        }
         if let Some(selected) = self.selected {
             if pressed_left {
-                self.set_selected(selected.wrapping_sub(1).min(self.work_filtered.len() - 1));
+                // Note: step back over the whole of the prior spread, if there is one.
+                let step = if selected >= 2 && self.shown_spread(selected - 2).is_some() {
+                    2
+                } else {
+                    1
+                };
+                self.set_selected(
+                    selected
+                        .wrapping_sub(step)
+                        .min(self.work_filtered.len() - 1),
+                );
                 self.scroll_to_selected = ScrollRequestKind::Movement;
             }
             if pressed_right {
-                self.set_selected(selected.saturating_add(1) % self.work_filtered.len());
+                let step = if self.shown_spread(selected).is_some() {
+                    2
+                } else {
+                    1
+                };
+                self.set_selected(selected.saturating_add(step) % self.work_filtered.len());
                 self.scroll_to_selected = ScrollRequestKind::Movement;
             }
             if pressed_up {
@@ -574,6 +1170,38 @@ impl UxWork {
                 }
             }
         }
+        // Note: the platform copy shortcut arrives as a copy event, rather than as a key.
+        let copy = ui.input(|input| input.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        // Note: words selected on a document page take the copy instead.
+        if copy
+            && !self.document.has_selection()
+            && ui.memory(|mem| mem.focused().is_none())
+            && let Some(work) = self.get_selected_work()
+        {
+            self.copy_work_image(work, ui.ctx());
+        }
+    }
+
+    // Puts the work's best local image on the clipboard, for pasting into other programs.
+    fn copy_work_image(&self, work: &DbWork, ctx: &egui::Context) {
+        let Some(path) = work
+            .screen_path()
+            .or(work.preview_path())
+            .filter(|path| is_image(path) && self.storage.is_available(path))
+        else {
+            info!("No local image to copy for {}", work.name());
+            return;
+        };
+        match open_image(&self.storage.resolve(path)) {
+            Ok(image) => {
+                let image = image.to_rgba8();
+                ctx.copy_image(egui::ColorImage::from_rgba_unmultiplied(
+                    [image.width() as usize, image.height() as usize],
+                    image.as_raw(),
+                ));
+            }
+            Err(e) => error!("Failed to copy {}: {e}", work.name()),
+        }
     }
 
     fn check_slideshow_key_binds(&mut self, ui: &egui::Ui) {
@@ -586,6 +1214,15 @@ impl UxWork {
                 Key::Num0,
                 Key::Comma,
                 Key::Period,
+                Key::I,
+                Key::L,
+                Key::O,
+                Key::M,
+                Key::B,
+                Key::C,
+                Key::T,
+                Key::U,
+                Key::Num3,
             ],
         );
         let ctrl_pressed = Self::get_pressed_keys_with_mods(
@@ -602,6 +1239,33 @@ impl UxWork {
         if pressed.contains(&Key::Num0) {
             self.slide_xform.reset();
         }
+        if pressed.contains(&Key::I) {
+            self.image_info.toggle_overlay();
+        }
+        if pressed.contains(&Key::L) {
+            self.image_info.toggle_loupe();
+        }
+        if pressed.contains(&Key::O) {
+            self.annotations.toggle_overlay();
+        }
+        if pressed.contains(&Key::M) {
+            self.annotations.toggle_marking();
+        }
+        if pressed.contains(&Key::B) {
+            self.slideshow.toggle_spread();
+        }
+        if pressed.contains(&Key::C) {
+            self.reference.toggle();
+        }
+        if pressed.contains(&Key::T) {
+            self.transcript.toggle_panel();
+        }
+        if pressed.contains(&Key::U) {
+            self.transcript.toggle_subtitles();
+        }
+        if pressed.contains(&Key::Num3) {
+            self.show_models = !self.show_models;
+        }
         if pressed.contains(&Key::Comma) {
             self.mpv.seek_frame_backward_async().ok();
         }
@@ -615,8 +1279,12 @@ impl UxWork {
             self.mpv.seek_forward_async(5.0).ok();
         }
 
+        if self.in_document_view || self.in_model_view {
+            return;
+        }
         ui.ctx().input_mut(|input| {
             if input.pointer.button_down(PointerButton::Primary)
+                && !self.annotations.is_marking()
                 && let Some(motion) = input.pointer.motion()
             {
                 self.slide_xform.pan(motion);
@@ -651,6 +1319,7 @@ impl UxWork {
         &mut self,
         tags: Option<&HashMap<TagId, DbTag>>,
         mut tutorial: Tutorial<'_>,
+        db_read: &DbReadHandle,
         db_write: &DbWriteHandle,
         host: &mut PluginHost,
         ui: &mut egui::Ui,
@@ -674,7 +1343,7 @@ impl UxWork {
                 ui.label("");
                 ui.label("Importantly, the list of tags on the work allows you to add and remove tags, just like the tags bar. This is a fast way to narrow in on a style or subject matter.");
                 ui.label("");
-                ui.label("To continue, press the Spacebar key to enter the slideshow.");
+                ui.label("To continue, press F11 to show the work in the slideshow. (The Spacebar takes a quick look without leaving the gallery.)");
                 tutorial.button_area(NextButton::Skip, ui);
             });
         }
@@ -689,6 +1358,35 @@ impl UxWork {
 
             ui.small(format!("({offset} of {})", self.work_filtered.len()));
         });
+        ui.horizontal(|ui| {
+            for stars in 1..=5 {
+                let icon = if stars <= work.rating() { "★" } else { "☆" };
+                if ui.small_button(icon).clicked() {
+                    // Clicking the current rating again clears it.
+                    let rating = if stars == work.rating() { 0 } else { stars };
+                    db_write
+                        .set_work_rating(work.id(), rating)
+                        .expect("set rating");
+                }
+            }
+        });
+        let conversion = work
+            .screen_path()
+            .map(|path| format!("file://{}", self.storage.resolve(path).display()))
+            .and_then(|uri| self.color.conversion(&uri))
+            .unwrap_or_default();
+        if conversion.profile {
+            ui.small(format!(
+                "🎨 Colors converted from the file's ICC profile to {}",
+                self.color.target_name()
+            ));
+        }
+        if conversion.tone_mapped {
+            ui.small("🌅 Tone mapped from more than 8 bits per channel");
+        }
+        if conversion.raw_preview {
+            ui.small("📷 Showing the preview the camera embedded in the RAW file");
+        }
         ui.add_space(SPACING / 2.);
 
         if let Some(location) = work.location() {
@@ -845,9 +1543,59 @@ impl UxWork {
                 .filter_map(|tag_id| tags.get(&tag_id))
                 .sorted_by_key(|tag| tag.name())
                 .for_each(|tag| {
-                    self.tag_selection
-                        .tag_row_ui(tag, host, db_write, ui, &mut tutorial);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button("🗑")
+                            .on_hover_text("remove from this work; Edit > Sync Tag Edits Upstream can remove it at the source too")
+                            .clicked()
+                            && let Err(e) = db_write.remove_work_tag(work.screen_url(), tag.name())
+                        {
+                            error!("Failed to remove {} from the work: {e}", tag.name());
+                        }
+                        self.tag_selection
+                            .tag_row_ui(tag, host, db_write, ui, &mut tutorial);
+                    });
+                });
+        }
+
+        if let Some(remote_id) = work.remote_id()
+            && let Some(plugin) = Self::downloads_by_plugin(tags, once(work))
+                .into_keys()
+                .next()
+            && let Some(handle) = host.plugins_mut().find(|handle| handle.name() == plugin)
+        {
+            let actions = handle
+                .metadata()
+                .map(|metadata| {
+                    metadata
+                        .actions()
+                        .iter()
+                        .filter(|action| action.scope() == ActionScope::Work)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            if !actions.is_empty() {
+                ui.add_space(SPACING);
+                ui.heading("Actions");
+                ui.separator();
+                ui.horizontal_wrapped(|ui| {
+                    for action in &actions {
+                        let description = if action.description().is_empty() {
+                            plugin.as_str()
+                        } else {
+                            action.description()
+                        };
+                        if ui
+                            .button(action.label())
+                            .on_hover_text(description)
+                            .clicked()
+                        {
+                            handle.run_action(action, Some(remote_id));
+                        }
+                    }
                 });
+            }
         }
 
         ui.add_space(SPACING);
@@ -864,6 +1612,17 @@ impl UxWork {
                 ui.add(egui::Label::new(work.screen_url()).truncate());
                 ui.end_row();
 
+                let link = work_link(work);
+                if ui
+                    .button("Link 📋")
+                    .on_hover_text("Copy a link that opens this work here, e.g. from your notes")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(link.clone());
+                }
+                ui.add(egui::Label::new(link).truncate());
+                ui.end_row();
+
                 if let Some(url) = work.archive_url() {
                     ui.label("Archive");
                     ui.add(egui::Label::new(url).truncate());
@@ -871,40 +1630,290 @@ impl UxWork {
                 }
 
                 if let Some(path) = work.screen_path() {
-                    let path = self.data_dir.join(path);
+                    let path = self.storage.resolve(path);
                     if ui.button("Path 📋").clicked() {
                         ui.ctx().copy_text(path.display().to_string());
                     }
                     ui.add(egui::Label::new(path.display().to_string()).truncate());
                     ui.end_row();
+
+                    if ui
+                        .button("Image 📋")
+                        .on_hover_text("Copy the image itself, to paste into other programs")
+                        .clicked()
+                    {
+                        self.copy_work_image(work, ui.ctx());
+                    }
+                    ui.end_row();
                 }
             });
-    }
-
-    pub fn gallery_ui(
-        &mut self,
-        tags: Option<&HashMap<TagId, DbTag>>,
-        mut tutorial: Tutorial<'_>,
-        db_write: &DbWriteHandle,
-        perf: &mut PerfTrack,
-        ui: &mut egui::Ui,
-    ) {
-        self.mpv.monitor_events();
 
-        if tutorial.step() == TutorialStep::WorksIntro {
+        ui.add_space(SPACING);
+        if let Some(work) = works.get(work_id) {
+            let screen_url = work.screen_url().to_owned();
+            egui::CollapsingHeader::new("Notes")
+                .id_salt("work_info_notes")
+                .default_open(true)
+                .show(ui, |ui| {
+                    self.work_note
+                        .ui(*work_id, &screen_url, db_read, db_write, ui)
+                });
+            egui::CollapsingHeader::new("Annotations")
+                .id_salt("work_info_annotations")
+                .show(ui, |ui| {
+                    self.annotations.info_ui(&screen_url, db_read, db_write, ui)
+                });
+            egui::CollapsingHeader::new("Display")
+                .id_salt("work_info_display")
+                .show(ui, |ui| self.display.ui(&screen_url, db_write, ui));
+        }
+
+        ui.add_space(SPACING);
+        let id = *work_id;
+        egui::CollapsingHeader::new("History")
+            .id_salt("work_info_history")
+            .show(ui, |ui| match &self.work_history {
+                WorkDetail::Loaded(loaded, revisions) if *loaded == id => {
+                    if revisions.is_empty() {
+                        ui.label("No changes have been seen since this work was first fetched.");
+                    }
+                    for (i, revision) in revisions.iter().enumerate() {
+                        ui.strong(
+                            revision
+                                .changed_at
+                                .to_zoned(TimeZone::system())
+                                .strftime("%Y-%m-%d %H:%M")
+                                .to_string(),
+                        );
+                        egui::Grid::new(format!("work_info_history_{i}"))
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for change in &revision.changes {
+                                    ui.label(&change.field);
+                                    ui.vertical(|ui| {
+                                        ui.add(
+                                            egui::Label::new(
+                                                egui::RichText::new(
+                                                    change.old.as_deref().unwrap_or("(none)"),
+                                                )
+                                                .strikethrough()
+                                                .weak(),
+                                            )
+                                            .wrap(),
+                                        );
+                                        ui.add(
+                                            egui::Label::new(
+                                                change.new.as_deref().unwrap_or("(none)"),
+                                            )
+                                            .wrap(),
+                                        );
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    }
+                }
+                WorkDetail::Loading(loading) if *loading == id => {
+                    ui.spinner();
+                }
+                _ => {
+                    db_read.get_work_history(id);
+                    self.work_history = WorkDetail::Loading(id);
+                }
+            });
+        let mut jump_to = None;
+        egui::CollapsingHeader::new("Linked Data")
+            .id_salt("work_info_identity")
+            .show(ui, |ui| match &self.work_identity {
+                WorkDetail::Loaded(loaded, identity) if *loaded == id => {
+                    if identity.external_ids.is_empty() {
+                        ui.label("The plugin did not say what other catalogs call this work.");
+                    }
+                    egui::Grid::new("work_info_external_ids")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (authority, value) in &identity.external_ids {
+                                ui.label(authority);
+                                match Authority::try_from(authority.as_str())
+                                    .ok()
+                                    .and_then(|authority| authority.url_for(value))
+                                {
+                                    Some(url) => ui.hyperlink_to(value, url),
+                                    None => ui.label(value),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                    if !identity.same_object.is_empty() {
+                        ui.label("Also seen as:");
+                    }
+                    for linked in &identity.same_object {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .link(&linked.name)
+                                .on_hover_text("Select this work, if the gallery is showing it")
+                                .clicked()
+                            {
+                                jump_to = Some(linked.screen_url.clone());
+                            }
+                            ui.weak(format!("from {}", linked.plugin));
+                        });
+                    }
+                    for artist in &identity.artists {
+                        let years = match (artist.birthday, artist.deathday) {
+                            (Some(born), Some(died)) => {
+                                format!(" ({}–{})", born.year(), died.year())
+                            }
+                            (Some(born), None) => format!(" (born {})", born.year()),
+                            _ => String::new(),
+                        };
+                        let row = ui.horizontal(|ui| {
+                            ui.label("Artist:");
+                            ui.strong(format!("{}{years}", artist.name));
+                            if let Some(nationality) = &artist.nationality {
+                                ui.label(nationality);
+                            }
+                        });
+                        if let Some(bio) = &artist.bio {
+                            row.response.on_hover_text(bio);
+                        }
+                    }
+                    for enrichment in identity
+                        .enrichments
+                        .iter()
+                        .filter(|e| e.statement != Statement::Creator)
+                    {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}:", enrichment.statement.name()));
+                            match Authority::Wikidata.url_for(&enrichment.value) {
+                                Some(url) => ui.hyperlink_to(&enrichment.label, url),
+                                None => ui.label(&enrichment.label),
+                            };
+                        });
+                    }
+                }
+                WorkDetail::Loading(loading) if *loading == id => {
+                    ui.spinner();
+                }
+                _ => {
+                    db_read.get_work_identity(id);
+                    self.work_identity = WorkDetail::Loading(id);
+                }
+            });
+        if let Some(screen_url) = jump_to {
+            self.select_screen_url(&screen_url);
+        }
+        egui::CollapsingHeader::new("Variants")
+            .id_salt("work_info_variants")
+            .show(ui, |ui| match &self.work_variants {
+                WorkDetail::Loaded(loaded, variants) if *loaded == id => {
+                    if variants.is_empty() {
+                        ui.label("Edits that you save next to the work's file after opening it from the gallery's context menu, e.g. as work_edited.png, show up here.");
+                    }
+                    ui.horizontal_wrapped(|ui| {
+                        for stored in variants {
+                            let path = self.storage.resolve(Path::new(stored));
+                            let resp = ui
+                                .add(
+                                    egui::Image::new(format!("file://{}", path.display()))
+                                        .max_size(Vec2::splat(96.))
+                                        .sense(Sense::click()),
+                                )
+                                .on_hover_text(stored);
+                            if resp.clicked()
+                                && let Err(e) = open_in_default_viewer(&path)
+                            {
+                                error!("Failed to open {}: {e}", path.display());
+                            }
+                        }
+                    });
+                }
+                WorkDetail::Loading(loading) if *loading == id => {
+                    ui.spinner();
+                }
+                _ => {
+                    db_read.get_work_variants(id);
+                    self.work_variants = WorkDetail::Loading(id);
+                }
+            });
+        egui::CollapsingHeader::new("Source")
+            .id_salt("work_info_source")
+            .show(ui, |ui| match &self.work_source {
+                WorkDetail::Loaded(loaded, Some(source)) if *loaded == id => {
+                    if ui.button("Copy 📋").clicked() {
+                        ui.ctx().copy_text(source.clone());
+                    }
+                    egui::ScrollArea::both().max_height(400.).show(ui, |ui| {
+                        ui.add(
+                            egui::Label::new(egui::RichText::new(source).monospace())
+                                .selectable(true),
+                        );
+                    });
+                }
+                WorkDetail::Loaded(loaded, None) if *loaded == id => {
+                    ui.label("The raw record for this work was not kept. Turn on keeping raw records in the Storage preferences, then refresh the work's tag.");
+                }
+                WorkDetail::Loading(loading) if *loading == id => {
+                    ui.spinner();
+                }
+                _ => {
+                    db_read.get_work_source(id);
+                    self.work_source = WorkDetail::Loading(id);
+                }
+            });
+    }
+
+    pub fn gallery_ui(
+        &mut self,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        mut tutorial: Tutorial<'_>,
+        db_write: &DbWriteHandle,
+        host: &mut PluginHost,
+        perf: &mut PerfTrack,
+        ui: &mut egui::Ui,
+    ) {
+        self.mpv.monitor_events();
+
+        if tutorial.step() == TutorialStep::WorksIntro {
             tutorial.frame(ui, |ui, tutorial| {
                 ui.heading("Works Gallery");
                 ui.separator();
                 ui.label("This is the works gallery. It shows works matching the selected tags.");
                 ui.label("");
-                ui.label("From here you can select works by clicking on them, using the arrow keys, view a work in fullscreen (Spacebar), delete works (Delete), or Favorite and Unfavorite works (F6 and F7).");
+                ui.label("From here you can select works by clicking on them, using the arrow keys, take a quick look at a work (Spacebar), view a work in fullscreen (F11), delete works (Delete), Favorite and Unfavorite works (F6 and F7), jump to a random work (R), or copy a work's image to paste elsewhere (Ctrl+C). Drop image files on the window to import them.");
                 ui.label("");
                 ui.label("Once works show up (it may take time to download them), click on one to select it.");
                 tutorial.button_area(NextButton::Skip, ui);
             });
         }
 
+        let (back, forward) = ui.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::ALT, Key::ArrowLeft)
+                    || input.pointer.button_pressed(PointerButton::Extra1),
+                input.consume_key(Modifiers::ALT, Key::ArrowRight)
+                    || input.pointer.button_pressed(PointerButton::Extra2),
+            )
+        });
         ui.horizontal_wrapped(|ui| {
+            if ui
+                .add_enabled(self.can_go_back(), egui::Button::new("⏴"))
+                .on_hover_text("Back (Alt+Left)")
+                .clicked()
+                || back
+            {
+                self.go_back();
+            }
+            if ui
+                .add_enabled(self.can_go_forward(), egui::Button::new("⏵"))
+                .on_hover_text("Forward (Alt+Right)")
+                .clicked()
+                || forward
+            {
+                self.go_forward();
+            }
             if let Some(tags) = tags {
                 self.tag_selection.location_ui(tags, ui);
             }
@@ -913,11 +1922,36 @@ impl UxWork {
             }
             ui.label(format!("({})", self.work_filtered.len()));
         });
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(self.media_types.is_empty(), "All Media")
+                .clicked()
+                && !self.media_types.is_empty()
+            {
+                self.media_types.clear();
+                self.reproject_work(tags);
+            }
+            for kind in MediaType::ALL {
+                let picked = self.media_types.contains(&kind);
+                if ui.selectable_label(picked, kind.to_string()).clicked() {
+                    if picked {
+                        self.media_types.remove(&kind);
+                    } else {
+                        self.media_types.insert(kind);
+                    }
+                    self.reproject_work(tags);
+                }
+            }
+        });
         ui.horizontal(|ui| {
             ui.label("Sort");
             if self.order.ui(ui) {
                 self.reproject_work(tags);
             }
+            ui.label("Group");
+            if self.grouping.ui(ui) {
+                self.reproject_work(tags);
+            }
 
             ui.separator();
 
@@ -925,6 +1959,13 @@ impl UxWork {
             if self.showing.ui(ui) {
                 self.reproject_work(tags);
             }
+            if ui
+                .checkbox(&mut self.show_undownloaded, "Not Downloaded")
+                .on_hover_text("Also show works that were recorded without downloading them")
+                .changed()
+            {
+                self.reproject_work(tags);
+            }
 
             ui.separator();
 
@@ -937,7 +1978,137 @@ impl UxWork {
                     .show_value(true)
                     .suffix("px"),
             );
+            // Note: the layout notices the new mode itself, next frame.
+            self.gallery_mode.ui(ui);
+
+            ui.separator();
+
+            if ui
+                .button("Export...")
+                .on_hover_text(
+                    "Copy the works in the gallery to a folder, optionally converting them",
+                )
+                .clicked()
+            {
+                self.export.open();
+            }
+            if ui
+                .button("Contact Sheet...")
+                .on_hover_text("Print the works in the gallery to a PDF")
+                .clicked()
+            {
+                self.contact_sheet.open();
+            }
+            if ui
+                .button("Wallpaper...")
+                .on_hover_text("Rotate the desktop wallpaper through works")
+                .clicked()
+            {
+                self.wallpaper.open();
+            }
+
+            ui.separator();
+
+            ui.menu_button("Download Now", |ui| {
+                let selected = self
+                    .get_selected_work()
+                    .filter(|work| work.screen_path().is_none());
+                if ui
+                    .add_enabled(selected.is_some(), egui::Button::new("Selected Work"))
+                    .clicked()
+                {
+                    host.download_works(
+                        Self::downloads_by_plugin(tags, selected.into_iter()),
+                        false,
+                    );
+                    ui.close();
+                }
+                let original = self
+                    .get_selected_work()
+                    .filter(|work| work.archive_url().is_some() && work.archive_path().is_none());
+                if ui
+                    .add_enabled(
+                        original.is_some(),
+                        egui::Button::new("Selected Work with its Original"),
+                    )
+                    .on_hover_text("Originals can be very large; this may take a while")
+                    .clicked()
+                {
+                    host.download_works(
+                        Self::downloads_by_plugin(tags, original.into_iter()),
+                        true,
+                    );
+                    ui.close();
+                }
+                let missing = self.undownloaded_works().count();
+                if ui
+                    .add_enabled(
+                        missing > 0,
+                        egui::Button::new(format!("All {missing} Not Downloaded in the Gallery")),
+                    )
+                    .clicked()
+                {
+                    host.download_works(
+                        Self::downloads_by_plugin(tags, self.undownloaded_works()),
+                        false,
+                    );
+                    ui.close();
+                }
+            });
         });
+        self.export.window(
+            self.work_filtered.len(),
+            || {
+                let Some(works) = self.work_matching_tag.as_ref() else {
+                    return Vec::new();
+                };
+                self.work_filtered
+                    .iter()
+                    .filter_map(|id| works.get(id)?.screen_path())
+                    .filter(|path| self.storage.is_available(path))
+                    .map(|path| self.storage.resolve(path))
+                    .collect()
+            },
+            ui.ctx(),
+        );
+        self.contact_sheet.window(
+            self.work_filtered.len(),
+            || {
+                let Some(works) = self.work_matching_tag.as_ref() else {
+                    return Vec::new();
+                };
+                let local = |path: Option<&Path>| {
+                    path.filter(|path| is_image(path) && self.storage.is_available(path))
+                        .map(|path| self.storage.resolve(path))
+                };
+                self.work_filtered
+                    .iter()
+                    .filter_map(|id| works.get(id))
+                    .map(|work| SheetEntry {
+                        image: local(work.screen_path()).or_else(|| local(work.preview_path())),
+                        title: work.name().to_owned(),
+                        date: work.date().to_string(),
+                    })
+                    .collect()
+            },
+            ui.ctx(),
+        );
+        self.wallpaper.window(
+            self.work_filtered.len(),
+            || {
+                let Some(works) = self.work_matching_tag.as_ref() else {
+                    return Vec::new();
+                };
+                self.work_filtered
+                    .iter()
+                    .filter_map(|id| works.get(id)?.screen_path())
+                    .filter(|path| is_image(path))
+                    .map(Path::to_owned)
+                    .collect()
+            },
+            &self.storage,
+            ui.ctx(),
+        );
         if self.work_matching_tag.is_none() {
             ui.spinner();
             return;
@@ -949,185 +2120,426 @@ impl UxWork {
 
         self.check_common_key_binds(tags, db_write, n_wide, ui);
 
-        // Note: if we deleted by keypress, the number of rows may have changed.
-        let n_rows = self.work_filtered.len().div_ceil(n_wide);
+        // Note: if we deleted by keypress, the number of works may have changed.
+        if self.gallery_layout.as_ref().is_none_or(|layout| {
+            !layout.matches(self.gallery_mode, width, size, self.work_filtered.len())
+        }) {
+            self.gallery_layout = Some(self.layout_gallery(tags, width, size));
+        }
+        let layout = self.gallery_layout.take().unwrap_or_default();
+        // The prefetch plan works in bands of one thumbnail height, whatever the mode.
+        let n_bands = (layout.height() / size).ceil() as usize;
 
-        egui::ScrollArea::vertical()
+        // Note: ahead of the thumbnails, so that the per-frame upload limit can't hold it up.
+        if self.peeking
+            && let Some(selected) = self.selected
+        {
+            let screen = ui.ctx().screen_rect().size();
+            self.ensure_work_cached(ui.ctx(), selected, screen, true);
+        }
+
+        let restore_scroll = if !self.is_loading_works && !self.tag_selection.is_changed() {
+            self.restore_scroll.take()
+        } else {
+            None
+        };
+        if restore_scroll.is_some() {
+            // Note: put the gallery back as it was, rather than centering the restored selection.
+            self.scroll_to_selected = ScrollRequestKind::None;
+        }
+        let scroll = egui::ScrollArea::vertical()
             .auto_shrink([false, false])
-            .show_rows(ui, size, n_rows, |ui, rows| {
-                // We may have advanced past the area covered by `rows`, so we might not
-                // do a draw call on the selected item, which means that we wouldn't ever
-                // call a scroll-to on the response, so the scroll would just not happen.
-                // Instead, we construct a virtual rect in the scroll area to scroll to.
-                //
-                // This is tricky. The viewport max-rect (in this case cursor, since we're
-                // doing this first) is the offset of the current viewport relative
-                // to rows. So if we're at the top, it will be the offset from the top of
-                // the window to the top of the scroll area. As we scroll down, this lowers
-                // to zero when the top of the first drawn item is at the top of the window
-                // (hidden behind the menubar and whatnot). As we scroll further, this goes
-                // negative as the logical top of the first row in the viewport goes above
-                // the top of the window. Once the row is fully out of view, the cursor
-                // resets to the next row, with the offset returning to the distance from
-                // the top of window, to the top of the scroll area.
-                //
-                // So it seems like we need to have the rect relative to the top of the
-                // `window`.
-                //
-                //  - - - - - -  top of scroll
-                //  |-|-|-|-|-|
-                //  -----------  top of window (0)
-                //  |  menus  |
-                //  -----------  cursor.top()
-                //  -----------  top of viewport
-                //  |-|-|-|-|-|
-                //  |-|-|||-|-|  selected
-                //  |-|-|-|-|-|
-                //  -----------  bottom of window
+            .show_viewport(ui, |ui, viewport| {
+                let origin = ui.max_rect().min.to_vec2();
+                ui.set_height(layout.height());
+
+                // The selected work may be well outside of the viewport, but since the layout
+                // knows where every work goes, we can scroll straight to its cell.
                 if let Some(selected) = self.selected
-                    && self.scroll_to_selected != ScrollRequestKind::None
-                    && !(rows.start..rows.end.saturating_sub(1)).contains(&(selected / n_wide))
+                    && let Some(cell) = layout.cell(selected)
                 {
-                    let selected_row = selected / n_wide;
-                    let scroll_to_selected = selected_row as f32 * size;
-                    let scroll_to_cursor = rows.start as f32 * size;
-                    let cursor_to_selected = scroll_to_selected - scroll_to_cursor;
-                    let selected_to_window = ui.cursor().top() + cursor_to_selected;
-                    let y = selected_to_window;
-                    let rect = Rect::from_x_y_ranges(0f32..=10f32, y..=y + size);
                     match self.scroll_to_selected {
                         ScrollRequestKind::LeaveSlideshow => {
-                            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                            ui.scroll_to_rect(cell.translate(origin), Some(egui::Align::Center));
                         }
                         ScrollRequestKind::Movement => {
-                            ui.scroll_to_rect(rect, None);
+                            ui.scroll_to_rect(cell.translate(origin), None);
                         }
                         ScrollRequestKind::None => {}
                     }
                     self.scroll_to_selected = ScrollRequestKind::None;
                 }
+                if let Some(offset) = restore_scroll {
+                    let top = Rect::from_min_size(egui::pos2(0., offset), viewport.size());
+                    ui.scroll_to_rect(top.translate(origin), Some(egui::Align::TOP));
+                }
 
-                // Overfetch by 1x our current visible area in both directions so we can
-                // usually scroll in either direction without pause or loading spinners.
+                // Overfetch around the visible area so we can usually scroll without pause
+                // or loading spinners. The window reaches further in the direction we are
+                // scrolling, the faster we go; see ScrollPrefetch.
                 //
-                //  All works (ideal case shown; the actual slice may go before or after)
-                //  |--------<  [  ]  >--|
-                //              [  ] <- visible slice
-                //           |        | <- query slice
-                //           |--[  ]--| <- works slice
+                //  All works (scrolling down; the actual slice may go before or after)
+                //  |-----<  [  ]      >--|
+                //           [  ] <- visible slice
+                //        |            | <- query slice
                 //
-                let visible_start = rows.start * n_wide;
-                let visible_end = (rows.end * n_wide).min(self.work_filtered.len());
-                let visible_slice = visible_start..visible_end;
-                let win = visible_slice.len().max(10);
-                let query_start = visible_slice.start.saturating_sub(win);
-                let query_end = visible_slice
-                    .end
-                    .saturating_add(win)
-                    .min(self.work_filtered.len());
-                let query_slice = query_start..query_end;
-
-                // Pre-scan the works slice to ask to pre-load all the images that
-                // are in our query window (Note: this extends outside the visible area
-                // to make scrolling faster).
+                // Note: the plan puts the visible works first and then the bands about to
+                //       come into view, so the per-frame upload limit never starves the
+                //       tiles we are actually looking at.
                 let cache_start = Instant::now();
-                for work_offset in query_slice {
-                    self.ensure_work_cached(ui.ctx(), work_offset, ui.available_size());
+                let visible_bands = (viewport.top() / size).floor().max(0.) as usize
+                    ..(viewport.bottom() / size).ceil() as usize;
+                let mut planned = HashSet::new();
+                for band in self.scroll_prefetch.plan(visible_bands, n_bands) {
+                    let span = Rangef::new(band as f32 * size, (band + 1) as f32 * size);
+                    for work_offset in layout.works_in(span) {
+                        if planned.insert(work_offset) {
+                            self.ensure_work_cached(
+                                ui.ctx(),
+                                work_offset,
+                                ui.available_size(),
+                                false,
+                            );
+                        }
+                    }
                 }
-                self.flush_works_lru(ui.ctx());
+                self.flush_image_cache(ui.ctx());
                 perf.sample("Cache Images", cache_start.elapsed());
 
                 let sel_color = ui.style().visuals.selection.bg_fill;
-                ui.style_mut().spacing.item_spacing = Vec2::ZERO;
 
                 let draw_start = Instant::now();
-                for row_work_offsets in &visible_slice.chunks(n_wide) {
-                    ui.horizontal(|ui| {
-                        for work_offset in row_work_offsets {
-                            // Selection uses the selection color for the background
-                            let is_selected = self.selected == Some(work_offset);
-
-                            // Borrow work off self
-                            let work = &self
-                                .work_matching_tag
-                                .as_ref()
-                                .expect("no work after check")[&self.work_filtered[work_offset]];
-
-                            // Image is a thin wrapper around a TextureSource, which is a Cow to
-                            // the URI. This doesn't actually borrow anything off work because we
-                            // format! to create the URI off of the path in the DbWork.
-                            let img = self
-                                .get_preview_image(self.preview_uri(work))
-                                .alt_text(work.name())
-                                .show_loading_spinner(true)
-                                .maintain_aspect_ratio(true);
-
-                            let mut pad = 0.;
-                            let mut inner_margin = Margin::ZERO;
-                            if let Some(loaded_size) =
-                                img.load_and_calc_size(ui, Vec2::new(size, size))
-                            {
-                                // Wide things are already centered for some reason,
-                                // so we only need to care about tall images
-                                if loaded_size.y > loaded_size.x {
-                                    pad = (size - loaded_size.x) / 2.;
-                                    inner_margin.left = pad as i8;
-                                }
-                            }
+                for work_offset in layout.works_in(viewport.y_range()) {
+                    let Some(cell) = layout.cell(work_offset) else {
+                        continue;
+                    };
+                    if !cell.intersects(viewport) {
+                        continue;
+                    }
+                    let cell = cell.translate(origin);
+                    let resp = ui.allocate_rect(cell, Sense::click());
 
-                            let btn = egui::ImageButton::new(img)
-                                .frame(false)
-                                .selected(is_selected)
-                                .sense(Sense::click());
+                    // Selection uses the selection color for the background
+                    let is_selected = self.selected == Some(work_offset);
+                    if is_selected {
+                        ui.painter().rect_filled(cell, 0., sel_color);
+                    }
 
-                            let mut frm = egui::Frame::default()
-                                .outer_margin(Margin::ZERO)
-                                .inner_margin(inner_margin);
-                            if is_selected {
-                                frm = frm.fill(sel_color);
-                            }
+                    // Borrow work off self
+                    let work = &self
+                        .work_matching_tag
+                        .as_ref()
+                        .expect("no work after check")[&self.work_filtered[work_offset]];
+
+                    // Image is a thin wrapper around a TextureSource, which is a Cow to
+                    // the URI. This doesn't actually borrow anything off work because we
+                    // format! to create the URI off of the path in the DbWork.
+                    let img = self
+                        .get_preview_image(self.thumb_uri(work))
+                        .alt_text(work.name())
+                        .show_loading_spinner(true)
+                        .maintain_aspect_ratio(true);
+
+                    // Note: blurred works stay blurred until the user clicks on them, at
+                    //       which point they show as normal for the rest of the session.
+                    let work_id = work.id();
+                    if self.work_blurred.contains(&work_id) && !self.filters.is_revealed(work_id) {
+                        let blurred = work
+                            .preview_path()
+                            .filter(|path| self.storage.is_available(path))
+                            .map(|path| self.storage.resolve(path))
+                            .and_then(|path| self.filters.blurred_image(ui.ctx(), &path));
+                        let img = blurred.unwrap_or_else(|| {
+                            egui::Image::new(include_image!("../../assets/loading-preview.png"))
+                        });
+                        let shown = img
+                            .load_and_calc_size(ui, cell.size())
+                            .map_or(cell.size(), |natural| fit(natural, cell.size()));
+                        img.paint_at(ui, Rect::from_center_size(cell.center(), shown));
+                        ui.painter().text(
+                            cell.center(),
+                            egui::Align2::CENTER_CENTER,
+                            "Click to show",
+                            egui::FontId::proportional(12.),
+                            ui.visuals().strong_text_color(),
+                        );
+                        if resp.on_hover_text("Hidden by a content filter").clicked() {
+                            self.filters.reveal(work_id);
+                            self.set_selected(work_offset);
+                        }
+                        continue;
+                    }
 
-                            let rsz = egui::Resize::default()
-                                .min_size(Vec2::new(size - pad, size))
-                                .max_size(Vec2::new(size - pad, size))
-                                .default_size(Vec2::new(size - pad, size))
-                                .resizable([false, false]);
-
-                            frm.show(ui, |ui| {
-                                rsz.show(ui, |ui| {
-                                    let resp = ui.add(btn);
-                                    if resp.clicked() {
-                                        self.set_selected(work_offset);
-                                        if tutorial.step() == TutorialStep::WorksIntro {
-                                            tutorial.next();
-                                        }
-                                    }
-                                });
-                            });
+                    if let Some(transform) = self.display.get(work.screen_url()) {
+                        let preview = work
+                            .preview_path()
+                            .filter(|path| self.storage.is_available(path))
+                            .map(|path| self.storage.resolve(path));
+                        let img = match preview {
+                            Some(path) if transform.has_adjustments() => self
+                                .display
+                                .adjusted_image(ui.ctx(), &path, &transform)
+                                .map_or(img, |adjusted| adjusted.maintain_aspect_ratio(true)),
+                            _ => img,
+                        };
+                        let natural = img
+                            .load_and_calc_size(ui, cell.size())
+                            .unwrap_or(cell.size());
+                        let shown = fit(displayed_size(&transform, natural), cell.size());
+                        let (img, paint_rect) = apply(
+                            &transform,
+                            img,
+                            Rect::from_center_size(cell.center(), shown),
+                        );
+                        img.paint_at(ui, paint_rect);
+                    } else {
+                        let shown = img
+                            .load_and_calc_size(ui, cell.size())
+                            .map_or(cell.size(), |natural| fit(natural, cell.size()));
+                        img.paint_at(ui, Rect::from_center_size(cell.center(), shown));
+                    }
+                    match resp
+                        .context_menu(|ui| self.work_context_menu(work, ui))
+                        .and_then(|menu| menu.inner)
+                    {
+                        Some(WorkMenuAction::HandedOff(stored)) => {
+                            let local = self.storage.resolve(&stored);
+                            self.variant_watcher.watch(
+                                work.screen_url(),
+                                &stored.to_string_lossy(),
+                                &local,
+                            );
                         }
-                    });
+                        Some(WorkMenuAction::Enqueue) => self.queue.enqueue(work),
+                        None => {}
+                    }
+                    if resp.hovered() && !is_selected {
+                        ui.painter().rect_stroke(
+                            cell,
+                            0.,
+                            ui.visuals().widgets.hovered.bg_stroke,
+                            egui::StrokeKind::Inside,
+                        );
+                    }
+                    if resp.clicked() {
+                        self.set_selected(work_offset);
+                        if tutorial.step() == TutorialStep::WorksIntro {
+                            tutorial.next();
+                        }
+                    }
+                }
+                for (rect, heading) in layout.headings_in(viewport.y_range()) {
+                    Self::paint_group_heading(ui, rect.translate(origin), heading);
+                }
+                // Keep the heading for the group at the top of the view pinned in place.
+                if let Some(heading) = layout.group_at(viewport.top()) {
+                    let band = Rect::from_min_size(
+                        ui.clip_rect().left_top(),
+                        Vec2::new(ui.clip_rect().width(), GalleryLayout::HEADING_HEIGHT),
+                    );
+                    Self::paint_group_heading(ui, band, heading);
                 }
                 perf.sample("Draw Works", draw_start.elapsed());
             });
+        self.gallery_layout = Some(layout);
+        self.gallery_scroll = scroll.state.offset.y;
+        // Note: this feeds the next frame's prefetch plan.
+        self.scroll_prefetch
+            .observe(scroll.state.offset.y / size, Instant::now());
+
+        self.peek_ui(ui.ctx());
+    }
+
+    // The selected work, large and centered over everything, until Space or Escape. It moves
+    // with the selection, so the arrow keys flip through the gallery.
+    fn peek_ui(&mut self, ctx: &egui::Context) {
+        if !self.peeking {
+            return;
+        }
+        let Some(work) = self.get_selected_work() else {
+            self.peeking = false;
+            return;
+        };
+        // Note: videos and songs stay in the slideshow, where the player is; peek at their preview.
+        let screen_uri = work
+            .screen_path()
+            .filter(|path| is_image(path) && self.storage.is_available(path))
+            .map(|path| format!("file://{}", self.storage.resolve(path).display()))
+            .filter(|uri| self.image_cache.contains(uri));
+        let img = match screen_uri {
+            Some(uri) => egui::Image::new(uri),
+            None => self.get_preview_image(self.thumb_uri(work)),
+        };
+        let name = work.name().to_owned();
+        // Note: color adjustments are left to the slideshow, which makes them at full size.
+        let transform = self.display.get(work.screen_url());
+        let bounds = ctx.screen_rect().shrink2(ctx.screen_rect().size() * 0.075);
+
+        let clicked = egui::Area::new(egui::Id::new("work_peek"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(ctx.screen_rect().min)
+            .show(ctx, |ui| {
+                let resp = ui.allocate_rect(ctx.screen_rect(), Sense::click());
+                ui.painter()
+                    .rect_filled(ctx.screen_rect(), 0., Color32::from_black_alpha(160));
+                let img = img.show_loading_spinner(false).maintain_aspect_ratio(true);
+                let natural = img
+                    .load_and_calc_size(ui, bounds.size())
+                    .unwrap_or(bounds.size());
+                match transform {
+                    Some(transform) => {
+                        let shown = fit(displayed_size(&transform, natural), bounds.size());
+                        let (img, rect) = apply(
+                            &transform,
+                            img,
+                            Rect::from_center_size(bounds.center(), shown),
+                        );
+                        img.paint_at(ui, rect);
+                    }
+                    None => {
+                        let shown = fit(natural, bounds.size());
+                        img.paint_at(ui, Rect::from_center_size(bounds.center(), shown));
+                    }
+                }
+                ui.painter().text(
+                    bounds.center_bottom() + Vec2::new(0., 8.),
+                    egui::Align2::CENTER_TOP,
+                    name,
+                    egui::FontId::proportional(14.),
+                    Color32::WHITE,
+                );
+                resp.clicked()
+            })
+            .inner;
+        if clicked {
+            self.peeking = false;
+        }
+    }
+
+    fn layout_gallery(
+        &self,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        width: f32,
+        size: f32,
+    ) -> GalleryLayout {
+        let works = self.work_matching_tag.as_ref();
+        let items = self.work_filtered.iter().map(|id| {
+            let work = works.and_then(|works| works.get(id));
+            LayoutItem {
+                group: work.and_then(|work| self.grouping.label(work, tags)),
+                aspect: work.and_then(|work| self.displayed_aspect_ratio(work)),
+            }
+        });
+        GalleryLayout::build(self.gallery_mode, items, width, size)
+    }
+
+    // The shape the work will show at in the gallery, taking any turns into account.
+    fn displayed_aspect_ratio(&self, work: &DbWork) -> Option<f32> {
+        let aspect = work.aspect_ratio()?;
+        Some(match self.display.get(work.screen_url()) {
+            Some(transform) => {
+                let shown = displayed_size(&transform, Vec2::new(aspect, 1.));
+                shown.x / shown.y
+            }
+            None => aspect,
+        })
+    }
+
+    fn paint_group_heading(ui: &egui::Ui, band: Rect, heading: &str) {
+        let font = egui::FontId::proportional(16.);
+        let painter = ui.painter();
+        painter.rect_filled(band, 0., ui.visuals().extreme_bg_color.gamma_multiply(0.85));
+        painter.text(
+            band.left_center() + Vec2::new(8., 0.),
+            egui::Align2::LEFT_CENTER,
+            heading,
+            font,
+            ui.visuals().strong_text_color(),
+        );
     }
 
     pub fn slideshow_ui(
         &mut self,
         tags: Option<&HashMap<TagId, DbTag>>,
         mut tutorial: Tutorial<'_>,
-        db_write: &DbWriteHandle,
+        host: &mut PluginHost,
+        (db_read, db_write): (&DbReadHandle, &DbWriteHandle),
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
     ) {
+        // Note: let videos and songs play out before auto-advancing past them.
+        let played_out = self.has_loaded_media && self.mpv.percent_pos() >= 99.5;
+        if self.playback.tick(played_out, ctx) {
+            self.mpv.pause_async().ok();
+        }
+        // Note: once the sleep timer goes off, nothing plays on until the user says so.
+        let played_out = played_out && !self.playback.is_asleep();
+        // Note: works played from the queue go on to the next one in it, not along the gallery.
+        let queue_moved = played_out
+            && self
+                .get_selected_work()
+                .map(|work| work.screen_url().to_owned())
+                .is_some_and(|screen_url| self.queue.finished(&screen_url));
+        let ready = (!self.has_loaded_media || played_out) && !queue_moved;
+        self.in_spread_view = self.slideshow.shows_spread();
+        self.in_document_view = self
+            .get_selected_work()
+            .and_then(DbWork::screen_path)
+            .is_some_and(is_paged);
+        self.in_model_view = self.show_models && self.shown_model_path().is_some();
+        // Note: a spread advances from its second page.
+        if let Some(selected) = self.selected
+            && let Some(next) = self.slideshow.tick(
+                self.shown_spread(selected).unwrap_or(selected),
+                self.work_filtered.len(),
+                ready,
+                ctx,
+            )
+        {
+            self.set_selected(next);
+        }
         let work_offset = self
             .selected
             .expect("entered slideshow without a selection");
+        // Note: views keep works out of cold storage, and bring them back if they are already there.
+        if let Some(work_id) = self.get_selected_work().map(DbWork::id)
+            && self.last_viewed != Some(work_id)
+        {
+            self.last_viewed = Some(work_id);
+            if let Err(e) = db_write.note_work_viewed(work_id) {
+                error!("Failed to record work view: {e}");
+            }
+        }
+        // Note: plugins that fetch screen images on view leave us with only the preview.
+        if tags.is_some()
+            && let Some(work) = self.get_selected_work()
+            && work.screen_path().is_none()
+            && !self.requested_screens.contains(&work.id())
+        {
+            let work_id = work.id();
+            let downloads = Self::downloads_by_plugin(tags, once(work));
+            host.download_works(downloads, false);
+            self.requested_screens.insert(work_id);
+        }
+        // Note: the filmstrip comes and goes with the cursor, so only load its thumbnails then.
+        let filmstrip = (self.slideshow.shows_filmstrip()
+            && self.last_mouse_motion.elapsed() < Duration::from_secs(2))
+        .then(|| {
+            let count = (ctx.screen_rect().width() / FILMSTRIP_THUMB).floor() as usize;
+            filmstrip_window(
+                work_offset,
+                self.work_filtered.len(),
+                count.saturating_sub(1),
+            )
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             let size = self.thumb_size;
             let width = ui.available_width();
             let n_wide = (width / size).floor().max(1.) as usize;
-            self.check_common_key_binds(tags, db_write, n_wide, ui);
-            self.check_slideshow_key_binds(ui);
+            if !self.annotations.is_labeling() {
+                self.check_common_key_binds(tags, db_write, n_wide, ui);
+                self.check_slideshow_key_binds(ui);
+            }
 
             // Note: we rate-limit the number of loads we allow per frame. Make sure that
             // we preferentially load the image we're actually looking at so we're not stuck
@@ -1135,21 +2547,80 @@ impl UxWork {
             // Follow this by the image in front of us, then behind us, spiraling outwards.
             let forward = work_offset.saturating_add(1)..work_offset.saturating_add(n_wide).min(self.work_filtered.len());
             let backward = (work_offset.saturating_sub(n_wide)..work_offset).rev();
-            for offset in once(work_offset).chain(forward.interleave(backward)) {
-                self.ensure_work_cached(ui.ctx(), offset, ctx.screen_rect().size());
+            let current = self.selected.unwrap_or(work_offset);
+            let spread = self.shown_spread(current);
+            for offset in once(work_offset)
+                .chain(spread)
+                .chain(forward.interleave(backward))
+            {
+                self.ensure_work_cached(ui.ctx(), offset, ctx.screen_rect().size(), true);
+            }
+            for offset in filmstrip.clone().into_iter().flatten() {
+                self.ensure_work_cached(ui.ctx(), offset, Vec2::splat(FILMSTRIP_THUMB), false);
+            }
+            self.flush_image_cache(ui.ctx());
+
+            if let Some(partner) = spread {
+                self.paint_spread(ui, (current, partner));
+                self.draw_offset_label(ui, current);
+                return;
+            }
+
+            if let Some(path) = self
+                .get_selected_work()
+                .and_then(DbWork::screen_path)
+                .filter(|path| is_paged(path) && self.storage.is_available(path))
+                .map(|path| self.storage.resolve(path))
+            {
+                self.document.ui(&path, ui);
+                self.draw_offset_label(ui, work_offset);
+                return;
+            }
+
+            if self.show_models
+                && let Some(path) = self.shown_model_path()
+            {
+                self.model.ui(&path, ui);
+                self.draw_offset_label(ui, work_offset);
+                return;
             }
-            self.flush_works_lru(ui.ctx());
 
             let full = ui.available_size() * self.slide_xform.zoom;
-            let (img, size) = match self.get_screen_image() {
+            let preview = self
+                .get_selected_work()
+                .and_then(DbWork::preview_path)
+                .map(|path| self.storage.resolve(path));
+            let screen_path = self
+                .get_selected_work()
+                .and_then(DbWork::screen_path)
+                .filter(|path| is_image(path) && self.storage.is_available(path))
+                .map(|path| self.storage.resolve(path));
+            // Note: drawings are drawn again at the zoomed size, rather than scaled up.
+            if let Some(path) = screen_path.as_deref().filter(|path| is_vector(path)) {
+                self.vector.request(path, full, ctx);
+            }
+            let transform = self
+                .get_selected_work()
+                .and_then(|work| self.display.get(work.screen_url()))
+                .unwrap_or_default();
+            let (img, size, is_image) = match self.get_screen_image() {
                 DisplayKind::Image(img) => {
                     // Set the maintain_aspect_ratio flag, then call load_and_calc_size to
                     // upscale the image size (not the image itself!) to fit in our virtual "full"
                     // viewport, with zoom. Note that we already called load on svg with a SizeHint
                     // of the actual screen size, so racing with zoom won't mess anything up here.
+                    let img = match screen_path.as_deref() {
+                        Some(path) if transform.has_adjustments() => self
+                            .display
+                            .adjusted_image(ctx, path, &transform)
+                            .unwrap_or(img),
+                        _ => img,
+                    };
                     let img = img.show_loading_spinner(false).maintain_aspect_ratio(true);
                     let size = img.load_and_calc_size(ui, full).unwrap_or([48., 48.].into());
-                    (img, size)
+                    // Note: the display transform changes the shape we have to fit on screen.
+                    let size = fit(displayed_size(&transform, size), full);
+                    (img, size, true)
                 }
                 DisplayKind::MediaPlayer => {
                     // TODO: currently, using the native size of the display area here. This will
@@ -1167,10 +2638,28 @@ impl UxWork {
                     } else {
                         egui::Image::new(include_image!("../../assets/loading-preview.png"))
                     };
-                    (img, screen.size() * self.slide_xform.zoom)
+                    (img, screen.size() * self.slide_xform.zoom, false)
                 }
             };
 
+            // Note: Ken Burns drives the same zoom and pan that the user does, so re-derive the
+            //       zoomed sizes from the framing it picks.
+            let fill = size / full;
+            let (full, size) = if is_image
+                && let Some((zoom, focus)) = self.slideshow.ken_burns(
+                    self.selected.unwrap_or(work_offset),
+                    fill,
+                    || preview.as_deref().and_then(salient_point),
+                    ctx,
+                ) {
+                self.slide_xform
+                    .frame(zoom, focus, fill, ui.available_size());
+                let full = ui.available_size() * self.slide_xform.zoom;
+                (full, full * fill)
+            } else {
+                (full, size)
+            };
+
             // Pan the zoomed image within the virtual `full` zoomed size, to account for both
             // letterboxing and our current pan position.
             let (mut left, mut right, mut top, mut bottom) = (0., full.x, 0., full.y);
@@ -1185,16 +2674,76 @@ impl UxWork {
             let rect = Rect::from_x_y_ranges(left..=right, top..=bottom)
                 .translate(self.slide_xform.pan);
 
-            // Paint the image.
-            img.paint_at(ui, rect);
+            // Paint the image; key binds above may have already moved the selection.
+            let uri = img.source(ui.ctx()).uri().map(str::to_owned);
+            let (img, paint_rect) = if is_image {
+                apply(&transform, img, rect)
+            } else {
+                (img, rect)
+            };
+            self.slideshow
+                .paint(ui, self.selected.unwrap_or(work_offset), img, paint_rect);
+            self.image_info.ui(
+                (screen_path.as_deref(), uri.as_deref()),
+                rect,
+                self.slide_xform.zoom,
+                ctx,
+            );
+            // Note: the reference goes in the work's rect before any display transform, so that
+            //       it lines up with the file the difference is made from.
+            self.reference.ui(screen_path.as_deref(), rect, ui);
+            if is_image
+                && let Some(screen_url) = self
+                    .get_selected_work()
+                    .map(|work| work.screen_url().to_owned())
+            {
+                self.annotations.slideshow_ui(
+                    &screen_url,
+                    &transform,
+                    rect,
+                    (db_read, db_write),
+                    ui,
+                );
+            }
 
             // Draw UX on top.
             self.draw_offset_label(ui, work_offset);
+            if self.has_loaded_media
+                && let Some(work) = self.get_selected_work()
+            {
+                let screen_url = work.screen_url().to_owned();
+                let media = work
+                    .screen_path()
+                    .filter(|path| self.storage.is_available(path))
+                    .map(|path| self.storage.resolve(path));
+                // Note: mpv can't seek until it knows how long the file is.
+                if self.mpv.duration() > 0.
+                    && let Some((_, secs)) = self
+                        .seek_when_loaded
+                        .take_if(|(url, _)| *url == screen_url)
+                {
+                    self.mpv.seek_absolute_async(secs).ok();
+                }
+                if let Some(secs) = self.transcript.slideshow_ui(
+                    (&screen_url, media.as_deref()),
+                    self.mpv.time_pos(),
+                    ui.max_rect(),
+                    (db_read, db_write),
+                    ui,
+                ) {
+                    self.mpv.seek_absolute_async(secs).ok();
+                }
+            }
             if self.has_loaded_media {
+                let feed = self.selected_feed(tags);
+                if let Some(speed) = self.playback.speed_to_apply(feed.as_deref()) {
+                    self.mpv.set_speed_async(speed).ok();
+                }
                 ui.with_layout(egui::Layout::left_to_right(egui::Align::Max), |ui| {
                     ui.horizontal(|ui| {
                         if self.mpv.is_paused() && ui.button("▶").clicked() {
                             self.mpv.unpause_async().ok();
+                            self.playback.wake();
                         } else if ui.button("⏸").clicked() {
                             self.mpv.pause_async().ok();
                         }
@@ -1217,6 +2766,9 @@ impl UxWork {
                         if ui.button("⏩").clicked() {
                             self.mpv.seek_forward_async(5.).ok();
                         }
+                        if let Some(speed) = self.playback.controls_ui(feed.as_deref(), ui) {
+                            self.mpv.set_speed_async(speed).ok();
+                        }
                     });
                 });
             }
@@ -1231,12 +2783,29 @@ impl UxWork {
                     ui.label("");
                     ui.label("Use the left and right arrow keys to navigate to the next and previous work.");
                     ui.label("");
-                    ui.label("To continue, exit the slideshow by pressing the Spacebar again.");
+                    ui.label("Press I to show details about the image, and L for a loupe to inspect it up close.");
+                    ui.label("");
+                    ui.label("Press M to mark a spot on the work with a label, and O to show or hide the marks.");
+                    ui.label("");
+                    ui.label("Press C, or drop an image file on the work, to compare it against a photograph or scan of your own.");
+                    ui.label("");
+                    ui.label("While a video or episode plays, press T for its transcript, and U to show or hide subtitles.");
+                    ui.label("");
+                    ui.label("To continue, exit the slideshow by pressing the Spacebar or Escape.");
                     tutorial.button_area(NextButton::Skip, ui);
                 });
             }
         });
 
+        if self.last_mouse_motion.elapsed() < Duration::from_secs(2) {
+            self.slideshow.hud_ui(ctx);
+        }
+        if let Some(window) = filmstrip
+            && let Some(offset) = self.filmstrip_ui(window, ctx)
+        {
+            self.set_selected(offset);
+        }
+
         // Hide the mouse cursor on inactivity
         let mouse_is_moving = ctx.input_mut(|input| {
             input.raw_scroll_delta != Vec2::ZERO
@@ -1260,6 +2829,298 @@ impl UxWork {
         }
     }
 
+    // The name of the selected work's Series tag: for a podcast episode, the feed it's from.
+    fn selected_feed(&self, tags: Option<&HashMap<TagId, DbTag>>) -> Option<String> {
+        let tags = tags?;
+        self.get_selected_work()?
+            .tags()
+            .filter_map(|tag_id| tags.get(&tag_id))
+            .find(|tag| tag.kind() == TagKind::Series)
+            .map(|tag| tag.name().to_owned())
+    }
+
+    // The work after `offset`, if the slideshow is showing spreads and the two are pages of the
+    // same volume or portfolio. Videos and songs are never pages.
+    fn shown_spread(&self, offset: usize) -> Option<usize> {
+        if !self.in_spread_view {
+            return None;
+        }
+        let works = self.work_matching_tag.as_ref()?;
+        let page = works.get(self.work_filtered.get(offset)?)?;
+        let next = works.get(self.work_filtered.get(offset + 1)?)?;
+        let is_page = |work: &DbWork| work.screen_path().is_none_or(is_image);
+        let bound_together = page
+            .tags()
+            .any(|tag| self.book_tags.contains(&tag) && next.tags().any(|other| other == tag));
+        (is_page(page) && is_page(next) && bound_together).then_some(offset + 1)
+    }
+
+    // Paint two pages meeting at the middle of the screen, zoomed and panned together like a
+    // single work. Display transforms and overlays are left to the single page view.
+    fn paint_spread(&self, ui: &egui::Ui, pages: (usize, usize)) {
+        let (left, right) = match self.slideshow.spread_direction() {
+            SpreadDirection::LeftToRight => pages,
+            SpreadDirection::RightToLeft => (pages.1, pages.0),
+        };
+        let full = ui.available_size() * self.slide_xform.zoom;
+        let bounds = Vec2::new(full.x / 2., full.y);
+        let spine = (full / 2.).to_pos2();
+        for (offset, is_left) in [(left, true), (right, false)] {
+            let img = self
+                .page_image(offset)
+                .show_loading_spinner(false)
+                .maintain_aspect_ratio(true);
+            let size = img
+                .load_and_calc_size(ui, bounds)
+                .map_or(bounds, |natural| fit(natural, bounds));
+            let min = if is_left {
+                spine - Vec2::new(size.x, size.y / 2.)
+            } else {
+                spine - Vec2::new(0., size.y / 2.)
+            };
+            img.paint_at(
+                ui,
+                Rect::from_min_size(min, size).translate(self.slide_xform.pan),
+            );
+        }
+    }
+
+    // The screen image for a page of a spread, or its preview until that is loaded.
+    fn page_image<'b>(&self, offset: usize) -> egui::Image<'b> {
+        let Some(work) = self
+            .work_filtered
+            .get(offset)
+            .and_then(|id| self.work_matching_tag.as_ref()?.get(id))
+        else {
+            return egui::Image::new(include_image!("../../assets/loading-preview.png"));
+        };
+        let screen_uri = work
+            .screen_path()
+            .filter(|path| self.storage.is_available(path))
+            .map(|path| format!("file://{}", self.storage.resolve(path).display()))
+            .filter(|uri| self.image_cache.contains(uri));
+        match screen_uri {
+            Some(uri) => egui::Image::new(uri),
+            None => self.get_preview_image(self.preview_uri(work)),
+        }
+    }
+
+    // Thumbnails of the works around the current one, for jumping about the filtered set. Returns
+    // the offset of the work that was clicked on, if any.
+    fn filmstrip_ui(&mut self, window: Range<usize>, ctx: &egui::Context) -> Option<usize> {
+        let works = self.work_matching_tag.as_ref()?;
+        let mut clicked = None;
+        let area = egui::Area::new(egui::Id::new("slideshow_filmstrip"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0., -8.])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        for offset in window {
+                            let Some(work) = works.get(&self.work_filtered[offset]) else {
+                                continue;
+                            };
+                            let (cell, resp) = ui
+                                .allocate_exact_size(Vec2::splat(FILMSTRIP_THUMB), Sense::click());
+                            if self.selected == Some(offset) {
+                                ui.painter()
+                                    .rect_filled(cell, 0., ui.visuals().selection.bg_fill);
+                            }
+                            // Note: works behind a content filter stay hidden here, too.
+                            let hidden = self.work_blurred.contains(&work.id())
+                                && !self.filters.is_revealed(work.id());
+                            let img = if hidden {
+                                egui::Image::new(include_image!("../../assets/loading-preview.png"))
+                            } else {
+                                self.get_preview_image(self.thumb_uri(work))
+                            }
+                            .show_loading_spinner(false)
+                            .maintain_aspect_ratio(true);
+                            let inner = cell.shrink(4.);
+                            let shown = img
+                                .load_and_calc_size(ui, inner.size())
+                                .map_or(inner.size(), |natural| fit(natural, inner.size()));
+                            img.paint_at(ui, Rect::from_center_size(inner.center(), shown));
+                            if resp.on_hover_text(work.name()).clicked() {
+                                clicked = Some(offset);
+                            }
+                        }
+                    });
+                });
+            });
+        // Note: keep the strip up while the mouse rests on it.
+        if area.response.contains_pointer() {
+            self.last_mouse_motion = Instant::now();
+        }
+        clicked
+    }
+
+    fn undownloaded_works(&self) -> impl Iterator<Item = &DbWork> {
+        let works = self.work_matching_tag.as_ref();
+        self.work_filtered
+            .iter()
+            .filter_map(move |id| works?.get(id))
+            .filter(|work| work.screen_path().is_none())
+    }
+
+    // Downloads go through the plugin that the work came from, so that they respect its rate
+    // limits; we know that from the sources of the work's tags.
+    fn downloads_by_plugin<'a>(
+        tags: Option<&HashMap<TagId, DbTag>>,
+        works: impl Iterator<Item = &'a DbWork>,
+    ) -> HashMap<String, Vec<String>> {
+        let mut by_plugin: HashMap<String, Vec<String>> = HashMap::new();
+        for work in works {
+            let plugin = work
+                .tags()
+                .filter_map(|id| tags?.get(&id))
+                .find_map(|tag| tag.sources().find(|name| !name.is_empty()));
+            if let Some(plugin) = plugin {
+                by_plugin
+                    .entry(plugin.to_owned())
+                    .or_default()
+                    .push(work.screen_url().to_owned());
+            }
+        }
+        by_plugin
+    }
+
+    pub fn slideshow_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        self.slideshow.preferences_ui(ui);
+    }
+
+    // Returns whether the filters changed.
+    pub fn transcript_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        self.transcript.preferences_ui(ui);
+    }
+
+    pub fn external_editors_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Programs to offer under Open With, e.g. `gimp` or `krita --nosplash {}`:");
+        let mut remove = None;
+        egui::Grid::new("external_editors_grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (offset, editor) in self.external_editors.iter_mut().enumerate() {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut editor.name)
+                            .hint_text("name")
+                            .desired_width(100.),
+                    );
+                    ui.add(egui::TextEdit::singleline(&mut editor.command).hint_text("command"));
+                    if ui.small_button("🗑").clicked() {
+                        remove = Some(offset);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(offset) = remove {
+            self.external_editors.remove(offset);
+        }
+        if ui.button("Add Editor").clicked() {
+            self.external_editors.push(ExternalEditor::default());
+        }
+    }
+
+    // The largest file we have for the work on this machine: editors want the archive TIFF, not
+    // the screen JPEG, where we have both.
+    fn best_local_file<'w>(&self, work: &'w DbWork) -> Option<&'w Path> {
+        work.archive_path()
+            .or(work.screen_path())
+            .or(work.preview_path())
+            .filter(|path| self.storage.is_available(path))
+    }
+
+    fn work_context_menu(&self, work: &DbWork, ui: &mut egui::Ui) -> Option<WorkMenuAction> {
+        if ui.button("Copy Link").clicked() {
+            ui.ctx().copy_text(work_link(work));
+            ui.close();
+        }
+        let mut action = None;
+        if PlayQueue::can_enqueue(work)
+            && ui
+                .add_enabled(
+                    !self.queue.contains(work.screen_url()),
+                    egui::Button::new("Add to Queue"),
+                )
+                .clicked()
+        {
+            action = Some(WorkMenuAction::Enqueue);
+            ui.close();
+        }
+        let Some(stored) = self.best_local_file(work) else {
+            ui.label("Nothing downloaded for this work yet");
+            return action;
+        };
+        let path = self.storage.resolve(stored);
+        let mut handed_off = false;
+        if ui.button("Open in Default Viewer").clicked() {
+            match open_in_default_viewer(&path) {
+                Ok(()) => handed_off = true,
+                Err(e) => error!("Failed to open {}: {e}", path.display()),
+            }
+            ui.close();
+        }
+        ui.menu_button("Open With", |ui| {
+            if self.external_editors.is_empty() {
+                ui.label("Add editors in the Preferences");
+            }
+            for editor in &self.external_editors {
+                if ui.button(&editor.name).clicked() {
+                    match editor.open(&path) {
+                        Ok(()) => handed_off = true,
+                        Err(e) => error!(
+                            "Failed to open {} with {}: {e}",
+                            path.display(),
+                            editor.name
+                        ),
+                    }
+                    ui.close();
+                }
+            }
+        });
+        if ui.button("Reveal in File Manager").clicked() {
+            if let Err(e) = reveal_in_file_manager(&path) {
+                error!("Failed to show {}: {e}", path.display());
+            }
+            ui.close();
+        }
+        if ui.button("Copy Image").clicked() {
+            self.copy_work_image(work, ui.ctx());
+            ui.close();
+        }
+        if handed_off {
+            return Some(WorkMenuAction::HandedOff(stored.to_owned()));
+        }
+        action
+    }
+
+    pub fn image_cache_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        self.image_cache.preferences_ui(ui);
+    }
+
+    pub fn color_preferences_ui(&mut self, ui: &mut egui::Ui) {
+        self.color.preferences_ui(ui);
+    }
+
+    pub fn image_cache_usage_ui(&self, ui: &mut egui::Ui) {
+        self.image_cache.usage_ui(ui);
+    }
+
+    pub fn filter_preferences_ui(
+        &mut self,
+        tags: Option<&HashMap<TagId, DbTag>>,
+        ui: &mut egui::Ui,
+    ) -> bool {
+        let changed = self.filters.ui(tags, ui);
+        if changed {
+            self.reproject_work(tags);
+        }
+        changed
+    }
+
+    pub fn public_view(&self, tags: &HashMap<TagId, DbTag>) -> PublicView {
+        self.filters.compile(Some(tags)).public_view()
+    }
+
     fn draw_offset_label(&self, ui: &mut egui::Ui, offset: usize) {
         ui.label(format!(
             "{offset} of {} {}",
@@ -1272,12 +3133,20 @@ impl UxWork {
 
     fn preview_uri(&self, work: &DbWork) -> Option<String> {
         work.preview_path()
-            .map(|path| format!("file://{}", self.data_dir.join(path).display()))
+            .map(|path| format!("file://{}", self.storage.resolve(path).display()))
+    }
+
+    // The gallery shows our own thumbnail of the screen image where we have one, as the plugin's
+    // preview may be too small for the larger cell sizes.
+    fn thumb_uri(&self, work: &DbWork) -> Option<String> {
+        work.thumb_path()
+            .or(work.preview_path())
+            .map(|path| format!("file://{}", self.storage.resolve(path).display()))
     }
 
     fn get_preview_image<'b>(&self, uri: Option<String>) -> egui::Image<'b> {
         if let Some(uri) = uri
-            && self.works_lru.contains(&uri)
+            && self.image_cache.contains(&uri)
         {
             egui::Image::new(uri)
         } else {
@@ -1285,25 +3154,56 @@ impl UxWork {
         }
     }
 
+    // The selected work's 3D model, if we have it on hand to show; models on remote storage show
+    // the image until they arrive in the cache.
+    fn shown_model_path(&self) -> Option<PathBuf> {
+        let path = self.get_selected_work()?.model_path()?;
+        if !self.storage.is_available(path) {
+            self.storage.prefetch(path);
+            return None;
+        }
+        Some(self.storage.resolve(path))
+    }
+
     fn get_screen_image<'b>(&mut self) -> DisplayKind<'b> {
         if let Some(work) = self.get_selected_work()
             && let Some(screen_path) = work.screen_path()
         {
-            let screen_path = self.data_dir.join(screen_path);
+            // Note: files on remote storage show the preview until they arrive in the cache.
+            if !self.storage.is_available(screen_path) {
+                self.storage.prefetch(screen_path);
+                return DisplayKind::Image(self.get_preview_image(self.preview_uri(work)));
+            }
+            let screen_path = self.storage.resolve(screen_path);
             let screen_path_str = screen_path.display().to_string();
             let screen_uri = format!("file://{screen_path_str}");
-            if is_image(&screen_path) {
-                if self.works_lru.contains(&screen_uri) {
+            if is_vector(&screen_path) {
+                // Note: the preview stands in until the first drawing is done.
+                return DisplayKind::Image(match self.vector.texture(&screen_path) {
+                    Some(texture) => egui::Image::from_texture(texture),
+                    None => self.get_preview_image(self.preview_uri(work)),
+                });
+            } else if is_image(&screen_path) {
+                if self.image_cache.contains(&screen_uri) {
                     return DisplayKind::Image(egui::Image::new(screen_uri));
                 }
+            } else if is_paged(&screen_path) {
+                // Note: the slideshow reads documents itself; this is only for the peek.
+                return DisplayKind::Image(self.get_preview_image(self.preview_uri(work)));
             } else if !self.has_loaded_media {
                 self.mpv.playlist_replace_async(&screen_path, None).ok();
                 self.mpv.unpause_async().ok();
+                self.playback.on_load();
                 self.has_loaded_media = true;
                 return DisplayKind::MediaPlayer;
             } else {
                 return DisplayKind::MediaPlayer;
             }
+        } else if let Some(work) = self.get_selected_work()
+            && work.preview_path().is_some()
+        {
+            // Waiting on the screen image to be fetched.
+            return DisplayKind::Image(self.get_preview_image(self.preview_uri(work)));
         }
 
         // Note: Fall through to try to load the preview image so we have something to show.
@@ -1312,17 +3212,19 @@ impl UxWork {
         )))
     }
 
-    fn ensure_work_cached(&mut self, ctx: &egui::Context, work_offset: usize, screen_size: Vec2) {
+    // The gallery only needs the thumbnails, where the slideshow wants the screen images.
+    fn ensure_work_cached(
+        &mut self,
+        ctx: &egui::Context,
+        work_offset: usize,
+        screen_size: Vec2,
+        screens: bool,
+    ) {
         // If we restore from an exit in slideshow mode and haven't loaded yet.
         if self.work_matching_tag.is_none() {
             return;
         }
 
-        // Limit number of times we call try_load_image per frame to prevent pauses
-        if self.per_frame_work_upload_count > Self::MAX_PER_FRAME_UPLOADS {
-            return;
-        }
-
         // Note: Only the svg loader uses this. Tell svg to load at the full screen size.
         let size_hint = SizeHint::Size {
             width: screen_size.x as u32,
@@ -1335,43 +3237,81 @@ impl UxWork {
             .expect("no work after check");
         if let Some(work_id) = self.work_filtered.get(work_offset)
             && let Some(work) = works.get(work_id)
-            && let Some(screen_path) = work.screen_path()
+            && let Some(screen_path) = work.screen_path().filter(|_| screens)
             && is_image(screen_path)
+            && !is_vector(screen_path)
         {
-            let screen_uri = format!("file://{}", self.data_dir.join(screen_path).display());
-            if !self.works_lru.contains(&screen_uri) {
-                ctx.try_load_image(&screen_uri, size_hint).ok();
+            let screen_uri = format!("file://{}", self.storage.resolve(screen_path).display());
+            if !self.storage.is_available(screen_path) {
+                self.storage.prefetch(screen_path);
+            } else if !self.image_cache.touch(&screen_uri)
+                // Limit number of times we call try_load_image per frame to prevent pauses
+                && self.per_frame_work_upload_count <= Self::MAX_PER_FRAME_UPLOADS
+            {
+                self.image_cache.load(ctx, screen_uri, size_hint);
                 self.per_frame_work_upload_count += 1;
-                self.works_lru.get_or_insert(screen_uri, || 0);
             }
         }
         if let Some(work_id) = self.work_filtered.get(work_offset)
             && let Some(work) = works.get(work_id)
-            && let Some(preview_path) = work.preview_path()
+            && let Some(preview_path) = if screens {
+                work.preview_path()
+            } else {
+                work.thumb_path().or(work.preview_path())
+            }
         {
             // Note: non-image previews will just show up as an error icon; the thumbnailing
             //       should already have happened out of line.
-            let preview_uri = format!("file://{}", self.data_dir.join(preview_path).display());
-            if !self.works_lru.contains(&preview_uri) {
-                ctx.try_load_image(&preview_uri, size_hint).ok();
+            let preview_uri = format!("file://{}", self.storage.resolve(preview_path).display());
+            if !self.storage.is_available(preview_path) {
+                self.storage.prefetch(preview_path);
+            } else if !self.image_cache.touch(&preview_uri)
+                && self.per_frame_work_upload_count <= Self::MAX_PER_FRAME_UPLOADS
+            {
+                self.image_cache.load(ctx, preview_uri, size_hint);
                 self.per_frame_work_upload_count += 1;
-                self.works_lru.get_or_insert(preview_uri, || 0);
             }
         }
     }
 
-    fn flush_works_lru(&mut self, ctx: &egui::Context) {
+    fn flush_image_cache(&mut self, ctx: &egui::Context) {
         self.per_frame_work_upload_count = 0;
-        while self.works_lru.len() > Self::LRU_CACHE_SIZE {
-            if let Some((uri, _)) = self.works_lru.pop_lru() {
-                ctx.forget_image(&uri);
-            }
-        }
+        self.image_cache.flush(ctx);
     }
 }
 
+// Where a work lands in a random order: the same for the same seed, for as long as we run.
+fn shuffle_key(seed: u64, id: WorkId) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, id).hash(&mut hasher);
+    hasher.finish()
+}
+
 #[cfg(test)]
 mod test {
+    use super::ZoomPan;
+    use egui::Vec2;
+
+    #[test]
+    fn test_zoom_pan_frame() {
+        let viewport = Vec2::new(100., 100.);
+        let mut xform = ZoomPan::default();
+
+        // Centered on a square image is just a centered zoom.
+        xform.frame(2., Vec2::splat(0.5), Vec2::splat(1.), viewport);
+        assert_eq!(xform.pan, Vec2::splat(-50.), "centered");
+
+        // Focusing on a corner stops at the edge of the image rather than showing background.
+        xform.frame(2., Vec2::ZERO, Vec2::splat(1.), viewport);
+        assert_eq!(xform.pan, Vec2::ZERO, "top left");
+        xform.frame(2., Vec2::splat(1.), Vec2::splat(1.), viewport);
+        assert_eq!(xform.pan, Vec2::splat(-100.), "bottom right");
+
+        // A wide image that still fits vertically when zoomed stays centered on that axis.
+        xform.frame(1.5, Vec2::new(0., 0.), Vec2::new(1., 0.5), viewport);
+        assert_eq!(xform.pan, Vec2::new(0., -25.), "letterboxed");
+    }
+
     #[test]
     fn test_next_power_of_two() {
         assert_eq!((127.5f32.round() as u32).next_power_of_two(), 128);
@@ -0,0 +1,185 @@
+// Cargo helpers for plugin authors. Install with `cargo install --path tools/cargo-artchiver`,
+// after which cargo will find it as `cargo artchiver`.
+use anyhow::{Context as _, Result, bail, ensure};
+use clap::{Parser, Subcommand};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SDK_GIT: &str = "https://github.com/Trained-Monkey-Studios/artchiver";
+
+// Note: cargo runs `cargo-artchiver artchiver <args>`, so the first argument is our own name.
+#[derive(Debug, Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum CargoArgs {
+    Artchiver(ArtchiverArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct ArtchiverArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create a new plugin crate, ready to build for wasm and to test against the mock host.
+    NewPlugin {
+        /// The crate name, e.g. artx-rijks
+        name: String,
+
+        /// The name to show in Artchiver; by default, derived from the crate name.
+        #[arg(long)]
+        title: Option<String>,
+
+        /// The directory to create the plugin in.
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+
+        /// The path to artchiver_sdk, relative to the new plugin. By default, we use the sibling
+        /// checkout when creating in Artchiver's plugins folder and the git repository otherwise.
+        #[arg(long)]
+        sdk: Option<String>,
+    },
+}
+
+// Each file in a new plugin, and its template.
+const TEMPLATES: [(&str, &str); 8] = [
+    ("Cargo.toml", include_str!("../templates/Cargo.toml.in")),
+    (
+        ".cargo/config.toml",
+        include_str!("../templates/config.toml.in"),
+    ),
+    (
+        "rust-toolchain.toml",
+        include_str!("../templates/rust-toolchain.toml.in"),
+    ),
+    (".gitignore", include_str!("../templates/gitignore.in")),
+    ("src/lib.rs", include_str!("../templates/lib.rs.in")),
+    ("fixtures/tags.json", include_str!("../templates/tags.json")),
+    (
+        "fixtures/works.json",
+        include_str!("../templates/works.json"),
+    ),
+    ("README.md", include_str!("../templates/README.md.in")),
+];
+
+fn main() -> Result<()> {
+    let CargoArgs::Artchiver(args) = CargoArgs::parse();
+    match args.command {
+        Command::NewPlugin {
+            name,
+            title,
+            dir,
+            sdk,
+        } => new_plugin(&name, title, &dir, sdk),
+    }
+}
+
+fn new_plugin(name: &str, title: Option<String>, dir: &Path, sdk: Option<String>) -> Result<()> {
+    check_crate_name(name)?;
+    let root = dir.join(name);
+    ensure!(!root.exists(), "{} already exists", root.display());
+    let title = title.unwrap_or_else(|| title_for(name));
+    let sdk = sdk.map_or_else(|| sdk_dependency(dir), |path| format!("path = \"{path}\""));
+
+    for (path, template) in TEMPLATES {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+        fs::write(&path, render(template, name, &title, &sdk))
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+
+    println!("Created {title} in {}", root.display());
+    println!("  cargo test            # run the fixture tests against the mock host");
+    println!("  cargo build-plugin    # build target/wasm32-unknown-unknown/release/*.wasm");
+    Ok(())
+}
+
+fn render(template: &str, name: &str, title: &str, sdk: &str) -> String {
+    template
+        .replace("{{name}}", name)
+        .replace("{{title}}", title)
+        .replace("{{sdk}}", sdk)
+}
+
+// The same rules as cargo, minus the reserved names, which cargo will tell you about.
+fn check_crate_name(name: &str) -> Result<()> {
+    let Some(first) = name.chars().next() else {
+        bail!("the plugin name must not be empty");
+    };
+    ensure!(
+        first.is_ascii_alphabetic(),
+        "the plugin name must start with a letter: {name}"
+    );
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_')
+    {
+        bail!("the plugin name may not contain {c:?}: {name}");
+    }
+    Ok(())
+}
+
+// artx-rijks-museum => Rijks Museum
+fn title_for(name: &str) -> String {
+    name.strip_prefix("artx-")
+        .unwrap_or(name)
+        .split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The dependency spec for artchiver_sdk in the new Cargo.toml.
+fn sdk_dependency(dir: &Path) -> String {
+    if dir.join("artchiver_sdk").join("Cargo.toml").is_file() {
+        "path = \"../artchiver_sdk\"".to_owned()
+    } else {
+        format!("git = \"{SDK_GIT}\"")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_crate_name, render, title_for};
+
+    #[test]
+    fn test_crate_names() {
+        assert!(check_crate_name("artx-rijks").is_ok());
+        assert!(check_crate_name("artx_rijks2").is_ok());
+        assert!(check_crate_name("").is_err());
+        assert!(check_crate_name("2artx").is_err());
+        assert!(check_crate_name("artx rijks").is_err());
+    }
+
+    #[test]
+    fn test_titles() {
+        assert_eq!(title_for("artx-rijks-museum"), "Rijks Museum");
+        assert_eq!(title_for("my_plugin"), "My Plugin");
+    }
+
+    #[test]
+    fn test_render() {
+        let out = render(
+            "name = \"{{name}}\" # {{title}}\nsdk = { {{sdk}} }",
+            "artx-rijks",
+            "Rijks",
+            "path = \"../artchiver_sdk\"",
+        );
+        assert_eq!(
+            out,
+            "name = \"artx-rijks\" # Rijks\nsdk = { path = \"../artchiver_sdk\" }"
+        );
+    }
+}